@@ -0,0 +1,108 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Measures resolving the intents of requests with the Intent Broker, and
+//! reports the heap allocations per request, such that changes to the hot
+//! path can be compared for constrained targets.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use intent_brokering::registry::{
+    ExecutionLocality, IntentConfiguration, IntentKind, Registry, ServiceConfiguration, ServiceId,
+};
+use intent_brokering::streaming::StreamingEss;
+use intent_brokering::IntentBroker;
+
+const NUMBER_OF_NAMESPACES: &[usize] = &[10, 100];
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Returns the number of heap allocations made by a function.
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn namespace(index: usize) -> String {
+    format!("sdv.bench.namespace{index}")
+}
+
+fn broker_with_namespaces(namespaces: usize) -> IntentBroker {
+    let broker = IntentBroker::new("http://localhost:4243".parse().unwrap(), StreamingEss::new());
+    let mut registry = Registry::new(broker.clone(), Default::default());
+
+    for index in 0..namespaces {
+        let service = ServiceConfiguration::new(
+            ServiceId::new(format!("provider{index}"), "1.0.0"),
+            format!("http://localhost:{}", 50000 + index).parse().unwrap(),
+            ExecutionLocality::Local,
+        );
+        let read = IntentConfiguration::new(namespace(index), IntentKind::Read);
+        let intents = vec![read.with_intent(IntentKind::Invoke), read];
+        registry.upsert(service, intents, Instant::now()).unwrap();
+    }
+
+    broker
+}
+
+/// Resolves the intent of a request like the Intent Broker does, from the
+/// namespace decoded from the request.
+fn resolve(broker: &IntentBroker, namespace: String, intent: IntentKind) {
+    let intent = IntentConfiguration::new(broker.intern(namespace), intent);
+    black_box(broker.resolve(&intent));
+}
+
+fn resolve_bench(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let _guard = runtime.enter();
+
+    for namespaces in NUMBER_OF_NAMESPACES.iter().cloned() {
+        let broker = broker_with_namespaces(namespaces);
+        let cases = [
+            ("read", namespace(0), IntentKind::Read),
+            ("inspect", "system.registry".to_owned(), IntentKind::Inspect),
+        ];
+
+        for (name, namespace, intent) in cases {
+            let id = format!("resolve/{name}/{namespaces}-namespaces");
+            let request = namespace.clone();
+            println!(
+                "{id}: {} allocations per request",
+                allocations(|| resolve(&broker, request, intent))
+            );
+
+            c.bench_function(&id, |b| {
+                b.iter_batched(
+                    || namespace.clone(),
+                    |namespace| resolve(&broker, namespace, intent),
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+    }
+}
+
+criterion_group!(benches, resolve_bench);
+criterion_main!(benches);
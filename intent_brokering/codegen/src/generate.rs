@@ -0,0 +1,250 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Generates a module per namespace, with a unit struct per member which
+//! implements the traits of its kind, i.e. `Property`, `Command` or
+//! `EventSource`. The members are named after their paths in upper camel
+//! case, and their values are typed after the `type` item of their entries
+//! in the catalog if it is a primitive type, or as `Value` otherwise.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Property,
+    Command,
+    Event,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Property => "property",
+            Kind::Command => "command",
+            Kind::Event => "event",
+        }
+    }
+
+    fn trait_name(self) -> &'static str {
+        match self {
+            Kind::Property => "Property",
+            Kind::Command => "Command",
+            Kind::Event => "EventSource",
+        }
+    }
+
+    fn type_name(self) -> &'static str {
+        match self {
+            Kind::Property | Kind::Event => "Value",
+            Kind::Command => "Return",
+        }
+    }
+}
+
+/// A member of a namespace, as listed in the catalog.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Member {
+    namespace: String,
+    path: String,
+    kind: Kind,
+    r#type: Option<String>,
+}
+
+impl Member {
+    /// Returns the member, or `None` if its member type is not known.
+    pub fn new(
+        namespace: &str,
+        path: &str,
+        member_type: &str,
+        r#type: Option<&str>,
+    ) -> Option<Self> {
+        let kind = [Kind::Property, Kind::Command, Kind::Event]
+            .into_iter()
+            .find(|kind| kind.name() == member_type)?;
+
+        Some(Self {
+            namespace: namespace.to_owned(),
+            path: path.to_owned(),
+            kind,
+            r#type: r#type.map(ToOwned::to_owned),
+        })
+    }
+
+    /// Commands are typed opaquely by the catalog, hence they return values.
+    fn rust_type(&self) -> &'static str {
+        match (self.kind, self.r#type.as_deref()) {
+            (Kind::Command, _) => "Value",
+            (_, Some("int32")) => "i32",
+            (_, Some("int64")) => "i64",
+            (_, Some("bool" | "boolean")) => "bool",
+            (_, Some("string")) => "String",
+            _ => "Value",
+        }
+    }
+}
+
+/// Generates the bindings of the members, ordered by namespace and path.
+pub fn generate(members: impl IntoIterator<Item = Member>) -> String {
+    let mut members_by_namespace = BTreeMap::<_, Vec<_>>::new();
+    for member in members {
+        members_by_namespace.entry(member.namespace.clone()).or_default().push(member);
+    }
+
+    let mut output = String::from(
+        "// Generated by chariott-codegen from the catalog of the Intent Broker, do not edit.\n",
+    );
+
+    for (namespace, mut members) in members_by_namespace {
+        members.sort_by(|a, b| a.path.cmp(&b.path));
+        let traits = members
+            .iter()
+            .map(|member| member.kind.trait_name())
+            .chain(["Member"])
+            .collect::<BTreeSet<_>>();
+
+        writeln!(output, "\n/// The members of the namespace `{namespace}`.").unwrap();
+        writeln!(output, "pub mod {} {{", snake_case(&namespace)).unwrap();
+        writeln!(
+            output,
+            "    use examples_common::intent_brokering::typed::{{{}}};",
+            traits.into_iter().collect::<Vec<_>>().join(", ")
+        )
+        .unwrap();
+        if members.iter().any(|member| member.rust_type() == "Value") {
+            writeln!(output, "    use examples_common::intent_brokering::value::Value;").unwrap();
+        }
+
+        let mut names = BTreeSet::new();
+        for member in members {
+            let name = unique(upper_camel_case(&member.path), &mut names);
+            let kind = member.kind.name();
+            let doc = match &member.r#type {
+                Some(r#type) => format!("The {kind} `{}` of type `{type}`.", member.path),
+                None => format!("The {kind} `{}`.", member.path),
+            };
+
+            write!(
+                output,
+                r#"
+    /// {doc}
+    pub struct {name};
+
+    impl Member for {name} {{
+        const NAMESPACE: &'static str = "{namespace}";
+        const PATH: &'static str = "{path}";
+    }}
+
+    impl {trait_name} for {name} {{
+        type {type_name} = {rust_type};
+    }}
+"#,
+                path = member.path.escape_default(),
+                namespace = namespace.escape_default(),
+                trait_name = member.kind.trait_name(),
+                type_name = member.kind.type_name(),
+                rust_type = member.rust_type(),
+            )
+            .unwrap();
+        }
+
+        writeln!(output, "}}").unwrap();
+    }
+
+    output
+}
+
+fn words(value: &str) -> impl Iterator<Item = &str> {
+    value.split(|c: char| !c.is_ascii_alphanumeric()).filter(|word| !word.is_empty())
+}
+
+/// Identifiers must not start with a digit.
+fn identifier(name: String) -> String {
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => name,
+        _ => format!("_{name}"),
+    }
+}
+
+fn snake_case(value: &str) -> String {
+    identifier(words(value).map(str::to_ascii_lowercase).collect::<Vec<_>>().join("_"))
+}
+
+fn upper_camel_case(value: &str) -> String {
+    identifier(
+        words(value)
+            .map(|word| {
+                let (first, rest) = word.split_at(1);
+                first.to_ascii_uppercase() + rest
+            })
+            .collect(),
+    )
+}
+
+/// Paths which differ only in separators map to the same name, hence
+/// subsequent ones are numbered.
+fn unique(name: String, names: &mut BTreeSet<String>) -> String {
+    let name = (1..)
+        .map(|i| if i == 1 { name.clone() } else { format!("{name}{i}") })
+        .find(|name| !names.contains(name))
+        .unwrap();
+    names.insert(name.clone());
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(namespace: &str, path: &str, member_type: &str, r#type: &str) -> Member {
+        Member::new(namespace, path, member_type, Some(r#type)).unwrap()
+    }
+
+    #[test]
+    fn generate_emits_module_per_namespace_with_typed_members() {
+        // arrange
+        let members = [
+            member("sdv.vdt", "Vehicle.Speed", "property", "int32"),
+            member("sdv.vdt", "Vehicle.Cabin.Door.Lock", "command", "IAcmeDoor:Lock"),
+            member("sdv.kvs", "time", "event", "string"),
+        ];
+
+        // act
+        let result = generate(members);
+
+        // assert
+        assert!(
+            result.find("pub mod sdv_kvs {").unwrap() < result.find("pub mod sdv_vdt {").unwrap()
+        );
+        assert!(result.contains(
+            "    use examples_common::intent_brokering::typed::{Command, Member, Property};"
+        ));
+        assert!(result.contains("    pub struct VehicleSpeed;"));
+        assert!(result.contains("        const PATH: &'static str = \"Vehicle.Speed\";"));
+        assert!(result.contains("    impl Property for VehicleSpeed {\n        type Value = i32;"));
+        assert!(result
+            .contains("    impl Command for VehicleCabinDoorLock {\n        type Return = Value;"));
+        assert!(result.contains("    impl EventSource for Time {\n        type Value = String;"));
+    }
+
+    #[test]
+    fn generate_names_members_uniquely() {
+        // act
+        let result = generate([
+            member("sdv.vdt", "a.b", "property", "bool"),
+            member("sdv.vdt", "a_b", "property", "bool"),
+            member("sdv.vdt", "1.c", "property", "bool"),
+        ]);
+
+        // assert
+        assert!(result.contains("pub struct AB;"));
+        assert!(result.contains("pub struct AB2;"));
+        assert!(result.contains("pub struct _1C;"));
+    }
+
+    #[test]
+    fn new_returns_none_for_unknown_member_type() {
+        assert_eq!(None, Member::new("sdv.vdt", "Vehicle", "branch", None));
+    }
+}
@@ -0,0 +1,41 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Generates typed Rust bindings for the members of all namespaces listed in
+//! the catalog of the Intent Broker, see the `typed` module of the examples
+//! library. The bindings are written to standard output:
+//!
+//! ```bash
+//! chariott-codegen [<query>] > src/vehicle.rs
+//! ```
+
+mod generate;
+
+use examples_common::intent_brokering::api::{GrpcIntentBrokering, IntentBrokering as _};
+use intent_brokering_common::error::Error;
+
+use crate::generate::{generate, Member};
+
+const SYSTEM_CATALOG_NAMESPACE: &str = "system.catalog";
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let query = std::env::args().nth(1).unwrap_or_else(|| "**".to_owned());
+
+    let catalog =
+        GrpcIntentBrokering::connect().await?.inspect(SYSTEM_CATALOG_NAMESPACE, query).await?;
+
+    let members = catalog.iter().filter_map(|entry| {
+        let item = |key: &str| entry.get(key).and_then(|value| value.as_str().ok());
+        let member =
+            Member::new(item("namespace")?, entry.path(), item("member_type")?, item("type"));
+        if member.is_none() {
+            eprintln!("Skipping '{}', its member type is not known.", entry.path());
+        }
+        member
+    });
+
+    print!("{}", generate(members));
+    Ok(())
+}
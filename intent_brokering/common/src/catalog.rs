@@ -0,0 +1,298 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::{collections::HashMap, fmt};
+
+use intent_brokering_proto::common::ValueEnum;
+use serde::Deserialize;
+use tonic::{metadata::MetadataValue, Status};
+
+use crate::error::{Error, ResultExt as _};
+
+/// The metadata of a status which identifies the violated constraint of a
+/// rejected write, see [`ValidationError::code`].
+pub const VALIDATION_ERROR_METADATA_KEY: &str = "x-chariott-validation-error";
+
+/// The type of the values of a property.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PropertyType {
+    Bool,
+    Int32,
+    Int64,
+    Float32,
+    Float64,
+    String,
+    Timestamp,
+}
+
+impl fmt::Display for PropertyType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PropertyType::Bool => "bool",
+            PropertyType::Int32 => "int32",
+            PropertyType::Int64 => "int64",
+            PropertyType::Float32 => "float32",
+            PropertyType::Float64 => "float64",
+            PropertyType::String => "string",
+            PropertyType::Timestamp => "timestamp",
+        })
+    }
+}
+
+/// A value which a property may be restricted to.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Literal {
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Bool(value) => write!(f, "{value}"),
+            Literal::Number(value) => write!(f, "{value}"),
+            Literal::String(value) => write!(f, "'{value}'"),
+        }
+    }
+}
+
+/// Declares the type of a property and the constraints its values must
+/// satisfy. The range applies to numbers only.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Property {
+    #[serde(rename = "type")]
+    pub kind: PropertyType,
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+    #[serde(default, rename = "enum")]
+    pub allowed: Option<Vec<Literal>>,
+}
+
+/// The constraint which a written value violates.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation {
+    UnknownKey,
+    TypeMismatch(PropertyType),
+    BelowMinimum(f64),
+    AboveMaximum(f64),
+    NotAllowed(Vec<Literal>),
+}
+
+/// Describes why a value cannot be written to a key.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationError {
+    pub key: Box<str>,
+    pub violation: Violation,
+}
+
+impl ValidationError {
+    /// A stable identifier of the violated constraint, which consumers can
+    /// match on.
+    pub fn code(&self) -> &'static str {
+        match self.violation {
+            Violation::UnknownKey => "unknown_key",
+            Violation::TypeMismatch(_) => "type_mismatch",
+            Violation::BelowMinimum(_) => "below_minimum",
+            Violation::AboveMaximum(_) => "above_maximum",
+            Violation::NotAllowed(_) => "not_allowed",
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let key = &self.key;
+        match &self.violation {
+            Violation::UnknownKey => write!(f, "Key '{key}' is not declared."),
+            Violation::TypeMismatch(kind) => {
+                write!(f, "Value of '{key}' must be of type '{kind}'.")
+            }
+            Violation::BelowMinimum(min) => write!(f, "Value of '{key}' must be at least {min}."),
+            Violation::AboveMaximum(max) => write!(f, "Value of '{key}' must be at most {max}."),
+            Violation::NotAllowed(allowed) => {
+                let allowed: Vec<_> = allowed.iter().map(Literal::to_string).collect();
+                write!(f, "Value of '{key}' must be one of {}.", allowed.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl From<ValidationError> for Status {
+    /// Converts the error into an `INVALID_ARGUMENT` status, whose
+    /// `x-chariott-validation-error` metadata holds the code of the error.
+    fn from(error: ValidationError) -> Self {
+        let mut status = Status::invalid_argument(error.to_string());
+        status
+            .metadata_mut()
+            .insert(VALIDATION_ERROR_METADATA_KEY, MetadataValue::from_static(error.code()));
+        status
+    }
+}
+
+/// Declares the properties of a store, such that writes can be validated
+/// before they are applied. Keys which are not declared are accepted with any
+/// value, unless `additional_keys` is disabled. The default catalog accepts
+/// all writes.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Catalog {
+    #[serde(default = "additional_keys_default")]
+    additional_keys: bool,
+    #[serde(default)]
+    properties: HashMap<Box<str>, Property>,
+}
+
+fn additional_keys_default() -> bool {
+    true
+}
+
+impl Default for Catalog {
+    fn default() -> Self {
+        Self { additional_keys: additional_keys_default(), properties: HashMap::new() }
+    }
+}
+
+impl Catalog {
+    /// Parses a catalog given as JSON, e.g.
+    /// `{ "properties": { "gear": { "type": "string", "enum": ["P", "D"] } } }`.
+    pub fn parse(catalog: &str) -> Result<Self, Error> {
+        serde_json::from_str(catalog).map_err_with("Failed to parse the catalog.")
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Property> {
+        self.properties.get(key)
+    }
+
+    /// Validates that a value can be written to a key.
+    pub fn validate(&self, key: &str, value: &ValueEnum) -> Result<(), ValidationError> {
+        let error = |violation| Err(ValidationError { key: key.into(), violation });
+
+        let Some(property) = self.properties.get(key) else {
+            return if self.additional_keys { Ok(()) } else { error(Violation::UnknownKey) };
+        };
+
+        let number = match (property.kind, value) {
+            (PropertyType::Bool, ValueEnum::Bool(_))
+            | (PropertyType::String, ValueEnum::String(_))
+            | (PropertyType::Timestamp, ValueEnum::Timestamp(_)) => None,
+            (PropertyType::Int32, ValueEnum::Int32(value)) => Some(*value as f64),
+            (PropertyType::Int64, ValueEnum::Int64(value)) => Some(*value as f64),
+            (PropertyType::Float32, ValueEnum::Float32(value)) => Some(*value as f64),
+            (PropertyType::Float64, ValueEnum::Float64(value)) => Some(*value),
+            (kind, _) => return error(Violation::TypeMismatch(kind)),
+        };
+
+        if let Some(number) = number {
+            // NaN violates any bound.
+            match (property.min, property.max) {
+                (Some(min), _) if number.is_nan() || number < min => {
+                    return error(Violation::BelowMinimum(min))
+                }
+                (_, Some(max)) if number.is_nan() || number > max => {
+                    return error(Violation::AboveMaximum(max))
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(allowed) = &property.allowed {
+            let matches = |literal: &Literal| match (literal, value) {
+                (Literal::Bool(literal), ValueEnum::Bool(value)) => literal == value,
+                (Literal::String(literal), ValueEnum::String(value)) => literal == value,
+                (Literal::Number(literal), _) => number == Some(*literal),
+                _ => false,
+            };
+
+            if !allowed.iter().any(matches) {
+                return error(Violation::NotAllowed(allowed.clone()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Code;
+
+    use super::*;
+
+    const CATALOG: &str = r#"{
+        "additional_keys": false,
+        "properties": {
+            "seat-position": { "type": "int32", "min": 0, "max": 10 },
+            "gear": { "type": "string", "enum": ["P", "R", "N", "D"] },
+            "heating": { "type": "bool" }
+        }
+    }"#;
+
+    #[test]
+    fn validate_accepts_valid_values() {
+        // arrange
+        let subject = Catalog::parse(CATALOG).unwrap();
+
+        // act + assert
+        assert_eq!(Ok(()), subject.validate("seat-position", &ValueEnum::Int32(10)));
+        assert_eq!(Ok(()), subject.validate("gear", &ValueEnum::String("D".to_owned())));
+        assert_eq!(Ok(()), subject.validate("heating", &ValueEnum::Bool(true)));
+    }
+
+    #[test]
+    fn validate_rejects_invalid_values() {
+        // arrange
+        let subject = Catalog::parse(CATALOG).unwrap();
+        let violation = |key, value| subject.validate(key, &value).unwrap_err().violation;
+
+        // act + assert
+        assert_eq!(
+            Violation::TypeMismatch(PropertyType::Int32),
+            violation("seat-position", ValueEnum::Int64(1))
+        );
+        assert_eq!(Violation::BelowMinimum(0.0), violation("seat-position", ValueEnum::Int32(-1)));
+        assert_eq!(Violation::AboveMaximum(10.0), violation("seat-position", ValueEnum::Int32(11)));
+        assert_eq!(
+            Violation::NotAllowed(["P", "R", "N", "D"].map(|g| Literal::String(g.into())).into()),
+            violation("gear", ValueEnum::String("X".to_owned()))
+        );
+        assert_eq!(Violation::UnknownKey, violation("unknown", ValueEnum::Bool(true)));
+    }
+
+    #[test]
+    fn validate_accepts_any_value_of_additional_keys_by_default() {
+        // act
+        let result = Catalog::default().validate("unknown", &ValueEnum::Int32(1));
+
+        // assert
+        assert_eq!(Ok(()), result);
+    }
+
+    #[test]
+    fn validation_error_converts_into_status_with_code() {
+        // arrange
+        let error = ValidationError {
+            key: "seat-position".into(),
+            violation: Violation::AboveMaximum(10.0),
+        };
+
+        // act
+        let status = Status::from(error);
+
+        // assert
+        assert_eq!(Code::InvalidArgument, status.code());
+        assert_eq!("Value of 'seat-position' must be at most 10.", status.message());
+        assert_eq!(
+            "above_maximum",
+            status.metadata().get(VALIDATION_ERROR_METADATA_KEY).unwrap().to_str().unwrap()
+        );
+    }
+}
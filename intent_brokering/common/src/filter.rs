@@ -0,0 +1,382 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Filter expressions, which subscribers attach to a source such that only
+//! matching events are delivered. An expression compares the `value` of an
+//! event, or the amount `changed_by` which it differs from the last
+//! delivered value, with literals:
+//!
+//! ```text
+//! value > 100
+//! changed_by >= 0.5 && value != "idle"
+//! !(value == true) || (value >= 10 && value < 20)
+//! ```
+//!
+//! Supported literals are numbers, strings in single or double quotes,
+//! `true`, `false` and `null`. Numbers of any type are compared numerically.
+//! `changed_by` is only defined for numeric values and is infinite for the
+//! first event, such that the first event passes a deadband filter. An
+//! expression which cannot be evaluated, e.g. because it compares a string
+//! with a number using `<`, does not match.
+
+use std::{iter::Peekable, str::Chars};
+
+use intent_brokering_proto::common::ValueEnum;
+
+use crate::error::Error;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Scalar {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+impl Scalar {
+    fn from_value(value: &ValueEnum) -> Option<Self> {
+        match value {
+            ValueEnum::Null(_) => Some(Self::Null),
+            ValueEnum::Bool(value) => Some(Self::Bool(*value)),
+            ValueEnum::Int32(value) => Some(Self::Number((*value).into())),
+            ValueEnum::Int64(value) => Some(Self::Number(*value as f64)),
+            ValueEnum::Float32(value) => Some(Self::Number((*value).into())),
+            ValueEnum::Float64(value) => Some(Self::Number(*value)),
+            ValueEnum::String(value) => Some(Self::String(value.clone())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Literal(Scalar),
+    Value,
+    ChangedBy,
+    Not(Box<Expr>),
+    Binary(Box<Expr>, Operator, Box<Expr>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    String(String),
+    Operator(Operator),
+    Not,
+    LParen,
+    RParen,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, Error> {
+    fn next_is(chars: &mut Peekable<Chars>, c: char) -> bool {
+        chars.next_if_eq(&c).is_some()
+    }
+
+    let mut chars = input.chars().peekable();
+    let mut tokens = vec![];
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '=' if next_is(&mut chars, '=') => Token::Operator(Operator::Eq),
+            '!' if next_is(&mut chars, '=') => Token::Operator(Operator::Ne),
+            '!' => Token::Not,
+            '<' if next_is(&mut chars, '=') => Token::Operator(Operator::Le),
+            '<' => Token::Operator(Operator::Lt),
+            '>' if next_is(&mut chars, '=') => Token::Operator(Operator::Ge),
+            '>' => Token::Operator(Operator::Gt),
+            '&' if next_is(&mut chars, '&') => Token::Operator(Operator::And),
+            '|' if next_is(&mut chars, '|') => Token::Operator(Operator::Or),
+            '"' | '\'' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some('\\') => value.extend(chars.next()),
+                        Some(c) => value.push(c),
+                        None => return Err(Error::new("Unterminated string.")),
+                    }
+                }
+                Token::String(value)
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '.' => {
+                let mut number = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '.') {
+                    number.push(c);
+                }
+                Token::Number(
+                    number
+                        .parse()
+                        .map_err(|_| Error::new(format!("Invalid number '{number}'.")))?,
+                )
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    ident.push(c);
+                }
+                Token::Ident(ident)
+            }
+            c => return Err(Error::new(format!("Unexpected character '{c}'."))),
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// A recursive descent parser, in which `||` binds weaker than `&&`, which
+/// binds weaker than comparisons.
+struct Parser {
+    tokens: std::vec::IntoIter<Token>,
+    peeked: Option<Token>,
+}
+
+impl Parser {
+    fn peek(&mut self) -> Option<&Token> {
+        if self.peeked.is_none() {
+            self.peeked = self.tokens.next();
+        }
+        self.peeked.as_ref()
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        self.peeked.take().or_else(|| self.tokens.next())
+    }
+
+    fn next_if_operator(&mut self, accept: impl Fn(Operator) -> bool) -> Option<Operator> {
+        match self.peek() {
+            Some(Token::Operator(operator)) if accept(*operator) => {
+                let operator = *operator;
+                self.next();
+                Some(operator)
+            }
+            _ => None,
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.and()?;
+        while let Some(operator) = self.next_if_operator(|o| o == Operator::Or) {
+            expr = Expr::Binary(Box::new(expr), operator, Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.comparison()?;
+        while let Some(operator) = self.next_if_operator(|o| o == Operator::And) {
+            expr = Expr::Binary(Box::new(expr), operator, Box::new(self.comparison()?));
+        }
+        Ok(expr)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, Error> {
+        let expr = self.unary()?;
+        match self.next_if_operator(|o| !matches!(o, Operator::And | Operator::Or)) {
+            Some(operator) => Ok(Expr::Binary(Box::new(expr), operator, Box::new(self.unary()?))),
+            None => Ok(expr),
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, Error> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some(Token::LParen) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => Err(Error::new("Expected ')'.")),
+                }
+            }
+            Some(Token::Number(value)) => Ok(Expr::Literal(Scalar::Number(value))),
+            Some(Token::String(value)) => Ok(Expr::Literal(Scalar::String(value))),
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "value" => Ok(Expr::Value),
+                "changed_by" => Ok(Expr::ChangedBy),
+                "true" => Ok(Expr::Literal(Scalar::Bool(true))),
+                "false" => Ok(Expr::Literal(Scalar::Bool(false))),
+                "null" => Ok(Expr::Literal(Scalar::Null)),
+                _ => Err(Error::new(format!("Unknown identifier '{ident}'."))),
+            },
+            Some(token) => Err(Error::new(format!("Unexpected token {token:?}."))),
+            None => Err(Error::new("Unexpected end of expression.")),
+        }
+    }
+}
+
+/// A parsed filter expression, which tracks the last value it matched to
+/// evaluate `changed_by`.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    expr: Expr,
+    last: Option<f64>,
+}
+
+impl Filter {
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        let mut parser = Parser { tokens: tokenize(input)?.into_iter(), peeked: None };
+        let expr = parser.or()?;
+        if let Some(token) = parser.next() {
+            return Err(Error::new(format!("Unexpected token {token:?}.")));
+        }
+        Ok(Self { expr, last: None })
+    }
+
+    /// Evaluates the filter for a value. If the value matches, it becomes the
+    /// reference for `changed_by`.
+    pub fn matches(&mut self, value: &ValueEnum) -> bool {
+        let Some(value) = Scalar::from_value(value) else {
+            return false;
+        };
+
+        let matches = evaluate(&self.expr, &value, self.last) == Some(Scalar::Bool(true));
+        if matches {
+            if let Scalar::Number(value) = value {
+                self.last = Some(value);
+            }
+        }
+        matches
+    }
+}
+
+fn evaluate(expr: &Expr, value: &Scalar, last: Option<f64>) -> Option<Scalar> {
+    match expr {
+        Expr::Literal(literal) => Some(literal.clone()),
+        Expr::Value => Some(value.clone()),
+        Expr::ChangedBy => match value {
+            Scalar::Number(value) => {
+                Some(Scalar::Number(last.map_or(f64::INFINITY, |last| (value - last).abs())))
+            }
+            _ => None,
+        },
+        Expr::Not(expr) => match evaluate(expr, value, last)? {
+            Scalar::Bool(value) => Some(Scalar::Bool(!value)),
+            _ => None,
+        },
+        Expr::Binary(lhs, Operator::And, rhs) => match evaluate(lhs, value, last)? {
+            Scalar::Bool(false) => Some(Scalar::Bool(false)),
+            Scalar::Bool(true) => match evaluate(rhs, value, last)? {
+                Scalar::Bool(rhs) => Some(Scalar::Bool(rhs)),
+                _ => None,
+            },
+            _ => None,
+        },
+        Expr::Binary(lhs, Operator::Or, rhs) => match evaluate(lhs, value, last)? {
+            Scalar::Bool(true) => Some(Scalar::Bool(true)),
+            Scalar::Bool(false) => match evaluate(rhs, value, last)? {
+                Scalar::Bool(rhs) => Some(Scalar::Bool(rhs)),
+                _ => None,
+            },
+            _ => None,
+        },
+        Expr::Binary(lhs, operator, rhs) => {
+            let lhs = evaluate(lhs, value, last)?;
+            let rhs = evaluate(rhs, value, last)?;
+            let ordering = match (&lhs, &rhs) {
+                (Scalar::Number(lhs), Scalar::Number(rhs)) => lhs.partial_cmp(rhs),
+                (Scalar::String(lhs), Scalar::String(rhs)) => Some(lhs.cmp(rhs)),
+                _ => None,
+            };
+
+            Some(Scalar::Bool(match operator {
+                Operator::Eq => lhs == rhs,
+                Operator::Ne => lhs != rhs,
+                Operator::Lt => ordering?.is_lt(),
+                Operator::Le => ordering?.is_le(),
+                Operator::Gt => ordering?.is_gt(),
+                Operator::Ge => ordering?.is_ge(),
+                Operator::And | Operator::Or => unreachable!(),
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(expression: &str, value: ValueEnum) -> bool {
+        Filter::parse(expression).unwrap().matches(&value)
+    }
+
+    #[test]
+    fn comparisons_coerce_numbers() {
+        assert!(matches("value > 100", ValueEnum::Int32(101)));
+        assert!(!matches("value > 100", ValueEnum::Int64(100)));
+        assert!(matches("value <= 1.5", ValueEnum::Float32(1.5)));
+        assert!(matches("100 < value", ValueEnum::Float64(100.5)));
+        assert!(matches("value == -2", ValueEnum::Int32(-2)));
+    }
+
+    #[test]
+    fn comparisons_of_strings_bools_and_null() {
+        assert!(matches("value == 'idle'", ValueEnum::String("idle".to_owned())));
+        assert!(matches(r#"value != "idle""#, ValueEnum::String("busy".to_owned())));
+        assert!(matches("value < 'b'", ValueEnum::String("a".to_owned())));
+        assert!(matches("value == true", ValueEnum::Bool(true)));
+        assert!(matches("value == null", ValueEnum::Null(0)));
+    }
+
+    #[test]
+    fn mismatched_types_are_not_equal_and_not_ordered() {
+        assert!(!matches("value == 1", ValueEnum::String("1".to_owned())));
+        assert!(matches("value != 1", ValueEnum::String("1".to_owned())));
+        assert!(!matches("value > 1", ValueEnum::String("2".to_owned())));
+        assert!(!matches("!(value > 1)", ValueEnum::String("2".to_owned())));
+    }
+
+    #[test]
+    fn logical_operators_respect_precedence() {
+        let expression = "value > 10 && value < 20 || value == 0";
+
+        assert!(matches(expression, ValueEnum::Int32(15)));
+        assert!(matches(expression, ValueEnum::Int32(0)));
+        assert!(!matches(expression, ValueEnum::Int32(25)));
+        assert!(matches("!(value > 10 && value < 20)", ValueEnum::Int32(25)));
+    }
+
+    #[test]
+    fn changed_by_is_relative_to_last_match() {
+        // arrange
+        let mut filter = Filter::parse("changed_by >= 0.5").unwrap();
+
+        // act
+        let results =
+            [1.0, 1.2, 1.4, 1.6, 0.5].map(|value| filter.matches(&ValueEnum::Float64(value)));
+
+        // assert
+        assert_eq!([true, false, false, true, true], results);
+    }
+
+    #[test]
+    fn non_scalar_values_do_not_match() {
+        assert!(!matches("value == null", ValueEnum::List(Default::default())));
+    }
+
+    #[test]
+    fn parse_fails_for_invalid_expressions() {
+        for expression in
+            ["", "value >", "value > 1 1", "(value > 1", "speed > 1", "value = 1", "'open", "1..2"]
+        {
+            assert!(Filter::parse(expression).is_err(), "{expression}");
+        }
+    }
+}
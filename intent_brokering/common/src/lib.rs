@@ -16,6 +16,17 @@
 //! intent_brokering_common = { path = "../common/" }
 //! ```
 //!
+//! # Features
+//! The `runtime` feature, which is enabled by default, builds the modules
+//! which depend on the gRPC contract and the async runtime. Constrained
+//! components, e.g. a CAN gateway on a small MPU, can disable the default
+//! features to only build the core types, e.g. the [data model](model),
+//! errors and configuration utilities:
+//!
+//! ```toml
+//! intent_brokering_common = { path = "../common/", default-features = false }
+//! ```
+//!
 
 /// Generic error handling
 pub mod error;
@@ -26,14 +37,39 @@ pub mod ext;
 /// Configuration related utilites
 pub mod config;
 
+/// The data model, without dependencies on the gRPC contract
+pub mod model;
+
 /// Integration of the event sub-system with the gRPC streaming contract.
+#[cfg(feature = "runtime")]
 pub mod streaming_ess;
 
+/// Filter expressions for event subscriptions
+#[cfg(feature = "runtime")]
+pub mod filter;
+
 /// Query utilities
 pub mod query;
 
+/// Storage backends shared by the persistence features
+pub mod storage;
+
+/// Helpers for providers to evaluate conditional (compare-and-set) writes
+#[cfg(feature = "runtime")]
+pub mod precondition;
+
+/// Typed property catalogs for providers to validate writes
+#[cfg(feature = "runtime")]
+pub mod catalog;
+
 /// Graceful shutdown helpers
+#[cfg(feature = "runtime")]
 pub mod shutdown;
 
+/// Retrying failed operations with backoff
+#[cfg(feature = "runtime")]
+pub mod retry;
+
 /// Tokio utilities
+#[cfg(feature = "runtime")]
 pub mod tokio_runtime_fork;
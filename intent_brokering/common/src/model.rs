@@ -0,0 +1,267 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! The data model of Intent Brokering as plain Rust types, which are built
+//! without the gRPC contract and the async runtime. Components which do not
+//! talk gRPC themselves, e.g. a CAN gateway on a small MPU, can share the
+//! data model with the components that do. With the `runtime` feature, the
+//! types convert to and from their protobuf counterparts of every version of
+//! the gRPC contracts, which makes the data model the shim through which the
+//! versions convert into each other.
+
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use crate::error::Error;
+
+/// The kind of an intent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum IntentKind {
+    Discover,
+    Inspect,
+    Read,
+    Write,
+    Invoke,
+    Subscribe,
+    Delete,
+}
+
+impl fmt::Display for IntentKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            IntentKind::Discover => "discover",
+            IntentKind::Inspect => "inspect",
+            IntentKind::Read => "read",
+            IntentKind::Write => "write",
+            IntentKind::Invoke => "invoke",
+            IntentKind::Subscribe => "subscribe",
+            IntentKind::Delete => "delete",
+        })
+    }
+}
+
+impl FromStr for IntentKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "discover" => Ok(IntentKind::Discover),
+            "inspect" => Ok(IntentKind::Inspect),
+            "read" => Ok(IntentKind::Read),
+            "write" => Ok(IntentKind::Write),
+            "invoke" => Ok(IntentKind::Invoke),
+            "subscribe" => Ok(IntentKind::Subscribe),
+            "delete" => Ok(IntentKind::Delete),
+            _ => Err(Error::new(format!("Intent '{s}' is not known."))),
+        }
+    }
+}
+
+/// A value which is read, written or published, or passed to and returned
+/// from commands.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int32(i32),
+    Int64(i64),
+    Float32(f32),
+    Float64(f64),
+    String(String),
+    /// A point in time as seconds and nanoseconds since the Unix epoch.
+    Timestamp {
+        seconds: i64,
+        nanos: i32,
+    },
+    List(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+    /// Binary data described by a media type, e.g. `image/png`.
+    Blob {
+        media_type: String,
+        bytes: Vec<u8>,
+    },
+    /// An encoded protobuf message of the given type.
+    Any {
+        type_url: String,
+        value: Vec<u8>,
+    },
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Int32(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Int64(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::Float32(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float64(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+/// Conversions from and to the `v1` contracts.
+#[cfg(feature = "runtime")]
+mod v1 {
+    use intent_brokering_proto::v1::common::{
+        Blob, IntentEnum, List, Map, ValueEnum, ValueMessage,
+    };
+
+    use super::{IntentKind, Value};
+
+    impl From<&IntentEnum> for IntentKind {
+        fn from(intent: &IntentEnum) -> Self {
+            match intent {
+                IntentEnum::Discover(_) => IntentKind::Discover,
+                IntentEnum::Inspect(_) => IntentKind::Inspect,
+                IntentEnum::Read(_) => IntentKind::Read,
+                IntentEnum::Write(_) | IntentEnum::WriteBatch(_) => IntentKind::Write,
+                IntentEnum::Invoke(_) => IntentKind::Invoke,
+                IntentEnum::Subscribe(_) => IntentKind::Subscribe,
+                IntentEnum::Delete(_) => IntentKind::Delete,
+            }
+        }
+    }
+
+    impl From<ValueEnum> for Value {
+        fn from(value: ValueEnum) -> Self {
+            match value {
+                ValueEnum::Null(_) => Value::Null,
+                ValueEnum::Bool(value) => Value::Bool(value),
+                ValueEnum::Int32(value) => Value::Int32(value),
+                ValueEnum::Int64(value) => Value::Int64(value),
+                ValueEnum::Float32(value) => Value::Float32(value),
+                ValueEnum::Float64(value) => Value::Float64(value),
+                ValueEnum::String(value) => Value::String(value),
+                ValueEnum::Timestamp(timestamp) => {
+                    Value::Timestamp { seconds: timestamp.seconds, nanos: timestamp.nanos }
+                }
+                ValueEnum::List(list) => {
+                    Value::List(list.value.into_iter().map(Value::from).collect())
+                }
+                ValueEnum::Map(map) => Value::Map(
+                    map.map.into_iter().map(|(key, value)| (key, Value::from(value))).collect(),
+                ),
+                ValueEnum::Blob(blob) => {
+                    Value::Blob { media_type: blob.media_type, bytes: blob.bytes }
+                }
+                ValueEnum::Any(any) => Value::Any { type_url: any.type_url, value: any.value },
+            }
+        }
+    }
+
+    /// Converts an unset value into [`Value::Null`].
+    impl From<ValueMessage> for Value {
+        fn from(value: ValueMessage) -> Self {
+            value.value.map(Value::from).unwrap_or(Value::Null)
+        }
+    }
+
+    impl From<Value> for ValueEnum {
+        fn from(value: Value) -> Self {
+            match value {
+                Value::Null => ValueEnum::Null(0),
+                Value::Bool(value) => ValueEnum::Bool(value),
+                Value::Int32(value) => ValueEnum::Int32(value),
+                Value::Int64(value) => ValueEnum::Int64(value),
+                Value::Float32(value) => ValueEnum::Float32(value),
+                Value::Float64(value) => ValueEnum::Float64(value),
+                Value::String(value) => ValueEnum::String(value),
+                Value::Timestamp { seconds, nanos } => {
+                    ValueEnum::Timestamp(prost_types::Timestamp { seconds, nanos })
+                }
+                Value::List(values) => {
+                    ValueEnum::List(List { value: values.into_iter().map(Into::into).collect() })
+                }
+                Value::Map(map) => ValueEnum::Map(Map {
+                    map: map.into_iter().map(|(key, value)| (key, value.into())).collect(),
+                }),
+                Value::Blob { media_type, bytes } => ValueEnum::Blob(Blob { media_type, bytes }),
+                Value::Any { type_url, value } => {
+                    ValueEnum::Any(prost_types::Any { type_url, value })
+                }
+            }
+        }
+    }
+
+    impl From<Value> for ValueMessage {
+        fn from(value: Value) -> Self {
+            ValueMessage { value: Some(value.into()) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intent_kind_round_trips_through_string() {
+        for kind in [
+            IntentKind::Discover,
+            IntentKind::Inspect,
+            IntentKind::Read,
+            IntentKind::Write,
+            IntentKind::Invoke,
+            IntentKind::Subscribe,
+            IntentKind::Delete,
+        ] {
+            assert_eq!(kind, kind.to_string().parse().unwrap());
+        }
+
+        assert!("unknown".parse::<IntentKind>().is_err());
+    }
+
+    #[cfg(feature = "runtime")]
+    #[test]
+    fn value_round_trips_through_v1() {
+        use intent_brokering_proto::v1::common::{ValueEnum, ValueMessage};
+
+        // arrange
+        let value = Value::Map(BTreeMap::from([
+            ("speed".to_owned(), Value::Float64(42.5)),
+            ("doors".to_owned(), Value::List(vec![Value::Bool(true), Value::Null])),
+            ("seen".to_owned(), Value::Timestamp { seconds: 1, nanos: 2 }),
+            (
+                "image".to_owned(),
+                Value::Blob { media_type: "image/png".to_owned(), bytes: vec![1, 2] },
+            ),
+        ]));
+
+        // act
+        let result = Value::from(ValueEnum::from(value.clone()));
+
+        // assert
+        assert_eq!(value, result);
+        assert_eq!(Value::Null, Value::from(ValueMessage { value: None }));
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::{borrow::Borrow, collections::HashMap, hash::Hash};
+
+use intent_brokering_proto::common::{
+    write_precondition::Precondition, NullValue, ValueEnum, WritePrecondition,
+};
+use tonic::Status;
+
+/// The version of a key that does not exist.
+pub const ABSENT_VERSION: u64 = 0;
+
+/// Tracks the version of each key of a store, such that a provider can return
+/// version tokens on reads and writes and evaluate `expected_version`
+/// preconditions. Versions start at 1 on the first write of a key and are
+/// incremented on every subsequent write.
+#[derive(Clone, Debug)]
+pub struct Versions<K>(HashMap<K, u64>);
+
+impl<K> Default for Versions<K> {
+    fn default() -> Self {
+        Self(HashMap::new())
+    }
+}
+
+impl<K: Eq + Hash> Versions<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the current version of a key, or [`ABSENT_VERSION`] if the key was
+    /// never written.
+    pub fn get<Q>(&self, key: &Q) -> u64
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.0.get(key).copied().unwrap_or(ABSENT_VERSION)
+    }
+
+    /// Increments the version of a key after it was written and returns the
+    /// new version.
+    pub fn increment(&mut self, key: K) -> u64 {
+        let version = self.0.entry(key).or_insert(ABSENT_VERSION);
+        *version += 1;
+        *version
+    }
+
+    /// Forgets the version of a key after it was removed.
+    pub fn remove<Q>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.0.remove(key);
+    }
+}
+
+/// Converts a version into the token returned to consumers.
+pub fn version_token(version: u64) -> String {
+    version.to_string()
+}
+
+/// Evaluates the precondition of a write against the current value and
+/// version of the key. A missing value is compared as `null`. Returns an
+/// `ABORTED` status if the precondition does not hold, as the consumer is
+/// expected to read the key again before retrying the write.
+pub fn check_precondition(
+    precondition: Option<&WritePrecondition>,
+    current_value: Option<&ValueEnum>,
+    current_version: u64,
+) -> Result<(), Status> {
+    const NULL: ValueEnum = ValueEnum::Null(NullValue::Unspecified as i32);
+
+    match precondition.and_then(|p| p.precondition.as_ref()) {
+        None => Ok(()),
+        Some(Precondition::ExpectedValue(expected)) => {
+            let expected = expected.value.as_ref().unwrap_or(&NULL);
+            if expected == current_value.unwrap_or(&NULL) {
+                Ok(())
+            } else {
+                Err(Status::aborted("Current value does not match the expected value."))
+            }
+        }
+        Some(Precondition::ExpectedVersion(expected)) => {
+            if *expected == version_token(current_version) {
+                Ok(())
+            } else {
+                Err(Status::aborted(format!(
+                    "Current version '{current_version}' does not match the expected version '{expected}'."
+                )))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use intent_brokering_proto::common::ValueMessage;
+    use tonic::Code;
+
+    use super::*;
+
+    #[test]
+    fn versions_start_at_one_and_increment_on_write() {
+        // arrange
+        let mut subject = Versions::new();
+
+        // act + assert
+        assert_eq!(ABSENT_VERSION, subject.get("foo"));
+        assert_eq!(1, subject.increment("foo"));
+        assert_eq!(2, subject.increment("foo"));
+        assert_eq!(2, subject.get("foo"));
+        subject.remove("foo");
+        assert_eq!(ABSENT_VERSION, subject.get("foo"));
+    }
+
+    #[test]
+    fn check_precondition_succeeds_without_precondition() {
+        assert!(check_precondition(None, None, ABSENT_VERSION).is_ok());
+        assert!(
+            check_precondition(Some(&WritePrecondition { precondition: None }), None, 3).is_ok()
+        );
+    }
+
+    #[test]
+    fn check_precondition_compares_expected_value() {
+        let expected = |value: Option<ValueEnum>| WritePrecondition {
+            precondition: Some(Precondition::ExpectedValue(ValueMessage { value })),
+        };
+
+        let current = ValueEnum::Int32(1);
+
+        assert!(
+            check_precondition(Some(&expected(Some(current.clone()))), Some(&current), 1).is_ok()
+        );
+        assert!(check_precondition(Some(&expected(None)), None, ABSENT_VERSION).is_ok());
+        assert_eq!(
+            Code::Aborted,
+            check_precondition(Some(&expected(Some(ValueEnum::Int32(2)))), Some(&current), 1)
+                .unwrap_err()
+                .code()
+        );
+        assert_eq!(
+            Code::Aborted,
+            check_precondition(Some(&expected(None)), Some(&current), 1).unwrap_err().code()
+        );
+    }
+
+    #[test]
+    fn check_precondition_compares_expected_version() {
+        let expected = |version: u64| WritePrecondition {
+            precondition: Some(Precondition::ExpectedVersion(version_token(version))),
+        };
+
+        assert!(check_precondition(Some(&expected(ABSENT_VERSION)), None, ABSENT_VERSION).is_ok());
+        assert!(check_precondition(Some(&expected(2)), None, 2).is_ok());
+        assert_eq!(
+            Code::Aborted,
+            check_precondition(Some(&expected(1)), None, 2).unwrap_err().code()
+        );
+    }
+}
@@ -4,12 +4,25 @@
 
 use regex::Regex;
 
+/// Converts a query to a regex pattern, in which `**` matches any characters,
+/// `*` matches any characters except `.` and all other characters match
+/// literally.
 pub fn regex_pattern_from_query(query: &str) -> String {
-    format!("^{}$", query.replace("**", ".{0,}").replace('*', "[^.]{0,}"))
+    let pattern = query
+        .split("**")
+        .map(|part| part.split('*').map(regex::escape).collect::<Vec<_>>().join("[^.]{0,}"))
+        .collect::<Vec<_>>()
+        .join(".{0,}");
+
+    format!("^{pattern}$")
+}
+
+pub fn try_regex_from_query(query: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&regex_pattern_from_query(query))
 }
 
 pub fn regex_from_query(query: &str) -> Regex {
-    Regex::new(&regex_pattern_from_query(query)).unwrap()
+    try_regex_from_query(query).unwrap()
 }
 
 #[cfg(test)]
@@ -29,6 +42,10 @@ mod tests {
         test(true, "vdt.**.temp*", "vdt.cabin.hvac.temperature");
         test(false, "temp*erature", "temp.erature");
         test(true, "**.doors.**.lock", "vdt.cabin.doors.door1.lock");
+        test(false, "sdv.vss", "sdvxvss");
+        test(true, "sdv.(door)+", "sdv.(door)+");
+        test(false, "sdv.(door)+", "sdv.doordoor");
+        test(true, "a[*]", "a[b]");
 
         fn test(expected: bool, query: &str, input: &str) {
             let regex = regex_from_query(query);
@@ -0,0 +1,213 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::{future::Future, time::Duration};
+
+use rand::Rng as _;
+use tokio::time::sleep;
+
+/// Describes how often and after which delays a failed operation is retried.
+/// The delays follow an exponential backoff with decorrelated jitter: each
+/// delay is drawn at random between the base delay and three times the
+/// previous delay, and is capped at the maximum delay. The jitter keeps
+/// clients which failed at the same time from retrying at the same time.
+#[derive(Clone, Debug)]
+pub struct Backoff {
+    base: Duration,
+    cap: Duration,
+    max_attempts: Option<u32>,
+}
+
+impl Backoff {
+    /// Creates a backoff which retries without limit, waiting at least `base`
+    /// and at most `cap` between attempts.
+    pub fn new(base: Duration, cap: Duration) -> Self {
+        Self { base, cap: std::cmp::max(base, cap), max_attempts: None }
+    }
+
+    /// The minimum delay between attempts.
+    pub fn base(&self) -> Duration {
+        self.base
+    }
+
+    /// The maximum delay between attempts.
+    pub fn cap(&self) -> Duration {
+        self.cap
+    }
+
+    /// The maximum number of attempts, including the first one, if limited.
+    pub fn max_attempts(&self) -> Option<u32> {
+        self.max_attempts
+    }
+
+    /// Limits the number of attempts, including the first one. A limit of
+    /// zero is treated as one, i.e. the operation is not retried.
+    pub fn set_max_attempts(self, value: u32) -> Self {
+        Self { max_attempts: Some(std::cmp::max(value, 1)), ..self }
+    }
+
+    /// Returns the delays to wait before each retry, which end when the
+    /// maximum number of attempts is reached.
+    pub fn delays(&self) -> Delays {
+        Delays {
+            backoff: self.clone(),
+            previous: self.base,
+            remaining: self.max_attempts.map(|max_attempts| max_attempts - 1),
+        }
+    }
+}
+
+/// The delays of a [`Backoff`], see [`Backoff::delays`].
+#[derive(Clone, Debug)]
+pub struct Delays {
+    backoff: Backoff,
+    previous: Duration,
+    remaining: Option<u32>,
+}
+
+impl Iterator for Delays {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(remaining) = self.remaining.as_mut() {
+            *remaining = remaining.checked_sub(1)?;
+        }
+
+        let Backoff { base, cap, .. } = self.backoff;
+        let upper = std::cmp::max(self.previous.saturating_mul(3), base);
+        let delay = std::cmp::min(rand::thread_rng().gen_range(base..=upper), cap);
+        self.previous = delay;
+        Some(delay)
+    }
+}
+
+/// Runs `operation` until it succeeds, fails with an error for which
+/// `is_retryable` returns `false`, or the maximum number of attempts of the
+/// backoff is reached. Returns the result of the last attempt.
+pub async fn retry_with_backoff<T, E, F, Fut>(
+    backoff: &Backoff,
+    mut operation: F,
+    is_retryable: impl Fn(&E) -> bool,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut delays = backoff.delays();
+    let mut attempt = 1;
+
+    loop {
+        match operation().await {
+            Err(e) if is_retryable(&e) => match delays.next() {
+                Some(delay) => {
+                    tracing::debug!("Attempt {attempt} failed, retrying after {delay:?}.");
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                None => return Err(e),
+            },
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    fn backoff() -> Backoff {
+        Backoff::new(Duration::from_millis(1), Duration::from_millis(5))
+    }
+
+    #[test]
+    fn delays_are_between_base_and_cap() {
+        // arrange
+        let subject = backoff();
+
+        // act
+        let delays: Vec<_> = subject.delays().take(100).collect();
+
+        // assert
+        assert_eq!(100, delays.len());
+        assert!(delays.iter().all(|d| *d >= subject.base() && *d <= subject.cap()));
+    }
+
+    #[test]
+    fn delays_end_when_max_attempts_are_reached() {
+        // act
+        let delays = backoff().set_max_attempts(3).delays().count();
+        let no_delays = backoff().set_max_attempts(0).delays().count();
+
+        // assert
+        assert_eq!(2, delays);
+        assert_eq!(0, no_delays);
+    }
+
+    #[tokio::test]
+    async fn retry_with_backoff_retries_until_operation_succeeds() {
+        // arrange
+        let attempts = AtomicU32::new(0);
+
+        // act
+        let result = retry_with_backoff(
+            &backoff(),
+            || async {
+                match attempts.fetch_add(1, Ordering::Relaxed) {
+                    0 | 1 => Err("unavailable"),
+                    attempt => Ok(attempt),
+                }
+            },
+            |_| true,
+        )
+        .await;
+
+        // assert
+        assert_eq!(Ok(2), result);
+        assert_eq!(3, attempts.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn retry_with_backoff_stops_at_errors_which_are_not_retryable() {
+        // arrange
+        let attempts = AtomicU32::new(0);
+
+        // act
+        let result: Result<(), _> = retry_with_backoff(
+            &backoff(),
+            || async {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Err("invalid")
+            },
+            |e| *e != "invalid",
+        )
+        .await;
+
+        // assert
+        assert_eq!(Err("invalid"), result);
+        assert_eq!(1, attempts.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn retry_with_backoff_stops_after_max_attempts() {
+        // arrange
+        let attempts = AtomicU32::new(0);
+
+        // act
+        let result: Result<(), _> = retry_with_backoff(
+            &backoff().set_max_attempts(3),
+            || async {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Err("unavailable")
+            },
+            |_| true,
+        )
+        .await;
+
+        // assert
+        assert_eq!(Err("unavailable"), result);
+        assert_eq!(3, attempts.load(Ordering::Relaxed));
+    }
+}
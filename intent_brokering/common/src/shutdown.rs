@@ -3,7 +3,8 @@
 // SPDX-License-Identifier: MIT
 
 use std::net::SocketAddr;
-use tokio::{signal::ctrl_c, spawn};
+use tokio::{net::TcpListener, signal::ctrl_c, spawn};
+use tokio_stream::wrappers::TcpListenerStream;
 use tokio_util::sync::CancellationToken;
 use tonic::{async_trait, transport::server::Router};
 use tracing::error;
@@ -33,6 +34,14 @@ pub trait RouterExt {
         cancellation_token: CancellationToken,
     ) -> Result<(), Error>;
 
+    /// Serves on a listener which is already bound, e.g. to notify that the
+    /// server is ready before serving.
+    async fn serve_listener_with_cancellation(
+        self,
+        listener: TcpListener,
+        cancellation_token: CancellationToken,
+    ) -> Result<(), Error>;
+
     async fn serve_with_ctrl_c_shutdown(self, socket_addr: SocketAddr) -> Result<(), Error>;
 }
 
@@ -48,6 +57,19 @@ impl RouterExt for Router {
             .map_err_with("Error when serving gRPC server.")
     }
 
+    async fn serve_listener_with_cancellation(
+        self,
+        listener: TcpListener,
+        cancellation_token: CancellationToken,
+    ) -> Result<(), Error> {
+        self.serve_with_incoming_shutdown(
+            TcpListenerStream::new(listener),
+            cancellation_token.cancelled(),
+        )
+        .await
+        .map_err_with("Error when serving gRPC server.")
+    }
+
     async fn serve_with_ctrl_c_shutdown(self, socket_addr: SocketAddr) -> Result<(), Error> {
         self.serve_with_cancellation(socket_addr, ctrl_c_cancellation()).await
     }
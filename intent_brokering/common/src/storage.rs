@@ -0,0 +1,290 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Persists the state of components across restarts behind a shared
+//! [`Storage`] trait, such that embedded targets can plug in a store suited
+//! to their hardware, e.g. one that is friendly to flash memory, once for all
+//! persistence features.
+//!
+//! Values are stored as bytes under a key within a namespace, which keeps the
+//! entries of different features apart. [`InMemoryStorage`] keeps entries for
+//! the lifetime of the process, e.g. for tests, and [`FileStorage`] stores
+//! each entry in a file of its own.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{ErrorKind, Write as _};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::error::{Error, ResultExt as _};
+
+/// Stores byte values by key within namespaces. Namespaces and keys must not
+/// be empty.
+pub trait Storage: Send + Sync {
+    /// Returns the value of a key, if it is stored.
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>, Error>;
+
+    /// Stores the value of a key, replacing its previous value.
+    fn put(&self, namespace: &str, key: &str, value: &[u8]) -> Result<(), Error>;
+
+    /// Returns the entries of a namespace whose key starts with the given
+    /// prefix, ordered by key.
+    fn scan(&self, namespace: &str, prefix: &str) -> Result<Vec<(String, Vec<u8>)>, Error>;
+
+    /// Removes a key, returning whether it was stored.
+    fn delete(&self, namespace: &str, key: &str) -> Result<bool, Error>;
+}
+
+/// Stores entries in memory, such that they are lost when the process ends.
+#[derive(Debug, Default)]
+pub struct InMemoryStorage(Mutex<BTreeMap<String, BTreeMap<String, Vec<u8>>>>);
+
+impl InMemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for InMemoryStorage {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.0.lock().unwrap().get(namespace).and_then(|entries| entries.get(key)).cloned())
+    }
+
+    fn put(&self, namespace: &str, key: &str, value: &[u8]) -> Result<(), Error> {
+        self.0
+            .lock()
+            .unwrap()
+            .entry(namespace.to_owned())
+            .or_default()
+            .insert(key.to_owned(), value.to_vec());
+        Ok(())
+    }
+
+    fn scan(&self, namespace: &str, prefix: &str) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        let namespaces = self.0.lock().unwrap();
+        let Some(entries) = namespaces.get(namespace) else {
+            return Ok(Vec::new());
+        };
+
+        Ok(entries
+            .range(prefix.to_owned()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<bool, Error> {
+        let mut namespaces = self.0.lock().unwrap();
+        let Some(entries) = namespaces.get_mut(namespace) else {
+            return Ok(false);
+        };
+
+        let deleted = entries.remove(key).is_some();
+        if entries.is_empty() {
+            namespaces.remove(namespace);
+        }
+
+        Ok(deleted)
+    }
+}
+
+/// Stores each entry in a file named after its key, in a directory named
+/// after its namespace below the root directory. Names are percent-encoded,
+/// such that any key maps to a valid file name. A value is written to a
+/// temporary file first, which then replaces the file of the entry, hence a
+/// crash while writing does not corrupt the previous value.
+#[derive(Debug, Clone)]
+pub struct FileStorage {
+    root: PathBuf,
+}
+
+impl FileStorage {
+    /// Stores the entries below the root directory, which is created when
+    /// the first entry is stored.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn directory(&self, namespace: &str) -> Result<PathBuf, Error> {
+        Ok(self.root.join(encode(namespace)?))
+    }
+
+    fn file(&self, namespace: &str, key: &str) -> Result<PathBuf, Error> {
+        Ok(self.directory(namespace)?.join(encode(key)?))
+    }
+}
+
+impl Storage for FileStorage {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        match fs::read(self.file(namespace, key)?) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).map_err_with(format!("Failed to read '{key}' of '{namespace}'.")),
+        }
+    }
+
+    fn put(&self, namespace: &str, key: &str, value: &[u8]) -> Result<(), Error> {
+        let directory = self.directory(namespace)?;
+        let file = self.file(namespace, key)?;
+        // Encoded names never start with a dot, hence temporary files cannot
+        // collide with entries.
+        let temporary = directory.join(format!(".{}.tmp", encode(key)?));
+
+        fs::create_dir_all(&directory)
+            .and_then(|_| {
+                let mut temporary = fs::File::create(&temporary)?;
+                temporary.write_all(value)?;
+                temporary.sync_all()
+            })
+            .and_then(|_| fs::rename(&temporary, &file))
+            .map_err_with(format!("Failed to write '{key}' of '{namespace}'."))
+    }
+
+    fn scan(&self, namespace: &str, prefix: &str) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        let directory = match fs::read_dir(self.directory(namespace)?) {
+            Ok(directory) => directory,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).map_err_with(format!("Failed to scan '{namespace}'.")),
+        };
+
+        let mut entries = Vec::new();
+        for entry in directory {
+            let entry = entry.map_err_with(format!("Failed to scan '{namespace}'."))?;
+            let Some(key) = entry.file_name().to_str().and_then(decode) else {
+                continue;
+            };
+
+            if key.starts_with(prefix) {
+                let value = fs::read(entry.path())
+                    .map_err_with(format!("Failed to read '{key}' of '{namespace}'."))?;
+                entries.push((key, value));
+            }
+        }
+
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(entries)
+    }
+
+    fn delete(&self, namespace: &str, key: &str) -> Result<bool, Error> {
+        match fs::remove_file(self.file(namespace, key)?) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e).map_err_with(format!("Failed to delete '{key}' of '{namespace}'.")),
+        }
+    }
+}
+
+/// Percent-encodes all bytes of a name except ASCII letters, digits, `-`,
+/// `_` and dots which do not lead the name.
+fn encode(name: &str) -> Result<String, Error> {
+    if name.is_empty() {
+        return Err(Error::new("Namespaces and keys of entries must not be empty."));
+    }
+
+    let mut encoded = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' => encoded.push(byte as char),
+            b'.' if !encoded.is_empty() => encoded.push('.'),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+
+    Ok(encoded)
+}
+
+/// Decodes a name encoded with [`encode`], or returns `None` for file names
+/// which are not encoded names, e.g. temporary files.
+fn decode(name: &str) -> Option<String> {
+    if name.is_empty() || name.starts_with('.') {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut rest = name.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, FileStorage, InMemoryStorage, Storage};
+
+    #[test]
+    fn in_memory_storage_stores_entries() {
+        assert_stores_entries(&InMemoryStorage::new());
+    }
+
+    #[test]
+    fn file_storage_stores_entries() {
+        let directory = tempfile::tempdir().unwrap();
+        assert_stores_entries(&FileStorage::new(directory.path().join("storage")));
+    }
+
+    #[test]
+    fn file_storage_keeps_entries_across_instances() {
+        // arrange
+        let directory = tempfile::tempdir().unwrap();
+        FileStorage::new(directory.path()).put("registry", "snapshot", b"{}").unwrap();
+
+        // act
+        let result = FileStorage::new(directory.path()).get("registry", "snapshot").unwrap();
+
+        // assert
+        assert_eq!(Some(b"{}".to_vec()), result);
+    }
+
+    #[test]
+    fn encode_maps_names_to_file_names() {
+        for name in ["sdv.door/key", "..", ".hidden", "ä%", "a b"] {
+            let encoded = encode(name).unwrap();
+            assert!(!encoded.starts_with('.'));
+            assert!(!encoded.contains(['/', '\\', ' ']));
+            assert_eq!(Some(name.to_owned()), decode(&encoded));
+        }
+
+        assert!(encode("").is_err());
+        assert_eq!(None, decode(".a.tmp"));
+    }
+
+    fn assert_stores_entries(subject: &dyn Storage) {
+        // arrange
+        subject.put("idempotency", "sdv.door/b", b"2").unwrap();
+        subject.put("idempotency", "sdv.door/a", b"1").unwrap();
+        subject.put("idempotency", "sdv.seat/a", b"3").unwrap();
+        subject.put("registry", "sdv.door/a", b"4").unwrap();
+        subject.put("idempotency", "sdv.door/b", b"5").unwrap();
+
+        // act
+        let scanned = subject.scan("idempotency", "sdv.door/").unwrap();
+        let deleted = subject.delete("idempotency", "sdv.door/a").unwrap();
+        let deleted_again = subject.delete("idempotency", "sdv.door/a").unwrap();
+
+        // assert
+        assert_eq!(
+            vec![
+                ("sdv.door/a".to_owned(), b"1".to_vec()),
+                ("sdv.door/b".to_owned(), b"5".to_vec())
+            ],
+            scanned
+        );
+        assert!(deleted);
+        assert!(!deleted_again);
+        assert_eq!(None, subject.get("idempotency", "sdv.door/a").unwrap());
+        assert_eq!(Some(b"4".to_vec()), subject.get("registry", "sdv.door/a").unwrap());
+        assert_eq!(1, subject.scan("idempotency", "sdv.seat").unwrap().len());
+        assert!(subject.scan("unknown", "").unwrap().is_empty());
+    }
+}
@@ -2,31 +2,73 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
-use std::{ops::Deref, sync::Arc, time::SystemTime};
+use std::{
+    any::Any,
+    collections::HashMap,
+    ops::Deref,
+    pin::Pin,
+    sync::{Arc, Mutex, RwLock},
+    task::{Context, Poll},
+    time::{Duration, Instant, SystemTime},
+};
 
 use async_trait::async_trait;
 use intent_brokering_proto::{
     common::ValueMessage,
-    common::{SubscribeFulfillment, SubscribeIntent, ValueEnum},
-    streaming::{channel_service_server::ChannelService, Event, OpenRequest},
+    common::{Freshness, SourceType, SubscribeFulfillment, SubscribeIntent, ValueEnum},
+    streaming::{
+        channel_service_server::ChannelService, CloseRequest, CloseResponse, Event, Gap,
+        ListSubscriptionsRequest, ListSubscriptionsResponse, OpenRequest, RenewLeaseRequest,
+        RenewLeaseResponse, Subscription, UpdateSubscriptionRequest, UpdateSubscriptionResponse,
+    },
 };
-use tokio::spawn;
-use tokio_stream::wrappers::ReceiverStream;
+use tokio::{spawn, task::JoinHandle};
+use tokio_stream::Stream;
 use tonic::{Response, Status};
+use tracing::Instrument as _;
 use uuid::Uuid;
 
+use crate::filter::Filter;
+
+pub use ess::SourceChange;
+
 type EventSubSystem<T> = ess::EventSubSystem<Box<str>, Box<str>, T, Result<Event, Status>>;
 
+type ParametersBySource = HashMap<Box<str>, Arc<Mutex<Parameters>>>;
+
+type TasksBySource = HashMap<Box<str>, ServeTask>;
+
 /// [`StreamingEss`](StreamingEss) integrates the reusable
 /// [`EventSubSystem`](ess::EventSubSystem) component with the Intent Broker gRPC
 /// streaming contract. Cloning [`StreamingEss`](StreamingEss) is cheap, it will
 /// not create a new instance but refer to the same underlying instance instead.
 #[derive(Clone)]
-pub struct StreamingEss<T>(Arc<EventSubSystem<T>>);
+pub struct StreamingEss<T> {
+    ess: Arc<EventSubSystem<T>>,
+    /// The parameters of the served subscriptions, by channel, which are
+    /// shared with the tasks serving them.
+    parameters: Arc<Mutex<HashMap<Box<str>, ParametersBySource>>>,
+    /// The tasks serving the subscriptions, by channel.
+    tasks: Arc<Mutex<HashMap<Box<str>, TasksBySource>>>,
+    /// The leases of the channels opened with a lease, by channel.
+    leases: Arc<Mutex<HashMap<Box<str>, Arc<Lease>>>>,
+    /// The declared types of the values of sources, by source.
+    source_types: Arc<RwLock<HashMap<Box<str>, SourceType>>>,
+}
 
 impl<T: Clone> StreamingEss<T> {
     pub fn new() -> Self {
-        Self(Arc::new(EventSubSystem::new()))
+        Self::new_with_config(Default::default())
+    }
+
+    pub fn new_with_config(config: ess::Config) -> Self {
+        Self {
+            ess: Arc::new(EventSubSystem::new_with_config(config)),
+            parameters: Default::default(),
+            tasks: Default::default(),
+            leases: Default::default(),
+            source_types: Default::default(),
+        }
     }
 }
 
@@ -36,77 +78,664 @@ impl<T: Clone> Default for StreamingEss<T> {
     }
 }
 
+/// The parameters of a served subscription, which can be replaced while the
+/// subscription is served.
+#[derive(Default)]
+struct Parameters {
+    filter: Option<(String, Filter)>,
+    min_interval: Option<Duration>,
+    last_delivered: Option<Instant>,
+}
+
+impl Parameters {
+    fn new(filter: Option<(String, Filter)>) -> Self {
+        Self { filter, ..Default::default() }
+    }
+
+    /// Returns whether an event is delivered. The value of the event is only
+    /// computed if a filter needs to be evaluated.
+    fn admits(&mut self, value: impl FnOnce() -> ValueEnum) -> bool {
+        if let (Some(min_interval), Some(last_delivered)) = (self.min_interval, self.last_delivered)
+        {
+            if last_delivered.elapsed() < min_interval {
+                return false;
+            }
+        }
+
+        if let Some((_, filter)) = self.filter.as_mut() {
+            if !filter.matches(&value()) {
+                return false;
+            }
+        }
+
+        if self.min_interval.is_some() {
+            self.last_delivered = Some(Instant::now());
+        }
+
+        true
+    }
+}
+
+/// The lease of a channel, which is closed unless the lease is renewed within
+/// its duration.
+struct Lease {
+    duration: Duration,
+    renewed: Mutex<Instant>,
+}
+
+impl Lease {
+    fn new(duration: Duration) -> Self {
+        Self { duration, renewed: Mutex::new(Instant::now()) }
+    }
+
+    fn renew(&self) {
+        *self.renewed.lock().unwrap() = Instant::now();
+    }
+
+    fn expires(&self) -> Instant {
+        *self.renewed.lock().unwrap() + self.duration
+    }
+}
+
+/// A task serving a subscription of a channel.
+struct ServeTask {
+    started: Instant,
+    handle: JoinHandle<()>,
+}
+
+/// Represents the lifecycle of a task serving a subscription of a channel.
+/// Tasks are tracked until their channel is closed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TaskStatistics {
+    pub channel_id: Box<str>,
+    pub source: Box<str>,
+    /// The time elapsed since the task was spawned.
+    pub age: Duration,
+    /// Whether the task is still serving the subscription. A task stops when
+    /// its subscription is revoked or its channel is abandoned.
+    pub alive: bool,
+}
+
+/// The stream of events of a channel. Dropping the stream, e.g. when the
+/// consumer disconnects, closes the channel. Reading an event renews the
+/// lease of the channel, if any.
+pub struct ChannelStream {
+    inner: ess::EventStream<Result<Event, Status>>,
+    lease: Option<Arc<Lease>>,
+    on_close: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl Stream for ChannelStream {
+    type Item = Result<Event, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = Pin::new(&mut self.inner).poll_next(cx);
+        if let (Poll::Ready(Some(_)), Some(lease)) = (&poll, &self.lease) {
+            lease.renew();
+        }
+        poll
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for ChannelStream {
+    fn drop(&mut self) {
+        if let Some(on_close) = self.on_close.take() {
+            on_close();
+        }
+    }
+}
+
+/// The data of an event along with the time at which it was sampled, on the
+/// clock of its provider, and optionally that time corrected for the skew of
+/// the provider clock, and how fresh the data is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timestamped<T> {
+    pub data: T,
+    pub timestamp: SystemTime,
+    pub normalized_timestamp: Option<SystemTime>,
+    pub freshness: Option<Freshness>,
+}
+
+impl<T> Timestamped<T> {
+    pub fn new(data: T, timestamp: SystemTime) -> Self {
+        Self { data, timestamp, normalized_timestamp: None, freshness: None }
+    }
+
+    pub fn now(data: T) -> Self {
+        Self::new(data, SystemTime::now())
+    }
+
+    /// Describes how fresh the data is, e.g. whether it is cached. The time
+    /// at which the data was sampled is the timestamp of the event instead.
+    pub fn with_freshness(self, freshness: Freshness) -> Self {
+        Self { freshness: Some(Freshness { sampled_at: None, ..freshness }), ..self }
+    }
+}
+
+/// Encodes the data of the events of a source into the values delivered to
+/// consumers. Closures taking the data and returning a timestamped value are
+/// encoders, regardless of the source. Use [`SourceEncoders`] to encode the
+/// events of each source differently.
+pub trait Encoder<T>: Send + Sync {
+    fn encode(&self, source: &str, data: T) -> Timestamped<ValueEnum>;
+}
+
+impl<T, F> Encoder<T> for F
+where
+    F: Fn(T) -> Timestamped<ValueEnum> + Send + Sync,
+{
+    fn encode(&self, _source: &str, data: T) -> Timestamped<ValueEnum> {
+        self(data)
+    }
+}
+
+/// Encodes the events of each source with the encoder of the source, or with
+/// a fallback encoder for sources without an encoder of their own.
+pub struct SourceEncoders<T> {
+    by_source: HashMap<Box<str>, Box<dyn Encoder<T>>>,
+    fallback: Box<dyn Encoder<T>>,
+}
+
+impl<T> SourceEncoders<T> {
+    pub fn new(fallback: impl Encoder<T> + 'static) -> Self {
+        Self { by_source: HashMap::new(), fallback: Box::new(fallback) }
+    }
+
+    /// Encodes the events of the source with the given encoder.
+    pub fn with(mut self, source: impl Into<Box<str>>, encoder: impl Encoder<T> + 'static) -> Self {
+        self.by_source.insert(source.into(), Box::new(encoder));
+        self
+    }
+}
+
+impl<T> Encoder<T> for SourceEncoders<T> {
+    fn encode(&self, source: &str, data: T) -> Timestamped<ValueEnum> {
+        self.by_source.get(source).unwrap_or(&self.fallback).encode(source, data)
+    }
+}
+
+/// Data of any type, which lets sources publishing different types of data
+/// share one ESS. Such data is encoded with the encoders returned by
+/// [`downcast`], typically one per source using [`SourceEncoders`].
+pub type AnyData = Arc<dyn Any + Send + Sync>;
+
+/// Returns an encoder of data of type `U` published as [`AnyData`], which
+/// stamps each event with the time at which it is sent. Data of any other type
+/// is encoded as null.
+pub fn downcast<U: Any>(
+    into_value: impl Fn(&U) -> ValueEnum + Send + Sync,
+) -> impl Encoder<AnyData> {
+    move |data: AnyData| {
+        Timestamped::now(match data.downcast_ref::<U>() {
+            Some(data) => into_value(data),
+            None => {
+                tracing::warn!(
+                    "Encoding data other than '{}' as null.",
+                    std::any::type_name::<U>()
+                );
+                ValueEnum::Null(0)
+            }
+        })
+    }
+}
+
 impl<T: Clone + Send + 'static> StreamingEss<T> {
+    /// Serves the subscriptions, stamping each event with the time at which
+    /// it is sent.
     pub fn serve_subscriptions(
         &self,
         subscribe_intent: SubscribeIntent,
-        into_value: fn(T) -> ValueEnum,
+        into_value: impl Fn(T) -> ValueEnum + Send + Sync + 'static,
     ) -> Result<SubscribeFulfillment, Status> {
+        self.serve_encoded_subscriptions(subscribe_intent, move |data| {
+            Timestamped::now(into_value(data))
+        })
+    }
+
+    /// Serves the subscriptions, stamping each event with the timestamps of
+    /// its data, e.g. the time at which the provider sampled the value.
+    pub fn serve_timestamped_subscriptions(
+        &self,
+        subscribe_intent: SubscribeIntent,
+        into_value: impl Fn(T) -> Timestamped<ValueEnum> + Send + Sync + 'static,
+    ) -> Result<SubscribeFulfillment, Status> {
+        self.serve_encoded_subscriptions(subscribe_intent, into_value)
+    }
+
+    /// Serves the subscriptions, encoding the events with the given encoder.
+    /// Events dropped for a subscription are reported to its channel with a
+    /// gap marker before the next event. Each subscription is served by a task
+    /// of its own, which is instrumented with a span naming its channel and
+    /// source.
+    pub fn serve_encoded_subscriptions(
+        &self,
+        subscribe_intent: SubscribeIntent,
+        encoder: impl Encoder<T> + 'static,
+    ) -> Result<SubscribeFulfillment, Status> {
+        let encoder: Arc<dyn Encoder<T>> = Arc::new(encoder);
+        let SubscribeIntent { channel_id, sources, filters } = subscribe_intent;
+        let mut filters = parse_filters(&sources, filters)?;
+        let channel_id: Box<str> = channel_id.into();
+
         let subscriptions = self
-            .register_subscriptions(
-                subscribe_intent.channel_id.into(),
-                subscribe_intent.sources.into_iter().map(|s| s.into()),
-            )
+            .register_subscriptions(channel_id.clone(), sources.into_iter().map(|s| s.into()))
             .map_err(|_| Status::failed_precondition("The specified client does not exist."))?;
+        self.renew_channel_lease(&channel_id)?;
 
         for subscription in subscriptions {
-            let source = subscription.event_id().to_string();
+            let event_id = subscription.event_id().clone();
+            let source = event_id.to_string();
+            let parameters = Arc::new(Mutex::new(Parameters::new(filters.remove(&source))));
+            self.parameters
+                .lock()
+                .unwrap()
+                .entry(channel_id.clone())
+                .or_default()
+                .insert(event_id.clone(), Arc::clone(&parameters));
+
+            let into_gap = {
+                let source = source.clone();
+                move |from_seq, to_seq| {
+                    Ok(Event {
+                        source: source.clone(),
+                        timestamp: Some(SystemTime::now().into()),
+                        gap: Some(Gap { from_seq, to_seq }),
+                        ..Default::default()
+                    })
+                }
+            };
 
-            spawn(subscription.serve(move |data, seq| {
+            let source_type = self.source_type(&source);
+
+            let span = tracing::info_span!("serve_subscription", channel_id = %channel_id, source = %source);
+
+            let admits = {
+                let encoder = Arc::clone(&encoder);
+                let source = source.clone();
+                move |data: &T| {
+                    parameters.lock().unwrap().admits(|| encoder.encode(&source, data.clone()).data)
+                }
+            };
+
+            let encoder = Arc::clone(&encoder);
+            let into_event = move |data, seq| {
+                let Timestamped { data, timestamp, normalized_timestamp, freshness } =
+                    encoder.encode(&source, data);
                 Ok(Event {
                     source: source.clone(),
-                    value: Some(ValueMessage { value: Some(into_value(data)) }),
+                    value: Some(ValueMessage { value: Some(data) }),
                     seq,
-                    timestamp: Some(SystemTime::now().into()),
+                    timestamp: Some(timestamp.into()),
+                    normalized_timestamp: normalized_timestamp.map(Into::into),
+                    gap: None,
+                    end_of_stream: false,
+                    freshness,
+                    source_type: source_type.clone(),
                 })
-            }));
+            };
+
+            let handle = spawn(
+                subscription.serve_with_gap_markers(admits, into_event, into_gap).instrument(span),
+            );
+
+            self.tasks
+                .lock()
+                .unwrap()
+                .entry(channel_id.clone())
+                .or_default()
+                .insert(event_id, ServeTask { started: Instant::now(), handle });
+        }
+
+        Ok(SubscribeFulfillment::default())
+    }
+
+    /// Returns whether the subscriptions can still be served, i.e. no task
+    /// panicked while holding the locks of the ESS.
+    pub fn is_healthy(&self) -> bool {
+        self.tasks.lock().is_ok() && self.parameters.lock().is_ok()
+    }
+
+    /// Returns the lifecycle of the tasks serving the subscriptions of all
+    /// channels.
+    pub fn task_statistics(&self) -> Vec<TaskStatistics> {
+        self.tasks
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|(channel_id, tasks)| {
+                tasks.iter().map(|(source, task)| TaskStatistics {
+                    channel_id: channel_id.clone(),
+                    source: source.clone(),
+                    age: task.started.elapsed(),
+                    alive: !task.handle.is_finished(),
+                })
+            })
+            .collect()
+    }
+
+    /// Closes a channel, deregistering its subscriptions and cancelling the
+    /// tasks serving them. The stream of the channel ends with an event
+    /// marking its end. Closing a channel which is not open has no effect.
+    pub fn close_channel(&self, channel_id: &str) {
+        let end_of_stream = Event {
+            timestamp: Some(SystemTime::now().into()),
+            end_of_stream: true,
+            ..Default::default()
+        };
+
+        _ = self.stop_reading_events_with(channel_id, Ok(end_of_stream));
+        self.parameters.lock().unwrap().remove(channel_id);
+        self.leases.lock().unwrap().remove(channel_id);
+
+        // Deregistering the subscriptions lets the tasks finish gracefully.
+        // They are not aborted, as a finishing task removes the source of its
+        // subscription if no other subscription remains, such that events are
+        // no longer published to it.
+        self.tasks.lock().unwrap().remove(channel_id);
+    }
+
+    /// Returns the active subscriptions of a channel, ordered by source.
+    pub fn list_channel_subscriptions(
+        &self,
+        channel_id: &str,
+    ) -> Result<Vec<Subscription>, Status> {
+        let sources = self.active_subscriptions(channel_id)?;
+        let mut parameters = self.parameters.lock().unwrap();
+
+        // Parameters of subscriptions which are no longer served are removed.
+        let parameters = parameters.entry(channel_id.into()).or_default();
+        parameters.retain(|source, _| sources.contains(source));
+
+        let mut subscriptions: Vec<_> = sources
+            .into_iter()
+            .map(|source| {
+                let (filter, min_interval) = parameters
+                    .get(&source)
+                    .map(|parameters| {
+                        let parameters = parameters.lock().unwrap();
+                        (
+                            parameters.filter.as_ref().map(|(expression, _)| expression.clone()),
+                            parameters.min_interval,
+                        )
+                    })
+                    .unwrap_or_default();
+
+                Subscription {
+                    source: source.into(),
+                    filter: filter.unwrap_or_default(),
+                    min_interval: min_interval.and_then(|d| d.try_into().ok()),
+                }
+            })
+            .collect();
+
+        subscriptions.sort_by(|a, b| a.source.cmp(&b.source));
+        Ok(subscriptions)
+    }
+
+    /// Replaces the parameters of an active subscription of a channel. An
+    /// empty filter delivers all events, and no or a zero minimum interval
+    /// removes the rate limit.
+    pub fn update_channel_subscription(
+        &self,
+        channel_id: &str,
+        subscription: Subscription,
+    ) -> Result<(), Status> {
+        let Subscription { source, filter, min_interval } = subscription;
+
+        if !self.active_subscriptions(channel_id)?.iter().any(|s| s.as_ref() == source) {
+            return Err(Status::not_found(format!("Channel is not subscribed to '{source}'.")));
+        }
+
+        let filter = match filter.as_str() {
+            "" => None,
+            expression => Some((
+                expression.to_owned(),
+                Filter::parse(expression).map_err(|e| {
+                    Status::invalid_argument(format!("Invalid filter for '{source}': {e}"))
+                })?,
+            )),
+        };
+
+        let min_interval = min_interval
+            .map(Duration::try_from)
+            .transpose()
+            .map_err(|_| Status::invalid_argument("Minimum interval must not be negative."))?
+            .filter(|d| !d.is_zero());
+
+        let parameters = self
+            .parameters
+            .lock()
+            .unwrap()
+            .get(channel_id)
+            .and_then(|parameters| parameters.get(source.as_str()))
+            .cloned()
+            .ok_or_else(|| {
+                Status::failed_precondition(format!(
+                    "Subscription to '{source}' is not served by the channel service."
+                ))
+            })?;
+
+        let mut parameters = parameters.lock().unwrap();
+        parameters.filter = filter;
+        parameters.min_interval = min_interval;
+        Ok(())
+    }
+
+    /// Renews the lease of a channel, which has no effect if the channel was
+    /// opened without a lease.
+    pub fn renew_channel_lease(&self, channel_id: &str) -> Result<(), Status> {
+        if !self.is_reading_events(channel_id) {
+            return Err(Status::failed_precondition("The specified client does not exist."));
+        }
+
+        if let Some(lease) = self.leases.lock().unwrap().get(channel_id) {
+            lease.renew();
         }
 
-        Ok(SubscribeFulfillment {})
+        Ok(())
+    }
+
+    /// Declares the type of the values of a source, which is attached to its
+    /// events. Subscriptions served already keep the type declared when they
+    /// were served.
+    pub fn set_source_type(&self, source: impl Into<Box<str>>, source_type: SourceType) {
+        self.source_types.write().unwrap().insert(source.into(), source_type);
+    }
+
+    /// Returns the declared type of the values of a source, if any.
+    pub fn source_type(&self, source: &str) -> Option<SourceType> {
+        self.source_types.read().unwrap().get(source).cloned()
+    }
+
+    /// Forgets the declared types of the sources for which `f` returns
+    /// false, returning how many were forgotten.
+    pub fn retain_source_types(&self, mut f: impl FnMut(&str) -> bool) -> usize {
+        let mut source_types = self.source_types.write().unwrap();
+        let count = source_types.len();
+        source_types.retain(|source, _| f(source));
+        count - source_types.len()
+    }
+
+    fn active_subscriptions(&self, channel_id: &str) -> Result<Vec<Box<str>>, Status> {
+        self.renew_channel_lease(channel_id)?;
+        Ok(self.get_subscriptions(channel_id).into_iter().collect())
+    }
+
+    /// Closes a channel once its lease expires. The task ends early if the
+    /// channel is closed before.
+    fn expire_lease(&self, channel_id: Box<str>, lease: Arc<Lease>) {
+        let ess = self.clone();
+        spawn(async move {
+            loop {
+                let expires = lease.expires();
+                if expires <= Instant::now() {
+                    break;
+                }
+
+                tokio::time::sleep_until(expires.into()).await;
+
+                let leased = ess
+                    .leases
+                    .lock()
+                    .unwrap()
+                    .get(&channel_id)
+                    .is_some_and(|l| Arc::ptr_eq(l, &lease));
+                if !leased {
+                    return;
+                }
+            }
+
+            tracing::warn!(channel_id = %channel_id, "Closing channel because its lease expired.");
+            ess.close_channel(&channel_id);
+        });
     }
 }
 
+/// Parses the filter expressions of a subscription, each of which must be
+/// attached to one of the subscribed sources.
+fn parse_filters(
+    sources: &[String],
+    filters: HashMap<String, String>,
+) -> Result<HashMap<String, (String, Filter)>, Status> {
+    filters
+        .into_iter()
+        .map(|(source, expression)| {
+            if !sources.contains(&source) {
+                return Err(Status::invalid_argument(format!(
+                    "Filter is attached to '{source}', which is not subscribed to."
+                )));
+            }
+
+            let filter = Filter::parse(&expression).map_err(|e| {
+                Status::invalid_argument(format!("Invalid filter for '{source}': {e}"))
+            })?;
+
+            Ok((source, (expression, filter)))
+        })
+        .collect()
+}
+
 #[async_trait]
 impl<T> ChannelService for StreamingEss<T>
 where
     T: Clone + Send + Sync + 'static,
 {
-    type OpenStream = ReceiverStream<Result<Event, Status>>;
+    type OpenStream = ChannelStream;
 
     async fn open(
         &self,
-        _: tonic::Request<OpenRequest>,
+        request: tonic::Request<OpenRequest>,
     ) -> Result<Response<Self::OpenStream>, Status> {
         const METADATA_KEY: &str = "x-chariott-channel-id";
 
+        let OpenRequest { buffer_size, lease } = request.into_inner();
+        let lease = lease
+            .map(Duration::try_from)
+            .transpose()
+            .map_err(|_| Status::invalid_argument("Lease must not be negative."))?
+            .filter(|d| !d.is_zero())
+            .map(|d| Arc::new(Lease::new(d)));
+
         let id = Uuid::new_v4().to_string();
-        let (_, receiver_stream) = self.read_events(id.clone().into());
-        let mut response = Response::new(receiver_stream);
+        let (_, receiver_stream) = match buffer_size {
+            0 => self.read_events(id.clone().into()),
+            buffer_size => self.read_events_with_buffer_size(id.clone().into(), buffer_size as _),
+        }
+        .map_err(|_| Status::resource_exhausted("The channel buffer exceeds the buffer budget."))?;
+
+        if let Some(lease) = &lease {
+            self.leases.lock().unwrap().insert(id.clone().into(), Arc::clone(lease));
+            self.expire_lease(id.clone().into(), Arc::clone(lease));
+        }
+
+        let on_close = {
+            let ess = self.clone();
+            let id = id.clone();
+            move || ess.close_channel(&id)
+        };
+
+        let mut response = Response::new(ChannelStream {
+            inner: receiver_stream,
+            lease,
+            on_close: Some(Box::new(on_close)),
+        });
         response.metadata_mut().insert(METADATA_KEY, id.try_into().unwrap());
         Ok(response)
     }
+
+    async fn list_subscriptions(
+        &self,
+        request: tonic::Request<ListSubscriptionsRequest>,
+    ) -> Result<Response<ListSubscriptionsResponse>, Status> {
+        let subscriptions = self.list_channel_subscriptions(&request.into_inner().channel_id)?;
+        Ok(Response::new(ListSubscriptionsResponse { subscriptions }))
+    }
+
+    async fn update_subscription(
+        &self,
+        request: tonic::Request<UpdateSubscriptionRequest>,
+    ) -> Result<Response<UpdateSubscriptionResponse>, Status> {
+        let UpdateSubscriptionRequest { channel_id, subscription } = request.into_inner();
+        let subscription = subscription
+            .ok_or_else(|| Status::invalid_argument("Subscription must be specified."))?;
+
+        self.update_channel_subscription(&channel_id, subscription)?;
+        Ok(Response::new(UpdateSubscriptionResponse {}))
+    }
+
+    async fn close(
+        &self,
+        request: tonic::Request<CloseRequest>,
+    ) -> Result<Response<CloseResponse>, Status> {
+        let channel_id = request.into_inner().channel_id;
+
+        if !self.is_reading_events(channel_id.as_str()) {
+            return Err(Status::failed_precondition("The specified client does not exist."));
+        }
+
+        self.close_channel(&channel_id);
+        Ok(Response::new(CloseResponse {}))
+    }
+
+    async fn renew_lease(
+        &self,
+        request: tonic::Request<RenewLeaseRequest>,
+    ) -> Result<Response<RenewLeaseResponse>, Status> {
+        self.renew_channel_lease(&request.into_inner().channel_id)?;
+        Ok(Response::new(RenewLeaseResponse {}))
+    }
 }
 
 impl<T> Deref for StreamingEss<T> {
     type Target = EventSubSystem<T>;
 
     fn deref(&self) -> &Self::Target {
-        self.0.as_ref()
+        self.ess.as_ref()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
 
     use intent_brokering_proto::{
-        common::{SubscribeIntent, ValueEnum, ValueMessage},
-        streaming::{channel_service_server::ChannelService, OpenRequest},
+        common::{
+            freshness::Quality, Freshness, SourceType, SubscribeIntent, ValueEnum, ValueMessage,
+        },
+        streaming::{
+            channel_service_server::ChannelService, CloseRequest, OpenRequest, RenewLeaseRequest,
+            Subscription,
+        },
     };
     use tokio_stream::StreamExt as _;
     use tonic::{Code, Request};
 
-    use super::StreamingEss;
+    use super::{downcast, AnyData, SourceEncoders, StreamingEss, Timestamped};
 
     #[tokio::test]
     async fn open_should_set_channel_id() {
@@ -114,12 +743,31 @@ mod tests {
         let subject = setup();
 
         // act
-        let response = subject.open(Request::new(OpenRequest {})).await.unwrap();
+        let response = subject.open(Request::new(OpenRequest::default())).await.unwrap();
 
         // assert
         assert!(!response.metadata().get("x-chariott-channel-id").unwrap().is_empty());
     }
 
+    #[tokio::test]
+    async fn open_should_error_when_buffer_exceeds_budget() {
+        // arrange
+        let subject = StreamingEss::<()>::new_with_config(
+            ess::Config::default().set_client_buffer_budget(10).clone(),
+        );
+        let _open = subject
+            .open(Request::new(OpenRequest { buffer_size: 6, ..Default::default() }))
+            .await
+            .unwrap();
+
+        // act
+        let result =
+            subject.open(Request::new(OpenRequest { buffer_size: 5, ..Default::default() })).await;
+
+        // assert
+        assert_eq!(Code::ResourceExhausted, result.err().unwrap().code());
+    }
+
     #[tokio::test]
     async fn serve_subscriptions_should_serve_subscription_for_event() {
         // arrange
@@ -127,14 +775,18 @@ mod tests {
         const EVENT_B: &str = "test-event-b";
 
         let subject = setup();
-        let response = subject.open(Request::new(OpenRequest {})).await.unwrap();
+        let response = subject.open(Request::new(OpenRequest::default())).await.unwrap();
         let channel_id =
             response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
 
         // act
         subject
             .serve_subscriptions(
-                SubscribeIntent { channel_id, sources: vec![EVENT_A.into(), EVENT_B.into()] },
+                SubscribeIntent {
+                    channel_id,
+                    sources: vec![EVENT_A.into(), EVENT_B.into()],
+                    ..Default::default()
+                },
                 |_| ValueEnum::Null(0),
             )
             .unwrap();
@@ -169,7 +821,11 @@ mod tests {
 
         // act
         let result = subject.serve_subscriptions(
-            SubscribeIntent { channel_id: "client".into(), sources: vec!["test-event".into()] },
+            SubscribeIntent {
+                channel_id: "client".into(),
+                sources: vec!["test-event".into()],
+                ..Default::default()
+            },
             |_| ValueEnum::Null(0),
         );
 
@@ -179,6 +835,531 @@ mod tests {
         assert_eq!("The specified client does not exist.", result.message());
     }
 
+    #[tokio::test]
+    async fn serve_subscriptions_should_deliver_only_events_matching_filter() {
+        // arrange
+        const EVENT: &str = "test-event";
+
+        let subject = StreamingEss::<i32>::new();
+        let response = subject.open(Request::new(OpenRequest::default())).await.unwrap();
+        let channel_id =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+
+        // act
+        subject
+            .serve_subscriptions(
+                SubscribeIntent {
+                    channel_id,
+                    sources: vec![EVENT.into()],
+                    filters: [(EVENT.into(), "value > 100".into())].into(),
+                },
+                ValueEnum::Int32,
+            )
+            .unwrap();
+
+        // assert
+        for value in [50, 150, 100, 200] {
+            subject.publish(EVENT, value);
+        }
+
+        let result = response
+            .into_inner()
+            .timeout(Duration::from_millis(100))
+            .take_while(|e| e.is_ok())
+            .map(|e| e.unwrap().unwrap())
+            .map(|e| (e.seq, e.value.and_then(|v| v.value)))
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(
+            vec![(1, Some(ValueEnum::Int32(150))), (2, Some(ValueEnum::Int32(200)))],
+            result
+        );
+    }
+
+    #[tokio::test]
+    async fn serve_subscriptions_should_error_when_filter_is_invalid() {
+        // arrange
+        let subject = setup();
+        let response = subject.open(Request::new(OpenRequest::default())).await.unwrap();
+        let channel_id: String =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+
+        for (source, filter) in [("test-event", "value >"), ("other-event", "value > 1")] {
+            // act
+            let result = subject.serve_subscriptions(
+                SubscribeIntent {
+                    channel_id: channel_id.clone(),
+                    sources: vec!["test-event".into()],
+                    filters: [(source.into(), filter.into())].into(),
+                },
+                |_| ValueEnum::Null(0),
+            );
+
+            // assert
+            assert_eq!(Code::InvalidArgument, result.unwrap_err().code());
+            assert!(subject.get_subscriptions(channel_id.as_str()).into_iter().next().is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn serve_subscriptions_should_encode_events_with_closure() {
+        // arrange
+        const EVENT: &str = "test-event";
+
+        let factor = 10;
+        let subject = StreamingEss::<i32>::new();
+        let response = subject.open(Request::new(OpenRequest::default())).await.unwrap();
+        let channel_id =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+
+        // act
+        subject
+            .serve_subscriptions(
+                SubscribeIntent { channel_id, sources: vec![EVENT.into()], ..Default::default() },
+                move |value| ValueEnum::Int32(value * factor),
+            )
+            .unwrap();
+
+        // assert
+        subject.publish(EVENT, 4);
+
+        let event = response.into_inner().next().await.unwrap().unwrap();
+        assert_eq!(Some(ValueMessage { value: Some(ValueEnum::Int32(40)) }), event.value);
+    }
+
+    #[tokio::test]
+    async fn serve_encoded_subscriptions_should_encode_events_of_each_source() {
+        // arrange
+        const COUNT: &str = "count";
+        const NAME: &str = "name";
+
+        let subject = StreamingEss::<AnyData>::new();
+        let response = subject.open(Request::new(OpenRequest::default())).await.unwrap();
+        let channel_id =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+
+        let encoders = SourceEncoders::new(downcast(|value: &i32| ValueEnum::Int32(*value)))
+            .with(NAME, downcast(|value: &String| ValueEnum::String(value.clone())));
+
+        // act
+        subject
+            .serve_encoded_subscriptions(
+                SubscribeIntent {
+                    channel_id,
+                    sources: vec![COUNT.into(), NAME.into()],
+                    ..Default::default()
+                },
+                encoders,
+            )
+            .unwrap();
+
+        // assert
+        subject.publish(COUNT, Arc::new(1) as AnyData);
+        subject.publish(NAME, Arc::new("door".to_owned()) as AnyData);
+        subject.publish(COUNT, Arc::new("not a count".to_owned()) as AnyData);
+
+        let mut result = response
+            .into_inner()
+            .timeout(Duration::from_millis(100))
+            .take_while(|e| e.is_ok())
+            .map(|e| e.unwrap().unwrap())
+            .map(|e| (e.source, e.value.and_then(|v| v.value)))
+            .collect::<Vec<_>>()
+            .await;
+
+        // Each source is served by a task of its own, hence only the events
+        // of a source are ordered.
+        result.sort_by(|(a, _), (b, _)| a.cmp(b));
+        assert_eq!(
+            vec![
+                (COUNT.to_owned(), Some(ValueEnum::Int32(1))),
+                (COUNT.to_owned(), Some(ValueEnum::Null(0))),
+                (NAME.to_owned(), Some(ValueEnum::String("door".to_owned()))),
+            ],
+            result
+        );
+    }
+
+    #[tokio::test]
+    async fn serve_timestamped_subscriptions_should_stamp_events_with_timestamps_and_freshness_of_data(
+    ) {
+        // arrange
+        const EVENT: &str = "test-event";
+
+        let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let normalized_timestamp = timestamp + Duration::from_millis(250);
+        let freshness = Freshness {
+            sampled_at: Some(timestamp.into()),
+            source: "sensor".to_owned(),
+            quality: Quality::Cached as i32,
+        };
+
+        let subject = StreamingEss::<Timestamped<ValueEnum>>::new();
+        let response = subject.open(Request::new(OpenRequest::default())).await.unwrap();
+        let channel_id =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+
+        // act
+        subject
+            .serve_timestamped_subscriptions(
+                SubscribeIntent { channel_id, sources: vec![EVENT.into()], ..Default::default() },
+                |data| data,
+            )
+            .unwrap();
+
+        // assert
+        subject.publish(
+            EVENT,
+            Timestamped {
+                data: ValueEnum::Int32(1),
+                timestamp,
+                normalized_timestamp: Some(normalized_timestamp),
+                freshness: None,
+            }
+            .with_freshness(freshness.clone()),
+        );
+
+        let event = response.into_inner().next().await.unwrap().unwrap();
+
+        assert_eq!(Some(timestamp.into()), event.timestamp);
+        assert_eq!(Some(normalized_timestamp.into()), event.normalized_timestamp);
+        assert_eq!(Some(ValueMessage { value: Some(ValueEnum::Int32(1)) }), event.value);
+        assert_eq!(Some(Freshness { sampled_at: None, ..freshness }), event.freshness);
+    }
+
+    #[tokio::test]
+    async fn serve_subscriptions_should_attach_declared_source_types_to_events() {
+        // arrange
+        const TYPED: &str = "typed-event";
+        const UNTYPED: &str = "untyped-event";
+
+        let source_type =
+            SourceType { value_type: "int32".to_owned(), schema: "vss:Vehicle.Speed".to_owned() };
+        let subject = StreamingEss::<i32>::new();
+        subject.set_source_type(TYPED, source_type.clone());
+        let response = subject.open(Request::new(OpenRequest::default())).await.unwrap();
+        let channel_id =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+
+        // act
+        subject
+            .serve_subscriptions(
+                SubscribeIntent {
+                    channel_id,
+                    sources: vec![TYPED.into(), UNTYPED.into()],
+                    ..Default::default()
+                },
+                ValueEnum::Int32,
+            )
+            .unwrap();
+
+        // assert
+        subject.publish(TYPED, 1);
+        subject.publish(UNTYPED, 2);
+
+        let mut events = response.into_inner();
+        let typed = events.next().await.unwrap().unwrap();
+        let untyped = events.next().await.unwrap().unwrap();
+
+        assert_eq!(Some(source_type), typed.source_type);
+        assert_eq!(None, untyped.source_type);
+        assert_eq!(1, subject.retain_source_types(|source| source != TYPED));
+        assert_eq!(None, subject.source_type(TYPED));
+    }
+
+    #[tokio::test]
+    async fn list_channel_subscriptions_should_return_parameters_of_subscriptions() {
+        // arrange
+        let subject = setup();
+        let response = subject.open(Request::new(OpenRequest::default())).await.unwrap();
+        let channel_id: String =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+
+        subject
+            .serve_subscriptions(
+                SubscribeIntent {
+                    channel_id: channel_id.clone(),
+                    sources: vec!["b".into(), "a".into()],
+                    filters: [("b".into(), "value > 1".into())].into(),
+                },
+                |_| ValueEnum::Null(0),
+            )
+            .unwrap();
+
+        // act
+        let result = subject.list_channel_subscriptions(&channel_id).unwrap();
+
+        // assert
+        assert_eq!(
+            vec![
+                Subscription { source: "a".into(), ..Default::default() },
+                Subscription {
+                    source: "b".into(),
+                    filter: "value > 1".into(),
+                    ..Default::default()
+                },
+            ],
+            result
+        );
+    }
+
+    #[tokio::test]
+    async fn update_channel_subscription_should_replace_filter_and_rate_limit() {
+        // arrange
+        const EVENT: &str = "test-event";
+
+        let subject = StreamingEss::<i32>::new();
+        let response = subject.open(Request::new(OpenRequest::default())).await.unwrap();
+        let channel_id: String =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+
+        subject
+            .serve_subscriptions(
+                SubscribeIntent {
+                    channel_id: channel_id.clone(),
+                    sources: vec![EVENT.into()],
+                    filters: [(EVENT.into(), "value < 0".into())].into(),
+                },
+                ValueEnum::Int32,
+            )
+            .unwrap();
+
+        let subscription = Subscription {
+            source: EVENT.into(),
+            filter: "value > 100".into(),
+            min_interval: Some(Duration::from_secs(3600).try_into().unwrap()),
+        };
+
+        // act
+        subject.update_channel_subscription(&channel_id, subscription.clone()).unwrap();
+
+        // assert
+        assert_eq!(vec![subscription], subject.list_channel_subscriptions(&channel_id).unwrap());
+        for value in [50, 150, 200] {
+            subject.publish(EVENT, value);
+        }
+
+        let result = response
+            .into_inner()
+            .timeout(Duration::from_millis(100))
+            .take_while(|e| e.is_ok())
+            .map(|e| e.unwrap().unwrap())
+            .map(|e| (e.seq, e.value.and_then(|v| v.value)))
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(vec![(1, Some(ValueEnum::Int32(150)))], result);
+    }
+
+    #[tokio::test]
+    async fn update_channel_subscription_should_error_when_not_subscribed() {
+        // arrange
+        let subject = setup();
+        let response = subject.open(Request::new(OpenRequest::default())).await.unwrap();
+        let channel_id: String =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+
+        // act
+        let result = subject.update_channel_subscription(
+            &channel_id,
+            Subscription { source: "test-event".into(), ..Default::default() },
+        );
+
+        // assert
+        assert_eq!(Code::NotFound, result.unwrap_err().code());
+    }
+
+    #[tokio::test]
+    async fn task_statistics_should_track_tasks_serving_subscriptions() {
+        // arrange
+        let subject = setup();
+        let response = subject.open(Request::new(OpenRequest::default())).await.unwrap();
+        let channel_id: String =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+
+        subject
+            .serve_subscriptions(
+                SubscribeIntent {
+                    channel_id: channel_id.clone(),
+                    sources: vec!["a".into(), "b".into()],
+                    ..Default::default()
+                },
+                |_| ValueEnum::Null(0),
+            )
+            .unwrap();
+
+        // act
+        let mut result = subject.task_statistics();
+
+        // assert
+        result.sort_by(|a, b| a.source.cmp(&b.source));
+        assert_eq!(2, result.len());
+        for (expected_source, task) in ["a", "b"].into_iter().zip(result) {
+            assert_eq!(channel_id.as_str(), task.channel_id.as_ref());
+            assert_eq!(expected_source, task.source.as_ref());
+            assert!(task.alive);
+        }
+    }
+
+    #[tokio::test]
+    async fn dropping_channel_stream_should_close_channel() {
+        // arrange
+        let subject = setup();
+        let response = subject.open(Request::new(OpenRequest::default())).await.unwrap();
+        let channel_id: String =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+
+        subject
+            .serve_subscriptions(
+                SubscribeIntent {
+                    channel_id: channel_id.clone(),
+                    sources: vec!["test-event".into()],
+                    ..Default::default()
+                },
+                |_| ValueEnum::Null(0),
+            )
+            .unwrap();
+
+        // act
+        drop(response);
+
+        // assert
+        assert!(!subject.is_reading_events(channel_id.as_str()));
+        assert!(subject.task_statistics().is_empty());
+        assert_eq!(
+            Code::FailedPrecondition,
+            subject.list_channel_subscriptions(&channel_id).unwrap_err().code()
+        );
+    }
+
+    #[tokio::test]
+    async fn close_should_end_stream_with_end_of_stream_marker() {
+        // arrange
+        const EVENT: &str = "test-event";
+
+        let subject = setup();
+        let response = subject.open(Request::new(OpenRequest::default())).await.unwrap();
+        let channel_id: String =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+
+        subject
+            .serve_subscriptions(
+                SubscribeIntent {
+                    channel_id: channel_id.clone(),
+                    sources: vec![EVENT.into()],
+                    ..Default::default()
+                },
+                |_| ValueEnum::Null(0),
+            )
+            .unwrap();
+
+        subject.publish(EVENT, ());
+        let mut stream = response.into_inner();
+        let event = stream.next().await.unwrap().unwrap();
+
+        // act
+        subject.close(Request::new(CloseRequest { channel_id: channel_id.clone() })).await.unwrap();
+
+        // assert
+        let result = stream
+            .timeout(Duration::from_secs(5))
+            .map(|e| e.unwrap().unwrap())
+            .collect::<Vec<_>>()
+            .await;
+        assert!(!event.end_of_stream);
+        assert_eq!(1, result.len());
+        assert!(result[0].end_of_stream);
+        assert!(subject.task_statistics().is_empty());
+        assert!(subject.get_subscribed_events().into_iter().next().is_none());
+        assert_eq!(
+            Code::FailedPrecondition,
+            subject.close(Request::new(CloseRequest { channel_id })).await.unwrap_err().code()
+        );
+    }
+
+    #[tokio::test]
+    async fn open_should_close_channel_once_lease_expires() {
+        // arrange
+        let subject = setup();
+        let lease = Duration::from_millis(50).try_into().unwrap();
+        let response = subject
+            .open(Request::new(OpenRequest { lease: Some(lease), ..Default::default() }))
+            .await
+            .unwrap();
+        let channel_id: String =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+
+        subject
+            .serve_subscriptions(
+                SubscribeIntent {
+                    channel_id: channel_id.clone(),
+                    sources: vec!["test-event".into()],
+                    ..Default::default()
+                },
+                |_| ValueEnum::Null(0),
+            )
+            .unwrap();
+
+        // act
+        let result = response
+            .into_inner()
+            .timeout(Duration::from_secs(5))
+            .map(|e| e.unwrap().unwrap())
+            .collect::<Vec<_>>()
+            .await;
+
+        // assert
+        assert_eq!(1, result.len());
+        assert!(result[0].end_of_stream);
+        assert!(!subject.is_reading_events(channel_id.as_str()));
+        assert!(subject.get_subscribed_events().into_iter().next().is_none());
+    }
+
+    #[tokio::test]
+    async fn renew_lease_should_keep_channel_open() {
+        // arrange
+        let subject = setup();
+        let lease = Duration::from_millis(200).try_into().unwrap();
+        let response = subject
+            .open(Request::new(OpenRequest { lease: Some(lease), ..Default::default() }))
+            .await
+            .unwrap();
+        let channel_id: String =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+
+        // act
+        for _ in 0..3 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            subject
+                .renew_lease(Request::new(RenewLeaseRequest { channel_id: channel_id.clone() }))
+                .await
+                .unwrap();
+        }
+
+        // assert
+        assert!(subject.is_reading_events(channel_id.as_str()));
+        assert_eq!(
+            Code::FailedPrecondition,
+            subject.renew_channel_lease("unknown").unwrap_err().code()
+        );
+    }
+
+    #[tokio::test]
+    async fn open_should_error_when_lease_is_negative() {
+        // arrange
+        let subject = setup();
+        let lease = prost_types::Duration { seconds: -1, nanos: 0 };
+
+        // act
+        let result = subject
+            .open(Request::new(OpenRequest { lease: Some(lease), ..Default::default() }))
+            .await;
+
+        // assert
+        assert_eq!(Code::InvalidArgument, result.err().unwrap().code());
+    }
+
     fn setup() -> StreamingEss<()> {
         Default::default()
     }
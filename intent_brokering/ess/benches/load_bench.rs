@@ -10,6 +10,7 @@ const EVENT_ID: EventId = EventId;
 const DATA1: &str = "data1";
 const NUMBER_OF_EVENTS: &[usize] = &[1000, 10000];
 const NUMBER_OF_SUBSCRIBERS: &[usize] = &[1, 10, 100];
+const HOT_SOURCE_EVENTS: usize = 10000;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 struct EventId;
@@ -38,51 +39,71 @@ struct Event(EventId, SeqNum, &'static str);
 fn event_sub_system_bench(c: &mut Criterion) {
     for events in NUMBER_OF_EVENTS.iter().cloned() {
         for subscribers in NUMBER_OF_SUBSCRIBERS.iter().cloned() {
-            let runtime =
-                tokio::runtime::Builder::new_multi_thread().worker_threads(1).fork().unwrap();
-            let sut = Arc::new(Ess::new_with_config(
-                ess::Config::default()
-                    .set_client_buffer_size(events)
-                    .set_publish_buffer_size(events)
-                    .clone(),
-            ));
-
-            let (sender, _) = broadcast::channel(subscribers);
-            for i in 0..subscribers {
-                let client_id = ClientId(format!("client{}", i));
-                let (_, mut receiver_stream) = sut.read_events(client_id.clone());
-                {
-                    let sender = sender.clone();
-                    runtime.handle().spawn(async move {
-                        let mut count: usize = 0;
-                        while (receiver_stream.next().await).is_some() {
-                            count += 1;
-                            if count == events {
-                                count = 0;
-                                sender.send(()).unwrap();
-                            }
-                        }
-                    });
-                }
-                for sub in sut.register_subscriptions(client_id, [EVENT_ID]).unwrap() {
-                    runtime.handle().spawn(
-                        sub.serve(move |Event(id, _, data), seq| Event(id, SeqNum(seq), data)),
-                    );
-                }
-            }
-
+            let config = ess::Config::default()
+                .set_client_buffer_size(events)
+                .set_publish_buffer_size(events)
+                .clone();
             let function_setup = FunctionSetup { events, subscribers };
-            c.bench_with_input(
-                BenchmarkId::new("ess", function_setup),
-                &function_setup,
-                |b, &function_setup| {
-                    b.to_async(tokio::runtime::Runtime::new().unwrap()).iter(|| async {
-                        bench_function(function_setup, sut.clone(), sender.subscribe()).await
-                    });
-                },
-            );
+            bench_with_config(c, BenchmarkId::new("ess", function_setup), function_setup, config);
+        }
+    }
+}
+
+/// Compares receiving the events of a hot source one at a time with receiving
+/// them in batches, when many subscribers subscribe to the source.
+fn hot_source_bench(c: &mut Criterion) {
+    let function_setup = FunctionSetup { events: HOT_SOURCE_EVENTS, subscribers: 100 };
+    for receive_batch_size in [1, ess::DEFAULT_RECEIVE_BATCH_SIZE] {
+        let config = ess::Config::default()
+            .set_client_buffer_size(HOT_SOURCE_EVENTS)
+            .set_publish_buffer_size(HOT_SOURCE_EVENTS)
+            .set_receive_batch_size(receive_batch_size)
+            .clone();
+        let id =
+            BenchmarkId::new(format!("ess-hot-source/{receive_batch_size}-batch"), function_setup);
+        bench_with_config(c, id, function_setup, config);
+    }
+}
+
+fn bench_with_config(
+    c: &mut Criterion,
+    id: BenchmarkId,
+    function_setup: FunctionSetup,
+    config: ess::Config,
+) {
+    let FunctionSetup { events, subscribers } = function_setup;
+    let runtime = tokio::runtime::Builder::new_multi_thread().worker_threads(1).fork().unwrap();
+    let sut = Arc::new(Ess::new_with_config(config));
+
+    let (sender, _) = broadcast::channel(subscribers);
+    for i in 0..subscribers {
+        let client_id = ClientId(format!("client{}", i));
+        let (_, mut receiver_stream) = sut.read_events(client_id.clone()).unwrap();
+        {
+            let sender = sender.clone();
+            runtime.handle().spawn(async move {
+                let mut count: usize = 0;
+                while (receiver_stream.next().await).is_some() {
+                    count += 1;
+                    if count == events {
+                        count = 0;
+                        sender.send(()).unwrap();
+                    }
+                }
+            });
+        }
+        for sub in sut.register_subscriptions(client_id, [EVENT_ID]).unwrap() {
+            runtime
+                .handle()
+                .spawn(sub.serve(move |Event(id, _, data), seq| Event(id, SeqNum(seq), data)));
         }
     }
+
+    c.bench_with_input(id, &function_setup, |b, &function_setup| {
+        b.to_async(tokio::runtime::Runtime::new().unwrap()).iter(|| async {
+            bench_function(function_setup, sut.clone(), sender.subscribe()).await
+        });
+    });
 }
 
 async fn bench_function(setup: FunctionSetup, sut: Arc<Ess>, mut receiver: Receiver<()>) {
@@ -106,5 +127,5 @@ impl std::fmt::Display for FunctionSetup {
     }
 }
 
-criterion_group!(benches, event_sub_system_bench);
+criterion_group!(benches, event_sub_system_bench, hot_source_bench);
 criterion_main!(benches);
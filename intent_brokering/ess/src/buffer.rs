@@ -0,0 +1,260 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use tokio::sync::mpsc::error::TrySendError;
+use tokio_stream::Stream;
+
+/// Represents the priority class of the events of a source, see
+/// [`crate::Config::set_source_priority`].
+///
+/// When the buffer of a client is full, an event evicts the most recent event
+/// of a lower priority from the buffer, and is dropped if there is none. The
+/// events in the buffer are delivered by priority, and in the order in which
+/// they were buffered within a priority. Evicted events are counted as dropped
+/// for the client, but not for their event type, and are not reported by gap
+/// markers.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    const ALL: [Priority; 3] = [Priority::Low, Priority::Normal, Priority::High];
+}
+
+/// A bounded queue of events which are dequeued by priority.
+pub(crate) struct PriorityQueue<T> {
+    queues: [VecDeque<T>; 3],
+    capacity: usize,
+}
+
+impl<T> PriorityQueue<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self { queues: Default::default(), capacity }
+    }
+
+    pub fn len(&self) -> usize {
+        self.queues.iter().map(VecDeque::len).sum()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Enqueues an event, returning the event it evicted if the queue is
+    /// full, or the event itself if it cannot evict any event.
+    pub fn push(&mut self, event: T, priority: Priority) -> Result<Option<T>, T> {
+        let evicted = match self.len() < self.capacity {
+            true => None,
+            false => match Priority::ALL
+                .into_iter()
+                .take_while(|lower| *lower < priority)
+                .find_map(|lower| self.queues[lower as usize].pop_back())
+            {
+                Some(evicted) => Some(evicted),
+                None => return Err(event),
+            },
+        };
+
+        self.queues[priority as usize].push_back(event);
+        Ok(evicted)
+    }
+
+    /// Dequeues the oldest event of the highest priority.
+    pub fn pop(&mut self) -> Option<T> {
+        self.queues.iter_mut().rev().find_map(VecDeque::pop_front)
+    }
+}
+
+struct State<T> {
+    queue: PriorityQueue<T>,
+    senders: usize,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+/// Creates the buffer of a client, holding up to `capacity` events.
+pub(crate) fn channel<T>(capacity: usize) -> (Sender<T>, EventStream<T>) {
+    let state = Arc::new(Mutex::new(State {
+        queue: PriorityQueue::new(capacity),
+        senders: 1,
+        closed: false,
+        waker: None,
+    }));
+
+    (Sender(Arc::clone(&state)), EventStream(state))
+}
+
+/// Buffers the events of a client. The stream of the client ends once all
+/// senders are dropped.
+pub(crate) struct Sender<T>(Arc<Mutex<State<T>>>);
+
+impl<T> Sender<T> {
+    /// Buffers an event, returning the event it evicted, if any, see
+    /// [`Priority`].
+    pub fn try_send(&self, event: T, priority: Priority) -> Result<Option<T>, TrySendError<T>> {
+        let mut state = self.0.lock().unwrap();
+        if state.closed {
+            return Err(TrySendError::Closed(event));
+        }
+
+        let evicted = state.queue.push(event, priority).map_err(TrySendError::Full)?;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+
+        Ok(evicted)
+    }
+
+    /// Returns whether the client abandoned its stream.
+    pub fn is_closed(&self) -> bool {
+        self.0.lock().unwrap().closed
+    }
+
+    pub fn capacity(&self) -> usize {
+        let state = self.0.lock().unwrap();
+        state.queue.capacity() - state.queue.len()
+    }
+
+    pub fn max_capacity(&self) -> usize {
+        self.0.lock().unwrap().queue.capacity()
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.0.lock().unwrap().senders += 1;
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.0.lock().unwrap();
+        state.senders -= 1;
+        if state.senders == 0 {
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// The stream of the events delivered to a client, by priority. Dropping
+/// the stream abandons it.
+pub struct EventStream<T>(Arc<Mutex<State<T>>>);
+
+impl<T> Stream for EventStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut state = self.0.lock().unwrap();
+        match state.queue.pop() {
+            Some(event) => Poll::Ready(Some(event)),
+            None if state.senders == 0 => Poll::Ready(None),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.lock().unwrap().queue.len(), None)
+    }
+}
+
+impl<T> Drop for EventStream<T> {
+    fn drop(&mut self) {
+        let mut state = self.0.lock().unwrap();
+        state.closed = true;
+        // Events buffered for an abandoned stream are never delivered.
+        state.queue = PriorityQueue::new(state.queue.capacity());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::error::TrySendError;
+    use tokio_stream::StreamExt as _;
+
+    use super::{channel, Priority, PriorityQueue};
+
+    #[test]
+    fn pop_returns_events_by_priority_and_order() {
+        // arrange
+        let mut subject = PriorityQueue::new(4);
+        subject.push("low", Priority::Low).unwrap();
+        subject.push("normal1", Priority::Normal).unwrap();
+        subject.push("high", Priority::High).unwrap();
+        subject.push("normal2", Priority::Normal).unwrap();
+
+        // act
+        let result: Vec<_> = std::iter::from_fn(|| subject.pop()).collect();
+
+        // assert
+        assert_eq!(vec!["high", "normal1", "normal2", "low"], result);
+    }
+
+    #[test]
+    fn push_evicts_most_recent_event_of_lowest_priority_if_full() {
+        // arrange
+        let mut subject = PriorityQueue::new(3);
+        subject.push("low1", Priority::Low).unwrap();
+        subject.push("low2", Priority::Low).unwrap();
+        subject.push("normal", Priority::Normal).unwrap();
+
+        // act
+        let high = subject.push("high", Priority::High);
+        let normal = subject.push("normal2", Priority::Normal);
+
+        // assert
+        assert_eq!(Ok(Some("low2")), high);
+        assert_eq!(Ok(Some("low1")), normal);
+        assert_eq!(Err("low3"), subject.push("low3", Priority::Low));
+        assert_eq!(Err("normal3"), subject.push("normal3", Priority::Normal));
+        assert_eq!(3, subject.len());
+    }
+
+    #[tokio::test]
+    async fn stream_delivers_events_by_priority_until_senders_are_dropped() {
+        // arrange
+        let (sender, stream) = channel(2);
+        let other = sender.clone();
+        sender.try_send("normal", Priority::Normal).unwrap();
+        other.try_send("high1", Priority::High).unwrap();
+
+        // act
+        let evicted = sender.try_send("high2", Priority::High);
+        let capacity = (sender.capacity(), sender.max_capacity());
+        drop((sender, other));
+        let result: Vec<_> = stream.collect().await;
+
+        // assert
+        assert_eq!(Ok(Some("normal")), evicted);
+        assert_eq!((0, 2), capacity);
+        assert_eq!(vec!["high1", "high2"], result);
+    }
+
+    #[test]
+    fn try_send_fails_once_stream_is_dropped() {
+        // arrange
+        let (sender, stream) = channel(2);
+
+        // act
+        drop(stream);
+
+        // assert
+        assert!(sender.is_closed());
+        assert!(matches!(sender.try_send("event", Priority::High), Err(TrySendError::Closed(_))));
+    }
+}
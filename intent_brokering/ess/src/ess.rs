@@ -6,16 +6,20 @@ use std::borrow::Borrow;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::hash::Hash;
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 #[cfg(test)]
-use tests::{mpsc, ReceiverStream};
-#[cfg(not(test))]
-use tokio::sync::mpsc;
-#[cfg(not(test))]
-use tokio_stream::wrappers::ReceiverStream;
+use tests::{buffer, EventStream};
 use tokio_util::sync::CancellationToken;
 
-use tokio::sync::broadcast;
+use crate::buffer::Priority;
+#[cfg(not(test))]
+use crate::buffer::{self, EventStream};
+
+use tokio::sync::{
+    broadcast,
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+};
 
 /// Represents the result of an upsert opertion, indicating whether the result
 /// ended up inserting a new entry or updating an existing entry.
@@ -33,10 +37,110 @@ pub enum UpsertResult {
 #[derive(Debug, Eq, PartialEq)]
 pub struct NotReadingEvents;
 
+/// Represents the (error) status that the buffer of a client cannot be
+/// allocated without exceeding the client buffer budget, see
+/// [`Config::set_client_buffer_budget`].
+#[derive(Debug, Eq, PartialEq)]
+pub struct BufferBudgetExceeded;
+
 // Represents a single client with one ore more subscriptions.
 struct Client<EventId, ClientEvent> {
-    sender: mpsc::Sender<ClientEvent>,
+    sender: buffer::Sender<ClientEvent>,
     subscriptions: HashMap<EventId, CancellationToken>,
+    counters: Arc<Counters>,
+}
+
+// Represents an event type with at least one subscription.
+struct Source<Event> {
+    sender: broadcast::Sender<Event>,
+    published: AtomicU64,
+    counters: Arc<Counters>,
+}
+
+// Counts the events delivered to or dropped for the subscriptions of a client
+// or an event type.
+#[derive(Default)]
+struct Counters {
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl Counters {
+    fn count_delivered(&self) {
+        self.delivered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn count_dropped(&self, amount: u64) {
+        self.dropped.fetch_add(amount, Ordering::Relaxed);
+    }
+}
+
+/// Represents a change of whether an event type has subscriptions, which lets
+/// a publisher only produce the events of event types with subscriptions.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SourceChange<EventId> {
+    /// The first subscription to the event type was registered.
+    Subscribed(EventId),
+    /// The last subscription to the event type was removed.
+    Unsubscribed(EventId),
+}
+
+// Notifies the watchers of changes of the event types with subscriptions,
+// forgetting watchers which dropped their receiver.
+struct SourceWatchers<EventId>(Mutex<Vec<UnboundedSender<SourceChange<EventId>>>>);
+
+impl<EventId> Default for SourceWatchers<EventId> {
+    fn default() -> Self {
+        Self(Mutex::new(Vec::new()))
+    }
+}
+
+impl<EventId: Clone> SourceWatchers<EventId> {
+    fn notify(&self, change: SourceChange<EventId>) {
+        self.0.lock().unwrap().retain(|watcher| watcher.send(change.clone()).is_ok());
+    }
+}
+
+/// Represents the statistics of a client reading events.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClientStatistics<ClientId, EventId> {
+    pub client_id: ClientId,
+    /// The identifiers of the events the client has subscriptions to.
+    pub subscriptions: Vec<EventId>,
+    /// The number of events delivered to the buffer of the client.
+    pub delivered: u64,
+    /// The number of events dropped because the buffer of the client was
+    /// full, because a subscription lagged behind the publisher, or because
+    /// they were evicted by events of a higher [`Priority`].
+    pub dropped: u64,
+    /// The number of events in the buffer, which the client did not read yet.
+    pub buffered: usize,
+    /// The size of the buffer.
+    pub buffer_size: usize,
+}
+
+/// Represents the statistics of an event type. Statistics are only kept
+/// while the event type has subscriptions.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EventStatistics<EventId> {
+    pub event_id: EventId,
+    /// The number of subscriptions to the event type.
+    pub subscriptions: usize,
+    /// The number of events published.
+    pub published: u64,
+    /// The number of events delivered to the buffers of clients.
+    pub delivered: u64,
+    /// The number of events dropped for subscriptions, see
+    /// [`ClientStatistics::dropped`].
+    pub dropped: u64,
+}
+
+/// Represents the statistics of the event sub-system, which help finding
+/// clients that cannot keep up with the published events.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Statistics<ClientId, EventId> {
+    pub clients: Vec<ClientStatistics<ClientId, EventId>>,
+    pub events: Vec<EventStatistics<EventId>>,
 }
 
 /// Default size of the buffer for publishing events to all subscriptions.
@@ -45,12 +149,18 @@ pub const DEFAULT_PUBLISH_BUFFER_SIZE: usize = 10;
 /// Default size of the buffer for delivering events to a client.
 pub const DEFAULT_CLIENT_BUFFER_SIZE: usize = 200;
 
+/// Default maximum number of events a subscription receives at once.
+pub const DEFAULT_RECEIVE_BATCH_SIZE: usize = 32;
+
 /// Represents the configuration for the event sub-system, such as the sizes
 /// of the pub-sub channels.
 #[derive(Clone, Debug)]
 pub struct Config {
     publish_buffer_size: usize,
     client_buffer_size: usize,
+    client_buffer_budget: Option<usize>,
+    receive_batch_size: usize,
+    source_priorities: Vec<(Box<str>, Priority)>,
 }
 
 impl Default for Config {
@@ -58,6 +168,9 @@ impl Default for Config {
         Self {
             publish_buffer_size: DEFAULT_PUBLISH_BUFFER_SIZE,
             client_buffer_size: DEFAULT_CLIENT_BUFFER_SIZE,
+            client_buffer_budget: None,
+            receive_batch_size: DEFAULT_RECEIVE_BATCH_SIZE,
+            source_priorities: Vec::new(),
         }
     }
 }
@@ -70,11 +183,52 @@ impl Config {
         self
     }
 
-    /// Sets the size of the channel used to deliver events to a client.
+    /// Sets the size of the channel used to deliver events to a client,
+    /// unless a size is requested by the client.
     pub fn set_client_buffer_size(&mut self, value: usize) -> &mut Self {
         self.client_buffer_size = value;
         self
     }
+
+    /// Sets the maximum number of events buffered for all clients together.
+    /// A client can only read events if its buffer fits into the budget
+    /// left by the buffers of the other clients reading events. By default,
+    /// the budget is unlimited.
+    pub fn set_client_buffer_budget(&mut self, value: usize) -> &mut Self {
+        self.client_buffer_budget = Some(value);
+        self
+    }
+
+    /// Sets the maximum number of events a subscription receives at once when
+    /// it is woken by a publish. Larger batches cost fewer wake-ups of the
+    /// subscriptions of hot sources, smaller batches let the subscriptions of
+    /// a source take turns more often. A size of zero is treated as one.
+    pub fn set_receive_batch_size(&mut self, value: usize) -> &mut Self {
+        self.receive_batch_size = std::cmp::max(value, 1);
+        self
+    }
+
+    /// Sets the priority of the events of the event types whose identifier
+    /// starts with the given prefix, e.g. to deliver safety-relevant events
+    /// before telemetry when a client lags behind. The priority of the
+    /// longest matching prefix applies. By default, events have the
+    /// [`Priority::Normal`] priority.
+    pub fn set_source_priority(
+        &mut self,
+        prefix: impl Into<Box<str>>,
+        priority: Priority,
+    ) -> &mut Self {
+        self.source_priorities.push((prefix.into(), priority));
+        self
+    }
+
+    fn source_priority(&self, event_id: &str) -> Priority {
+        self.source_priorities
+            .iter()
+            .filter(|(prefix, _)| event_id.starts_with(prefix.as_ref()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(Priority::default(), |(_, priority)| *priority)
+    }
 }
 
 /// Implementation of an eventing/pub-sub system that can be used to publish
@@ -90,8 +244,9 @@ impl Config {
 #[derive(Default)]
 pub struct EventSubSystem<ClientId, EventId, Event, ClientEvent> {
     config: Config,
-    sender_by_event_id: Arc<RwLock<HashMap<EventId, broadcast::Sender<Event>>>>,
+    source_by_event_id: Arc<RwLock<HashMap<EventId, Source<Event>>>>,
     client_by_id: Arc<RwLock<HashMap<ClientId, Client<EventId, ClientEvent>>>>,
+    source_watchers: Arc<SourceWatchers<EventId>>,
 }
 
 impl<ClientId, EventId, Event, ClientEvent> EventSubSystem<ClientId, EventId, Event, ClientEvent>
@@ -104,14 +259,20 @@ where
     pub fn new() -> Self {
         Self {
             config: Default::default(),
-            sender_by_event_id: Default::default(),
+            source_by_event_id: Default::default(),
             client_by_id: Default::default(),
+            source_watchers: Default::default(),
         }
     }
 
     /// Initializes the event sub-system with no subscriptions.
     pub fn new_with_config(config: Config) -> Self {
-        Self { config, sender_by_event_id: Default::default(), client_by_id: Default::default() }
+        Self {
+            config,
+            source_by_event_id: Default::default(),
+            client_by_id: Default::default(),
+            source_watchers: Default::default(),
+        }
     }
 
     /// Publishes an event instance for an event type. Returns a Boolean
@@ -122,9 +283,10 @@ where
         EventId: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
-        if let Some(sender) = self.sender_by_event_id.read().unwrap().get(event_id) {
+        if let Some(source) = self.source_by_event_id.read().unwrap().get(event_id) {
+            source.published.fetch_add(1, Ordering::Relaxed);
             // Ignore send errors, which can only occur if there are no receivers.
-            _ = sender.send(event);
+            _ = source.sender.send(event);
             true
         } else {
             false
@@ -140,18 +302,52 @@ where
     /// Note that if the client abandons the stream returned then housekeeping
     /// of associated state is not done until the next attempt to deliver to
     /// the client.
-    pub fn read_events(&self, client_id: ClientId) -> (UpsertResult, ReceiverStream<ClientEvent>) {
-        let (tx, rx) = mpsc::channel::<ClientEvent>(self.config.client_buffer_size);
+    ///
+    /// If the buffer of the client does not fit into the client buffer
+    /// budget, an error of type [`BufferBudgetExceeded`] is returned.
+    pub fn read_events(
+        &self,
+        client_id: ClientId,
+    ) -> Result<(UpsertResult, EventStream<ClientEvent>), BufferBudgetExceeded> {
+        self.read_events_with_buffer_size(client_id, self.config.client_buffer_size)
+    }
+
+    /// Like [`Self::read_events`], but delivers the events to the client
+    /// through a buffer of the given size, which must be greater than zero.
+    pub fn read_events_with_buffer_size(
+        &self,
+        client_id: ClientId,
+        buffer_size: usize,
+    ) -> Result<(UpsertResult, EventStream<ClientEvent>), BufferBudgetExceeded> {
         let mut client_by_id = self.client_by_id.write().unwrap();
+
+        if let Some(budget) = self.config.client_buffer_budget {
+            // Buffers of clients which abandoned their stream, or whose buffer
+            // is replaced, are released.
+            let allocated: usize = client_by_id
+                .iter()
+                .filter(|(id, client)| **id != client_id && !client.sender.is_closed())
+                .map(|(_, client)| client.sender.max_capacity())
+                .sum();
+
+            if allocated.saturating_add(buffer_size) > budget {
+                return Err(BufferBudgetExceeded);
+            }
+        }
+
+        let (tx, rx) = buffer::channel::<ClientEvent>(buffer_size);
         let upsert = if client_by_id
-            .insert(client_id, Client { sender: tx, subscriptions: HashMap::new() })
+            .insert(
+                client_id,
+                Client { sender: tx, subscriptions: HashMap::new(), counters: Default::default() },
+            )
             .is_some()
         {
             UpsertResult::Updated
         } else {
             UpsertResult::Inserted
         };
-        (upsert, ReceiverStream::new(rx))
+        Ok((upsert, rx))
     }
 
     /// Registers one or more subscriptions for a client and returns a
@@ -173,7 +369,10 @@ where
     ) -> Result<
         impl IntoIterator<Item = Subscription<ClientId, EventId, Event, ClientEvent>>,
         NotReadingEvents,
-    > {
+    >
+    where
+        EventId: Display,
+    {
         let mut client_by_id = self.client_by_id.write().unwrap();
 
         let client = client_by_id.get_mut(&client_id).ok_or(NotReadingEvents)?;
@@ -187,27 +386,34 @@ where
                 continue; // already subscribed
             }
 
-            let receiver = {
-                let mut sender_by_event_id = self.sender_by_event_id.write().unwrap();
+            let (receiver, source_counters) = {
+                let mut source_by_event_id = self.source_by_event_id.write().unwrap();
+
+                let source = source_by_event_id.entry(event_id.clone()).or_insert_with(|| {
+                    self.source_watchers.notify(SourceChange::Subscribed(event_id.clone()));
+                    let (sender, _) = broadcast::channel(self.config.publish_buffer_size);
+                    Source { sender, published: AtomicU64::new(0), counters: Default::default() }
+                });
 
-                sender_by_event_id
-                    .entry(event_id.clone())
-                    .or_insert_with(|| {
-                        let (sender, _) = broadcast::channel(self.config.publish_buffer_size);
-                        sender
-                    })
-                    .subscribe()
+                (source.sender.subscribe(), Arc::clone(&source.counters))
             };
 
             let subscription_cancellation_token = CancellationToken::new();
             subscriptions.insert(event_id.clone(), subscription_cancellation_token.clone());
 
+            let priority = self.config.source_priority(&event_id.to_string());
             new_subscriptions.push(Subscription {
                 id: SubscriptionId { client_id: client_id.clone(), event_id },
                 cancellation_token: subscription_cancellation_token,
                 receiver,
                 sender: client.sender.clone(),
+                priority,
                 client_by_id: Arc::clone(&self.client_by_id),
+                client_counters: Arc::clone(&client.counters),
+                source_counters,
+                source_by_event_id: Arc::clone(&self.source_by_event_id),
+                source_watchers: Arc::clone(&self.source_watchers),
+                receive_batch_size: self.config.receive_batch_size,
             });
         }
 
@@ -227,6 +433,75 @@ where
             None => vec![],
         }
     }
+
+    /// Returns a receiver which is notified whenever an event type gains its
+    /// first subscription or loses its last one, e.g. such that a publisher
+    /// only produces the events of event types with subscriptions. Event
+    /// types which have subscriptions at the time of the call are notified as
+    /// subscribed first.
+    pub fn watch_sources(&self) -> UnboundedReceiver<SourceChange<EventId>> {
+        let (sender, receiver) = unbounded_channel();
+
+        // Changes are notified while holding the lock of the sources, hence
+        // no change is missed or notified twice.
+        let sources = self.source_by_event_id.read().unwrap();
+        for event_id in sources.keys() {
+            _ = sender.send(SourceChange::Subscribed(event_id.clone()));
+        }
+
+        self.source_watchers.0.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Returns the identifiers of the events which have subscriptions of any
+    /// client.
+    pub fn get_subscribed_events(&self) -> impl IntoIterator<Item = EventId> {
+        self.source_by_event_id.read().unwrap().keys().cloned().collect::<Vec<_>>()
+    }
+
+    /// Returns whether [`Self::read_events`] has been called for the given
+    /// client, i.e. whether the client is known to the event sub-system.
+    pub fn is_reading_events<Q>(&self, client_id: &Q) -> bool
+    where
+        ClientId: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.client_by_id.read().unwrap().contains_key(client_id)
+    }
+
+    /// Returns a snapshot of the statistics of all clients and event types.
+    pub fn statistics(&self) -> Statistics<ClientId, EventId> {
+        let clients = self
+            .client_by_id
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(client_id, client)| ClientStatistics {
+                client_id: client_id.clone(),
+                subscriptions: client.subscriptions.keys().cloned().collect(),
+                delivered: client.counters.delivered.load(Ordering::Relaxed),
+                dropped: client.counters.dropped.load(Ordering::Relaxed),
+                buffered: client.sender.max_capacity() - client.sender.capacity(),
+                buffer_size: client.sender.max_capacity(),
+            })
+            .collect();
+
+        let events = self
+            .source_by_event_id
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(event_id, source)| EventStatistics {
+                event_id: event_id.clone(),
+                subscriptions: source.sender.receiver_count(),
+                published: source.published.load(Ordering::Relaxed),
+                delivered: source.counters.delivered.load(Ordering::Relaxed),
+                dropped: source.counters.dropped.load(Ordering::Relaxed),
+            })
+            .collect();
+
+        Statistics { clients, events }
+    }
 }
 
 impl<ClientId, EventId, Event, ClientEvent> EventSubSystem<ClientId, EventId, Event, ClientEvent>
@@ -255,9 +530,10 @@ where
         for id in event_ids {
             let succeeded = if let Some(cancellation_token) = subscriptions.remove(&id) {
                 cancellation_token.cancel();
-                let mut senders = self.sender_by_event_id.write().unwrap();
-                if senders.get(&id).map(|s| s.receiver_count()) == Some(0) {
-                    senders.remove(&id);
+                let mut sources = self.source_by_event_id.write().unwrap();
+                if sources.get(&id).map(|s| s.sender.receiver_count()) == Some(0) {
+                    sources.remove(&id);
+                    self.source_watchers.notify(SourceChange::Unsubscribed(id.clone()));
                 }
                 true
             } else {
@@ -267,6 +543,63 @@ where
         }
         Ok(())
     }
+
+    /// Stops reading events for a client, e.g. because it abandoned its
+    /// stream, and deregisters all of its subscriptions. The client is no
+    /// longer known to the event sub-system afterwards.
+    ///
+    /// If [`Self::read_events`] has not been called for the client then an
+    /// error of type [`NotReadingEvents`] is returned.
+    pub fn stop_reading_events<Q>(&self, client_id: &Q) -> Result<(), NotReadingEvents>
+    where
+        ClientId: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.remove_client(client_id).map(|_| ())
+    }
+
+    /// Like [`Self::stop_reading_events`], but delivers a last event to the
+    /// client after the events already buffered for it, e.g. to mark the end
+    /// of its stream. The last event is dropped if the buffer of the client
+    /// is full.
+    pub fn stop_reading_events_with<Q>(
+        &self,
+        client_id: &Q,
+        last_event: ClientEvent,
+    ) -> Result<(), NotReadingEvents>
+    where
+        ClientId: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let client = self.remove_client(client_id)?;
+        // Events of the lowest priority are delivered last.
+        if client.sender.try_send(last_event, Priority::Low).is_err() {
+            tracing::debug!("Dropped last event of a client which stopped reading events.");
+        }
+        Ok(())
+    }
+
+    fn remove_client<Q>(
+        &self,
+        client_id: &Q,
+    ) -> Result<Client<EventId, ClientEvent>, NotReadingEvents>
+    where
+        ClientId: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut client =
+            self.client_by_id.write().unwrap().remove(client_id).ok_or(NotReadingEvents)?;
+        let mut sources = self.source_by_event_id.write().unwrap();
+        for (id, cancellation_token) in client.subscriptions.drain() {
+            cancellation_token.cancel();
+            if sources.get(&id).map(|s| s.sender.receiver_count()) == Some(0) {
+                sources.remove(&id);
+                self.source_watchers.notify(SourceChange::Unsubscribed(id.clone()));
+            }
+            tracing::debug!("Deregistration for \"{}\": true", id);
+        }
+        Ok(client)
+    }
 }
 
 /// Represents an identifier for a single and unique event subscription.
@@ -302,8 +635,14 @@ pub struct Subscription<ClientId, EventId, Event, ClientEvent> {
     id: SubscriptionId<ClientId, EventId>,
     cancellation_token: CancellationToken,
     receiver: broadcast::Receiver<Event>,
-    sender: mpsc::Sender<ClientEvent>,
+    sender: buffer::Sender<ClientEvent>,
+    priority: Priority,
     client_by_id: Arc<RwLock<HashMap<ClientId, self::Client<EventId, ClientEvent>>>>,
+    client_counters: Arc<Counters>,
+    source_counters: Arc<Counters>,
+    source_by_event_id: Arc<RwLock<HashMap<EventId, Source<Event>>>>,
+    source_watchers: Arc<SourceWatchers<EventId>>,
+    receive_batch_size: usize,
 }
 
 impl<ClientId, EventId, Event, ClientEvent> Subscription<ClientId, EventId, Event, ClientEvent> {
@@ -317,7 +656,7 @@ impl<ClientId, EventId, Event, ClientEvent> Subscription<ClientId, EventId, Even
 where
     Event: Clone,
     ClientId: Display + Eq + Hash,
-    EventId: Display + Eq + Hash,
+    EventId: Clone + Display + Eq + Hash,
 {
     /// Returns a future that, when spawned, serves the subscription. The
     /// future remains pending until the subscription terminates due to either
@@ -330,11 +669,47 @@ where
     pub fn serve(
         self,
         f: impl Fn(Event, u64) -> ClientEvent,
+    ) -> impl std::future::Future<Output = ()> {
+        self.serve_filtered(|_| true, f)
+    }
+
+    /// Like [`Self::serve`], but only delivers events for which `filter`
+    /// returns `true`. Events which are filtered out do not consume a
+    /// sequence number.
+    pub fn serve_filtered(
+        self,
+        filter: impl FnMut(&Event) -> bool,
+        f: impl Fn(Event, u64) -> ClientEvent,
+    ) -> impl std::future::Future<Output = ()> {
+        self.serve_with_gap(filter, f, None::<fn(u64, u64) -> ClientEvent>)
+    }
+
+    /// Like [`Self::serve_filtered`], but when events are dropped, delivers
+    /// the client event returned by `gap` for the first and the last
+    /// sequence number of the dropped events before the next event. This
+    /// lets the client tell lost events from sequence numbers which were
+    /// never assigned.
+    pub fn serve_with_gap_markers(
+        self,
+        filter: impl FnMut(&Event) -> bool,
+        f: impl Fn(Event, u64) -> ClientEvent,
+        gap: impl Fn(u64, u64) -> ClientEvent,
+    ) -> impl std::future::Future<Output = ()> {
+        self.serve_with_gap(filter, f, Some(gap))
+    }
+
+    fn serve_with_gap(
+        self,
+        filter: impl FnMut(&Event) -> bool,
+        f: impl Fn(Event, u64) -> ClientEvent,
+        gap: Option<impl Fn(u64, u64) -> ClientEvent>,
     ) -> impl std::future::Future<Output = ()> {
         use tracing::*;
 
         self.serve_with_handlers(
+            filter,
             f,
+            gap,
             // on_subscription_revoked:
             Some(|id: &SubscriptionId<ClientId, EventId>| {
                 debug!("Subscription \"{id}\" is revoked.");
@@ -367,13 +742,15 @@ impl<ClientId, EventId, Event, ClientEvent> Subscription<ClientId, EventId, Even
 where
     Event: Clone,
     ClientId: Eq + Hash,
-    EventId: Eq + Hash,
+    EventId: Clone + Eq + Hash,
 {
     // DevSkim: ignore DS176209 TODO address too many arguments
     #[allow(clippy::too_many_arguments)]
     async fn serve_with_handlers(
         mut self,
+        mut filter: impl FnMut(&Event) -> bool,
         f: impl Fn(Event, u64) -> ClientEvent,
+        gap: Option<impl Fn(u64, u64) -> ClientEvent>,
         on_subscription_revoked: Option<impl Fn(&SubscriptionId<ClientId, EventId>)>,
         on_client_disconnected: Option<impl Fn(&SubscriptionId<ClientId, EventId>)>,
         on_done: Option<impl Fn(&SubscriptionId<ClientId, EventId>)>,
@@ -381,56 +758,131 @@ where
         on_event_dropped: Option<impl Fn(&SubscriptionId<ClientId, EventId>, ClientEvent)>,
         on_publisher_lagged: Option<impl Fn(&SubscriptionId<ClientId, EventId>, u64)>,
     ) {
+        // Extends the range of sequence numbers of the dropped events, which
+        // have not been reported to the client yet.
+        fn extend(dropped: Option<(u64, u64)>, from_seq: u64, to_seq: u64) -> Option<(u64, u64)> {
+            Some((dropped.map_or(from_seq, |(from_seq, _)| from_seq), to_seq))
+        }
+
         let mut seq = 0_u64;
-        loop {
+        let mut dropped = None;
+        'serve: loop {
             let rx = &mut self.receiver;
-            tokio::select! {
+            let mut event = tokio::select! {
+                biased;
                 _ = self.cancellation_token.cancelled() => {
                     if let Some(ref on_subscription_revoked) = on_subscription_revoked {
                         on_subscription_revoked(&self.id);
                     }
                     break;
                 }
-                event = rx.recv() => {
-                    use tokio::sync::broadcast::error::RecvError;
-                    use tokio::sync::mpsc::error::TrySendError;
-
-                    match event {
-                        Ok(event) => {
-                            seq += 1;
-                            match self.sender.try_send(f(event, seq)) {
-                                Ok(_) => continue,
-                                Err(TrySendError::Full(event)) => {
-                                    if let Some(ref on_event_dropped) = on_event_dropped {
-                                        on_event_dropped(&self.id, event);
+                event = rx.recv() => event,
+            };
+
+            // Once woken, the events which are ready are drained in a batch,
+            // such that a hot source with many subscriptions does not cost a
+            // wake-up of each subscription per event.
+            let mut received = 1;
+            loop {
+                use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+                use tokio::sync::mpsc::error::TrySendError;
+
+                match event {
+                    Ok(event) if !filter(&event) => {}
+                    Ok(event) => {
+                        seq += 1;
+                        let result = match (dropped, &gap) {
+                            (Some((from_seq, to_seq)), Some(gap)) => {
+                                match self.sender.try_send(gap(from_seq, to_seq), self.priority) {
+                                    Ok(evicted) => {
+                                        dropped = None;
+                                        if evicted.is_some() {
+                                            self.client_counters.count_dropped(1);
+                                        }
+                                        self.sender.try_send(f(event, seq), self.priority)
                                     }
-                                    continue;
-                                }
-                                Err(TrySendError::Closed(event)) => {
-                                    if let Some(ref on_client_abandoned) = on_client_abandoned {
-                                        on_client_abandoned(&self.id, event);
+                                    Err(TrySendError::Full(_)) => {
+                                        Err(TrySendError::Full(f(event, seq)))
                                     }
-                                    let mut client_by_id = self.client_by_id.write().unwrap();
-                                    if let Some(client) = client_by_id.get_mut(self.id.client_id()) {
-                                        client.subscriptions.remove(self.id.event_id());
+                                    Err(TrySendError::Closed(_)) => {
+                                        Err(TrySendError::Closed(f(event, seq)))
                                     }
-                                    break;
                                 }
                             }
-                        }
-                        Err(RecvError::Closed) => {
-                            if let Some(ref on_client_abandoned) = on_client_disconnected {
-                                on_client_abandoned(&self.id);
-                            };
-                            break;
-                        }
-                        Err(RecvError::Lagged(amount)) => {
-                            seq = seq.wrapping_add(amount);
-                            if let Some(ref on_publisher_lagged) = on_publisher_lagged {
-                                on_publisher_lagged(&self.id, amount);
+                            _ => self.sender.try_send(f(event, seq), self.priority),
+                        };
+                        match result {
+                            Ok(evicted) => {
+                                self.client_counters.count_delivered();
+                                self.source_counters.count_delivered();
+                                if evicted.is_some() {
+                                    self.client_counters.count_dropped(1);
+                                }
+                            }
+                            Err(TrySendError::Full(event)) => {
+                                dropped = extend(dropped, seq, seq);
+                                self.client_counters.count_dropped(1);
+                                self.source_counters.count_dropped(1);
+                                if let Some(ref on_event_dropped) = on_event_dropped {
+                                    on_event_dropped(&self.id, event);
+                                }
+                            }
+                            Err(TrySendError::Closed(event)) => {
+                                if let Some(ref on_client_abandoned) = on_client_abandoned {
+                                    on_client_abandoned(&self.id, event);
+                                }
+                                let mut client_by_id = self.client_by_id.write().unwrap();
+                                if let Some(client) = client_by_id.get_mut(self.id.client_id()) {
+                                    client.subscriptions.remove(self.id.event_id());
+                                }
+                                break 'serve;
                             }
                         }
                     }
+                    Err(RecvError::Closed) => {
+                        if let Some(ref on_client_abandoned) = on_client_disconnected {
+                            on_client_abandoned(&self.id);
+                        };
+                        break 'serve;
+                    }
+                    Err(RecvError::Lagged(amount)) => {
+                        dropped = extend(dropped, seq.wrapping_add(1), seq.wrapping_add(amount));
+                        seq = seq.wrapping_add(amount);
+                        self.client_counters.count_dropped(amount);
+                        self.source_counters.count_dropped(amount);
+                        if let Some(ref on_publisher_lagged) = on_publisher_lagged {
+                            on_publisher_lagged(&self.id, amount);
+                        }
+                    }
+                }
+
+                // A revoked subscription is stopped by the next iteration of
+                // the outer loop.
+                if received >= self.receive_batch_size || self.cancellation_token.is_cancelled() {
+                    continue 'serve;
+                }
+
+                event = match self.receiver.try_recv() {
+                    Ok(event) => Ok(event),
+                    Err(TryRecvError::Empty) => continue 'serve,
+                    Err(TryRecvError::Closed) => Err(RecvError::Closed),
+                    Err(TryRecvError::Lagged(amount)) => Err(RecvError::Lagged(amount)),
+                };
+                received += 1;
+            }
+        }
+        // The source is removed along with its last subscription, such that
+        // events are no longer published to it. The receiver of this
+        // subscription is still counted, and a source which was replaced in
+        // the meantime is told apart by its counters.
+        {
+            let mut sources = self.source_by_event_id.write().unwrap();
+            if sources.get(self.id.event_id()).map_or(false, |source| {
+                Arc::ptr_eq(&source.counters, &self.source_counters)
+                    && source.sender.receiver_count() == 1
+            }) {
+                if let Some((event_id, _)) = sources.remove_entry(self.id.event_id()) {
+                    self.source_watchers.notify(SourceChange::Unsubscribed(event_id));
                 }
             }
         }
@@ -442,7 +894,10 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::{EventSubSystem, UpsertResult};
+    use crate::{
+        BufferBudgetExceeded, ClientStatistics, Config, EventStatistics, EventSubSystem, Priority,
+        SourceChange, UpsertResult, DEFAULT_CLIENT_BUFFER_SIZE,
+    };
     use intent_brokering_common::tokio_runtime_fork;
     use std::time::Duration;
 
@@ -458,12 +913,14 @@ mod tests {
     #[derive(Debug, Clone, Eq, PartialEq, Hash)]
     enum EventId {
         Foo,
+        Bar,
     }
 
     impl std::fmt::Display for EventId {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             match self {
                 EventId::Foo => f.write_str("Foo"),
+                EventId::Bar => f.write_str("Bar"),
             }
         }
     }
@@ -482,17 +939,11 @@ mod tests {
         }
     }
 
-    pub struct ReceiverStream<T> {
+    pub struct EventStream<T> {
         unused: std::marker::PhantomData<T>,
     }
 
-    impl<T> ReceiverStream<T> {
-        pub(crate) fn new(_receiver: ()) -> Self {
-            Self { unused: std::marker::PhantomData }
-        }
-    }
-
-    impl<T> futures::Stream for ReceiverStream<T> {
+    impl<T> futures::Stream for EventStream<T> {
         type Item = T;
 
         fn poll_next(
@@ -503,27 +954,45 @@ mod tests {
         }
     }
 
-    pub(crate) mod mpsc {
+    pub(crate) mod buffer {
         use std::sync::Arc;
 
+        use crate::buffer::{Priority, PriorityQueue};
+
         pub(crate) struct Sender<T> {
-            events: Arc<std::sync::Mutex<Vec<T>>>,
+            events: Arc<std::sync::Mutex<PriorityQueue<T>>>,
         }
 
         impl<T> Sender<T> {
-            pub fn try_send(&self, t: T) -> Result<(), tokio::sync::mpsc::error::TrySendError<T>> {
-                self.events.lock().unwrap().push(t);
-                Ok(())
+            pub fn try_send(
+                &self,
+                t: T,
+                priority: Priority,
+            ) -> Result<Option<T>, tokio::sync::mpsc::error::TrySendError<T>> {
+                self.events
+                    .lock()
+                    .unwrap()
+                    .push(t, priority)
+                    .map_err(tokio::sync::mpsc::error::TrySendError::Full)
+            }
+
+            pub fn is_closed(&self) -> bool {
+                false
+            }
+
+            pub fn capacity(&self) -> usize {
+                let events = self.events.lock().unwrap();
+                events.capacity() - events.len()
+            }
+
+            pub fn max_capacity(&self) -> usize {
+                self.events.lock().unwrap().capacity()
             }
         }
 
         impl<T> Sender<T> {
             pub fn dequeue_event(&self) -> Result<T, ()> {
-                let mut events = self.events.lock().unwrap();
-                if events.is_empty() {
-                    return Err(());
-                }
-                Ok(events.remove(0))
+                self.events.lock().unwrap().pop().ok_or(())
             }
         }
 
@@ -533,9 +1002,10 @@ mod tests {
             }
         }
 
-        pub(crate) fn channel<T>(_buffer: usize) -> (Sender<T>, ()) {
-            let tx = Sender::<T> { events: Arc::new(std::sync::Mutex::new(vec![])) };
-            (tx, ())
+        pub(crate) fn channel<T>(buffer: usize) -> (Sender<T>, super::EventStream<T>) {
+            let tx =
+                Sender::<T> { events: Arc::new(std::sync::Mutex::new(PriorityQueue::new(buffer))) };
+            (tx, super::EventStream { unused: std::marker::PhantomData })
         }
     }
 
@@ -567,14 +1037,146 @@ mod tests {
         const CLIENT_ID: &ClientId = &ClientId("client");
         let sut = sut();
         // act
-        let (upsert1, stream1) = sut.read_events(CLIENT_ID.clone());
-        let (upsert2, stream2) = sut.read_events(CLIENT_ID.clone());
+        let (upsert1, stream1) = sut.read_events(CLIENT_ID.clone()).unwrap();
+        let (upsert2, stream2) = sut.read_events(CLIENT_ID.clone()).unwrap();
         // assert
         assert_eq!(UpsertResult::Inserted, upsert1);
         assert_eq!(UpsertResult::Updated, upsert2);
         assert!(!std::ptr::eq(&stream1, &stream2));
     }
 
+    #[test]
+    fn read_events_fails_when_buffer_exceeds_budget() {
+        // arrange
+        const CLIENT1: ClientId = ClientId("client1");
+        const CLIENT2: ClientId = ClientId("client2");
+        let sut = Ess::new_with_config(Config::default().set_client_buffer_budget(10).clone());
+        sut.read_events_with_buffer_size(CLIENT1, 6).unwrap();
+        // act
+        let exceeded = sut.read_events_with_buffer_size(CLIENT2, 5);
+        let replaced = sut.read_events_with_buffer_size(CLIENT1, 10);
+        // assert
+        assert_eq!(Some(BufferBudgetExceeded), exceeded.err());
+        assert_eq!(UpsertResult::Updated, replaced.unwrap().0);
+    }
+
+    #[test]
+    fn serve_with_gap_markers_reports_dropped_events_before_next_event() {
+        // arrange
+        const EVENT_ID: EventId = EventId::Foo;
+        const CLIENT_ID: ClientId = ClientId("client");
+        let (sut, runtime_fork) = sut_with_runtime();
+        sut.read_events_with_buffer_size(CLIENT_ID, 2).unwrap();
+        for subscription in sut.register_subscriptions(CLIENT_ID, [EVENT_ID]).unwrap() {
+            runtime_fork.handle().spawn(subscription.serve_with_gap_markers(
+                |_| true,
+                |Event(id, _, data), seq| Event(id, SeqNum(seq), data),
+                |from_seq, to_seq| {
+                    let data = format!("gap {from_seq}-{to_seq}").into_boxed_str();
+                    Event(EVENT_ID, SeqNum(0), Box::leak(data))
+                },
+            ));
+        }
+        for data in ["data1", "data2", "data3", "data4"] {
+            sut.publish(&EVENT_ID, Event(EVENT_ID, SeqNum(0), data));
+        }
+        // DevSkim: ignore DS176209 TODO investigate how to avoid sleeping here
+        std::thread::sleep(Duration::from_secs_f64(0.1));
+        for _ in 0..2 {
+            _ = TestClient::read_event(&sut, &CLIENT_ID).unwrap();
+        }
+        // act
+        sut.publish(&EVENT_ID, Event(EVENT_ID, SeqNum(0), "data5"));
+        // DevSkim: ignore DS176209 TODO investigate how to avoid sleeping here
+        std::thread::sleep(Duration::from_secs_f64(0.1));
+        // assert
+        let Event(_, _, gap) = TestClient::read_event(&sut, &CLIENT_ID).unwrap();
+        assert_eq!("gap 3-4", gap);
+        let Event(_, SeqNum(seq), data) = TestClient::read_event(&sut, &CLIENT_ID).unwrap();
+        assert_eq!(5, seq);
+        assert_eq!("data5", data);
+        assert_eq!(2, sut.statistics().clients[0].dropped);
+        drop(runtime_fork);
+    }
+
+    #[test]
+    fn serve_receives_ready_events_in_batches_and_reports_lag() {
+        // arrange
+        const EVENT_ID: EventId = EventId::Foo;
+        const CLIENT_ID: ClientId = ClientId("client");
+        let (_, runtime_fork) = sut_with_runtime();
+        let sut = Ess::new_with_config(
+            Config::default().set_publish_buffer_size(4).set_receive_batch_size(2).clone(),
+        );
+        sut.read_events(CLIENT_ID).unwrap();
+        let subscriptions = sut.register_subscriptions(CLIENT_ID, [EVENT_ID]).unwrap();
+        for data in ["data1", "data2", "data3", "data4", "data5", "data6"] {
+            sut.publish(&EVENT_ID, Event(EVENT_ID, SeqNum(0), data));
+        }
+        // act
+        for subscription in subscriptions {
+            runtime_fork.handle().spawn(subscription.serve_with_gap_markers(
+                |_| true,
+                |Event(id, _, data), seq| Event(id, SeqNum(seq), data),
+                |from_seq, to_seq| {
+                    let data = format!("gap {from_seq}-{to_seq}").into_boxed_str();
+                    Event(EVENT_ID, SeqNum(0), Box::leak(data))
+                },
+            ));
+        }
+        // DevSkim: ignore DS176209 TODO investigate how to avoid sleeping here
+        std::thread::sleep(Duration::from_secs_f64(0.1));
+        // assert
+        let mut events = vec![];
+        while let Some(Event(_, SeqNum(seq), data)) = TestClient::read_event(&sut, &CLIENT_ID) {
+            events.push((seq, data));
+        }
+        assert_eq!(
+            vec![(0, "gap 1-2"), (3, "data3"), (4, "data4"), (5, "data5"), (6, "data6")],
+            events
+        );
+        assert_eq!(2, sut.statistics().clients[0].dropped);
+        drop(runtime_fork);
+    }
+
+    #[test]
+    fn serve_delivers_events_of_higher_priority_first() {
+        // arrange
+        const CLIENT_ID: ClientId = ClientId("client");
+        let (_, runtime_fork) = sut_with_runtime();
+        let sut = Ess::new_with_config(
+            Config::default()
+                .set_source_priority("Foo", Priority::High)
+                .set_source_priority("Bar", Priority::Low)
+                .clone(),
+        );
+        sut.read_events_with_buffer_size(CLIENT_ID, 2).unwrap();
+        for subscription in
+            sut.register_subscriptions(CLIENT_ID, [EventId::Bar, EventId::Foo]).unwrap()
+        {
+            runtime_fork
+                .handle()
+                .spawn(subscription.serve(|Event(id, _, data), seq| Event(id, SeqNum(seq), data)));
+        }
+        for data in ["data1", "data2"] {
+            sut.publish(&EventId::Bar, Event(EventId::Bar, SeqNum(0), data));
+        }
+        // DevSkim: ignore DS176209 TODO investigate how to avoid sleeping here
+        std::thread::sleep(Duration::from_secs_f64(0.1));
+        // act
+        sut.publish(&EventId::Foo, Event(EventId::Foo, SeqNum(0), "data3"));
+        // DevSkim: ignore DS176209 TODO investigate how to avoid sleeping here
+        std::thread::sleep(Duration::from_secs_f64(0.1));
+        // assert
+        let Event(id, _, data) = TestClient::read_event(&sut, &CLIENT_ID).unwrap();
+        assert_eq!((EventId::Foo, "data3"), (id, data));
+        let Event(id, _, data) = TestClient::read_event(&sut, &CLIENT_ID).unwrap();
+        assert_eq!((EventId::Bar, "data1"), (id, data));
+        assert!(TestClient::read_event(&sut, &CLIENT_ID).is_none());
+        assert_eq!(1, sut.statistics().clients[0].dropped);
+        drop(runtime_fork);
+    }
+
     #[test]
     fn read_events_streams_event_on_update() {
         // arrange
@@ -615,6 +1217,81 @@ mod tests {
         drop(runtime_fork); // not needed but helps to avoid marking "runtime_fork" as unused
     }
 
+    #[test]
+    fn serve_filtered_streams_only_matching_events() {
+        // arrange
+        const EVENT_ID: EventId = EventId::Foo;
+        const CLIENT_ID: ClientId = ClientId("client");
+        let (sut, runtime_fork) = sut_with_runtime();
+        _ = sut.read_events(CLIENT_ID);
+        let subscriptions = sut.register_subscriptions(CLIENT_ID, [EVENT_ID]).unwrap();
+        for subscription in subscriptions {
+            runtime_fork.handle().spawn(subscription.serve_filtered(
+                |Event(_, _, data)| data.starts_with("match"),
+                |Event(id, _, data), seq| Event(id, SeqNum(seq), data),
+            ));
+        }
+        // act
+        for data in ["match1", "other", "match2"] {
+            sut.publish(&EVENT_ID, Event(EVENT_ID, SeqNum(0), data));
+        }
+        // DevSkim: ignore DS176209 TODO investigate how to avoid sleeping here
+        std::thread::sleep(Duration::from_secs_f64(0.1));
+        // assert
+        let Event(_, SeqNum(seq), data) = TestClient::read_event(&sut, &CLIENT_ID).unwrap();
+        assert_eq!((1, "match1"), (seq, data));
+        let Event(_, SeqNum(seq), data) = TestClient::read_event(&sut, &CLIENT_ID).unwrap();
+        assert_eq!((2, "match2"), (seq, data));
+        assert!(TestClient::read_event(&sut, &CLIENT_ID).is_none());
+        drop(runtime_fork);
+    }
+
+    #[test]
+    fn statistics_counts_published_and_delivered_events() {
+        // arrange
+        const EVENT_ID: EventId = EventId::Foo;
+        const CLIENT_ID: ClientId = ClientId("client");
+        let (sut, runtime_fork) = sut_with_runtime();
+        _ = sut.read_events(CLIENT_ID);
+        let subscriptions = sut.register_subscriptions(CLIENT_ID, [EVENT_ID]).unwrap();
+        for subscription in subscriptions {
+            runtime_fork
+                .handle()
+                .spawn(subscription.serve(|Event(id, _, data), seq| Event(id, SeqNum(seq), data)));
+        }
+        for data in ["data1", "data2"] {
+            sut.publish(&EVENT_ID, Event(EVENT_ID, SeqNum(0), data));
+        }
+        // DevSkim: ignore DS176209 TODO investigate how to avoid sleeping here
+        std::thread::sleep(Duration::from_secs_f64(0.1));
+        _ = TestClient::read_event(&sut, &CLIENT_ID);
+        // act
+        let result = sut.statistics();
+        // assert
+        assert_eq!(
+            vec![ClientStatistics {
+                client_id: CLIENT_ID,
+                subscriptions: vec![EVENT_ID],
+                delivered: 2,
+                dropped: 0,
+                buffered: 1,
+                buffer_size: DEFAULT_CLIENT_BUFFER_SIZE,
+            }],
+            result.clients
+        );
+        assert_eq!(
+            vec![EventStatistics {
+                event_id: EVENT_ID,
+                subscriptions: 1,
+                published: 2,
+                delivered: 2,
+                dropped: 0,
+            }],
+            result.events
+        );
+        drop(runtime_fork);
+    }
+
     #[test]
     fn read_events_does_not_stream_events_of_unregistered_subscriptions() {
         // arrange
@@ -655,6 +1332,108 @@ mod tests {
         drop(runtime_fork); // not needed but helps to avoid marking "runtime_fork" as unused
     }
 
+    #[tokio::test]
+    async fn stop_reading_events_terminates_subscription_servers_of_client() {
+        // arrange
+        const CLIENT_ID: &ClientId = &ClientId("client");
+        let (sut, runtime_fork) = sut_with_runtime();
+        _ = sut.read_events(CLIENT_ID.clone());
+        let mut subscriptions =
+            sut.register_subscriptions(CLIENT_ID.clone(), [EventId::Foo]).unwrap().into_iter();
+        let subscription = subscriptions.next().unwrap();
+        let subscription_server = runtime_fork
+            .handle()
+            .spawn(subscription.serve(|Event(id, _, data), seq| Event(id, SeqNum(seq), data)));
+        // act
+        sut.stop_reading_events(CLIENT_ID).unwrap();
+        // assert
+        assert!(!sut.is_reading_events(CLIENT_ID));
+        assert!(sut.stop_reading_events(CLIENT_ID).is_err());
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {
+                panic!("Subscription server should have terminated shortly after the client stopped reading events!")
+            }
+            result = subscription_server => {
+                assert!(result.is_ok());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn subscription_server_removes_source_of_last_subscription() {
+        // arrange
+        const CLIENT_ID: &ClientId = &ClientId("client");
+        let (sut, runtime_fork) = sut_with_runtime();
+        _ = sut.read_events(CLIENT_ID.clone());
+        let subscription = sut
+            .register_subscriptions(CLIENT_ID.clone(), [EventId::Foo])
+            .unwrap()
+            .into_iter()
+            .next()
+            .unwrap();
+        let subscription_server = runtime_fork
+            .handle()
+            .spawn(subscription.serve(|Event(id, _, data), seq| Event(id, SeqNum(seq), data)));
+        sut.deregister_subscriptions(CLIENT_ID, [EventId::Foo]).unwrap();
+        // act
+        subscription_server.await.unwrap();
+        // assert
+        assert_eq!(None, sut.get_subscribed_events().into_iter().next());
+        assert!(!sut.publish(&EventId::Foo, Event(EventId::Foo, SeqNum(0), "data")));
+    }
+
+    #[tokio::test]
+    async fn watch_sources_notifies_first_and_last_subscription() {
+        // arrange
+        const CLIENT1: &ClientId = &ClientId("client1");
+        const CLIENT2: &ClientId = &ClientId("client2");
+        let (sut, runtime_fork) = sut_with_runtime();
+        let serve = |client_id: &ClientId| {
+            _ = sut.read_events(client_id.clone());
+            let subscription = sut
+                .register_subscriptions(client_id.clone(), [EventId::Foo])
+                .unwrap()
+                .into_iter()
+                .next()
+                .unwrap();
+            runtime_fork
+                .handle()
+                .spawn(subscription.serve(|Event(id, _, data), seq| Event(id, SeqNum(seq), data)))
+        };
+        let server1 = serve(CLIENT1);
+        let mut changes = sut.watch_sources();
+        // act
+        let server2 = serve(CLIENT2);
+        sut.deregister_subscriptions(CLIENT1, [EventId::Foo]).unwrap();
+        server1.await.unwrap();
+        sut.deregister_subscriptions(CLIENT2, [EventId::Foo]).unwrap();
+        server2.await.unwrap();
+        // assert
+        let mut result = vec![];
+        while let Ok(change) = changes.try_recv() {
+            result.push(change);
+        }
+        assert_eq!(
+            vec![SourceChange::Subscribed(EventId::Foo), SourceChange::Unsubscribed(EventId::Foo)],
+            result
+        );
+    }
+
+    #[test]
+    fn stop_reading_events_with_delivers_last_event() {
+        // arrange
+        const CLIENT_ID: &ClientId = &ClientId("client");
+        let sut = sut();
+        _ = sut.read_events(CLIENT_ID.clone());
+        let sender = sut.client_by_id.read().unwrap().get(CLIENT_ID).unwrap().sender.clone();
+        // act
+        sut.stop_reading_events_with(CLIENT_ID, Event(EventId::Foo, SeqNum(0), "end")).unwrap();
+        // assert
+        assert!(!sut.is_reading_events(CLIENT_ID));
+        let Event(_, _, data) = sender.dequeue_event().unwrap();
+        assert_eq!("end", data);
+    }
+
     #[test]
     fn register_subscriptions_cannot_be_called_if_events_are_not_being_read() {
         // arrange
@@ -688,4 +1467,14 @@ mod tests {
         // assert
         assert_eq!(None, result.into_iter().next());
     }
+
+    #[test]
+    fn is_reading_events_returns_true_only_for_known_clients() {
+        // arrange
+        let sut = sut();
+        _ = sut.read_events(ClientId("client"));
+        // act + assert
+        assert!(sut.is_reading_events(&ClientId("client")));
+        assert!(!sut.is_reading_events(&ClientId("other")));
+    }
 }
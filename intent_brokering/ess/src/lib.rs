@@ -2,5 +2,7 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
+mod buffer;
 mod ess;
+pub use crate::buffer::{EventStream, Priority};
 pub use crate::ess::*;
@@ -0,0 +1,257 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Parsing of the subset of the DBC format used by the provider: the message
+//! (`BO_`) and signal (`SG_`) definitions. All other definitions, such as
+//! comments and value tables, are ignored.
+
+use intent_brokering_common::error::Error;
+
+/// The flag marking the identifier of a message as extended (29 bit).
+const EXTENDED_ID_FLAG: u32 = 0x8000_0000;
+const MAX_DATA_LENGTH: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// Intel byte order, where the start bit is the least significant bit.
+    LittleEndian,
+    /// Motorola byte order, where the start bit is the most significant bit.
+    BigEndian,
+}
+
+#[derive(Clone, Debug)]
+pub struct Signal {
+    pub name: Box<str>,
+    pub start_bit: usize,
+    pub length: usize,
+    pub byte_order: ByteOrder,
+    pub signed: bool,
+    pub factor: f64,
+    pub offset: f64,
+    pub minimum: f64,
+    pub maximum: f64,
+}
+
+impl Signal {
+    /// Returns the positions of the bits of the signal in the payload, from
+    /// the most to the least significant bit.
+    fn positions(&self) -> Vec<usize> {
+        match self.byte_order {
+            ByteOrder::LittleEndian => {
+                (self.start_bit..self.start_bit + self.length).rev().collect()
+            }
+            ByteOrder::BigEndian => {
+                let mut positions = Vec::with_capacity(self.length);
+                let mut position = self.start_bit;
+                for _ in 0..self.length {
+                    positions.push(position);
+                    // Continue with the most significant bit of the next byte
+                    // once the least significant bit of a byte was reached.
+                    position = if position % 8 == 0 { position + 15 } else { position - 1 };
+                }
+                positions
+            }
+        }
+    }
+
+    /// Decodes the physical value of the signal from the payload of a frame.
+    pub fn decode(&self, data: &[u8]) -> Option<f64> {
+        let mut raw = 0u64;
+        for position in self.positions() {
+            let byte = data.get(position / 8)?;
+            raw = raw << 1 | u64::from(byte >> (position % 8) & 1);
+        }
+
+        let raw = if self.signed {
+            // Sign-extend the raw value to 64 bits.
+            let shift = 64 - self.length;
+            ((raw << shift) as i64 >> shift) as f64
+        } else {
+            raw as f64
+        };
+
+        Some(raw * self.factor + self.offset)
+    }
+
+    /// Encodes the physical value of the signal into the payload of a frame,
+    /// leaving the bits of all other signals untouched.
+    pub fn encode(&self, data: &mut [u8], value: f64) -> Result<(), String> {
+        if self.minimum < self.maximum && !(self.minimum..=self.maximum).contains(&value) {
+            return Err(format!(
+                "Value {value} is out of the range [{}, {}] of signal '{}'.",
+                self.minimum, self.maximum, self.name
+            ));
+        }
+
+        let raw = ((value - self.offset) / self.factor).round();
+        let (min, max) = if self.signed {
+            (-(2f64.powi(self.length as i32 - 1)), 2f64.powi(self.length as i32 - 1) - 1.0)
+        } else {
+            (0.0, 2f64.powi(self.length as i32) - 1.0)
+        };
+
+        if !raw.is_finite() || raw < min || raw > max {
+            return Err(format!("Value {value} cannot be represented by signal '{}'.", self.name));
+        }
+
+        let raw = if self.signed { raw as i64 as u64 } else { raw as u64 };
+        for (index, position) in self.positions().into_iter().rev().enumerate() {
+            let byte = data
+                .get_mut(position / 8)
+                .ok_or_else(|| format!("Frame is too short for signal '{}'.", self.name))?;
+            let mask = 1 << (position % 8);
+            if raw >> index & 1 == 1 {
+                *byte |= mask;
+            } else {
+                *byte &= !mask;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Message {
+    pub id: u32,
+    pub extended: bool,
+    pub name: Box<str>,
+    pub size: usize,
+    pub transmitter: Box<str>,
+    pub signals: Vec<Signal>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Database {
+    pub messages: Vec<Message>,
+}
+
+impl Database {
+    /// Parses the message and signal definitions of a DBC file. Multiplexed
+    /// signals are not supported and are skipped.
+    pub fn parse(dbc: &str) -> Result<Self, Error> {
+        let mut messages: Vec<Message> = vec![];
+
+        for (index, line) in dbc.lines().enumerate() {
+            let line = line.trim();
+            let error = |e: String| Error::new(format!("Failed to parse line {}: {e}", index + 1));
+
+            if let Some(definition) = line.strip_prefix("BO_ ") {
+                messages.push(parse_message(definition).map_err(error)?);
+            } else if let Some(definition) = line.strip_prefix("SG_ ") {
+                let message = messages
+                    .last_mut()
+                    .ok_or_else(|| error("Signal is not part of a message.".to_owned()))?;
+
+                if let Some(signal) = parse_signal(definition).map_err(error)? {
+                    if signal.positions().iter().any(|p| p / 8 >= message.size) {
+                        return Err(error(format!(
+                            "Signal '{}' exceeds the size of message '{}'.",
+                            signal.name, message.name
+                        )));
+                    }
+
+                    message.signals.push(signal);
+                }
+            }
+        }
+
+        Ok(Self { messages })
+    }
+
+    pub fn message(&self, id: u32, extended: bool) -> Option<&Message> {
+        self.messages.iter().find(|m| m.id == id && m.extended == extended)
+    }
+}
+
+/// Parses `<id> <name>: <size> <transmitter>`.
+fn parse_message(definition: &str) -> Result<Message, String> {
+    let (head, tail) =
+        definition.split_once(':').ok_or_else(|| "Message definition lacks ':'.".to_owned())?;
+    let mut head = head.split_whitespace();
+    let mut tail = tail.split_whitespace();
+
+    let id: u32 = head
+        .next()
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| "Message identifier is invalid.".to_owned())?;
+    let name = head.next().ok_or_else(|| "Message name is missing.".to_owned())?;
+    let size: usize = tail
+        .next()
+        .and_then(|size| size.parse().ok())
+        .filter(|size| *size <= MAX_DATA_LENGTH)
+        .ok_or_else(|| format!("Size of message '{name}' is invalid."))?;
+
+    Ok(Message {
+        id: id & !EXTENDED_ID_FLAG,
+        extended: id & EXTENDED_ID_FLAG != 0,
+        name: name.into(),
+        size,
+        transmitter: tail.next().unwrap_or_default().into(),
+        signals: vec![],
+    })
+}
+
+/// Parses `<name> [<multiplexer>] : <start>|<length>@<order><sign>
+/// (<factor>,<offset>) [<minimum>|<maximum>] "<unit>" <receivers>`. Returns
+/// `None` for multiplexed signals.
+fn parse_signal(definition: &str) -> Result<Option<Signal>, String> {
+    let (head, tail) =
+        definition.split_once(':').ok_or_else(|| "Signal definition lacks ':'.".to_owned())?;
+    let mut head = head.split_whitespace();
+    let name = head.next().ok_or_else(|| "Signal name is missing.".to_owned())?;
+
+    if head.next().is_some() {
+        return Ok(None);
+    }
+
+    let invalid = || format!("Definition of signal '{name}' is invalid.");
+    let mut tail = tail.split_whitespace();
+    let mut next = || tail.next().ok_or_else(invalid);
+
+    let (start_bit, rest) = next()?.split_once('|').ok_or_else(invalid)?;
+    let (length, format) = rest.split_once('@').ok_or_else(invalid)?;
+    let (factor, offset) = next()?
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .and_then(|s| s.split_once(','))
+        .ok_or_else(invalid)?;
+    let (minimum, maximum) = next()?
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .and_then(|s| s.split_once('|'))
+        .ok_or_else(invalid)?;
+
+    let (byte_order, signed) = match format {
+        "1+" => (ByteOrder::LittleEndian, false),
+        "1-" => (ByteOrder::LittleEndian, true),
+        "0+" => (ByteOrder::BigEndian, false),
+        "0-" => (ByteOrder::BigEndian, true),
+        _ => return Err(invalid()),
+    };
+
+    let number = |s: &str| s.parse::<f64>().map_err(|_| invalid());
+    let length: usize = length.parse().map_err(|_| invalid())?;
+
+    if length == 0 || length > 64 {
+        return Err(format!("Length of signal '{name}' must be between 1 and 64 bits."));
+    }
+
+    let factor = number(factor)?;
+    if factor == 0.0 {
+        return Err(format!("Factor of signal '{name}' must not be zero."));
+    }
+
+    Ok(Some(Signal {
+        name: name.into(),
+        start_bit: start_bit.parse().map_err(|_| invalid())?,
+        length,
+        byte_order,
+        signed,
+        factor,
+        offset: number(offset)?,
+        minimum: number(minimum)?,
+        maximum: number(maximum)?,
+    }))
+}
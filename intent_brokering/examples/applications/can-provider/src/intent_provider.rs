@@ -0,0 +1,180 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use examples_common::intent_brokering::{self, streaming::ProtoExt as _};
+use tokio::sync::Mutex;
+use tonic::{Request, Response, Status};
+use url::Url;
+
+use intent_brokering_proto::{
+    common::{
+        discover_fulfillment::Service, value::Value, DiscoverFulfillment, FulfillmentEnum,
+        FulfillmentMessage, IntentEnum, SubscribeIntent, WriteFulfillment, WriteIntent,
+    },
+    provider::{provider_service_server::ProviderService, FulfillRequest, FulfillResponse},
+};
+
+use crate::{
+    dbc::{Database, Message, Signal},
+    socketcan::{CanSocket, Frame},
+};
+
+pub type StreamingStore = intent_brokering::streaming::StreamingStore<Value>;
+
+/// Serves the signals of a DBC database, which are identified by the name of
+/// their message and their own name, e.g. `EngineData.EngineSpeed`. Signals
+/// of messages transmitted by the node of the provider can be written.
+pub struct IntentProvider {
+    url: Url,
+    database: Database,
+    node: Box<str>,
+    socket: CanSocket,
+    /// The last payload received or sent per message, such that writing a
+    /// signal retains the values of the other signals of its message.
+    frames: Mutex<HashMap<Box<str>, Vec<u8>>>,
+    streaming_store: Arc<StreamingStore>,
+}
+
+impl IntentProvider {
+    pub fn new(
+        url: Url,
+        database: Database,
+        node: Box<str>,
+        socket: CanSocket,
+        streaming_store: Arc<StreamingStore>,
+    ) -> Self {
+        Self { url, database, node, socket, frames: Default::default(), streaming_store }
+    }
+
+    fn signal(&self, key: &str) -> Result<(&Message, &Signal), Status> {
+        key.split_once('.')
+            .and_then(|(message, signal)| {
+                let message = self.database.messages.iter().find(|m| m.name.as_ref() == message)?;
+                let signal = message.signals.iter().find(|s| s.name.as_ref() == signal)?;
+                Some((message, signal))
+            })
+            .ok_or_else(|| Status::not_found(format!("Signal '{key}' does not exist.")))
+    }
+
+    fn publish(&self, message: &Message, data: &[u8]) {
+        self.streaming_store.set_many(message.signals.iter().filter_map(|signal| {
+            signal.decode(data).map(|value| {
+                (format!("{}.{}", message.name, signal.name).into(), Value::Float64(value))
+            })
+        }));
+    }
+
+    /// Receives frames from the CAN bus and publishes the decoded signals of
+    /// the messages known to the database.
+    pub async fn receive_frames(self: Arc<Self>) {
+        loop {
+            let frame = match self.socket.recv().await {
+                Ok(frame) => frame,
+                Err(e) => {
+                    tracing::warn!("Failed to receive CAN frame: {e}");
+                    continue;
+                }
+            };
+
+            let Some(message) = self.database.message(frame.id, frame.extended) else {
+                continue;
+            };
+
+            if frame.data.len() < message.size {
+                tracing::warn!("Dropping frame of '{}' with too short payload.", message.name);
+                continue;
+            }
+
+            self.frames.lock().await.insert(message.name.clone(), frame.data.clone());
+            self.publish(message, &frame.data);
+        }
+    }
+
+    async fn write(&self, intent: WriteIntent) -> Result<WriteFulfillment, Status> {
+        if intent.precondition.is_some() {
+            return Err(Status::unimplemented("Conditional writes are not supported."));
+        }
+
+        let (message, signal) = self.signal(&intent.key)?;
+        if message.transmitter != self.node {
+            return Err(Status::failed_precondition(format!(
+                "Signal '{}' is not transmitted by node '{}'.",
+                intent.key, self.node
+            )));
+        }
+
+        let value = match intent.value.and_then(|v| v.value) {
+            Some(Value::Bool(value)) => f64::from(u8::from(value)),
+            Some(Value::Int32(value)) => f64::from(value),
+            Some(Value::Int64(value)) => value as f64,
+            Some(Value::Float32(value)) => f64::from(value),
+            Some(Value::Float64(value)) => value,
+            Some(_) => return Err(Status::invalid_argument("Value must be a number or bool.")),
+            None => return Err(Status::invalid_argument("Value must be specified.")),
+        };
+
+        let mut frames = self.frames.lock().await;
+        let mut data = frames.get(&message.name).cloned().unwrap_or_else(|| vec![0; message.size]);
+        signal.encode(&mut data, value).map_err(Status::invalid_argument)?;
+
+        let frame = Frame { id: message.id, extended: message.extended, data };
+        self.socket
+            .send(&frame)
+            .await
+            .map_err(|e| Status::unavailable(format!("Failed to send CAN frame: {e}")))?;
+
+        // Frames sent through the socket are not received by it, hence the
+        // signals are published directly.
+        self.publish(message, &frame.data);
+        frames.insert(message.name.clone(), frame.data);
+        Ok(WriteFulfillment::default())
+    }
+
+    fn subscribe(&self, intent: SubscribeIntent) -> Result<FulfillmentEnum, Status> {
+        for source in intent.sources.iter() {
+            self.signal(source)?;
+        }
+
+        self.streaming_store.subscribe(intent)
+    }
+}
+
+#[async_trait]
+impl ProviderService for IntentProvider {
+    async fn fulfill(
+        &self,
+        request: Request<FulfillRequest>,
+    ) -> Result<Response<FulfillResponse>, Status> {
+        let fulfillment = match request
+            .into_inner()
+            .intent
+            .and_then(|i| i.intent)
+            .ok_or_else(|| Status::invalid_argument("Intent must be specified."))?
+        {
+            IntentEnum::Read(intent) => {
+                self.signal(&intent.key).map(|_| self.streaming_store.read(intent))
+            }
+            IntentEnum::Write(intent) => self.write(intent).await.map(FulfillmentEnum::Write),
+            IntentEnum::Subscribe(intent) => self.subscribe(intent),
+            IntentEnum::Discover(_intent) => Ok(FulfillmentEnum::Discover(DiscoverFulfillment {
+                services: vec![Service {
+                    url: self.url.to_string(),
+                    schema_kind: "grpc+proto".to_owned(),
+                    schema_reference: "intent_brokering.streaming.v1".to_owned(),
+                    metadata: HashMap::new(),
+                }],
+            })),
+            _ => Err(Status::unknown("Unsupported or unknown intent."))?,
+        };
+
+        fulfillment.map(|f| {
+            Response::new(FulfillResponse {
+                fulfillment: Some(FulfillmentMessage { fulfillment: Some(f) }),
+            })
+        })
+    }
+}
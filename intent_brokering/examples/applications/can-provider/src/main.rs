@@ -0,0 +1,84 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+mod dbc;
+mod intent_provider;
+mod socketcan;
+
+use std::{fs, sync::Arc};
+
+use examples_common::intent_brokering::{self, registration::Builder};
+use intent_brokering_common::config::env;
+use intent_brokering_common::error::{Error, ResultExt as _};
+use intent_brokering_common::shutdown::RouterExt as _;
+use intent_brokering_proto::{
+    provider::provider_service_server::ProviderServiceServer,
+    runtime::{intent_registration::Intent, intent_service_registration::ExecutionLocality},
+    streaming::channel_service_server::ChannelServiceServer,
+};
+use tonic::transport::Server;
+use url::Url;
+
+use crate::dbc::Database;
+use crate::intent_provider::{IntentProvider, StreamingStore};
+use crate::socketcan::CanSocket;
+
+intent_brokering::provider::main!(wain);
+
+/// An example database, used unless `CAN_DBC_PATH` points to a different
+/// database.
+const DEFAULT_DBC: &str = include_str!("../example.dbc");
+
+async fn wain() -> Result<(), Error> {
+    let url: Url = env("CAN_PROVIDER_URL")
+        .unwrap_or_else(|| "http://0.0.0.0:50068".to_owned()) // DevSkim: ignore DS137138
+        .parse()
+        .map_err_with("Failed to parse URL.")?;
+
+    let database = match env::<String>("CAN_DBC_PATH") {
+        Some(path) => {
+            fs::read_to_string(&path).map_err_with(format!("Failed to read '{path}'."))?
+        }
+        None => DEFAULT_DBC.to_owned(),
+    };
+    let database = Database::parse(&database)?;
+
+    let interface: String = env("CAN_INTERFACE").unwrap_or_else(|| "vcan0".to_owned());
+    let namespace: String = env("CAN_NAMESPACE").unwrap_or_else(|| "sdv.can".to_owned());
+    let node: Box<str> = env::<String>("CAN_NODE").unwrap_or_else(|| "CHARIOTT".to_owned()).into();
+
+    let mut intents = vec![Intent::Read, Intent::Subscribe, Intent::Discover];
+    if database.messages.iter().any(|m| m.transmitter == node && !m.signals.is_empty()) {
+        intents.push(Intent::Write);
+    }
+
+    let socket = CanSocket::open(&interface)?;
+
+    let registration = Builder::new(
+        "sdv.can-provider",
+        "0.0.1",
+        url,
+        &namespace,
+        intents,
+        ExecutionLocality::Local,
+    )
+    .from_env();
+
+    let socket_address = registration.parse_provider_socket_address()?;
+    let url = registration.announce_url().to_owned();
+    tokio::task::spawn(registration.register());
+
+    tracing::info!("Application listening on: {url}, receiving from '{interface}'");
+
+    let streaming_store = Arc::new(StreamingStore::new());
+    let provider =
+        Arc::new(IntentProvider::new(url, database, node, socket, Arc::clone(&streaming_store)));
+    tokio::task::spawn(Arc::clone(&provider).receive_frames());
+
+    Server::builder()
+        .add_service(ProviderServiceServer::from_arc(provider))
+        .add_service(ChannelServiceServer::new(streaming_store.ess().clone()))
+        .serve_with_ctrl_c_shutdown(socket_address)
+        .await
+}
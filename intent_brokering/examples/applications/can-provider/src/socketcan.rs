@@ -0,0 +1,166 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! A minimal asynchronous binding to raw SocketCAN sockets, supporting
+//! classic CAN frames with up to eight bytes of data.
+
+use std::{
+    ffi::CString,
+    io, mem,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+};
+
+use intent_brokering_common::error::{Error, ResultExt as _};
+use tokio::io::unix::AsyncFd;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub id: u32,
+    pub extended: bool,
+    pub data: Vec<u8>,
+}
+
+impl Frame {
+    fn to_raw(&self) -> libc::can_frame {
+        // SAFETY: `can_frame` is plain data, for which all zeroes is valid.
+        let mut frame: libc::can_frame = unsafe { mem::zeroed() };
+        frame.can_id = if self.extended {
+            self.id & libc::CAN_EFF_MASK | libc::CAN_EFF_FLAG
+        } else {
+            self.id & libc::CAN_SFF_MASK
+        };
+        frame.can_dlc = self.data.len() as u8;
+        frame.data[..self.data.len()].copy_from_slice(&self.data);
+        frame
+    }
+
+    /// Converts a received frame, unless it is a remote or error frame.
+    fn from_raw(frame: &libc::can_frame) -> Option<Self> {
+        if frame.can_id & (libc::CAN_RTR_FLAG | libc::CAN_ERR_FLAG) != 0 {
+            return None;
+        }
+
+        let extended = frame.can_id & libc::CAN_EFF_FLAG != 0;
+        Some(Self {
+            id: frame.can_id & if extended { libc::CAN_EFF_MASK } else { libc::CAN_SFF_MASK },
+            extended,
+            data: frame.data[..usize::from(frame.can_dlc).min(libc::CAN_MAX_DLEN)].to_vec(),
+        })
+    }
+}
+
+pub struct CanSocket(AsyncFd<OwnedFd>);
+
+impl CanSocket {
+    /// Opens a raw CAN socket bound to the given interface, e.g. `can0`.
+    pub fn open(interface: &str) -> Result<Self, Error> {
+        let name = CString::new(interface).map_err_with("Interface name must not contain NUL.")?;
+
+        // SAFETY: `name` is a valid NUL-terminated string.
+        let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+        if index == 0 {
+            return Err(io::Error::last_os_error())
+                .map_err_with(format!("CAN interface '{interface}' does not exist."));
+        }
+
+        // SAFETY: The arguments are valid constants, and the returned file
+        // descriptor is checked before it is taken ownership of.
+        let fd = unsafe {
+            libc::socket(
+                libc::PF_CAN,
+                libc::SOCK_RAW | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+                libc::CAN_RAW,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error()).map_err_with("Failed to create CAN socket.");
+        }
+        // SAFETY: `fd` is a freshly created file descriptor, owned by no one else.
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        // SAFETY: `sockaddr_can` is plain data, for which all zeroes is valid.
+        let mut address: libc::sockaddr_can = unsafe { mem::zeroed() };
+        address.can_family = libc::AF_CAN as libc::sa_family_t;
+        address.can_ifindex = index as libc::c_int;
+
+        // SAFETY: `address` is a valid `sockaddr_can` of the given length.
+        let result = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &address as *const libc::sockaddr_can as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_can>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error())
+                .map_err_with(format!("Failed to bind CAN socket to '{interface}'."));
+        }
+
+        Ok(Self(AsyncFd::new(fd).map_err_with("Failed to register CAN socket.")?))
+    }
+
+    /// Receives the next data frame, skipping remote and error frames.
+    pub async fn recv(&self) -> io::Result<Frame> {
+        loop {
+            let mut guard = self.0.readable().await?;
+
+            let result = guard.try_io(|fd| {
+                // SAFETY: `can_frame` is plain data, for which all zeroes is valid.
+                let mut frame: libc::can_frame = unsafe { mem::zeroed() };
+                // SAFETY: `frame` is a writable buffer of the given length.
+                let length = unsafe {
+                    libc::read(
+                        fd.as_raw_fd(),
+                        &mut frame as *mut libc::can_frame as *mut libc::c_void,
+                        mem::size_of::<libc::can_frame>(),
+                    )
+                };
+
+                match length {
+                    length if length < 0 => Err(io::Error::last_os_error()),
+                    length if length as usize == mem::size_of::<libc::can_frame>() => {
+                        Ok(Some(frame))
+                    }
+                    // Incomplete frames, e.g. of CAN FD, are dropped.
+                    _ => Ok(None),
+                }
+            });
+
+            if let Ok(frame) = result {
+                if let Some(frame) = frame?.as_ref().and_then(Frame::from_raw) {
+                    return Ok(frame);
+                }
+            }
+        }
+    }
+
+    pub async fn send(&self, frame: &Frame) -> io::Result<()> {
+        let frame = frame.to_raw();
+
+        loop {
+            let mut guard = self.0.writable().await?;
+
+            let result = guard.try_io(|fd| {
+                // SAFETY: `frame` is a readable buffer of the given length.
+                let length = unsafe {
+                    libc::write(
+                        fd.as_raw_fd(),
+                        &frame as *const libc::can_frame as *const libc::c_void,
+                        mem::size_of::<libc::can_frame>(),
+                    )
+                };
+
+                match length {
+                    length if length < 0 => Err(io::Error::last_os_error()),
+                    length if length as usize == mem::size_of::<libc::can_frame>() => Ok(()),
+                    _ => Err(io::Error::new(io::ErrorKind::WriteZero, "Frame was truncated.")),
+                }
+            });
+
+            if let Ok(result) = result {
+                return result;
+            }
+        }
+    }
+}
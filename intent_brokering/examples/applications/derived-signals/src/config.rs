@@ -0,0 +1,85 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+};
+
+use intent_brokering_common::error::{Error, ResultExt as _};
+use serde::Deserialize;
+
+use crate::expression::Expression;
+
+/// An event source of another namespace, which expressions refer to by name.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Input {
+    pub namespace: String,
+    pub source: String,
+}
+
+/// A derived event source, whose value is the value of the expression.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Signal {
+    pub source: String,
+    pub expression: String,
+}
+
+/// The definition of the derived signals. Signals refer to inputs, or to
+/// signals defined before them, by name.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The namespace the derived signals are published in.
+    pub namespace: String,
+    pub inputs: HashMap<String, Input>,
+    pub signals: Vec<Signal>,
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let config = fs::read_to_string(path).map_err_with(format!("Failed to read '{path}'."))?;
+        Self::parse(&config)
+    }
+
+    pub fn parse(config: &str) -> Result<Self, Error> {
+        let config: Self =
+            serde_json::from_str(config).map_err_with("Failed to parse the signal definitions.")?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parses the expressions of the signals, in the order of definition.
+    pub fn expressions(&self) -> Result<Vec<(String, Expression)>, Error> {
+        self.signals
+            .iter()
+            .map(|signal| {
+                Expression::parse(&signal.expression)
+                    .map(|expression| (signal.source.clone(), expression))
+                    .map_err_with(format!("Failed to parse expression of '{}'.", signal.source))
+            })
+            .collect()
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let expressions = self.expressions()?;
+        let mut names: HashSet<&str> = self.inputs.keys().map(String::as_str).collect();
+
+        for (source, expression) in expressions.iter() {
+            if let Some(name) = expression.inputs().into_iter().find(|name| !names.contains(name)) {
+                return Err(Error::new(format!(
+                    "Signal '{source}' refers to '{name}', which is neither an input nor a signal defined before it."
+                )));
+            }
+
+            if !names.insert(source) {
+                return Err(Error::new(format!("Name '{source}' is defined more than once.")));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::collections::{HashMap, HashSet};
+
+use tokio::time::Instant;
+
+use crate::expression::{Context, Expression, Scalar};
+
+struct Derived {
+    source: String,
+    expression: Expression,
+    inputs: HashSet<String>,
+    is_time_dependent: bool,
+}
+
+/// Evaluates the derived signals whenever their inputs change. Signals are
+/// evaluated in the order of definition, such that a signal observes the
+/// updated values of the signals defined before it.
+pub struct Engine {
+    values: HashMap<String, Scalar>,
+    signals: Vec<Derived>,
+}
+
+impl Engine {
+    pub fn new(signals: Vec<(String, Expression)>) -> Self {
+        let signals = signals
+            .into_iter()
+            .map(|(source, expression)| Derived {
+                inputs: expression.inputs().into_iter().map(str::to_owned).collect(),
+                is_time_dependent: expression.is_time_dependent(),
+                source,
+                expression,
+            })
+            .collect();
+
+        Self { values: HashMap::new(), signals }
+    }
+
+    /// Whether any signal must be re-evaluated periodically, see [`Self::tick`].
+    pub fn is_time_dependent(&self) -> bool {
+        self.signals.iter().any(|signal| signal.is_time_dependent)
+    }
+
+    /// Updates the value of an input and returns the signals whose values
+    /// changed as a result. Signals without a value are returned as `None`.
+    pub fn set(
+        &mut self,
+        input: &str,
+        value: Option<Scalar>,
+        now: Instant,
+    ) -> Vec<(String, Option<Scalar>)> {
+        if !self.update(input, value) {
+            return vec![];
+        }
+
+        self.evaluate(HashSet::from([input.to_owned()]), now, false)
+    }
+
+    /// Re-evaluates the time dependent signals, e.g. `debounce`.
+    pub fn tick(&mut self, now: Instant) -> Vec<(String, Option<Scalar>)> {
+        self.evaluate(HashSet::new(), now, true)
+    }
+
+    fn update(&mut self, name: &str, value: Option<Scalar>) -> bool {
+        match value {
+            Some(value) => self.values.insert(name.to_owned(), value) != Some(value),
+            None => self.values.remove(name).is_some(),
+        }
+    }
+
+    fn evaluate(
+        &mut self,
+        mut changed: HashSet<String>,
+        now: Instant,
+        tick: bool,
+    ) -> Vec<(String, Option<Scalar>)> {
+        let mut updates = vec![];
+
+        for index in 0..self.signals.len() {
+            let signal = &mut self.signals[index];
+            if !(tick && signal.is_time_dependent) && signal.inputs.is_disjoint(&changed) {
+                continue;
+            }
+
+            let context = Context { values: &self.values, now, sample: !tick };
+            let value = signal.expression.evaluate(&context);
+            let source = signal.source.clone();
+
+            if self.update(&source, value) {
+                changed.insert(source.clone());
+                updates.push((source, value));
+            }
+        }
+
+        updates
+    }
+}
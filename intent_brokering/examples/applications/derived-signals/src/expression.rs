@@ -0,0 +1,493 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Expressions defining derived signals in terms of named inputs:
+//!
+//! ```text
+//! speed / 3.6
+//! avg(speed, 10)
+//! debounce(door_open && speed > 5, 1000)
+//! ```
+//!
+//! Values are numbers or booleans. Besides the arithmetic (`+ - * / %`),
+//! comparison (`== != < <= > >=`) and logical (`&& || !`) operators, the
+//! following functions are supported:
+//!
+//! - `abs(x)`, `min(x, y)` and `max(x, y)`.
+//! - `avg(x, n)`, the moving average of the last `n` values of `x`, sampled
+//!   whenever one of the inputs of the expression changes.
+//! - `debounce(x, ms)`, the value of `x` once it did not change for `ms`
+//!   milliseconds.
+//!
+//! An expression which cannot be evaluated, e.g. because an input has no
+//! value yet or the types of the operands do not match, has no value.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    iter::Peekable,
+    str::Chars,
+    time::Duration,
+};
+
+use intent_brokering_common::error::Error;
+use intent_brokering_proto::common::ValueEnum;
+use tokio::time::Instant;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scalar {
+    Bool(bool),
+    Number(f64),
+}
+
+impl Scalar {
+    pub fn from_value(value: &ValueEnum) -> Option<Self> {
+        match value {
+            ValueEnum::Bool(value) => Some(Self::Bool(*value)),
+            ValueEnum::Int32(value) => Some(Self::Number((*value).into())),
+            ValueEnum::Int64(value) => Some(Self::Number(*value as f64)),
+            ValueEnum::Float32(value) => Some(Self::Number((*value).into())),
+            ValueEnum::Float64(value) => Some(Self::Number(*value)),
+            _ => None,
+        }
+    }
+
+    fn number(self) -> Option<f64> {
+        match self {
+            Self::Number(value) => Some(value),
+            Self::Bool(_) => None,
+        }
+    }
+
+    fn bool(self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(value),
+            Self::Number(_) => None,
+        }
+    }
+}
+
+impl From<Scalar> for ValueEnum {
+    fn from(value: Scalar) -> Self {
+        match value {
+            Scalar::Bool(value) => ValueEnum::Bool(value),
+            Scalar::Number(value) => ValueEnum::Float64(value),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+#[derive(Clone, Debug)]
+enum Function {
+    Abs(Box<Expr>),
+    Min(Box<Expr>, Box<Expr>),
+    Max(Box<Expr>, Box<Expr>),
+    Avg {
+        expr: Box<Expr>,
+        window: usize,
+        samples: VecDeque<f64>,
+    },
+    Debounce {
+        expr: Box<Expr>,
+        delay: Duration,
+        stable: Option<Scalar>,
+        pending: Option<(Scalar, Instant)>,
+    },
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Literal(Scalar),
+    Input(String),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(Box<Expr>, Operator, Box<Expr>),
+    Call(Function),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Operator(Operator),
+    Not,
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, Error> {
+    fn next_is(chars: &mut Peekable<Chars>, c: char) -> bool {
+        chars.next_if_eq(&c).is_some()
+    }
+
+    let mut chars = input.chars().peekable();
+    let mut tokens = vec![];
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            '+' => Token::Operator(Operator::Add),
+            '-' => Token::Operator(Operator::Sub),
+            '*' => Token::Operator(Operator::Mul),
+            '/' => Token::Operator(Operator::Div),
+            '%' => Token::Operator(Operator::Rem),
+            '=' if next_is(&mut chars, '=') => Token::Operator(Operator::Eq),
+            '!' if next_is(&mut chars, '=') => Token::Operator(Operator::Ne),
+            '!' => Token::Not,
+            '<' if next_is(&mut chars, '=') => Token::Operator(Operator::Le),
+            '<' => Token::Operator(Operator::Lt),
+            '>' if next_is(&mut chars, '=') => Token::Operator(Operator::Ge),
+            '>' => Token::Operator(Operator::Gt),
+            '&' if next_is(&mut chars, '&') => Token::Operator(Operator::And),
+            '|' if next_is(&mut chars, '|') => Token::Operator(Operator::Or),
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut number = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '.') {
+                    number.push(c);
+                }
+                Token::Number(
+                    number
+                        .parse()
+                        .map_err(|_| Error::new(format!("Invalid number '{number}'.")))?,
+                )
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    ident.push(c);
+                }
+                Token::Ident(ident)
+            }
+            c => return Err(Error::new(format!("Unexpected character '{c}'."))),
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// A recursive descent parser, with the usual precedence of `||` over `&&`
+/// over comparisons over `+ -` over `* / %` over unary operators.
+struct Parser {
+    tokens: std::vec::IntoIter<Token>,
+    peeked: Option<Token>,
+}
+
+impl Parser {
+    fn peek(&mut self) -> Option<&Token> {
+        if self.peeked.is_none() {
+            self.peeked = self.tokens.next();
+        }
+        self.peeked.as_ref()
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        self.peeked.take().or_else(|| self.tokens.next())
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), Error> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            _ => Err(Error::new(format!("Expected {expected:?}."))),
+        }
+    }
+
+    fn next_if_operator(&mut self, accept: &[Operator]) -> Option<Operator> {
+        match self.peek() {
+            Some(Token::Operator(operator)) if accept.contains(operator) => {
+                let operator = *operator;
+                self.next();
+                Some(operator)
+            }
+            _ => None,
+        }
+    }
+
+    fn binary(
+        &mut self,
+        operators: &[Operator],
+        operand: fn(&mut Self) -> Result<Expr, Error>,
+    ) -> Result<Expr, Error> {
+        let mut expr = operand(self)?;
+        while let Some(operator) = self.next_if_operator(operators) {
+            expr = Expr::Binary(Box::new(expr), operator, Box::new(operand(self)?));
+        }
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, Error> {
+        self.binary(&[Operator::Or], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, Error> {
+        self.binary(&[Operator::And], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, Error> {
+        use Operator::*;
+        let expr = self.sum()?;
+        match self.next_if_operator(&[Eq, Ne, Lt, Le, Gt, Ge]) {
+            Some(operator) => Ok(Expr::Binary(Box::new(expr), operator, Box::new(self.sum()?))),
+            None => Ok(expr),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, Error> {
+        self.binary(&[Operator::Add, Operator::Sub], Self::product)
+    }
+
+    fn product(&mut self) -> Result<Expr, Error> {
+        self.binary(&[Operator::Mul, Operator::Div, Operator::Rem], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr, Error> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some(Token::Operator(Operator::Sub)) => Ok(Expr::Neg(Box::new(self.unary()?))),
+            Some(Token::LParen) => {
+                let expr = self.or()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(Token::Number(value)) => Ok(Expr::Literal(Scalar::Number(value))),
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "true" => Ok(Expr::Literal(Scalar::Bool(true))),
+                "false" => Ok(Expr::Literal(Scalar::Bool(false))),
+                _ if self.peek() == Some(&Token::LParen) => self.call(ident),
+                _ => Ok(Expr::Input(ident)),
+            },
+            Some(token) => Err(Error::new(format!("Unexpected token {token:?}."))),
+            None => Err(Error::new("Unexpected end of expression.")),
+        }
+    }
+
+    fn call(&mut self, name: String) -> Result<Expr, Error> {
+        self.expect(Token::LParen)?;
+        let mut args = vec![];
+        if self.peek() != Some(&Token::RParen) {
+            args.push(self.or()?);
+            while self.peek() == Some(&Token::Comma) {
+                self.next();
+                args.push(self.or()?);
+            }
+        }
+        self.expect(Token::RParen)?;
+
+        // The parameters of stateful functions must be constant.
+        fn constant(expr: &Expr, function: &str) -> Result<f64, Error> {
+            match expr {
+                Expr::Literal(Scalar::Number(value)) if *value >= 0.0 => Ok(*value),
+                _ => Err(Error::new(format!(
+                    "The second argument of '{function}' must be a non-negative number."
+                ))),
+            }
+        }
+
+        let mut args = args.into_iter().map(Box::new);
+        let function = match (name.as_str(), args.next(), args.next(), args.next()) {
+            ("abs", Some(x), None, None) => Function::Abs(x),
+            ("min", Some(x), Some(y), None) => Function::Min(x, y),
+            ("max", Some(x), Some(y), None) => Function::Max(x, y),
+            ("avg", Some(expr), Some(window), None) => {
+                let window = constant(&window, "avg")? as usize;
+                if window == 0 {
+                    return Err(Error::new("The window of 'avg' must not be empty."));
+                }
+                Function::Avg { expr, window, samples: VecDeque::with_capacity(window) }
+            }
+            ("debounce", Some(expr), Some(delay), None) => Function::Debounce {
+                expr,
+                delay: Duration::from_millis(constant(&delay, "debounce")? as u64),
+                stable: None,
+                pending: None,
+            },
+            ("abs" | "min" | "max" | "avg" | "debounce", ..) => {
+                return Err(Error::new(format!("Wrong number of arguments for '{name}'.")))
+            }
+            _ => return Err(Error::new(format!("Unknown function '{name}'."))),
+        };
+
+        Ok(Expr::Call(function))
+    }
+}
+
+/// The circumstances of an evaluation.
+pub struct Context<'a> {
+    pub values: &'a HashMap<String, Scalar>,
+    pub now: Instant,
+    /// Whether an input changed, as opposed to a periodic re-evaluation of
+    /// time dependent expressions. Only the former is sampled by `avg`.
+    pub sample: bool,
+}
+
+/// A parsed expression, which holds the state of the stateful functions it
+/// calls.
+#[derive(Clone, Debug)]
+pub struct Expression {
+    expr: Expr,
+}
+
+impl Expression {
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        let mut parser = Parser { tokens: tokenize(input)?.into_iter(), peeked: None };
+        let expr = parser.or()?;
+        if let Some(token) = parser.next() {
+            return Err(Error::new(format!("Unexpected token {token:?}.")));
+        }
+        Ok(Self { expr })
+    }
+
+    /// The names of the inputs the expression refers to.
+    pub fn inputs(&self) -> HashSet<&str> {
+        fn collect<'a>(expr: &'a Expr, inputs: &mut HashSet<&'a str>) {
+            match expr {
+                Expr::Literal(_) => {}
+                Expr::Input(name) => _ = inputs.insert(name),
+                Expr::Neg(expr) | Expr::Not(expr) => collect(expr, inputs),
+                Expr::Binary(lhs, _, rhs) => {
+                    collect(lhs, inputs);
+                    collect(rhs, inputs);
+                }
+                Expr::Call(function) => match function {
+                    Function::Abs(expr)
+                    | Function::Avg { expr, .. }
+                    | Function::Debounce { expr, .. } => collect(expr, inputs),
+                    Function::Min(x, y) | Function::Max(x, y) => {
+                        collect(x, inputs);
+                        collect(y, inputs);
+                    }
+                },
+            }
+        }
+
+        let mut inputs = HashSet::new();
+        collect(&self.expr, &mut inputs);
+        inputs
+    }
+
+    /// Whether the value of the expression can change without any of its
+    /// inputs changing, i.e. it needs to be re-evaluated periodically.
+    pub fn is_time_dependent(&self) -> bool {
+        fn any(expr: &Expr) -> bool {
+            match expr {
+                Expr::Literal(_) | Expr::Input(_) => false,
+                Expr::Neg(expr) | Expr::Not(expr) => any(expr),
+                Expr::Binary(lhs, _, rhs) => any(lhs) || any(rhs),
+                Expr::Call(function) => match function {
+                    Function::Debounce { .. } => true,
+                    Function::Abs(expr) | Function::Avg { expr, .. } => any(expr),
+                    Function::Min(x, y) | Function::Max(x, y) => any(x) || any(y),
+                },
+            }
+        }
+
+        any(&self.expr)
+    }
+
+    pub fn evaluate(&mut self, context: &Context) -> Option<Scalar> {
+        evaluate(&mut self.expr, context)
+    }
+}
+
+fn evaluate(expr: &mut Expr, context: &Context) -> Option<Scalar> {
+    let number = |value: f64| value.is_finite().then_some(Scalar::Number(value));
+
+    match expr {
+        Expr::Literal(literal) => Some(*literal),
+        Expr::Input(name) => context.values.get(name).copied(),
+        Expr::Neg(expr) => number(-evaluate(expr, context)?.number()?),
+        Expr::Not(expr) => Some(Scalar::Bool(!evaluate(expr, context)?.bool()?)),
+        Expr::Binary(lhs, operator, rhs) => {
+            // Both operands are evaluated, such that the state of stateful
+            // functions does not depend on short-circuiting.
+            let (lhs, rhs) = (evaluate(lhs, context), evaluate(rhs, context));
+            let (lhs, rhs) = (lhs?, rhs?);
+            match (*operator, lhs, rhs) {
+                (Operator::And, Scalar::Bool(lhs), Scalar::Bool(rhs)) => {
+                    Some(Scalar::Bool(lhs && rhs))
+                }
+                (Operator::Or, Scalar::Bool(lhs), Scalar::Bool(rhs)) => {
+                    Some(Scalar::Bool(lhs || rhs))
+                }
+                (Operator::Eq, lhs, rhs) => Some(Scalar::Bool(lhs == rhs)),
+                (Operator::Ne, lhs, rhs) => Some(Scalar::Bool(lhs != rhs)),
+                (operator, Scalar::Number(lhs), Scalar::Number(rhs)) => match operator {
+                    Operator::Add => number(lhs + rhs),
+                    Operator::Sub => number(lhs - rhs),
+                    Operator::Mul => number(lhs * rhs),
+                    Operator::Div => number(lhs / rhs),
+                    Operator::Rem => number(lhs % rhs),
+                    Operator::Lt => Some(Scalar::Bool(lhs < rhs)),
+                    Operator::Le => Some(Scalar::Bool(lhs <= rhs)),
+                    Operator::Gt => Some(Scalar::Bool(lhs > rhs)),
+                    Operator::Ge => Some(Scalar::Bool(lhs >= rhs)),
+                    Operator::And | Operator::Or | Operator::Eq | Operator::Ne => None,
+                },
+                _ => None,
+            }
+        }
+        Expr::Call(Function::Abs(expr)) => number(evaluate(expr, context)?.number()?.abs()),
+        Expr::Call(Function::Min(x, y)) => {
+            let (x, y) = (evaluate(x, context), evaluate(y, context));
+            number(x?.number()?.min(y?.number()?))
+        }
+        Expr::Call(Function::Max(x, y)) => {
+            let (x, y) = (evaluate(x, context), evaluate(y, context));
+            number(x?.number()?.max(y?.number()?))
+        }
+        Expr::Call(Function::Avg { expr, window, samples }) => {
+            let value = evaluate(expr, context).and_then(Scalar::number);
+            if let (true, Some(value)) = (context.sample, value) {
+                if samples.len() == *window {
+                    samples.pop_front();
+                }
+                samples.push_back(value);
+            }
+
+            if samples.is_empty() {
+                None
+            } else {
+                number(samples.iter().sum::<f64>() / samples.len() as f64)
+            }
+        }
+        Expr::Call(Function::Debounce { expr, delay, stable, pending }) => {
+            match evaluate(expr, context) {
+                Some(value) if Some(value) == *stable => *pending = None,
+                Some(value) => {
+                    let since = match pending {
+                        Some((pending, since)) if *pending == value => *since,
+                        _ => pending.insert((value, context.now)).1,
+                    };
+                    if context.now.duration_since(since) >= *delay {
+                        *stable = Some(value);
+                        *pending = None;
+                    }
+                }
+                None => {}
+            }
+
+            *stable
+        }
+    }
+}
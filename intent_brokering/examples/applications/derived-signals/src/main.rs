@@ -0,0 +1,209 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+mod config;
+mod engine;
+mod expression;
+mod intent_provider;
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use examples_common::intent_brokering::{
+    self,
+    api::{GrpcIntentBrokering, IntentBrokering as _, IntentBrokeringExt as _},
+    registration::Builder,
+    value::Value,
+};
+use futures::StreamExt as _;
+use intent_brokering_common::config::env;
+use intent_brokering_common::error::{Error, ResultExt as _};
+use intent_brokering_common::shutdown::RouterExt as _;
+use intent_brokering_proto::{
+    common::ValueEnum,
+    provider::provider_service_server::ProviderServiceServer,
+    runtime::{intent_registration::Intent, intent_service_registration::ExecutionLocality},
+    streaming::channel_service_server::ChannelServiceServer,
+};
+use tokio::{
+    sync::mpsc,
+    time::{sleep, Instant, MissedTickBehavior},
+};
+use tonic::transport::Server;
+use url::Url;
+
+use crate::config::Config;
+use crate::engine::Engine;
+use crate::expression::Scalar;
+use crate::intent_provider::{IntentProvider, StreamingStore};
+
+intent_brokering::provider::main!(wain);
+
+/// An example definition, used unless `DERIVED_SIGNALS_CONFIG` points to a
+/// different definition.
+const DEFAULT_CONFIG: &str = include_str!("../signals.json");
+
+/// The interval in which time dependent signals are re-evaluated.
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+type Update = (String, Option<Scalar>);
+
+/// Forwards the value of a source to the inputs referring to it. Returns
+/// whether the values are still being evaluated.
+async fn forward(
+    namespace: &str,
+    inputs: &HashMap<String, Vec<String>>,
+    updates: &mpsc::Sender<Update>,
+    source: &str,
+    value: Option<Value>,
+) -> bool {
+    let value = value.and_then(|value| Scalar::from_value(&value.into()));
+    if value.is_none() {
+        tracing::debug!("Value of '{namespace}/{source}' is neither a number nor a bool.");
+    }
+
+    for input in inputs.get(source).into_iter().flatten() {
+        if updates.send((input.clone(), value)).await.is_err() {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Subscribes to the sources of a namespace and forwards their values to the
+/// inputs referring to them, until the stream of events ends.
+async fn subscribe_inputs(
+    namespace: &str,
+    inputs: &HashMap<String, Vec<String>>,
+    updates: &mpsc::Sender<Update>,
+) -> Result<(), Error> {
+    let mut intent_broker = GrpcIntentBrokering::connect().await?;
+    intent_broker.wait_for_service(namespace, [Intent::Read, Intent::Subscribe], None).await?;
+
+    let mut events =
+        intent_broker.listen(namespace, inputs.keys().map(|source| source.as_str().into())).await?;
+
+    tracing::info!("Subscribed to {} source(s) of '{namespace}'.", inputs.len());
+
+    // Events are only published on changes, hence the current values are read
+    // once subscribed.
+    for source in inputs.keys() {
+        match intent_broker.read(namespace, source.as_str()).await {
+            Ok(value) => {
+                if !forward(namespace, inputs, updates, source, value).await {
+                    return Ok(());
+                }
+            }
+            Err(e) => tracing::debug!("Reading '{namespace}/{source}' failed: {e:?}"),
+        }
+    }
+
+    while let Some(event) = events.next().await {
+        let event = event?;
+        if !forward(namespace, inputs, updates, &event.id, Some(event.data)).await {
+            return Ok(());
+        }
+    }
+
+    Err(Error::new("Stream of events ended."))
+}
+
+async fn wain() -> Result<(), Error> {
+    let url: Url = env("DERIVED_SIGNALS_URL")
+        .unwrap_or_else(|| "http://0.0.0.0:50070".to_owned()) // DevSkim: ignore DS137138
+        .parse()
+        .map_err_with("Failed to parse URL.")?;
+
+    let config = match env::<String>("DERIVED_SIGNALS_CONFIG") {
+        Some(path) => Config::load(&path)?,
+        None => Config::parse(DEFAULT_CONFIG)?,
+    };
+
+    let registration = Builder::new(
+        "sdv.derived-signals",
+        "0.0.1",
+        url,
+        &config.namespace,
+        [Intent::Read, Intent::Subscribe, Intent::Discover],
+        ExecutionLocality::Local,
+    )
+    .from_env();
+
+    let socket_address = registration.parse_provider_socket_address()?;
+    let url = registration.announce_url().to_owned();
+    tokio::task::spawn(registration.register());
+
+    tracing::info!("Application listening on: {url}");
+
+    // The inputs referring to each source, by namespace.
+    let mut namespaces: HashMap<String, HashMap<String, Vec<String>>> = HashMap::new();
+    for (name, input) in config.inputs.iter() {
+        namespaces
+            .entry(input.namespace.clone())
+            .or_default()
+            .entry(input.source.clone())
+            .or_default()
+            .push(name.clone());
+    }
+
+    let (sender, mut receiver) = mpsc::channel(100);
+    for (namespace, inputs) in namespaces {
+        let sender = sender.clone();
+        tokio::task::spawn(async move {
+            loop {
+                if let Err(e) = subscribe_inputs(&namespace, &inputs, &sender).await {
+                    tracing::warn!("Subscribing to '{namespace}' failed, retrying: {e:?}");
+                }
+
+                if sender.is_closed() {
+                    break;
+                }
+
+                sleep(RETRY_INTERVAL).await;
+            }
+        });
+    }
+
+    let streaming_store = Arc::new(StreamingStore::new());
+    let provider = IntentProvider::new(url, Arc::clone(&streaming_store));
+    let mut engine = Engine::new(config.expressions()?);
+
+    let evaluate = {
+        let streaming_store = Arc::clone(&streaming_store);
+        async move {
+            let publish = |updates: Vec<Update>| {
+                for (source, value) in updates {
+                    match value {
+                        Some(value) => streaming_store.set(source.into(), ValueEnum::from(value)),
+                        None => _ = streaming_store.remove(&source.into()),
+                    }
+                }
+            };
+
+            let mut tick = tokio::time::interval(TICK_INTERVAL);
+            tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            let is_time_dependent = engine.is_time_dependent();
+
+            loop {
+                tokio::select! {
+                    update = receiver.recv() => match update {
+                        Some((input, value)) => publish(engine.set(&input, value, Instant::now())),
+                        None => break,
+                    },
+                    now = tick.tick(), if is_time_dependent => publish(engine.tick(now)),
+                }
+            }
+        }
+    };
+
+    tokio::select! {
+        result = Server::builder()
+            .add_service(ProviderServiceServer::new(provider))
+            .add_service(ChannelServiceServer::new(streaming_store.ess().clone()))
+            .serve_with_ctrl_c_shutdown(socket_address) => result,
+        _ = evaluate => Err(Error::new("Evaluation of the signals stopped unexpectedly.")),
+    }
+}
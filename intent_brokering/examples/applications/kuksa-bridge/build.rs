@@ -0,0 +1,14 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::{error::Error, path::Path};
+use tonic_build::configure;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    configure()
+        .build_server(false)
+        .compile(&[Path::new("proto/kuksa/val/v1/val.proto")], &[Path::new("proto/")])?;
+
+    Ok(())
+}
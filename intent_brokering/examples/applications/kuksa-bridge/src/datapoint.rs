@@ -0,0 +1,147 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use intent_brokering_proto::common::{value::Value, List, ValueMessage};
+
+use crate::proto::val::{
+    datapoint::Value as DatapointValue, BoolArray, DataType, DoubleArray, FloatArray, Int32Array,
+    Int64Array, StringArray, Uint32Array, Uint64Array,
+};
+
+/// Translates the value of a Kuksa datapoint into a `common::Value`. Unsigned
+/// integers are represented as `int64`, as they may not fit into `int32`.
+/// Arrays are represented as lists.
+pub fn to_value(datapoint: DatapointValue) -> Result<Value, String> {
+    fn list<T>(values: Vec<T>, f: impl Fn(T) -> Result<Value, String>) -> Result<Value, String> {
+        values
+            .into_iter()
+            .map(|v| f(v).map(|v| ValueMessage { value: Some(v) }))
+            .collect::<Result<_, _>>()
+            .map(|value| Value::List(List { value }))
+    }
+
+    fn uint64(value: u64) -> Result<Value, String> {
+        i64::try_from(value)
+            .map(Value::Int64)
+            .map_err(|_| format!("Value '{value}' exceeds the range of int64."))
+    }
+
+    Ok(match datapoint {
+        DatapointValue::String(v) => Value::String(v),
+        DatapointValue::Bool(v) => Value::Bool(v),
+        DatapointValue::Int32(v) => Value::Int32(v),
+        DatapointValue::Int64(v) => Value::Int64(v),
+        DatapointValue::Uint32(v) => Value::Int64(v.into()),
+        DatapointValue::Uint64(v) => uint64(v)?,
+        DatapointValue::Float(v) => Value::Float32(v),
+        DatapointValue::Double(v) => Value::Float64(v),
+        DatapointValue::StringArray(a) => list(a.values, |v| Ok(Value::String(v)))?,
+        DatapointValue::BoolArray(a) => list(a.values, |v| Ok(Value::Bool(v)))?,
+        DatapointValue::Int32Array(a) => list(a.values, |v| Ok(Value::Int32(v)))?,
+        DatapointValue::Int64Array(a) => list(a.values, |v| Ok(Value::Int64(v)))?,
+        DatapointValue::Uint32Array(a) => list(a.values, |v| Ok(Value::Int64(v.into())))?,
+        DatapointValue::Uint64Array(a) => list(a.values, uint64)?,
+        DatapointValue::FloatArray(a) => list(a.values, |v| Ok(Value::Float32(v)))?,
+        DatapointValue::DoubleArray(a) => list(a.values, |v| Ok(Value::Float64(v)))?,
+    })
+}
+
+/// Translates a `common::Value` into the value of a Kuksa datapoint of the
+/// given data type. Integers are accepted regardless of their width, as long
+/// as they are in the range of the data type.
+pub fn from_value(value: Value, data_type: DataType) -> Result<DatapointValue, String> {
+    let mismatch =
+        || format!("Value is not compatible with data type '{}'.", data_type.as_str_name());
+
+    let Some(element_type) = element_type(data_type) else {
+        return scalar(value, data_type).ok_or_else(mismatch);
+    };
+
+    let Value::List(list) = value else {
+        return Err(mismatch());
+    };
+
+    let elements = list
+        .value
+        .into_iter()
+        .map(|v| v.value.and_then(|v| scalar(v, element_type)))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(mismatch)?;
+
+    macro_rules! array {
+        ($array:ident, $variant:ident) => {
+            DatapointValue::$array($array {
+                values: elements
+                    .into_iter()
+                    .filter_map(|v| match v {
+                        DatapointValue::$variant(v) => Some(v),
+                        _ => None,
+                    })
+                    .collect(),
+            })
+        };
+    }
+
+    Ok(match element_type {
+        DataType::String => array!(StringArray, String),
+        DataType::Boolean => array!(BoolArray, Bool),
+        DataType::Int64 => array!(Int64Array, Int64),
+        DataType::Uint64 => array!(Uint64Array, Uint64),
+        DataType::Uint8 | DataType::Uint16 | DataType::Uint32 => array!(Uint32Array, Uint32),
+        DataType::Float => array!(FloatArray, Float),
+        DataType::Double => array!(DoubleArray, Double),
+        _ => array!(Int32Array, Int32),
+    })
+}
+
+/// The data type of the elements of an array data type.
+fn element_type(data_type: DataType) -> Option<DataType> {
+    Some(match data_type {
+        DataType::StringArray => DataType::String,
+        DataType::BooleanArray => DataType::Boolean,
+        DataType::Int8Array => DataType::Int8,
+        DataType::Int16Array => DataType::Int16,
+        DataType::Int32Array => DataType::Int32,
+        DataType::Int64Array => DataType::Int64,
+        DataType::Uint8Array => DataType::Uint8,
+        DataType::Uint16Array => DataType::Uint16,
+        DataType::Uint32Array => DataType::Uint32,
+        DataType::Uint64Array => DataType::Uint64,
+        DataType::FloatArray => DataType::Float,
+        DataType::DoubleArray => DataType::Double,
+        _ => return None,
+    })
+}
+
+fn scalar(value: Value, data_type: DataType) -> Option<DatapointValue> {
+    let integer = match value {
+        Value::Int32(v) => Some(i64::from(v)),
+        Value::Int64(v) => Some(v),
+        _ => None,
+    };
+    let in_range = |min: i64, max: i64| integer.filter(|v| (min..=max).contains(v));
+
+    Some(match (data_type, value) {
+        (DataType::String, Value::String(v)) => DatapointValue::String(v),
+        (DataType::Boolean, Value::Bool(v)) => DatapointValue::Bool(v),
+        (DataType::Float, Value::Float32(v)) => DatapointValue::Float(v),
+        (DataType::Double, Value::Float32(v)) => DatapointValue::Double(v.into()),
+        (DataType::Double, Value::Float64(v)) => DatapointValue::Double(v),
+        (DataType::Int8, _) => {
+            DatapointValue::Int32(in_range(i8::MIN.into(), i8::MAX.into())? as i32)
+        }
+        (DataType::Int16, _) => {
+            DatapointValue::Int32(in_range(i16::MIN.into(), i16::MAX.into())? as i32)
+        }
+        (DataType::Int32, _) => {
+            DatapointValue::Int32(in_range(i32::MIN.into(), i32::MAX.into())? as i32)
+        }
+        (DataType::Int64, _) => DatapointValue::Int64(integer?),
+        (DataType::Uint8, _) => DatapointValue::Uint32(in_range(0, u8::MAX.into())? as u32),
+        (DataType::Uint16, _) => DatapointValue::Uint32(in_range(0, u16::MAX.into())? as u32),
+        (DataType::Uint32, _) => DatapointValue::Uint32(in_range(0, u32::MAX.into())? as u32),
+        (DataType::Uint64, _) => DatapointValue::Uint64(in_range(0, i64::MAX)? as u64),
+        _ => return None,
+    })
+}
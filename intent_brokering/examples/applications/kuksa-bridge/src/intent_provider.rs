@@ -0,0 +1,325 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::{collections::HashMap, sync::Arc, time::SystemTime};
+
+use async_trait::async_trait;
+use intent_brokering_common::streaming_ess::{SourceChange, StreamingEss, Timestamped};
+use tokio::{spawn, sync::Mutex, task::JoinHandle};
+use tokio_stream::StreamExt as _;
+use tonic::{transport::Channel, Request, Response, Status};
+use url::Url;
+
+use intent_brokering_proto::{
+    common::{
+        discover_fulfillment::Service, freshness::Quality, value::Value, DiscoverFulfillment,
+        Freshness, FulfillmentEnum, FulfillmentMessage, IntentEnum, ReadFulfillment, ReadIntent,
+        SubscribeFulfillment, SubscribeIntent, ValueMessage, WriteFulfillment, WriteIntent,
+    },
+    provider::{provider_service_server::ProviderService, FulfillRequest, FulfillResponse},
+};
+
+use crate::datapoint::{from_value, to_value};
+use crate::proto::val::{
+    val_client::ValClient, DataEntry, DataEntryError, DataType, Datapoint, EntryRequest,
+    EntryUpdate, Error as KuksaError, Field, GetRequest, SetRequest, SubscribeEntry,
+    SubscribeRequest, View,
+};
+
+/// Translates an error reported by the databroker, which uses HTTP status
+/// codes, into a gRPC status.
+fn status(error: &KuksaError) -> Status {
+    let message = format!("{}: {}", error.reason, error.message);
+    match error.code {
+        400 => Status::invalid_argument(message),
+        401 => Status::unauthenticated(message),
+        403 => Status::permission_denied(message),
+        404 => Status::not_found(message),
+        _ => Status::unknown(message),
+    }
+}
+
+fn entry_status(errors: &[DataEntryError]) -> Result<(), Status> {
+    match errors.first() {
+        Some(DataEntryError { error: Some(error), .. }) => Err(status(error)),
+        Some(DataEntryError { path, error: None }) => {
+            Err(Status::unknown(format!("Databroker failed for '{path}'.")))
+        }
+        None => Ok(()),
+    }
+}
+
+/// The source of the freshness of the values of the bridge.
+const FRESHNESS_SOURCE: &str = "kuksa.val";
+
+/// Forwards intents to a Kuksa.val databroker, where keys and subscription
+/// sources are the VSS paths of the datapoints. Subscriptions are relayed
+/// through the ESS of the bridge, with a single subscription to the
+/// databroker per datapoint, which is stopped once no consumer is subscribed
+/// to the datapoint anymore.
+pub struct IntentProvider {
+    url: Url,
+    client: ValClient<Channel>,
+    ess: StreamingEss<Timestamped<Value>>,
+    data_types: Mutex<HashMap<Box<str>, DataType>>,
+    subscriptions: Arc<Mutex<HashMap<Box<str>, JoinHandle<()>>>>,
+}
+
+impl IntentProvider {
+    pub fn new(
+        url: Url,
+        client: ValClient<Channel>,
+        ess: StreamingEss<Timestamped<Value>>,
+    ) -> Self {
+        let subscriptions = Arc::new(Mutex::new(HashMap::new()));
+        spawn(stop_unsubscribed(ess.clone(), Arc::clone(&subscriptions)));
+        Self { url, client, ess, data_types: Mutex::new(HashMap::new()), subscriptions }
+    }
+
+    async fn get(&self, path: &str, view: View) -> Result<DataEntry, Status> {
+        let response = self
+            .client
+            .clone()
+            .get(GetRequest {
+                entries: vec![EntryRequest {
+                    path: path.to_owned(),
+                    view: view as i32,
+                    fields: vec![],
+                }],
+            })
+            .await?
+            .into_inner();
+
+        if let Some(error) = response.error.as_ref().filter(|e| e.code != 0 && e.code != 200) {
+            return Err(status(error));
+        }
+        entry_status(&response.errors)?;
+
+        response
+            .entries
+            .into_iter()
+            .next()
+            .ok_or_else(|| Status::not_found(format!("Datapoint '{path}' does not exist.")))
+    }
+
+    /// Gets the data type of a datapoint, which is required to translate
+    /// values written to it. Data types are cached, as they do not change.
+    async fn data_type(&self, path: &str) -> Result<DataType, Status> {
+        if let Some(data_type) = self.data_types.lock().await.get(path) {
+            return Ok(*data_type);
+        }
+
+        let data_type = self
+            .get(path, View::Metadata)
+            .await?
+            .metadata
+            .and_then(|m| DataType::try_from(m.data_type).ok())
+            .filter(|data_type| *data_type != DataType::Unspecified)
+            .ok_or_else(|| Status::unknown(format!("Data type of '{path}' is unknown.")))?;
+
+        self.data_types.lock().await.insert(path.into(), data_type);
+        Ok(data_type)
+    }
+
+    async fn read(&self, intent: ReadIntent) -> Result<ReadFulfillment, Status> {
+        let datapoint = self.get(&intent.key, View::CurrentValue).await?.value;
+
+        // The databroker returns the value set last, which is as fresh as
+        // the time at which it was set.
+        let freshness = datapoint.as_ref().map(|datapoint| Freshness {
+            sampled_at: datapoint.timestamp.clone(),
+            source: FRESHNESS_SOURCE.to_owned(),
+            quality: Quality::Cached as i32,
+        });
+
+        let value = datapoint
+            .and_then(|datapoint| datapoint.value)
+            .map(to_value)
+            .transpose()
+            .map_err(Status::out_of_range)?;
+
+        Ok(ReadFulfillment { value: Some(ValueMessage { value }), freshness, ..Default::default() })
+    }
+
+    async fn write(&self, intent: WriteIntent) -> Result<WriteFulfillment, Status> {
+        if intent.precondition.is_some() {
+            return Err(Status::unimplemented("Conditional writes are not supported."));
+        }
+
+        let value = intent
+            .value
+            .and_then(|v| v.value)
+            .ok_or_else(|| Status::invalid_argument("Value must be specified."))?;
+        let data_type = self.data_type(&intent.key).await?;
+        let value = from_value(value, data_type).map_err(Status::invalid_argument)?;
+
+        let response = self
+            .client
+            .clone()
+            .set(SetRequest {
+                updates: vec![EntryUpdate {
+                    entry: Some(DataEntry {
+                        path: intent.key,
+                        value: Some(Datapoint { timestamp: None, value: Some(value) }),
+                        ..Default::default()
+                    }),
+                    fields: vec![Field::Value as i32],
+                }],
+            })
+            .await?
+            .into_inner();
+
+        if let Some(error) = response.error.as_ref().filter(|e| e.code != 0 && e.code != 200) {
+            return Err(status(error));
+        }
+        entry_status(&response.errors)?;
+
+        Ok(WriteFulfillment::default())
+    }
+
+    /// Subscribes to the datapoints with the databroker, unless a
+    /// subscription exists already, and relays their updates to the ESS.
+    async fn subscribe(&self, intent: SubscribeIntent) -> Result<SubscribeFulfillment, Status> {
+        let mut subscriptions = self.subscriptions.lock().await;
+
+        for path in intent.sources.iter() {
+            if subscriptions.contains_key(path.as_str()) {
+                continue;
+            }
+
+            let mut stream = self
+                .client
+                .clone()
+                .subscribe(SubscribeRequest {
+                    entries: vec![SubscribeEntry {
+                        path: path.clone(),
+                        view: View::CurrentValue as i32,
+                        fields: vec![Field::Value as i32],
+                    }],
+                })
+                .await?
+                .into_inner();
+
+            let path: Box<str> = path.as_str().into();
+            let key = path.clone();
+            let ess = self.ess.clone();
+            let all_subscriptions = Arc::clone(&self.subscriptions);
+
+            let task = spawn(async move {
+                while let Some(response) = stream.next().await {
+                    let response = match response {
+                        Ok(response) => response,
+                        Err(e) => {
+                            tracing::warn!("Subscription to '{path}' failed: {e}");
+                            break;
+                        }
+                    };
+
+                    for datapoint in
+                        response.updates.into_iter().filter_map(|u| u.entry.and_then(|e| e.value))
+                    {
+                        let Some(value) = datapoint.value else {
+                            continue;
+                        };
+
+                        // The databroker stamps datapoints with the time at
+                        // which they were set.
+                        let timestamp = datapoint
+                            .timestamp
+                            .and_then(|t| SystemTime::try_from(t).ok())
+                            .unwrap_or_else(SystemTime::now);
+
+                        match to_value(value) {
+                            Ok(value) => {
+                                let freshness = Freshness {
+                                    source: FRESHNESS_SOURCE.to_owned(),
+                                    quality: Quality::Live as i32,
+                                    ..Default::default()
+                                };
+                                _ = ess.publish(
+                                    path.as_ref(),
+                                    Timestamped::new(value, timestamp).with_freshness(freshness),
+                                )
+                            }
+                            Err(e) => tracing::warn!("Dropping update of '{path}': {e}"),
+                        }
+                    }
+                }
+
+                all_subscriptions.lock().await.remove(&path);
+            });
+
+            subscriptions.insert(key, task);
+        }
+
+        // The subscriptions are served before releasing the lock, such that
+        // the subscription to the databroker is not stopped in between.
+        let result = self.ess.serve_timestamped_subscriptions(intent, |value| value);
+        drop(subscriptions);
+        result
+    }
+}
+
+/// Stops the subscription to a datapoint with the databroker once the last
+/// consumer unsubscribed from it.
+async fn stop_unsubscribed(
+    ess: StreamingEss<Timestamped<Value>>,
+    subscriptions: Arc<Mutex<HashMap<Box<str>, JoinHandle<()>>>>,
+) {
+    let mut changes = ess.watch_sources();
+
+    while let Some(change) = changes.recv().await {
+        let SourceChange::Unsubscribed(path) = change else {
+            continue;
+        };
+
+        let mut subscriptions = subscriptions.lock().await;
+
+        // A consumer may have subscribed again in the meantime.
+        if ess.get_subscribed_events().into_iter().any(|source| source == path) {
+            continue;
+        }
+
+        if let Some(task) = subscriptions.remove(&path) {
+            tracing::debug!("Stopping subscription to '{path}', which has no consumers.");
+            task.abort();
+        }
+    }
+}
+
+#[async_trait]
+impl ProviderService for IntentProvider {
+    async fn fulfill(
+        &self,
+        request: Request<FulfillRequest>,
+    ) -> Result<Response<FulfillResponse>, Status> {
+        let fulfillment = match request
+            .into_inner()
+            .intent
+            .and_then(|i| i.intent)
+            .ok_or_else(|| Status::invalid_argument("Intent must be specified."))?
+        {
+            IntentEnum::Read(intent) => self.read(intent).await.map(FulfillmentEnum::Read),
+            IntentEnum::Write(intent) => self.write(intent).await.map(FulfillmentEnum::Write),
+            IntentEnum::Subscribe(intent) => {
+                self.subscribe(intent).await.map(FulfillmentEnum::Subscribe)
+            }
+            IntentEnum::Discover(_intent) => Ok(FulfillmentEnum::Discover(DiscoverFulfillment {
+                services: vec![Service {
+                    url: self.url.to_string(),
+                    schema_kind: "grpc+proto".to_owned(),
+                    schema_reference: "intent_brokering.streaming.v1".to_owned(),
+                    metadata: HashMap::new(),
+                }],
+            })),
+            _ => Err(Status::unknown("Unsupported or unknown intent."))?,
+        };
+
+        fulfillment.map(|f| {
+            Response::new(FulfillResponse {
+                fulfillment: Some(FulfillmentMessage { fulfillment: Some(f) }),
+            })
+        })
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+mod datapoint;
+mod intent_provider;
+mod proto;
+
+use examples_common::intent_brokering::{self, registration::Builder};
+use intent_brokering_common::config::env;
+use intent_brokering_common::error::{Error, ResultExt as _};
+use intent_brokering_common::shutdown::RouterExt as _;
+use intent_brokering_common::streaming_ess::StreamingEss;
+use intent_brokering_proto::{
+    provider::provider_service_server::ProviderServiceServer,
+    runtime::{intent_registration::Intent, intent_service_registration::ExecutionLocality},
+    streaming::channel_service_server::ChannelServiceServer,
+};
+use tonic::transport::{Endpoint, Server};
+use url::Url;
+
+use crate::intent_provider::IntentProvider;
+use crate::proto::val::val_client::ValClient;
+
+intent_brokering::provider::main!(wain);
+
+async fn wain() -> Result<(), Error> {
+    let url: Url = env("KUKSA_BRIDGE_URL")
+        .unwrap_or_else(|| "http://0.0.0.0:50066".to_owned()) // DevSkim: ignore DS137138
+        .parse()
+        .map_err_with("Failed to parse URL.")?;
+
+    let databroker_url: String =
+        env("KUKSA_DATABROKER_URL").unwrap_or_else(|| "http://127.0.0.1:55555".to_owned()); // DevSkim: ignore DS137138
+
+    let namespace: String = env("KUKSA_NAMESPACE").unwrap_or_else(|| "sdv.kuksa".to_owned());
+
+    // Connect lazily, such that the bridge can start before the databroker.
+    let client = ValClient::new(
+        Endpoint::from_shared(databroker_url.clone())
+            .map_err_with("Failed to parse databroker URL.")?
+            .connect_lazy(),
+    );
+
+    let registration = Builder::new(
+        "sdv.kuksa-bridge",
+        "0.0.1",
+        url,
+        &namespace,
+        [Intent::Read, Intent::Write, Intent::Subscribe, Intent::Discover],
+        ExecutionLocality::Local,
+    )
+    .from_env();
+
+    let socket_address = registration.parse_provider_socket_address()?;
+    let url = registration.announce_url().to_owned();
+    tokio::task::spawn(registration.register());
+
+    tracing::info!("Application listening on: {url}, forwarding to: {databroker_url}");
+
+    let ess = StreamingEss::new();
+    let provider = IntentProvider::new(url, client, ess.clone());
+
+    Server::builder()
+        .add_service(ProviderServiceServer::new(provider))
+        .add_service(ChannelServiceServer::new(ess))
+        .serve_with_ctrl_c_shutdown(socket_address)
+        .await
+}
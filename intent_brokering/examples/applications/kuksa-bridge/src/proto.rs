@@ -0,0 +1,17 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+mod kuksa {
+    pub mod val {
+        pub mod v1 {
+            // see https://github.com/hyperium/tonic/issues/1056
+            // and https://github.com/tokio-rs/prost/issues/661#issuecomment-1156606409
+            // why we use allow derive_partial_eq_without_eq
+            #![allow(clippy::derive_partial_eq_without_eq)]
+            tonic::include_proto!("kuksa.val.v1");
+        }
+    }
+}
+
+pub use kuksa::val::v1 as val;
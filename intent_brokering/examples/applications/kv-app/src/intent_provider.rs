@@ -2,42 +2,392 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
 
 use async_trait::async_trait;
 use examples_common::intent_brokering::{self, streaming::ProtoExt as _};
+use intent_brokering_common::{
+    catalog::Catalog,
+    error::Error,
+    precondition::{check_precondition, version_token, Versions},
+};
 use tonic::{Request, Response, Status};
 
 use url::Url;
 
+use crate::persistence::Log;
+
 use intent_brokering_proto::{
     common::{
-        discover_fulfillment::Service, value::Value, DiscoverFulfillment, FulfillmentEnum,
-        FulfillmentMessage, IntentEnum, WriteFulfillment, WriteIntent,
+        discover_fulfillment::Service, value::Value, write_batch_fulfillment, DeleteFulfillment,
+        DeleteIntent, DiscoverFulfillment, FulfillmentEnum, FulfillmentMessage, IntentEnum,
+        InvokeFulfillment, InvokeIntent, ReadFulfillment, ReadIntent, ValueMessage,
+        WriteBatchFulfillment, WriteBatchIntent, WriteFulfillment, WriteIntent, WritePrecondition,
+    },
+    provider::{
+        provider_service_server::ProviderService, transaction::Phase, FulfillRequest,
+        FulfillResponse, Transaction,
     },
-    provider::{provider_service_server::ProviderService, FulfillRequest, FulfillResponse},
 };
 
 pub type StreamingStore = intent_brokering::streaming::StreamingStore<Value>;
 
+const NOT_APPLIED_MESSAGE: &str = "Batch was not applied due to invalid writes.";
+
+/// A write which was validated, but not yet applied to the store.
+struct StagedWrite {
+    key: Box<str>,
+    value: Value,
+    precondition: Option<WritePrecondition>,
+}
+
+impl TryFrom<WriteIntent> for StagedWrite {
+    type Error = Status;
+
+    fn try_from(intent: WriteIntent) -> Result<Self, Self::Error> {
+        let value = intent
+            .value
+            .and_then(|v| v.value)
+            .ok_or_else(|| Status::invalid_argument("Value must be specified."))?;
+        Ok(Self { key: intent.key.into(), value, precondition: intent.precondition })
+    }
+}
+
+/// The state which accompanies the values of the store.
+#[derive(Default)]
+struct State {
+    versions: Versions<Box<str>>,
+    expirations: HashMap<Box<str>, SystemTime>,
+    log: Option<Log>,
+}
+
+impl State {
+    fn log(&mut self, append: impl FnOnce(&mut Log) -> Result<(), Error>) -> Result<(), Status> {
+        match self.log.as_mut() {
+            Some(log) => append(log).map_err(|e| Status::internal(e.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
 pub struct IntentProvider {
     url: Url,
     streaming_store: Arc<StreamingStore>,
+    // All changes are serialized through this lock, such that preconditions
+    // are evaluated and changes applied and logged atomically.
+    state: Mutex<State>,
+    transactions: Mutex<HashMap<Box<str>, Vec<StagedWrite>>>,
+    catalog: Catalog,
 }
 
 impl IntentProvider {
     pub fn new(url: Url, streaming_store: Arc<StreamingStore>) -> Self {
-        Self { url, streaming_store }
+        Self {
+            url,
+            streaming_store,
+            state: Mutex::new(State::default()),
+            transactions: Mutex::new(HashMap::new()),
+            catalog: Catalog::default(),
+        }
+    }
+
+    /// Validates the values of writes against the declared properties of the
+    /// catalog before they are applied.
+    pub fn with_catalog(self, catalog: Catalog) -> Self {
+        Self { catalog, ..self }
+    }
+
+    /// Persists all changes to the log at the given path, after restoring the
+    /// keys which were logged previously and are not expired yet.
+    pub fn with_log(self, path: impl AsRef<Path>) -> Result<Self, Error> {
+        let (log, entries) = Log::open(path.as_ref())?;
+        let now = SystemTime::now();
+
+        {
+            let mut state = self.state.lock().unwrap();
+            for (key, entry) in entries {
+                match entry.expires_at {
+                    Some(expires_at) if expires_at <= now => continue,
+                    Some(expires_at) => _ = state.expirations.insert(key.clone(), expires_at),
+                    None => {}
+                }
+                state.versions.increment(key.clone());
+                self.streaming_store.set(key, entry.value);
+            }
+            state.log = Some(log);
+        }
+
+        Ok(self)
+    }
+
+    /// Stages a write if its value is valid according to the catalog.
+    fn stage(&self, intent: WriteIntent) -> Result<StagedWrite, Status> {
+        let write = StagedWrite::try_from(intent)?;
+        self.catalog.validate(&write.key, &write.value)?;
+        Ok(write)
+    }
+
+    fn check(&self, versions: &Versions<Box<str>>, write: &StagedWrite) -> Result<(), Status> {
+        check_precondition(
+            write.precondition.as_ref(),
+            self.streaming_store.get(&write.key).as_ref(),
+            versions.get(&write.key),
+        )
+    }
+
+    /// Applies all writes at once if the preconditions of all of them hold,
+    /// or none of them otherwise. Returns the new version token of each key.
+    /// Writing a key clears its expiration.
+    fn apply(&self, writes: Vec<StagedWrite>) -> Vec<Result<String, Status>> {
+        let mut state = self.state.lock().unwrap();
+        let checks: Vec<_> =
+            writes.iter().map(|write| self.check(&state.versions, write)).collect();
+
+        if checks.iter().any(Result::is_err) {
+            return checks
+                .into_iter()
+                .map(|check| check.and(Err(Status::aborted(NOT_APPLIED_MESSAGE))))
+                .collect();
+        }
+
+        if let Err(e) = state.log(|log| {
+            writes.iter().try_for_each(|write| log.append_set(&write.key, &write.value, None))
+        }) {
+            return writes.iter().map(|_| Err(e.clone())).collect();
+        }
+
+        let tokens: Vec<_> = writes
+            .iter()
+            .map(|write| {
+                state.expirations.remove(&write.key);
+                version_token(state.versions.increment(write.key.clone()))
+            })
+            .collect();
+        self.streaming_store.set_many(writes.into_iter().map(|write| (write.key, write.value)));
+        tokens.into_iter().map(Ok).collect()
+    }
+
+    /// Removes a key from the store and forgets its state. Removal publishes a
+    /// `null` value to the subscribers of the key.
+    fn remove(&self, state: &mut State, key: Box<str>) -> Result<(), Status> {
+        state.log(|log| log.append_remove(&key))?;
+        self.streaming_store.remove(&key);
+        state.versions.remove(&key);
+        state.expirations.remove(&key);
+        Ok(())
+    }
+
+    /// Removes all keys whose expiration passed. Runs until the provider is
+    /// shut down.
+    pub async fn expire_keys(self: Arc<Self>, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+
+        loop {
+            interval.tick().await;
+
+            let now = SystemTime::now();
+            let mut state = self.state.lock().unwrap();
+            let expired: Vec<_> = state
+                .expirations
+                .iter()
+                .filter(|(_, expires_at)| **expires_at <= now)
+                .map(|(key, _)| key.clone())
+                .collect();
+
+            for key in expired {
+                tracing::debug!("Key '{key}' expired.");
+                if let Err(e) = self.remove(&mut state, key.clone()) {
+                    tracing::warn!("Failed to expire key '{key}': {e}");
+                }
+            }
+        }
+    }
+
+    /// Stages writes when preparing a transaction and only applies them to
+    /// the store once the transaction is committed. Preconditions are
+    /// evaluated when preparing and again when committing, in which case a
+    /// failing precondition fails the commit.
+    fn fulfill_transaction(
+        &self,
+        transaction: Transaction,
+        intent: Option<IntentEnum>,
+    ) -> Result<Option<FulfillmentEnum>, Status> {
+        let id: Box<str> = transaction.id.into();
+        let phase = Phase::try_from(transaction.phase)
+            .map_err(|_| Status::invalid_argument("Unknown transaction phase."))?;
+
+        match phase {
+            Phase::Prepare => {
+                let (writes, fulfillment) = match intent {
+                    Some(IntentEnum::Write(intent)) => (
+                        vec![self.stage(intent)?],
+                        FulfillmentEnum::Write(WriteFulfillment::default()),
+                    ),
+                    Some(IntentEnum::WriteBatch(intent)) => {
+                        let writes = intent
+                            .writes
+                            .into_iter()
+                            .map(|intent| self.stage(intent))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        let fulfillment = WriteBatchFulfillment {
+                            entries: writes
+                                .iter()
+                                .map(|write| batch_entry(&write.key, Ok(String::new())))
+                                .collect(),
+                        };
+                        (writes, FulfillmentEnum::WriteBatch(fulfillment))
+                    }
+                    _ => {
+                        Err(Status::invalid_argument("Only writes can be part of a transaction."))?
+                    }
+                };
+
+                {
+                    let state = self.state.lock().unwrap();
+                    for write in writes.iter() {
+                        self.check(&state.versions, write)?;
+                    }
+                }
+
+                self.transactions.lock().unwrap().entry(id).or_default().extend(writes);
+                Ok(Some(fulfillment))
+            }
+            Phase::Commit => {
+                let writes = self.transactions.lock().unwrap().remove(&id).unwrap_or_default();
+                match self.apply(writes).into_iter().find_map(Result::err) {
+                    Some(e) => Err(e),
+                    None => Ok(None),
+                }
+            }
+            Phase::Abort => {
+                self.transactions.lock().unwrap().remove(&id);
+                Ok(None)
+            }
+        }
+    }
+
+    fn read(&self, intent: ReadIntent) -> FulfillmentEnum {
+        let state = self.state.lock().unwrap();
+        let version = version_token(state.versions.get(intent.key.as_str()));
+
+        match self.streaming_store.read(intent) {
+            FulfillmentEnum::Read(read) => {
+                FulfillmentEnum::Read(ReadFulfillment { version, ..read })
+            }
+            fulfillment => fulfillment,
+        }
     }
 
     fn write(&self, intent: WriteIntent) -> Result<WriteFulfillment, Status> {
-        let key = intent.key.into();
-        let value = intent
-            .value
-            .and_then(|v| v.value)
-            .ok_or_else(|| Status::unknown("Value must be specified."))?;
-        self.streaming_store.set(key, value);
-        Ok(WriteFulfillment {})
+        let write = self.stage(intent)?;
+        let version = self.apply(vec![write]).pop().unwrap()?;
+        Ok(WriteFulfillment { version })
+    }
+
+    fn delete(&self, intent: DeleteIntent) -> Result<DeleteFulfillment, Status> {
+        let key: Box<str> = intent.key.into();
+        let mut state = self.state.lock().unwrap();
+
+        check_precondition(
+            intent.precondition.as_ref(),
+            self.streaming_store.get(&key).as_ref(),
+            state.versions.get(&key),
+        )?;
+
+        self.remove(&mut state, key)?;
+        Ok(DeleteFulfillment {})
+    }
+
+    /// Supports the following commands:
+    /// - `expire(key, seconds)` removes an existing key once the given number
+    ///   of seconds elapsed, unless it is written or deleted before.
+    /// - `compact()` rewrites the log to only contain the existing keys.
+    fn invoke(&self, intent: InvokeIntent) -> Result<InvokeFulfillment, Status> {
+        let mut args = intent.args.into_iter().map(|arg| arg.value);
+
+        match (intent.command.as_str(), args.next(), args.next(), args.next()) {
+            ("expire", Some(Some(Value::String(key))), Some(Some(seconds)), None) => {
+                let seconds = match seconds {
+                    Value::Int32(seconds) => u64::try_from(seconds).ok(),
+                    Value::Int64(seconds) => u64::try_from(seconds).ok(),
+                    _ => None,
+                }
+                .ok_or_else(|| {
+                    Status::invalid_argument("Seconds must be a non-negative integer.")
+                })?;
+
+                let key: Box<str> = key.into();
+                let mut state = self.state.lock().unwrap();
+                let value = self
+                    .streaming_store
+                    .get(&key)
+                    .ok_or_else(|| Status::not_found(format!("Key '{key}' does not exist.")))?;
+
+                let expires_at = SystemTime::now() + Duration::from_secs(seconds);
+                state.log(|log| log.append_set(&key, &value, Some(expires_at)))?;
+                state.expirations.insert(key, expires_at);
+            }
+            ("compact", None, None, None) => {
+                let mut state = self.state.lock().unwrap();
+                let State { expirations, log, .. } = &mut *state;
+                let log = log
+                    .as_mut()
+                    .ok_or_else(|| Status::failed_precondition("The store is not persisted."))?;
+
+                let entries = self.streaming_store.entries();
+                log.compact(entries.iter().map(|(key, value)| {
+                    (key.as_ref(), value, expirations.get(key.as_ref()).copied())
+                }))
+                .map_err(|e| Status::internal(e.to_string()))?;
+            }
+            (command @ ("expire" | "compact"), ..) => Err(Status::invalid_argument(format!(
+                "Invalid arguments for command '{command}'."
+            )))?,
+            (command, ..) => Err(Status::not_found(format!("No command found for '{command}'.")))?,
+        }
+
+        Ok(InvokeFulfillment { r#return: Some(ValueMessage { value: None }) })
+    }
+
+    /// Applies all writes of the batch at once, or none of them if any write
+    /// of the batch is invalid or its precondition does not hold.
+    fn write_batch(&self, intent: WriteBatchIntent) -> WriteBatchFulfillment {
+        let keys: Vec<_> = intent.writes.iter().map(|write| write.key.clone()).collect();
+        let writes: Vec<_> = intent.writes.into_iter().map(|intent| self.stage(intent)).collect();
+
+        let results = if writes.iter().all(Result::is_ok) {
+            self.apply(writes.into_iter().filter_map(Result::ok).collect())
+        } else {
+            writes
+                .into_iter()
+                .map(|write| write.and(Err(Status::aborted(NOT_APPLIED_MESSAGE))))
+                .collect()
+        };
+
+        WriteBatchFulfillment {
+            entries: keys
+                .iter()
+                .zip(results)
+                .map(|(key, result)| batch_entry(key, result))
+                .collect(),
+        }
+    }
+}
+
+fn batch_entry(key: &str, result: Result<String, Status>) -> write_batch_fulfillment::Entry {
+    use write_batch_fulfillment::{entry::Result, Entry};
+
+    Entry {
+        key: key.to_owned(),
+        result: Some(match result {
+            Ok(version) => Result::Fulfillment(WriteFulfillment { version }),
+            Err(e) => Result::Error(e.message().to_owned()),
+        }),
     }
 }
 
@@ -47,14 +397,27 @@ impl ProviderService for IntentProvider {
         &self,
         request: Request<FulfillRequest>,
     ) -> Result<Response<FulfillResponse>, Status> {
-        let fulfillment = match request
-            .into_inner()
-            .intent
-            .and_then(|i| i.intent)
+        let request = request.into_inner();
+        let intent = request.intent.and_then(|i| i.intent);
+
+        if let Some(transaction) = request.transaction {
+            return self.fulfill_transaction(transaction, intent).map(|f| {
+                Response::new(FulfillResponse {
+                    fulfillment: f.map(|f| FulfillmentMessage { fulfillment: Some(f) }),
+                })
+            });
+        }
+
+        let fulfillment = match intent
             .ok_or_else(|| Status::invalid_argument("Intent must be specified."))?
         {
-            IntentEnum::Read(intent) => Ok(self.streaming_store.read(intent)),
+            IntentEnum::Read(intent) => Ok(self.read(intent)),
             IntentEnum::Write(intent) => self.write(intent).map(FulfillmentEnum::Write),
+            IntentEnum::WriteBatch(intent) => {
+                Ok(FulfillmentEnum::WriteBatch(self.write_batch(intent)))
+            }
+            IntentEnum::Delete(intent) => self.delete(intent).map(FulfillmentEnum::Delete),
+            IntentEnum::Invoke(intent) => self.invoke(intent).map(FulfillmentEnum::Invoke),
             IntentEnum::Subscribe(intent) => self.streaming_store.subscribe(intent),
             IntentEnum::Discover(_intent) => Ok(FulfillmentEnum::Discover(DiscoverFulfillment {
                 services: vec![Service {
@@ -3,11 +3,14 @@
 // SPDX-License-Identifier: MIT
 
 mod intent_provider;
+mod persistence;
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
-use examples_common::intent_brokering;
-use intent_brokering_common::error::Error;
+use examples_common::intent_brokering::{self, registration::Builder};
+use intent_brokering_common::catalog::Catalog;
+use intent_brokering_common::config::env;
+use intent_brokering_common::error::{Error, ResultExt as _};
 use intent_brokering_common::shutdown::RouterExt as _;
 use intent_brokering_proto::{
     provider::provider_service_server::ProviderServiceServer,
@@ -15,27 +18,60 @@ use intent_brokering_proto::{
     streaming::channel_service_server::ChannelServiceServer,
 };
 use tonic::transport::Server;
+use url::Url;
 
 use crate::intent_provider::{IntentProvider, StreamingStore};
 
 intent_brokering::provider::main!(wain);
 
+const EXPIRATION_INTERVAL: Duration = Duration::from_secs(1);
+
 async fn wain() -> Result<(), Error> {
-    let (url, socket_address) = intent_brokering::provider::register(
+    let url: Url = env("KVS_URL")
+        .unwrap_or_else(|| "http://0.0.0.0:50064".to_owned()) // DevSkim: ignore DS137138
+        .parse()
+        .map_err_with("Failed to parse URL.")?;
+
+    let registration = Builder::new(
         "sdv.key-value-store",
         "0.0.1",
+        url,
         "sdv.kvs",
-        [Intent::Read, Intent::Write, Intent::Subscribe, Intent::Discover],
-        "KVS_URL",
-        "http://0.0.0.0:50064", // DevSkim: ignore DS137138
+        [
+            Intent::Read,
+            Intent::Write,
+            Intent::Delete,
+            Intent::Invoke,
+            Intent::Subscribe,
+            Intent::Discover,
+        ],
         ExecutionLocality::Local,
     )
-    .await?;
+    .set_transactional(true)
+    .from_env();
+
+    let socket_address = registration.parse_provider_socket_address()?;
+    let url = registration.announce_url().to_owned();
+    tokio::task::spawn(registration.register());
 
     tracing::info!("Application listening on: {url}");
 
     let streaming_store = Arc::new(StreamingStore::new());
-    let provider = Arc::new(IntentProvider::new(url.clone(), Arc::clone(&streaming_store)));
+    let mut provider = IntentProvider::new(url.clone(), Arc::clone(&streaming_store));
+
+    if let Some(log_path) = env::<String>("KVS_LOG_PATH") {
+        tracing::info!("Persisting store to: {log_path}");
+        provider = provider.with_log(log_path)?;
+    }
+
+    if let Some(catalog_path) = env::<String>("KVS_CATALOG_PATH") {
+        let catalog = std::fs::read_to_string(&catalog_path)
+            .map_err_with(format!("Failed to read '{catalog_path}'."))?;
+        provider = provider.with_catalog(Catalog::parse(&catalog)?);
+    }
+
+    let provider = Arc::new(provider);
+    tokio::task::spawn(Arc::clone(&provider).expire_keys(EXPIRATION_INTERVAL));
 
     Server::builder()
         .add_service(ProviderServiceServer::from_arc(Arc::clone(&provider)))
@@ -0,0 +1,149 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Read as _, Write as _},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use intent_brokering_common::error::{Error, ResultExt as _};
+use intent_brokering_proto::common::{value::Value, ValueMessage};
+use prost::Message;
+
+/// A single change to the store, as it is appended to the log.
+#[derive(Clone, PartialEq, Message)]
+struct Record {
+    #[prost(string, tag = "1")]
+    key: String,
+    /// The new value of the key, or none if the key was removed.
+    #[prost(message, optional, tag = "2")]
+    value: Option<ValueMessage>,
+    /// The time at which the key expires, in milliseconds since the Unix
+    /// epoch, or zero if the key does not expire.
+    #[prost(uint64, tag = "3")]
+    expires_at: u64,
+}
+
+/// An entry of the store as it is restored from the log.
+pub struct Entry {
+    pub value: Value,
+    pub expires_at: Option<SystemTime>,
+}
+
+/// An append-only log of all changes to the store. When the provider starts,
+/// the log is replayed to restore the store. As the log grows with every
+/// change, it can be compacted to hold a single record per existing key.
+pub struct Log {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl Log {
+    /// Opens the log at the given path, creating it if it does not exist, and
+    /// returns it together with the entries restored from it.
+    pub fn open(path: impl Into<PathBuf>) -> Result<(Self, HashMap<Box<str>, Entry>), Error> {
+        let path = path.into();
+        let mut entries = HashMap::new();
+
+        if path.exists() {
+            let mut buffer = vec![];
+            File::open(&path)
+                .and_then(|mut file| file.read_to_end(&mut buffer))
+                .map_err_with("Failed to read the store log.")?;
+
+            let mut buffer = buffer.as_slice();
+            while !buffer.is_empty() {
+                let record = Record::decode_length_delimited(&mut buffer)
+                    .map_err_with("Failed to decode the store log.")?;
+
+                match record.value.and_then(|v| v.value) {
+                    Some(value) => {
+                        let expires_at = from_millis(record.expires_at);
+                        entries.insert(record.key.into(), Entry { value, expires_at });
+                    }
+                    None => {
+                        entries.remove(record.key.as_str());
+                    }
+                }
+            }
+        }
+
+        let writer = Self::writer(&path, OpenOptions::new().create(true).append(true))?;
+        Ok((Self { path, writer }, entries))
+    }
+
+    fn writer(path: &Path, options: &OpenOptions) -> Result<BufWriter<File>, Error> {
+        options.open(path).map(BufWriter::new).map_err_with("Failed to open the store log.")
+    }
+
+    /// Appends the new value of a key to the log.
+    pub fn append_set(
+        &mut self,
+        key: &str,
+        value: &Value,
+        expires_at: Option<SystemTime>,
+    ) -> Result<(), Error> {
+        self.append(&record(key, Some(value), expires_at))
+    }
+
+    /// Appends the removal of a key to the log.
+    pub fn append_remove(&mut self, key: &str) -> Result<(), Error> {
+        self.append(&record(key, None, None))
+    }
+
+    fn append(&mut self, record: &Record) -> Result<(), Error> {
+        self.writer
+            .write_all(&record.encode_length_delimited_to_vec())
+            .and_then(|_| self.writer.flush())
+            .map_err_with("Failed to append to the store log.")
+    }
+
+    /// Rewrites the log such that it only contains the given entries. The
+    /// compacted log is written next to the current one and then replaces it,
+    /// so the current log remains intact if compaction fails.
+    pub fn compact<'a>(
+        &mut self,
+        entries: impl IntoIterator<Item = (&'a str, &'a Value, Option<SystemTime>)>,
+    ) -> Result<(), Error> {
+        let compacted_path = self.path.with_extension("compacting");
+        let mut compacted = Self::writer(
+            &compacted_path,
+            OpenOptions::new().create(true).write(true).truncate(true),
+        )?;
+
+        for (key, value, expires_at) in entries {
+            let record = record(key, Some(value), expires_at);
+            compacted
+                .write_all(&record.encode_length_delimited_to_vec())
+                .map_err_with("Failed to write the compacted log.")?;
+        }
+
+        compacted.flush().map_err_with("Failed to write the compacted log.")?;
+        drop(compacted);
+
+        fs::rename(&compacted_path, &self.path)
+            .map_err_with("Failed to replace the store log with the compacted log.")?;
+        self.writer = Self::writer(&self.path, OpenOptions::new().append(true))?;
+        Ok(())
+    }
+}
+
+fn record(key: &str, value: Option<&Value>, expires_at: Option<SystemTime>) -> Record {
+    Record {
+        key: key.to_owned(),
+        value: value.map(|v| ValueMessage { value: Some(v.clone()) }),
+        expires_at: expires_at.map_or(0, to_millis),
+    }
+}
+
+fn to_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64).max(1)
+}
+
+fn from_millis(millis: u64) -> Option<SystemTime> {
+    (millis > 0).then(|| UNIX_EPOCH + Duration::from_millis(millis))
+}
@@ -0,0 +1,85 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::{collections::HashSet, fs};
+
+use intent_brokering_common::error::{Error, ResultExt as _};
+use serde::Deserialize;
+
+/// A device, which is mapped into a namespace. The topics of the device are
+/// nested below its topic.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Device {
+    pub namespace: String,
+    pub topic: String,
+    /// Whether the properties of the device can be written.
+    #[serde(default)]
+    pub writable: bool,
+    /// The commands the device accepts through the `Invoke` intent.
+    #[serde(default)]
+    pub commands: HashSet<String>,
+}
+
+/// The mapping of devices into namespaces.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub devices: Vec<Device>,
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let config = fs::read_to_string(path).map_err_with(format!("Failed to read '{path}'."))?;
+        Self::parse(&config)
+    }
+
+    pub fn parse(config: &str) -> Result<Self, Error> {
+        let config: Self =
+            serde_json::from_str(config).map_err_with("Failed to parse the device mapping.")?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let mut namespaces = HashSet::new();
+
+        for device in self.devices.iter() {
+            if !namespaces.insert(device.namespace.as_str()) {
+                return Err(Error::new(format!(
+                    "Namespace '{}' is mapped more than once.",
+                    device.namespace
+                )));
+            }
+
+            if !is_topic_name(&device.topic) {
+                return Err(Error::new(format!(
+                    "Topic '{}' of namespace '{}' is not a valid topic name.",
+                    device.topic, device.namespace
+                )));
+            }
+
+            // Messages are routed to the device whose topic they are nested
+            // below, which must hence be unambiguous.
+            if let Some(other) = self.devices.iter().find(|other| {
+                other.namespace != device.namespace
+                    && (other.topic == device.topic
+                        || other.topic.starts_with(&format!("{}/", device.topic)))
+            }) {
+                return Err(Error::new(format!(
+                    "Topic '{}' of namespace '{}' overlaps with topic '{}'.",
+                    device.topic, device.namespace, other.topic
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks whether a topic name can be published to, i.e. it is not empty and
+/// does not contain wildcards.
+pub fn is_topic_name(topic: &str) -> bool {
+    !topic.is_empty() && !topic.contains(['#', '+', '\0'])
+}
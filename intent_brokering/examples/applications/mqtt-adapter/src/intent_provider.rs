@@ -0,0 +1,252 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use examples_common::intent_brokering::{self, streaming::ProtoExt as _};
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use tokio::{sync::oneshot, time::timeout};
+use tonic::{Request, Response, Status};
+use url::Url;
+
+use intent_brokering_proto::{
+    common::{
+        discover_fulfillment::Service, value::Value, DiscoverFulfillment, FulfillmentEnum,
+        FulfillmentMessage, IntentEnum, InvokeFulfillment, InvokeIntent, ReadIntent,
+        SubscribeIntent, ValueMessage, WriteFulfillment, WriteIntent,
+    },
+    provider::{provider_service_server::ProviderService, FulfillRequest, FulfillResponse},
+};
+
+use crate::{
+    config::{is_topic_name, Device},
+    json,
+    mqtt::{Client, Publish},
+};
+
+pub type StreamingStore = intent_brokering::streaming::StreamingStore<Value>;
+
+const INVOKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The response of a device to a command.
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum CommandResponse {
+    Result(JsonValue),
+    Error(String),
+}
+
+/// Maps the intents of a namespace onto the topics of a device:
+///
+/// - `Read` and `Subscribe` serve the last values published by the device to
+///   `{topic}/state/{key}`.
+/// - `Write` publishes the value to `{topic}/set/{key}`.
+/// - `Invoke` publishes `{ "id": ..., "args": [...] }` to
+///   `{topic}/invoke/{command}` and awaits the response of the device on
+///   `{topic}/response/{id}`.
+pub struct IntentProvider {
+    url: Url,
+    device: Device,
+    client: Arc<Client>,
+    streaming_store: StreamingStore,
+    next_id: AtomicU32,
+    pending: Mutex<HashMap<u32, oneshot::Sender<CommandResponse>>>,
+}
+
+impl IntentProvider {
+    pub fn new(url: Url, device: Device, client: Arc<Client>) -> Self {
+        Self {
+            url,
+            device,
+            client,
+            streaming_store: StreamingStore::new(),
+            next_id: AtomicU32::new(1),
+            pending: Default::default(),
+        }
+    }
+
+    pub fn streaming_store(&self) -> &StreamingStore {
+        &self.streaming_store
+    }
+
+    /// The topic filters the device publishes its state and responses to.
+    pub fn topic_filters(&self) -> Vec<String> {
+        let topic = &self.device.topic;
+        let mut filters = vec![format!("{topic}/state/#")];
+        if !self.device.commands.is_empty() {
+            filters.push(format!("{topic}/response/+"));
+        }
+        filters
+    }
+
+    /// Handles a message if it was published to a topic of the device.
+    /// Returns whether the message was handled.
+    pub fn handle(&self, publish: &Publish) -> bool {
+        let Some(topic) = publish
+            .topic
+            .strip_prefix(self.device.topic.as_str())
+            .and_then(|topic| topic.strip_prefix('/'))
+        else {
+            return false;
+        };
+
+        if let Some(key) = topic.strip_prefix("state/") {
+            // Clearing a retained message publishes an empty payload.
+            if publish.payload.is_empty() {
+                self.streaming_store.remove(&key.into());
+                return true;
+            }
+
+            match serde_json::from_slice(&publish.payload) {
+                Ok(value) => self.streaming_store.set(key.into(), json::decode(value)),
+                Err(e) => tracing::warn!("Dropping invalid state of '{}': {e}", publish.topic),
+            }
+        } else if let Some(id) = topic.strip_prefix("response/") {
+            let response = serde_json::from_slice(&publish.payload);
+            let sender = id.parse().ok().and_then(|id| self.pending.lock().unwrap().remove(&id));
+
+            match (sender, response) {
+                (Some(sender), Ok(response)) => _ = sender.send(response),
+                (Some(_), Err(e)) => {
+                    tracing::warn!("Dropping invalid response on '{}': {e}", publish.topic)
+                }
+                (None, _) => {
+                    tracing::debug!("Dropping unexpected response on '{}'.", publish.topic)
+                }
+            }
+        }
+
+        true
+    }
+
+    fn validate_key(key: &str) -> Result<(), Status> {
+        if is_topic_name(key) {
+            Ok(())
+        } else {
+            Err(Status::invalid_argument(format!("'{key}' is not a valid key.")))
+        }
+    }
+
+    fn read(&self, intent: ReadIntent) -> Result<FulfillmentEnum, Status> {
+        if self.streaming_store.get(&intent.key.as_str().into()).is_none() {
+            return Err(Status::not_found(format!("Key '{}' was not published.", intent.key)));
+        }
+
+        Ok(self.streaming_store.read(intent))
+    }
+
+    async fn write(&self, intent: WriteIntent) -> Result<WriteFulfillment, Status> {
+        if !self.device.writable {
+            return Err(Status::failed_precondition("Device is not writable."));
+        }
+
+        if intent.precondition.is_some() {
+            return Err(Status::unimplemented("Conditional writes are not supported."));
+        }
+
+        Self::validate_key(&intent.key)?;
+        let value = json::encode(intent.value.and_then(|v| v.value))?;
+
+        self.client
+            .publish(
+                &format!("{}/set/{}", self.device.topic, intent.key),
+                value.to_string().as_bytes(),
+            )
+            .await
+            .map_err(|e| Status::unavailable(e.to_string()))?;
+
+        Ok(WriteFulfillment::default())
+    }
+
+    async fn invoke(&self, intent: InvokeIntent) -> Result<InvokeFulfillment, Status> {
+        if !self.device.commands.contains(&intent.command) {
+            return Err(Status::not_found(format!("Command '{}' does not exist.", intent.command)));
+        }
+
+        let args = intent
+            .args
+            .into_iter()
+            .map(|arg| json::encode(arg.value))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, sender);
+
+        let result = async {
+            self.client
+                .publish(
+                    &format!("{}/invoke/{}", self.device.topic, intent.command),
+                    json!({ "id": id, "args": args }).to_string().as_bytes(),
+                )
+                .await
+                .map_err(|e| Status::unavailable(e.to_string()))?;
+
+            match timeout(INVOKE_TIMEOUT, receiver).await {
+                Ok(Ok(CommandResponse::Result(value))) => Ok(InvokeFulfillment {
+                    r#return: Some(ValueMessage { value: Some(json::decode(value)) }),
+                }),
+                Ok(Ok(CommandResponse::Error(message))) => Err(Status::aborted(message)),
+                Ok(Err(_)) => Err(Status::internal("Response was dropped.")),
+                Err(_) => Err(Status::deadline_exceeded("Device did not respond in time.")),
+            }
+        }
+        .await;
+
+        self.pending.lock().unwrap().remove(&id);
+        result
+    }
+
+    fn subscribe(&self, intent: SubscribeIntent) -> Result<FulfillmentEnum, Status> {
+        for source in intent.sources.iter() {
+            Self::validate_key(source)?;
+        }
+
+        self.streaming_store.subscribe(intent)
+    }
+}
+
+#[async_trait]
+impl ProviderService for IntentProvider {
+    async fn fulfill(
+        &self,
+        request: Request<FulfillRequest>,
+    ) -> Result<Response<FulfillResponse>, Status> {
+        let fulfillment = match request
+            .into_inner()
+            .intent
+            .and_then(|i| i.intent)
+            .ok_or_else(|| Status::invalid_argument("Intent must be specified."))?
+        {
+            IntentEnum::Read(intent) => self.read(intent),
+            IntentEnum::Write(intent) => self.write(intent).await.map(FulfillmentEnum::Write),
+            IntentEnum::Invoke(intent) => self.invoke(intent).await.map(FulfillmentEnum::Invoke),
+            IntentEnum::Subscribe(intent) => self.subscribe(intent),
+            IntentEnum::Discover(_intent) => Ok(FulfillmentEnum::Discover(DiscoverFulfillment {
+                services: vec![Service {
+                    url: self.url.to_string(),
+                    schema_kind: "grpc+proto".to_owned(),
+                    schema_reference: "intent_brokering.streaming.v1".to_owned(),
+                    metadata: HashMap::new(),
+                }],
+            })),
+            _ => Err(Status::unknown("Unsupported or unknown intent."))?,
+        };
+
+        fulfillment.map(|f| {
+            Response::new(FulfillResponse {
+                fulfillment: Some(FulfillmentMessage { fulfillment: Some(f) }),
+            })
+        })
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Conversion between values and the JSON payloads exchanged with devices.
+//! JSON integers are converted to `int32` if they fit into 32 bits and to
+//! `int64` otherwise, all other numbers to `float64`.
+
+use intent_brokering_proto::common::{value::Value, List, Map, NullValue, ValueMessage};
+use serde_json::{Number, Value as JsonValue};
+use tonic::Status;
+
+pub fn encode(value: Option<Value>) -> Result<JsonValue, Status> {
+    let float = |value: f64| {
+        Number::from_f64(value)
+            .map(JsonValue::Number)
+            .ok_or_else(|| Status::invalid_argument("NaN and infinity cannot be sent."))
+    };
+
+    Ok(match value {
+        None | Some(Value::Null(_)) => JsonValue::Null,
+        Some(Value::Bool(value)) => value.into(),
+        Some(Value::Int32(value)) => value.into(),
+        Some(Value::Int64(value)) => value.into(),
+        Some(Value::Float32(value)) => float(value.into())?,
+        Some(Value::Float64(value)) => float(value)?,
+        Some(Value::String(value)) => value.into(),
+        Some(Value::List(List { value })) => {
+            value.into_iter().map(|v| encode(v.value)).collect::<Result<Vec<_>, _>>()?.into()
+        }
+        Some(Value::Map(Map { map })) => map
+            .into_iter()
+            .map(|(k, v)| encode(v.value).map(|v| (k, v)))
+            .collect::<Result<serde_json::Map<_, _>, _>>()?
+            .into(),
+        Some(Value::Timestamp(_) | Value::Blob(_) | Value::Any(_)) => {
+            return Err(Status::invalid_argument("Value cannot be represented as JSON."))
+        }
+    })
+}
+
+pub fn decode(value: JsonValue) -> Value {
+    match value {
+        JsonValue::Null => Value::Null(NullValue::Unspecified as i32),
+        JsonValue::Bool(value) => Value::Bool(value),
+        JsonValue::Number(number) => match number.as_i64() {
+            Some(value) => match i32::try_from(value) {
+                Ok(value) => Value::Int32(value),
+                Err(_) => Value::Int64(value),
+            },
+            None => Value::Float64(number.as_f64().unwrap_or_default()),
+        },
+        JsonValue::String(value) => Value::String(value),
+        JsonValue::Array(values) => Value::List(List {
+            value: values.into_iter().map(|v| ValueMessage { value: Some(decode(v)) }).collect(),
+        }),
+        JsonValue::Object(map) => Value::Map(Map {
+            map: map
+                .into_iter()
+                .map(|(k, v)| (k, ValueMessage { value: Some(decode(v)) }))
+                .collect(),
+        }),
+    }
+}
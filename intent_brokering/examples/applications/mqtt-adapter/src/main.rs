@@ -0,0 +1,124 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+mod config;
+mod intent_provider;
+mod json;
+mod mqtt;
+
+use std::{sync::Arc, time::Duration};
+
+use examples_common::intent_brokering::{self, registration::Builder};
+use futures::future::try_join_all;
+use intent_brokering_common::config::env;
+use intent_brokering_common::error::{Error, ResultExt as _};
+use intent_brokering_common::shutdown::RouterExt as _;
+use intent_brokering_proto::{
+    provider::provider_service_server::ProviderServiceServer,
+    runtime::{intent_registration::Intent, intent_service_registration::ExecutionLocality},
+    streaming::channel_service_server::ChannelServiceServer,
+};
+use tonic::transport::Server;
+use url::Url;
+
+use crate::config::{Config, Device};
+use crate::intent_provider::IntentProvider;
+use crate::mqtt::{Client, Options};
+
+intent_brokering::provider::main!(wain);
+
+/// An example mapping, used unless `MQTT_ADAPTER_CONFIG` points to a
+/// different mapping.
+const DEFAULT_CONFIG: &str = include_str!("../devices.json");
+
+fn intents(device: &Device) -> Vec<Intent> {
+    [
+        (Intent::Discover, true),
+        (Intent::Read, true),
+        (Intent::Subscribe, true),
+        (Intent::Write, device.writable),
+        (Intent::Invoke, !device.commands.is_empty()),
+    ]
+    .into_iter()
+    .filter_map(|(intent, supported)| supported.then_some(intent))
+    .collect()
+}
+
+async fn wain() -> Result<(), Error> {
+    let url: Url = env("MQTT_ADAPTER_URL")
+        .unwrap_or_else(|| "http://0.0.0.0:50069".to_owned()) // DevSkim: ignore DS137138
+        .parse()
+        .map_err_with("Failed to parse URL.")?;
+
+    let config = match env::<String>("MQTT_ADAPTER_CONFIG") {
+        Some(path) => Config::load(&path)?,
+        None => Config::parse(DEFAULT_CONFIG)?,
+    };
+
+    let broker_address: String =
+        env("MQTT_BROKER_ADDRESS").unwrap_or_else(|| "localhost:1883".to_owned());
+    let options = Options {
+        client_id: env("MQTT_CLIENT_ID").unwrap_or_else(|| "chariott-mqtt-adapter".to_owned()),
+        username: env("MQTT_USERNAME"),
+        password: env("MQTT_PASSWORD"),
+        keep_alive: Duration::from_secs(env("MQTT_KEEP_ALIVE_SECS").unwrap_or(30)),
+    };
+
+    let (client, mut messages) = Client::connect(&broker_address, options).await?;
+    tracing::info!("Connected to MQTT broker at {broker_address}");
+
+    let base_port = url.port().ok_or_else(|| Error::new("URL must specify a port."))?;
+    let mut providers = vec![];
+    let mut servers = vec![];
+
+    // Providers are not told the namespace an intent is addressed to, hence
+    // each device is served on its own port, starting with the port of the
+    // adapter URL.
+    for (index, device) in config.devices.into_iter().enumerate() {
+        let mut url = url.clone();
+        url.set_port(Some(base_port + index as u16))
+            .map_err(|_| Error::new("Failed to set port of URL."))?;
+
+        let registration = Builder::new(
+            &format!("sdv.mqtt-adapter.{}", device.namespace),
+            "0.0.1",
+            url,
+            &device.namespace,
+            intents(&device),
+            ExecutionLocality::Local,
+        )
+        .from_env();
+
+        let socket_address = registration.parse_provider_socket_address()?;
+        let url = registration.announce_url().to_owned();
+
+        tracing::info!("Mapping '{}' to MQTT topic '{}' on: {url}", device.namespace, device.topic);
+
+        let provider = Arc::new(IntentProvider::new(url, device, Arc::clone(&client)));
+        client.subscribe(&provider.topic_filters()).await?;
+
+        tokio::task::spawn(registration.register());
+
+        servers.push(
+            Server::builder()
+                .add_service(ProviderServiceServer::from_arc(Arc::clone(&provider)))
+                .add_service(ChannelServiceServer::new(provider.streaming_store().ess().clone()))
+                .serve_with_ctrl_c_shutdown(socket_address),
+        );
+        providers.push(provider);
+    }
+
+    let dispatch = async move {
+        while let Some(message) = messages.recv().await {
+            if !providers.iter().any(|provider| provider.handle(&message)) {
+                tracing::debug!("Dropping message on unmapped topic '{}'.", message.topic);
+            }
+        }
+    };
+
+    tokio::select! {
+        result = try_join_all(servers) => result.map(|_| ()),
+        _ = dispatch => Err(Error::new("Connection to the MQTT broker was closed.")),
+    }
+}
@@ -0,0 +1,267 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! A minimal MQTT 3.1.1 client, which publishes and subscribes with QoS 0
+//! only, i.e. messages are delivered at most once.
+
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use intent_brokering_common::error::{Error, ResultExt as _};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt as _, AsyncWriteExt as _},
+    net::{tcp::OwnedWriteHalf, TcpStream},
+    spawn,
+    sync::{mpsc, Mutex},
+    time::interval,
+};
+
+const PROTOCOL_NAME: &str = "MQTT";
+const PROTOCOL_LEVEL: u8 = 4;
+const MAX_PACKET_SIZE: usize = 1024 * 1024;
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const SUBSCRIBE: u8 = 0x82;
+const SUBACK: u8 = 0x90;
+const PINGREQ: u8 = 0xC0;
+const PINGRESP: u8 = 0xD0;
+
+const CLEAN_SESSION: u8 = 0x02;
+const PASSWORD: u8 = 0x40;
+const USERNAME: u8 = 0x80;
+const SUBACK_FAILURE: u8 = 0x80;
+
+pub struct Options {
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub keep_alive: Duration,
+}
+
+/// A message received from the broker.
+#[derive(Clone, Debug)]
+pub struct Publish {
+    pub topic: String,
+    pub payload: Vec<u8>,
+}
+
+pub struct Client {
+    writer: Mutex<OwnedWriteHalf>,
+    packet_id: AtomicU16,
+}
+
+impl Client {
+    /// Connects to a broker, returning the client and the receiver of the
+    /// messages published to its subscriptions. The receiver yields `None`
+    /// once the connection is closed.
+    pub async fn connect(
+        address: &str,
+        options: Options,
+    ) -> Result<(Arc<Self>, mpsc::Receiver<Publish>), Error> {
+        let stream = TcpStream::connect(address)
+            .await
+            .map_err_with(format!("Failed to connect to MQTT broker at '{address}'."))?;
+        let (mut reader, mut writer) = stream.into_split();
+
+        let keep_alive = u16::try_from(options.keep_alive.as_secs()).unwrap_or(u16::MAX);
+        let mut flags = CLEAN_SESSION;
+        let mut payload = vec![];
+        put_string(&mut payload, &options.client_id);
+        if let Some(username) = options.username.as_deref() {
+            flags |= USERNAME;
+            put_string(&mut payload, username);
+        }
+        if let Some(password) = options.password.as_deref() {
+            flags |= PASSWORD;
+            put_string(&mut payload, password);
+        }
+
+        let mut body = vec![];
+        put_string(&mut body, PROTOCOL_NAME);
+        body.extend([PROTOCOL_LEVEL, flags]);
+        body.extend(keep_alive.to_be_bytes());
+        body.extend(payload);
+
+        writer.write_all(&packet(CONNECT, &body)).await.map_err_with("Failed to send CONNECT.")?;
+
+        match read_packet(&mut reader).await.map_err_with("Failed to receive CONNACK.")? {
+            (CONNACK, body) if body.len() == 2 && body[1] == 0 => {}
+            (CONNACK, body) if body.len() == 2 => {
+                return Err(Error::new(format!(
+                    "MQTT broker refused connection: {}",
+                    match body[1] {
+                        1 => "unacceptable protocol version",
+                        2 => "identifier rejected",
+                        3 => "server unavailable",
+                        4 => "bad user name or password",
+                        5 => "not authorized",
+                        _ => "unknown reason",
+                    }
+                )))
+            }
+            _ => return Err(Error::new("Expected CONNACK from MQTT broker.")),
+        }
+
+        let (sender, receiver) = mpsc::channel(64);
+        spawn(receive(reader, sender));
+
+        let client = Arc::new(Self { writer: Mutex::new(writer), packet_id: AtomicU16::new(1) });
+
+        if keep_alive > 0 {
+            let client = Arc::clone(&client);
+            spawn(async move {
+                let mut interval = interval(options.keep_alive / 2);
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = client.send(&packet(PINGREQ, &[])).await {
+                        tracing::warn!("Failed to send PINGREQ: {e}");
+                        break;
+                    }
+                }
+            });
+        }
+
+        Ok((client, receiver))
+    }
+
+    async fn send(&self, packet: &[u8]) -> io::Result<()> {
+        self.writer.lock().await.write_all(packet).await
+    }
+
+    /// Returns the next packet identifier, skipping zero which is not a
+    /// valid identifier.
+    fn next_packet_id(&self) -> u16 {
+        loop {
+            let packet_id = self.packet_id.fetch_add(1, Ordering::Relaxed);
+            if packet_id != 0 {
+                return packet_id;
+            }
+        }
+    }
+
+    /// Subscribes to topic filters. Failures reported by the broker are
+    /// logged, as acknowledgements are received asynchronously.
+    pub async fn subscribe(&self, filters: &[String]) -> Result<(), Error> {
+        let mut body = vec![];
+        body.extend(self.next_packet_id().to_be_bytes());
+        for filter in filters {
+            put_string(&mut body, filter);
+            body.push(0);
+        }
+
+        self.send(&packet(SUBSCRIBE, &body)).await.map_err_with("Failed to send SUBSCRIBE.")
+    }
+
+    pub async fn publish(&self, topic: &str, payload: &[u8]) -> Result<(), Error> {
+        let mut body = vec![];
+        put_string(&mut body, topic);
+        body.extend(payload);
+
+        self.send(&packet(PUBLISH, &body)).await.map_err_with("Failed to send PUBLISH.")
+    }
+}
+
+async fn receive(mut reader: impl AsyncRead + Unpin, sender: mpsc::Sender<Publish>) {
+    loop {
+        let (header, body) = match read_packet(&mut reader).await {
+            Ok(packet) => packet,
+            Err(e) => {
+                tracing::warn!("Connection to MQTT broker closed: {e}");
+                break;
+            }
+        };
+
+        match header & 0xF0 {
+            PUBLISH => match parse_publish(header, &body) {
+                Some(publish) => {
+                    if sender.send(publish).await.is_err() {
+                        break;
+                    }
+                }
+                None => tracing::warn!("Dropping malformed PUBLISH."),
+            },
+            SUBACK if body[2..].contains(&SUBACK_FAILURE) => {
+                tracing::warn!("MQTT broker rejected a subscription.")
+            }
+            SUBACK | PINGRESP => {}
+            header => tracing::debug!("Ignoring MQTT packet of type {:#04x}.", header),
+        }
+    }
+}
+
+fn parse_publish(header: u8, body: &[u8]) -> Option<Publish> {
+    let [high, low, rest @ ..] = body else {
+        return None;
+    };
+    let length = usize::from(u16::from_be_bytes([*high, *low]));
+    let topic = rest.get(..length)?;
+    let mut payload = &rest[length..];
+
+    // Subscriptions are made with QoS 0, such that the broker delivers all
+    // messages with QoS 0. Should it not, the packet identifier is skipped.
+    if (header >> 1) & 0x03 != 0 {
+        payload = payload.get(2..)?;
+    }
+
+    Some(Publish { topic: String::from_utf8(topic.to_vec()).ok()?, payload: payload.to_vec() })
+}
+
+async fn read_packet(reader: &mut (impl AsyncRead + Unpin)) -> io::Result<(u8, Vec<u8>)> {
+    let header = reader.read_u8().await?;
+
+    let mut length = 0usize;
+    for i in 0..4 {
+        let byte = reader.read_u8().await?;
+        length |= usize::from(byte & 0x7F) << (7 * i);
+        if byte & 0x80 == 0 {
+            break;
+        } else if i == 3 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Malformed remaining length."));
+        }
+    }
+
+    if length > MAX_PACKET_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Packet is too big."));
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+
+    // SUBACK packets contain at least the packet identifier.
+    if header & 0xF0 == SUBACK && body.len() < 2 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Malformed SUBACK."));
+    }
+
+    Ok((header, body))
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    let mut length = body.len();
+    loop {
+        let byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            packet.push(byte | 0x80);
+        } else {
+            packet.push(byte);
+            break;
+        }
+    }
+    packet.extend(body);
+    packet
+}
+
+fn put_string(buffer: &mut Vec<u8>, value: &str) {
+    buffer.extend((value.len() as u16).to_be_bytes());
+    buffer.extend(value.as_bytes());
+}
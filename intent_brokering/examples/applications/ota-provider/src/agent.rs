@@ -0,0 +1,130 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::fmt;
+
+use intent_brokering_common::error::Error;
+use intent_brokering_proto::common::value::Value;
+
+pub const STATUS: &str = "Update.Status";
+pub const PROGRESS: &str = "Update.Progress";
+pub const INSTALLED_VERSION: &str = "Update.InstalledVersion";
+pub const AVAILABLE_VERSION: &str = "Update.AvailableVersion";
+
+/// The step of the update workflow the agent is in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum State {
+    /// No update is known, or the update server was not checked yet.
+    Idle,
+    Available(String),
+    /// Downloading a version, with the percentage downloaded so far.
+    Downloading(String, u8),
+    Downloaded(String),
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            State::Idle => "idle",
+            State::Available(_) => "available",
+            State::Downloading(..) => "downloading",
+            State::Downloaded(_) => "downloaded",
+        })
+    }
+}
+
+/// A stand-in for the OTA agent of a vehicle, which checks an update server
+/// for a new version of the vehicle software, downloads it and applies it.
+/// The update server offers a single version, and downloads progress when
+/// advanced by the caller instead of over a network.
+pub struct Agent {
+    installed: String,
+    offered: String,
+    state: State,
+}
+
+impl Agent {
+    pub fn new(installed: impl Into<String>, offered: impl Into<String>) -> Self {
+        Self { installed: installed.into(), offered: offered.into(), state: State::Idle }
+    }
+
+    /// Checks the update server, returning the version which can be
+    /// downloaded, if it is not installed already.
+    pub fn check(&mut self) -> Option<&str> {
+        if self.state == State::Idle && self.offered != self.installed {
+            self.state = State::Available(self.offered.clone());
+        }
+
+        self.available()
+    }
+
+    /// Starts downloading the available version, returning the version.
+    pub fn download(&mut self) -> Result<String, Error> {
+        match &self.state {
+            State::Available(version) => {
+                let version = version.clone();
+                self.state = State::Downloading(version.clone(), 0);
+                Ok(version)
+            }
+            state => Err(Error::new(format!("Cannot download an update while {state}."))),
+        }
+    }
+
+    /// Advances the download by a percentage, returning whether the download
+    /// is still in progress.
+    pub fn advance(&mut self, percent: u8) -> bool {
+        let State::Downloading(version, progress) = &mut self.state else {
+            return false;
+        };
+
+        *progress = progress.saturating_add(percent).min(100);
+        if *progress == 100 {
+            self.state = State::Downloaded(std::mem::take(version));
+            return false;
+        }
+
+        true
+    }
+
+    /// Applies the downloaded version, returning the version now installed.
+    pub fn apply(&mut self) -> Result<&str, Error> {
+        match std::mem::replace(&mut self.state, State::Idle) {
+            State::Downloaded(version) => {
+                self.installed = version;
+                Ok(self.installed.as_str())
+            }
+            state => {
+                let error = Error::new(format!("Cannot apply an update while {state}."));
+                self.state = state;
+                Err(error)
+            }
+        }
+    }
+
+    fn available(&self) -> Option<&str> {
+        match &self.state {
+            State::Idle => None,
+            State::Available(version)
+            | State::Downloading(version, _)
+            | State::Downloaded(version) => Some(version),
+        }
+    }
+
+    /// Returns the state of the update workflow as signals.
+    pub fn signals(&self) -> impl Iterator<Item = (&'static str, Value)> {
+        let progress = match self.state {
+            State::Idle | State::Available(_) => 0,
+            State::Downloading(_, progress) => progress,
+            State::Downloaded(_) => 100,
+        };
+
+        [
+            (STATUS, Value::String(self.state.to_string())),
+            (PROGRESS, Value::Int32(progress.into())),
+            (INSTALLED_VERSION, Value::String(self.installed.clone())),
+            (AVAILABLE_VERSION, Value::String(self.available().unwrap_or_default().to_owned())),
+        ]
+        .into_iter()
+    }
+}
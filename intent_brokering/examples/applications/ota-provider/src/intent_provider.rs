@@ -0,0 +1,155 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use examples_common::intent_brokering::{self, streaming::ProtoExt as _};
+use tokio::time::sleep;
+use tonic::{Request, Response, Status};
+use url::Url;
+
+use intent_brokering_proto::{
+    common::{
+        discover_fulfillment::Service, value::Value, DiscoverFulfillment, FulfillmentEnum,
+        FulfillmentMessage, IntentEnum, InvokeFulfillment, InvokeIntent, ValueMessage,
+    },
+    provider::{provider_service_server::ProviderService, FulfillRequest, FulfillResponse},
+};
+
+use crate::agent::Agent;
+
+pub type StreamingStore = intent_brokering::streaming::StreamingStore<Value>;
+
+/// The percentage by which a download advances with each step.
+const DOWNLOAD_STEP: u8 = 10;
+
+/// Bridges the update workflow of the OTA agent to intents, and publishes the
+/// state of the workflow, e.g. the progress of a download, as events.
+pub struct IntentProvider {
+    url: Url,
+    agent: Arc<Mutex<Agent>>,
+    download_duration: Duration,
+    streaming_store: Arc<StreamingStore>,
+}
+
+impl IntentProvider {
+    pub fn new(
+        url: Url,
+        agent: Arc<Mutex<Agent>>,
+        download_duration: Duration,
+        streaming_store: Arc<StreamingStore>,
+    ) -> Self {
+        let provider = Self { url, agent, download_duration, streaming_store };
+        provider.publish(&provider.agent.lock().unwrap());
+        provider
+    }
+
+    fn publish(&self, agent: &Agent) {
+        publish(&self.streaming_store, agent);
+    }
+
+    /// Supports the following commands:
+    /// - `check()` checks for an update, returning the available version, or
+    ///   an empty string if the installed version is up to date.
+    /// - `download()` starts downloading the available version in the
+    ///   background, returning the version.
+    /// - `apply()` applies the downloaded version, returning the version now
+    ///   installed.
+    fn invoke(&self, intent: InvokeIntent) -> Result<InvokeFulfillment, Status> {
+        if !intent.args.is_empty() {
+            return Err(Status::invalid_argument(format!(
+                "Command '{}' does not take arguments.",
+                intent.command
+            )));
+        }
+
+        let mut agent = self.agent.lock().unwrap();
+        let result = match intent.command.as_str() {
+            "check" => agent.check().unwrap_or_default().to_owned(),
+            "download" => {
+                let version =
+                    agent.download().map_err(|e| Status::failed_precondition(e.to_string()))?;
+                tokio::spawn(download(
+                    Arc::clone(&self.agent),
+                    self.download_duration,
+                    Arc::clone(&self.streaming_store),
+                ));
+                version
+            }
+            "apply" => {
+                agent.apply().map_err(|e| Status::failed_precondition(e.to_string()))?.to_owned()
+            }
+            command => Err(Status::not_found(format!("No command found for '{command}'.")))?,
+        };
+
+        self.publish(&agent);
+
+        Ok(InvokeFulfillment {
+            r#return: Some(ValueMessage { value: Some(Value::String(result)) }),
+        })
+    }
+}
+
+fn publish(streaming_store: &StreamingStore, agent: &Agent) {
+    streaming_store.set_many(agent.signals().map(|(key, value)| (key.into(), value)));
+}
+
+/// Advances a download in steps spread over its duration, publishing the
+/// progress after each step.
+async fn download(
+    agent: Arc<Mutex<Agent>>,
+    duration: Duration,
+    streaming_store: Arc<StreamingStore>,
+) {
+    let step = duration / (100 / DOWNLOAD_STEP as u32);
+
+    loop {
+        sleep(step).await;
+        let mut agent = agent.lock().unwrap();
+        let downloading = agent.advance(DOWNLOAD_STEP);
+        publish(&streaming_store, &agent);
+        if !downloading {
+            break;
+        }
+    }
+}
+
+#[async_trait]
+impl ProviderService for IntentProvider {
+    async fn fulfill(
+        &self,
+        request: Request<FulfillRequest>,
+    ) -> Result<Response<FulfillResponse>, Status> {
+        let fulfillment = match request
+            .into_inner()
+            .intent
+            .and_then(|i| i.intent)
+            .ok_or_else(|| Status::invalid_argument("Intent must be specified."))?
+        {
+            IntentEnum::Read(intent) => Ok(self.streaming_store.read(intent)),
+            IntentEnum::Subscribe(intent) => self.streaming_store.subscribe(intent),
+            IntentEnum::Invoke(intent) => self.invoke(intent).map(FulfillmentEnum::Invoke),
+            IntentEnum::Discover(_intent) => Ok(FulfillmentEnum::Discover(DiscoverFulfillment {
+                services: vec![Service {
+                    url: self.url.to_string(),
+                    schema_kind: "grpc+proto".to_owned(),
+                    schema_reference: "intent_brokering.streaming.v1".to_owned(),
+                    metadata: HashMap::new(),
+                }],
+            })),
+            _ => Err(Status::unknown("Unsupported or unknown intent."))?,
+        };
+
+        fulfillment.map(|f| {
+            Response::new(FulfillResponse {
+                fulfillment: Some(FulfillmentMessage { fulfillment: Some(f) }),
+            })
+        })
+    }
+}
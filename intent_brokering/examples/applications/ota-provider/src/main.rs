@@ -0,0 +1,77 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+mod agent;
+mod intent_provider;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use examples_common::intent_brokering::{self, registration::Builder};
+use intent_brokering_common::config::env;
+use intent_brokering_common::error::{Error, ResultExt as _};
+use intent_brokering_common::shutdown::RouterExt as _;
+use intent_brokering_proto::{
+    provider::provider_service_server::ProviderServiceServer,
+    runtime::{intent_registration::Intent, intent_service_registration::ExecutionLocality},
+    streaming::channel_service_server::ChannelServiceServer,
+};
+use tonic::transport::Server;
+use url::Url;
+
+use crate::agent::Agent;
+use crate::intent_provider::{IntentProvider, StreamingStore};
+
+intent_brokering::provider::main!(wain);
+
+const NAMESPACE: &str = "sdv.update";
+
+async fn wain() -> Result<(), Error> {
+    let url: Url = env("OTA_PROVIDER_URL")
+        .unwrap_or_else(|| "http://0.0.0.0:50073".to_owned()) // DevSkim: ignore DS137138
+        .parse()
+        .map_err_with("Failed to parse URL.")?;
+
+    let installed: String = env("OTA_INSTALLED_VERSION").unwrap_or_else(|| "1.0.0".to_owned());
+    let offered: String = env("OTA_OFFERED_VERSION").unwrap_or_else(|| "1.1.0".to_owned());
+    let download_secs: u64 = env("OTA_DOWNLOAD_SECS").unwrap_or(10);
+    if download_secs == 0 {
+        return Err(Error::new("Download duration must be positive."));
+    }
+
+    let registration = Builder::new(
+        "sdv.ota-provider",
+        "0.0.1",
+        url,
+        NAMESPACE,
+        [Intent::Read, Intent::Subscribe, Intent::Invoke, Intent::Discover],
+        ExecutionLocality::Local,
+    )
+    .from_env();
+
+    let socket_address = registration.parse_provider_socket_address()?;
+    let url = registration.announce_url().to_owned();
+    tokio::task::spawn(registration.register());
+
+    tracing::info!(
+        "Application listening on: {url}, updating version {installed} in '{NAMESPACE}'"
+    );
+
+    let agent = Arc::new(Mutex::new(Agent::new(installed, offered)));
+    let streaming_store = Arc::new(StreamingStore::new());
+    let provider = IntentProvider::new(
+        url,
+        agent,
+        Duration::from_secs(download_secs),
+        Arc::clone(&streaming_store),
+    );
+
+    Server::builder()
+        .add_service(ProviderServiceServer::new(provider))
+        .add_service(ChannelServiceServer::new(streaming_store.ess().clone()))
+        .serve_with_ctrl_c_shutdown(socket_address)
+        .await
+}
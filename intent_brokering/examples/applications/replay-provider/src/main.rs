@@ -0,0 +1,104 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+mod intent_provider;
+mod trace;
+
+use std::sync::Arc;
+
+use examples_common::intent_brokering::{self, registration::Builder};
+use intent_brokering_common::config::env;
+use intent_brokering_common::error::{Error, ResultExt as _};
+use intent_brokering_common::shutdown::RouterExt as _;
+use intent_brokering_proto::{
+    provider::provider_service_server::ProviderServiceServer,
+    runtime::{intent_registration::Intent, intent_service_registration::ExecutionLocality},
+    streaming::channel_service_server::ChannelServiceServer,
+};
+use tokio::time::{sleep_until, Instant};
+use tonic::transport::Server;
+use url::Url;
+
+use crate::intent_provider::{IntentProvider, StreamingStore};
+use crate::trace::Trace;
+
+intent_brokering::provider::main!(wain);
+
+/// An example trace, used unless `REPLAY_TRACE` points to a different trace.
+const DEFAULT_TRACE: &str = include_str!("../trace.csv");
+
+const INTENTS: [Intent; 3] = [Intent::Read, Intent::Subscribe, Intent::Discover];
+
+/// Publishes the samples of the trace at the time they were recorded, divided
+/// by the speed. Starts over at the end of the trace if `repeat` is set.
+async fn replay(trace: Trace, speed: f64, repeat: bool, streaming_store: Arc<StreamingStore>) {
+    loop {
+        let start = Instant::now();
+
+        for sample in trace.samples.iter() {
+            sleep_until(start + sample.offset.div_f64(speed)).await;
+            streaming_store.set(sample.source.as_str().into(), sample.value.clone());
+        }
+
+        tracing::info!("Replayed trace of {:?}.", trace.duration());
+
+        if !repeat {
+            break;
+        }
+    }
+}
+
+async fn wain() -> Result<(), Error> {
+    let url: Url = env("REPLAY_PROVIDER_URL")
+        .unwrap_or_else(|| "http://0.0.0.0:50071".to_owned()) // DevSkim: ignore DS137138
+        .parse()
+        .map_err_with("Failed to parse URL.")?;
+
+    let trace = match env::<String>("REPLAY_TRACE") {
+        Some(path) => Trace::load(&path)?,
+        None => Trace::parse_csv(DEFAULT_TRACE)?,
+    };
+
+    let speed: f64 = env("REPLAY_SPEED").unwrap_or(1.0);
+    if !speed.is_finite() || speed <= 0.0 {
+        return Err(Error::new("Replay speed must be a positive number."));
+    }
+
+    let repeat: bool = env("REPLAY_REPEAT").unwrap_or(false);
+
+    let namespaces = trace.namespaces();
+    let mut registration = Builder::new(
+        "sdv.replay-provider",
+        "0.0.1",
+        url,
+        namespaces[0],
+        INTENTS,
+        ExecutionLocality::Local,
+    );
+
+    for namespace in namespaces.iter().skip(1) {
+        registration = registration.add_namespace(namespace, INTENTS);
+    }
+
+    let registration = registration.from_env();
+    let socket_address = registration.parse_provider_socket_address()?;
+    let url = registration.announce_url().to_owned();
+    tokio::task::spawn(registration.register());
+
+    tracing::info!(
+        "Application listening on: {url}, replaying {} sample(s) of {} namespace(s) at {speed}x speed",
+        trace.samples.len(),
+        namespaces.len()
+    );
+
+    let streaming_store = Arc::new(StreamingStore::new());
+    let provider = IntentProvider::new(url, Arc::clone(&streaming_store));
+    tokio::task::spawn(replay(trace, speed, repeat, Arc::clone(&streaming_store)));
+
+    Server::builder()
+        .add_service(ProviderServiceServer::new(provider))
+        .add_service(ChannelServiceServer::new(streaming_store.ess().clone()))
+        .serve_with_ctrl_c_shutdown(socket_address)
+        .await
+}
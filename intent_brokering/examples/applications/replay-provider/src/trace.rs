@@ -0,0 +1,183 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::{collections::HashMap, fs, path::Path, time::Duration};
+
+use intent_brokering_common::error::{Error, ResultExt as _};
+use intent_brokering_proto::common::value::Value;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+
+/// The value of a source at a point in time of a trace.
+#[derive(Clone, Debug)]
+pub struct Sample {
+    /// The time since the first sample of the trace.
+    pub offset: Duration,
+    pub namespace: String,
+    pub source: String,
+    pub value: Value,
+}
+
+/// A recording of signal values, ordered by time.
+#[derive(Clone, Debug)]
+pub struct Trace {
+    pub samples: Vec<Sample>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonSample {
+    timestamp_ms: u64,
+    namespace: String,
+    source: String,
+    value: JsonValue,
+}
+
+impl Trace {
+    /// Loads a trace, whose format is inferred from the extension of the
+    /// path.
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let trace = fs::read_to_string(path).map_err_with(format!("Failed to read '{path}'."))?;
+
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("csv") => Self::parse_csv(&trace),
+            Some(e) if e.eq_ignore_ascii_case("json") => Self::parse_json(&trace),
+            _ => Err(Error::new(format!(
+                "Format of '{path}' is not supported, expected a '.csv' or '.json' file."
+            ))),
+        }
+    }
+
+    /// Parses a trace with one sample per line, given as
+    /// `timestamp_ms,namespace,source,value`. A header line is skipped.
+    pub fn parse_csv(trace: &str) -> Result<Self, Error> {
+        let mut samples = vec![];
+
+        for (index, line) in trace.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || (index == 0 && line.starts_with("timestamp")) {
+                continue;
+            }
+
+            let error = || Error::new(format!("Line {} of the trace is not valid.", index + 1));
+            let mut fields = line.splitn(4, ',').map(str::trim);
+            let (Some(timestamp), Some(namespace), Some(source), Some(value)) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(error());
+            };
+
+            samples.push((
+                timestamp.parse().map_err(|_| error())?,
+                namespace.to_owned(),
+                source.to_owned(),
+                parse_value(value),
+            ));
+        }
+
+        Self::new(samples)
+    }
+
+    /// Parses a trace given as a JSON array of samples, e.g.
+    /// `[{ "timestamp_ms": 0, "namespace": "sdv.replay", "source": "Speed", "value": 5 }]`.
+    pub fn parse_json(trace: &str) -> Result<Self, Error> {
+        let samples: Vec<JsonSample> =
+            serde_json::from_str(trace).map_err_with("Failed to parse the trace.")?;
+
+        let samples = samples
+            .into_iter()
+            .map(|sample| {
+                let value = decode(sample.value).ok_or_else(|| {
+                    Error::new(format!(
+                        "Value of '{}' at {} ms must be a bool, number or string.",
+                        sample.source, sample.timestamp_ms
+                    ))
+                })?;
+                Ok((sample.timestamp_ms, sample.namespace, sample.source, value))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Self::new(samples)
+    }
+
+    fn new(mut samples: Vec<(u64, String, String, Value)>) -> Result<Self, Error> {
+        // Samples with the same timestamp keep the order of the trace.
+        samples.sort_by_key(|(timestamp, ..)| *timestamp);
+
+        let start = samples.first().ok_or_else(|| Error::new("Trace has no samples."))?.0;
+
+        // Providers are not told the namespace an intent is addressed to,
+        // hence sources must be unique across namespaces.
+        let mut namespaces: HashMap<&str, &str> = HashMap::new();
+        for (_, namespace, source, _) in samples.iter() {
+            if let Some(other) = namespaces.insert(source.as_str(), namespace.as_str()) {
+                if other != namespace.as_str() {
+                    return Err(Error::new(format!(
+                        "Source '{source}' is part of namespaces '{other}' and '{namespace}'."
+                    )));
+                }
+            }
+        }
+
+        Ok(Self {
+            samples: samples
+                .into_iter()
+                .map(|(timestamp, namespace, source, value)| Sample {
+                    offset: Duration::from_millis(timestamp - start),
+                    namespace,
+                    source,
+                    value,
+                })
+                .collect(),
+        })
+    }
+
+    /// The namespaces of the trace, in the order of their first sample.
+    pub fn namespaces(&self) -> Vec<&str> {
+        let mut namespaces: Vec<&str> = vec![];
+        for sample in self.samples.iter() {
+            if !namespaces.contains(&sample.namespace.as_str()) {
+                namespaces.push(&sample.namespace);
+            }
+        }
+        namespaces
+    }
+
+    /// The time from the first to the last sample.
+    pub fn duration(&self) -> Duration {
+        self.samples.last().map(|s| s.offset).unwrap_or_default()
+    }
+}
+
+/// Parses a value of a CSV trace as bool or number, falling back to a
+/// string. Quotes around a string are removed.
+fn parse_value(value: &str) -> Value {
+    if let Ok(value) = value.parse::<bool>() {
+        Value::Bool(value)
+    } else if let Ok(value) = value.parse::<i32>() {
+        Value::Int32(value)
+    } else if let Ok(value) = value.parse::<i64>() {
+        Value::Int64(value)
+    } else if let Ok(value) = value.parse::<f64>() {
+        Value::Float64(value)
+    } else {
+        let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+        Value::String(value.to_owned())
+    }
+}
+
+fn decode(value: JsonValue) -> Option<Value> {
+    match value {
+        JsonValue::Bool(value) => Some(Value::Bool(value)),
+        JsonValue::Number(number) => Some(match number.as_i64() {
+            Some(value) => match i32::try_from(value) {
+                Ok(value) => Value::Int32(value),
+                Err(_) => Value::Int64(value),
+            },
+            None => Value::Float64(number.as_f64().unwrap_or_default()),
+        }),
+        JsonValue::String(value) => Some(Value::String(value)),
+        JsonValue::Null | JsonValue::Array(_) | JsonValue::Object(_) => None,
+    }
+}
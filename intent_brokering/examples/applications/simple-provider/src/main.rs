@@ -63,9 +63,11 @@ async fn register_and_announce_once(
         url: reg_params.url,
         version: reg_params.version,
         locality: reg_params.locality as i32,
+        transactional: false,
+        max_concurrency: 0,
     });
 
-    let announce_req = AnnounceRequest { service: service.clone() };
+    let announce_req = AnnounceRequest { service: service.clone(), fetch_registration: false };
 
     // Always announce to IntentBrokering.
     let registration_state = client
@@ -89,6 +91,9 @@ async fn register_and_announce_once(
                 .map(|i| IntentRegistration {
                     intent: *i as i32,
                     namespace: reg_params.namespace.clone(),
+                    deprecation: None,
+                    fallbacks: Default::default(),
+                    source_types: Default::default(),
                 })
                 .collect(),
         };
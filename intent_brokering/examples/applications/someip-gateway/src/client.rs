@@ -0,0 +1,228 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use intent_brokering_common::{
+    error::{Error, ResultExt as _},
+    streaming_ess::StreamingEss,
+};
+use intent_brokering_proto::common::value::Value;
+use tokio::{net::UdpSocket, spawn, sync::oneshot, time::timeout};
+use tonic::Status;
+
+use crate::{
+    config::{Service, Type},
+    someip::{self, Message, MessageType},
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_DATAGRAM_SIZE: usize = 65535;
+
+type PendingRequests = Arc<Mutex<HashMap<u16, oneshot::Sender<Message>>>>;
+
+/// Communicates with a SOME/IP service instance over UDP. Responses are
+/// correlated with requests by their session id, while notifications are
+/// published to the ESS under the name of their event or field.
+pub struct Client {
+    service: Service,
+    socket: Arc<UdpSocket>,
+    client_id: u16,
+    session_id: AtomicU16,
+    pending: PendingRequests,
+    eventgroups: tokio::sync::Mutex<HashSet<u16>>,
+}
+
+impl Client {
+    pub async fn connect(
+        service: Service,
+        client_id: u16,
+        ess: StreamingEss<Value>,
+    ) -> Result<Self, Error> {
+        let socket = UdpSocket::bind(match service.endpoint {
+            SocketAddr::V4(_) => "0.0.0.0:0",
+            SocketAddr::V6(_) => "[::]:0",
+        })
+        .await
+        .map_err_with("Failed to bind SOME/IP socket.")?;
+
+        socket
+            .connect(service.endpoint)
+            .await
+            .map_err_with(format!("Failed to connect to '{}'.", service.endpoint))?;
+
+        let socket = Arc::new(socket);
+        let pending = PendingRequests::default();
+
+        let notifications: HashMap<u16, (Box<str>, Type)> = service
+            .events
+            .keys()
+            .chain(service.fields.keys())
+            .filter_map(|name| {
+                service
+                    .notification(name)
+                    .map(|(id, _, data_type)| (id, (name.as_str().into(), data_type)))
+            })
+            .collect();
+
+        spawn(receive(Arc::clone(&socket), Arc::clone(&pending), notifications, ess));
+
+        Ok(Self {
+            service,
+            socket,
+            client_id,
+            session_id: AtomicU16::new(1),
+            pending,
+            eventgroups: Default::default(),
+        })
+    }
+
+    pub fn service(&self) -> &Service {
+        &self.service
+    }
+
+    /// Returns the next session id, skipping zero which is reserved for
+    /// disabled session handling.
+    fn next_session_id(&self) -> u16 {
+        loop {
+            let session_id = self.session_id.fetch_add(1, Ordering::Relaxed);
+            if session_id != 0 {
+                return session_id;
+            }
+        }
+    }
+
+    /// Sends a request to a method, getter or setter and returns the payload
+    /// of the response.
+    pub async fn request(&self, method_id: u16, payload: Vec<u8>) -> Result<Vec<u8>, Status> {
+        let session_id = self.next_session_id();
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().unwrap().insert(session_id, sender);
+
+        let message = Message {
+            service_id: self.service.service_id,
+            method_id,
+            client_id: self.client_id,
+            session_id,
+            interface_version: self.service.major_version,
+            message_type: MessageType::Request,
+            return_code: 0,
+            payload,
+        };
+
+        let response = async {
+            self.socket
+                .send(&message.encode())
+                .await
+                .map_err(|e| Status::unavailable(format!("Failed to send request: {e}")))?;
+
+            timeout(REQUEST_TIMEOUT, receiver)
+                .await
+                .map_err(|_| Status::deadline_exceeded("SOME/IP service did not respond."))?
+                .map_err(|_| Status::unavailable("SOME/IP socket was closed."))
+        }
+        .await;
+
+        self.pending.lock().unwrap().remove(&session_id);
+        let response = response?;
+
+        match (response.message_type, response.return_code) {
+            (MessageType::Response, 0) => Ok(response.payload),
+            (_, return_code) => Err(Status::unknown(format!(
+                "SOME/IP service responded with return code {return_code:#04x}."
+            ))),
+        }
+    }
+
+    /// Subscribes to an eventgroup through service discovery, unless the
+    /// gateway subscribed to it already. The subscription is not acknowledged,
+    /// as notifications are delivered to the socket used for requests.
+    pub async fn subscribe(&self, eventgroup_id: u16) -> Result<(), Status> {
+        let mut eventgroups = self.eventgroups.lock().await;
+        if eventgroups.contains(&eventgroup_id) {
+            return Ok(());
+        }
+
+        let endpoint = match self.socket.local_addr() {
+            Ok(SocketAddr::V4(endpoint)) => endpoint,
+            _ => return Err(Status::unimplemented("Subscriptions require an IPv4 endpoint.")),
+        };
+
+        let message = someip::subscribe_eventgroup(
+            self.next_session_id(),
+            self.service.service_id,
+            self.service.instance_id,
+            self.service.major_version,
+            eventgroup_id,
+            endpoint,
+        );
+
+        // Service discovery messages are sent from a separate socket, as the
+        // socket used for requests only communicates with the service endpoint.
+        let sd_socket = UdpSocket::bind("0.0.0.0:0")
+            .await
+            .map_err(|e| Status::unavailable(format!("Failed to bind SD socket: {e}")))?;
+        sd_socket
+            .send_to(&message.encode(), self.service.sd_endpoint())
+            .await
+            .map_err(|e| Status::unavailable(format!("Failed to subscribe to eventgroup: {e}")))?;
+
+        eventgroups.insert(eventgroup_id);
+        Ok(())
+    }
+}
+
+async fn receive(
+    socket: Arc<UdpSocket>,
+    pending: PendingRequests,
+    notifications: HashMap<u16, (Box<str>, Type)>,
+    ess: StreamingEss<Value>,
+) {
+    let mut buffer = vec![0; MAX_DATAGRAM_SIZE];
+
+    loop {
+        let length = match socket.recv(&mut buffer).await {
+            Ok(length) => length,
+            Err(e) => {
+                tracing::warn!("Failed to receive SOME/IP message: {e}");
+                continue;
+            }
+        };
+
+        let message = match Message::decode(&buffer[..length]) {
+            Ok(message) => message,
+            Err(e) => {
+                tracing::warn!("Dropping SOME/IP message: {e}");
+                continue;
+            }
+        };
+
+        match message.message_type {
+            MessageType::Response | MessageType::Error => {
+                if let Some(sender) = pending.lock().unwrap().remove(&message.session_id) {
+                    _ = sender.send(message);
+                }
+            }
+            MessageType::Notification => {
+                let Some((name, data_type)) = notifications.get(&message.method_id) else {
+                    continue;
+                };
+
+                match someip::deserialize(*data_type, &message.payload) {
+                    Ok(value) => _ = ess.publish(name.as_ref(), value),
+                    Err(e) => tracing::warn!("Dropping notification of '{name}': {e}"),
+                }
+            }
+            MessageType::Request => {}
+        }
+    }
+}
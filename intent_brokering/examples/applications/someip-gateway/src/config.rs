@@ -0,0 +1,158 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::{collections::HashMap, collections::HashSet, fs, net::SocketAddr};
+
+use intent_brokering_common::error::{Error, ResultExt as _};
+use serde::Deserialize;
+
+/// The default port on which SOME/IP service discovery messages are received.
+const DEFAULT_SD_PORT: u16 = 30490;
+
+/// The data types which can be (de)serialized as SOME/IP payload.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Type {
+    Bool,
+    Uint8,
+    Uint16,
+    Uint32,
+    Uint64,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    Float32,
+    Float64,
+    String,
+}
+
+/// A method, exposed through the `Invoke` intent with the name of the method
+/// as command.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Method {
+    pub id: u16,
+    #[serde(default)]
+    pub params: Vec<Type>,
+    pub returns: Option<Type>,
+}
+
+/// A field, exposed through the `Read` intent if it has a getter, the `Write`
+/// intent if it has a setter and the `Subscribe` intent if it has a notifier.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Field {
+    #[serde(rename = "type")]
+    pub data_type: Type,
+    pub getter: Option<u16>,
+    pub setter: Option<u16>,
+    pub notifier: Option<u16>,
+    pub eventgroup: Option<u16>,
+}
+
+/// An event, exposed through the `Subscribe` intent.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Event {
+    pub id: u16,
+    pub eventgroup: u16,
+    #[serde(rename = "type")]
+    pub data_type: Type,
+}
+
+/// A SOME/IP service instance, which is mapped into a namespace.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Service {
+    pub namespace: String,
+    pub service_id: u16,
+    pub instance_id: u16,
+    pub major_version: u8,
+    /// The unicast UDP endpoint of the service instance.
+    pub endpoint: SocketAddr,
+    /// The endpoint to which eventgroup subscriptions are sent. Defaults to
+    /// the service discovery port on the host of the service instance.
+    sd_endpoint: Option<SocketAddr>,
+    #[serde(default)]
+    pub methods: HashMap<String, Method>,
+    #[serde(default)]
+    pub fields: HashMap<String, Field>,
+    #[serde(default)]
+    pub events: HashMap<String, Event>,
+}
+
+impl Service {
+    pub fn sd_endpoint(&self) -> SocketAddr {
+        self.sd_endpoint.unwrap_or_else(|| SocketAddr::new(self.endpoint.ip(), DEFAULT_SD_PORT))
+    }
+
+    /// Looks up the eventgroup and data type of the notifications which are
+    /// published for an event or a field with a notifier.
+    pub fn notification(&self, name: &str) -> Option<(u16, u16, Type)> {
+        match (self.events.get(name), self.fields.get(name)) {
+            (Some(event), _) => Some((event.id, event.eventgroup, event.data_type)),
+            (None, Some(Field { notifier: Some(id), eventgroup: Some(group), data_type, .. })) => {
+                Some((*id, *group, *data_type))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The mapping of SOME/IP service instances into namespaces.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub services: Vec<Service>,
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let config = fs::read_to_string(path).map_err_with(format!("Failed to read '{path}'."))?;
+        Self::parse(&config)
+    }
+
+    pub fn parse(config: &str) -> Result<Self, Error> {
+        let config: Self =
+            serde_json::from_str(config).map_err_with("Failed to parse the SOME/IP mapping.")?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let mut namespaces = HashSet::new();
+
+        for service in self.services.iter() {
+            if !namespaces.insert(service.namespace.as_str()) {
+                return Err(Error::new(format!(
+                    "Namespace '{}' is mapped more than once.",
+                    service.namespace
+                )));
+            }
+
+            if let Some(name) =
+                service.events.keys().find(|name| service.fields.contains_key(*name))
+            {
+                return Err(Error::new(format!(
+                    "'{name}' of namespace '{}' is both a field and an event.",
+                    service.namespace
+                )));
+            }
+
+            if let Some((name, _)) = service
+                .fields
+                .iter()
+                .find(|(_, field)| field.notifier.is_some() != field.eventgroup.is_some())
+            {
+                return Err(Error::new(format!(
+                    "Field '{name}' of namespace '{}' must specify both a notifier and an eventgroup, or neither.",
+                    service.namespace
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use intent_brokering_common::streaming_ess::StreamingEss;
+use tonic::{Request, Response, Status};
+use url::Url;
+
+use intent_brokering_proto::{
+    common::{
+        discover_fulfillment::Service, value::Value, DiscoverFulfillment, FulfillmentEnum,
+        FulfillmentMessage, IntentEnum, InvokeFulfillment, InvokeIntent, ReadFulfillment,
+        ReadIntent, SubscribeFulfillment, SubscribeIntent, ValueMessage, WriteFulfillment,
+        WriteIntent,
+    },
+    provider::{provider_service_server::ProviderService, FulfillRequest, FulfillResponse},
+};
+
+use crate::{client::Client, config::Field, someip};
+
+/// Maps the intents of a namespace onto a SOME/IP service instance: `Invoke`
+/// calls a method, `Read` and `Write` get and set a field and `Subscribe`
+/// subscribes to the eventgroup of an event or field notifier.
+pub struct IntentProvider {
+    url: Url,
+    client: Client,
+    ess: StreamingEss<Value>,
+}
+
+impl IntentProvider {
+    pub fn new(url: Url, client: Client, ess: StreamingEss<Value>) -> Self {
+        Self { url, client, ess }
+    }
+
+    fn field(&self, name: &str) -> Result<&Field, Status> {
+        self.client
+            .service()
+            .fields
+            .get(name)
+            .ok_or_else(|| Status::not_found(format!("Field '{name}' does not exist.")))
+    }
+
+    async fn invoke(&self, intent: InvokeIntent) -> Result<InvokeFulfillment, Status> {
+        let method = self.client.service().methods.get(&intent.command).ok_or_else(|| {
+            Status::not_found(format!("Method '{}' does not exist.", intent.command))
+        })?;
+
+        if method.params.len() != intent.args.len() {
+            return Err(Status::invalid_argument(format!(
+                "Method '{}' expects {} arguments.",
+                intent.command,
+                method.params.len()
+            )));
+        }
+
+        let args = method
+            .params
+            .iter()
+            .zip(intent.args)
+            .map(|(data_type, arg)| {
+                arg.value
+                    .map(|value| (*data_type, value))
+                    .ok_or_else(|| Status::invalid_argument("Arguments must be specified."))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let payload = someip::serialize(args).map_err(Status::invalid_argument)?;
+        let response = self.client.request(method.id, payload).await?;
+
+        let value = method
+            .returns
+            .map(|data_type| someip::deserialize(data_type, &response))
+            .transpose()
+            .map_err(Status::internal)?;
+
+        Ok(InvokeFulfillment { r#return: Some(ValueMessage { value }) })
+    }
+
+    async fn read(&self, intent: ReadIntent) -> Result<ReadFulfillment, Status> {
+        let field = self.field(&intent.key)?;
+        let getter = field.getter.ok_or_else(|| {
+            Status::failed_precondition(format!("Field '{}' has no getter.", intent.key))
+        })?;
+
+        let response = self.client.request(getter, vec![]).await?;
+        let value = someip::deserialize(field.data_type, &response).map_err(Status::internal)?;
+        Ok(ReadFulfillment {
+            value: Some(ValueMessage { value: Some(value) }),
+            ..Default::default()
+        })
+    }
+
+    async fn write(&self, intent: WriteIntent) -> Result<WriteFulfillment, Status> {
+        if intent.precondition.is_some() {
+            return Err(Status::unimplemented("Conditional writes are not supported."));
+        }
+
+        let field = self.field(&intent.key)?;
+        let setter = field.setter.ok_or_else(|| {
+            Status::failed_precondition(format!("Field '{}' has no setter.", intent.key))
+        })?;
+        let value = intent
+            .value
+            .and_then(|v| v.value)
+            .ok_or_else(|| Status::invalid_argument("Value must be specified."))?;
+
+        let payload =
+            someip::serialize([(field.data_type, value)]).map_err(Status::invalid_argument)?;
+        self.client.request(setter, payload).await?;
+        Ok(WriteFulfillment::default())
+    }
+
+    async fn subscribe(&self, intent: SubscribeIntent) -> Result<SubscribeFulfillment, Status> {
+        for source in intent.sources.iter() {
+            let (_, eventgroup, _) =
+                self.client.service().notification(source).ok_or_else(|| {
+                    Status::not_found(format!("Event or field notifier '{source}' does not exist."))
+                })?;
+
+            self.client.subscribe(eventgroup).await?;
+        }
+
+        self.ess.serve_subscriptions(intent, |value| value)
+    }
+}
+
+#[async_trait]
+impl ProviderService for IntentProvider {
+    async fn fulfill(
+        &self,
+        request: Request<FulfillRequest>,
+    ) -> Result<Response<FulfillResponse>, Status> {
+        let fulfillment = match request
+            .into_inner()
+            .intent
+            .and_then(|i| i.intent)
+            .ok_or_else(|| Status::invalid_argument("Intent must be specified."))?
+        {
+            IntentEnum::Invoke(intent) => self.invoke(intent).await.map(FulfillmentEnum::Invoke),
+            IntentEnum::Read(intent) => self.read(intent).await.map(FulfillmentEnum::Read),
+            IntentEnum::Write(intent) => self.write(intent).await.map(FulfillmentEnum::Write),
+            IntentEnum::Subscribe(intent) => {
+                self.subscribe(intent).await.map(FulfillmentEnum::Subscribe)
+            }
+            IntentEnum::Discover(_intent) => Ok(FulfillmentEnum::Discover(DiscoverFulfillment {
+                services: vec![Service {
+                    url: self.url.to_string(),
+                    schema_kind: "grpc+proto".to_owned(),
+                    schema_reference: "intent_brokering.streaming.v1".to_owned(),
+                    metadata: HashMap::new(),
+                }],
+            })),
+            _ => Err(Status::unknown("Unsupported or unknown intent."))?,
+        };
+
+        fulfillment.map(|f| {
+            Response::new(FulfillResponse {
+                fulfillment: Some(FulfillmentMessage { fulfillment: Some(f) }),
+            })
+        })
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+mod client;
+mod config;
+mod intent_provider;
+mod someip;
+
+use examples_common::intent_brokering::{self, registration::Builder};
+use futures::future::try_join_all;
+use intent_brokering_common::config::env;
+use intent_brokering_common::error::{Error, ResultExt as _};
+use intent_brokering_common::shutdown::RouterExt as _;
+use intent_brokering_common::streaming_ess::StreamingEss;
+use intent_brokering_proto::{
+    provider::provider_service_server::ProviderServiceServer,
+    runtime::{intent_registration::Intent, intent_service_registration::ExecutionLocality},
+    streaming::channel_service_server::ChannelServiceServer,
+};
+use tonic::transport::Server;
+use url::Url;
+
+use crate::client::Client;
+use crate::config::{Config, Service};
+use crate::intent_provider::IntentProvider;
+
+intent_brokering::provider::main!(wain);
+
+/// An example mapping, used unless `SOMEIP_GATEWAY_CONFIG` points to a
+/// different mapping.
+const DEFAULT_CONFIG: &str = include_str!("../someip.json");
+
+/// The intents to register for a service, depending on the methods, fields
+/// and events it exposes.
+fn intents(service: &Service) -> Vec<Intent> {
+    let fields = || service.fields.values();
+
+    [
+        (Intent::Discover, true),
+        (Intent::Invoke, !service.methods.is_empty()),
+        (Intent::Read, fields().any(|f| f.getter.is_some())),
+        (Intent::Write, fields().any(|f| f.setter.is_some())),
+        (Intent::Subscribe, !service.events.is_empty() || fields().any(|f| f.notifier.is_some())),
+    ]
+    .into_iter()
+    .filter_map(|(intent, supported)| supported.then_some(intent))
+    .collect()
+}
+
+async fn wain() -> Result<(), Error> {
+    let url: Url = env("SOMEIP_GATEWAY_URL")
+        .unwrap_or_else(|| "http://0.0.0.0:50067".to_owned()) // DevSkim: ignore DS137138
+        .parse()
+        .map_err_with("Failed to parse URL.")?;
+
+    let config = match env::<String>("SOMEIP_GATEWAY_CONFIG") {
+        Some(path) => Config::load(&path)?,
+        None => Config::parse(DEFAULT_CONFIG)?,
+    };
+
+    let client_id: u16 = env("SOMEIP_CLIENT_ID").unwrap_or(0x1000);
+    let base_port = url.port().ok_or_else(|| Error::new("URL must specify a port."))?;
+    let mut servers = vec![];
+
+    // Providers are not told the namespace an intent is addressed to, hence
+    // each service instance is served on its own port, starting with the
+    // port of the gateway URL.
+    for (index, service) in config.services.into_iter().enumerate() {
+        let mut url = url.clone();
+        url.set_port(Some(base_port + index as u16))
+            .map_err(|_| Error::new("Failed to set port of URL."))?;
+
+        let registration = Builder::new(
+            &format!("sdv.someip-gateway.{}", service.namespace),
+            "0.0.1",
+            url,
+            &service.namespace,
+            intents(&service),
+            ExecutionLocality::Local,
+        )
+        .from_env();
+
+        let socket_address = registration.parse_provider_socket_address()?;
+        let url = registration.provider_url().to_owned();
+
+        tracing::info!(
+            "Mapping '{}' to SOME/IP service {:#06x} at {} on: {url}",
+            service.namespace,
+            service.service_id,
+            service.endpoint
+        );
+
+        let ess = StreamingEss::new();
+        let client = Client::connect(service, client_id, ess.clone()).await?;
+        let provider = IntentProvider::new(url, client, ess.clone());
+
+        tokio::task::spawn(registration.register());
+
+        servers.push(
+            Server::builder()
+                .add_service(ProviderServiceServer::new(provider))
+                .add_service(ChannelServiceServer::new(ess))
+                .serve_with_ctrl_c_shutdown(socket_address),
+        );
+    }
+
+    try_join_all(servers).await.map(|_| ())
+}
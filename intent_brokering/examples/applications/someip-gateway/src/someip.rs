@@ -0,0 +1,251 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Encoding and decoding of the subset of SOME/IP used by the gateway: the
+//! message header, payloads of primitive data types and the service discovery
+//! entry for subscribing to an eventgroup.
+
+use std::net::SocketAddrV4;
+
+use intent_brokering_proto::common::value::Value;
+
+use crate::config::Type;
+
+const HEADER_LENGTH: usize = 16;
+const PROTOCOL_VERSION: u8 = 0x01;
+const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+
+const SD_SERVICE_ID: u16 = 0xFFFF;
+const SD_METHOD_ID: u16 = 0x8100;
+const SD_INTERFACE_VERSION: u8 = 0x01;
+const SD_FLAGS_REBOOT_UNICAST: u8 = 0xC0;
+const SD_ENTRY_SUBSCRIBE_EVENTGROUP: u8 = 0x06;
+const SD_OPTION_IPV4_ENDPOINT: u8 = 0x04;
+const SD_L4_PROTOCOL_UDP: u8 = 0x11;
+/// Keeps the subscription until the gateway restarts.
+const SD_TTL_INFINITE: u32 = 0xFF_FFFF;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageType {
+    Request = 0x00,
+    Notification = 0x02,
+    Response = 0x80,
+    Error = 0x81,
+}
+
+impl MessageType {
+    fn parse(value: u8) -> Option<Self> {
+        Some(match value {
+            0x00 => Self::Request,
+            0x02 => Self::Notification,
+            0x80 => Self::Response,
+            0x81 => Self::Error,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    pub service_id: u16,
+    pub method_id: u16,
+    pub client_id: u16,
+    pub session_id: u16,
+    pub interface_version: u8,
+    pub message_type: MessageType,
+    pub return_code: u8,
+    pub payload: Vec<u8>,
+}
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(HEADER_LENGTH + self.payload.len());
+        buffer.extend(self.service_id.to_be_bytes());
+        buffer.extend(self.method_id.to_be_bytes());
+        // The length covers the remainder of the header and the payload.
+        buffer.extend((8 + self.payload.len() as u32).to_be_bytes());
+        buffer.extend(self.client_id.to_be_bytes());
+        buffer.extend(self.session_id.to_be_bytes());
+        buffer.extend([
+            PROTOCOL_VERSION,
+            self.interface_version,
+            self.message_type as u8,
+            self.return_code,
+        ]);
+        buffer.extend(&self.payload);
+        buffer
+    }
+
+    pub fn decode(buffer: &[u8]) -> Result<Self, String> {
+        if buffer.len() < HEADER_LENGTH {
+            return Err("Message is shorter than the SOME/IP header.".to_owned());
+        }
+
+        let u16_at = |i: usize| u16::from_be_bytes([buffer[i], buffer[i + 1]]);
+        let length = u32::from_be_bytes([buffer[4], buffer[5], buffer[6], buffer[7]]) as usize;
+
+        if length < 8 || buffer.len() < length + 8 {
+            return Err("Message length does not match the SOME/IP header.".to_owned());
+        }
+
+        if buffer[12] != PROTOCOL_VERSION {
+            return Err(format!("Unsupported SOME/IP protocol version {}.", buffer[12]));
+        }
+
+        Ok(Self {
+            service_id: u16_at(0),
+            method_id: u16_at(2),
+            client_id: u16_at(8),
+            session_id: u16_at(10),
+            interface_version: buffer[13],
+            message_type: MessageType::parse(buffer[14])
+                .ok_or_else(|| format!("Unsupported SOME/IP message type {}.", buffer[14]))?,
+            return_code: buffer[15],
+            payload: buffer[HEADER_LENGTH..length + 8].to_vec(),
+        })
+    }
+}
+
+/// Builds the service discovery message subscribing the given endpoint to an
+/// eventgroup of a service instance.
+pub fn subscribe_eventgroup(
+    session_id: u16,
+    service_id: u16,
+    instance_id: u16,
+    major_version: u8,
+    eventgroup_id: u16,
+    endpoint: SocketAddrV4,
+) -> Message {
+    let mut entry = Vec::with_capacity(16);
+    // Type, index of the first and second option run, number of options in
+    // the first and second run.
+    entry.extend([SD_ENTRY_SUBSCRIBE_EVENTGROUP, 0, 0, 0x10]);
+    entry.extend(service_id.to_be_bytes());
+    entry.extend(instance_id.to_be_bytes());
+    entry.push(major_version);
+    entry.extend(&SD_TTL_INFINITE.to_be_bytes()[1..]);
+    // Reserved, initial data requested flag and counter.
+    entry.extend([0, 0]);
+    entry.extend(eventgroup_id.to_be_bytes());
+
+    let mut option = Vec::with_capacity(12);
+    option.extend(9u16.to_be_bytes());
+    option.extend([SD_OPTION_IPV4_ENDPOINT, 0]);
+    option.extend(endpoint.ip().octets());
+    option.extend([0, SD_L4_PROTOCOL_UDP]);
+    option.extend(endpoint.port().to_be_bytes());
+
+    let mut payload = vec![SD_FLAGS_REBOOT_UNICAST, 0, 0, 0];
+    payload.extend((entry.len() as u32).to_be_bytes());
+    payload.extend(entry);
+    payload.extend((option.len() as u32).to_be_bytes());
+    payload.extend(option);
+
+    Message {
+        service_id: SD_SERVICE_ID,
+        method_id: SD_METHOD_ID,
+        client_id: 0,
+        session_id,
+        interface_version: SD_INTERFACE_VERSION,
+        message_type: MessageType::Notification,
+        return_code: 0,
+        payload,
+    }
+}
+
+/// Serializes values as a SOME/IP payload. Integers are accepted regardless
+/// of their width, as long as they are in the range of the data type. Strings
+/// are serialized with a length field, a byte order mark and a terminator.
+pub fn serialize(values: impl IntoIterator<Item = (Type, Value)>) -> Result<Vec<u8>, String> {
+    let mut buffer = vec![];
+
+    for (data_type, value) in values {
+        let mismatch = || format!("Value is not compatible with type '{data_type:?}'.");
+
+        let integer = match value {
+            Value::Int32(v) => Some(i64::from(v)),
+            Value::Int64(v) => Some(v),
+            _ => None,
+        };
+
+        macro_rules! integer {
+            ($t:ty) => {
+                buffer.extend(
+                    integer
+                        .and_then(|v| <$t>::try_from(v).ok())
+                        .ok_or_else(mismatch)?
+                        .to_be_bytes(),
+                )
+            };
+        }
+
+        match (data_type, value) {
+            (Type::Bool, Value::Bool(v)) => buffer.push(v.into()),
+            (Type::Float32, Value::Float32(v)) => buffer.extend(v.to_be_bytes()),
+            (Type::Float64, Value::Float32(v)) => buffer.extend(f64::from(v).to_be_bytes()),
+            (Type::Float64, Value::Float64(v)) => buffer.extend(v.to_be_bytes()),
+            (Type::String, Value::String(v)) => {
+                let length = UTF8_BOM.len() + v.len() + 1;
+                buffer.extend((length as u32).to_be_bytes());
+                buffer.extend(UTF8_BOM);
+                buffer.extend(v.as_bytes());
+                buffer.push(0);
+            }
+            (Type::Uint8, _) => integer!(u8),
+            (Type::Uint16, _) => integer!(u16),
+            (Type::Uint32, _) => integer!(u32),
+            (Type::Uint64, _) => integer!(u64),
+            (Type::Int8, _) => integer!(i8),
+            (Type::Int16, _) => integer!(i16),
+            (Type::Int32, _) => integer!(i32),
+            (Type::Int64, _) => integer!(i64),
+            _ => return Err(mismatch()),
+        }
+    }
+
+    Ok(buffer)
+}
+
+/// Deserializes a SOME/IP payload consisting of a single value. Integers that
+/// fit into 32 bits are represented as `int32`, all others as `int64`.
+pub fn deserialize(data_type: Type, payload: &[u8]) -> Result<Value, String> {
+    let truncated = || format!("Payload is too short for type '{data_type:?}'.");
+
+    macro_rules! read {
+        ($t:ty) => {
+            <$t>::from_be_bytes(
+                payload
+                    .get(..std::mem::size_of::<$t>())
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or_else(truncated)?,
+            )
+        };
+    }
+
+    Ok(match data_type {
+        Type::Bool => Value::Bool(read!(u8) != 0),
+        Type::Uint8 => Value::Int32(read!(u8).into()),
+        Type::Uint16 => Value::Int32(read!(u16).into()),
+        Type::Uint32 => Value::Int64(read!(u32).into()),
+        Type::Uint64 => Value::Int64(
+            i64::try_from(read!(u64))
+                .map_err(|_| "Value exceeds the range of int64.".to_owned())?,
+        ),
+        Type::Int8 => Value::Int32(read!(i8).into()),
+        Type::Int16 => Value::Int32(read!(i16).into()),
+        Type::Int32 => Value::Int32(read!(i32)),
+        Type::Int64 => Value::Int64(read!(i64)),
+        Type::Float32 => Value::Float32(read!(f32)),
+        Type::Float64 => Value::Float64(read!(f64)),
+        Type::String => {
+            let length = read!(u32) as usize;
+            let bytes = payload.get(4..4 + length).ok_or_else(truncated)?;
+            let bytes = bytes.strip_prefix(&UTF8_BOM).unwrap_or(bytes);
+            let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+            Value::String(
+                String::from_utf8(bytes.to_vec()).map_err(|_| "String is not UTF-8.".to_owned())?,
+            )
+        }
+    })
+}
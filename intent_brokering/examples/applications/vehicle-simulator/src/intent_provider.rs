@@ -0,0 +1,125 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use examples_common::intent_brokering::{self, streaming::ProtoExt as _};
+use tonic::{Request, Response, Status};
+use url::Url;
+
+use intent_brokering_proto::{
+    common::{
+        discover_fulfillment::Service, value::Value, DiscoverFulfillment, FulfillmentEnum,
+        FulfillmentMessage, IntentEnum, InvokeFulfillment, InvokeIntent, ValueMessage,
+    },
+    provider::{provider_service_server::ProviderService, FulfillRequest, FulfillResponse},
+};
+
+use crate::simulation::Vehicle;
+
+pub type StreamingStore = intent_brokering::streaming::StreamingStore<Value>;
+
+/// Serves the signals of the simulated vehicle and forwards commands to it.
+pub struct IntentProvider {
+    url: Url,
+    vehicle: Arc<Mutex<Vehicle>>,
+    streaming_store: Arc<StreamingStore>,
+}
+
+impl IntentProvider {
+    pub fn new(
+        url: Url,
+        vehicle: Arc<Mutex<Vehicle>>,
+        streaming_store: Arc<StreamingStore>,
+    ) -> Self {
+        Self { url, vehicle, streaming_store }
+    }
+
+    /// Supports the following commands, which return the resulting state:
+    /// - `lock()` locks the doors, which lets the vehicle drive.
+    /// - `unlock()` unlocks the doors, unless the vehicle is moving.
+    /// - `start_climate(target?)` starts the climate control with an optional
+    ///   target temperature in °C.
+    /// - `stop_climate()` stops the climate control.
+    fn invoke(&self, intent: InvokeIntent) -> Result<InvokeFulfillment, Status> {
+        let mut args = intent.args.into_iter().map(|arg| arg.value);
+        let mut vehicle = self.vehicle.lock().unwrap();
+
+        let result = match (intent.command.as_str(), args.next(), args.next()) {
+            ("lock", None, None) => {
+                vehicle.lock();
+                vehicle.is_locked()
+            }
+            ("unlock", None, None) => {
+                vehicle.unlock().map_err(|e| Status::failed_precondition(e.to_string()))?;
+                vehicle.is_locked()
+            }
+            ("start_climate", target, None) => {
+                let target = match target {
+                    None | Some(None) => None,
+                    Some(Some(Value::Int32(target))) => Some(target.into()),
+                    Some(Some(Value::Float32(target))) => Some(target.into()),
+                    Some(Some(Value::Float64(target))) => Some(target),
+                    _ => Err(Status::invalid_argument("Target temperature must be a number."))?,
+                };
+
+                vehicle
+                    .start_climate(target)
+                    .map_err(|e| Status::invalid_argument(e.to_string()))?;
+                vehicle.is_climate_active()
+            }
+            ("stop_climate", None, None) => {
+                vehicle.stop_climate();
+                vehicle.is_climate_active()
+            }
+            (command @ ("lock" | "unlock" | "stop_climate"), ..) => Err(Status::invalid_argument(
+                format!("Invalid arguments for command '{command}'."),
+            ))?,
+            (command, ..) => Err(Status::not_found(format!("No command found for '{command}'.")))?,
+        };
+
+        // Publish the new state right away instead of with the next step.
+        self.streaming_store.set_many(vehicle.signals().map(|(key, value)| (key.into(), value)));
+
+        Ok(InvokeFulfillment { r#return: Some(ValueMessage { value: Some(Value::Bool(result)) }) })
+    }
+}
+
+#[async_trait]
+impl ProviderService for IntentProvider {
+    async fn fulfill(
+        &self,
+        request: Request<FulfillRequest>,
+    ) -> Result<Response<FulfillResponse>, Status> {
+        let fulfillment = match request
+            .into_inner()
+            .intent
+            .and_then(|i| i.intent)
+            .ok_or_else(|| Status::invalid_argument("Intent must be specified."))?
+        {
+            IntentEnum::Read(intent) => Ok(self.streaming_store.read(intent)),
+            IntentEnum::Subscribe(intent) => self.streaming_store.subscribe(intent),
+            IntentEnum::Invoke(intent) => self.invoke(intent).map(FulfillmentEnum::Invoke),
+            IntentEnum::Discover(_intent) => Ok(FulfillmentEnum::Discover(DiscoverFulfillment {
+                services: vec![Service {
+                    url: self.url.to_string(),
+                    schema_kind: "grpc+proto".to_owned(),
+                    schema_reference: "intent_brokering.streaming.v1".to_owned(),
+                    metadata: HashMap::new(),
+                }],
+            })),
+            _ => Err(Status::unknown("Unsupported or unknown intent."))?,
+        };
+
+        fulfillment.map(|f| {
+            Response::new(FulfillResponse {
+                fulfillment: Some(FulfillmentMessage { fulfillment: Some(f) }),
+            })
+        })
+    }
+}
@@ -0,0 +1,94 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+mod intent_provider;
+mod simulation;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use examples_common::intent_brokering::{self, registration::Builder};
+use intent_brokering_common::config::env;
+use intent_brokering_common::error::{Error, ResultExt as _};
+use intent_brokering_common::shutdown::RouterExt as _;
+use intent_brokering_proto::{
+    provider::provider_service_server::ProviderServiceServer,
+    runtime::{intent_registration::Intent, intent_service_registration::ExecutionLocality},
+    streaming::channel_service_server::ChannelServiceServer,
+};
+use tokio::time::{interval, Instant, MissedTickBehavior};
+use tonic::transport::Server;
+use url::Url;
+
+use crate::intent_provider::{IntentProvider, StreamingStore};
+use crate::simulation::Vehicle;
+
+intent_brokering::provider::main!(wain);
+
+const NAMESPACE: &str = "sdv.simulated.vehicle";
+
+/// Advances the simulation in the given interval and publishes the signals
+/// of the vehicle after each step.
+async fn simulate(
+    vehicle: Arc<Mutex<Vehicle>>,
+    step_interval: Duration,
+    streaming_store: Arc<StreamingStore>,
+) {
+    let mut interval = interval(step_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut last_step = Instant::now();
+
+    loop {
+        let now = interval.tick().await;
+        let mut vehicle = vehicle.lock().unwrap();
+        vehicle.step(now - last_step);
+        streaming_store.set_many(vehicle.signals().map(|(key, value)| (key.into(), value)));
+        last_step = now;
+    }
+}
+
+async fn wain() -> Result<(), Error> {
+    let url: Url = env("VEHICLE_SIMULATOR_URL")
+        .unwrap_or_else(|| "http://0.0.0.0:50072".to_owned()) // DevSkim: ignore DS137138
+        .parse()
+        .map_err_with("Failed to parse URL.")?;
+
+    let step_interval: u64 = env("VEHICLE_SIMULATOR_INTERVAL_MS").unwrap_or(500);
+    if step_interval == 0 {
+        return Err(Error::new("Simulation interval must be positive."));
+    }
+
+    let registration = Builder::new(
+        "sdv.vehicle-simulator",
+        "0.0.1",
+        url,
+        NAMESPACE,
+        [Intent::Read, Intent::Subscribe, Intent::Invoke, Intent::Discover],
+        ExecutionLocality::Local,
+    )
+    .from_env();
+
+    let socket_address = registration.parse_provider_socket_address()?;
+    let url = registration.announce_url().to_owned();
+    tokio::task::spawn(registration.register());
+
+    tracing::info!("Application listening on: {url}, simulating a vehicle in '{NAMESPACE}'");
+
+    let vehicle = Arc::new(Mutex::new(Vehicle::new()));
+    let streaming_store = Arc::new(StreamingStore::new());
+    let provider = IntentProvider::new(url, Arc::clone(&vehicle), Arc::clone(&streaming_store));
+    tokio::task::spawn(simulate(
+        vehicle,
+        Duration::from_millis(step_interval),
+        Arc::clone(&streaming_store),
+    ));
+
+    Server::builder()
+        .add_service(ProviderServiceServer::new(provider))
+        .add_service(ChannelServiceServer::new(streaming_store.ess().clone()))
+        .serve_with_ctrl_c_shutdown(socket_address)
+        .await
+}
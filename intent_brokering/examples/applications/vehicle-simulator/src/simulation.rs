@@ -0,0 +1,230 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::{f64::consts::PI, time::Duration};
+
+use intent_brokering_common::error::Error;
+use intent_brokering_proto::common::value::Value;
+
+pub const SPEED: &str = "Vehicle.Speed";
+pub const STATE_OF_CHARGE: &str = "Vehicle.Powertrain.TractionBattery.StateOfCharge.Current";
+pub const LATITUDE: &str = "Vehicle.CurrentLocation.Latitude";
+pub const LONGITUDE: &str = "Vehicle.CurrentLocation.Longitude";
+pub const IS_LOCKED: &str = "Vehicle.Cabin.Door.IsLocked";
+pub const IS_CLIMATE_ACTIVE: &str = "Vehicle.Cabin.HVAC.IsAirConditioningActive";
+pub const CABIN_TEMPERATURE: &str = "Vehicle.Cabin.HVAC.AmbientAirTemperature";
+
+/// The drive cycle as pairs of a target speed in km/h and the time in seconds
+/// for which the driver aims for it. The cycle repeats once it ends.
+const DRIVE_CYCLE: [(f64, f64); 8] = [
+    (0.0, 10.0),
+    (50.0, 40.0),
+    (30.0, 20.0),
+    (0.0, 15.0),
+    (80.0, 45.0),
+    (120.0, 60.0),
+    (60.0, 30.0),
+    (0.0, 20.0),
+];
+
+const MAX_ACCELERATION: f64 = 2.5; // m/s²
+const MAX_DECELERATION: f64 = 3.5; // m/s²
+
+const MASS: f64 = 2000.0; // kg
+const DRAG_AREA: f64 = 0.6; // drag coefficient times frontal area in m²
+const AIR_DENSITY: f64 = 1.2; // kg/m³
+const ROLLING_RESISTANCE: f64 = 0.01;
+const GRAVITY: f64 = 9.81; // m/s²
+const DRIVETRAIN_EFFICIENCY: f64 = 0.9;
+const REGENERATION_EFFICIENCY: f64 = 0.6;
+const AUXILIARY_POWER: f64 = 300.0; // W
+const CLIMATE_POWER: f64 = 2500.0; // W
+const BATTERY_CAPACITY: f64 = 75.0 * 3_600_000.0; // J
+
+const AMBIENT_TEMPERATURE: f64 = 30.0; // °C
+const DEFAULT_CLIMATE_TARGET: f64 = 21.0; // °C
+const CLIMATE_TIME_CONSTANT: f64 = 120.0; // s
+const CABIN_TIME_CONSTANT: f64 = 900.0; // s
+
+/// The vehicle drives on a circular track around this origin.
+const TRACK_ORIGIN: (f64, f64) = (47.6396, -122.1280);
+const TRACK_RADIUS: f64 = 2000.0; // m
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// A vehicle which follows a drive cycle while its doors are locked and is
+/// parked otherwise.
+pub struct Vehicle {
+    cycle_time: f64,
+    speed: f64,
+    state_of_charge: f64,
+    distance: f64,
+    is_locked: bool,
+    is_climate_active: bool,
+    climate_target: f64,
+    cabin_temperature: f64,
+}
+
+impl Vehicle {
+    pub fn new() -> Self {
+        Self {
+            cycle_time: 0.0,
+            speed: 0.0,
+            state_of_charge: 80.0,
+            distance: 0.0,
+            is_locked: true,
+            is_climate_active: false,
+            climate_target: DEFAULT_CLIMATE_TARGET,
+            cabin_temperature: AMBIENT_TEMPERATURE,
+        }
+    }
+
+    /// Advances the simulation by the elapsed time.
+    pub fn step(&mut self, elapsed: Duration) {
+        let dt = elapsed.as_secs_f64();
+        if dt <= 0.0 {
+            return;
+        }
+
+        // The driver aims for the target speed of the drive cycle, limited by
+        // the acceleration of the vehicle. Once the battery is empty, the
+        // vehicle comes to a stop.
+        let target_speed = if self.is_locked && self.state_of_charge > 0.0 {
+            self.cycle_time = (self.cycle_time + dt) % cycle_duration();
+            target_speed(self.cycle_time) / 3.6
+        } else {
+            0.0
+        };
+
+        let previous_speed = self.speed;
+        self.speed = if target_speed > self.speed {
+            (self.speed + MAX_ACCELERATION * dt).min(target_speed)
+        } else {
+            (self.speed - MAX_DECELERATION * dt).max(target_speed)
+        };
+
+        let acceleration = (self.speed - previous_speed) / dt;
+        let average_speed = (self.speed + previous_speed) / 2.0;
+        self.distance += average_speed * dt;
+
+        // The traction power overcomes drag, rolling resistance and inertia.
+        // Braking recovers part of the energy.
+        let traction_power = (0.5 * AIR_DENSITY * DRAG_AREA * average_speed.powi(2)
+            + ROLLING_RESISTANCE * MASS * GRAVITY
+            + MASS * acceleration)
+            * average_speed;
+        let traction_power = if traction_power > 0.0 {
+            traction_power / DRIVETRAIN_EFFICIENCY
+        } else {
+            traction_power * REGENERATION_EFFICIENCY
+        };
+
+        let climate_power = if self.is_climate_active { CLIMATE_POWER } else { 0.0 };
+        let power = traction_power + AUXILIARY_POWER + climate_power;
+        self.state_of_charge =
+            (self.state_of_charge - power * dt / BATTERY_CAPACITY * 100.0).clamp(0.0, 100.0);
+
+        // The cabin approaches the target temperature while the climate is
+        // active, and the ambient temperature otherwise.
+        let (temperature, time_constant) = if self.is_climate_active {
+            (self.climate_target, CLIMATE_TIME_CONSTANT)
+        } else {
+            (AMBIENT_TEMPERATURE, CABIN_TIME_CONSTANT)
+        };
+        self.cabin_temperature +=
+            (temperature - self.cabin_temperature) * (1.0 - (-dt / time_constant).exp());
+    }
+
+    pub fn lock(&mut self) {
+        self.is_locked = true;
+    }
+
+    pub fn unlock(&mut self) -> Result<(), Error> {
+        if self.speed > 0.0 {
+            return Err(Error::new("Doors cannot be unlocked while the vehicle is moving."));
+        }
+
+        self.is_locked = false;
+        Ok(())
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.is_locked
+    }
+
+    /// Starts the climate control with the given target temperature in °C,
+    /// or the default target of 21 °C.
+    pub fn start_climate(&mut self, target: Option<f64>) -> Result<(), Error> {
+        let target = target.unwrap_or(DEFAULT_CLIMATE_TARGET);
+        if !(16.0..=30.0).contains(&target) {
+            return Err(Error::new("Target temperature must be between 16 and 30 °C."));
+        }
+
+        self.is_climate_active = true;
+        self.climate_target = target;
+        Ok(())
+    }
+
+    pub fn stop_climate(&mut self) {
+        self.is_climate_active = false;
+    }
+
+    pub fn is_climate_active(&self) -> bool {
+        self.is_climate_active
+    }
+
+    /// Returns the current value of each simulated signal.
+    pub fn signals(&self) -> [(&'static str, Value); 7] {
+        let (latitude, longitude) = self.location();
+
+        [
+            (SPEED, Value::Float64(round(self.speed * 3.6, 1))),
+            (STATE_OF_CHARGE, Value::Float64(round(self.state_of_charge, 2))),
+            (LATITUDE, Value::Float64(round(latitude, 6))),
+            (LONGITUDE, Value::Float64(round(longitude, 6))),
+            (IS_LOCKED, Value::Bool(self.is_locked)),
+            (IS_CLIMATE_ACTIVE, Value::Bool(self.is_climate_active)),
+            (CABIN_TEMPERATURE, Value::Float64(round(self.cabin_temperature, 1))),
+        ]
+    }
+
+    /// Returns the latitude and longitude of the vehicle on its track.
+    fn location(&self) -> (f64, f64) {
+        let (latitude, longitude) = TRACK_ORIGIN;
+        let angle = self.distance / TRACK_RADIUS;
+        let north = TRACK_RADIUS * angle.sin();
+        let east = TRACK_RADIUS * (1.0 - angle.cos());
+
+        (
+            latitude + north / METERS_PER_DEGREE,
+            longitude + east / (METERS_PER_DEGREE * (latitude * PI / 180.0).cos()),
+        )
+    }
+}
+
+impl Default for Vehicle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn cycle_duration() -> f64 {
+    DRIVE_CYCLE.iter().map(|(_, duration)| duration).sum()
+}
+
+fn target_speed(cycle_time: f64) -> f64 {
+    let mut end = 0.0;
+    for (speed, duration) in DRIVE_CYCLE {
+        end += duration;
+        if cycle_time < end {
+            return speed;
+        }
+    }
+
+    0.0
+}
+
+fn round(value: f64, decimals: i32) -> f64 {
+    let factor = 10f64.powi(decimals);
+    (value * factor).round() / factor
+}
@@ -0,0 +1,313 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::collections::{BTreeSet, HashMap};
+
+use intent_brokering_common::error::{Error, ResultExt as _};
+use intent_brokering_proto::common::{value::Value, List, ValueMessage};
+use serde_json::{Map, Value as Json};
+
+/// The kind of a VSS signal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignalKind {
+    /// A value measured by the vehicle.
+    Sensor,
+    /// A value which can be set to change the state of the vehicle.
+    Actuator,
+    /// A static value describing the vehicle.
+    Attribute,
+}
+
+/// The primitive VSS data types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scalar {
+    Boolean,
+    String,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    Uint8,
+    Uint16,
+    Uint32,
+    Uint64,
+    Float,
+    Double,
+}
+
+/// The data type of a VSS signal, which is either a primitive type or an
+/// array of a primitive type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataType {
+    Scalar(Scalar),
+    Array(Scalar),
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "boolean" => Self::Boolean,
+            "string" => Self::String,
+            "int8" => Self::Int8,
+            "int16" => Self::Int16,
+            "int32" => Self::Int32,
+            "int64" => Self::Int64,
+            "uint8" => Self::Uint8,
+            "uint16" => Self::Uint16,
+            "uint32" => Self::Uint32,
+            "uint64" => Self::Uint64,
+            "float" => Self::Float,
+            "double" => Self::Double,
+            _ => return None,
+        })
+    }
+
+    /// The range of values of an integer type.
+    fn range(self) -> Option<(i64, i64)> {
+        Some(match self {
+            Self::Int8 => (i8::MIN.into(), i8::MAX.into()),
+            Self::Int16 => (i16::MIN.into(), i16::MAX.into()),
+            Self::Int32 => (i32::MIN.into(), i32::MAX.into()),
+            Self::Int64 => (i64::MIN, i64::MAX),
+            Self::Uint8 => (0, u8::MAX.into()),
+            Self::Uint16 => (0, u16::MAX.into()),
+            Self::Uint32 => (0, u32::MAX.into()),
+            // Values beyond `i64::MAX` cannot be represented by `common::Value`.
+            Self::Uint64 => (0, i64::MAX),
+            _ => return None,
+        })
+    }
+
+    /// Translates an integer into the `common::Value` representing the type.
+    /// Types that fit into 32 bits are represented as `int32`, all others as
+    /// `int64`.
+    fn integer(self, value: i64) -> Option<Value> {
+        let (min, max) = self.range()?;
+        if value < min || value > max {
+            return None;
+        }
+
+        Some(match i32::try_from(value) {
+            Ok(value) if max <= i32::MAX.into() => Value::Int32(value),
+            _ => Value::Int64(value),
+        })
+    }
+
+    /// Translates a value into the `common::Value` representing the type, or
+    /// returns none if the value is not compatible with the type. Integers
+    /// are accepted as long as they are in range, regardless of their width.
+    fn translate(self, value: Value) -> Option<Value> {
+        match (self, value) {
+            (Self::Boolean, value @ Value::Bool(_)) => Some(value),
+            (Self::String, value @ Value::String(_)) => Some(value),
+            (Self::Float, Value::Float32(value)) => Some(Value::Float32(value)),
+            (Self::Double, Value::Float32(value)) => Some(Value::Float64(value.into())),
+            (Self::Double, Value::Float64(value)) => Some(Value::Float64(value)),
+            (scalar, Value::Int32(value)) => scalar.integer(value.into()),
+            (scalar, Value::Int64(value)) => scalar.integer(value),
+            _ => None,
+        }
+    }
+
+    /// Translates a JSON value of a VSS catalog, such as a default value, into
+    /// the `common::Value` representing the type.
+    fn parse_json(self, json: &Json) -> Option<Value> {
+        match (self, json) {
+            (Self::Boolean, Json::Bool(value)) => Some(Value::Bool(*value)),
+            (Self::String, Json::String(value)) => Some(Value::String(value.clone())),
+            (Self::Float, Json::Number(value)) => value.as_f64().map(|v| Value::Float32(v as f32)),
+            (Self::Double, Json::Number(value)) => value.as_f64().map(Value::Float64),
+            (scalar, Json::Number(value)) => value.as_i64().and_then(|v| scalar.integer(v)),
+            _ => None,
+        }
+    }
+}
+
+impl DataType {
+    fn parse(name: &str) -> Option<Self> {
+        match name.strip_suffix("[]") {
+            Some(name) => Scalar::parse(name).map(Self::Array),
+            None => Scalar::parse(name).map(Self::Scalar),
+        }
+    }
+
+    /// Translates a value into the `common::Value` representing the type, or
+    /// returns none if the value is not compatible with the type. Arrays are
+    /// represented as lists.
+    pub fn translate(self, value: Value) -> Option<Value> {
+        match (self, value) {
+            (Self::Scalar(scalar), value) => scalar.translate(value),
+            (Self::Array(scalar), Value::List(list)) => list
+                .value
+                .into_iter()
+                .map(|v| v.value.and_then(|v| scalar.translate(v)).map(message))
+                .collect::<Option<_>>()
+                .map(|value| Value::List(List { value })),
+            _ => None,
+        }
+    }
+
+    fn parse_json(self, json: &Json) -> Option<Value> {
+        match (self, json) {
+            (Self::Scalar(scalar), json) => scalar.parse_json(json),
+            (Self::Array(scalar), Json::Array(values)) => values
+                .iter()
+                .map(|v| scalar.parse_json(v).map(message))
+                .collect::<Option<_>>()
+                .map(|value| Value::List(List { value })),
+            _ => None,
+        }
+    }
+}
+
+fn message(value: Value) -> ValueMessage {
+    ValueMessage { value: Some(value) }
+}
+
+/// A signal of a VSS catalog, identified by its full path, e.g.
+/// `Vehicle.Cabin.Door.Row1.Left.IsOpen`.
+#[derive(Clone, Debug)]
+pub struct Signal {
+    pub path: Box<str>,
+    pub kind: SignalKind,
+    pub data_type: DataType,
+    pub default: Option<Value>,
+    min: Option<f64>,
+    max: Option<f64>,
+    allowed: Option<Vec<Value>>,
+}
+
+impl Signal {
+    /// The path of the branch containing the signal.
+    pub fn branch(&self) -> &str {
+        self.path.rsplit_once('.').map_or("", |(branch, _)| branch)
+    }
+
+    /// Translates a value written to the signal into its VSS data type and
+    /// validates it against the restrictions of the signal.
+    pub fn translate(&self, value: Value) -> Result<Value, String> {
+        let value = self.data_type.translate(value).ok_or_else(|| {
+            format!(
+                "Value is not compatible with data type '{:?}' of '{}'.",
+                self.data_type, self.path
+            )
+        })?;
+
+        let number = match value {
+            Value::Int32(v) => Some(f64::from(v)),
+            Value::Int64(v) => Some(v as f64),
+            Value::Float32(v) => Some(f64::from(v)),
+            Value::Float64(v) => Some(v),
+            _ => None,
+        };
+
+        if let Some(number) = number {
+            if self.min.map_or(false, |min| number < min)
+                || self.max.map_or(false, |max| number > max)
+            {
+                return Err(format!("Value is out of the range of '{}'.", self.path));
+            }
+        }
+
+        if let Some(allowed) = &self.allowed {
+            if !allowed.contains(&value) {
+                return Err(format!("Value is not one of the allowed values of '{}'.", self.path));
+            }
+        }
+
+        Ok(value)
+    }
+}
+
+/// The signals of a VSS catalog in the JSON format generated by the VSS tools
+/// (`vspec2json`), where each node is keyed by its name and declares its
+/// `type`, with branches nesting their `children`.
+#[derive(Clone, Debug, Default)]
+pub struct Catalog {
+    signals: HashMap<Box<str>, Signal>,
+}
+
+impl Catalog {
+    pub fn parse(json: &str) -> Result<Self, Error> {
+        let root: Map<String, Json> =
+            serde_json::from_str(json).map_err_with("Failed to parse VSS catalog.")?;
+
+        let mut catalog = Self::default();
+        for (name, node) in root.iter() {
+            catalog.add(name, node)?;
+        }
+        Ok(catalog)
+    }
+
+    fn add(&mut self, path: &str, node: &Json) -> Result<(), Error> {
+        let invalid = |reason: &str| Error::new(format!("Invalid VSS node '{path}': {reason}"));
+        let kind =
+            node.get("type").and_then(Json::as_str).ok_or_else(|| invalid("missing type"))?;
+
+        let kind = match kind {
+            "branch" => {
+                if let Some(children) = node.get("children").and_then(Json::as_object) {
+                    for (name, child) in children {
+                        self.add(&format!("{path}.{name}"), child)?;
+                    }
+                }
+                return Ok(());
+            }
+            "sensor" => SignalKind::Sensor,
+            "actuator" => SignalKind::Actuator,
+            "attribute" => SignalKind::Attribute,
+            // Structs and properties of structs are not supported.
+            _ => return Ok(()),
+        };
+
+        let data_type = node
+            .get("datatype")
+            .and_then(Json::as_str)
+            .ok_or_else(|| invalid("missing datatype"))?;
+        let data_type =
+            DataType::parse(data_type).ok_or_else(|| invalid("unsupported datatype"))?;
+
+        let parse_json = |json: &Json| {
+            data_type.parse_json(json).ok_or_else(|| invalid("value does not match datatype"))
+        };
+
+        let default = node.get("default").map(parse_json).transpose()?;
+        let allowed = match data_type {
+            DataType::Scalar(_) => node
+                .get("allowed")
+                .and_then(Json::as_array)
+                .map(|allowed| allowed.iter().map(parse_json).collect::<Result<Vec<_>, _>>())
+                .transpose()?,
+            // The allowed values of arrays restrict their elements, which is
+            // not enforced.
+            DataType::Array(_) => None,
+        };
+
+        let signal = Signal {
+            path: path.into(),
+            kind,
+            data_type,
+            default,
+            min: node.get("min").and_then(Json::as_f64),
+            max: node.get("max").and_then(Json::as_f64),
+            allowed,
+        };
+
+        self.signals.insert(signal.path.clone(), signal);
+        Ok(())
+    }
+
+    pub fn get(&self, path: &str) -> Option<&Signal> {
+        self.signals.get(path)
+    }
+
+    pub fn signals(&self) -> impl Iterator<Item = &Signal> {
+        self.signals.values()
+    }
+
+    /// The paths of all branches directly containing signals.
+    pub fn branches(&self) -> BTreeSet<&str> {
+        self.signals().map(Signal::branch).collect()
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use examples_common::intent_brokering::{self, streaming::ProtoExt as _};
+use tonic::{Request, Response, Status};
+use url::Url;
+
+use intent_brokering_proto::{
+    common::{
+        discover_fulfillment::Service, value::Value, DiscoverFulfillment, FulfillmentEnum,
+        FulfillmentMessage, IntentEnum, SubscribeIntent, WriteFulfillment, WriteIntent,
+    },
+    provider::{provider_service_server::ProviderService, FulfillRequest, FulfillResponse},
+};
+
+use crate::catalog::{Catalog, Signal, SignalKind};
+
+pub type StreamingStore = intent_brokering::streaming::StreamingStore<Value>;
+
+/// Serves the signals of a VSS catalog, which are identified by their full
+/// path regardless of the namespace of their branch.
+pub struct IntentProvider {
+    url: Url,
+    catalog: Catalog,
+    streaming_store: Arc<StreamingStore>,
+}
+
+impl IntentProvider {
+    /// Creates the provider, initializing each signal with its default value
+    /// from the catalog, if any.
+    pub fn new(url: Url, catalog: Catalog, streaming_store: Arc<StreamingStore>) -> Self {
+        streaming_store.set_many(
+            catalog.signals().filter_map(|signal| {
+                signal.default.clone().map(|value| (signal.path.clone(), value))
+            }),
+        );
+
+        Self { url, catalog, streaming_store }
+    }
+
+    fn signal(&self, path: &str) -> Result<&Signal, Status> {
+        self.catalog
+            .get(path)
+            .ok_or_else(|| Status::not_found(format!("Signal '{path}' does not exist.")))
+    }
+
+    fn write(&self, intent: WriteIntent) -> Result<WriteFulfillment, Status> {
+        if intent.precondition.is_some() {
+            return Err(Status::unimplemented("Conditional writes are not supported."));
+        }
+
+        let signal = self.signal(&intent.key)?;
+        if signal.kind == SignalKind::Attribute {
+            return Err(Status::failed_precondition(format!(
+                "Attribute '{}' cannot be written.",
+                signal.path
+            )));
+        }
+
+        let value = intent
+            .value
+            .and_then(|v| v.value)
+            .ok_or_else(|| Status::invalid_argument("Value must be specified."))?;
+        let value = signal.translate(value).map_err(Status::invalid_argument)?;
+
+        self.streaming_store.set(signal.path.clone(), value);
+        Ok(WriteFulfillment::default())
+    }
+
+    fn subscribe(&self, intent: SubscribeIntent) -> Result<FulfillmentEnum, Status> {
+        for source in intent.sources.iter() {
+            self.signal(source)?;
+        }
+
+        self.streaming_store.subscribe(intent)
+    }
+}
+
+#[async_trait]
+impl ProviderService for IntentProvider {
+    async fn fulfill(
+        &self,
+        request: Request<FulfillRequest>,
+    ) -> Result<Response<FulfillResponse>, Status> {
+        let fulfillment = match request
+            .into_inner()
+            .intent
+            .and_then(|i| i.intent)
+            .ok_or_else(|| Status::invalid_argument("Intent must be specified."))?
+        {
+            IntentEnum::Read(intent) => {
+                self.signal(&intent.key).map(|_| self.streaming_store.read(intent))
+            }
+            IntentEnum::Write(intent) => self.write(intent).map(FulfillmentEnum::Write),
+            IntentEnum::Subscribe(intent) => self.subscribe(intent),
+            IntentEnum::Discover(_intent) => Ok(FulfillmentEnum::Discover(DiscoverFulfillment {
+                services: vec![Service {
+                    url: self.url.to_string(),
+                    schema_kind: "grpc+proto".to_owned(),
+                    schema_reference: "intent_brokering.streaming.v1".to_owned(),
+                    metadata: HashMap::new(),
+                }],
+            })),
+            _ => Err(Status::unknown("Unsupported or unknown intent."))?,
+        };
+
+        fulfillment.map(|f| {
+            Response::new(FulfillResponse {
+                fulfillment: Some(FulfillmentMessage { fulfillment: Some(f) }),
+            })
+        })
+    }
+}
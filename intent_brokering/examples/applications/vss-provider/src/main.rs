@@ -0,0 +1,81 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+mod catalog;
+mod intent_provider;
+
+use std::{fs, sync::Arc};
+
+use examples_common::intent_brokering::{self, registration::Builder};
+use intent_brokering_common::config::env;
+use intent_brokering_common::error::{Error, ResultExt as _};
+use intent_brokering_common::shutdown::RouterExt as _;
+use intent_brokering_proto::{
+    provider::provider_service_server::ProviderServiceServer,
+    runtime::{intent_registration::Intent, intent_service_registration::ExecutionLocality},
+    streaming::channel_service_server::ChannelServiceServer,
+};
+use tonic::transport::Server;
+use url::Url;
+
+use crate::catalog::Catalog;
+use crate::intent_provider::{IntentProvider, StreamingStore};
+
+intent_brokering::provider::main!(wain);
+
+/// A small excerpt of the VSS catalog, used unless `VSS_CATALOG_PATH` points
+/// to a different catalog.
+const DEFAULT_CATALOG: &str = include_str!("../vss.json");
+
+async fn wain() -> Result<(), Error> {
+    let url: Url = env("VSS_URL")
+        .unwrap_or_else(|| "http://0.0.0.0:50065".to_owned()) // DevSkim: ignore DS137138
+        .parse()
+        .map_err_with("Failed to parse URL.")?;
+
+    let catalog = match env::<String>("VSS_CATALOG_PATH") {
+        Some(path) => {
+            fs::read_to_string(&path).map_err_with(format!("Failed to read '{path}'."))?
+        }
+        None => DEFAULT_CATALOG.to_owned(),
+    };
+    let catalog = Catalog::parse(&catalog)?;
+
+    let namespace: String = env("VSS_NAMESPACE").unwrap_or_else(|| "sdv.vss".to_owned());
+
+    // Each branch containing signals is exposed as a namespace of its own,
+    // e.g. `sdv.vss.Vehicle.Cabin.Door.Row1.Left`.
+    let registration = catalog.branches().into_iter().fold(
+        Builder::new(
+            "sdv.vss-provider",
+            "0.0.1",
+            url,
+            &namespace,
+            [Intent::Discover],
+            ExecutionLocality::Local,
+        ),
+        |registration, branch| {
+            registration.add_namespace(
+                &format!("{namespace}.{branch}"),
+                [Intent::Read, Intent::Write, Intent::Subscribe, Intent::Discover],
+            )
+        },
+    );
+    let registration = registration.from_env();
+
+    let socket_address = registration.parse_provider_socket_address()?;
+    let url = registration.announce_url().to_owned();
+    tokio::task::spawn(registration.register());
+
+    tracing::info!("Application listening on: {url}");
+
+    let streaming_store = Arc::new(StreamingStore::new());
+    let provider = IntentProvider::new(url, catalog, Arc::clone(&streaming_store));
+
+    Server::builder()
+        .add_service(ProviderServiceServer::new(provider))
+        .add_service(ChannelServiceServer::new(streaming_store.ess().clone()))
+        .serve_with_ctrl_c_shutdown(socket_address)
+        .await
+}
@@ -19,19 +19,24 @@ use futures::{stream::BoxStream, StreamExt};
 use intent_brokering_common::error::{Error, ResultExt as _};
 use intent_brokering_proto::{
     common::{
-        discover_fulfillment::Service as ServiceMessage, DiscoverFulfillment, DiscoverIntent,
-        FulfillmentEnum, InspectFulfillment, InspectIntent, IntentEnum, IntentMessage,
-        InvokeFulfillment, InvokeIntent, ReadFulfillment, ReadIntent, SubscribeFulfillment,
-        SubscribeIntent, WriteFulfillment, WriteIntent,
+        discover_fulfillment::Service as ServiceMessage,
+        write_batch_fulfillment::entry::Result as WriteBatchResult, DeleteFulfillment,
+        DeleteIntent, DiscoverFulfillment, DiscoverIntent, FulfillmentEnum, InspectFulfillment,
+        InspectIntent, IntentEnum, IntentMessage, InvokeFulfillment, InvokeIntent, ReadFulfillment,
+        ReadIntent, SubscribeFulfillment, SubscribeIntent, WriteBatchFulfillment, WriteBatchIntent,
+        WriteFulfillment, WriteIntent, WritePrecondition,
     },
     runtime::{
-        intent_brokering_service_client::IntentBrokeringServiceClient, FulfillRequest,
-        FulfillResponse,
+        intent_brokering_service_client::IntentBrokeringServiceClient, intent_registration::Intent,
+        FulfillRequest, FulfillResponse, FulfillTransactionRequest, FulfillTransactionResponse,
+        WaitForServiceRequest,
+    },
+    streaming::{
+        channel_service_client::ChannelServiceClient, Event as StreamingEvent, OpenRequest,
     },
-    streaming::{channel_service_client::ChannelServiceClient, OpenRequest},
 };
 use tonic::{transport::Channel, Request, Response};
-use tracing::debug;
+use tracing::{debug, warn};
 
 const INTENT_BROKER_URL_KEY: &str = "INTENT_BROKER_URL";
 const DEFAULT_INTENT_BROKER_URL: &str = env!("DEFAULT_INTENT_BROKER_URL");
@@ -77,6 +82,8 @@ macro_rules! impl_try_from_var {
 impl_try_from_var!(Fulfillment, FulfillmentEnum::Inspect, InspectFulfillment);
 impl_try_from_var!(Fulfillment, FulfillmentEnum::Read, ReadFulfillment);
 impl_try_from_var!(Fulfillment, FulfillmentEnum::Write, WriteFulfillment);
+impl_try_from_var!(Fulfillment, FulfillmentEnum::WriteBatch, WriteBatchFulfillment);
+impl_try_from_var!(Fulfillment, FulfillmentEnum::Delete, DeleteFulfillment);
 impl_try_from_var!(Fulfillment, FulfillmentEnum::Invoke, InvokeFulfillment);
 impl_try_from_var!(Fulfillment, FulfillmentEnum::Subscribe, SubscribeFulfillment);
 impl_try_from_var!(Fulfillment, FulfillmentEnum::Discover, DiscoverFulfillment);
@@ -96,6 +103,51 @@ impl GrpcIntentBrokering {
 
         Ok(Self { client })
     }
+
+    /// Fulfills the intents for their namespaces as a single transaction.
+    pub async fn fulfill_transaction(
+        &mut self,
+        operations: impl IntoIterator<Item = (Box<str>, IntentEnum)>,
+    ) -> Result<FulfillTransactionResponse, Error> {
+        let operations = operations
+            .into_iter()
+            .map(|(namespace, intent)| FulfillRequest {
+                intent: Some(IntentMessage { intent: Some(intent) }),
+                namespace: namespace.into(),
+            })
+            .collect();
+
+        self.client
+            .fulfill_transaction(Request::new(FulfillTransactionRequest { operations }))
+            .await
+            .map_err_with("Transaction fulfillment failed.")
+            .map(|r| r.into_inner())
+    }
+
+    /// Waits until services are registered for all of the intents of the
+    /// namespace, or for any intent of the namespace if no intents are given.
+    pub async fn wait_for_service(
+        &mut self,
+        namespace: impl Into<Box<str>>,
+        intents: impl IntoIterator<Item = Intent>,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<(), Error> {
+        let namespace = namespace.into();
+        let timeout =
+            timeout.map(TryInto::try_into).transpose().map_err_with("Timeout is out of range.")?;
+
+        debug!("Waiting for namespace '{namespace}' to be served.");
+
+        self.client
+            .wait_for_service(Request::new(WaitForServiceRequest {
+                namespace: namespace.into(),
+                intents: intents.into_iter().map(|intent| intent as i32).collect(),
+                timeout,
+            }))
+            .await
+            .map_err_with("Waiting for service failed.")
+            .map(|_| ())
+    }
 }
 
 #[async_trait]
@@ -161,11 +213,35 @@ pub trait IntentBrokering: Send {
         value: Value,
     ) -> Result<(), Error>;
 
+    /// Writes a key only if the precondition holds for its current value and
+    /// returns the version token of the written value.
+    async fn write_if(
+        &mut self,
+        namespace: impl Into<Box<str>> + Send,
+        key: impl Into<Box<str>> + Send,
+        value: Value,
+        precondition: WritePrecondition,
+    ) -> Result<Box<str>, Error>;
+
+    /// Writes several keys with a single intent and returns the result of the
+    /// write for each key.
+    async fn write_batch<I: IntoIterator<Item = (Box<str>, Value)> + Send>(
+        &mut self,
+        namespace: impl Into<Box<str>> + Send,
+        writes: I,
+    ) -> Result<Vec<(Box<str>, Result<(), Error>)>, Error>;
+
     async fn read(
         &mut self,
         namespace: impl Into<Box<str>> + Send,
         key: impl Into<Box<str>> + Send,
     ) -> Result<Option<Value>, Error>;
+
+    async fn delete(
+        &mut self,
+        namespace: impl Into<Box<str>> + Send,
+        key: impl Into<Box<str>> + Send,
+    ) -> Result<(), Error>;
 }
 
 #[async_trait]
@@ -205,7 +281,11 @@ impl<T: IntentBrokeringCommunication> IntentBrokering for T {
 
         self.fulfill(
             namespace,
-            IntentEnum::Subscribe(SubscribeIntent { channel_id: channel_id.into(), sources }),
+            IntentEnum::Subscribe(SubscribeIntent {
+                channel_id: channel_id.into(),
+                sources,
+                ..Default::default()
+            }),
         )
         .await?
         .fulfillment()
@@ -268,13 +348,74 @@ impl<T: IntentBrokeringCommunication> IntentBrokering for T {
 
         self.fulfill(
             namespace,
-            IntentEnum::Write(WriteIntent { key: key.into(), value: Some(value.into()) }),
+            IntentEnum::Write(WriteIntent {
+                key: key.into(),
+                value: Some(value.into()),
+                precondition: None,
+            }),
         )
         .await?
         .fulfillment()
         .map(|_: WriteFulfillment| ())
     }
 
+    async fn write_if(
+        &mut self,
+        namespace: impl Into<Box<str>> + Send,
+        key: impl Into<Box<str>> + Send,
+        value: Value,
+        precondition: WritePrecondition,
+    ) -> Result<Box<str>, Error> {
+        let key = key.into();
+        debug!("Writing key '{:?}' with value '{:?}' if {:?}.", key, value, precondition);
+
+        self.fulfill(
+            namespace,
+            IntentEnum::Write(WriteIntent {
+                key: key.into(),
+                value: Some(value.into()),
+                precondition: Some(precondition),
+            }),
+        )
+        .await?
+        .fulfillment()
+        .map(|write: WriteFulfillment| write.version.into())
+    }
+
+    async fn write_batch<I: IntoIterator<Item = (Box<str>, Value)> + Send>(
+        &mut self,
+        namespace: impl Into<Box<str>> + Send,
+        writes: I,
+    ) -> Result<Vec<(Box<str>, Result<(), Error>)>, Error> {
+        let writes: Vec<_> = writes
+            .into_iter()
+            .map(|(key, value)| WriteIntent {
+                key: key.into(),
+                value: Some(value.into()),
+                precondition: None,
+            })
+            .collect();
+        debug!("Writing batch of {} keys.", writes.len());
+
+        self.fulfill(namespace, IntentEnum::WriteBatch(WriteBatchIntent { writes }))
+            .await?
+            .fulfillment()
+            .map(|write_batch: WriteBatchFulfillment| {
+                write_batch
+                    .entries
+                    .into_iter()
+                    .map(|entry| {
+                        let result = match entry.result {
+                            Some(WriteBatchResult::Fulfillment(_)) => Ok(()),
+                            Some(WriteBatchResult::Error(message)) => Err(Error::new(message)),
+                            None => Err(Error::new("Did not receive write result.")),
+                        };
+                        (entry.key.into(), result)
+                    })
+                    .collect()
+            })
+    }
+
     async fn read(
         &mut self,
         namespace: impl Into<Box<str>> + Send,
@@ -297,6 +438,23 @@ impl<T: IntentBrokeringCommunication> IntentBrokering for T {
                 None => Ok(None),
             })
     }
+
+    async fn delete(
+        &mut self,
+        namespace: impl Into<Box<str>> + Send,
+        key: impl Into<Box<str>> + Send,
+    ) -> Result<(), Error> {
+        let key = key.into();
+        debug!("Deleting key '{:?}'.", key);
+
+        self.fulfill(
+            namespace,
+            IntentEnum::Delete(DeleteIntent { key: key.into(), precondition: None }),
+        )
+        .await?
+        .fulfillment()
+        .map(|_: DeleteFulfillment| ())
+    }
 }
 
 #[async_trait::async_trait]
@@ -306,6 +464,18 @@ pub trait IntentBrokeringExt {
         namespace: impl Into<Box<str>> + Send,
         subscription_sources: impl IntoIterator<Item = Box<str>> + Send,
     ) -> Result<BoxStream<'b, Result<Event, Error>>, Error>;
+
+    /// Listens to the events of sources of a namespace and converts their
+    /// values to `V`, yielding each value along with its source. Values which
+    /// cannot be converted are yielded as errors, which do not end the stream.
+    async fn subscribe_typed<'b, V>(
+        self,
+        namespace: impl Into<Box<str>> + Send,
+        sources: impl IntoIterator<Item = Box<str>> + Send,
+    ) -> Result<BoxStream<'b, Result<(Box<str>, V), Error>>, Error>
+    where
+        V: TryFrom<Value> + Send + 'b,
+        V::Error: std::error::Error + Send + Sync + 'static;
 }
 
 #[async_trait::async_trait]
@@ -344,7 +514,7 @@ where
             .map_err_with("Connecting to streaming endpoint failed.")?;
 
         let response = provider_client
-            .open(Request::new(OpenRequest {}))
+            .open(Request::new(OpenRequest::default()))
             .await
             .map_err_with("Opening stream failed.")?;
 
@@ -357,8 +527,18 @@ where
             .ok_or_else(|| Error::new("Channel ID header not found."))?
             .into();
 
-        let result_stream = response.into_inner().map(|r| {
-            r.map_err_with("Could not establish stream.").and_then(|event| {
+        let result_stream = response.into_inner().filter_map(|r| async move {
+            // The stream ends after the end of stream marker of a closed channel.
+            if let Ok(StreamingEvent { end_of_stream: true, .. }) = &r {
+                return None;
+            }
+
+            if let Ok(StreamingEvent { source, gap: Some(gap), .. }) = &r {
+                warn!("Events {}-{} of '{source}' were dropped.", gap.from_seq, gap.to_seq);
+                return None;
+            }
+
+            Some(r.map_err_with("Could not establish stream.").and_then(|event| {
                 event
                     .value
                     .ok_or_else(|| Error::new("No value found in event payload."))
@@ -366,13 +546,37 @@ where
                         v.try_into().map_err(|_e: ()| Error::new("Could not parse protobuf value."))
                     })
                     .map(|data| Event { id: event.source.into_boxed_str(), data, seq: event.seq })
-            })
+            }))
         });
 
         self.subscribe(namespace, channel_id, subscription_sources).await?;
 
         Ok(result_stream.boxed())
     }
+
+    async fn subscribe_typed<'b, V>(
+        self,
+        namespace: impl Into<Box<str>> + Send,
+        sources: impl IntoIterator<Item = Box<str>> + Send,
+    ) -> Result<BoxStream<'b, Result<(Box<str>, V), Error>>, Error>
+    where
+        V: TryFrom<Value> + Send + 'b,
+        V::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let events = self.listen(namespace, sources).await?;
+
+        Ok(events
+            .map(|event| {
+                event.and_then(|Event { id, data, .. }| match V::try_from(data) {
+                    Ok(value) => Ok((id, value)),
+                    Err(e) => Err(Error::from_error(
+                        format!("Could not convert value of '{id}'."),
+                        Box::new(e),
+                    )),
+                })
+            })
+            .boxed())
+    }
 }
 
 pub struct Event {
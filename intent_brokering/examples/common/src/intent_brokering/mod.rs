@@ -7,4 +7,5 @@ pub mod inspection;
 pub mod provider;
 pub mod registration;
 pub mod streaming;
+pub mod typed;
 pub mod value;
@@ -2,19 +2,33 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
-use std::{env, net::SocketAddr, time::Duration};
+use std::{
+    collections::HashMap,
+    env,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
 
 use intent_brokering_common::{
     config,
     error::{Error, ResultExt},
+    retry::{retry_with_backoff, Backoff},
 };
+use intent_brokering_proto::common::{SourceType, ValueMessage};
 use intent_brokering_proto::runtime::{
-    intent_brokering_service_client::IntentBrokeringServiceClient, intent_registration::Intent,
-    intent_service_registration::ExecutionLocality, AnnounceRequest, IntentRegistration,
-    IntentServiceRegistration, RegisterRequest, RegistrationState,
+    intent_brokering_service_client::IntentBrokeringServiceClient,
+    intent_registration::Intent,
+    intent_service_registration::ExecutionLocality,
+    registration_service_server::{RegistrationService, RegistrationServiceServer},
+    AnnounceRequest, Deprecation, GetRegistrationRequest, GetRegistrationResponse,
+    IntentRegistration, IntentServiceRegistration, RegisterRequest, RegistrationState,
 };
 use tokio::time::sleep;
-use tonic::transport::Channel;
+use tonic::{async_trait, transport::Channel, Request, Response, Status};
 use tracing::warn;
 use url::Url;
 
@@ -23,6 +37,10 @@ use crate::url::UrlExt as _;
 const INTENT_BROKER_URL_KEY: &str = "INTENT_BROKER_URL";
 const DEFAULT_INTENT_BROKER_URL: &str = env!("DEFAULT_INTENT_BROKER_URL");
 const ANNOUNCE_URL_KEY: &str = "ANNOUNCE_URL";
+const RETRY_AFTER_METADATA_KEY: &str = "x-chariott-retry-after-ms";
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+type ReregisteredCallback = Arc<dyn Fn() + Send + Sync>;
 
 pub enum ConfigSource<'a, T> {
     Value(T),
@@ -34,11 +52,17 @@ pub struct Builder {
     version: Box<str>,
     announce_url: Url,
     provider_url: Url,
-    namespace: Box<str>,
-    intents: Vec<Intent>,
+    intents: Vec<(Box<str>, Intent)>,
     intent_broker_url: Url,
     registration_interval: Duration,
     locality: ExecutionLocality,
+    transactional: bool,
+    max_concurrency: u32,
+    fetch_registration: bool,
+    deprecations: HashMap<Box<str>, Deprecation>,
+    fallbacks: HashMap<Box<str>, HashMap<String, ValueMessage>>,
+    source_types: HashMap<Box<str>, HashMap<String, SourceType>>,
+    on_reregistered: Option<ReregisteredCallback>,
 }
 
 impl Builder {
@@ -63,14 +87,111 @@ impl Builder {
             version: version.into(),
             announce_url,
             provider_url: url,
-            namespace: namespace.into(),
-            intents: intents.into_iter().collect(),
+            intents: intents.into_iter().map(|i| (namespace.into(), i)).collect(),
             intent_broker_url,
             registration_interval: Duration::from_secs(5),
             locality,
+            transactional: false,
+            max_concurrency: 0,
+            fetch_registration: false,
+            deprecations: HashMap::new(),
+            fallbacks: HashMap::new(),
+            source_types: HashMap::new(),
+            on_reregistered: None,
         }
     }
 
+    /// Registers the provider for intents of an additional namespace.
+    pub fn add_namespace(
+        mut self,
+        namespace: &str,
+        intents: impl IntoIterator<Item = Intent>,
+    ) -> Self {
+        self.intents.extend(intents.into_iter().map(|i| (namespace.into(), i)));
+        self
+    }
+
+    /// Marks all intents of a namespace as deprecated, optionally with the time
+    /// at which they will be removed and what to use instead.
+    pub fn deprecate_namespace(
+        mut self,
+        namespace: &str,
+        sunset: Option<SystemTime>,
+        replacement: Option<&str>,
+    ) -> Self {
+        self.deprecations.insert(
+            namespace.into(),
+            Deprecation {
+                sunset: sunset.map(Into::into),
+                replacement: replacement.unwrap_or_default().to_owned(),
+            },
+        );
+        self
+    }
+
+    /// Declares the value of a key, which the Intent Broker returns for `Read`
+    /// intents of a namespace if the provider is unreachable and fallbacks
+    /// are enabled.
+    pub fn set_fallback(
+        mut self,
+        namespace: &str,
+        key: &str,
+        value: impl Into<ValueMessage>,
+    ) -> Self {
+        self.fallbacks.entry(namespace.into()).or_default().insert(key.to_owned(), value.into());
+        self
+    }
+
+    /// Declares the type of the values of a source of a namespace, e.g.
+    /// `int32`, and optionally a reference to their schema, which the Intent
+    /// Broker attaches to the events of the source it relays.
+    pub fn set_source_type(
+        mut self,
+        namespace: &str,
+        source: &str,
+        value_type: &str,
+        schema: Option<&str>,
+    ) -> Self {
+        self.source_types.entry(namespace.into()).or_default().insert(
+            source.to_owned(),
+            SourceType {
+                value_type: value_type.to_owned(),
+                schema: schema.unwrap_or_default().to_owned(),
+            },
+        );
+        self
+    }
+
+    /// Sets a callback which is called whenever the provider registered again
+    /// after the Intent Broker was unreachable or lost its registration, e.g.
+    /// as it restarted, such that the provider can resync its state with the
+    /// Intent Broker.
+    pub fn set_on_reregistered(mut self, callback: impl Fn() + Send + Sync + 'static) -> Self {
+        self.on_reregistered = Some(Arc::new(callback));
+        self
+    }
+
+    /// Sets whether the provider can prepare, commit and abort transactions.
+    pub fn set_transactional(mut self, value: bool) -> Self {
+        self.transactional = value;
+        self
+    }
+
+    /// Limits the number of requests which the Intent Broker forwards to the
+    /// provider at once. A value of zero, the default, does not limit them.
+    pub fn set_max_concurrency(mut self, value: u32) -> Self {
+        self.max_concurrency = value;
+        self
+    }
+
+    /// Sets whether the provider only announces itself and lets the Intent
+    /// Broker fetch its intents. The provider must then serve the
+    /// `registration_service`.
+    pub fn set_fetch_registration(mut self, value: bool) -> Self {
+        self.fetch_registration = value;
+        self
+    }
+
     pub fn set_registration_interval(mut self, value: ConfigSource<Duration>) -> Self {
         match value {
             ConfigSource::Value(value) => self.registration_interval = value,
@@ -113,27 +234,92 @@ impl Builder {
         &self.provider_url
     }
 
+    /// Returns a `RegistrationService` serving the intents of the provider,
+    /// from which the Intent Broker fetches them if `fetch_registration` is
+    /// set.
+    pub fn registration_service(&self) -> RegistrationServiceServer<IntentsRegistration> {
+        RegistrationServiceServer::new(IntentsRegistration { intents: self.intent_registrations() })
+    }
+
+    fn intent_registrations(&self) -> Vec<IntentRegistration> {
+        self.intents
+            .iter()
+            .map(|(namespace, i)| IntentRegistration {
+                intent: *i as i32,
+                namespace: namespace.to_string(),
+                deprecation: self.deprecations.get(namespace).cloned(),
+                fallbacks: match i {
+                    Intent::Read => self.fallbacks.get(namespace).cloned().unwrap_or_default(),
+                    _ => HashMap::new(),
+                },
+                source_types: match i {
+                    Intent::Subscribe => {
+                        self.source_types.get(namespace).cloned().unwrap_or_default()
+                    }
+                    _ => HashMap::new(),
+                },
+            })
+            .collect()
+    }
+
     pub fn parse_provider_socket_address(&self) -> Result<SocketAddr, Error> {
         self.provider_url()
             .parse_socket_address()
             .map_err_with("Error parsing provider socket address.")
     }
 
+    /// Announces the provider in its registration interval for as long as it
+    /// runs, which doubles as a heartbeat to detect restarts of the Intent
+    /// Broker. The provider is registered again whenever the Intent Broker
+    /// was unreachable or lost its registration.
     pub async fn register(self) {
-        let mut client = None;
+        let client = tokio::sync::Mutex::new(None);
+        let backoff = Backoff::new(RETRY_BASE_DELAY, self.registration_interval);
+        let unreachable = AtomicBool::new(false);
         let mut first_iteration = true;
 
         loop {
-            match self.register_once(&mut client, first_iteration).await {
-                Ok(_) => {
+            // Failed registrations are retried with a jittered backoff, such
+            // that providers do not reconnect at once after the Intent Broker
+            // restarted.
+            let result = retry_with_backoff(
+                &backoff,
+                || async {
+                    let mut client = client.lock().await;
+                    let result = self.register_once(&mut client, first_iteration).await;
+                    if let Err(e) = &result {
+                        if retry_after(e).is_none() {
+                            warn!("Registration failed with '{:?}'. Retrying.", e);
+                            *client = None;
+                            unreachable.store(true, Ordering::Relaxed);
+                        }
+                    }
+                    result
+                },
+                |e| retry_after(e).is_none(),
+            )
+            .await;
+
+            match result {
+                Ok(lost_registration) => {
+                    let unreachable = unreachable.swap(false, Ordering::Relaxed);
+                    if !first_iteration && (lost_registration || unreachable) {
+                        warn!("Registered again after the Intent Broker was unreachable or lost the registration.");
+                        if let Some(on_reregistered) = &self.on_reregistered {
+                            on_reregistered();
+                        }
+                    }
+
                     first_iteration = false;
                 }
                 Err(e) => {
-                    warn!(
-                        "Registration failed with '{:?}'. Retrying after {:?}.",
-                        e, self.registration_interval
-                    );
-                    client = None;
+                    // The Intent Broker rejects registrations beyond its
+                    // admitted rate with the delay after which to retry.
+                    if let Some(retry_after) = retry_after(&e) {
+                        warn!("Registration was rejected. Retrying after {:?}.", retry_after);
+                        sleep(retry_after).await;
+                        continue;
+                    }
                 }
             }
 
@@ -141,11 +327,14 @@ impl Builder {
         }
     }
 
+    /// Announces the provider, and registers its intents if the Intent Broker
+    /// does not know them. Returns whether the Intent Broker lost the
+    /// registration since the previous announcement.
     pub async fn register_once(
         &self,
         client: &mut Option<IntentBrokeringServiceClient<Channel>>,
         first_iteration: bool,
-    ) -> Result<(), Error> {
+    ) -> Result<bool, Error> {
         if client.is_none() {
             *client = Some(
                 IntentBrokeringServiceClient::connect(self.intent_broker_url.to_string())
@@ -164,7 +353,10 @@ impl Builder {
                     url: self.announce_url.to_string(),
                     version: self.version.to_string(),
                     locality: self.locality as i32,
+                    transactional: self.transactional,
+                    max_concurrency: self.max_concurrency,
                 }),
+                fetch_registration: self.fetch_registration,
             };
 
             let registration_state = client
@@ -174,17 +366,15 @@ impl Builder {
                 .into_inner()
                 .registration_state;
 
-            if first_iteration || registration_state == RegistrationState::Announced as i32 {
+            let lost_registration =
+                !first_iteration && registration_state == RegistrationState::Announced as i32;
+
+            // The Intent Broker fetches the intents of a provider which is
+            // not registered if `fetch_registration` is set.
+            if !self.fetch_registration && (first_iteration || lost_registration) {
                 let register_request = RegisterRequest {
                     service: announce_request.service.clone(),
-                    intents: self
-                        .intents
-                        .iter()
-                        .map(|i| IntentRegistration {
-                            intent: *i as i32,
-                            namespace: self.namespace.to_string(),
-                        })
-                        .collect(),
+                    intents: self.intent_registrations(),
                 };
 
                 tracing::info!("Registered with IntentBrokering runtime: {:?}", register_request);
@@ -193,8 +383,37 @@ impl Builder {
                     .await
                     .map_err_with("Error when registering with IntentBrokering.")?;
             }
+
+            return Ok(lost_registration);
         }
 
-        Ok(())
+        Ok(false)
+    }
+}
+
+/// Returns the delay after which to retry a registration, if the Intent Broker
+/// rejected it with one.
+fn retry_after(error: &Error) -> Option<Duration> {
+    let status = std::error::Error::source(error)?.downcast_ref::<Status>()?;
+    status
+        .metadata()
+        .get(RETRY_AFTER_METADATA_KEY)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .map(Duration::from_millis)
+}
+
+/// Serves the intents of a provider to the Intent Broker.
+pub struct IntentsRegistration {
+    intents: Vec<IntentRegistration>,
+}
+
+#[async_trait]
+impl RegistrationService for IntentsRegistration {
+    async fn get_registration(
+        &self,
+        _: Request<GetRegistrationRequest>,
+    ) -> Result<Response<GetRegistrationResponse>, Status> {
+        Ok(Response::new(GetRegistrationResponse { intents: self.intents.clone() }))
     }
 }
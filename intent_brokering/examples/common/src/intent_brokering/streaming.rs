@@ -4,7 +4,8 @@
 
 use intent_brokering_common::streaming_ess::StreamingEss;
 use intent_brokering_proto::common::{
-    fulfillment::Fulfillment, ReadFulfillment, ReadIntent, SubscribeIntent, ValueEnum, ValueMessage,
+    fulfillment::Fulfillment, NullValue, ReadFulfillment, ReadIntent, SubscribeIntent, ValueEnum,
+    ValueMessage,
 };
 use keyvalue::{InMemoryKeyValueStore, Observer};
 use std::sync::RwLock;
@@ -13,13 +14,17 @@ use tonic::Status;
 type EventId = Box<str>;
 
 /// Wrapper around the [`StreamingEss`](StreamingEss) to allow implementing the
-/// `Observer` trait for said type.
+/// `Observer` trait for said type. Removals are published without a value.
 #[derive(Clone)]
-struct InternalStreamingEss<T>(StreamingEss<(EventId, T)>);
+struct InternalStreamingEss<T>(StreamingEss<(EventId, Option<T>)>);
 
 impl<T: Clone + Send + 'static> Observer<EventId, T> for InternalStreamingEss<T> {
     fn on_set(&mut self, key: &EventId, value: &T) {
-        self.0.publish(key, (key.clone(), value.clone()));
+        self.0.publish(key, (key.clone(), Some(value.clone())));
+    }
+
+    fn on_remove(&mut self, key: &EventId) {
+        self.0.publish(key, (key.clone(), None));
     }
 }
 
@@ -33,7 +38,7 @@ pub struct StreamingStore<T> {
 }
 
 impl<T> StreamingStore<T> {
-    pub fn ess(&self) -> &StreamingEss<(EventId, T)> {
+    pub fn ess(&self) -> &StreamingEss<(EventId, Option<T>)> {
         &self.ess.0
     }
 }
@@ -65,6 +70,25 @@ where
     pub fn set(&self, key: EventId, value: T) {
         self.store.write().unwrap().set(key, value)
     }
+
+    /// Remove a value from the store.
+    pub fn remove(&self, key: &EventId) -> Option<T> {
+        self.store.write().unwrap().remove(key)
+    }
+
+    /// Take a snapshot of all entries of the store.
+    pub fn entries(&self) -> Vec<(EventId, T)> {
+        self.store.read().unwrap().iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+
+    /// Write several values to the store at once. Readers observe either none
+    /// or all of the values.
+    pub fn set_many(&self, entries: impl IntoIterator<Item = (EventId, T)>) {
+        let mut store = self.store.write().unwrap();
+        for (key, value) in entries {
+            store.set(key, value);
+        }
+    }
 }
 
 pub trait ProtoExt {
@@ -77,7 +101,9 @@ where
     T: Into<ValueEnum> + Clone + Send + Sync + 'static,
 {
     fn subscribe(&self, subscribe_intent: SubscribeIntent) -> Result<Fulfillment, Status> {
-        let result = self.ess().serve_subscriptions(subscribe_intent, |(_, v)| v.into())?;
+        let result = self.ess().serve_subscriptions(subscribe_intent, |(_, v)| {
+            v.map(|v| v.into()).unwrap_or(ValueEnum::Null(NullValue::Unspecified as i32))
+        })?;
         Ok(Fulfillment::Subscribe(result))
     }
 
@@ -85,6 +111,7 @@ where
         let value = self.get(&intent.key.into());
         Fulfillment::Read(ReadFulfillment {
             value: Some(ValueMessage { value: value.map(|v| v.into()) }),
+            ..Default::default()
         })
     }
 }
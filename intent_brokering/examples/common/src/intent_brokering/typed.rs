@@ -0,0 +1,104 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Typed access to the properties, commands and events of a namespace on top
+//! of [`IntentBrokering`], instead of passing namespaces, paths and values
+//! around as strings. The members are usually generated from the catalog of
+//! the Intent Broker with `chariott-codegen`, e.g.:
+//!
+//! ```ignore
+//! let speed = client.read_property::<sdv_vdt::VehicleSpeed>().await?;
+//! ```
+
+use async_trait::async_trait;
+use futures::{stream::BoxStream, StreamExt as _};
+use intent_brokering_common::error::Error;
+
+use super::api::{IntentBrokering, IntentBrokeringExt as _};
+use super::value::Value;
+
+/// A member of a namespace, identified by its path.
+pub trait Member {
+    const NAMESPACE: &'static str;
+    const PATH: &'static str;
+}
+
+/// A property which can be read or written.
+pub trait Property: Member {
+    type Value: FromValue + Into<Value> + Send;
+}
+
+/// A command which can be invoked.
+pub trait Command: Member {
+    type Return: FromValue + Send;
+}
+
+/// An event source which can be subscribed to.
+pub trait EventSource: Member {
+    type Value: FromValue + Send;
+}
+
+/// Converts values received from the Intent Broker into typed values.
+pub trait FromValue: Sized {
+    fn from_value(value: Value) -> Result<Self, Error>;
+}
+
+impl FromValue for Value {
+    fn from_value(value: Value) -> Result<Self, Error> {
+        Ok(value)
+    }
+}
+
+impl FromValue for i32 {
+    fn from_value(value: Value) -> Result<Self, Error> {
+        value.to_i32().map_err(|_| Error::new("Expected a value of type 'int32'."))
+    }
+}
+
+impl FromValue for i64 {
+    fn from_value(value: Value) -> Result<Self, Error> {
+        value.to_i64().map_err(|_| Error::new("Expected a value of type 'int64'."))
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: Value) -> Result<Self, Error> {
+        value.to_bool().map_err(|_| Error::new("Expected a value of type 'bool'."))
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: Value) -> Result<Self, Error> {
+        value.into_string().map_err(|_| Error::new("Expected a value of type 'string'."))
+    }
+}
+
+/// Reads, writes, invokes and subscribes to typed members of namespaces.
+#[async_trait]
+pub trait TypedIntentBrokering: IntentBrokering {
+    async fn read_property<P: Property>(&mut self) -> Result<Option<P::Value>, Error> {
+        self.read(P::NAMESPACE, P::PATH).await?.map(P::Value::from_value).transpose()
+    }
+
+    async fn write_property<P: Property>(&mut self, value: P::Value) -> Result<(), Error> {
+        self.write(P::NAMESPACE, P::PATH, value.into()).await
+    }
+
+    async fn invoke_command<C: Command>(&mut self, args: Vec<Value>) -> Result<C::Return, Error> {
+        C::Return::from_value(self.invoke(C::NAMESPACE, C::PATH, args).await?)
+    }
+
+    /// Listens to the events of a source, which are converted to its type.
+    async fn listen_event<'b, E: EventSource + 'b>(
+        &mut self,
+    ) -> Result<BoxStream<'b, Result<E::Value, Error>>, Error>
+    where
+        Self: Sized,
+    {
+        let events = self.listen(E::NAMESPACE, [E::PATH.into()]).await?;
+        Ok(events.map(|event| event.and_then(|event| E::Value::from_value(event.data))).boxed())
+    }
+}
+
+impl<T: IntentBrokering> TypedIntentBrokering for T {}
@@ -168,3 +168,31 @@ impl From<bool> for Value {
         Value(ValueEnum::Bool(value))
     }
 }
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value(ValueEnum::String(value))
+    }
+}
+
+macro_rules! impl_try_from_value {
+    ($variant:path, $target:ty) => {
+        impl TryFrom<Value> for $target {
+            type Error = InvalidValueType;
+
+            fn try_from(value: Value) -> Result<Self, Self::Error> {
+                match value {
+                    Value($variant(value)) => Ok(value),
+                    value => Err(InvalidValueType(value)),
+                }
+            }
+        }
+    };
+}
+
+impl_try_from_value!(ValueEnum::Bool, bool);
+impl_try_from_value!(ValueEnum::Int32, i32);
+impl_try_from_value!(ValueEnum::Int64, i64);
+impl_try_from_value!(ValueEnum::Float32, f32);
+impl_try_from_value!(ValueEnum::Float64, f64);
+impl_try_from_value!(ValueEnum::String, String);
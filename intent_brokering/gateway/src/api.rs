@@ -0,0 +1,323 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Translates the REST API of the gateway into calls of the gRPC runtime API:
+//!
+//! - `POST /v1/{namespace}/invoke` with `{ "command": ..., "args": [...] }`
+//! - `GET /v1/{namespace}/read?key=...`
+//! - `PUT /v1/{namespace}/write` with `{ "key": ..., "value": ... }`
+//! - `GET /v1/{namespace}/subscribe?sources=...,...` as Server-Sent Events
+//! - `GET /v1/events` as WebSocket, see [`crate::websocket`]
+//!
+//! Errors are returned as `{ "code": ..., "message": ... }`, with the HTTP
+//! status derived from the gRPC status code.
+
+use std::{collections::HashMap, convert::Infallible, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::{
+        sse::{Event as SseEvent, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post, put},
+    Router,
+};
+use futures::{future, Stream, StreamExt as _};
+use intent_brokering_proto::{
+    common::{
+        FulfillmentEnum, IntentEnum, IntentMessage, InvokeIntent, ReadIntent, SubscribeIntent,
+        WriteIntent,
+    },
+    runtime::{intent_brokering_service_client::IntentBrokeringServiceClient, FulfillRequest},
+    streaming::{channel_service_client::ChannelServiceClient, Event, OpenRequest},
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value as JsonValue};
+use tonic::{transport::Channel, Code, Status, Streaming};
+
+use crate::{json, websocket};
+
+const CHANNEL_ID_HEADER_NAME: &str = "x-chariott-channel-id";
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// A gRPC status, returned as JSON with the corresponding HTTP status.
+pub struct ApiError(pub Status);
+
+impl From<Status> for ApiError {
+    fn from(status: Status) -> Self {
+        Self(status)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = json!({ "code": format!("{:?}", self.0.code()), "message": self.0.message() });
+        (http_status(self.0.code()), [(CONTENT_TYPE, "application/json")], body.to_string())
+            .into_response()
+    }
+}
+
+/// Maps gRPC status codes to HTTP status codes, following the mapping of the
+/// Google API design guide.
+fn http_status(code: Code) -> StatusCode {
+    match code {
+        Code::Ok => StatusCode::OK,
+        Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => {
+            StatusCode::BAD_REQUEST
+        }
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::AlreadyExists | Code::Aborted => StatusCode::CONFLICT,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::Cancelled => StatusCode::REQUEST_TIMEOUT,
+        Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        Code::Unknown | Code::Internal | Code::DataLoss => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn json_response(body: JsonValue) -> Response {
+    ([(CONTENT_TYPE, "application/json")], body.to_string()).into_response()
+}
+
+fn parse_body<T: DeserializeOwned>(body: &str) -> Result<T, ApiError> {
+    serde_json::from_str(body)
+        .map_err(|e| Status::invalid_argument(format!("Invalid request body: {e}")).into())
+}
+
+#[derive(Clone)]
+pub struct Gateway {
+    channel: Channel,
+    websocket: Arc<websocket::Config>,
+}
+
+impl Gateway {
+    pub fn new(channel: Channel, websocket: websocket::Config) -> Self {
+        Self { channel, websocket: Arc::new(websocket) }
+    }
+
+    pub fn websocket(&self) -> &websocket::Config {
+        &self.websocket
+    }
+
+    pub async fn fulfill(
+        &self,
+        namespace: String,
+        intent: IntentEnum,
+    ) -> Result<FulfillmentEnum, Status> {
+        IntentBrokeringServiceClient::new(self.channel.clone())
+            .fulfill(FulfillRequest {
+                namespace,
+                intent: Some(IntentMessage { intent: Some(intent) }),
+            })
+            .await?
+            .into_inner()
+            .fulfillment
+            .and_then(|f| f.fulfillment)
+            .ok_or_else(|| Status::internal("Did not receive fulfillment."))
+    }
+
+    pub async fn subscribe(
+        &self,
+        namespace: String,
+        channel_id: String,
+        sources: Vec<String>,
+        filters: HashMap<String, String>,
+    ) -> Result<(), Status> {
+        let intent = SubscribeIntent { channel_id, sources, filters };
+        match self.fulfill(namespace, IntentEnum::Subscribe(intent)).await? {
+            FulfillmentEnum::Subscribe(_) => Ok(()),
+            _ => Err(unexpected_fulfillment().0),
+        }
+    }
+
+    /// Opens a channel with the Intent Broker and returns its id, which can
+    /// be subscribed to sources of any namespace, and the stream of events.
+    /// Events relayed by the Intent Broker have their source prefixed with
+    /// their namespace, i.e. `{namespace}/{source}`.
+    pub async fn open_channel(&self) -> Result<(String, Streaming<Event>), Status> {
+        let response =
+            ChannelServiceClient::new(self.channel.clone()).open(OpenRequest::default()).await?;
+        let channel_id = response
+            .metadata()
+            .get(CHANNEL_ID_HEADER_NAME)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| Status::internal("Channel ID header not found."))?
+            .to_owned();
+
+        Ok((channel_id, response.into_inner()))
+    }
+}
+
+fn unexpected_fulfillment() -> ApiError {
+    Status::internal("Received unexpected fulfillment.").into()
+}
+
+/// Creates the router of the REST API, which forwards requests to the
+/// Intent Broker connected to through the given channel.
+pub fn router(channel: Channel, websocket: websocket::Config) -> Router {
+    Router::new()
+        .route("/v1/:namespace/invoke", post(invoke))
+        .route("/v1/:namespace/read", get(read))
+        .route("/v1/:namespace/write", put(write))
+        .route("/v1/:namespace/subscribe", get(subscribe))
+        .route("/v1/events", get(websocket::connect))
+        .with_state(Gateway::new(channel, websocket))
+}
+
+#[derive(Deserialize)]
+struct InvokeBody {
+    command: String,
+    #[serde(default)]
+    args: Vec<JsonValue>,
+}
+
+async fn invoke(
+    State(gateway): State<Gateway>,
+    Path(namespace): Path<String>,
+    body: String,
+) -> Result<Response, ApiError> {
+    let body: InvokeBody = parse_body(&body)?;
+    let intent = InvokeIntent {
+        command: body.command,
+        args: body.args.into_iter().map(json::decode_message).collect(),
+    };
+
+    match gateway.fulfill(namespace, IntentEnum::Invoke(intent)).await? {
+        FulfillmentEnum::Invoke(fulfillment) => Ok(json_response(
+            json!({ "return": json::encode(fulfillment.r#return.and_then(|v| v.value)) }),
+        )),
+        _ => Err(unexpected_fulfillment()),
+    }
+}
+
+#[derive(Deserialize)]
+struct ReadQuery {
+    key: String,
+}
+
+async fn read(
+    State(gateway): State<Gateway>,
+    Path(namespace): Path<String>,
+    Query(query): Query<ReadQuery>,
+) -> Result<Response, ApiError> {
+    match gateway.fulfill(namespace, IntentEnum::Read(ReadIntent { key: query.key })).await? {
+        FulfillmentEnum::Read(fulfillment) => Ok(json_response(
+            json!({ "value": json::encode(fulfillment.value.and_then(|v| v.value)) }),
+        )),
+        _ => Err(unexpected_fulfillment()),
+    }
+}
+
+#[derive(Deserialize)]
+struct WriteBody {
+    key: String,
+    value: JsonValue,
+}
+
+async fn write(
+    State(gateway): State<Gateway>,
+    Path(namespace): Path<String>,
+    body: String,
+) -> Result<StatusCode, ApiError> {
+    let body: WriteBody = parse_body(&body)?;
+    let intent = WriteIntent {
+        key: body.key,
+        value: Some(json::decode_message(body.value)),
+        precondition: None,
+    };
+
+    match gateway.fulfill(namespace, IntentEnum::Write(intent)).await? {
+        FulfillmentEnum::Write(_) => Ok(StatusCode::NO_CONTENT),
+        _ => Err(unexpected_fulfillment()),
+    }
+}
+
+#[derive(Deserialize)]
+struct SubscribeQuery {
+    sources: String,
+}
+
+/// Opens a channel with the Intent Broker and subscribes it to the sources,
+/// relaying each event as `{ "source": ..., "value": ..., "seq": ... }`.
+/// Dropped events are reported as `gap` events with
+/// `{ "source": ..., "from_seq": ..., "to_seq": ... }`. The stream ends when
+/// the channel is closed.
+async fn subscribe(
+    State(gateway): State<Gateway>,
+    Path(namespace): Path<String>,
+    Query(query): Query<SubscribeQuery>,
+) -> Result<Sse<impl Stream<Item = Result<SseEvent, Infallible>>>, ApiError> {
+    let sources: Vec<String> =
+        query.sources.split(',').filter(|s| !s.is_empty()).map(str::to_owned).collect();
+
+    if sources.is_empty() {
+        return Err(Status::invalid_argument("At least one source must be specified.").into());
+    }
+
+    let (channel_id, events) = gateway.open_channel().await?;
+    gateway.subscribe(namespace.clone(), channel_id, sources, HashMap::new()).await?;
+
+    let prefix = format!("{namespace}/");
+    let events = events.scan((), move |_, event| {
+        future::ready(match event {
+            Ok(event) => {
+                let source = event.source.strip_prefix(&prefix).unwrap_or(&event.source);
+                if let Some(gap) = event.gap {
+                    let data = json!({
+                        "source": source,
+                        "from_seq": gap.from_seq,
+                        "to_seq": gap.to_seq,
+                    });
+                    return future::ready(Some(Ok(SseEvent::default()
+                        .event("gap")
+                        .data(data.to_string()))));
+                }
+
+                let data = json!({
+                    "source": source,
+                    "value": json::encode(event.value.and_then(|v| v.value)),
+                    "seq": event.seq,
+                });
+                Some(Ok(SseEvent::default().id(event.seq.to_string()).data(data.to_string())))
+            }
+            Err(e) => {
+                tracing::debug!("Event stream ended: {e}");
+                None
+            }
+        })
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::new().interval(KEEP_ALIVE_INTERVAL)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(Code::NotFound, StatusCode::NOT_FOUND)]
+    #[test_case(Code::InvalidArgument, StatusCode::BAD_REQUEST)]
+    #[test_case(Code::FailedPrecondition, StatusCode::BAD_REQUEST)]
+    #[test_case(Code::Unimplemented, StatusCode::NOT_IMPLEMENTED)]
+    #[test_case(Code::Unavailable, StatusCode::SERVICE_UNAVAILABLE)]
+    #[test_case(Code::Unknown, StatusCode::INTERNAL_SERVER_ERROR)]
+    fn http_status_maps_grpc_code(code: Code, expected: StatusCode) {
+        assert_eq!(expected, http_status(code));
+    }
+
+    #[test]
+    fn api_error_is_returned_as_json() {
+        // act
+        let response = ApiError(Status::not_found("No provider found.")).into_response();
+
+        // assert
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+        assert_eq!("application/json", response.headers()[CONTENT_TYPE]);
+    }
+}
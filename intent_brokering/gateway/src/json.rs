@@ -0,0 +1,155 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Conversion between `common::Value` and plain JSON values. Values are
+//! mapped to their natural JSON counterpart, at the cost of the conversion
+//! not being lossless: JSON integers are converted to `int32` if they fit
+//! into 32 bits and to `int64` otherwise, all other numbers to `float64`.
+//! Timestamps, blobs and `Any` values are not inferred from JSON.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use intent_brokering_proto::common::{Blob, List, Map, NullValue, ValueEnum, ValueMessage};
+use serde_json::{json, Number, Value as JsonValue};
+
+/// Converts a value to JSON. Timestamps are represented as RFC 3339 strings,
+/// blobs and `Any` values as objects with their payload encoded as base64.
+pub fn encode(value: Option<ValueEnum>) -> JsonValue {
+    match value {
+        None | Some(ValueEnum::Null(_)) => JsonValue::Null,
+        Some(ValueEnum::Bool(value)) => value.into(),
+        Some(ValueEnum::Int32(value)) => value.into(),
+        Some(ValueEnum::Int64(value)) => value.into(),
+        Some(ValueEnum::Float32(value)) => float(value.into()),
+        Some(ValueEnum::Float64(value)) => float(value),
+        Some(ValueEnum::String(value)) => value.into(),
+        Some(ValueEnum::Timestamp(value)) => value.to_string().into(),
+        Some(ValueEnum::List(List { value })) => {
+            value.into_iter().map(|v| encode(v.value)).collect::<Vec<_>>().into()
+        }
+        Some(ValueEnum::Map(Map { map })) => map
+            .into_iter()
+            .map(|(k, v)| (k, encode(v.value)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        Some(ValueEnum::Blob(Blob { media_type, bytes })) => {
+            json!({ "media_type": media_type, "bytes": BASE64.encode(bytes) })
+        }
+        Some(ValueEnum::Any(any)) => {
+            json!({ "type_url": any.type_url, "value": BASE64.encode(any.value) })
+        }
+    }
+}
+
+/// JSON cannot represent NaN and infinity, which are converted to `null`.
+fn float(value: f64) -> JsonValue {
+    Number::from_f64(value).map(JsonValue::Number).unwrap_or(JsonValue::Null)
+}
+
+/// Converts a JSON value to a value.
+pub fn decode(value: JsonValue) -> ValueEnum {
+    match value {
+        JsonValue::Null => ValueEnum::Null(NullValue::Unspecified as i32),
+        JsonValue::Bool(value) => ValueEnum::Bool(value),
+        JsonValue::Number(number) => match number.as_i64() {
+            Some(value) => match i32::try_from(value) {
+                Ok(value) => ValueEnum::Int32(value),
+                Err(_) => ValueEnum::Int64(value),
+            },
+            // Integers exceeding the range of `i64` lose precision.
+            None => ValueEnum::Float64(number.as_f64().unwrap_or_default()),
+        },
+        JsonValue::String(value) => ValueEnum::String(value),
+        JsonValue::Array(values) => {
+            ValueEnum::List(List { value: values.into_iter().map(decode_message).collect() })
+        }
+        JsonValue::Object(map) => ValueEnum::Map(Map {
+            map: map.into_iter().map(|(k, v)| (k, decode_message(v))).collect(),
+        }),
+    }
+}
+
+/// Converts a JSON value to a value message.
+pub fn decode_message(value: JsonValue) -> ValueMessage {
+    ValueMessage { value: Some(decode(value)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(json!(null), ValueEnum::Null(0) ; "null")]
+    #[test_case(json!(true), ValueEnum::Bool(true) ; "bool")]
+    #[test_case(json!(42), ValueEnum::Int32(42) ; "int32")]
+    #[test_case(json!(-5_000_000_000i64), ValueEnum::Int64(-5_000_000_000) ; "int64")]
+    #[test_case(json!(1.5), ValueEnum::Float64(1.5) ; "float64")]
+    #[test_case(json!("foo"), ValueEnum::String("foo".to_owned()) ; "string")]
+    fn decode_maps_scalars(value: JsonValue, expected: ValueEnum) {
+        assert_eq!(expected, decode(value));
+    }
+
+    #[test]
+    fn decode_maps_arrays_and_objects_recursively() {
+        // arrange
+        let value = json!({ "list": [1, "a"] });
+
+        // act
+        let result = decode(value);
+
+        // assert
+        let list = ValueEnum::List(List {
+            value: vec![
+                ValueMessage { value: Some(ValueEnum::Int32(1)) },
+                ValueMessage { value: Some(ValueEnum::String("a".to_owned())) },
+            ],
+        });
+        let expected = ValueEnum::Map(Map {
+            map: [("list".to_owned(), ValueMessage { value: Some(list) })].into(),
+        });
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn encode_roundtrips_decoded_values() {
+        // arrange
+        let value = json!({ "a": [true, 1, 5_000_000_000i64, 0.25, "b", null], "c": {} });
+
+        // act
+        let result = encode(Some(decode(value.clone())));
+
+        // assert
+        assert_eq!(value, result);
+    }
+
+    #[test]
+    fn encode_maps_missing_value_and_nan_to_null() {
+        assert_eq!(JsonValue::Null, encode(None));
+        assert_eq!(JsonValue::Null, encode(Some(ValueEnum::Float32(f32::NAN))));
+    }
+
+    #[test]
+    fn encode_maps_blob_to_base64() {
+        // arrange
+        let blob =
+            ValueEnum::Blob(Blob { media_type: "text/plain".to_owned(), bytes: b"hi".to_vec() });
+
+        // act
+        let result = encode(Some(blob));
+
+        // assert
+        assert_eq!(json!({ "media_type": "text/plain", "bytes": "aGk=" }), result);
+    }
+
+    #[test]
+    fn encode_maps_timestamp_to_rfc3339() {
+        // arrange
+        let timestamp = ValueEnum::Timestamp(prost_types::Timestamp { seconds: 0, nanos: 0 });
+
+        // act
+        let result = encode(Some(timestamp));
+
+        // assert
+        assert_eq!(json!("1970-01-01T00:00:00Z"), result);
+    }
+}
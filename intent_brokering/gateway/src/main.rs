@@ -0,0 +1,66 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! An optional REST gateway in front of the Intent Brokering runtime API, for
+//! clients without gRPC support such as web dashboards and scripts.
+
+mod api;
+mod json;
+mod websocket;
+
+use std::net::SocketAddr;
+
+use intent_brokering_common::config::env;
+use intent_brokering_common::shutdown::ctrl_c_cancellation;
+use tonic::transport::Endpoint;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    const INTENT_BROKER_URL_ENV: &str = "INTENT_BROKER_URL";
+    const GATEWAY_ADDRESS_ENV: &str = "GATEWAY_ADDRESS";
+    const GATEWAY_TOKEN_ENV: &str = "GATEWAY_TOKEN";
+    const GATEWAY_WS_MAX_SUBSCRIPTIONS_ENV: &str = "GATEWAY_WS_MAX_SUBSCRIPTIONS";
+    const GATEWAY_WS_MAX_MESSAGE_SIZE_ENV: &str = "GATEWAY_WS_MAX_MESSAGE_SIZE";
+
+    let collector = tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::builder()
+                .with_default_directive(tracing::Level::INFO.into())
+                .from_env_lossy(),
+        )
+        .finish();
+
+    collector.init();
+
+    let broker_url: String =
+        env(INTENT_BROKER_URL_ENV).unwrap_or_else(|| "http://localhost:4243".to_owned()); // DevSkim: ignore DS137138
+    let address: SocketAddr =
+        env(GATEWAY_ADDRESS_ENV).unwrap_or_else(|| "0.0.0.0:4280".parse().unwrap());
+
+    let websocket = websocket::Config {
+        token: env::<String>(GATEWAY_TOKEN_ENV).map(Into::into),
+        max_subscriptions: env(GATEWAY_WS_MAX_SUBSCRIPTIONS_ENV).unwrap_or(64),
+        max_message_size: env(GATEWAY_WS_MAX_MESSAGE_SIZE_ENV).unwrap_or(64 * 1024),
+    };
+
+    if websocket.token.is_none() {
+        tracing::warn!("{GATEWAY_TOKEN_ENV} is not set, WebSocket clients are not authenticated.");
+    }
+
+    // The connection is established on the first request, such that the
+    // gateway can be started before the Intent Broker.
+    let channel = Endpoint::from_shared(broker_url.clone())?.connect_lazy();
+
+    tracing::info!("Gateway listening on {address}, forwarding to {broker_url}");
+
+    let cancellation_token = ctrl_c_cancellation();
+    axum::Server::bind(&address)
+        .serve(api::router(channel, websocket).into_make_service())
+        .with_graceful_shutdown(cancellation_token.cancelled())
+        .await?;
+
+    Ok(())
+}
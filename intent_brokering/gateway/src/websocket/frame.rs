@@ -0,0 +1,319 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Reading and writing of WebSocket frames as specified by RFC 6455. Frames
+//! received from clients must be masked, while frames sent by the server are
+//! not. Fragmented messages are reassembled before they are returned.
+
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+
+const FIN: u8 = 0x80;
+const MASK: u8 = 0x80;
+const MAX_CONTROL_PAYLOAD_LENGTH: usize = 125;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// Close status codes used by the gateway.
+pub const CLOSE_NORMAL: u16 = 1000;
+pub const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+pub const CLOSE_UNSUPPORTED_DATA: u16 = 1003;
+pub const CLOSE_MESSAGE_TOO_BIG: u16 = 1009;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(Option<(u16, String)>),
+}
+
+/// An error while reading a message, with the status code to close the
+/// connection with.
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    Protocol(u16, &'static str),
+}
+
+impl From<io::Error> for ReadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Reads messages from a client.
+pub struct Reader<R> {
+    reader: R,
+    max_size: usize,
+    /// The opcode and payload of a fragmented message being received.
+    fragments: Option<(u8, Vec<u8>)>,
+}
+
+impl<R: AsyncRead + Unpin> Reader<R> {
+    /// Creates a reader which fails if the payload of a message exceeds the
+    /// given size.
+    pub fn new(reader: R, max_size: usize) -> Self {
+        Self { reader, max_size, fragments: None }
+    }
+
+    pub async fn read(&mut self) -> Result<Message, ReadError> {
+        loop {
+            let mut header = [0; 2];
+            self.reader.read_exact(&mut header).await?;
+
+            let fin = header[0] & FIN != 0;
+            let opcode = header[0] & 0x0F;
+
+            if header[0] & 0x70 != 0 {
+                return Err(ReadError::Protocol(
+                    CLOSE_PROTOCOL_ERROR,
+                    "Extensions are not supported.",
+                ));
+            }
+
+            if header[1] & MASK == 0 {
+                return Err(ReadError::Protocol(
+                    CLOSE_PROTOCOL_ERROR,
+                    "Client frames must be masked.",
+                ));
+            }
+
+            let length = match header[1] & 0x7F {
+                126 => usize::from(self.reader.read_u16().await?),
+                127 => usize::try_from(self.reader.read_u64().await?).unwrap_or(usize::MAX),
+                length => usize::from(length),
+            };
+
+            let is_control = opcode & 0x8 != 0;
+            if is_control && (!fin || length > MAX_CONTROL_PAYLOAD_LENGTH) {
+                return Err(ReadError::Protocol(CLOSE_PROTOCOL_ERROR, "Invalid control frame."));
+            }
+
+            let buffered = self.fragments.as_ref().map_or(0, |(_, payload)| payload.len());
+            if length > self.max_size.saturating_sub(buffered) {
+                return Err(ReadError::Protocol(CLOSE_MESSAGE_TOO_BIG, "Message is too big."));
+            }
+
+            let mut mask = [0; 4];
+            self.reader.read_exact(&mut mask).await?;
+            let mut payload = vec![0; length];
+            self.reader.read_exact(&mut payload).await?;
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+
+            // Control frames may be interleaved with the fragments of a
+            // message, which remain buffered.
+            if is_control {
+                return match opcode {
+                    OPCODE_PING => Ok(Message::Ping(payload)),
+                    OPCODE_PONG => Ok(Message::Pong(payload)),
+                    OPCODE_CLOSE => Ok(Message::Close(parse_close(&payload))),
+                    _ => Err(ReadError::Protocol(CLOSE_PROTOCOL_ERROR, "Unknown opcode.")),
+                };
+            }
+
+            let (opcode, payload) = match (opcode, self.fragments.take()) {
+                (OPCODE_CONTINUATION, Some((opcode, mut buffer))) => {
+                    buffer.extend(payload);
+                    (opcode, buffer)
+                }
+                (OPCODE_CONTINUATION, None) => {
+                    return Err(ReadError::Protocol(
+                        CLOSE_PROTOCOL_ERROR,
+                        "Unexpected continuation frame.",
+                    ))
+                }
+                (_, Some(_)) => {
+                    return Err(ReadError::Protocol(
+                        CLOSE_PROTOCOL_ERROR,
+                        "Expected continuation frame.",
+                    ))
+                }
+                (opcode, None) => (opcode, payload),
+            };
+
+            if !fin {
+                self.fragments = Some((opcode, payload));
+                continue;
+            }
+
+            return match opcode {
+                OPCODE_TEXT => String::from_utf8(payload)
+                    .map(Message::Text)
+                    .map_err(|_| ReadError::Protocol(CLOSE_UNSUPPORTED_DATA, "Text is not UTF-8.")),
+                OPCODE_BINARY => Ok(Message::Binary(payload)),
+                _ => Err(ReadError::Protocol(CLOSE_PROTOCOL_ERROR, "Unknown opcode.")),
+            };
+        }
+    }
+}
+
+fn parse_close(payload: &[u8]) -> Option<(u16, String)> {
+    match payload {
+        [high, low, reason @ ..] => {
+            Some((u16::from_be_bytes([*high, *low]), String::from_utf8_lossy(reason).into_owned()))
+        }
+        _ => None,
+    }
+}
+
+/// Writes a message as a single, unmasked frame.
+pub async fn write_message(
+    writer: &mut (impl AsyncWrite + Unpin),
+    message: &Message,
+) -> io::Result<()> {
+    let close;
+    let (opcode, payload): (u8, &[u8]) = match message {
+        Message::Text(text) => (OPCODE_TEXT, text.as_bytes()),
+        Message::Binary(payload) => (OPCODE_BINARY, payload),
+        Message::Ping(payload) => (OPCODE_PING, payload),
+        Message::Pong(payload) => (OPCODE_PONG, payload),
+        Message::Close(None) => (OPCODE_CLOSE, &[]),
+        Message::Close(Some((code, reason))) => {
+            close = [&code.to_be_bytes()[..], reason.as_bytes()].concat();
+            (OPCODE_CLOSE, &close)
+        }
+    };
+
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(FIN | opcode);
+    match payload.len() {
+        length @ 0..=125 => frame.push(length as u8),
+        length @ 126..=0xFFFF => {
+            frame.push(126);
+            frame.extend((length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(127);
+            frame.extend((length as u64).to_be_bytes());
+        }
+    }
+    frame.extend(payload);
+
+    writer.write_all(&frame).await?;
+    writer.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A masked frame containing "Hello", as given by RFC 6455.
+    const MASKED_HELLO: [u8; 11] =
+        [0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58];
+
+    fn masked(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [1, 2, 3, 4];
+        let mut frame = vec![if fin { FIN } else { 0 } | opcode, MASK | payload.len() as u8];
+        frame.extend(mask);
+        frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        frame
+    }
+
+    #[tokio::test]
+    async fn read_unmasks_text_message() {
+        // arrange
+        let mut reader = Reader::new(&MASKED_HELLO[..], 1024);
+
+        // act
+        let result = reader.read().await.unwrap();
+
+        // assert
+        assert_eq!(Message::Text("Hello".to_owned()), result);
+    }
+
+    #[tokio::test]
+    async fn read_reassembles_fragments_interleaved_with_control_frames() {
+        // arrange
+        let input = [
+            masked(false, OPCODE_TEXT, b"Hel"),
+            masked(true, OPCODE_PING, b"p"),
+            masked(true, OPCODE_CONTINUATION, b"lo"),
+        ]
+        .concat();
+        let mut reader = Reader::new(&input[..], 1024);
+
+        // act
+        let first = reader.read().await.unwrap();
+        let second = reader.read().await.unwrap();
+
+        // assert
+        assert_eq!(Message::Ping(b"p".to_vec()), first);
+        assert_eq!(Message::Text("Hello".to_owned()), second);
+    }
+
+    #[tokio::test]
+    async fn read_rejects_unmasked_frame() {
+        // arrange
+        let input = [0x81, 0x01, b'a'];
+        let mut reader = Reader::new(&input[..], 1024);
+
+        // act
+        let result = reader.read().await;
+
+        // assert
+        assert!(matches!(result, Err(ReadError::Protocol(CLOSE_PROTOCOL_ERROR, _))));
+    }
+
+    #[tokio::test]
+    async fn read_rejects_message_exceeding_max_size() {
+        // arrange
+        let input =
+            [masked(false, OPCODE_TEXT, b"Hel"), masked(true, OPCODE_CONTINUATION, b"lo")].concat();
+        let mut reader = Reader::new(&input[..], 4);
+
+        // act
+        let result = reader.read().await;
+
+        // assert
+        assert!(matches!(result, Err(ReadError::Protocol(CLOSE_MESSAGE_TOO_BIG, _))));
+    }
+
+    #[tokio::test]
+    async fn read_parses_close_status() {
+        // arrange
+        let input = masked(true, OPCODE_CLOSE, &[0x03, 0xE8, b'b', b'y', b'e']);
+        let mut reader = Reader::new(&input[..], 1024);
+
+        // act
+        let result = reader.read().await.unwrap();
+
+        // assert
+        assert_eq!(Message::Close(Some((CLOSE_NORMAL, "bye".to_owned()))), result);
+    }
+
+    #[tokio::test]
+    async fn write_message_writes_unmasked_frame() {
+        // arrange
+        let mut output = vec![];
+
+        // act
+        write_message(&mut output, &Message::Text("Hello".to_owned())).await.unwrap();
+
+        // assert
+        assert_eq!(b"\x81\x05Hello".to_vec(), output);
+    }
+
+    #[tokio::test]
+    async fn write_message_uses_extended_length() {
+        // arrange
+        let mut output = vec![];
+
+        // act
+        write_message(&mut output, &Message::Binary(vec![0; 300])).await.unwrap();
+
+        // assert
+        assert_eq!([0x82, 126, 0x01, 0x2C], output[..4]);
+        assert_eq!(304, output.len());
+    }
+}
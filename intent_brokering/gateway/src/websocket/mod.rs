@@ -0,0 +1,538 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! A WebSocket endpoint for event subscriptions, for clients such as HMI web
+//! apps which cannot consume gRPC streams. Clients send JSON requests:
+//!
+//! - `{ "type": "subscribe", "namespace": ..., "sources": [...], "filters": { ... } }`
+//! - `{ "type": "unsubscribe", "namespace": ..., "sources": [...] }`
+//!
+//! An optional `id` is echoed in the response, which is either of type
+//! `subscribed`, `unsubscribed` or `error`. Events are sent as
+//! `{ "type": "event", "seq": ..., "namespace": ..., "source": ..., "value": ... }`,
+//! where `seq` is a sequence number counting the events of the connection.
+//! Dropped events are reported as
+//! `{ "type": "gap", "namespace": ..., "source": ..., "from_seq": ..., "to_seq": ... }`,
+//! with the range of sequence numbers of the source in the Intent Broker.
+//!
+//! The server pings the client periodically and closes the connection if the
+//! client did not send any frame for two intervals.
+
+mod frame;
+mod handshake;
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{
+        header::{
+            AUTHORIZATION, CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY,
+            SEC_WEBSOCKET_VERSION, UPGRADE,
+        },
+        HeaderMap, Request, StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use futures::StreamExt as _;
+use intent_brokering_proto::streaming::Event;
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use tokio::{
+    io::AsyncWrite,
+    select,
+    sync::mpsc,
+    time::{interval_at, Instant},
+};
+use tonic::Status;
+
+use crate::api::{ApiError, Gateway};
+use crate::json;
+
+use self::frame::{Message, ReadError, Reader};
+
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+const CLOSE_INTERNAL_ERROR: u16 = 1011;
+
+/// The configuration of the WebSocket endpoint.
+pub struct Config {
+    /// The bearer token clients must present, either in the `Authorization`
+    /// header or in the `token` query parameter, as browsers cannot set
+    /// headers for WebSocket requests. Clients are not authenticated unless
+    /// a token is configured.
+    pub token: Option<Box<str>>,
+    /// The maximum number of sources a connection can be subscribed to.
+    pub max_subscriptions: usize,
+    /// The maximum size of a message received from a client, in bytes.
+    pub max_message_size: usize,
+}
+
+/// Compares the tokens in constant time, such that the configured token
+/// cannot be guessed from the time taken to reject a token.
+fn tokens_match(expected: &str, actual: &str) -> bool {
+    expected.len() == actual.len()
+        && expected.bytes().zip(actual.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn token<'a>(headers: &'a HeaderMap, query: Option<&'a str>) -> Option<&'a str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| query?.split('&').find_map(|parameter| parameter.strip_prefix("token=")))
+}
+
+fn header_contains(
+    headers: &HeaderMap,
+    name: impl axum::http::header::AsHeaderName,
+    token: &str,
+) -> bool {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|value| value.trim().eq_ignore_ascii_case(token))
+}
+
+/// Authenticates the client and upgrades the connection to a WebSocket.
+pub async fn connect(
+    State(gateway): State<Gateway>,
+    mut request: Request<Body>,
+) -> Result<Response, ApiError> {
+    let headers = request.headers();
+
+    if let Some(expected) = gateway.websocket().token.as_deref() {
+        match token(headers, request.uri().query()) {
+            Some(actual) if tokens_match(expected, actual) => {}
+            _ => return Err(ApiError(Status::unauthenticated("Invalid or missing token."))),
+        }
+    }
+
+    if !header_contains(headers, UPGRADE, "websocket")
+        || !header_contains(headers, CONNECTION, "upgrade")
+    {
+        return Err(ApiError(Status::invalid_argument("Expected a WebSocket upgrade request.")));
+    }
+
+    if !header_contains(headers, SEC_WEBSOCKET_VERSION, "13") {
+        return Err(ApiError(Status::invalid_argument("Unsupported WebSocket version.")));
+    }
+
+    let accept = headers
+        .get(SEC_WEBSOCKET_KEY)
+        .and_then(|key| key.to_str().ok())
+        .map(handshake::accept_key)
+        .ok_or_else(|| ApiError(Status::invalid_argument("WebSocket key is missing.")))?;
+
+    let upgrade = hyper::upgrade::on(&mut request);
+    tokio::spawn(async move {
+        match upgrade.await {
+            Ok(upgraded) => Session::new(gateway).run(upgraded).await,
+            Err(e) => tracing::debug!("WebSocket upgrade failed: {e}"),
+        }
+    });
+
+    Ok((
+        StatusCode::SWITCHING_PROTOCOLS,
+        [
+            (UPGRADE, "websocket".to_owned()),
+            (CONNECTION, "Upgrade".to_owned()),
+            (SEC_WEBSOCKET_ACCEPT, accept),
+        ],
+    )
+        .into_response())
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Command {
+    Subscribe {
+        namespace: String,
+        sources: Vec<String>,
+        /// Optional filter expressions, by source.
+        #[serde(default)]
+        filters: HashMap<String, String>,
+    },
+    Unsubscribe {
+        namespace: String,
+        sources: Vec<String>,
+    },
+}
+
+#[derive(Deserialize)]
+struct ClientRequest {
+    #[serde(default)]
+    id: JsonValue,
+    #[serde(flatten)]
+    command: Command,
+}
+
+struct Session {
+    gateway: Gateway,
+    /// The subscribed sources, by namespace and source.
+    subscriptions: HashSet<(String, String)>,
+    seq: u64,
+}
+
+impl Session {
+    fn new(gateway: Gateway) -> Self {
+        Self { gateway, subscriptions: HashSet::new(), seq: 0 }
+    }
+
+    async fn run<S>(mut self, socket: S)
+    where
+        S: tokio::io::AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (reader, mut writer) = tokio::io::split(socket);
+
+        // Frames are read by a separate task, as reading a frame cannot be
+        // cancelled without losing its partially read data.
+        let (sender, mut messages) = mpsc::channel(1);
+        let mut reader = Reader::new(reader, self.gateway.websocket().max_message_size);
+        let reader = tokio::spawn(async move {
+            loop {
+                let message = reader.read().await;
+                let done = !matches!(
+                    message,
+                    Ok(Message::Text(_) | Message::Ping(_) | Message::Pong(_) | Message::Binary(_))
+                );
+                if sender.send(message).await.is_err() || done {
+                    break;
+                }
+            }
+        });
+
+        let (channel_id, mut events) = match self.gateway.open_channel().await {
+            Ok(channel) => channel,
+            Err(e) => {
+                tracing::warn!("Failed to open channel with the Intent Broker: {e}");
+                _ = close(&mut writer, CLOSE_INTERNAL_ERROR, "Failed to open channel.").await;
+                reader.abort();
+                return;
+            }
+        };
+
+        let mut ping = interval_at(Instant::now() + PING_INTERVAL, PING_INTERVAL);
+        let mut last_seen = Instant::now();
+
+        loop {
+            let message = select! {
+                message = messages.recv() => {
+                    last_seen = Instant::now();
+                    match message {
+                        Some(Ok(Message::Text(text))) => {
+                            Message::Text(self.handle(&channel_id, &text).await.to_string())
+                        }
+                        Some(Ok(Message::Ping(payload))) => Message::Pong(payload),
+                        Some(Ok(Message::Pong(_))) => continue,
+                        Some(Ok(Message::Binary(_))) => {
+                            _ = close(&mut writer, frame::CLOSE_UNSUPPORTED_DATA, "Binary messages are not supported.").await;
+                            break;
+                        }
+                        Some(Ok(Message::Close(_))) => {
+                            _ = close(&mut writer, frame::CLOSE_NORMAL, "").await;
+                            break;
+                        }
+                        Some(Err(ReadError::Protocol(code, reason))) => {
+                            _ = close(&mut writer, code, reason).await;
+                            break;
+                        }
+                        Some(Err(ReadError::Io(_))) | None => break,
+                    }
+                }
+                event = events.next() => match event {
+                    Some(Ok(event)) => match self.event(event) {
+                        Some(event) => Message::Text(event.to_string()),
+                        None => continue,
+                    },
+                    _ => {
+                        _ = close(&mut writer, CLOSE_INTERNAL_ERROR, "Event stream ended.").await;
+                        break;
+                    }
+                },
+                _ = ping.tick() => {
+                    if last_seen.elapsed() > 2 * PING_INTERVAL {
+                        tracing::debug!("Closing WebSocket connection after keepalive timeout.");
+                        break;
+                    }
+
+                    Message::Ping(vec![])
+                }
+            };
+
+            if frame::write_message(&mut writer, &message).await.is_err() {
+                break;
+            }
+        }
+
+        reader.abort();
+    }
+
+    async fn handle(&mut self, channel_id: &str, text: &str) -> JsonValue {
+        let request: ClientRequest = match serde_json::from_str(text) {
+            Ok(request) => request,
+            Err(e) => {
+                return error(
+                    JsonValue::Null,
+                    Status::invalid_argument(format!("Invalid request: {e}")),
+                )
+            }
+        };
+
+        match request.command {
+            Command::Subscribe { namespace, sources, mut filters } => {
+                // Filters cannot be changed for sources already subscribed to.
+                filters.retain(|source, _| {
+                    !self.subscriptions.contains(&(namespace.clone(), source.clone()))
+                });
+                let sources: Vec<String> = sources
+                    .into_iter()
+                    .filter(|source| {
+                        !self.subscriptions.contains(&(namespace.clone(), source.clone()))
+                    })
+                    .collect::<HashSet<_>>()
+                    .into_iter()
+                    .collect();
+
+                let max_subscriptions = self.gateway.websocket().max_subscriptions;
+                if self.subscriptions.len() + sources.len() > max_subscriptions {
+                    return error(
+                        request.id,
+                        Status::resource_exhausted(format!(
+                            "Connections are limited to {max_subscriptions} subscriptions."
+                        )),
+                    );
+                }
+
+                if !sources.is_empty() {
+                    if let Err(e) = self
+                        .gateway
+                        .subscribe(
+                            namespace.clone(),
+                            channel_id.to_owned(),
+                            sources.clone(),
+                            filters,
+                        )
+                        .await
+                    {
+                        return error(request.id, e);
+                    }
+                }
+
+                self.subscriptions
+                    .extend(sources.into_iter().map(|source| (namespace.clone(), source)));
+                json!({ "type": "subscribed", "id": request.id, "namespace": namespace })
+            }
+            // The Intent Broker does not support cancelling subscriptions,
+            // hence events of unsubscribed sources are dropped instead.
+            Command::Unsubscribe { namespace, sources } => {
+                for source in sources {
+                    self.subscriptions.remove(&(namespace.clone(), source));
+                }
+
+                json!({ "type": "unsubscribed", "id": request.id, "namespace": namespace })
+            }
+        }
+    }
+
+    /// Converts an event relayed by the Intent Broker, unless the source was
+    /// unsubscribed from.
+    fn event(&mut self, event: Event) -> Option<JsonValue> {
+        let (namespace, source) = event.source.split_once('/')?;
+        if !self.subscriptions.contains(&(namespace.to_owned(), source.to_owned())) {
+            return None;
+        }
+
+        if let Some(gap) = event.gap {
+            return Some(json!({
+                "type": "gap",
+                "namespace": namespace,
+                "source": source,
+                "from_seq": gap.from_seq,
+                "to_seq": gap.to_seq,
+            }));
+        }
+
+        self.seq += 1;
+        Some(json!({
+            "type": "event",
+            "seq": self.seq,
+            "namespace": namespace,
+            "source": source,
+            "value": json::encode(event.value.and_then(|v| v.value)),
+        }))
+    }
+}
+
+fn error(id: JsonValue, status: Status) -> JsonValue {
+    json!({
+        "type": "error",
+        "id": id,
+        "code": format!("{:?}", status.code()),
+        "message": status.message(),
+    })
+}
+
+async fn close(
+    writer: &mut (impl AsyncWrite + Unpin),
+    code: u16,
+    reason: &str,
+) -> std::io::Result<()> {
+    frame::write_message(writer, &Message::Close(Some((code, reason.to_owned())))).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intent_brokering_proto::{
+        common::{ValueEnum, ValueMessage},
+        streaming::Gap,
+    };
+    use tonic::{transport::Endpoint, Code};
+
+    fn session() -> Session {
+        let channel = Endpoint::from_static("http://localhost:4243").connect_lazy(); // DevSkim: ignore DS137138
+        let config = Config { token: None, max_subscriptions: 2, max_message_size: 1024 };
+        Session::new(Gateway::new(channel, config))
+    }
+
+    fn event(source: &str, value: i32) -> Event {
+        Event {
+            source: source.to_owned(),
+            value: Some(ValueMessage { value: Some(ValueEnum::Int32(value)) }),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn event_is_forwarded_with_connection_sequence_number() {
+        // arrange
+        let mut session = session();
+        session.subscriptions.insert(("sdv.kvs".to_owned(), "a".to_owned()));
+
+        // act
+        let first = session.event(event("sdv.kvs/a", 1));
+        let second = session.event(event("sdv.kvs/a", 2));
+
+        // assert
+        let expected = |seq: u64, value: i32| json!({ "type": "event", "seq": seq, "namespace": "sdv.kvs", "source": "a", "value": value });
+        assert_eq!(Some(expected(1, 1)), first);
+        assert_eq!(Some(expected(2, 2)), second);
+    }
+
+    #[tokio::test]
+    async fn gap_is_forwarded_without_consuming_sequence_number() {
+        // arrange
+        let mut session = session();
+        session.subscriptions.insert(("sdv.kvs".to_owned(), "a".to_owned()));
+        let gap = Event {
+            source: "sdv.kvs/a".to_owned(),
+            gap: Some(Gap { from_seq: 3, to_seq: 5 }),
+            ..Default::default()
+        };
+
+        // act
+        let result = session.event(gap);
+
+        // assert
+        assert_eq!(
+            Some(
+                json!({ "type": "gap", "namespace": "sdv.kvs", "source": "a", "from_seq": 3, "to_seq": 5 })
+            ),
+            result
+        );
+        assert_eq!(0, session.seq);
+    }
+
+    #[tokio::test]
+    async fn event_of_unsubscribed_source_is_dropped() {
+        // arrange
+        let mut session = session();
+        session.subscriptions.insert(("sdv.kvs".to_owned(), "a".to_owned()));
+
+        // act
+        let result = session
+            .handle(
+                "channel",
+                r#"{ "type": "unsubscribe", "namespace": "sdv.kvs", "sources": ["a"] }"#,
+            )
+            .await;
+
+        // assert
+        assert_eq!(json!({ "type": "unsubscribed", "id": null, "namespace": "sdv.kvs" }), result);
+        assert_eq!(None, session.event(event("sdv.kvs/a", 1)));
+        assert_eq!(0, session.seq);
+    }
+
+    #[tokio::test]
+    async fn subscribe_beyond_limit_fails() {
+        // arrange
+        let mut session = session();
+
+        // act
+        let result = session
+            .handle("channel", r#"{ "type": "subscribe", "id": 7, "namespace": "sdv.kvs", "sources": ["a", "b", "c"] }"#)
+            .await;
+
+        // assert
+        assert_eq!(json!(7), result["id"]);
+        assert_eq!(json!("ResourceExhausted"), result["code"]);
+        assert!(session.subscriptions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn invalid_request_fails() {
+        // act
+        let result = session().handle("channel", r#"{ "type": "publish" }"#).await;
+
+        // assert
+        assert_eq!(json!("InvalidArgument"), result["code"]);
+    }
+
+    #[test]
+    fn tokens_match_only_identical_tokens() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secret", "secreT"));
+        assert!(!tokens_match("secret", "secret2"));
+    }
+
+    #[test]
+    fn token_is_read_from_header_or_query() {
+        // arrange
+        let mut headers = HeaderMap::new();
+
+        // act + assert
+        assert_eq!(Some("b"), token(&headers, Some("a=1&token=b")));
+        headers.insert(AUTHORIZATION, "Bearer a".parse().unwrap());
+        assert_eq!(Some("a"), token(&headers, Some("token=b")));
+    }
+
+    #[test]
+    fn header_contains_matches_comma_separated_tokens() {
+        // arrange
+        let mut headers = HeaderMap::new();
+        headers.insert(CONNECTION, "keep-alive, Upgrade".parse().unwrap());
+
+        // act + assert
+        assert!(header_contains(&headers, CONNECTION, "upgrade"));
+        assert!(!header_contains(&headers, CONNECTION, "close"));
+    }
+
+    #[test]
+    fn error_contains_id_and_status() {
+        // act
+        let result = error(json!(1), Status::new(Code::NotFound, "No provider found."));
+
+        // assert
+        assert_eq!(
+            json!({ "type": "error", "id": 1, "code": "NotFound", "message": "No provider found." }),
+            result
+        );
+    }
+}
@@ -11,6 +11,9 @@ use std::hash::Hash;
 /// about field updates.
 pub trait Observer<K, V> {
     fn on_set(&mut self, key: &K, value: &V);
+
+    /// Called when a key is removed from the store. Does nothing by default.
+    fn on_remove(&mut self, _key: &K) {}
 }
 
 /// Implementation of the in memory key value store
@@ -108,6 +111,31 @@ where
             self.store.insert(key, value);
         }
     }
+
+    /// Removes a value from the store
+    ///
+    /// # Arguments
+    /// * `key` - The key to remove the value for
+    ///
+    /// # Returns
+    /// * [`Option<V>`](std::option) that was removed for the given key, if any
+    ///
+    /// > **Note** calls the observer, if any and only if the key existed
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let value = self.store.remove(key);
+        if let (Some(ref mut observer), Some(_)) = (&mut self.observer, &value) {
+            observer.on_remove(key);
+        }
+        value
+    }
+
+    /// Iterates over all entries of the store, in arbitrary order
+    ///
+    /// # Returns
+    /// * An iterator over references to each key and value
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.store.iter()
+    }
 }
 
 #[cfg(test)]
@@ -196,6 +224,53 @@ mod tests {
         store.set("key".into(), "value".into());
     }
 
+    #[test]
+    fn test_key_value_store_remove() {
+        let mut store = setup_none_observer::<String, String>();
+        store.set("key".into(), "value".into());
+        assert_eq!(store.remove(&"key".to_string()), Some("value".to_string()));
+        assert_eq!(store.get(&"key".to_string()), None);
+        assert_eq!(store.remove(&"key".to_string()), None);
+    }
+
+    #[test]
+    fn test_key_value_store_iter() {
+        let mut store = setup_none_observer::<String, String>();
+        store.set("a".into(), "1".into());
+        store.set("b".into(), "2".into());
+        let mut entries: Vec<_> = store.iter().collect();
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![(&"a".to_string(), &"1".to_string()), (&"b".to_string(), &"2".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_observer_gets_notified_only_when_removing_existing_key() {
+        #[derive(Clone)]
+        pub struct MyObserver {
+            counter: Arc<AtomicUsize>,
+        }
+
+        impl Observer<String, String> for MyObserver {
+            fn on_set(&mut self, _key: &String, _value: &String) {}
+
+            fn on_remove(&mut self, key: &String) {
+                assert_eq!(key, "key");
+                self.counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+        }
+
+        let my_observer = MyObserver { counter: Arc::new(AtomicUsize::new(0)) };
+        let mut store = InMemoryKeyValueStore::new(Some(my_observer.clone()));
+        store.set("key".into(), "value".into());
+        store.remove(&"key".to_string());
+        store.remove(&"key".to_string());
+
+        assert_eq!(my_observer.counter.load(std::sync::atomic::Ordering::Relaxed), 1);
+    }
+
     #[test]
     fn test_observer_gets_notified_twice_when_setting_an_unchanged_value() {
         #[derive(Clone)]
@@ -5,10 +5,18 @@
 use std::{error::Error, path::Path};
 use tonic_build::configure;
 
+// The contracts of all versions served side by side, each of which includes
+// the common contract of its version.
+const CONTRACTS: &[&str] = &[
+    "../proto/intent_brokering/runtime/v1/runtime.proto",
+    "../proto/intent_brokering/provider/v1/provider.proto",
+    "../proto/intent_brokering/streaming/v1/streaming.proto",
+];
+
 fn main() -> Result<(), Box<dyn Error>> {
-    compile_with_common("../proto/intent_brokering/runtime/v1/runtime.proto")?;
-    compile_with_common("../proto/intent_brokering/provider/v1/provider.proto")?;
-    compile_with_common("../proto/intent_brokering/streaming/v1/streaming.proto")?;
+    for path in CONTRACTS {
+        compile_with_common(path)?;
+    }
 
     Ok(())
 }
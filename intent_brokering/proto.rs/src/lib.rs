@@ -2,6 +2,21 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
+//! The gRPC contracts of Intent Brokering, generated from the protobuf files
+//! in `proto/intent_brokering/{contract}/{version}`.
+//!
+//! Each version of the contracts is exposed as a module, e.g. [`v1`], such
+//! that the Intent Broker can serve several versions side by side while
+//! deployed providers still speak an older one. The contracts of the current
+//! version are re-exported at the root of the crate. Versions are converted
+//! into each other through the data model of `intent_brokering_common`,
+//! which every version converts from and into.
+//!
+//! To evolve the contracts without breaking deployed providers, add the
+//! protobuf files of the new version next to the existing ones, compile them
+//! in `build.rs`, add a module for the version here, and add the conversions
+//! of the version to the data model.
+
 // see https://github.com/hyperium/tonic/issues/1056
 // and https://github.com/tokio-rs/prost/issues/661#issuecomment-1156606409
 // why we use allow derive_partial_eq_without_eq
@@ -41,7 +56,13 @@ pub mod intent_brokering {
     }
 }
 
-pub use intent_brokering::common::v1 as common;
-pub use intent_brokering::provider::v1 as provider;
-pub use intent_brokering::runtime::v1 as runtime;
-pub use intent_brokering::streaming::v1 as streaming;
+/// The first version of the contracts.
+pub mod v1 {
+    pub use crate::intent_brokering::common::v1 as common;
+    pub use crate::intent_brokering::provider::v1 as provider;
+    pub use crate::intent_brokering::runtime::v1 as runtime;
+    pub use crate::intent_brokering::streaming::v1 as streaming;
+}
+
+// The current version of the contracts.
+pub use v1::{common, provider, runtime, streaming};
@@ -0,0 +1,319 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Accounts the resources used by each caller of the Intent Broker, such that
+//! a single runaway application cannot exhaust them for all others.
+//!
+//! The resources of a caller are its outstanding fulfill requests and the
+//! channels it subscribed through, together with their subscriptions and the
+//! events buffered for them. A channel is accounted to the caller subscribing
+//! through it first, as channels are opened without identifying the caller.
+//! Requests and subscriptions exceeding a ceiling of the caller are rejected
+//! with `RESOURCE_EXHAUSTED`. The usage of all callers is read with the
+//! `usage` key of `system.accounting`.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use tonic::Status;
+
+use crate::identity::Caller;
+use crate::streaming::StreamingEss;
+
+/// The ceilings of the resources of each caller, which are unlimited unless
+/// set.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    outstanding_requests: Option<usize>,
+    channels: Option<usize>,
+    subscriptions: Option<usize>,
+    buffered_events: Option<usize>,
+}
+
+impl Limits {
+    /// The number of fulfill requests of a caller in flight at once.
+    pub fn outstanding_requests(&self) -> Option<usize> {
+        self.outstanding_requests
+    }
+
+    /// The number of channels a caller subscribes through.
+    pub fn channels(&self) -> Option<usize> {
+        self.channels
+    }
+
+    /// The number of subscriptions across the channels of a caller.
+    pub fn subscriptions(&self) -> Option<usize> {
+        self.subscriptions
+    }
+
+    /// The number of events buffered across the channels of a caller, above
+    /// which it cannot subscribe to further sources.
+    pub fn buffered_events(&self) -> Option<usize> {
+        self.buffered_events
+    }
+
+    pub fn set_outstanding_requests(self, value: usize) -> Self {
+        Self { outstanding_requests: Some(value), ..self }
+    }
+
+    pub fn set_channels(self, value: usize) -> Self {
+        Self { channels: Some(value), ..self }
+    }
+
+    pub fn set_subscriptions(self, value: usize) -> Self {
+        Self { subscriptions: Some(value), ..self }
+    }
+
+    pub fn set_buffered_events(self, value: usize) -> Self {
+        Self { buffered_events: Some(value), ..self }
+    }
+}
+
+/// The resources used by a caller.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub outstanding_requests: usize,
+    pub channels: usize,
+    pub subscriptions: usize,
+    pub buffered_events: usize,
+}
+
+#[derive(Default)]
+struct State {
+    outstanding_requests: HashMap<Caller, usize>,
+    channels: HashMap<Box<str>, Caller>,
+}
+
+/// Accounts the resources of callers. Cloning is cheap and refers to the
+/// same accounts.
+#[derive(Clone)]
+pub struct Accounting {
+    ess: StreamingEss,
+    limits: Limits,
+    state: Arc<Mutex<State>>,
+}
+
+impl Accounting {
+    pub fn new(ess: StreamingEss, limits: Limits) -> Self {
+        Self { ess, limits, state: Default::default() }
+    }
+
+    /// Accounts a fulfill request of a caller until the returned guard is
+    /// dropped, unless the caller has too many requests in flight.
+    pub fn begin_request(&self, caller: &Caller) -> Result<RequestGuard, Status> {
+        let mut state = self.state.lock().unwrap();
+        let outstanding = state.outstanding_requests.entry(caller.clone()).or_default();
+
+        if self.limits.outstanding_requests.is_some_and(|limit| *outstanding >= limit) {
+            return Err(self.exhausted(caller, "outstanding requests"));
+        }
+
+        *outstanding += 1;
+        Ok(RequestGuard { accounting: self.clone(), caller: caller.clone() })
+    }
+
+    /// Accounts the subscription of a channel to sources, unless the caller
+    /// would exceed its ceilings.
+    pub fn admit_subscription(
+        &self,
+        caller: &Caller,
+        channel_id: &str,
+        sources: &[String],
+    ) -> Result<(), Status> {
+        let statistics = self.ess.statistics();
+        let mut state = self.state.lock().unwrap();
+        prune_closed_channels(&mut state, statistics.clients.iter().map(|c| &c.client_id));
+
+        let owner = state.channels.get(channel_id).unwrap_or(caller).clone();
+        let channels: HashSet<_> = state
+            .channels
+            .iter()
+            .filter(|(_, c)| **c == owner)
+            .map(|(channel, _)| channel.as_ref())
+            .chain([channel_id])
+            .collect();
+
+        let owned = statistics.clients.iter().filter(|c| channels.contains(c.client_id.as_ref()));
+        let (mut subscriptions, mut buffered_events) = (0, 0);
+        for client in owned {
+            subscriptions += client.subscriptions.len();
+            buffered_events += client.buffered;
+            if client.client_id.as_ref() == channel_id {
+                subscriptions += sources
+                    .iter()
+                    .filter(|source| !client.subscriptions.iter().any(|s| s.as_ref() == *source))
+                    .count();
+            }
+        }
+
+        if self.limits.channels.is_some_and(|limit| channels.len() > limit) {
+            return Err(self.exhausted(&owner, "channels"));
+        }
+        if self.limits.subscriptions.is_some_and(|limit| subscriptions > limit) {
+            return Err(self.exhausted(&owner, "subscriptions"));
+        }
+        if self.limits.buffered_events.is_some_and(|limit| buffered_events > limit) {
+            return Err(self.exhausted(&owner, "buffered events"));
+        }
+
+        state.channels.insert(channel_id.into(), owner);
+        Ok(())
+    }
+
+    /// Returns the resources used by each caller, by the name under which
+    /// the caller is logged. Callers sharing a name, e.g. all callers with
+    /// bearer tokens, are summed up.
+    pub fn usage(&self) -> BTreeMap<String, Usage> {
+        let statistics = self.ess.statistics();
+        let mut state = self.state.lock().unwrap();
+        prune_closed_channels(&mut state, statistics.clients.iter().map(|c| &c.client_id));
+
+        let mut usage: BTreeMap<String, Usage> = BTreeMap::new();
+        for (caller, outstanding) in &state.outstanding_requests {
+            usage.entry(caller.to_string()).or_default().outstanding_requests += outstanding;
+        }
+
+        for client in &statistics.clients {
+            if let Some(caller) = state.channels.get(&client.client_id) {
+                let usage = usage.entry(caller.to_string()).or_default();
+                usage.channels += 1;
+                usage.subscriptions += client.subscriptions.len();
+                usage.buffered_events += client.buffered;
+            }
+        }
+
+        usage
+    }
+
+    fn exhausted(&self, caller: &Caller, resource: &str) -> Status {
+        tracing::warn!(identity = %caller, resource, "Caller exceeded its ceiling of resources.");
+        Status::resource_exhausted(format!("The caller exceeded its ceiling of {resource}."))
+    }
+}
+
+/// Forgets the channels which are no longer open.
+fn prune_closed_channels<'a>(state: &mut State, open: impl Iterator<Item = &'a Box<str>>) {
+    let open: HashSet<_> = open.collect();
+    state.channels.retain(|channel, _| open.contains(channel));
+}
+
+/// Accounts a request of a caller while it is in flight.
+pub struct RequestGuard {
+    accounting: Accounting,
+    caller: Caller,
+}
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        let mut state = self.accounting.state.lock().unwrap();
+        if let Some(outstanding) = state.outstanding_requests.get_mut(&self.caller) {
+            *outstanding -= 1;
+            if *outstanding == 0 {
+                state.outstanding_requests.remove(&self.caller);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use intent_brokering_common::streaming_ess::ChannelStream;
+    use intent_brokering_proto::streaming::{channel_service_server::ChannelService, OpenRequest};
+    use tonic::{Code, Request};
+
+    use super::{Accounting, Limits, Usage};
+    use crate::identity::Caller;
+    use crate::streaming::StreamingEss;
+
+    /// Opens a channel, which is closed once its stream is dropped.
+    async fn open_channel(ess: &StreamingEss) -> (String, ChannelStream) {
+        let response = ess.open(Request::new(OpenRequest::default())).await.unwrap();
+        let channel_id =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+        (channel_id, response.into_inner())
+    }
+
+    #[test]
+    fn begin_request_rejects_requests_beyond_ceiling() {
+        // arrange
+        let subject =
+            Accounting::new(StreamingEss::new(), Limits::default().set_outstanding_requests(1));
+        let caller = Caller::Named("hmi".into());
+
+        // act
+        let first = subject.begin_request(&caller);
+        let second = subject.begin_request(&caller);
+        let other = subject.begin_request(&Caller::Anonymous);
+        drop(first);
+        let third = subject.begin_request(&caller);
+
+        // assert
+        assert_eq!(Code::ResourceExhausted, second.err().unwrap().code());
+        assert!(other.is_ok());
+        assert!(third.is_ok());
+        assert_eq!(1, subject.usage()["hmi"].outstanding_requests);
+    }
+
+    #[tokio::test]
+    async fn admit_subscription_rejects_channels_beyond_ceiling() {
+        // arrange
+        let ess = StreamingEss::new();
+        let subject = Accounting::new(ess.clone(), Limits::default().set_channels(1));
+        let caller = Caller::Named("hmi".into());
+        let (first, _first_stream) = open_channel(&ess).await;
+        let (second, _second_stream) = open_channel(&ess).await;
+
+        // act
+        let admitted = subject.admit_subscription(&caller, &first, &["a".to_owned()]);
+        let again = subject.admit_subscription(&caller, &first, &["b".to_owned()]);
+        let rejected = subject.admit_subscription(&caller, &second, &["a".to_owned()]);
+        let other = subject.admit_subscription(&Caller::Anonymous, &second, &["a".to_owned()]);
+
+        // assert
+        assert!(admitted.is_ok());
+        assert!(again.is_ok());
+        assert_eq!(Code::ResourceExhausted, rejected.unwrap_err().code());
+        assert!(other.is_ok());
+    }
+
+    #[tokio::test]
+    async fn admit_subscription_rejects_subscriptions_beyond_ceiling() {
+        // arrange
+        let ess = StreamingEss::new();
+        let subject = Accounting::new(ess.clone(), Limits::default().set_subscriptions(2));
+        let caller = Caller::Named("hmi".into());
+        let (channel_id, _stream) = open_channel(&ess).await;
+        ess.register_subscriptions(channel_id.clone().into(), ["a".into()]).unwrap();
+
+        // act
+        let admitted = subject.admit_subscription(&caller, &channel_id, &["a".into(), "b".into()]);
+        let rejected = subject.admit_subscription(&caller, &channel_id, &["c".into(), "d".into()]);
+
+        // assert
+        assert!(admitted.is_ok());
+        assert_eq!(Code::ResourceExhausted, rejected.unwrap_err().code());
+        assert_eq!(
+            Usage { outstanding_requests: 0, channels: 1, subscriptions: 1, buffered_events: 0 },
+            subject.usage()["hmi"]
+        );
+    }
+
+    #[tokio::test]
+    async fn usage_forgets_closed_channels() {
+        // arrange
+        let ess = StreamingEss::new();
+        let subject = Accounting::new(ess.clone(), Limits::default());
+        let (channel_id, _stream) = open_channel(&ess).await;
+        subject.admit_subscription(&Caller::Anonymous, &channel_id, &["a".into()]).unwrap();
+
+        // act
+        let before = subject.usage();
+        ess.close_channel(&channel_id);
+        let after = subject.usage();
+
+        // assert
+        assert_eq!(1, before["anonymous"].channels);
+        assert!(after.is_empty());
+    }
+}
@@ -0,0 +1,664 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Access control lists which restrict the intents a caller may fulfill.
+//!
+//! Callers are identified as described in [`crate::identity`], e.g. by a
+//! bearer token in the `authorization` metadata of their requests. The list
+//! maps each identity, by its token or by its name, to the patterns of the
+//! namespaces and the intents it is allowed to fulfill, using the query syntax
+//! of the `Inspect` intent for the namespaces. Anonymous callers, and callers
+//! named by their credentials without an identity in the list, are subject to
+//! the rules for anonymous callers. Denied requests are logged with the
+//! `audit` target.
+//!
+//! Subscribing to a signal is authorized separately from reading it, by the
+//! `subscribe` intent. Rules allowing it can further restrict the sources
+//! which may be subscribed to, in which case the sources which are denied
+//! are reported per source instead of failing the whole intent.
+//!
+//! The list is a YAML file, which is reloaded when the file system notifies
+//! that it changed, e.g. through inotify on Linux.
+
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use intent_brokering_common::error::{Error, ResultExt as _};
+use intent_brokering_common::query::try_regex_from_query;
+use notify::{RecursiveMode, Watcher as _};
+use regex::Regex;
+use serde::Deserialize;
+use tonic::Status;
+
+use crate::identity::Caller;
+use crate::registry::{IntentConfiguration, IntentKind};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleDefinition {
+    namespace: String,
+    /// The allowed intents, or all intents if not set.
+    intents: Option<Vec<String>>,
+    /// The sources which may be subscribed to, or all sources if not set.
+    sources: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IdentityDefinition {
+    name: String,
+    /// The bearer token of the identity, if it is not identified by name.
+    token: Option<String>,
+    allow: Vec<RuleDefinition>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyDefinition {
+    #[serde(default)]
+    identities: Vec<IdentityDefinition>,
+    #[serde(default)]
+    anonymous: Vec<RuleDefinition>,
+}
+
+struct Rule {
+    namespace: Regex,
+    intents: Option<Vec<IntentKind>>,
+    sources: Option<Vec<Regex>>,
+}
+
+impl Rule {
+    fn parse(definition: RuleDefinition) -> Result<Self, Error> {
+        let intents = definition
+            .intents
+            .map(|intents| {
+                intents
+                    .iter()
+                    .map(|intent| parse_intent_kind(intent))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+
+        if definition.sources.is_some()
+            && matches!(&intents, Some(intents) if !intents.contains(&IntentKind::Subscribe))
+        {
+            return Err(Error::new("Sources can only be restricted for 'subscribe' intents."));
+        }
+
+        Ok(Self {
+            namespace: parse_pattern(&definition.namespace)?,
+            intents,
+            sources: definition
+                .sources
+                .map(|sources| {
+                    sources.iter().map(|source| parse_pattern(source)).collect::<Result<_, _>>()
+                })
+                .transpose()?,
+        })
+    }
+
+    fn allows(&self, intent: &IntentConfiguration) -> bool {
+        self.namespace.is_match(intent.namespace())
+            && self.intents.as_ref().map_or(true, |intents| intents.contains(&intent.intent()))
+    }
+
+    /// Whether the rule allows subscribing to a source, provided it allows
+    /// subscribing to the namespace.
+    fn allows_source(&self, source: &str) -> bool {
+        self.sources.as_ref().map_or(true, |sources| sources.iter().any(|s| s.is_match(source)))
+    }
+}
+
+struct Identity {
+    name: String,
+    token: Option<String>,
+    rules: Vec<Rule>,
+}
+
+struct Policy {
+    identities: Vec<Identity>,
+    anonymous: Vec<Rule>,
+}
+
+impl Policy {
+    fn parse(policy: &str) -> Result<Self, Error> {
+        let definition: PolicyDefinition = serde_yaml::from_str(policy)
+            .map_err_with("Failed to parse the access control list.")?;
+
+        let identities = definition
+            .identities
+            .into_iter()
+            .map(|identity| {
+                Ok(Identity {
+                    name: identity.name,
+                    token: identity.token,
+                    rules: identity.allow.into_iter().map(Rule::parse).collect::<Result<_, _>>()?,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+
+        let anonymous =
+            definition.anonymous.into_iter().map(Rule::parse).collect::<Result<_, _>>()?;
+
+        Ok(Self { identities, anonymous })
+    }
+
+    fn reject_named_identities(&self) -> Result<(), Error> {
+        match self.identities.iter().find(|identity| identity.token.is_none()) {
+            Some(identity) => Err(Error::new(format!(
+                "Identity '{}' is named by credentials which are not verified.",
+                identity.name
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the name of the identity of a caller and its rules, or `None`
+    /// if the caller presents a token which is not known.
+    fn rules<'a>(&'a self, caller: &'a Caller) -> Option<(&'a str, &'a [Rule])> {
+        match caller {
+            Caller::Anonymous => Some(("anonymous", &self.anonymous)),
+            Caller::Token(token) => self
+                .identities
+                .iter()
+                .find(|i| i.token.as_deref() == Some(&**token))
+                .map(|identity| (identity.name.as_str(), identity.rules.as_slice())),
+            Caller::Named(name) => Some((
+                &**name,
+                self.identities
+                    .iter()
+                    .find(|identity| identity.token.is_none() && *identity.name == **name)
+                    .map_or(&self.anonymous, |identity| &identity.rules),
+            )),
+        }
+    }
+}
+
+fn parse_pattern(pattern: &str) -> Result<Regex, Error> {
+    try_regex_from_query(pattern).map_err_with(format!("Pattern '{pattern}' is not valid."))
+}
+
+pub(crate) fn parse_intent_kind(intent: &str) -> Result<IntentKind, Error> {
+    IntentKind::ALL
+        .into_iter()
+        .find(|kind| kind.to_string().eq_ignore_ascii_case(intent))
+        .ok_or_else(|| Error::new(format!("Intent '{intent}' is not known.")))
+}
+
+/// An access control list loaded from a YAML file, which is reloaded when
+/// the file changes. Cloning is cheap and refers to the same list.
+#[derive(Clone)]
+pub struct Acl {
+    path: PathBuf,
+    // Whether identities named by credentials are allowed, see
+    // `Acl::without_named_identities`.
+    named_identities: bool,
+    policy: Arc<RwLock<Policy>>,
+    modified: Arc<RwLock<Option<SystemTime>>>,
+}
+
+impl Acl {
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let modified = modified(&path);
+        let policy = Self::read(&path, true)?;
+
+        Ok(Self {
+            path,
+            named_identities: true,
+            policy: Arc::new(RwLock::new(policy)),
+            modified: Arc::new(RwLock::new(modified)),
+        })
+    }
+
+    /// Rejects lists with identities named by credentials, i.e. without a
+    /// `token`, when reloading as well, as callers identified by credentials
+    /// which are not verified could claim such an identity, see
+    /// [`crate::identity::JwtSubject`].
+    pub fn without_named_identities(self) -> Result<Self, Error> {
+        self.policy.read().unwrap().reject_named_identities()?;
+        Ok(Self { named_identities: false, ..self })
+    }
+
+    fn read(path: &PathBuf, named_identities: bool) -> Result<Policy, Error> {
+        let policy = fs::read_to_string(path)
+            .map_err_with(format!("Failed to read '{}'.", path.display()))?;
+        let policy = Policy::parse(&policy)?;
+        if !named_identities {
+            policy.reject_named_identities()?;
+        }
+        Ok(policy)
+    }
+
+    /// Authorizes a caller to fulfill an intent.
+    pub fn authorize(&self, caller: &Caller, intent: &IntentConfiguration) -> Result<(), Status> {
+        let policy = self.policy.read().unwrap();
+
+        let Some((identity, rules)) = policy.rules(caller) else {
+            tracing::warn!(
+                target: "audit",
+                namespace = intent.namespace(),
+                intent = %intent.intent(),
+                "Denied intent of caller with unknown token."
+            );
+            return Err(Status::unauthenticated("The token is not known."));
+        };
+
+        if rules.iter().any(|rule| rule.allows(intent)) {
+            return Ok(());
+        }
+
+        tracing::warn!(
+            target: "audit",
+            identity,
+            namespace = intent.namespace(),
+            intent = %intent.intent(),
+            "Denied intent."
+        );
+
+        Err(Status::permission_denied(format!(
+            "Intent '{}' is not allowed for namespace '{}'.",
+            intent.intent(),
+            intent.namespace()
+        )))
+    }
+
+    /// Returns the sources of a namespace which a caller is not allowed to
+    /// subscribe to. Subscribing to the namespace must be authorized first.
+    pub fn denied_sources<'a>(
+        &self,
+        caller: &Caller,
+        namespace: &str,
+        sources: &'a [String],
+    ) -> Vec<&'a str> {
+        let policy = self.policy.read().unwrap();
+        let Some((identity, rules)) = policy.rules(caller) else {
+            return sources.iter().map(String::as_str).collect();
+        };
+
+        let intent = IntentConfiguration::new(namespace, IntentKind::Subscribe);
+        let rules = rules.iter().filter(|rule| rule.allows(&intent)).collect::<Vec<_>>();
+        let denied = sources
+            .iter()
+            .map(String::as_str)
+            .filter(|source| !rules.iter().any(|rule| rule.allows_source(source)))
+            .collect::<Vec<_>>();
+
+        if !denied.is_empty() {
+            tracing::warn!(
+                target: "audit",
+                identity,
+                namespace,
+                sources = ?denied,
+                "Denied sources of subscription."
+            );
+        }
+
+        denied
+    }
+
+    /// Reloads the list if the file was modified since it was last read.
+    /// Returns whether the list was reloaded. If the file cannot be parsed,
+    /// the current list is kept.
+    pub fn reload_if_modified(&self) -> Result<bool, Error> {
+        let modified = modified(&self.path);
+        if modified == *self.modified.read().unwrap() {
+            return Ok(false);
+        }
+
+        *self.modified.write().unwrap() = modified;
+        let policy = Self::read(&self.path, self.named_identities)?;
+        *self.policy.write().unwrap() = policy;
+        Ok(true)
+    }
+
+    /// Watches the file for changes, returning a future which reloads the
+    /// list whenever the file system notifies that the file changed. The
+    /// directory of the file is watched, such that files which are replaced
+    /// instead of modified in place, e.g. by editors, are reloaded as well.
+    pub fn watch(self) -> Result<impl Future<Output = ()>, Error> {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event| _ = sender.send(event))
+            .map_err_with("Failed to watch the access control list.")?;
+        let directory = match self.path.parent() {
+            Some(directory) if !directory.as_os_str().is_empty() => directory,
+            _ => Path::new("."),
+        };
+        watcher
+            .watch(directory, RecursiveMode::NonRecursive)
+            .map_err_with(format!("Failed to watch '{}'.", directory.display()))?;
+
+        Ok(async move {
+            // The watcher stops watching when it is dropped.
+            let _watcher = watcher;
+            while let Some(event) = receiver.recv().await {
+                let event: notify::Event = match event {
+                    Ok(event) => event,
+                    Err(e) => {
+                        tracing::warn!("Failed to watch access control list: {e}");
+                        continue;
+                    }
+                };
+
+                if !event.paths.iter().any(|path| path.file_name() == self.path.file_name()) {
+                    continue;
+                }
+
+                match self.reload_if_modified() {
+                    Ok(true) => tracing::info!("Reloaded access control list."),
+                    Ok(false) => {}
+                    Err(e) => tracing::error!("Keeping access control list, reloading failed: {e}"),
+                }
+            }
+        })
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+        time::Duration,
+    };
+
+    use tonic::Code;
+
+    use crate::identity::Caller;
+    use crate::registry::{IntentConfiguration, IntentKind};
+
+    use super::Acl;
+
+    const POLICY: &str = r#"{
+        "identities": [
+            {
+                "name": "hmi",
+                "token": "secret",
+                "allow": [{ "namespace": "sdv.vss.**", "intents": ["read", "subscribe"] }]
+            },
+            {
+                "name": "uid:1000",
+                "allow": [{ "namespace": "sdv.kvs" }]
+            }
+        ],
+        "anonymous": [{ "namespace": "system.registry" }]
+    }"#;
+
+    pub(crate) struct TempFile(PathBuf);
+
+    impl TempFile {
+        pub(crate) fn new(content: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("acl-{}.json", uuid::Uuid::new_v4()));
+            fs::write(&path, content).unwrap();
+            Self(path)
+        }
+
+        pub(crate) fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn caller(token: Option<&str>) -> Caller {
+        token.map_or(Caller::Anonymous, |token| Caller::Token(token.into()))
+    }
+
+    fn intent(namespace: &str, kind: IntentKind) -> IntentConfiguration {
+        IntentConfiguration::new(namespace, kind)
+    }
+
+    #[test]
+    fn authorize_allows_intents_of_identity() {
+        // arrange
+        let file = TempFile::new(POLICY);
+        let subject = Acl::load(file.path()).unwrap();
+
+        // act
+        let allowed = subject
+            .authorize(&caller(Some("secret")), &intent("sdv.vss.Vehicle", IntentKind::Read));
+        let denied = subject
+            .authorize(&caller(Some("secret")), &intent("sdv.vss.Vehicle", IntentKind::Write));
+
+        // assert
+        assert!(allowed.is_ok());
+        assert_eq!(Code::PermissionDenied, denied.unwrap_err().code());
+    }
+
+    #[test]
+    fn authorize_applies_anonymous_rules_without_token() {
+        // arrange
+        let file = TempFile::new(POLICY);
+        let subject = Acl::load(file.path()).unwrap();
+
+        // act
+        let allowed =
+            subject.authorize(&caller(None), &intent("system.registry", IntentKind::Inspect));
+        let denied = subject.authorize(&caller(None), &intent("sdv.vss.Vehicle", IntentKind::Read));
+
+        // assert
+        assert!(allowed.is_ok());
+        assert_eq!(Code::PermissionDenied, denied.unwrap_err().code());
+    }
+
+    #[test]
+    fn authorize_allows_intents_of_identity_named_by_credentials() {
+        // arrange
+        let file = TempFile::new(POLICY);
+        let subject = Acl::load(file.path()).unwrap();
+        let named = |name: &str| Caller::Named(name.into());
+
+        // act
+        let allowed = subject.authorize(&named("uid:1000"), &intent("sdv.kvs", IntentKind::Write));
+        let anonymous =
+            subject.authorize(&named("uid:1001"), &intent("system.registry", IntentKind::Inspect));
+        let denied = subject.authorize(&named("uid:1001"), &intent("sdv.kvs", IntentKind::Write));
+        let with_token =
+            subject.authorize(&named("hmi"), &intent("sdv.vss.Speed", IntentKind::Read));
+
+        // assert
+        assert!(allowed.is_ok());
+        assert!(anonymous.is_ok());
+        assert_eq!(Code::PermissionDenied, denied.unwrap_err().code());
+        assert_eq!(Code::PermissionDenied, with_token.unwrap_err().code());
+    }
+
+    #[test]
+    fn authorize_fails_for_unknown_token() {
+        // arrange
+        let file = TempFile::new(POLICY);
+        let subject = Acl::load(file.path()).unwrap();
+
+        // act
+        let result = subject
+            .authorize(&caller(Some("unknown")), &intent("system.registry", IntentKind::Inspect));
+
+        // assert
+        assert_eq!(Code::Unauthenticated, result.unwrap_err().code());
+    }
+
+    #[test]
+    fn denied_sources_returns_sources_not_allowed_for_subscriptions() {
+        // arrange
+        let file = TempFile::new(
+            r#"{
+                "anonymous": [
+                    { "namespace": "sdv.vss", "intents": ["read"] },
+                    { "namespace": "sdv.vss", "intents": ["subscribe"], "sources": ["Vehicle.Cabin.**"] }
+                ]
+            }"#,
+        );
+        let subject = Acl::load(file.path()).unwrap();
+        let sources = ["Vehicle.Cabin.Door".to_owned(), "Vehicle.Speed".to_owned()];
+
+        // act
+        let read = subject.authorize(&caller(None), &intent("sdv.vss", IntentKind::Read));
+        let subscribe = subject.authorize(&caller(None), &intent("sdv.vss", IntentKind::Subscribe));
+        let denied = subject.denied_sources(&caller(None), "sdv.vss", &sources);
+
+        // assert
+        assert!(read.is_ok());
+        assert!(subscribe.is_ok());
+        assert_eq!(vec!["Vehicle.Speed"], denied);
+    }
+
+    #[test]
+    fn load_fails_for_sources_of_other_than_subscribe() {
+        // arrange
+        let file = TempFile::new(
+            r#"{ "anonymous": [{ "namespace": "**", "intents": ["read"], "sources": ["**"] }] }"#,
+        );
+
+        // act
+        let result = Acl::load(file.path());
+
+        // assert
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn authorize_matches_metacharacters_of_patterns_literally() {
+        // arrange
+        let file = TempFile::new(r#"{ "anonymous": [{ "namespace": "sdv.(door|seat" }] }"#);
+        let subject = Acl::load(file.path()).unwrap();
+
+        // act
+        let allowed = subject.authorize(&caller(None), &intent("sdv.(door|seat", IntentKind::Read));
+        let denied = subject.authorize(&caller(None), &intent("sdvxseat", IntentKind::Read));
+
+        // assert
+        assert!(allowed.is_ok());
+        assert_eq!(Code::PermissionDenied, denied.unwrap_err().code());
+    }
+
+    #[test]
+    fn load_fails_for_unknown_intent() {
+        // arrange
+        let file = TempFile::new(r#"{ "anonymous": [{ "namespace": "**", "intents": ["fly"] }] }"#);
+
+        // act
+        let result = Acl::load(file.path());
+
+        // assert
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn load_parses_yaml() {
+        // arrange
+        let file = TempFile::new(
+            "
+            identities:
+              - name: hmi
+                token: secret
+                allow:
+                  - namespace: sdv.vss.**
+                    intents: [read]
+            anonymous: []
+            ",
+        );
+
+        // act
+        let subject = Acl::load(file.path()).unwrap();
+
+        // assert
+        assert!(subject
+            .authorize(&caller(Some("secret")), &intent("sdv.vss.Speed", IntentKind::Read))
+            .is_ok());
+        assert!(subject
+            .authorize(&caller(None), &intent("sdv.vss.Speed", IntentKind::Read))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn watch_reloads_list_when_file_changes() {
+        // arrange
+        let file = TempFile::new(POLICY);
+        let subject = Acl::load(file.path()).unwrap();
+        let anonymous = || subject.authorize(&caller(None), &intent("sdv.kvs", IntentKind::Read));
+        let watch = tokio::spawn(subject.clone().watch().unwrap());
+
+        // act
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        fs::write(&file.0, "anonymous: [{ namespace: sdv.kvs }]").unwrap();
+
+        // assert
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while anonymous().is_err() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        watch.abort();
+    }
+
+    #[test]
+    fn reload_if_modified_replaces_list() {
+        // arrange
+        let file = TempFile::new(POLICY);
+        let subject = Acl::load(file.path()).unwrap();
+        let anonymous = || subject.authorize(&caller(None), &intent("sdv.kvs", IntentKind::Read));
+        assert!(anonymous().is_err());
+
+        // act
+        std::thread::sleep(Duration::from_millis(10));
+        fs::write(&file.0, r#"{ "anonymous": [{ "namespace": "sdv.kvs" }] }"#).unwrap();
+        let reloaded = subject.reload_if_modified().unwrap();
+
+        // assert
+        assert!(reloaded);
+        assert!(anonymous().is_ok());
+        assert!(!subject.reload_if_modified().unwrap());
+    }
+
+    #[test]
+    fn without_named_identities_rejects_identities_without_token() {
+        // arrange
+        let file = TempFile::new(POLICY);
+        let tokens_only = TempFile::new(
+            r#"{ "identities": [{ "name": "hmi", "token": "secret", "allow": [] }], "anonymous": [] }"#,
+        );
+
+        // act
+        let named = Acl::load(file.path()).unwrap().without_named_identities();
+        let subject = Acl::load(tokens_only.path()).unwrap().without_named_identities().unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        fs::write(&tokens_only.0, POLICY).unwrap();
+        let reloaded = subject.reload_if_modified();
+
+        // assert
+        assert!(named.is_err());
+        assert!(reloaded.is_err());
+    }
+
+    #[test]
+    fn reload_if_modified_keeps_list_if_invalid() {
+        // arrange
+        let file = TempFile::new(POLICY);
+        let subject = Acl::load(file.path()).unwrap();
+
+        // act
+        std::thread::sleep(Duration::from_millis(10));
+        fs::write(&file.0, "invalid").unwrap();
+        let result = subject.reload_if_modified();
+
+        // assert
+        assert!(result.is_err());
+        assert!(subject
+            .authorize(&caller(None), &intent("system.registry", IntentKind::Inspect))
+            .is_ok());
+    }
+}
@@ -0,0 +1,206 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Activates the providers of namespaces on demand, such that services which
+//! are rarely used do not need to run all the time.
+//!
+//! When an intent arrives for a namespace which is not served, but has an
+//! activator, the broker runs the command of the activator and holds the
+//! intent until a provider registers for it or the activation times out. The
+//! command either is the provider itself, or asks an activator service to
+//! start it, e.g. `systemctl start`. A command is not run again while the
+//! process it started is still running.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use intent_brokering_common::error::{Error, ResultExt as _};
+use serde::Deserialize;
+use tokio::process::{Child, Command};
+use tokio::time::Instant;
+use tonic::Status;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ActivatorDefinition {
+    /// The program to run, followed by its arguments.
+    command: Vec<String>,
+    #[serde(default = "timeout_secs_default")]
+    timeout_secs: u64,
+}
+
+fn timeout_secs_default() -> u64 {
+    10
+}
+
+struct Activator {
+    program: String,
+    args: Vec<String>,
+    timeout: Duration,
+    process: Mutex<Option<Child>>,
+}
+
+impl Activator {
+    fn parse(namespace: &str, definition: ActivatorDefinition) -> Result<Self, Error> {
+        let mut command = definition.command.into_iter();
+        let program = command
+            .next()
+            .filter(|program| !program.is_empty())
+            .ok_or_else(|| Error::new(format!("Command of '{namespace}' must not be empty.")))?;
+
+        if definition.timeout_secs == 0 {
+            return Err(Error::new(format!("Timeout of '{namespace}' must be positive.")));
+        }
+
+        Ok(Self {
+            program,
+            args: command.collect(),
+            timeout: Duration::from_secs(definition.timeout_secs),
+            process: Mutex::new(None),
+        })
+    }
+
+    /// Runs the command, unless the process it started last is still
+    /// running.
+    fn start(&self, namespace: &str) -> Result<(), Status> {
+        let mut process = self.process.lock().unwrap();
+
+        if let Some(child) = process.as_mut() {
+            if matches!(child.try_wait(), Ok(None)) {
+                return Ok(());
+            }
+        }
+
+        tracing::info!("Activating the provider of namespace '{namespace}'.");
+
+        let child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::null())
+            .spawn()
+            .map_err(|e| {
+                tracing::warn!("Failed to activate the provider of namespace '{namespace}': {e}");
+                Status::unavailable(format!(
+                    "Failed to activate the provider of namespace '{namespace}'."
+                ))
+            })?;
+
+        *process = Some(child);
+        Ok(())
+    }
+}
+
+/// Activators loaded from a JSON file, which maps namespaces to the command
+/// activating their provider and the time to wait for it to register, e.g.
+/// `{ "sdv.camera": { "command": ["camera-provider", "--port", "50070"], "timeout_secs": 10 } }`.
+pub struct Activation {
+    activators: HashMap<Box<str>, Activator>,
+}
+
+impl Activation {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let activation = fs::read_to_string(path)
+            .map_err_with(format!("Failed to read '{}'.", path.display()))?;
+        Self::parse(&activation)
+    }
+
+    pub fn parse(activation: &str) -> Result<Self, Error> {
+        let definition: HashMap<String, ActivatorDefinition> =
+            serde_json::from_str(activation).map_err_with("Failed to parse the activators.")?;
+
+        let activators = definition
+            .into_iter()
+            .map(|(namespace, definition)| {
+                let activator = Activator::parse(&namespace, definition)?;
+                Ok((namespace.into(), activator))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Self { activators })
+    }
+
+    /// Activates the provider of a namespace, returning the deadline until
+    /// which to wait for it to register, or `None` if the namespace has no
+    /// activator.
+    pub fn activate(&self, namespace: &str) -> Result<Option<Instant>, Status> {
+        let Some(activator) = self.activators.get(namespace) else {
+            return Ok(None);
+        };
+
+        activator.start(namespace)?;
+        Ok(Some(Instant::now() + activator.timeout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tonic::Code;
+
+    use super::Activation;
+
+    #[tokio::test]
+    async fn activate_runs_command_once_while_process_is_running() {
+        // arrange
+        let log = std::env::temp_dir().join(format!("activation-{}.log", uuid::Uuid::new_v4()));
+        let subject = Activation::parse(&format!(
+            r#"{{ "sdv.camera": {{ "command": ["sh", "-c", "echo started >> {}; sleep 1"] }} }}"#,
+            log.display()
+        ))
+        .unwrap();
+
+        // act
+        let first = subject.activate("sdv.camera").unwrap();
+        let second = subject.activate("sdv.camera").unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // assert
+        assert!(first.is_some());
+        assert!(second.is_some());
+        assert_eq!("started\n", std::fs::read_to_string(&log).unwrap());
+        std::fs::remove_file(log).unwrap();
+    }
+
+    #[test]
+    fn activate_returns_none_for_namespaces_without_activator() {
+        // arrange
+        let subject = Activation::parse(r#"{ "sdv.camera": { "command": ["true"] } }"#).unwrap();
+
+        // act
+        let result = subject.activate("sdv.vdt");
+
+        // assert
+        assert_eq!(None, result.unwrap());
+    }
+
+    #[tokio::test]
+    async fn activate_fails_if_command_cannot_be_run() {
+        // arrange
+        let subject =
+            Activation::parse(r#"{ "sdv.camera": { "command": ["/does/not/exist"] } }"#).unwrap();
+
+        // act
+        let result = subject.activate("sdv.camera");
+
+        // assert
+        assert_eq!(Code::Unavailable, result.unwrap_err().code());
+    }
+
+    #[test]
+    fn parse_fails_for_invalid_activators() {
+        // act
+        let empty_command = Activation::parse(r#"{ "sdv.camera": { "command": [] } }"#);
+        let zero_timeout =
+            Activation::parse(r#"{ "sdv.camera": { "command": ["true"], "timeout_secs": 0 } }"#);
+
+        // assert
+        assert!(empty_command.is_err());
+        assert!(zero_timeout.is_err());
+    }
+}
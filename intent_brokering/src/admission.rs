@@ -0,0 +1,204 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Limits the rate at which providers are registered, e.g. when all providers
+//! register at once after Intent Brokering restarted.
+//!
+//! Registrations beyond the rate are rejected with `RESOURCE_EXHAUSTED` and
+//! the `x-chariott-retry-after-ms` metadata, which tells the provider after
+//! how many milliseconds to retry. The delay is jittered, such that rejected
+//! providers do not retry at once. Registrations of services with an intent
+//! in a priority namespace are always admitted.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tonic::Status;
+
+use crate::namespace;
+
+pub const RETRY_AFTER_METADATA_KEY: &str = "x-chariott-retry-after-ms";
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    rate: u32,
+    retry_jitter: Duration,
+    priority_namespaces: Vec<Box<str>>,
+}
+
+impl Config {
+    pub fn new(rate: u32) -> Self {
+        Self {
+            rate: std::cmp::max(rate, 1),
+            retry_jitter: Duration::from_secs(5),
+            priority_namespaces: vec![],
+        }
+    }
+
+    /// The number of registrations admitted per second.
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    /// The upper bound of the random delay added to the delay after which a
+    /// rejected provider should retry.
+    pub fn retry_jitter(&self) -> Duration {
+        self.retry_jitter
+    }
+
+    /// The namespaces whose registrations are always admitted. A namespace
+    /// also covers its sub-namespaces, e.g. `system` covers `system.registry`.
+    pub fn priority_namespaces(&self) -> &[Box<str>] {
+        &self.priority_namespaces
+    }
+
+    pub fn set_retry_jitter(self, value: Duration) -> Self {
+        Self { retry_jitter: value, ..self }
+    }
+
+    pub fn set_priority_namespaces(self, value: impl IntoIterator<Item = Box<str>>) -> Self {
+        Self { priority_namespaces: value.into_iter().collect(), ..self }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Admits registrations using a token bucket, which holds up to one second
+/// worth of registrations.
+pub struct Admission {
+    config: Config,
+    bucket: Mutex<Bucket>,
+}
+
+impl Admission {
+    pub fn new(config: Config) -> Self {
+        let bucket = Bucket { tokens: config.rate as f64, updated: Instant::now() };
+        Self { config, bucket: Mutex::new(bucket) }
+    }
+
+    /// Admits the registration of a service with intents in the given
+    /// namespaces, or returns the delay after which it should be retried.
+    pub fn admit<'a>(
+        &self,
+        namespaces: impl IntoIterator<Item = &'a str>,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let priority = namespaces.into_iter().any(|namespace| self.is_priority(namespace));
+        let rate = self.config.rate as f64;
+
+        let mut bucket = self.bucket.lock().unwrap();
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = f64::min(bucket.tokens + elapsed * rate, rate);
+        bucket.updated = std::cmp::max(bucket.updated, now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        if priority {
+            return Ok(());
+        }
+
+        let delay = Duration::from_secs_f64((1.0 - bucket.tokens) / rate);
+        Err(delay + self.config.retry_jitter.mul_f64(rand::random::<f64>()))
+    }
+
+    fn is_priority(&self, namespace: &str) -> bool {
+        self.config
+            .priority_namespaces
+            .iter()
+            .any(|priority| namespace::covers(priority, namespace))
+    }
+}
+
+/// Creates the status with which a registration is rejected.
+pub fn retry_later(delay: Duration) -> Status {
+    let mut status = Status::resource_exhausted(format!(
+        "Too many registrations, retry after {} ms.",
+        delay.as_millis()
+    ));
+
+    if let Ok(value) = delay.as_millis().to_string().parse() {
+        status.metadata_mut().insert(RETRY_AFTER_METADATA_KEY, value);
+    }
+
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn admission(rate: u32) -> Admission {
+        Admission::new(
+            Config::new(rate)
+                .set_retry_jitter(Duration::ZERO)
+                .set_priority_namespaces(["system".into()]),
+        )
+    }
+
+    #[test]
+    fn admit_rejects_registrations_beyond_rate() {
+        // arrange
+        let subject = admission(2);
+        let now = Instant::now();
+
+        // act
+        let results: Vec<_> = (0..3).map(|_| subject.admit(["sdv.door"], now)).collect();
+
+        // assert
+        assert_eq!(Ok(()), results[0]);
+        assert_eq!(Ok(()), results[1]);
+        assert_eq!(Err(Duration::from_millis(500)), results[2]);
+    }
+
+    #[test]
+    fn admit_admits_registrations_again_after_delay() {
+        // arrange
+        let subject = admission(1);
+        let now = Instant::now();
+        subject.admit(["sdv.door"], now).unwrap();
+
+        // act
+        let rejected = subject.admit(["sdv.door"], now + Duration::from_millis(500));
+        let admitted = subject.admit(["sdv.door"], now + Duration::from_secs(1));
+
+        // assert
+        assert!(rejected.is_err());
+        assert_eq!(Ok(()), admitted);
+    }
+
+    #[test]
+    fn admit_always_admits_priority_namespaces() {
+        // arrange
+        let subject = admission(1);
+        let now = Instant::now();
+        subject.admit(["sdv.door"], now).unwrap();
+
+        // act
+        let priority = subject.admit(["sdv.door", "system.registry"], now);
+        let other = subject.admit(["systems"], now);
+
+        // assert
+        assert_eq!(Ok(()), priority);
+        assert!(other.is_err());
+    }
+
+    #[test]
+    fn retry_later_sets_retry_after_metadata() {
+        // act
+        let status = retry_later(Duration::from_millis(1500));
+
+        // assert
+        assert_eq!(tonic::Code::ResourceExhausted, status.code());
+        assert_eq!(
+            "1500",
+            status.metadata().get(RETRY_AFTER_METADATA_KEY).unwrap().to_str().unwrap()
+        );
+    }
+}
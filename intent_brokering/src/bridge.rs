@@ -0,0 +1,479 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Re-publishes the events of sources under other namespaces and sources,
+//! e.g. to keep the event names consumers subscribe to while providers
+//! migrate to new namespaces.
+//!
+//! Each bridge subscribes to a source of the provider of its `from`
+//! namespace through a channel of the Intent Broker, and publishes its events
+//! under its `to` namespace and source. Numbers are converted with
+//! `value * scale + offset` and strings are mapped with `enum`, which maps
+//! the values of the `from` source to the ones of the `to` source. Events
+//! keep their timestamps and freshness.
+//!
+//! Consumers subscribe to bridged sources as to the sources of a provider,
+//! with the `Subscribe` intent of the `to` namespace on a channel of the
+//! Intent Broker, which is served by the Intent Broker as long as all
+//! requested sources are bridged. Bridges resubscribe whenever the provider
+//! of their `from` namespace changes.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+use intent_brokering_common::error::{Error, ResultExt as _};
+use intent_brokering_common::streaming_ess::Timestamped;
+use intent_brokering_proto::{
+    common::{IntentEnum, IntentMessage, SubscribeFulfillment, SubscribeIntent, ValueEnum},
+    streaming::Event,
+};
+use serde::Deserialize;
+use tokio::select;
+use tokio::sync::watch;
+use tokio_stream::StreamExt as _;
+use tonic::Status;
+use url::Url;
+use uuid::Uuid;
+
+use crate::registry::{IntentConfiguration, IntentKind};
+use crate::streaming::{proxied_source, StreamingEss};
+use crate::IntentBroker;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SourceDefinition {
+    namespace: String,
+    source: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BridgeDefinition {
+    from: SourceDefinition,
+    to: SourceDefinition,
+    #[serde(default = "scale_default")]
+    scale: f64,
+    #[serde(default)]
+    offset: f64,
+    #[serde(default, rename = "enum")]
+    values: HashMap<String, String>,
+}
+
+fn scale_default() -> f64 {
+    1.0
+}
+
+/// Converts the values of the events of a bridge.
+struct Mapping {
+    scale: f64,
+    offset: f64,
+    values: HashMap<String, String>,
+}
+
+impl Mapping {
+    fn apply(&self, value: &mut ValueEnum) {
+        // Numbers are only converted if needed, as 64-bit integers may lose
+        // precision.
+        let scaled = self.scale != 1.0 || self.offset != 0.0;
+        let f = |v: f64| v * self.scale + self.offset;
+
+        match value {
+            ValueEnum::Int32(v) if scaled => *v = f(*v as f64).round() as i32,
+            ValueEnum::Int64(v) if scaled => *v = f(*v as f64).round() as i64,
+            ValueEnum::Float32(v) if scaled => *v = f(*v as f64) as f32,
+            ValueEnum::Float64(v) if scaled => *v = f(*v),
+            ValueEnum::String(v) => {
+                if let Some(mapped) = self.values.get(v.as_str()) {
+                    *v = mapped.clone();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+struct Bridge {
+    from_namespace: Box<str>,
+    from_source: Box<str>,
+    /// The source under which the events of the `from` source are relayed
+    /// to the channel of the bridges.
+    from: Box<str>,
+    /// The source under which the events are re-published.
+    to: Box<str>,
+    mapping: Mapping,
+}
+
+impl Bridge {
+    fn parse(definition: BridgeDefinition) -> Result<Self, Error> {
+        let from = proxied_source(&definition.from.namespace, &definition.from.source);
+        let to = proxied_source(&definition.to.namespace, &definition.to.source);
+
+        if [&definition.from, &definition.to]
+            .iter()
+            .any(|source| source.namespace.is_empty() || source.source.is_empty())
+        {
+            return Err(Error::new(format!(
+                "Namespace and source of the bridge from '{from}' to '{to}' must not be empty."
+            )));
+        }
+
+        if !definition.scale.is_finite() || definition.scale == 0.0 {
+            return Err(Error::new(format!(
+                "Scale of the bridge to '{to}' must be a non-zero number."
+            )));
+        }
+
+        if !definition.offset.is_finite() {
+            return Err(Error::new(format!("Offset of the bridge to '{to}' must be a number.")));
+        }
+
+        Ok(Self {
+            from_namespace: definition.from.namespace.into(),
+            from_source: definition.from.source.into(),
+            from: from.into(),
+            to: to.into(),
+            mapping: Mapping {
+                scale: definition.scale,
+                offset: definition.offset,
+                values: definition.values,
+            },
+        })
+    }
+}
+
+/// Bridges loaded from a JSON file, e.g.
+/// `[{ "from": { "namespace": "sdv.vdt.v2", "source": "Cabin.Temperature" }, "to": { "namespace": "sdv.vdt", "source": "Vehicle.Cabin.Temperature" }, "scale": 1.8, "offset": 32 }]`.
+pub struct Bridges {
+    ess: StreamingEss,
+    bridges: Vec<Bridge>,
+}
+
+impl Bridges {
+    pub fn load(path: impl AsRef<Path>, ess: StreamingEss) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let bridges = fs::read_to_string(path)
+            .map_err_with(format!("Failed to read '{}'.", path.display()))?;
+        Self::parse(&bridges, ess)
+    }
+
+    pub fn parse(bridges: &str, ess: StreamingEss) -> Result<Self, Error> {
+        let definition: Vec<BridgeDefinition> =
+            serde_json::from_str(bridges).map_err_with("Failed to parse the bridges.")?;
+        let bridges = definition.into_iter().map(Bridge::parse).collect::<Result<Vec<_>, _>>()?;
+
+        let mut targets = HashSet::new();
+        for bridge in &bridges {
+            if !targets.insert(&*bridge.to) {
+                return Err(Error::new(format!(
+                    "Source '{}' is bridged to more than once.",
+                    bridge.to
+                )));
+            }
+        }
+
+        // Bridged sources are not re-published again, hence bridges cannot be
+        // chained.
+        if let Some(bridge) = bridges.iter().find(|bridge| targets.contains(&*bridge.from)) {
+            return Err(Error::new(format!(
+                "Source '{}' is both bridged from and bridged to.",
+                bridge.from
+            )));
+        }
+
+        Ok(Self { ess, bridges })
+    }
+
+    /// Serves a subscription to sources of a namespace from the bridges, if
+    /// all of the sources are bridged and the channel was opened on the
+    /// Intent Broker. Returns `None` if the subscription is to be fulfilled
+    /// by the provider of the namespace instead.
+    pub fn serve(
+        &self,
+        namespace: &str,
+        subscribe_intent: &SubscribeIntent,
+    ) -> Result<Option<SubscribeFulfillment>, Status> {
+        let SubscribeIntent { channel_id, sources, filters } = subscribe_intent;
+
+        let bridged = |source: &str| {
+            let source = proxied_source(namespace, source);
+            self.bridges.iter().any(|bridge| *bridge.to == source)
+        };
+
+        if sources.is_empty()
+            || !sources.iter().all(|source| bridged(source))
+            || !self.ess.is_reading_events(channel_id.as_str())
+        {
+            return Ok(None);
+        }
+
+        self.ess
+            .serve_timestamped_subscriptions(
+                SubscribeIntent {
+                    channel_id: channel_id.clone(),
+                    sources: sources.iter().map(|s| proxied_source(namespace, s)).collect(),
+                    filters: filters
+                        .iter()
+                        .map(|(s, f)| (proxied_source(namespace, s), f.clone()))
+                        .collect(),
+                },
+                |v| v,
+            )
+            .map(Some)
+    }
+
+    /// Subscribes to the bridged sources and re-publishes their events until
+    /// the registry is dropped, resubscribing whenever the registry changes.
+    pub async fn run(
+        &self,
+        broker: IntentBroker,
+        mut changes: watch::Receiver<()>,
+    ) -> Result<(), Error> {
+        if self.bridges.is_empty() {
+            return Ok(());
+        }
+
+        let channel_id: Box<str> = format!("bridges-{}", Uuid::new_v4()).into();
+        let (_, mut events) = self
+            .ess
+            .read_events(channel_id.clone())
+            .map_err(|_| Error::new("The channel of the bridges exceeds the buffer budget."))?;
+
+        let mut subscribed = HashMap::new();
+        self.subscribe(&broker, &channel_id, &mut subscribed).await;
+
+        loop {
+            select! {
+                changed = changes.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                    self.subscribe(&broker, &channel_id, &mut subscribed).await;
+                }
+                event = events.next() => match event {
+                    Some(Ok(event)) => self.republish(event),
+                    Some(Err(e)) => tracing::warn!("Reading bridged events failed: {e}"),
+                    None => break,
+                },
+            }
+        }
+
+        self.ess.close_channel(&channel_id);
+        Ok(())
+    }
+
+    /// Subscribes to the bridged sources of each namespace whose provider
+    /// changed since it was last subscribed to.
+    async fn subscribe(
+        &self,
+        broker: &IntentBroker,
+        channel_id: &str,
+        subscribed: &mut HashMap<Box<str>, Url>,
+    ) {
+        let namespaces: HashSet<_> =
+            self.bridges.iter().map(|bridge| bridge.from_namespace.clone()).collect();
+
+        for namespace in namespaces {
+            let intent = IntentConfiguration::new(&*namespace, IntentKind::Subscribe);
+            let (Some(url), Some(binding)) = (broker.primary_url(&intent), broker.resolve(&intent))
+            else {
+                subscribed.remove(&namespace);
+                continue;
+            };
+
+            if subscribed.get(&namespace) == Some(&url) {
+                continue;
+            }
+
+            let sources = self
+                .bridges
+                .iter()
+                .filter(|bridge| bridge.from_namespace == namespace)
+                .map(|bridge| bridge.from_source.to_string())
+                .collect();
+
+            let subscribe = IntentMessage {
+                intent: Some(IntentEnum::Subscribe(SubscribeIntent {
+                    channel_id: channel_id.to_owned(),
+                    sources,
+                    ..Default::default()
+                })),
+            };
+
+            match binding.execute(subscribe).await {
+                Ok(_) => {
+                    tracing::debug!("Bridging sources of '{namespace}' from '{url}'.");
+                    subscribed.insert(namespace, url);
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Subscribing to the bridged sources of '{namespace}' failed: {e}"
+                    );
+                    subscribed.remove(&namespace);
+                }
+            }
+        }
+    }
+
+    /// Re-publishes an event of a bridged source under the sources it is
+    /// bridged to.
+    fn republish(&self, event: Event) {
+        let Some(value) = event.value.and_then(|v| v.value) else {
+            return;
+        };
+
+        let timestamp = event
+            .timestamp
+            .and_then(|t| SystemTime::try_from(t).ok())
+            .unwrap_or_else(SystemTime::now);
+        let normalized_timestamp =
+            event.normalized_timestamp.and_then(|t| SystemTime::try_from(t).ok());
+
+        for bridge in self.bridges.iter().filter(|bridge| *bridge.from == event.source) {
+            let mut data = value.clone();
+            bridge.mapping.apply(&mut data);
+            self.ess.publish(
+                bridge.to.as_ref(),
+                Timestamped {
+                    data,
+                    timestamp,
+                    normalized_timestamp,
+                    freshness: event.freshness.clone(),
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::{Duration, SystemTime};
+
+    use intent_brokering_proto::{
+        common::{SubscribeIntent, ValueEnum, ValueMessage},
+        streaming::Event,
+    };
+    use tokio_stream::StreamExt as _;
+
+    use super::{Bridges, Mapping};
+    use crate::streaming::StreamingEss;
+
+    const BRIDGES: &str = r#"[
+        { "from": { "namespace": "sdv.vdt.v2", "source": "Cabin.Temperature" }, "to": { "namespace": "sdv.vdt", "source": "Vehicle.Cabin.Temperature" }, "scale": 1.8, "offset": 32 },
+        { "from": { "namespace": "sdv.vdt.v2", "source": "Cabin.Door" }, "to": { "namespace": "sdv.vdt", "source": "Vehicle.Cabin.Door" }, "enum": { "OPEN": "open" } }
+    ]"#;
+
+    fn subscribe(channel_id: &str, sources: &[&str]) -> SubscribeIntent {
+        SubscribeIntent {
+            channel_id: channel_id.to_owned(),
+            sources: sources.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn mapping_converts_numbers_and_maps_strings() {
+        // arrange
+        let subject = Mapping {
+            scale: 1.8,
+            offset: 32.0,
+            values: HashMap::from([("OPEN".to_owned(), "open".to_owned())]),
+        };
+        let mut int = ValueEnum::Int32(20);
+        let mut float = ValueEnum::Float64(-40.0);
+        let mut mapped = ValueEnum::String("OPEN".to_owned());
+        let mut unmapped = ValueEnum::String("CLOSED".to_owned());
+
+        // act
+        for value in [&mut int, &mut float, &mut mapped, &mut unmapped] {
+            subject.apply(value);
+        }
+
+        // assert
+        assert_eq!(ValueEnum::Int32(68), int);
+        assert_eq!(ValueEnum::Float64(-40.0), float);
+        assert_eq!(ValueEnum::String("open".to_owned()), mapped);
+        assert_eq!(ValueEnum::String("CLOSED".to_owned()), unmapped);
+    }
+
+    #[test]
+    fn parse_fails_for_invalid_bridges() {
+        // arrange
+        let bridge = |from: &str, to: &str, scale: f64| {
+            format!(
+                r#"{{ "from": {{ "namespace": "sdv", "source": "{from}" }}, "to": {{ "namespace": "sdv", "source": "{to}" }}, "scale": {scale} }}"#
+            )
+        };
+
+        // act
+        let scale = Bridges::parse(&format!("[{}]", bridge("a", "b", 0.0)), StreamingEss::new());
+        let empty = Bridges::parse(&format!("[{}]", bridge("", "b", 1.0)), StreamingEss::new());
+        let twice = Bridges::parse(
+            &format!("[{}, {}]", bridge("a", "b", 1.0), bridge("c", "b", 1.0)),
+            StreamingEss::new(),
+        );
+        let chained = Bridges::parse(
+            &format!("[{}, {}]", bridge("a", "b", 1.0), bridge("b", "c", 1.0)),
+            StreamingEss::new(),
+        );
+
+        // assert
+        assert!(scale.is_err());
+        assert!(empty.is_err());
+        assert!(twice.is_err());
+        assert!(chained.is_err());
+        assert!(Bridges::parse(BRIDGES, StreamingEss::new()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn serve_only_serves_bridged_sources_of_local_channels() {
+        // arrange
+        let ess = StreamingEss::new();
+        let subject = Bridges::parse(BRIDGES, ess.clone()).unwrap();
+        _ = ess.read_events("consumer".into()).unwrap();
+
+        // act
+        let bridged = subject.serve("sdv.vdt", &subscribe("consumer", &["Vehicle.Cabin.Door"]));
+        let partially = subject
+            .serve("sdv.vdt", &subscribe("consumer", &["Vehicle.Cabin.Door", "Vehicle.Speed"]));
+        let other_namespace =
+            subject.serve("sdv.vdt.v2", &subscribe("consumer", &["Vehicle.Cabin.Door"]));
+        let remote = subject.serve("sdv.vdt", &subscribe("remote", &["Vehicle.Cabin.Door"]));
+
+        // assert
+        assert!(bridged.unwrap().is_some());
+        assert!(partially.unwrap().is_none());
+        assert!(other_namespace.unwrap().is_none());
+        assert!(remote.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn republish_publishes_mapped_event_under_bridged_source() {
+        // arrange
+        let ess = StreamingEss::new();
+        let subject = Bridges::parse(BRIDGES, ess.clone()).unwrap();
+        let (_, mut events) = ess.read_events("consumer".into()).unwrap();
+        subject
+            .serve("sdv.vdt", &subscribe("consumer", &["Vehicle.Cabin.Temperature"]))
+            .unwrap()
+            .unwrap();
+        let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+
+        // act
+        subject.republish(Event {
+            source: "sdv.vdt.v2/Cabin.Temperature".to_owned(),
+            value: Some(ValueMessage { value: Some(ValueEnum::Float32(20.0)) }),
+            timestamp: Some(timestamp.into()),
+            ..Default::default()
+        });
+
+        // assert
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!("sdv.vdt/Vehicle.Cabin.Temperature", event.source);
+        assert_eq!(Some(ValueEnum::Float32(68.0)), event.value.and_then(|v| v.value));
+        assert_eq!(Some(timestamp.into()), event.timestamp);
+    }
+}
@@ -0,0 +1,297 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Injects faults into the fulfillment of intents, such that applications can
+//! verify on a bench that they are resilient against misbehaving brokers and
+//! providers. Not meant to be enabled in production.
+//!
+//! Each rule selects intents by namespace, using the query syntax of the
+//! `Inspect` intent, and optionally by kind, and injects its fault with the
+//! given probability:
+//!
+//! - `latency` delays the provider by the given milliseconds.
+//! - `error` fails the intent with the given gRPC status code, e.g.
+//!   `unavailable`, without calling the provider.
+//! - `drop` calls the provider, but discards its response and fails the
+//!   intent with `DEADLINE_EXCEEDED` after holding it for the given
+//!   milliseconds, as if the response was lost.
+//!
+//! The rules are rolled independently, such that latency adds up over all
+//! rules which were hit, while the first error or drop hit applies.
+
+use std::fs;
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
+
+use intent_brokering_common::error::{Error, ResultExt as _};
+use intent_brokering_common::query::regex_from_query;
+use regex::Regex;
+use serde::Deserialize;
+use tokio::time::sleep;
+use tonic::{Code, Status};
+
+use crate::acl::parse_intent_kind;
+use crate::registry::{IntentConfiguration, IntentKind};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum FaultDefinition {
+    Latency { millis: u64 },
+    Error { code: String },
+    Drop { hold_millis: u64 },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleDefinition {
+    namespace: String,
+    /// The intents to inject the fault into, or all intents if not set.
+    intents: Option<Vec<String>>,
+    probability: f64,
+    fault: FaultDefinition,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Fault {
+    Latency(Duration),
+    Error(Code),
+    Drop(Duration),
+}
+
+struct Rule {
+    namespace: Regex,
+    intents: Option<Vec<IntentKind>>,
+    probability: f64,
+    fault: Fault,
+}
+
+impl Rule {
+    fn parse(definition: RuleDefinition) -> Result<Self, Error> {
+        if !(0.0..=1.0).contains(&definition.probability) {
+            return Err(Error::new(format!(
+                "Probability of faults in '{}' must be between 0 and 1.",
+                definition.namespace
+            )));
+        }
+
+        let fault = match definition.fault {
+            FaultDefinition::Latency { millis } => Fault::Latency(Duration::from_millis(millis)),
+            FaultDefinition::Error { code } => Fault::Error(parse_code(&code)?),
+            FaultDefinition::Drop { hold_millis } => {
+                Fault::Drop(Duration::from_millis(hold_millis))
+            }
+        };
+
+        Ok(Self {
+            namespace: regex_from_query(&definition.namespace),
+            intents: definition
+                .intents
+                .map(|intents| intents.iter().map(|intent| parse_intent_kind(intent)).collect())
+                .transpose()?,
+            probability: definition.probability,
+            fault,
+        })
+    }
+
+    fn applies(&self, intent: &IntentConfiguration) -> bool {
+        self.namespace.is_match(intent.namespace())
+            && self.intents.as_ref().map_or(true, |intents| intents.contains(&intent.intent()))
+    }
+}
+
+/// Parses the name of a gRPC status code other than `OK`, e.g.
+/// `unavailable` or `deadline_exceeded`.
+fn parse_code(code: &str) -> Result<Code, Error> {
+    let name = code.replace('_', "");
+    (1..=16)
+        .map(Code::from_i32)
+        .find(|candidate| format!("{candidate:?}").eq_ignore_ascii_case(&name))
+        .ok_or_else(|| Error::new(format!("Status code '{code}' is not known.")))
+}
+
+/// Fault injection rules loaded from a JSON file, e.g.
+/// `[{ "namespace": "sdv.vdt", "intents": ["read"], "probability": 0.1, "fault": { "error": { "code": "unavailable" } } }]`.
+pub struct Chaos {
+    rules: Vec<Rule>,
+}
+
+impl Chaos {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let chaos = fs::read_to_string(path)
+            .map_err_with(format!("Failed to read '{}'.", path.display()))?;
+        Self::parse(&chaos)
+    }
+
+    pub fn parse(chaos: &str) -> Result<Self, Error> {
+        let definition: Vec<RuleDefinition> = serde_json::from_str(chaos)
+            .map_err_with("Failed to parse the fault injection rules.")?;
+
+        Ok(Self { rules: definition.into_iter().map(Rule::parse).collect::<Result<_, _>>()? })
+    }
+
+    /// Rolls the rules which apply to an intent, returning the faults to
+    /// inject into its fulfillment.
+    pub fn roll(&self, intent: &IntentConfiguration) -> Faults {
+        self.roll_with(intent, rand::random::<f64>)
+    }
+
+    fn roll_with(&self, intent: &IntentConfiguration, mut random: impl FnMut() -> f64) -> Faults {
+        let mut faults = Faults::default();
+
+        for rule in self.rules.iter().filter(|rule| rule.applies(intent)) {
+            if random() >= rule.probability {
+                continue;
+            }
+
+            tracing::info!(
+                namespace = intent.namespace(),
+                intent = %intent.intent(),
+                fault = ?rule.fault,
+                "Injecting fault."
+            );
+
+            match rule.fault {
+                Fault::Latency(latency) => faults.latency += latency,
+                fault => _ = faults.failure.get_or_insert(fault),
+            }
+        }
+
+        faults
+    }
+}
+
+/// The faults to inject into the fulfillment of an intent.
+#[derive(Debug, Default, PartialEq)]
+pub struct Faults {
+    latency: Duration,
+    /// The error or drop to inject, if any.
+    failure: Option<Fault>,
+}
+
+impl Faults {
+    /// Runs the fulfillment of an intent with the faults injected.
+    pub async fn inject<T>(
+        self,
+        fulfillment: impl Future<Output = Result<T, Status>>,
+    ) -> Result<T, Status> {
+        if !self.latency.is_zero() {
+            sleep(self.latency).await;
+        }
+
+        match self.failure {
+            Some(Fault::Error(code)) => Err(Status::new(code, "Injected fault.")),
+            Some(Fault::Drop(hold)) => {
+                _ = fulfillment.await;
+                sleep(hold).await;
+                Err(Status::deadline_exceeded("Injected fault: the response was dropped."))
+            }
+            _ => fulfillment.await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tonic::{Code, Status};
+
+    use super::{Chaos, Fault, Faults};
+    use crate::registry::{IntentConfiguration, IntentKind};
+
+    const RULES: &str = r#"[
+        { "namespace": "sdv.vdt", "intents": ["read"], "probability": 0.5, "fault": { "latency": { "millis": 100 } } },
+        { "namespace": "sdv.**", "probability": 0.5, "fault": { "latency": { "millis": 50 } } },
+        { "namespace": "sdv.**", "probability": 0.1, "fault": { "error": { "code": "deadline_exceeded" } } },
+        { "namespace": "sdv.**", "probability": 0.1, "fault": { "drop": { "hold_millis": 10 } } }
+    ]"#;
+
+    fn intent(namespace: &str, intent: IntentKind) -> IntentConfiguration {
+        IntentConfiguration::new(namespace, intent)
+    }
+
+    #[test]
+    fn roll_injects_faults_of_hit_rules() {
+        // arrange
+        let subject = Chaos::parse(RULES).unwrap();
+
+        // act
+        let all = subject.roll_with(&intent("sdv.vdt", IntentKind::Read), || 0.0);
+        let latency = subject.roll_with(&intent("sdv.vdt", IntentKind::Read), || 0.3);
+        let none = subject.roll_with(&intent("sdv.vdt", IntentKind::Read), || 0.5);
+        let other_intent = subject.roll_with(&intent("sdv.vdt", IntentKind::Invoke), || 0.3);
+        let other_namespace =
+            subject.roll_with(&intent("system.registry", IntentKind::Read), || 0.0);
+
+        // assert
+        assert_eq!(
+            Faults {
+                latency: Duration::from_millis(150),
+                failure: Some(Fault::Error(Code::DeadlineExceeded))
+            },
+            all
+        );
+        assert_eq!(Faults { latency: Duration::from_millis(150), failure: None }, latency);
+        assert_eq!(Faults::default(), none);
+        assert_eq!(Faults { latency: Duration::from_millis(50), failure: None }, other_intent);
+        assert_eq!(Faults::default(), other_namespace);
+    }
+
+    #[tokio::test]
+    async fn inject_fails_without_calling_the_provider_for_errors() {
+        // arrange
+        let subject =
+            Faults { latency: Duration::ZERO, failure: Some(Fault::Error(Code::Unavailable)) };
+        let mut called = false;
+
+        // act
+        let result = subject
+            .inject(async {
+                called = true;
+                Ok::<_, Status>(())
+            })
+            .await;
+
+        // assert
+        assert!(!called);
+        assert_eq!(Code::Unavailable, result.unwrap_err().code());
+    }
+
+    #[tokio::test]
+    async fn inject_discards_the_response_for_drops() {
+        // arrange
+        let subject =
+            Faults { latency: Duration::ZERO, failure: Some(Fault::Drop(Duration::ZERO)) };
+        let mut called = false;
+
+        // act
+        let result = subject
+            .inject(async {
+                called = true;
+                Ok::<_, Status>(())
+            })
+            .await;
+
+        // assert
+        assert!(called);
+        assert_eq!(Code::DeadlineExceeded, result.unwrap_err().code());
+    }
+
+    #[test]
+    fn parse_fails_for_invalid_rules() {
+        // act
+        let probability = Chaos::parse(
+            r#"[{ "namespace": "sdv", "probability": 2, "fault": { "latency": { "millis": 1 } } }]"#,
+        );
+        let code = Chaos::parse(
+            r#"[{ "namespace": "sdv", "probability": 1, "fault": { "error": { "code": "oops" } } }]"#,
+        );
+
+        // assert
+        assert!(probability.is_err());
+        assert!(code.is_err());
+    }
+}
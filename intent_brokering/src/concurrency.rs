@@ -0,0 +1,130 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Limits the number of requests forwarded to a provider at once, for
+//! providers which declare a maximum concurrency when they register, e.g. a
+//! camera pipeline which can only handle a handful of requests.
+//!
+//! Each provider with a limit has a semaphore holding as many permits as the
+//! limit. Excess requests are queued until a permit is released, for up to the
+//! queue timeout of the Intent Broker. Requests which do not get a permit in
+//! time are rejected with `RESOURCE_EXHAUSTED` and the
+//! `x-chariott-concurrency-limit` metadata, which holds the limit of the
+//! provider. A queue timeout of zero rejects excess requests immediately.
+
+use std::{sync::Arc, time::Duration};
+
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::timeout,
+};
+use tonic::{metadata::MetadataValue, Status};
+
+pub const CONCURRENCY_LIMIT_METADATA_KEY: &str = "x-chariott-concurrency-limit";
+
+/// The default time for which a request waits for a provider which is at its
+/// limit.
+pub const DEFAULT_QUEUE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The permits of a provider. Cloning is cheap and shares the permits.
+#[derive(Clone, Debug)]
+pub struct ConcurrencyLimit {
+    limit: u32,
+    queue_timeout: Duration,
+    semaphore: Arc<Semaphore>,
+}
+
+impl ConcurrencyLimit {
+    /// Creates a limit of at least one concurrent request.
+    pub fn new(limit: u32, queue_timeout: Duration) -> Self {
+        let limit = std::cmp::max(limit, 1);
+        let permits = std::cmp::min(limit as usize, Semaphore::MAX_PERMITS);
+        Self { limit, queue_timeout, semaphore: Arc::new(Semaphore::new(permits)) }
+    }
+
+    /// The maximum number of concurrent requests.
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Waits for a permit for up to the queue timeout. The permit is released
+    /// when it is dropped.
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit, Status> {
+        let semaphore = Arc::clone(&self.semaphore);
+        let permit = if self.queue_timeout.is_zero() {
+            semaphore.try_acquire_owned().ok()
+        } else {
+            timeout(self.queue_timeout, semaphore.acquire_owned()).await.ok().and_then(Result::ok)
+        };
+
+        permit.ok_or_else(|| limit_exceeded(self.limit))
+    }
+}
+
+/// Creates the status with which a request beyond the limit of a provider is
+/// rejected.
+pub fn limit_exceeded(limit: u32) -> Status {
+    let mut status = Status::resource_exhausted(format!(
+        "Provider is at its limit of {limit} concurrent requests, retry later."
+    ));
+    status.metadata_mut().insert(CONCURRENCY_LIMIT_METADATA_KEY, MetadataValue::from(limit));
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Code;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn acquire_rejects_excess_requests_without_queue_timeout() {
+        // arrange
+        let subject = ConcurrencyLimit::new(2, Duration::ZERO);
+
+        // act
+        let first = subject.acquire().await;
+        let second = subject.acquire().await;
+        let third = subject.acquire().await;
+
+        // assert
+        assert!(first.is_ok());
+        assert!(second.is_ok());
+        let status = third.unwrap_err();
+        assert_eq!(Code::ResourceExhausted, status.code());
+        assert_eq!("2", status.metadata().get(CONCURRENCY_LIMIT_METADATA_KEY).unwrap());
+    }
+
+    #[tokio::test]
+    async fn acquire_queues_excess_requests_until_a_permit_is_released() {
+        // arrange
+        let subject = ConcurrencyLimit::new(1, Duration::from_secs(5));
+        let permit = subject.acquire().await.unwrap();
+        let queued = tokio::spawn({
+            let subject = subject.clone();
+            async move { subject.acquire().await.map(|_| ()) }
+        });
+
+        // act
+        drop(permit);
+        let result = queued.await.unwrap();
+
+        // assert
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn acquire_rejects_queued_requests_after_queue_timeout() {
+        // arrange
+        let subject = ConcurrencyLimit::new(0, Duration::from_millis(10));
+        let _permit = subject.acquire().await.unwrap();
+
+        // act
+        let result = subject.acquire().await;
+
+        // assert
+        assert_eq!(1, subject.limit());
+        assert_eq!(Code::ResourceExhausted, result.unwrap_err().code());
+    }
+}
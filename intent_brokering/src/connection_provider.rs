@@ -2,7 +2,9 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use intent_brokering_common::error::{Error, ResultExt as _};
@@ -11,7 +13,10 @@ use intent_brokering_proto::provider::{
 };
 use tokio::sync::Mutex;
 use tonic::{transport::Channel, Request};
-use url::Url;
+use url::{Host, Url};
+
+use crate::correlation;
+use crate::provider_url;
 
 /// Contains abstractions and implementations related to communication with
 /// remote providers. The `ConnectionProvider` trait represents a remote
@@ -48,27 +53,48 @@ pub trait ConnectedProvider {
 
 /// Represents an unconnected, gRPC-based provider.
 #[derive(Clone, Debug)]
-pub struct GrpcProvider(pub(super) Url);
+pub struct GrpcProvider {
+    pub(super) url: Url,
+    max_response_size: Option<usize>,
+}
+
+impl GrpcProvider {
+    /// Limits the size of the responses decoded from the provider, such that
+    /// larger responses are rejected before they are buffered.
+    pub fn with_max_response_size(self, size: usize) -> Self {
+        Self { max_response_size: Some(size), ..self }
+    }
+}
 
 #[async_trait]
 impl ConnectionProvider for GrpcProvider {
     type ConnectedProvider = ProviderServiceClient<Channel>;
 
     fn new(url: Url) -> Self {
-        Self(url)
+        Self { url, max_response_size: None }
     }
 
     async fn connect(&mut self) -> Result<Self::ConnectedProvider, Error> {
-        ProviderServiceClient::connect(self.0.to_string())
+        let client = ProviderServiceClient::connect(self.url.to_string())
             .await
-            .map_err_with("Error when connecting to provider.")
+            .map_err_with("Error when connecting to provider.")?;
+
+        Ok(match self.max_response_size {
+            Some(size) => client.max_decoding_message_size(size),
+            None => client,
+        })
     }
 }
 
 #[async_trait]
 impl ConnectedProvider for ProviderServiceClient<Channel> {
     async fn fulfill(&mut self, fulfill_request: FulfillRequest) -> Result<FulfillResponse, Error> {
-        self.fulfill(Request::new(fulfill_request))
+        let mut request = Request::new(fulfill_request);
+        if let Some(correlation_id) = correlation::current() {
+            correlation::insert(request.metadata_mut(), &correlation_id);
+        }
+
+        self.fulfill(request)
             .await
             .map_err_with("Error when invoking provider.")
             .map(|r| r.into_inner())
@@ -77,10 +103,68 @@ impl ConnectedProvider for ProviderServiceClient<Channel> {
 
 /// Allows us to reuse a connected provider based on an unconnected provider,
 /// given that they support an efficient `Clone` implementation.
+///
+/// Providers registered with a host name, e.g. a Kubernetes service, may move
+/// to other addresses while the connection to them is reused. With
+/// re-resolution, the host name is resolved again once per interval, and the
+/// connection is dropped and established anew if the addresses changed, such
+/// that it does not stay pinned to an address which is gone. If the host name
+/// resolves to an address blocked for providers, see [`provider_url`], the
+/// connection is not established anew. Providers registered with an address
+/// are never re-resolved.
 #[derive(Clone, Debug)]
 pub struct ReusableProvider<T: ConnectionProvider + Clone> {
     pub(super) inner: T,
-    connected_inner: Arc<Mutex<Option<T::ConnectedProvider>>>,
+    url: Url,
+    re_resolution: Option<Duration>,
+    connected_inner: Arc<Mutex<Option<Connected<T::ConnectedProvider>>>>,
+}
+
+/// A connected provider and the addresses its host name resolved to when it
+/// was last resolved.
+#[derive(Debug)]
+struct Connected<T> {
+    provider: T,
+    addresses: Option<Vec<SocketAddr>>,
+    resolved_at: Instant,
+}
+
+impl<T: ConnectionProvider + Clone> ReusableProvider<T> {
+    /// Resolves the host name of the provider again once per interval, and
+    /// reconnects if its addresses changed. Has no effect if the provider is
+    /// registered with an address instead of a host name.
+    pub fn with_re_resolution(self, interval: Duration) -> Self {
+        let re_resolution = matches!(self.url.host(), Some(Host::Domain(_))).then_some(interval);
+        Self { re_resolution, ..self }
+    }
+
+    /// Resolves the host name of the provider at a URL, if re-resolution is
+    /// enabled. Returns `None` if it is disabled or the host name cannot be
+    /// resolved.
+    async fn resolve(url: &Url, re_resolution: Option<Duration>) -> Option<Vec<SocketAddr>> {
+        re_resolution?;
+        let host = url.host_str()?;
+        let port = url.port_or_known_default().unwrap_or_default();
+        match tokio::net::lookup_host((host, port)).await {
+            Ok(addresses) => {
+                let mut addresses = addresses.collect::<Vec<_>>();
+                addresses.sort_unstable();
+                Some(addresses)
+            }
+            Err(e) => {
+                tracing::debug!("Error when re-resolving provider '{}': {e}", url);
+                None
+            }
+        }
+    }
+}
+
+impl ReusableProvider<GrpcProvider> {
+    /// Limits the size of the responses decoded from the provider, see
+    /// [`GrpcProvider::with_max_response_size`].
+    pub fn with_max_response_size(self, size: usize) -> Self {
+        Self { inner: self.inner.with_max_response_size(size), ..self }
+    }
 }
 
 /// Reuses a cached connected instance to be optimize the reconnection. When
@@ -95,11 +179,17 @@ where
     type ConnectedProvider = T::ConnectedProvider;
 
     fn new(url: Url) -> Self {
-        Self { inner: T::new(url), connected_inner: Arc::new(Mutex::new(None)) }
+        Self {
+            inner: T::new(url.clone()),
+            url,
+            re_resolution: None,
+            connected_inner: Arc::new(Mutex::new(None)),
+        }
     }
 
     /// Establishes a connection to the provider if none exists, or clones the
-    /// cached connection if already present.
+    /// cached connection if already present. With re-resolution, the cached
+    /// connection is replaced if the addresses of the provider changed.
     async fn connect(&mut self) -> Result<Self::ConnectedProvider, Error> {
         // Even though this operation is expected to be write-heavy, we choose
         // Mutex over RwLock as otherwise we might drop a few connections when
@@ -107,15 +197,55 @@ where
         // in performance between the two, as the bottleneck is in a different
         // component.
 
+        if let Some(connected) = self.connected_inner.lock().await.as_mut() {
+            let due = self
+                .re_resolution
+                .map_or(false, |interval| connected.resolved_at.elapsed() >= interval);
+            if !due {
+                return Ok(connected.provider.clone());
+            }
+
+            // Marking the connection as resolved lets other intents reuse it
+            // while the host name is resolved.
+            connected.resolved_at = Instant::now();
+        }
+
+        // The host name is resolved without holding the lock, such that a
+        // slow lookup does not hold up intents reusing the connection.
+        let addresses = Self::resolve(&self.url, self.re_resolution).await;
+        let blocked = addresses
+            .iter()
+            .flatten()
+            .find_map(|address| provider_url::validate_address(address.ip()).err());
+
         let mut connected_inner = self.connected_inner.lock().await;
 
-        if let Some(connected_inner) = connected_inner.clone() {
-            Ok(connected_inner)
-        } else {
-            let client = self.inner.connect().await?;
-            *connected_inner = Some(client.clone());
-            Ok(client)
+        if let Some(e) = blocked {
+            tracing::warn!("Provider '{}' resolves to a blocked address: {e}", self.url);
+            return match connected_inner.as_ref() {
+                Some(connected) => Ok(connected.provider.clone()),
+                None => Err(Error::from_error("Error when connecting to provider.", Box::new(e))),
+            };
+        }
+
+        if let Some(connected) = connected_inner.as_ref() {
+            // Failing to resolve the host name keeps the connection, as the
+            // provider may still be reachable at its current address.
+            match &addresses {
+                Some(addresses) if Some(addresses) != connected.addresses.as_ref() => {
+                    tracing::info!(
+                        "Addresses of provider '{}' changed to {addresses:?}, reconnecting.",
+                        self.url
+                    );
+                }
+                _ => return Ok(connected.provider.clone()),
+            }
         }
+
+        let client = self.inner.connect().await?;
+        *connected_inner =
+            Some(Connected { provider: client.clone(), addresses, resolved_at: Instant::now() });
+        Ok(client)
     }
 }
 
@@ -125,6 +255,7 @@ mod tests {
         atomic::{AtomicUsize, Ordering},
         Arc,
     };
+    use std::time::Duration;
 
     use async_trait::async_trait;
     use intent_brokering_common::error::Error;
@@ -133,6 +264,84 @@ mod tests {
 
     use super::{ConnectedProvider, ConnectionProvider, ReusableProvider};
 
+    const HOST_URL: &str = "http://localhost:50051"; // DevSkim: ignore DS137138
+    const ADDRESS_URL: &str = "http://10.0.0.5:50051"; // DevSkim: ignore DS137138
+
+    /// Counts how often it connects.
+    #[derive(Clone, Default)]
+    struct CountingProvider {
+        connect_count: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl ConnectionProvider for CountingProvider {
+        type ConnectedProvider = ();
+
+        fn new(_: Url) -> Self {
+            Self::default()
+        }
+
+        async fn connect(&mut self) -> Result<Self::ConnectedProvider, Error> {
+            self.connect_count.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl ConnectedProvider for () {
+        async fn fulfill(&mut self, _: FulfillRequest) -> Result<FulfillResponse, Error> {
+            Err(Error::new("Not implemented"))
+        }
+    }
+
+    fn counting_provider(url: &str) -> (ReusableProvider<CountingProvider>, Arc<AtomicUsize>) {
+        let provider = ReusableProvider::<CountingProvider>::new(url.parse().unwrap())
+            .with_re_resolution(Duration::ZERO);
+        let connect_count = Arc::clone(&provider.inner.connect_count);
+        (provider, connect_count)
+    }
+
+    #[tokio::test]
+    async fn reusable_provider_with_re_resolution_reuses_provider_if_addresses_are_unchanged() {
+        // arrange
+        let (mut subject, connect_count) = counting_provider(HOST_URL);
+
+        // act
+        subject.connect().await.unwrap();
+        subject.connect().await.unwrap();
+
+        // assert
+        assert_eq!(1, connect_count.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn reusable_provider_with_re_resolution_reconnects_if_addresses_changed() {
+        // arrange
+        let (mut subject, connect_count) = counting_provider(HOST_URL);
+        subject.connect().await.unwrap();
+        subject.connected_inner.lock().await.as_mut().unwrap().addresses =
+            Some(vec!["10.0.0.5:50051".parse().unwrap()]);
+
+        // act
+        subject.connect().await.unwrap();
+
+        // assert
+        assert_eq!(2, connect_count.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn re_resolution_is_ignored_for_providers_registered_with_address() {
+        // act
+        let (address, _) = counting_provider(ADDRESS_URL);
+        let (ipv6, _) = counting_provider("http://[fd00::5]:50051"); // DevSkim: ignore DS137138
+        let (host, _) = counting_provider("https://provider.vehicle:50051");
+
+        // assert
+        assert_eq!(None, address.re_resolution);
+        assert_eq!(None, ipv6.re_resolution);
+        assert_eq!(Some(Duration::ZERO), host.re_resolution);
+    }
+
     #[tokio::test]
     async fn reusable_provider_when_already_connected_reuses_provider() {
         #[derive(Clone)]
@@ -175,7 +384,10 @@ mod tests {
 
         // assert
         async fn fulfill_any(provider: &mut MockConnectedProvider) {
-            provider.fulfill(FulfillRequest { intent: None }).await.unwrap_err();
+            provider
+                .fulfill(FulfillRequest { intent: None, transaction: None, operation: None })
+                .await
+                .unwrap_err();
         }
 
         fulfill_any(&mut first).await;
@@ -0,0 +1,99 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Correlates a fulfill request across the components it passes through.
+//!
+//! Callers may attach a correlation ID to a request with the
+//! `x-chariott-correlation-id` metadata, otherwise one is generated. The ID is
+//! forwarded to providers with the same metadata, recorded with the logs
+//! emitted while fulfilling the request, and returned with the metadata of
+//! the response or error.
+
+use std::future::Future;
+
+use tonic::metadata::{MetadataMap, MetadataValue};
+use uuid::Uuid;
+
+pub const CORRELATION_ID_METADATA_KEY: &str = "x-chariott-correlation-id";
+
+const MAX_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static CORRELATION_ID: Box<str>;
+}
+
+/// Returns the correlation ID attached to a request, or generates a new one
+/// if it is missing or not a printable ASCII string of at most 128
+/// characters.
+pub fn from_metadata(metadata: &MetadataMap) -> Box<str> {
+    metadata
+        .get(CORRELATION_ID_METADATA_KEY)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= MAX_LENGTH)
+        .map(|id| id.into())
+        .unwrap_or_else(|| Uuid::new_v4().to_string().into())
+}
+
+/// Attaches the correlation ID to the metadata of a request or response.
+pub fn insert(metadata: &mut MetadataMap, id: &str) {
+    if let Ok(value) = MetadataValue::try_from(id) {
+        metadata.insert(CORRELATION_ID_METADATA_KEY, value);
+    }
+}
+
+/// Runs `f` with the correlation ID as the one of the current request.
+pub async fn scope<F: Future>(id: Box<str>, f: F) -> F::Output {
+    CORRELATION_ID.scope(id, f).await
+}
+
+/// Returns the correlation ID of the request which is currently fulfilled, if
+/// any.
+pub fn current() -> Option<Box<str>> {
+    CORRELATION_ID.try_with(Clone::clone).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_metadata_accepts_correlation_id_of_caller() {
+        // arrange
+        let mut metadata = MetadataMap::new();
+        insert(&mut metadata, "request-1");
+
+        // act
+        let result = from_metadata(&metadata);
+
+        // assert
+        assert_eq!("request-1", result.as_ref());
+    }
+
+    #[test]
+    fn from_metadata_generates_correlation_id_if_missing_or_invalid() {
+        // arrange
+        let mut invalid = MetadataMap::new();
+        insert(&mut invalid, &"a".repeat(MAX_LENGTH + 1));
+
+        // act
+        let generated = from_metadata(&MetadataMap::new());
+        let replaced = from_metadata(&invalid);
+
+        // assert
+        assert!(Uuid::parse_str(&generated).is_ok());
+        assert!(Uuid::parse_str(&replaced).is_ok());
+        assert_ne!(generated, replaced);
+    }
+
+    #[tokio::test]
+    async fn current_returns_correlation_id_within_scope() {
+        // act
+        let within = scope("request-1".into(), async { current() }).await;
+        let outside = current();
+
+        // assert
+        assert_eq!(Some("request-1".into()), within);
+        assert_eq!(None, outside);
+    }
+}
@@ -3,26 +3,33 @@
 // SPDX-License-Identifier: MIT
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
+use crate::concurrency::ConcurrencyLimit;
 use crate::connection_provider::{ConnectedProvider, ConnectionProvider};
-use crate::registry::IntentConfiguration;
-use crate::streaming::StreamingEss;
+use crate::overrides::Canned;
+use crate::registry::{Deprecation, IntentConfiguration, IntentKind};
+use crate::streaming::{proxied_source, StreamingEss, SubscriptionProxy};
+use crate::system::SystemPlugin;
 use async_recursion::async_recursion;
-use intent_brokering_common::query::regex_from_query;
+use intent_brokering_common::{error::Error, query::regex_from_query};
 use intent_brokering_proto::{
     common::{
         discover_fulfillment::Service, inspect_fulfillment::Entry, DiscoverFulfillment,
-        FulfillmentEnum, FulfillmentMessage, InspectFulfillment, IntentEnum, IntentMessage, List,
-        ValueEnum, ValueMessage,
+        DiscoverIntent, FulfillmentEnum, FulfillmentMessage, InspectFulfillment, IntentEnum,
+        IntentMessage, List, Map, SubscribeIntent, ValueEnum, ValueMessage,
     },
     provider::{FulfillRequest, FulfillResponse},
 };
-use tonic::Status;
+use tonic::{Code, Status};
 use url::Url;
 
 const REGISTERED_INTENTS_KEY: &str = "registered_intents";
+pub(crate) const DEPRECATIONS_KEY: &str = "deprecations";
+const SCHEMA_VERSION_STREAMING: &str = "intent_brokering.streaming.v1";
+const SCHEMA_REFERENCE: &str = "grpc+proto";
 
-trait IterGroupingExt<K, V>: IntoIterator<Item = (K, V)> {
+pub(crate) trait IterGroupingExt<K, V>: IntoIterator<Item = (K, V)> {
     fn group(self) -> HashMap<K, Vec<V>>;
 }
 
@@ -44,9 +51,24 @@ where
 pub enum RuntimeBinding<T: ConnectionProvider> {
     Remote(T),
     Fallback(Box<RuntimeBinding<T>>, Box<RuntimeBinding<T>>),
-    SystemInspect(Vec<IntentConfiguration>),
+    /// Executes the inner binding once the provider is below its limit of
+    /// concurrent requests.
+    Limited(ConcurrencyLimit, Box<RuntimeBinding<T>>),
+    /// Inspects the registered intents, including the deprecations of the
+    /// deprecated ones.
+    SystemInspect(Vec<IntentConfiguration>, HashMap<IntentConfiguration, Deprecation>),
     SystemDiscover(Url),
     SystemSubscribe(StreamingEss),
+    /// Fulfills intents of a `system.*` namespace with a plugin of the
+    /// Intent Broker.
+    System(Arc<dyn SystemPlugin>),
+    /// Proxies subscriptions on channels opened with the Intent Broker to the
+    /// `ChannelService` of the provider resolved by the inner binding for the
+    /// given namespace. All other intents are executed by the inner binding.
+    ProxySubscribe(SubscriptionProxy, Arc<str>, Box<RuntimeBinding<T>>),
+    /// Fulfills an overridden intent with a canned outcome, see
+    /// [`crate::overrides`].
+    Canned(Canned),
     #[cfg(test)]
     Test(tests::TestBinding),
 }
@@ -54,7 +76,7 @@ pub enum RuntimeBinding<T: ConnectionProvider> {
 impl<T> RuntimeBinding<T>
 where
     T::ConnectedProvider: Send,
-    T: ConnectionProvider + Send + 'static,
+    T: ConnectionProvider + Clone + Send + 'static,
 {
     #[async_recursion]
     pub async fn execute(self, arg: IntentMessage) -> Result<FulfillResponse, Status> {
@@ -68,17 +90,28 @@ where
             RuntimeBinding::Remote(mut provider) => provider
                 .connect()
                 .await
-                .map_err(|e| Status::unknown(format!("Failed to connect to provider: {}.", e)))?
-                .fulfill(FulfillRequest { intent: Some(arg) })
+                .map_err(|e| Status::unavailable(format!("Failed to connect to provider: {}.", e)))?
+                .fulfill(FulfillRequest { intent: Some(arg), transaction: None, operation: None })
                 .await
-                .map_err(|e| Status::unknown(format!("Error when invoking provider: '{}'.", e))),
+                .map_err(|e| {
+                    let message = format!("Error when invoking provider: '{}'.", e);
+                    if is_unavailable(&e) {
+                        Status::unavailable(message)
+                    } else {
+                        Status::unknown(message)
+                    }
+                }),
             RuntimeBinding::Fallback(primary, secondary) => {
                 match primary.execute(arg.clone()).await {
                     ok @ Ok(_) => ok,
                     Err(_) => secondary.execute(arg).await,
                 }
             }
-            RuntimeBinding::SystemInspect(intents) => {
+            RuntimeBinding::Limited(limit, inner) => {
+                let _permit = limit.acquire().await?;
+                inner.execute(arg).await
+            }
+            RuntimeBinding::SystemInspect(intents, deprecations) => {
                 if let Some(IntentEnum::Inspect(inspect_intent)) = arg.intent {
                     let regex = regex_from_query(&inspect_intent.query);
 
@@ -91,9 +124,20 @@ where
                     fulfill_response(FulfillmentEnum::Inspect(InspectFulfillment {
                         entries: intents
                             .into_iter()
-                            .map(|(path, intent_kinds)| Entry {
-                                path,
-                                items: HashMap::from([(
+                            .map(|(path, intent_kinds)| {
+                                let deprecated: Vec<_> = intent_kinds
+                                    .iter()
+                                    .filter_map(|intent_kind| {
+                                        deprecations
+                                            .get(&IntentConfiguration::new(
+                                                Arc::clone(&path),
+                                                *intent_kind,
+                                            ))
+                                            .map(|deprecation| (*intent_kind, deprecation))
+                                    })
+                                    .collect();
+
+                                let mut items = HashMap::from([(
                                     REGISTERED_INTENTS_KEY.to_owned(),
                                     ValueMessage {
                                         value: Some(ValueEnum::List(List {
@@ -107,7 +151,18 @@ where
                                                 .collect(),
                                         })),
                                     },
-                                )]),
+                                )]);
+
+                                if !deprecated.is_empty() {
+                                    items.insert(
+                                        DEPRECATIONS_KEY.to_owned(),
+                                        ValueMessage {
+                                            value: Some(deprecations_value(deprecated)),
+                                        },
+                                    );
+                                }
+
+                                Entry { path: path.to_string(), items }
                             })
                             .collect(),
                     }))
@@ -116,9 +171,6 @@ where
                 }
             }
             RuntimeBinding::SystemDiscover(url) => {
-                const SCHEMA_VERSION_STREAMING: &str = "intent_brokering.streaming.v1";
-                const SCHEMA_REFERENCE: &str = "grpc+proto";
-
                 fulfill_response(FulfillmentEnum::Discover(DiscoverFulfillment {
                     services: vec![Service {
                         url: url.to_string(),
@@ -131,18 +183,143 @@ where
             RuntimeBinding::SystemSubscribe(ess) => {
                 if let Some(IntentEnum::Subscribe(subscribe_intent)) = arg.intent {
                     fulfill_response(FulfillmentEnum::Subscribe(
-                        ess.serve_subscriptions(subscribe_intent, |_| ValueEnum::Null(0))?,
+                        ess.serve_timestamped_subscriptions(subscribe_intent, |v| v)?,
                     ))
                 } else {
                     panic!("An intent other than 'Subscribe' was resolved to 'SystemSubscribe'.")
                 }
             }
+            RuntimeBinding::System(plugin) => match arg.intent {
+                Some(intent) => fulfill_response(plugin.fulfill(intent).await?),
+                None => Err(Status::invalid_argument("Intent is not known.")),
+            },
+            RuntimeBinding::ProxySubscribe(proxy, namespace, inner) => match arg.intent {
+                Some(IntentEnum::Subscribe(subscribe_intent))
+                    if proxy.is_local_channel(&subscribe_intent.channel_id) =>
+                {
+                    proxy_subscribe(proxy, &namespace, *inner, subscribe_intent).await
+                }
+                _ => inner.execute(arg).await,
+            },
+            RuntimeBinding::Canned(canned) => canned.fulfill(),
             #[cfg(test)]
             RuntimeBinding::Test(item) => item.execute(arg),
         }
     }
 }
 
+/// Whether invoking a provider failed because it is unreachable, e.g. as it
+/// stopped after the connection to it was established.
+fn is_unavailable(error: &Error) -> bool {
+    std::error::Error::source(error)
+        .and_then(|source| source.downcast_ref::<Status>())
+        .is_some_and(|status| status.code() == Code::Unavailable)
+}
+
+/// Converts deprecated intents into a map from each intent to its `sunset`
+/// and `replacement`, if known.
+pub(crate) fn deprecations_value<'a>(
+    deprecations: impl IntoIterator<Item = (IntentKind, &'a Deprecation)>,
+) -> ValueEnum {
+    ValueEnum::Map(Map {
+        map: deprecations
+            .into_iter()
+            .map(|(intent, deprecation)| {
+                let sunset = deprecation
+                    .sunset()
+                    .map(|sunset| ("sunset".to_owned(), ValueEnum::Timestamp(sunset.into())));
+                let replacement = deprecation.replacement().map(|replacement| {
+                    ("replacement".to_owned(), ValueEnum::String(replacement.to_owned()))
+                });
+
+                let value = ValueEnum::Map(Map {
+                    map: sunset
+                        .into_iter()
+                        .chain(replacement)
+                        .map(|(key, value)| (key, ValueMessage { value: Some(value) }))
+                        .collect(),
+                });
+
+                (intent.to_string(), ValueMessage { value: Some(value) })
+            })
+            .collect(),
+    })
+}
+
+/// Subscribes a consumer channel of the Intent Broker to the sources of a
+/// provider. The consumer subscriptions are registered before subscribing
+/// upstream, so that no early events of the provider are missed.
+async fn proxy_subscribe<T>(
+    proxy: SubscriptionProxy,
+    namespace: &str,
+    inner: RuntimeBinding<T>,
+    subscribe_intent: SubscribeIntent,
+) -> Result<FulfillResponse, Status>
+where
+    T::ConnectedProvider: Send,
+    T: ConnectionProvider + Clone + Send + 'static,
+{
+    let discover = IntentMessage { intent: Some(IntentEnum::Discover(DiscoverIntent {})) };
+    let discover_binding = inner.clone();
+
+    let streaming_url =
+        match discover_binding.execute(discover).await?.fulfillment.and_then(|f| f.fulfillment) {
+            Some(FulfillmentEnum::Discover(DiscoverFulfillment { services })) => services
+                .into_iter()
+                .find(|s| {
+                    s.schema_kind == SCHEMA_REFERENCE
+                        && s.schema_reference == SCHEMA_VERSION_STREAMING
+                })
+                .ok_or_else(|| Status::failed_precondition("Provider does not support streaming."))?
+                .url
+                .parse::<Url>()
+                .map_err(|_| Status::unknown("Provider streaming URL is not valid."))?,
+            _ => Err(Status::unknown("Provider did not return a discover fulfillment."))?,
+        };
+
+    let upstream_channel_id = proxy
+        .upstream_channel(namespace, streaming_url)
+        .await
+        .map_err(|e| Status::unavailable(format!("Failed to proxy subscription: {e}.")))?;
+
+    let SubscribeIntent { channel_id, sources, filters } = subscribe_intent;
+    let proxied_sources: Vec<_> = sources.iter().map(|s| proxied_source(namespace, s)).collect();
+
+    // The upstream channel is shared by all consumers, hence filters are
+    // evaluated by the Intent Broker rather than forwarded to the provider.
+    let fulfillment = proxy.ess().serve_timestamped_subscriptions(
+        SubscribeIntent {
+            channel_id: channel_id.clone(),
+            sources: proxied_sources.clone(),
+            filters: filters.into_iter().map(|(s, f)| (proxied_source(namespace, &s), f)).collect(),
+        },
+        |v| v,
+    )?;
+
+    let upstream_intent = IntentMessage {
+        intent: Some(IntentEnum::Subscribe(SubscribeIntent {
+            channel_id: upstream_channel_id.into(),
+            sources,
+            ..Default::default()
+        })),
+    };
+
+    match inner.execute(upstream_intent).await {
+        Ok(_) => Ok(FulfillResponse {
+            fulfillment: Some(FulfillmentMessage {
+                fulfillment: Some(FulfillmentEnum::Subscribe(fulfillment)),
+            }),
+        }),
+        Err(e) => {
+            _ = proxy.ess().deregister_subscriptions(
+                channel_id.as_str(),
+                proxied_sources.into_iter().map(|s| s.into()),
+            );
+            Err(e)
+        }
+    }
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
     use std::time::Duration;
@@ -150,13 +327,15 @@ pub(crate) mod tests {
     use crate::{
         connection_provider::GrpcProvider,
         registry::{IntentConfiguration, IntentKind},
+        system::EssStatistics,
     };
     use async_trait::async_trait;
     use futures::Stream;
+    use intent_brokering_common::streaming_ess::Timestamped;
     use intent_brokering_proto::{
         common::{
             DiscoverFulfillment, FulfillmentEnum, FulfillmentMessage, InspectIntent,
-            InvokeFulfillment, SubscribeFulfillment, SubscribeIntent,
+            InvokeFulfillment, ReadIntent, SubscribeFulfillment, SubscribeIntent,
         },
         streaming::{channel_service_server::ChannelService, OpenRequest},
     };
@@ -219,6 +398,20 @@ pub(crate) mod tests {
         )
     }
 
+    #[tokio::test]
+    async fn remote_binding_when_provider_is_unreachable_returns_unavailable() {
+        // arrange
+        let subject = RuntimeBinding::Remote(GrpcProvider::new(
+            "http://localhost:1".parse().unwrap(), // DevSkim: ignore DS137138
+        ));
+
+        // act
+        let result = execute_with_empty_intent(subject).await;
+
+        // assert
+        assert_eq!(Code::Unavailable, result.unwrap_err())
+    }
+
     #[tokio::test]
     async fn fallback_binding_when_first_succeeds_should_return_response() {
         // arrange
@@ -262,10 +455,29 @@ pub(crate) mod tests {
         assert_eq!(Code::Internal, result.unwrap_err())
     }
 
+    #[tokio::test]
+    async fn limited_binding_executes_inner_binding_below_limit_only() {
+        // arrange
+        let limit = ConcurrencyLimit::new(1, Duration::ZERO);
+        let subject = RuntimeBinding::Limited(
+            limit.clone(),
+            Box::new(RuntimeBinding::Test(TestBinding::from_result(Ok(1)))),
+        );
+
+        // act
+        let below_limit = execute_with_empty_intent(subject.clone()).await;
+        let _permit = limit.acquire().await.unwrap();
+        let at_limit = execute_with_empty_intent(subject).await;
+
+        // assert
+        assert_eq!(Ok(1), below_limit);
+        assert_eq!(Err(Code::ResourceExhausted), at_limit);
+    }
+
     #[tokio::test]
     #[should_panic = "An intent other than 'Inspect' was resolved to 'SystemInspect'."]
     async fn system_inspect_binding_fails_with_non_supported_intent() {
-        _ = execute_with_empty_intent(RuntimeBinding::SystemInspect(vec![])).await;
+        _ = execute_with_empty_intent(RuntimeBinding::SystemInspect(vec![], HashMap::new())).await;
     }
 
     #[tokio::test]
@@ -345,6 +557,36 @@ pub(crate) mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn system_inspect_binding_includes_deprecations() {
+        // arrange
+        let deprecated = IntentConfiguration::new("foo", IntentKind::Read);
+        let intents = vec![deprecated.clone(), IntentConfiguration::new("foo", IntentKind::Write)];
+        let deprecations =
+            HashMap::from([(deprecated, Deprecation::new(None, Some("bar".into())))]);
+
+        // act
+        let response = RuntimeBinding::<GrpcProvider>::SystemInspect(intents, deprecations)
+            .execute(IntentMessage {
+                intent: Some(IntentEnum::Inspect(InspectIntent { query: "foo".to_owned() })),
+            })
+            .await;
+
+        // assert
+        let Some(FulfillmentEnum::Inspect(InspectFulfillment { entries })) =
+            response.unwrap().fulfillment.unwrap().fulfillment
+        else {
+            panic!("Wrong fulfillment")
+        };
+        assert_eq!(
+            Some(deprecations_value([(
+                IntentKind::Read,
+                &Deprecation::new(None, Some("bar".into()))
+            )])),
+            entries[0].items[DEPRECATIONS_KEY].value
+        );
+    }
+
     #[tokio::test]
     async fn system_discover_binding_succeeds() {
         // arrange
@@ -386,7 +628,7 @@ pub(crate) mod tests {
         const EVENT: &str = "test-event";
 
         let streaming_ess = StreamingEss::new();
-        let response = streaming_ess.open(Request::new(OpenRequest {})).await.unwrap();
+        let response = streaming_ess.open(Request::new(OpenRequest::default())).await.unwrap();
         let channel_id =
             response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
         let stream = response.into_inner();
@@ -397,6 +639,7 @@ pub(crate) mod tests {
                 intent: Some(IntentEnum::Subscribe(SubscribeIntent {
                     channel_id,
                     sources: vec![EVENT.into()],
+                    ..Default::default()
                 })),
             })
             .await
@@ -406,21 +649,41 @@ pub(crate) mod tests {
         assert_eq!(
             FulfillResponse {
                 fulfillment: Some(FulfillmentMessage {
-                    fulfillment: Some(FulfillmentEnum::Subscribe(SubscribeFulfillment {})),
+                    fulfillment: Some(FulfillmentEnum::Subscribe(SubscribeFulfillment::default())),
                 }),
             },
             result
         );
 
         // assert that the correct subscription was served
-        streaming_ess.publish(EVENT, ());
+        streaming_ess.publish(EVENT, Timestamped::now(ValueEnum::Null(0)));
         let result = stream.collect_when_stable().await;
         assert_eq!(1, result.len());
         assert_eq!(EVENT, result[0].as_ref().unwrap().source.as_str());
     }
 
+    #[tokio::test]
+    async fn system_binding_fulfills_intent_with_plugin() {
+        // arrange
+        let plugin = Arc::new(EssStatistics::new(StreamingEss::new()));
+
+        // act
+        let response = RuntimeBinding::<GrpcProvider>::System(plugin)
+            .execute(IntentMessage {
+                intent: Some(IntentEnum::Read(ReadIntent { key: "unknown".to_owned() })),
+            })
+            .await
+            .unwrap();
+
+        // assert
+        assert!(matches!(
+            response.fulfillment.unwrap().fulfillment,
+            Some(FulfillmentEnum::Read(_))
+        ));
+    }
+
     async fn execute_system_inspect(query: &str, intents: Vec<IntentConfiguration>) -> Vec<Entry> {
-        let response = RuntimeBinding::<GrpcProvider>::SystemInspect(intents)
+        let response = RuntimeBinding::<GrpcProvider>::SystemInspect(intents, HashMap::new())
             .execute(IntentMessage {
                 intent: Some(IntentEnum::Inspect(InspectIntent { query: query.to_owned() })),
             })
@@ -0,0 +1,188 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Federates the Intent Brokers of several compute units of a vehicle, such
+//! that consumers see one logical broker.
+//!
+//! Each broker is configured with the URLs of its peers, and learns the
+//! namespaces each peer serves by inspecting the `system.registry` of the
+//! peer at a fixed interval. Intents for namespaces which are not served
+//! locally are forwarded to the first peer serving them with an
+//! [`Upstream`], hence the metadata of the caller is forwarded and loops are
+//! detected like for the upstream Intent Broker. Only the namespaces which a
+//! peer serves itself are learned, not the ones it forwards, and `system.*`
+//! namespaces are never federated, as every broker serves its own. The
+//! namespaces of a peer which cannot be inspected are forgotten until it can
+//! be inspected again.
+
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use intent_brokering_common::error::Error;
+use intent_brokering_proto::common::{
+    FulfillmentEnum, FulfillmentMessage, InspectIntent, IntentEnum, IntentMessage,
+};
+use tokio::{task::JoinSet, time::sleep};
+use tonic::metadata::MetadataMap;
+use url::Url;
+
+use crate::upstream::{self, Upstream};
+
+const SYSTEM_REGISTRY_NAMESPACE: &str = "system.registry";
+const SYSTEM_NAMESPACE_PREFIX: &str = "system.";
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    peers: Vec<Url>,
+    refresh_interval: Duration,
+}
+
+impl Config {
+    pub fn new(peers: impl IntoIterator<Item = Url>) -> Self {
+        Self { peers: peers.into_iter().collect(), refresh_interval: Duration::from_secs(10) }
+    }
+
+    /// The URLs of the peers, in the order in which they are preferred.
+    pub fn peers(&self) -> &[Url] {
+        &self.peers
+    }
+
+    /// The interval at which the namespaces served by the peers are learned.
+    pub fn refresh_interval(&self) -> Duration {
+        self.refresh_interval
+    }
+
+    pub fn set_refresh_interval(self, value: Duration) -> Self {
+        Self { refresh_interval: value, ..self }
+    }
+}
+
+struct Peer {
+    url: Url,
+    upstream: Upstream,
+    namespaces: RwLock<HashSet<Box<str>>>,
+}
+
+impl Peer {
+    async fn refresh(&self) {
+        let inspect = IntentMessage {
+            intent: Some(IntentEnum::Inspect(InspectIntent { query: "**".to_owned() })),
+        };
+
+        let namespaces = match self
+            .upstream
+            .forward(&MetadataMap::new(), SYSTEM_REGISTRY_NAMESPACE, inspect)
+            .await
+        {
+            Ok(Some(FulfillmentMessage {
+                fulfillment: Some(FulfillmentEnum::Inspect(inspection)),
+            })) => inspection
+                .entries
+                .into_iter()
+                .filter(|entry| !entry.path.starts_with(SYSTEM_NAMESPACE_PREFIX))
+                .map(|entry| entry.path.into())
+                .collect(),
+            Ok(_) => {
+                tracing::warn!("Peer '{}' did not fulfill 'Inspect' of its registry.", self.url);
+                HashSet::new()
+            }
+            Err(status) => {
+                tracing::warn!("Inspecting the registry of peer '{}' failed: {status}", self.url);
+                HashSet::new()
+            }
+        };
+
+        *self.namespaces.write().unwrap() = namespaces;
+    }
+}
+
+/// Forwards intents to the federated peers serving their namespaces.
+pub struct Federation {
+    peers: Vec<Arc<Peer>>,
+    refresh_interval: Duration,
+}
+
+impl Federation {
+    pub fn new(config: Config) -> Result<Self, Error> {
+        let peers = config
+            .peers
+            .into_iter()
+            .map(|url| {
+                Ok(Arc::new(Peer {
+                    upstream: Upstream::new(upstream::Config::new(url.clone()))?,
+                    url,
+                    namespaces: RwLock::new(HashSet::new()),
+                }))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Self { peers, refresh_interval: config.refresh_interval })
+    }
+
+    /// Returns the peer to forward intents of a namespace to, if any peer
+    /// serves it.
+    pub fn owner(&self, namespace: &str) -> Option<&Upstream> {
+        self.peers
+            .iter()
+            .find(|peer| peer.namespaces.read().unwrap().contains(namespace))
+            .map(|peer| &peer.upstream)
+    }
+
+    /// Learns the namespaces served by each peer, inspecting all peers at
+    /// once such that unreachable peers do not delay the others.
+    pub async fn refresh(&self) {
+        let mut refreshes = JoinSet::new();
+        for peer in &self.peers {
+            let peer = Arc::clone(peer);
+            refreshes.spawn(async move { peer.refresh().await });
+        }
+
+        while refreshes.join_next().await.is_some() {}
+    }
+
+    /// Learns the namespaces served by the peers once per refresh interval,
+    /// forever.
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            self.refresh().await;
+            sleep(self.refresh_interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, Federation};
+    use crate::upstream::tests::serve_broker;
+
+    #[tokio::test]
+    async fn refresh_learns_namespaces_served_by_peers() {
+        // arrange
+        let (url, _) = serve_broker().await;
+        let subject = Federation::new(Config::new([url])).unwrap();
+
+        // act
+        subject.refresh().await;
+
+        // assert
+        assert!(subject.owner("sdv.vdt").is_some());
+        assert!(subject.owner("sdv.kvs").is_none());
+        assert!(subject.owner("system.info").is_none());
+    }
+
+    #[tokio::test]
+    async fn refresh_forgets_namespaces_of_unreachable_peers() {
+        // arrange
+        let subject =
+            Federation::new(Config::new(["http://localhost:1".parse().unwrap()])).unwrap(); // DevSkim: ignore DS137138
+        subject.peers[0].namespaces.write().unwrap().insert("sdv.vdt".into());
+
+        // act
+        subject.refresh().await;
+
+        // assert
+        assert!(subject.owner("sdv.vdt").is_none());
+    }
+}
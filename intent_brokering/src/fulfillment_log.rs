@@ -0,0 +1,329 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Logs the fulfilled intents, summarizing hot intents instead of logging a
+//! line per fulfillment, as high-frequency `Read` and `Subscribe` traffic
+//! would flood the logs.
+//!
+//! Fulfillments of summarized intents are counted per namespace and intent,
+//! and a summary with the number of fulfilled and failed intents and their
+//! mean and maximum duration is logged for each of them once per flush
+//! interval. In addition, the first of every `sample_rate` fulfillments of a
+//! namespace and intent within an interval is logged on its own, and a
+//! sample rate of zero disables the samples. Other intents are logged once
+//! per fulfillment. All lines are logged with the `fulfillment` target.
+//!
+//! Counting does not allocate once a namespace was seen, and namespaces
+//! without fulfillments in an interval are forgotten. The sample rate and
+//! flush interval can be changed at runtime through `system.logging`, see
+//! [`crate::system::LogSettings`].
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use intent_brokering_common::error::Error;
+use tokio::time::sleep;
+use tonic::Code;
+
+use crate::acl::parse_intent_kind;
+use crate::registry::IntentKind;
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    sample_rate: u32,
+    flush_interval: Duration,
+    summarized: Vec<IntentKind>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            sample_rate: 1000,
+            flush_interval: Duration::from_secs(10),
+            summarized: vec![IntentKind::Read, IntentKind::Subscribe],
+        }
+    }
+}
+
+impl Config {
+    /// Every how many fulfillments of a summarized intent one is logged on
+    /// its own, or zero to only log summaries.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn set_sample_rate(self, value: u32) -> Self {
+        Self { sample_rate: value, ..self }
+    }
+
+    /// The interval at which summaries are logged.
+    pub fn flush_interval(&self) -> Duration {
+        self.flush_interval
+    }
+
+    /// Sets the interval at which summaries are logged, which is at least one
+    /// second.
+    pub fn set_flush_interval_bounded(self, value: Duration) -> Self {
+        Self { flush_interval: value.max(Duration::from_secs(1)), ..self }
+    }
+
+    /// The intents which are summarized instead of logged once per
+    /// fulfillment.
+    pub fn summarized(&self) -> &[IntentKind] {
+        &self.summarized
+    }
+
+    pub fn set_summarized(self, value: impl IntoIterator<Item = IntentKind>) -> Self {
+        Self { summarized: value.into_iter().collect(), ..self }
+    }
+}
+
+/// Parses a comma-separated list of intents, e.g. `read,subscribe`.
+pub fn parse_intents(value: &str) -> Result<Vec<IntentKind>, Error> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|intent| !intent.is_empty())
+        .map(parse_intent_kind)
+        .collect()
+}
+
+/// The fulfillments of an intent of a namespace within the current interval.
+#[derive(Debug, Default, PartialEq)]
+struct Counters {
+    fulfilled: u64,
+    failed: u64,
+    total: Duration,
+    max: Duration,
+}
+
+struct Inner {
+    sample_rate: AtomicU32,
+    flush_interval_millis: AtomicU64,
+    summarized: Vec<IntentKind>,
+    counters: Mutex<HashMap<Box<str>, HashMap<IntentKind, Counters>>>,
+}
+
+/// Logs the fulfilled intents. Cloning is cheap and refers to the same
+/// counters and settings.
+#[derive(Clone)]
+pub struct FulfillmentLog(Arc<Inner>);
+
+impl FulfillmentLog {
+    pub fn new(config: Config) -> Self {
+        Self(Arc::new(Inner {
+            sample_rate: AtomicU32::new(config.sample_rate),
+            flush_interval_millis: AtomicU64::new(config.flush_interval.as_millis() as u64),
+            summarized: config.summarized,
+            counters: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.0.sample_rate.load(Ordering::Relaxed)
+    }
+
+    pub fn set_sample_rate(&self, value: u32) {
+        self.0.sample_rate.store(value, Ordering::Relaxed);
+    }
+
+    pub fn flush_interval(&self) -> Duration {
+        Duration::from_millis(self.0.flush_interval_millis.load(Ordering::Relaxed))
+    }
+
+    /// Sets the interval at which summaries are logged, which is at least one
+    /// second, from the next interval on.
+    pub fn set_flush_interval_bounded(&self, value: Duration) {
+        let millis = value.max(Duration::from_secs(1)).as_millis() as u64;
+        self.0.flush_interval_millis.store(millis, Ordering::Relaxed);
+    }
+
+    /// Records a fulfillment of an intent of a namespace with the given
+    /// status code, which took the given time.
+    pub fn record(&self, namespace: &str, intent: IntentKind, code: Code, elapsed: Duration) {
+        if !self.0.summarized.contains(&intent) {
+            log_fulfillment(namespace, intent, code, elapsed, false);
+        } else if self.count(namespace, intent, code, elapsed) {
+            log_fulfillment(namespace, intent, code, elapsed, true);
+        }
+    }
+
+    /// Counts a fulfillment of a summarized intent, returning whether it is
+    /// sampled.
+    fn count(&self, namespace: &str, intent: IntentKind, code: Code, elapsed: Duration) -> bool {
+        let mut counters = self.0.counters.lock().unwrap();
+        if !counters.contains_key(namespace) {
+            counters.insert(namespace.into(), HashMap::new());
+        }
+
+        let counters = counters.get_mut(namespace).unwrap().entry(intent).or_default();
+        counters.fulfilled += 1;
+        if code != Code::Ok {
+            counters.failed += 1;
+        }
+        counters.total += elapsed;
+        counters.max = counters.max.max(elapsed);
+
+        let sample_rate = u64::from(self.sample_rate());
+        sample_rate != 0 && (counters.fulfilled - 1) % sample_rate == 0
+    }
+
+    /// Logs the summaries of the current interval and starts the next one.
+    pub fn flush(&self) {
+        let mut counters = self.0.counters.lock().unwrap();
+        counters.retain(|namespace, by_intent| {
+            by_intent.retain(|intent, counters| {
+                if counters.fulfilled == 0 {
+                    return false;
+                }
+
+                tracing::info!(
+                    target: "fulfillment",
+                    namespace = &**namespace,
+                    intent = %intent,
+                    fulfilled = counters.fulfilled,
+                    failed = counters.failed,
+                    mean_us = (counters.total.as_micros() / u128::from(counters.fulfilled)) as u64,
+                    max_us = counters.max.as_micros() as u64,
+                    "Summary of fulfilled intents."
+                );
+
+                *counters = Counters::default();
+                true
+            });
+
+            !by_intent.is_empty()
+        });
+    }
+
+    /// Logs the summaries once per flush interval, forever.
+    pub async fn run(self) {
+        loop {
+            sleep(self.flush_interval()).await;
+            self.flush();
+        }
+    }
+}
+
+fn log_fulfillment(
+    namespace: &str,
+    intent: IntentKind,
+    code: Code,
+    elapsed: Duration,
+    sampled: bool,
+) {
+    tracing::info!(
+        target: "fulfillment",
+        namespace,
+        intent = %intent,
+        code = ?code,
+        elapsed_us = elapsed.as_micros() as u64,
+        sampled,
+        "Fulfilled intent."
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tonic::Code;
+
+    use super::{parse_intents, Config, Counters, FulfillmentLog};
+    use crate::registry::IntentKind;
+
+    const MILLIS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn count_samples_first_of_every_sample_rate_fulfillments() {
+        // arrange
+        let subject = FulfillmentLog::new(Config::default().set_sample_rate(3));
+
+        // act
+        let sampled = (0..7)
+            .map(|_| subject.count("sdv.vdt", IntentKind::Read, Code::Ok, MILLIS))
+            .collect::<Vec<_>>();
+        subject.set_sample_rate(0);
+        let disabled = subject.count("sdv.vdt", IntentKind::Read, Code::Ok, MILLIS);
+
+        // assert
+        assert_eq!(vec![true, false, false, true, false, false, true], sampled);
+        assert!(!disabled);
+    }
+
+    #[test]
+    fn count_aggregates_fulfillments_by_namespace_and_intent() {
+        // arrange
+        let subject = FulfillmentLog::new(Config::default());
+
+        // act
+        subject.count("sdv.vdt", IntentKind::Read, Code::Ok, MILLIS);
+        subject.count("sdv.vdt", IntentKind::Read, Code::Unavailable, 3 * MILLIS);
+        subject.count("sdv.vdt", IntentKind::Subscribe, Code::Ok, MILLIS);
+
+        // assert
+        let counters = subject.0.counters.lock().unwrap();
+        assert_eq!(
+            Some(&Counters { fulfilled: 2, failed: 1, total: 4 * MILLIS, max: 3 * MILLIS }),
+            counters["sdv.vdt"].get(&IntentKind::Read)
+        );
+        assert_eq!(1, counters["sdv.vdt"][&IntentKind::Subscribe].fulfilled);
+    }
+
+    #[test]
+    fn flush_resets_counters_and_forgets_idle_namespaces() {
+        // arrange
+        let subject = FulfillmentLog::new(Config::default());
+        subject.count("sdv.vdt", IntentKind::Read, Code::Ok, MILLIS);
+        subject.flush();
+        subject.count("sdv.kvs", IntentKind::Read, Code::Ok, MILLIS);
+
+        // act
+        subject.flush();
+
+        // assert
+        let counters = subject.0.counters.lock().unwrap();
+        assert!(!counters.contains_key("sdv.vdt"));
+        assert_eq!(Some(&Counters::default()), counters["sdv.kvs"].get(&IntentKind::Read));
+    }
+
+    #[test]
+    fn record_does_not_count_intents_which_are_not_summarized() {
+        // arrange
+        let subject = FulfillmentLog::new(Config::default().set_summarized([IntentKind::Read]));
+
+        // act
+        subject.record("sdv.vdt", IntentKind::Invoke, Code::Ok, MILLIS);
+        subject.record("sdv.vdt", IntentKind::Read, Code::Ok, MILLIS);
+
+        // assert
+        let counters = subject.0.counters.lock().unwrap();
+        assert_eq!(None, counters["sdv.vdt"].get(&IntentKind::Invoke));
+        assert_eq!(1, counters["sdv.vdt"][&IntentKind::Read].fulfilled);
+    }
+
+    #[test]
+    fn parse_intents_fails_for_unknown_intents() {
+        // act + assert
+        assert_eq!(
+            vec![IntentKind::Read, IntentKind::Subscribe],
+            parse_intents("read, Subscribe").unwrap()
+        );
+        assert!(parse_intents("read,listen").is_err());
+    }
+
+    #[test]
+    fn flush_interval_is_at_least_one_second() {
+        // arrange
+        let subject = FulfillmentLog::new(Config::default());
+
+        // act
+        subject.set_flush_interval_bounded(Duration::ZERO);
+
+        // assert
+        assert_eq!(Duration::from_secs(1), subject.flush_interval());
+    }
+}
@@ -0,0 +1,547 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! gRPC-Web support, such that browser-based clients can call the gRPC
+//! services of the Intent Broker without a proxy. Requests with a gRPC-Web
+//! content type are translated into gRPC requests, and the trailers of the
+//! response are sent as the last frame of the response body, as browsers
+//! cannot read HTTP trailers. All other requests are passed through.
+//!
+//! Both the binary (`application/grpc-web`) and the base64 encoded text
+//! (`application/grpc-web-text`) formats are supported, the latter since it
+//! is the only format some clients support for server-streaming calls.
+
+use std::{
+    convert::Infallible,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use tokio_stream::Stream;
+use tonic::{
+    body::{empty_body, BoxBody},
+    codegen::{
+        http::{
+            header::{
+                ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+                ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+                ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, CONTENT_LENGTH,
+                CONTENT_TYPE, ORIGIN, VARY,
+            },
+            HeaderMap, HeaderValue, Method, Request, Response, StatusCode, Version,
+        },
+        Body as HttpBody, BoxFuture, Bytes, Service,
+    },
+    server::NamedService,
+    transport::Body,
+    Status,
+};
+
+const GRPC: &str = "application/grpc";
+const GRPC_WEB: &str = "application/grpc-web";
+const GRPC_WEB_PROTO: &str = "application/grpc-web+proto";
+const GRPC_WEB_TEXT: &str = "application/grpc-web-text";
+const GRPC_WEB_TEXT_PROTO: &str = "application/grpc-web-text+proto";
+
+/// The flag marking a frame of the response body as containing trailers.
+const TRAILERS_FLAG: u8 = 0x80;
+
+/// The response headers which browsers expose to clients. The channel ID is
+/// needed to subscribe to the events of a channel opened by the client.
+const EXPOSED_HEADERS: &str = "grpc-status, grpc-message, x-chariott-channel-id";
+const PREFLIGHT_MAX_AGE_SECS: &str = "86400";
+
+/// The origins which are allowed to make cross-origin requests.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum AllowedOrigins {
+    /// No cross-origin requests are allowed.
+    #[default]
+    None,
+    Any,
+    List(Vec<HeaderValue>),
+}
+
+impl AllowedOrigins {
+    /// Parses a comma-separated list of origins, or `*` to allow any origin.
+    pub fn parse(value: &str) -> Self {
+        if value.trim() == "*" {
+            return Self::Any;
+        }
+
+        let origins: Vec<_> = value
+            .split(',')
+            .map(str::trim)
+            .filter(|origin| !origin.is_empty())
+            .filter_map(|origin| HeaderValue::from_str(origin).ok())
+            .collect();
+
+        if origins.is_empty() {
+            Self::None
+        } else {
+            Self::List(origins)
+        }
+    }
+
+    fn allows(&self, origin: &HeaderValue) -> bool {
+        match self {
+            Self::None => false,
+            Self::Any => true,
+            Self::List(origins) => origins.contains(origin),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    Binary,
+    Text,
+}
+
+impl Encoding {
+    fn from_content_type(headers: &HeaderMap) -> Option<Self> {
+        let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+        match content_type.split(';').next().map(str::trim)? {
+            GRPC_WEB | GRPC_WEB_PROTO => Some(Self::Binary),
+            GRPC_WEB_TEXT | GRPC_WEB_TEXT_PROTO => Some(Self::Text),
+            _ => None,
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Binary => GRPC_WEB_PROTO,
+            Self::Text => GRPC_WEB_TEXT_PROTO,
+        }
+    }
+
+    fn encode(self, data: Bytes) -> Bytes {
+        match self {
+            Self::Binary => data,
+            Self::Text => BASE64.encode(data).into(),
+        }
+    }
+}
+
+/// Enables gRPC-Web for a service, allowing cross-origin requests from the
+/// given origins.
+pub fn enable<S>(service: S, allowed_origins: Arc<AllowedOrigins>) -> GrpcWeb<S> {
+    GrpcWeb { inner: service, allowed_origins }
+}
+
+/// A service translating gRPC-Web requests for the inner gRPC service.
+#[derive(Clone, Debug)]
+pub struct GrpcWeb<S> {
+    inner: S,
+    allowed_origins: Arc<AllowedOrigins>,
+}
+
+impl<S: NamedService> NamedService for GrpcWeb<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S> Service<Request<Body>> for GrpcWeb<S>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>, Error = Infallible>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let origin = request.headers().get(ORIGIN).cloned();
+        if request.method() == Method::OPTIONS
+            && request.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD)
+        {
+            let response = match origin {
+                Some(origin) if self.allowed_origins.allows(&origin) => {
+                    preflight(origin, request.headers().get(ACCESS_CONTROL_REQUEST_HEADERS))
+                }
+                _ => forbidden(),
+            };
+
+            return Box::pin(async move { Ok(response) });
+        }
+
+        let Some(encoding) = Encoding::from_content_type(request.headers()) else {
+            return Box::pin(self.inner.call(request));
+        };
+
+        if origin.as_ref().is_some_and(|origin| !self.allowed_origins.allows(origin)) {
+            return Box::pin(async move { Ok(forbidden()) });
+        }
+
+        *request.version_mut() = Version::HTTP_2;
+        let headers = request.headers_mut();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(GRPC));
+        if encoding == Encoding::Text {
+            headers.remove(CONTENT_LENGTH);
+            let body = std::mem::take(request.body_mut());
+            *request.body_mut() = Body::wrap_stream(TextDecoder { body, buffer: vec![] });
+        }
+
+        let response = self.inner.call(request);
+
+        Box::pin(async move {
+            let response = response.await?;
+            let (mut parts, body) = response.into_parts();
+
+            parts.headers.insert(CONTENT_TYPE, HeaderValue::from_static(encoding.content_type()));
+            if let Some(origin) = origin {
+                parts.headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+                parts.headers.insert(
+                    ACCESS_CONTROL_EXPOSE_HEADERS,
+                    HeaderValue::from_static(EXPOSED_HEADERS),
+                );
+                parts.headers.append(VARY, HeaderValue::from_static("origin"));
+            }
+
+            let body = WebBody { inner: body, encoding, done: false };
+            Ok(Response::from_parts(parts, BoxBody::new(body)))
+        })
+    }
+}
+
+fn preflight(origin: HeaderValue, request_headers: Option<&HeaderValue>) -> Response<BoxBody> {
+    let mut response = Response::new(empty_body());
+    *response.status_mut() = StatusCode::NO_CONTENT;
+
+    let headers = response.headers_mut();
+    headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    headers.insert(ACCESS_CONTROL_ALLOW_METHODS, HeaderValue::from_static("POST"));
+    if let Some(request_headers) = request_headers {
+        headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, request_headers.clone());
+    }
+    headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from_static(PREFLIGHT_MAX_AGE_SECS));
+    headers.insert(VARY, HeaderValue::from_static("origin"));
+
+    response
+}
+
+fn forbidden() -> Response<BoxBody> {
+    let mut response = Response::new(empty_body());
+    *response.status_mut() = StatusCode::FORBIDDEN;
+    response
+}
+
+/// Encodes the trailers as a frame of the response body, in the format of
+/// HTTP/1 headers.
+fn trailers_frame(trailers: &HeaderMap) -> Bytes {
+    let mut block = vec![];
+    for (name, value) in trailers {
+        block.extend(name.as_str().as_bytes());
+        block.push(b':');
+        block.extend(value.as_bytes());
+        block.extend(b"\r\n");
+    }
+
+    let mut frame = Vec::with_capacity(block.len() + 5);
+    frame.push(TRAILERS_FLAG);
+    frame.extend((block.len() as u32).to_be_bytes());
+    frame.extend(block);
+    frame.into()
+}
+
+/// The body of a gRPC-Web response, which ends with the trailers of the
+/// gRPC response.
+struct WebBody {
+    inner: BoxBody,
+    encoding: Encoding,
+    done: bool,
+}
+
+impl HttpBody for WebBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = &mut *self;
+
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        match ready!(Pin::new(&mut this.inner).poll_data(cx)) {
+            Some(Ok(data)) => return Poll::Ready(Some(Ok(this.encoding.encode(data)))),
+            Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+            None => {}
+        }
+
+        let trailers = ready!(Pin::new(&mut this.inner).poll_trailers(cx))?;
+        this.done = true;
+
+        Poll::Ready(trailers.map(|trailers| Ok(this.encoding.encode(trailers_frame(&trailers)))))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(None))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.done
+    }
+}
+
+/// Decodes the base64 encoded body of a `grpc-web-text` request.
+struct TextDecoder {
+    body: Body,
+    /// The received characters not yet decoded, as base64 decodes groups of
+    /// four characters.
+    buffer: Vec<u8>,
+}
+
+impl Stream for TextDecoder {
+    type Item = Result<Bytes, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        loop {
+            match ready!(Pin::new(&mut this.body).poll_data(cx)) {
+                Some(Ok(chunk)) => {
+                    this.buffer.extend_from_slice(&chunk);
+                    let complete = this.buffer.len() / 4 * 4;
+                    if complete == 0 {
+                        continue;
+                    }
+
+                    let decoded = BASE64
+                        .decode(&this.buffer[..complete])
+                        .map(Bytes::from)
+                        .map_err(|_| Status::invalid_argument("Request body is not base64."));
+                    this.buffer.drain(..complete);
+                    return Poll::Ready(Some(decoded));
+                }
+                Some(Err(e)) => return Poll::Ready(Some(Err(Status::from_error(Box::new(e))))),
+                None if this.buffer.is_empty() => return Poll::Ready(None),
+                None => {
+                    this.buffer.clear();
+                    return Poll::Ready(Some(Err(Status::invalid_argument(
+                        "Request body is truncated.",
+                    ))));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::{ready as future_ready, Ready};
+
+    use super::*;
+
+    /// A gRPC message containing the bytes `[1, 2, 3]`.
+    const MESSAGE: [u8; 8] = [0, 0, 0, 0, 3, 1, 2, 3];
+
+    /// A gRPC service echoing the request body, followed by trailers with an
+    /// OK status.
+    #[derive(Clone)]
+    struct Echo;
+
+    struct EchoBody {
+        data: Option<Body>,
+    }
+
+    impl HttpBody for EchoBody {
+        type Data = Bytes;
+        type Error = Status;
+
+        fn poll_data(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+            let Some(body) = self.data.as_mut() else {
+                return Poll::Ready(None);
+            };
+
+            let data = ready!(Pin::new(body).poll_data(cx));
+            if data.is_none() {
+                self.data = None;
+            }
+
+            Poll::Ready(data.map(|data| data.map_err(|e| Status::internal(e.to_string()))))
+        }
+
+        fn poll_trailers(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+            let mut trailers = HeaderMap::new();
+            trailers.insert("grpc-status", HeaderValue::from_static("0"));
+            Poll::Ready(Ok(Some(trailers)))
+        }
+    }
+
+    impl Service<Request<Body>> for Echo {
+        type Response = Response<BoxBody>;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<Body>) -> Self::Future {
+            let content_type = request.headers().get(CONTENT_TYPE).cloned();
+            let mut response =
+                Response::new(BoxBody::new(EchoBody { data: Some(request.into_body()) }));
+            if let Some(content_type) = content_type {
+                response.headers_mut().insert(CONTENT_TYPE, content_type);
+            }
+            future_ready(Ok(response))
+        }
+    }
+
+    fn service(allowed_origins: AllowedOrigins) -> GrpcWeb<Echo> {
+        enable(Echo, Arc::new(allowed_origins))
+    }
+
+    fn request(content_type: &str, origin: Option<&str>, body: impl Into<Body>) -> Request<Body> {
+        let mut builder =
+            Request::post("/intent_brokering.runtime.v1.IntentBrokeringService/Fulfill")
+                .header(CONTENT_TYPE, content_type);
+        if let Some(origin) = origin {
+            builder = builder.header(ORIGIN, origin);
+        }
+        builder.body(body.into()).unwrap()
+    }
+
+    async fn body_bytes(response: Response<BoxBody>) -> Vec<u8> {
+        let mut body = response.into_body();
+        let mut result = vec![];
+        while let Some(data) = body.data().await {
+            result.extend_from_slice(&data.unwrap());
+        }
+        assert!(body.trailers().await.unwrap().is_none());
+        result
+    }
+
+    #[tokio::test]
+    async fn binary_request_is_translated() {
+        // arrange
+        let mut service = service(AllowedOrigins::None);
+
+        // act
+        let response = service.call(request(GRPC_WEB, None, MESSAGE.to_vec())).await.unwrap();
+
+        // assert
+        assert_eq!(GRPC_WEB_PROTO, response.headers()[CONTENT_TYPE]);
+        let mut expected = MESSAGE.to_vec();
+        expected.extend([TRAILERS_FLAG, 0, 0, 0, 15]);
+        expected.extend(b"grpc-status:0\r\n");
+        assert_eq!(expected, body_bytes(response).await);
+    }
+
+    #[tokio::test]
+    async fn text_request_is_translated() {
+        // arrange
+        let mut service = service(AllowedOrigins::None);
+        let body = BASE64.encode(MESSAGE);
+
+        // act
+        let response = service.call(request(GRPC_WEB_TEXT, None, body)).await.unwrap();
+
+        // assert
+        assert_eq!(GRPC_WEB_TEXT_PROTO, response.headers()[CONTENT_TYPE]);
+        let mut trailers = vec![TRAILERS_FLAG, 0, 0, 0, 15];
+        trailers.extend(b"grpc-status:0\r\n");
+        let expected = [BASE64.encode(MESSAGE), BASE64.encode(trailers)].concat();
+        assert_eq!(expected.into_bytes(), body_bytes(response).await);
+    }
+
+    #[tokio::test]
+    async fn grpc_request_is_passed_through() {
+        // arrange
+        let mut service = service(AllowedOrigins::None);
+
+        // act
+        let response = service.call(request(GRPC, None, MESSAGE.to_vec())).await.unwrap();
+
+        // assert
+        assert_eq!(GRPC, response.headers()[CONTENT_TYPE]);
+        let mut body = response.into_body();
+        assert_eq!(MESSAGE.to_vec(), body.data().await.unwrap().unwrap());
+        assert!(body.data().await.is_none());
+        assert!(body.trailers().await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn request_from_allowed_origin_exposes_headers() {
+        // arrange
+        let mut service = service(AllowedOrigins::parse("http://localhost:8080")); // DevSkim: ignore DS137138
+
+        // act
+        let response = service
+            .call(request(GRPC_WEB, Some("http://localhost:8080"), MESSAGE.to_vec())) // DevSkim: ignore DS137138
+            .await
+            .unwrap();
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("http://localhost:8080", response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN]); // DevSkim: ignore DS137138
+        assert_eq!(EXPOSED_HEADERS, response.headers()[ACCESS_CONTROL_EXPOSE_HEADERS]);
+    }
+
+    #[tokio::test]
+    async fn request_from_other_origin_is_forbidden() {
+        // arrange
+        let mut service = service(AllowedOrigins::parse("http://localhost:8080")); // DevSkim: ignore DS137138
+
+        // act
+        let response = service
+            .call(request(GRPC_WEB, Some("http://example.com"), MESSAGE.to_vec())) // DevSkim: ignore DS137138
+            .await
+            .unwrap();
+
+        // assert
+        assert_eq!(StatusCode::FORBIDDEN, response.status());
+    }
+
+    #[tokio::test]
+    async fn preflight_request_is_answered() {
+        // arrange
+        let mut service = service(AllowedOrigins::Any);
+        let request = Request::options("/intent_brokering.streaming.v1.ChannelService/Open")
+            .header(ORIGIN, "http://localhost:8080") // DevSkim: ignore DS137138
+            .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(ACCESS_CONTROL_REQUEST_HEADERS, "content-type,x-grpc-web")
+            .body(Body::empty())
+            .unwrap();
+
+        // act
+        let response = service.call(request).await.unwrap();
+
+        // assert
+        assert_eq!(StatusCode::NO_CONTENT, response.status());
+        assert_eq!("http://localhost:8080", response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN]); // DevSkim: ignore DS137138
+        assert_eq!("POST", response.headers()[ACCESS_CONTROL_ALLOW_METHODS]);
+        assert_eq!("content-type,x-grpc-web", response.headers()[ACCESS_CONTROL_ALLOW_HEADERS]);
+    }
+
+    #[test]
+    fn parse_allowed_origins() {
+        assert_eq!(AllowedOrigins::Any, AllowedOrigins::parse(" * "));
+        assert_eq!(AllowedOrigins::None, AllowedOrigins::parse(" , "));
+        assert_eq!(
+            AllowedOrigins::List(vec![
+                HeaderValue::from_static("http://a"), // DevSkim: ignore DS137138
+                HeaderValue::from_static("http://b")  // DevSkim: ignore DS137138
+            ]),
+            AllowedOrigins::parse("http://a, http://b") // DevSkim: ignore DS137138
+        );
+    }
+}
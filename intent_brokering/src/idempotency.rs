@@ -0,0 +1,415 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Suppresses duplicate `Write` and `Invoke` requests, e.g. commands which
+//! are redelivered by a cloud connection.
+//!
+//! Callers attach an idempotency key to a request with the
+//! `x-chariott-idempotency-key` metadata. The response of the first
+//! successful request with a key is remembered for the namespace, and returned
+//! for duplicates without fulfilling them again. Duplicates which arrive while
+//! the first request is being fulfilled wait for its response. Failed requests
+//! are not remembered, such that they can be retried.
+//!
+//! If a storage is configured, the remembered responses are persisted to it,
+//! such that duplicates are suppressed across restarts of the Intent Broker.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use intent_brokering_common::storage::Storage;
+use intent_brokering_proto::provider::FulfillResponse;
+use prost::Message as _;
+use tonic::{metadata::MetadataMap, Status};
+
+pub const IDEMPOTENCY_KEY_METADATA_KEY: &str = "x-chariott-idempotency-key";
+const STORAGE_NAMESPACE: &str = "idempotency";
+
+#[derive(Clone)]
+pub struct Config {
+    ttl: Duration,
+    capacity: usize,
+    storage: Option<Arc<dyn Storage>>,
+}
+
+impl Config {
+    /// The duration for which the response to a key is remembered.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// The maximum number of remembered keys. When exceeded, the least
+    /// recently used key is forgotten.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn set_ttl(self, value: Duration) -> Self {
+        Self { ttl: value, ..self }
+    }
+
+    pub fn set_capacity_bounded(self, value: usize) -> Self {
+        Self { capacity: std::cmp::max(value, 1), ..self }
+    }
+
+    /// Persists the remembered responses to a storage, from which they are
+    /// restored when the cache is created.
+    pub fn set_storage(self, value: Arc<dyn Storage>) -> Self {
+        Self { storage: Some(value), ..self }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { ttl: Duration::from_secs(300), capacity: 1000, storage: None }
+    }
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("ttl", &self.ttl)
+            .field("capacity", &self.capacity)
+            .field("storage", &self.storage.is_some())
+            .finish()
+    }
+}
+
+type Slot = Arc<tokio::sync::Mutex<Option<FulfillResponse>>>;
+
+struct Entry {
+    slot: Slot,
+    created: Instant,
+    last_used: Instant,
+}
+
+/// Remembers the responses to requests with an idempotency key.
+pub struct IdempotencyCache {
+    config: Config,
+    entries: Mutex<HashMap<(String, String), Entry>>,
+}
+
+impl IdempotencyCache {
+    /// Creates the cache, restoring the responses persisted to the storage
+    /// of the configuration which did not expire.
+    pub fn new(config: Config) -> Self {
+        let cache = Self { config, entries: Default::default() };
+        if let Some(storage) = &cache.config.storage {
+            cache.restore(storage.as_ref(), Instant::now(), SystemTime::now());
+        }
+        cache
+    }
+
+    /// Returns the idempotency key attached to a request, if any.
+    pub fn key(metadata: &MetadataMap) -> Option<&str> {
+        metadata
+            .get(IDEMPOTENCY_KEY_METADATA_KEY)
+            .and_then(|value| value.to_str().ok())
+            .filter(|key| !key.is_empty())
+    }
+
+    /// Awaits `fulfill`, unless a response for the key is remembered for the
+    /// namespace, in which case that response is returned instead.
+    pub async fn fulfill_once(
+        &self,
+        namespace: &str,
+        key: &str,
+        fulfill: impl Future<Output = Result<FulfillResponse, Status>>,
+    ) -> Result<FulfillResponse, Status> {
+        let slot = self.slot(namespace, key, Instant::now());
+        let mut response = slot.lock().await;
+
+        if let Some(response) = response.as_ref() {
+            tracing::debug!("Returning remembered response for idempotency key '{key}'.");
+            return Ok(response.clone());
+        }
+
+        let result = fulfill.await;
+        if let Ok(fulfill_response) = &result {
+            *response = Some(fulfill_response.clone());
+            self.persist(namespace, key, fulfill_response, SystemTime::now());
+        }
+
+        result
+    }
+
+    fn slot(&self, namespace: &str, key: &str, now: Instant) -> Slot {
+        let mut forgotten = Vec::new();
+        let slot = {
+            let mut entries = self.entries.lock().unwrap();
+            entries.retain(|id, entry| {
+                let retained = now.saturating_duration_since(entry.created) < self.config.ttl;
+                if !retained {
+                    forgotten.push(id.clone());
+                }
+                retained
+            });
+
+            let id = (namespace.to_owned(), key.to_owned());
+            if let Some(entry) = entries.get_mut(&id) {
+                entry.last_used = now;
+                Arc::clone(&entry.slot)
+            } else {
+                if entries.len() >= self.config.capacity {
+                    if let Some(least_recently_used) = entries
+                        .iter()
+                        .min_by_key(|(_, entry)| entry.last_used)
+                        .map(|(id, _)| id.clone())
+                    {
+                        entries.remove(&least_recently_used);
+                        forgotten.push(least_recently_used);
+                    }
+                }
+
+                let slot = Slot::default();
+                entries.insert(id, Entry { slot: Arc::clone(&slot), created: now, last_used: now });
+                slot
+            }
+        };
+
+        if let Some(storage) = &self.config.storage {
+            for (namespace, key) in forgotten {
+                if let Err(e) = storage.delete(STORAGE_NAMESPACE, &storage_key(&namespace, &key)) {
+                    tracing::warn!("Failed to forget persisted idempotency key '{key}': {e}");
+                }
+            }
+        }
+
+        slot
+    }
+
+    /// Persists a response along with the time at which it was remembered.
+    fn persist(&self, namespace: &str, key: &str, response: &FulfillResponse, now: SystemTime) {
+        let Some(storage) = &self.config.storage else {
+            return;
+        };
+
+        let remembered = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+        let mut value = remembered.to_be_bytes().to_vec();
+        value.extend(response.encode_to_vec());
+
+        if let Err(e) = storage.put(STORAGE_NAMESPACE, &storage_key(namespace, key), &value) {
+            tracing::warn!("Failed to persist response for idempotency key '{key}': {e}");
+        }
+    }
+
+    /// Restores the persisted responses which did not expire, up to the
+    /// capacity of the cache, and forgets the other ones.
+    fn restore(&self, storage: &dyn Storage, now: Instant, system_now: SystemTime) {
+        let persisted = match storage.scan(STORAGE_NAMESPACE, "") {
+            Ok(persisted) => persisted,
+            Err(e) => {
+                tracing::warn!("Failed to restore persisted idempotency keys: {e}");
+                return;
+            }
+        };
+
+        let mut restored = Vec::new();
+        for (storage_key, value) in persisted {
+            match decode_persisted(&storage_key, &value) {
+                Some((id, remembered, response)) => {
+                    let age = system_now.duration_since(remembered).unwrap_or_default();
+                    if age < self.config.ttl {
+                        restored.push((id, age, response));
+                        continue;
+                    }
+                }
+                None => {
+                    tracing::warn!("Forgetting invalid persisted idempotency key '{storage_key}'.")
+                }
+            }
+
+            _ = storage.delete(STORAGE_NAMESPACE, &storage_key);
+        }
+
+        // The most recently remembered responses are restored first.
+        restored.sort_by_key(|(_, age, _)| *age);
+        let mut entries = self.entries.lock().unwrap();
+        for (id, age, response) in restored {
+            if entries.len() >= self.config.capacity {
+                _ = storage.delete(STORAGE_NAMESPACE, &storage_key(&id.0, &id.1));
+                continue;
+            }
+
+            let created = now.checked_sub(age).unwrap_or(now);
+            let slot = Arc::new(tokio::sync::Mutex::new(Some(response)));
+            entries.insert(id, Entry { slot, created, last_used: created });
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+}
+
+fn storage_key(namespace: &str, key: &str) -> String {
+    format!("{namespace}/{key}")
+}
+
+/// Decodes a persisted response along with its namespace, key and the time at
+/// which it was remembered.
+fn decode_persisted(
+    storage_key: &str,
+    value: &[u8],
+) -> Option<((String, String), SystemTime, FulfillResponse)> {
+    let (namespace, key) = storage_key.split_once('/')?;
+    if value.len() < 8 {
+        return None;
+    }
+
+    let (remembered, response) = value.split_at(8);
+    let remembered =
+        UNIX_EPOCH + Duration::from_millis(u64::from_be_bytes(remembered.try_into().ok()?));
+    let response = FulfillResponse::decode(response).ok()?;
+    Some(((namespace.to_owned(), key.to_owned()), remembered, response))
+}
+
+impl Default for IdempotencyCache {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use intent_brokering_common::storage::InMemoryStorage;
+    use intent_brokering_proto::common::{FulfillmentEnum, FulfillmentMessage, WriteFulfillment};
+    use tonic::Code;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn fulfill_once_returns_remembered_response_for_duplicates() {
+        // arrange
+        let subject = IdempotencyCache::default();
+        let count = AtomicUsize::new(0);
+
+        // act
+        for _ in 0..3 {
+            let response = subject.fulfill_once("sdv.door", "key", fulfill(&count)).await;
+
+            // assert
+            assert_eq!(write_response(), response.unwrap());
+        }
+
+        assert_eq!(1, count.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn fulfill_once_fulfills_distinct_keys_and_namespaces() {
+        // arrange
+        let subject = IdempotencyCache::default();
+        let count = AtomicUsize::new(0);
+
+        // act
+        for (namespace, key) in [("sdv.door", "a"), ("sdv.door", "b"), ("sdv.seat", "a")] {
+            subject.fulfill_once(namespace, key, fulfill(&count)).await.unwrap();
+        }
+
+        // assert
+        assert_eq!(3, count.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn fulfill_once_does_not_remember_errors() {
+        // arrange
+        let subject = IdempotencyCache::default();
+        let count = AtomicUsize::new(0);
+
+        // act
+        let failed = subject
+            .fulfill_once("sdv.door", "key", async { Err(Status::unavailable("Provider down.")) })
+            .await;
+        let retried = subject.fulfill_once("sdv.door", "key", fulfill(&count)).await;
+
+        // assert
+        assert_eq!(Code::Unavailable, failed.unwrap_err().code());
+        assert_eq!(write_response(), retried.unwrap());
+        assert_eq!(1, count.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn slot_forgets_expired_and_least_recently_used_keys() {
+        // arrange
+        let subject = IdempotencyCache::new(
+            Config::default().set_ttl(Duration::from_secs(10)).set_capacity_bounded(2),
+        );
+        let now = Instant::now();
+        let a = subject.slot("sdv.door", "a", now);
+        _ = subject.slot("sdv.door", "b", now + Duration::from_secs(1));
+
+        // act
+        let used_a = subject.slot("sdv.door", "a", now + Duration::from_secs(2));
+        _ = subject.slot("sdv.door", "c", now + Duration::from_secs(3));
+        let expired_a = subject.slot("sdv.door", "a", now + Duration::from_secs(11));
+
+        // assert
+        assert!(Arc::ptr_eq(&a, &used_a));
+        assert!(!Arc::ptr_eq(&a, &expired_a));
+        assert_eq!(2, subject.len());
+    }
+
+    #[tokio::test]
+    async fn fulfill_once_returns_response_persisted_before_restart() {
+        // arrange
+        let storage: Arc<dyn Storage> = Arc::new(InMemoryStorage::new());
+        let config = Config::default().set_storage(Arc::clone(&storage));
+        let count = AtomicUsize::new(0);
+        IdempotencyCache::new(config.clone())
+            .fulfill_once("sdv.door", "key", fulfill(&count))
+            .await
+            .unwrap();
+
+        // act
+        let result =
+            IdempotencyCache::new(config).fulfill_once("sdv.door", "key", fulfill(&count)).await;
+
+        // assert
+        assert_eq!(write_response(), result.unwrap());
+        assert_eq!(1, count.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn new_forgets_expired_and_invalid_persisted_responses() {
+        // arrange
+        let storage: Arc<dyn Storage> = Arc::new(InMemoryStorage::new());
+        let config =
+            Config::default().set_ttl(Duration::from_secs(10)).set_storage(Arc::clone(&storage));
+        let now = SystemTime::now();
+        let cache = IdempotencyCache::new(config.clone());
+        cache.persist("sdv.door", "expired", &write_response(), now - Duration::from_secs(11));
+        cache.persist("sdv.door", "recent", &write_response(), now);
+        storage.put(STORAGE_NAMESPACE, "sdv.door/invalid", b"1").unwrap();
+
+        // act
+        let result = IdempotencyCache::new(config);
+
+        // assert
+        assert_eq!(1, result.len());
+        let persisted = storage.scan(STORAGE_NAMESPACE, "").unwrap();
+        assert_eq!(
+            vec!["sdv.door/recent"],
+            persisted.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>()
+        );
+    }
+
+    fn write_response() -> FulfillResponse {
+        FulfillResponse {
+            fulfillment: Some(FulfillmentMessage {
+                fulfillment: Some(FulfillmentEnum::Write(WriteFulfillment::default())),
+            }),
+        }
+    }
+
+    async fn fulfill(count: &AtomicUsize) -> Result<FulfillResponse, Status> {
+        count.fetch_add(1, Ordering::Relaxed);
+        Ok(write_response())
+    }
+}
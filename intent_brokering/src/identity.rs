@@ -0,0 +1,231 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Identifies the callers of the Intent Broker, such that all policies, e.g.
+//! the access control list, and the audit log share one notion of who is
+//! calling.
+//!
+//! Identities are extracted from the metadata of a request. Extractors are
+//! tried in order, and the first one finding credentials of its kind
+//! identifies the caller. Callers without any credentials are anonymous.
+
+use std::fmt;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use intent_brokering_common::error::Error;
+use serde::Deserialize;
+use tonic::{metadata::MetadataMap, Status};
+
+pub const AUTHORIZATION_METADATA_KEY: &str = "authorization";
+const BEARER_PREFIX: &str = "Bearer ";
+
+/// The identity of a caller.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Caller {
+    /// A caller without credentials.
+    Anonymous,
+    /// A caller presenting an opaque bearer token, which the access control
+    /// list resolves to the name of an identity.
+    Token(Box<str>),
+    /// A caller whose name was established from its credentials, e.g. the
+    /// subject of a JWT.
+    Named(Box<str>),
+}
+
+/// Displays the caller without its credentials, e.g. for audit logs.
+impl fmt::Display for Caller {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Caller::Anonymous => f.write_str("anonymous"),
+            Caller::Token(_) => f.write_str("token"),
+            Caller::Named(name) => f.write_str(name),
+        }
+    }
+}
+
+/// Extracts the identity of the caller from a request.
+pub trait IdentityExtractor: Send + Sync {
+    /// Returns the caller of a request, or `None` if the request carries no
+    /// credentials of the kind of the extractor. Fails with `UNAUTHENTICATED`
+    /// if the credentials are malformed.
+    fn extract(&self, metadata: &MetadataMap) -> Result<Option<Caller>, Status>;
+
+    /// Whether the credentials are verified, such that callers cannot claim
+    /// an identity of their choice.
+    fn verifies(&self) -> bool {
+        true
+    }
+}
+
+fn bearer_token(metadata: &MetadataMap) -> Option<&str> {
+    metadata
+        .get(AUTHORIZATION_METADATA_KEY)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix(BEARER_PREFIX))
+}
+
+/// Identifies callers by the bearer token in the `authorization` metadata.
+pub struct BearerToken;
+
+impl IdentityExtractor for BearerToken {
+    fn extract(&self, metadata: &MetadataMap) -> Result<Option<Caller>, Status> {
+        Ok(bearer_token(metadata).map(|token| Caller::Token(token.into())))
+    }
+}
+
+/// Identifies callers by the `sub` claim of a JWT bearer token in the
+/// `authorization` metadata. Bearer tokens which are not JWTs are left to
+/// other extractors.
+///
+/// This is unsafe on its own: the signature of the JWT is not verified, hence
+/// the Intent Broker must only be reachable through a proxy verifying the
+/// tokens, e.g. a sidecar, as callers could claim any identity otherwise.
+/// Access control lists with identities named by credentials are hence
+/// rejected with this extractor, unless unverified identities are trusted
+/// explicitly.
+pub struct JwtSubject;
+
+impl IdentityExtractor for JwtSubject {
+    fn extract(&self, metadata: &MetadataMap) -> Result<Option<Caller>, Status> {
+        #[derive(Deserialize)]
+        struct Claims {
+            sub: Option<String>,
+        }
+
+        let Some(token) = bearer_token(metadata) else {
+            return Ok(None);
+        };
+
+        let [_, payload, _] = token.split('.').collect::<Vec<_>>()[..] else {
+            return Ok(None);
+        };
+
+        let claims: Claims = URL_SAFE_NO_PAD
+            .decode(payload)
+            .ok()
+            .and_then(|payload| serde_json::from_slice(&payload).ok())
+            .ok_or_else(|| Status::unauthenticated("The claims of the JWT are malformed."))?;
+
+        match claims.sub {
+            Some(sub) if !sub.is_empty() => Ok(Some(Caller::Named(sub.into()))),
+            _ => Err(Status::unauthenticated("The JWT has no subject.")),
+        }
+    }
+
+    fn verifies(&self) -> bool {
+        false
+    }
+}
+
+/// The extractors tried in order to identify a caller.
+pub struct Extractors(Vec<Box<dyn IdentityExtractor>>);
+
+impl Extractors {
+    pub fn new(extractors: Vec<Box<dyn IdentityExtractor>>) -> Self {
+        Self(extractors)
+    }
+
+    /// Parses a comma-separated list of the extractors `jwt` and `bearer`,
+    /// e.g. `jwt,bearer`.
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        value
+            .split(',')
+            .map(|name| -> Result<Box<dyn IdentityExtractor>, Error> {
+                match name.trim() {
+                    "bearer" => Ok(Box::new(BearerToken)),
+                    "jwt" => Ok(Box::new(JwtSubject)),
+                    name => Err(Error::new(format!("Identity extractor '{name}' is not known."))),
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+
+    /// Whether all extractors verify the credentials of callers.
+    pub fn verify(&self) -> bool {
+        self.0.iter().all(|extractor| extractor.verifies())
+    }
+
+    /// Identifies the caller of a request with the first extractor finding
+    /// credentials, or as anonymous.
+    pub fn identify(&self, metadata: &MetadataMap) -> Result<Caller, Status> {
+        for extractor in &self.0 {
+            if let Some(caller) = extractor.extract(metadata)? {
+                return Ok(caller);
+            }
+        }
+
+        Ok(Caller::Anonymous)
+    }
+}
+
+/// Identifies callers by their bearer token only.
+impl Default for Extractors {
+    fn default() -> Self {
+        Self(vec![Box::new(BearerToken)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+    use tonic::{metadata::MetadataMap, Code};
+
+    use super::{Caller, Extractors};
+
+    fn metadata(token: &str) -> MetadataMap {
+        let mut metadata = MetadataMap::new();
+        metadata.insert("authorization", format!("Bearer {token}").parse().unwrap());
+        metadata
+    }
+
+    fn jwt(claims: &str) -> String {
+        format!("e30.{}.c2lnbmF0dXJl", URL_SAFE_NO_PAD.encode(claims))
+    }
+
+    #[test]
+    fn identify_uses_first_extractor_finding_credentials() {
+        // arrange
+        let subject = Extractors::parse("jwt,bearer").unwrap();
+
+        // act
+        let named = subject.identify(&metadata(&jwt(r#"{"sub":"hmi"}"#)));
+        let token = subject.identify(&metadata("secret"));
+        let anonymous = subject.identify(&MetadataMap::new());
+
+        // assert
+        assert_eq!(Caller::Named("hmi".into()), named.unwrap());
+        assert_eq!(Caller::Token("secret".into()), token.unwrap());
+        assert_eq!(Caller::Anonymous, anonymous.unwrap());
+    }
+
+    #[test]
+    fn identify_fails_for_jwt_without_subject() {
+        // arrange
+        let subject = Extractors::parse("jwt").unwrap();
+
+        // act
+        let result = subject.identify(&metadata(&jwt("{}")));
+
+        // assert
+        assert_eq!(Code::Unauthenticated, result.unwrap_err().code());
+    }
+
+    #[test]
+    fn parse_fails_for_unknown_extractor() {
+        assert!(Extractors::parse("bearer,telepathy").is_err());
+        assert!(Extractors::parse("unix").is_err());
+    }
+
+    #[test]
+    fn verify_is_false_with_jwt_extractor() {
+        assert!(Extractors::parse("bearer").unwrap().verify());
+        assert!(!Extractors::parse("jwt,bearer").unwrap().verify());
+    }
+
+    #[test]
+    fn display_does_not_reveal_token() {
+        assert_eq!("token", Caller::Token("secret".into()).to_string());
+    }
+}
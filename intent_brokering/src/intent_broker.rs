@@ -3,57 +3,170 @@
 // SPDX-License-Identifier: MIT
 
 use std::{
-    collections::HashMap,
-    sync::{Arc, RwLock},
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock, Weak},
+    time::{Duration, Instant},
 };
 
+use intent_brokering_common::error::Error;
+use intent_brokering_proto::common::{SourceType, ValueMessage};
+use tonic::Status;
 use url::Url;
 
 use crate::{
+    concurrency::{ConcurrencyLimit, DEFAULT_QUEUE_TIMEOUT},
     connection_provider::{ConnectionProvider, GrpcProvider, ReusableProvider},
     execution::RuntimeBinding,
-    registry::{Change, ExecutionLocality, IntentConfiguration, IntentKind, Observer},
-    streaming::StreamingEss,
+    namespace::Namespaces,
+    overrides::{Overrides, Target},
+    registry::{
+        Change, Deprecation, ExecutionLocality, IntentConfiguration, IntentKind, Observer,
+        ServiceConfiguration,
+    },
+    streaming::{proxied_source, StreamingEss, SubscriptionProxy},
+    system::{Catalog, EssStatistics, SystemPlugin, SYSTEM_NAMESPACE_PREFIX},
+    transaction::Participant,
 };
 
 type Provider = ReusableProvider<GrpcProvider>;
 
+/// How long the time at which the last provider of an intent departed is
+/// remembered once the registry is compacted.
+const DEPARTURE_RETENTION: Duration = Duration::from_secs(600);
+
 #[derive(Clone)]
 enum Binding {
     Remote(Provider),
     Fallback(Box<Binding>, Box<Binding>),
+    Limited(Url, Box<Binding>),
     SystemInspect,
     SystemDiscover(Url),
     SystemSubscribe(StreamingEss),
+    System(Arc<dyn SystemPlugin>),
+    ProxySubscribe(Arc<str>, Box<Binding>),
 }
 
-#[derive(Default)]
 struct IntentBinder {
     bindings_by_intent: HashMap<IntentConfiguration, Binding>,
+    participants_by_intent: HashMap<IntentConfiguration, Participant<Provider>>,
+    deprecations_by_intent: HashMap<IntentConfiguration, Deprecation>,
+    fallbacks_by_intent: HashMap<IntentConfiguration, HashMap<String, ValueMessage>>,
+    // When the last provider of an intent which is no longer bound departed.
+    departures_by_intent: HashMap<IntentConfiguration, Instant>,
+    services_by_intent: HashMap<IntentConfiguration, HashSet<ServiceConfiguration>>,
+    limits_by_url: HashMap<Url, ConcurrencyLimit>,
+    // The namespaces of bound intents, see `IntentBroker::intern`.
+    namespaces: Namespaces,
+    overrides: Overrides,
+    queue_timeout: Duration,
+    // The interval at which the host names of providers are resolved again,
+    // see `ReusableProvider::with_re_resolution`.
+    re_resolution: Option<Duration>,
+    // The maximum size of the responses decoded from providers, see
+    // `GrpcProvider::with_max_response_size`.
+    max_response_size: Option<usize>,
+    subscription_proxy: SubscriptionProxy,
+}
+
+impl Default for IntentBinder {
+    fn default() -> Self {
+        Self {
+            bindings_by_intent: HashMap::new(),
+            participants_by_intent: HashMap::new(),
+            deprecations_by_intent: HashMap::new(),
+            fallbacks_by_intent: HashMap::new(),
+            departures_by_intent: HashMap::new(),
+            services_by_intent: HashMap::new(),
+            limits_by_url: HashMap::new(),
+            namespaces: Namespaces::new(),
+            overrides: Overrides::default(),
+            queue_timeout: DEFAULT_QUEUE_TIMEOUT,
+            re_resolution: None,
+            max_response_size: None,
+            subscription_proxy: SubscriptionProxy::default(),
+        }
+    }
 }
 
 impl IntentBinder {
     pub fn new(streaming_url: Url, streaming_ess: StreamingEss) -> Self {
         const SYSTEM_REGISTRY_NAMESPACE: &str = "system.registry";
 
-        Self {
+        let mut namespaces = Namespaces::new();
+        let registry = IntentConfiguration::new(SYSTEM_REGISTRY_NAMESPACE, IntentKind::Inspect)
+            .intern(&mut namespaces);
+        let mut binder = Self {
             bindings_by_intent: HashMap::from([
                 (
-                    IntentConfiguration::new(SYSTEM_REGISTRY_NAMESPACE, IntentKind::Inspect),
-                    Binding::SystemInspect,
-                ),
-                (
-                    IntentConfiguration::new(SYSTEM_REGISTRY_NAMESPACE, IntentKind::Discover),
+                    registry.with_intent(IntentKind::Discover),
                     Binding::SystemDiscover(streaming_url),
                 ),
                 (
-                    IntentConfiguration::new(SYSTEM_REGISTRY_NAMESPACE, IntentKind::Subscribe),
-                    Binding::SystemSubscribe(streaming_ess),
+                    registry.with_intent(IntentKind::Subscribe),
+                    Binding::SystemSubscribe(streaming_ess.clone()),
                 ),
+                (registry.clone(), Binding::SystemInspect),
             ]),
+            participants_by_intent: HashMap::new(),
+            deprecations_by_intent: HashMap::new(),
+            fallbacks_by_intent: HashMap::new(),
+            departures_by_intent: HashMap::new(),
+            services_by_intent: HashMap::new(),
+            limits_by_url: HashMap::new(),
+            namespaces,
+            overrides: Overrides::default(),
+            queue_timeout: DEFAULT_QUEUE_TIMEOUT,
+            re_resolution: None,
+            max_response_size: None,
+            subscription_proxy: SubscriptionProxy::new(streaming_ess.clone()),
+        };
+
+        // The built-in plugins serve distinct namespaces, hence mounting
+        // them cannot fail.
+        binder.mount(Arc::new(EssStatistics::new(streaming_ess))).unwrap();
+        binder
+    }
+
+    /// Binds the intents of a plugin, unless its namespace is not a system
+    /// namespace or any of its intents is bound already.
+    fn mount(&mut self, plugin: Arc<dyn SystemPlugin>) -> Result<(), Error> {
+        let namespace = plugin.namespace();
+        if !namespace.starts_with(SYSTEM_NAMESPACE_PREFIX) {
+            return Err(Error::new(format!(
+                "Namespace '{namespace}' of plugin does not start with '{SYSTEM_NAMESPACE_PREFIX}'."
+            )));
+        }
+
+        let namespace = self.namespaces.intern(&namespace.into());
+        let intents: Vec<_> = plugin
+            .intents()
+            .into_iter()
+            .map(|intent| IntentConfiguration::new(Arc::clone(&namespace), intent))
+            .collect();
+
+        if let Some(intent) = intents.iter().find(|i| self.bindings_by_intent.contains_key(*i)) {
+            return Err(Error::new(format!(
+                "Intent '{}' of namespace '{namespace}' is already bound.",
+                intent.intent()
+            )));
         }
+
+        for intent in intents {
+            self.bindings_by_intent.insert(intent, Binding::System(Arc::clone(&plugin)));
+        }
+
+        Ok(())
     }
 
+    pub fn resolve_participant(
+        &self,
+        intent: &IntentConfiguration,
+    ) -> Option<Participant<Provider>> {
+        self.participants_by_intent.get(intent).cloned()
+    }
+
+    /// Resolves the binding of an intent, which is the target of its
+    /// override if the intent is overridden.
     pub fn resolve(&self, intent: &IntentConfiguration) -> Option<RuntimeBinding<Provider>> {
         fn binding_into_runtime_binding(
             broker: &IntentBinder,
@@ -62,22 +175,71 @@ impl IntentBinder {
             match binding {
                 Binding::SystemInspect => RuntimeBinding::SystemInspect(
                     broker.bindings_by_intent.keys().cloned().collect(),
+                    broker.deprecations_by_intent.clone(),
                 ),
                 Binding::Remote(provider) => RuntimeBinding::Remote(provider.clone()),
                 Binding::Fallback(primary, secondary) => RuntimeBinding::Fallback(
                     Box::new(binding_into_runtime_binding(broker, primary)),
                     Box::new(binding_into_runtime_binding(broker, secondary)),
                 ),
+                Binding::Limited(url, inner) => {
+                    let inner = binding_into_runtime_binding(broker, inner);
+                    match broker.limits_by_url.get(url) {
+                        Some(limit) => RuntimeBinding::Limited(limit.clone(), Box::new(inner)),
+                        None => inner,
+                    }
+                }
                 Binding::SystemDiscover(url) => RuntimeBinding::SystemDiscover(url.clone()),
                 Binding::SystemSubscribe(ess) => RuntimeBinding::SystemSubscribe(ess.clone()),
+                Binding::System(plugin) => RuntimeBinding::System(Arc::clone(plugin)),
+                Binding::ProxySubscribe(namespace, inner) => RuntimeBinding::ProxySubscribe(
+                    broker.subscription_proxy.clone(),
+                    namespace.clone(),
+                    Box::new(binding_into_runtime_binding(broker, inner)),
+                ),
             }
         }
 
+        if let Some(target) = self.overrides.get(intent, Instant::now()) {
+            tracing::debug!(
+                "Intent '{}' of namespace '{}' is overridden.",
+                intent.intent(),
+                intent.namespace()
+            );
+
+            return Some(match target {
+                Target::Provider(url) => RuntimeBinding::Remote(self.provider(url)),
+                Target::Canned(canned) => RuntimeBinding::Canned(canned.clone()),
+            });
+        }
+
         self.bindings_by_intent
             .get(intent)
             .map(|binding| binding_into_runtime_binding(self, binding))
     }
 
+    /// Resolves the binding of an intent to the provider at a URL, if it
+    /// still serves the intent, unless the intent is overridden.
+    fn resolve_at(
+        &self,
+        intent: &IntentConfiguration,
+        url: &Url,
+    ) -> Option<RuntimeBinding<Provider>> {
+        if self.overrides.get(intent, Instant::now()).is_some() {
+            return self.resolve(intent);
+        }
+
+        if !self.services_by_intent.get(intent)?.iter().any(|service| service.url() == url) {
+            return None;
+        }
+
+        let binding = RuntimeBinding::Remote(self.provider(url));
+        Some(match self.limits_by_url.get(url) {
+            Some(limit) => RuntimeBinding::Limited(limit.clone(), Box::new(binding)),
+            None => binding,
+        })
+    }
+
     fn refresh<'a>(&mut self, changes: impl IntoIterator<Item = Change<'a>>) {
         for change in changes {
             let (intent_configuration, service_configurations) = match change {
@@ -85,52 +247,285 @@ impl IntentBinder {
                 Change::Modify(intent, services) => (intent, Some(services)),
                 Change::Remove(intent) => (intent, None),
             };
-
-            let mut cloud_service = None;
-            let mut local_service = None;
-
-            if let Some(service_configurations) = service_configurations {
-                for candidate in service_configurations {
-                    match (candidate.locality(), &local_service, &cloud_service) {
-                        // Stop on the first cloud/local provider that is
-                        // found. This could be evolved in the future by
-                        // always comparing all candidates using a priority
-                        // as a tie-breaker (which does not yet exist).
-                        (_, Some(_), Some(_)) => {
-                            break;
-                        }
-                        (ExecutionLocality::Local, None, _) => {
-                            local_service = Some(candidate);
-                        }
-                        (ExecutionLocality::Cloud, _, None) => {
-                            cloud_service = Some(candidate);
-                        }
-                        (ExecutionLocality::Local, Some(_), None) => {}
-                        (ExecutionLocality::Cloud, None, Some(_)) => {}
-                    }
-                }
-            }
+            let intent_configuration = &intent_configuration.clone().intern(&mut self.namespaces);
+
+            let (local_service, cloud_service) =
+                select(service_configurations.into_iter().flatten());
+
+            // Transactions are forwarded to the same provider that is tried
+            // first when fulfilling an intent, without falling back.
+            let participant = cloud_service.or(local_service).map(|service| {
+                Participant::new(
+                    service.url().to_owned(),
+                    self.provider(service.url()),
+                    service.transactional(),
+                )
+            });
 
             let binding = match (local_service, cloud_service) {
                 (Some(local_service), Some(cloud_service)) => Some(Binding::Fallback(
-                    Box::new(Binding::Remote(Provider::new(cloud_service.url().to_owned()))),
-                    Box::new(Binding::Remote(Provider::new(local_service.url().to_owned()))),
+                    Box::new(self.remote_binding(cloud_service)),
+                    Box::new(self.remote_binding(local_service)),
                 )),
-                (Some(service), None) => {
-                    Some(Binding::Remote(Provider::new(service.url().to_owned())))
-                }
-                (None, Some(service)) => {
-                    Some(Binding::Remote(Provider::new(service.url().to_owned())))
-                }
+                (Some(service), None) | (None, Some(service)) => Some(self.remote_binding(service)),
                 (None, None) => None,
             };
 
+            // Subscriptions on channels opened with the Intent Broker are
+            // proxied to the streaming endpoint of the provider.
+            let binding = match intent_configuration.intent() {
+                IntentKind::Subscribe => binding.map(|b| {
+                    Binding::ProxySubscribe(
+                        Arc::clone(intent_configuration.shared_namespace()),
+                        Box::new(b),
+                    )
+                }),
+                _ => binding,
+            };
+
+            match service_configurations {
+                Some(services) if !services.is_empty() => {
+                    self.services_by_intent.insert(intent_configuration.clone(), services.clone());
+                }
+                _ => _ = self.services_by_intent.remove(intent_configuration),
+            }
+
             if let Some(binding) = binding {
                 self.bindings_by_intent.insert(intent_configuration.clone(), binding);
+                self.departures_by_intent.remove(intent_configuration);
+            } else {
+                if self.bindings_by_intent.remove(intent_configuration).is_some() {
+                    // Departures do not share the interned namespace, such
+                    // that it is forgotten with the binding.
+                    let departed = IntentConfiguration::new(
+                        intent_configuration.namespace(),
+                        intent_configuration.intent(),
+                    );
+                    self.departures_by_intent.insert(departed, Instant::now());
+                }
+                self.deprecations_by_intent.remove(intent_configuration);
+                self.fallbacks_by_intent.remove(intent_configuration);
+                if intent_configuration.intent() == IntentKind::Subscribe {
+                    self.remove_source_types(intent_configuration.namespace());
+                }
+            }
+
+            if let Some(participant) = participant {
+                self.participants_by_intent.insert(intent_configuration.clone(), participant);
             } else {
-                self.bindings_by_intent.remove(intent_configuration);
+                self.participants_by_intent.remove(intent_configuration);
             }
         }
+
+        self.prune_limits();
+        self.namespaces.prune();
+    }
+
+    /// Forgets the declared types of the sources of a namespace.
+    fn remove_source_types(&self, namespace: &str) -> usize {
+        let prefix = proxied_source(namespace, "");
+        self.subscription_proxy.ess().retain_source_types(|source| !source.starts_with(&prefix))
+    }
+
+    /// Drops the deprecations, fallbacks, source types and participants of
+    /// intents which are no longer bound, e.g. if a deprecation was recorded
+    /// after its intent was removed, as well as old departures, and releases
+    /// the capacity no longer used.
+    fn compact(&mut self) -> usize {
+        let entry_count = self.deprecations_by_intent.len()
+            + self.fallbacks_by_intent.len()
+            + self.departures_by_intent.len()
+            + self.participants_by_intent.len()
+            + self.limits_by_url.len();
+
+        let bindings_by_intent = &self.bindings_by_intent;
+        self.deprecations_by_intent.retain(|intent, _| bindings_by_intent.contains_key(intent));
+        self.fallbacks_by_intent.retain(|intent, _| bindings_by_intent.contains_key(intent));
+        self.participants_by_intent.retain(|intent, _| bindings_by_intent.contains_key(intent));
+        self.departures_by_intent
+            .retain(|_, departed_at| departed_at.elapsed() < DEPARTURE_RETENTION);
+        let source_types = self.subscription_proxy.ess().retain_source_types(|source| {
+            source.split_once('/').is_some_and(|(namespace, _)| {
+                bindings_by_intent
+                    .contains_key(&IntentConfiguration::new(namespace, IntentKind::Subscribe))
+            })
+        });
+        self.prune_limits();
+        self.namespaces.prune();
+
+        self.bindings_by_intent.shrink_to_fit();
+        self.deprecations_by_intent.shrink_to_fit();
+        self.fallbacks_by_intent.shrink_to_fit();
+        self.departures_by_intent.shrink_to_fit();
+        self.participants_by_intent.shrink_to_fit();
+        self.limits_by_url.shrink_to_fit();
+        self.namespaces.shrink_to_fit();
+
+        entry_count
+            - self.deprecations_by_intent.len()
+            - self.fallbacks_by_intent.len()
+            - self.departures_by_intent.len()
+            - self.participants_by_intent.len()
+            - self.limits_by_url.len()
+            + source_types
+    }
+
+    /// Creates the provider at a URL, which resolves its host name again if
+    /// re-resolution is enabled and limits the size of its responses if a
+    /// maximum size is set.
+    fn provider(&self, url: &Url) -> Provider {
+        let provider = Provider::new(url.clone());
+        let provider = match self.re_resolution {
+            Some(interval) => provider.with_re_resolution(interval),
+            None => provider,
+        };
+        match self.max_response_size {
+            Some(size) => provider.with_max_response_size(size),
+            None => provider,
+        }
+    }
+
+    /// Binds the provider of a service, limiting the requests forwarded to it
+    /// at once if the service declares a maximum concurrency. The intents of a
+    /// service share its limit.
+    fn remote_binding(&mut self, service: &ServiceConfiguration) -> Binding {
+        let binding = Binding::Remote(self.provider(service.url()));
+        let Some(max_concurrency) = service.max_concurrency() else {
+            return binding;
+        };
+
+        match self.limits_by_url.get(service.url()) {
+            Some(limit) if limit.limit() == max_concurrency => {}
+            _ => {
+                let limit = ConcurrencyLimit::new(max_concurrency, self.queue_timeout);
+                self.limits_by_url.insert(service.url().to_owned(), limit);
+            }
+        }
+
+        Binding::Limited(service.url().to_owned(), Box::new(binding))
+    }
+
+    /// Drops the limits of providers which are no longer bound.
+    fn prune_limits(&mut self) {
+        fn collect_limited<'a>(binding: &'a Binding, urls: &mut HashSet<&'a Url>) {
+            match binding {
+                Binding::Limited(url, inner) => {
+                    urls.insert(url);
+                    collect_limited(inner, urls);
+                }
+                Binding::Fallback(primary, secondary) => {
+                    collect_limited(primary, urls);
+                    collect_limited(secondary, urls);
+                }
+                Binding::ProxySubscribe(_, inner) => collect_limited(inner, urls),
+                _ => {}
+            }
+        }
+
+        let mut urls = HashSet::new();
+        for binding in self.bindings_by_intent.values() {
+            collect_limited(binding, &mut urls);
+        }
+
+        self.limits_by_url.retain(|url, _| urls.contains(url));
+    }
+}
+
+/// Selects the first local and the first cloud service of the candidates for
+/// an intent.
+fn select<'a>(
+    candidates: impl IntoIterator<Item = &'a ServiceConfiguration>,
+) -> (Option<&'a ServiceConfiguration>, Option<&'a ServiceConfiguration>) {
+    let mut cloud_service = None;
+    let mut local_service = None;
+
+    for candidate in candidates {
+        match (candidate.locality(), &local_service, &cloud_service) {
+            // Stop on the first cloud/local provider that is found. This could
+            // be evolved in the future by always comparing all candidates
+            // using a priority as a tie-breaker (which does not yet exist).
+            (_, Some(_), Some(_)) => {
+                break;
+            }
+            (ExecutionLocality::Local, None, _) => {
+                local_service = Some(candidate);
+            }
+            (ExecutionLocality::Cloud, _, None) => {
+                cloud_service = Some(candidate);
+            }
+            (ExecutionLocality::Local, Some(_), None) => {}
+            (ExecutionLocality::Cloud, None, Some(_)) => {}
+        }
+    }
+
+    (local_service, cloud_service)
+}
+
+/// The role of a service in fulfilling an intent it is registered for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CandidateRole {
+    /// Requests are forwarded to the service first.
+    Primary,
+    /// Requests are forwarded to the service if the primary service fails.
+    Fallback,
+    /// Requests are not forwarded to the service.
+    Unselected,
+}
+
+/// Explains how an intent is routed to the services registered for it, by
+/// returning the role of each candidate together with a rationale. The
+/// candidates must be given in the order in which they were bound, i.e. in
+/// the order of the registry.
+pub fn explain<'a>(
+    candidates: impl IntoIterator<Item = &'a ServiceConfiguration> + Clone,
+) -> Vec<(&'a ServiceConfiguration, CandidateRole, String)> {
+    let (local_service, cloud_service) = select(candidates.clone());
+
+    candidates
+        .into_iter()
+        .map(|candidate| {
+            let is_cloud_service = cloud_service == Some(candidate);
+            let is_local_service = local_service == Some(candidate);
+            let (role, rationale) = match (is_cloud_service, is_local_service) {
+                (true, _) if local_service.is_some() => {
+                    (CandidateRole::Primary, "First cloud service, tried before local services.")
+                }
+                (true, _) => {
+                    (CandidateRole::Primary, "First cloud service, no local service is registered.")
+                }
+                (_, true) if cloud_service.is_some() => (
+                    CandidateRole::Fallback,
+                    "First local service, tried if the cloud service fails.",
+                ),
+                (_, true) => {
+                    (CandidateRole::Primary, "First local service, no cloud service is registered.")
+                }
+                _ => (
+                    CandidateRole::Unselected,
+                    "Another service of the same locality was found first, they are not ranked.",
+                ),
+            };
+
+            let rationale = match candidate.max_concurrency() {
+                Some(limit) if role != CandidateRole::Unselected => {
+                    format!("{rationale} At most {limit} requests are forwarded at once.")
+                }
+                _ => rationale.to_owned(),
+            };
+
+            (candidate, role, rationale)
+        })
+        .collect()
+}
+
+/// A reference to an [`IntentBroker`] which does not keep it alive, e.g. for
+/// plugins mounted on the broker which resolve intents with it.
+#[derive(Clone)]
+pub(crate) struct WeakIntentBroker(Weak<RwLock<IntentBinder>>);
+
+impl WeakIntentBroker {
+    pub fn upgrade(&self) -> Option<IntentBroker> {
+        self.0.upgrade().map(IntentBroker)
     }
 }
 
@@ -141,18 +536,288 @@ pub struct IntentBroker(Arc<RwLock<IntentBinder>>);
 
 impl IntentBroker {
     pub fn new(streaming_url: Url, streaming_ess: StreamingEss) -> Self {
-        Self(Arc::new(RwLock::new(IntentBinder::new(streaming_url, streaming_ess))))
+        let broker = Self(Arc::new(RwLock::new(IntentBinder::new(streaming_url, streaming_ess))));
+        // The catalog resolves the intents it aggregates with the broker, and
+        // serves a namespace distinct from the other built-in plugins.
+        broker.mount(Arc::new(Catalog::new(broker.downgrade()))).unwrap();
+        broker
+    }
+
+    /// Returns a reference to the broker which does not keep it alive.
+    pub(crate) fn downgrade(&self) -> WeakIntentBroker {
+        WeakIntentBroker(Arc::downgrade(&self.0))
+    }
+
+    /// Enables estimating the clock skew of providers whose events are
+    /// relayed, see [`SubscriptionProxy`].
+    pub fn with_clock_skew_estimation(self, enabled: bool) -> Self {
+        self.0.write().unwrap().subscription_proxy.set_clock_skew_estimation(enabled);
+        self
+    }
+
+    /// Enables dropping relayed events whose value does not have the type
+    /// declared for their source, see [`SubscriptionProxy`].
+    pub fn with_source_type_validation(self, enabled: bool) -> Self {
+        self.0.write().unwrap().subscription_proxy.set_source_type_validation(enabled);
+        self
+    }
+
+    /// Sets how long requests wait for a provider which is at its limit of
+    /// concurrent requests before they are rejected, see
+    /// [`crate::concurrency`].
+    pub fn with_provider_queue_timeout(self, timeout: Duration) -> Self {
+        self.0.write().unwrap().queue_timeout = timeout;
+        self
+    }
+
+    /// Resolves the host names of providers again once per interval, such
+    /// that connections to providers which moved to other addresses are
+    /// established anew, see [`ReusableProvider`].
+    pub fn with_provider_re_resolution(self, interval: Duration) -> Self {
+        self.0.write().unwrap().re_resolution = Some(interval);
+        self
+    }
+
+    /// Limits the size of the responses decoded from providers, see
+    /// [`crate::response_limits`].
+    pub fn with_provider_max_response_size(self, size: usize) -> Self {
+        self.0.write().unwrap().max_response_size = Some(size);
+        self
     }
 
     pub fn resolve(&self, intent: &IntentConfiguration) -> Option<RuntimeBinding<Provider>> {
         self.0.read().unwrap().resolve(intent)
     }
+
+    /// Resolves the binding of an intent to the provider at a URL, if it
+    /// still serves the intent, see [`crate::session`].
+    pub(crate) fn resolve_at(
+        &self,
+        intent: &IntentConfiguration,
+        url: &Url,
+    ) -> Option<RuntimeBinding<Provider>> {
+        self.0.read().unwrap().resolve_at(intent, url)
+    }
+
+    /// Returns when the last provider of an intent which is no longer bound
+    /// departed, see [`crate::restart_queue`].
+    pub(crate) fn departed_at(&self, intent: &IntentConfiguration) -> Option<Instant> {
+        self.0.read().unwrap().departures_by_intent.get(intent).copied()
+    }
+
+    /// Returns the URL of the provider to which an intent is forwarded first.
+    pub(crate) fn primary_url(&self, intent: &IntentConfiguration) -> Option<Url> {
+        self.resolve_participant(intent).map(|participant| participant.url().clone())
+    }
+
+    /// Returns whether the provider at a URL serves an intent.
+    pub(crate) fn serves(&self, intent: &IntentConfiguration, url: &Url) -> bool {
+        self.0
+            .read()
+            .unwrap()
+            .services_by_intent
+            .get(intent)
+            .is_some_and(|services| services.iter().any(|service| service.url() == url))
+    }
+
+    /// Returns whether the provider at a URL serves any intent of a
+    /// namespace.
+    pub(crate) fn serves_namespace(&self, namespace: &str, url: &Url) -> bool {
+        self.0.read().unwrap().services_by_intent.iter().any(|(intent, services)| {
+            intent.namespace() == namespace && services.iter().any(|service| service.url() == url)
+        })
+    }
+
+    /// Returns the namespace shared by the bound intents of a namespace, such
+    /// that configurations of intents of requests share it instead of
+    /// allocating their own. Namespaces without bound intents are not shared.
+    pub fn intern(&self, namespace: String) -> Arc<str> {
+        let interned = self.0.read().unwrap().namespaces.get(&namespace);
+        interned.unwrap_or_else(|| namespace.into())
+    }
+
+    /// Returns whether intents can still be resolved, and the channels opened
+    /// with the Intent Broker served, i.e. no task panicked while holding the
+    /// lock of the broker or its ESS.
+    pub fn is_healthy(&self) -> bool {
+        self.0.read().is_ok_and(|binder| binder.subscription_proxy.ess().is_healthy())
+    }
+
+    /// Returns the namespaces in which an intent is served.
+    pub(crate) fn namespaces(&self, intent: IntentKind) -> Vec<String> {
+        self.0
+            .read()
+            .unwrap()
+            .bindings_by_intent
+            .keys()
+            .filter(|config| config.intent() == intent)
+            .map(|config| config.namespace().to_owned())
+            .collect()
+    }
+
+    /// Returns the registered services, along with the intents each of them
+    /// serves, ordered by name and version.
+    pub(crate) fn services(&self) -> Vec<(ServiceConfiguration, Vec<IntentConfiguration>)> {
+        let binder = self.0.read().unwrap();
+        let mut intents_by_service: HashMap<_, Vec<_>> = HashMap::new();
+        for (intent, services) in &binder.services_by_intent {
+            for service in services {
+                intents_by_service.entry(service.clone()).or_default().push(intent.clone());
+            }
+        }
+
+        let mut services: Vec<_> = intents_by_service.into_iter().collect();
+        services.sort_by(|(a, _), (b, _)| {
+            (a.id().name(), a.id().version(), a.url().as_str()).cmp(&(
+                b.id().name(),
+                b.id().version(),
+                b.url().as_str(),
+            ))
+        });
+        services
+    }
+
+    /// Returns the namespaces of the mounted plugins, ordered by namespace.
+    pub(crate) fn plugins(&self) -> Vec<String> {
+        let binder = self.0.read().unwrap();
+        let mut namespaces: Vec<_> = binder
+            .bindings_by_intent
+            .iter()
+            .filter(|(_, binding)| matches!(binding, Binding::System(_)))
+            .map(|(config, _)| config.namespace().to_owned())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        namespaces.sort();
+        namespaces
+    }
+
+    /// Returns the ESS serving the channels opened with the Intent Broker.
+    pub(crate) fn streaming_ess(&self) -> StreamingEss {
+        self.0.read().unwrap().subscription_proxy.ess().clone()
+    }
+
+    /// Mounts a plugin, which serves the intents of a `system.*` namespace.
+    /// Fails if the namespace of the plugin is not a system namespace, or if
+    /// any of its intents is served already.
+    pub fn mount(&self, plugin: Arc<dyn SystemPlugin>) -> Result<(), Error> {
+        self.0.write().unwrap().mount(plugin)
+    }
+
+    /// Resolves the provider taking part in a transaction for an intent.
+    pub(crate) fn resolve_participant(
+        &self,
+        intent: &IntentConfiguration,
+    ) -> Option<Participant<Provider>> {
+        self.0.read().unwrap().resolve_participant(intent)
+    }
+
+    /// Records which of the intents of a registration are deprecated.
+    pub fn set_deprecations(
+        &self,
+        registrations: impl IntoIterator<Item = (IntentConfiguration, Option<Deprecation>)>,
+    ) {
+        let deprecations_by_intent = &mut self.0.write().unwrap().deprecations_by_intent;
+        for (intent, deprecation) in registrations {
+            match deprecation {
+                Some(deprecation) => _ = deprecations_by_intent.insert(intent, deprecation),
+                None => _ = deprecations_by_intent.remove(&intent),
+            }
+        }
+    }
+
+    /// Returns the deprecation of an intent, if it is deprecated.
+    pub fn deprecation(&self, intent: &IntentConfiguration) -> Option<Deprecation> {
+        self.0.read().unwrap().deprecations_by_intent.get(intent).cloned()
+    }
+
+    /// Records the fallback values which a registration declares for the keys
+    /// of its `Read` intents.
+    pub fn set_fallbacks(
+        &self,
+        registrations: impl IntoIterator<Item = (IntentConfiguration, HashMap<String, ValueMessage>)>,
+    ) {
+        let fallbacks_by_intent = &mut self.0.write().unwrap().fallbacks_by_intent;
+        for (intent, fallbacks) in registrations {
+            if fallbacks.is_empty() {
+                fallbacks_by_intent.remove(&intent);
+            } else {
+                fallbacks_by_intent.insert(intent, fallbacks);
+            }
+        }
+    }
+
+    /// Records the types which a registration declares for the sources of its
+    /// `Subscribe` intents, replacing the types declared before. The types
+    /// are attached to the relayed events of the sources.
+    pub fn set_source_types(
+        &self,
+        registrations: impl IntoIterator<Item = (IntentConfiguration, HashMap<String, SourceType>)>,
+    ) {
+        let binder = self.0.read().unwrap();
+        for (intent, source_types) in registrations {
+            if intent.intent() != IntentKind::Subscribe {
+                continue;
+            }
+
+            binder.remove_source_types(intent.namespace());
+            for (source, source_type) in source_types {
+                binder
+                    .subscription_proxy
+                    .ess()
+                    .set_source_type(proxied_source(intent.namespace(), &source), source_type);
+            }
+        }
+    }
+
+    /// Returns the fallback value of a key read with an intent, if declared.
+    pub fn fallback(&self, intent: &IntentConfiguration, key: &str) -> Option<ValueMessage> {
+        self.0.read().unwrap().fallbacks_by_intent.get(intent)?.get(key).cloned()
+    }
+
+    /// Overrides an intent until the time to live elapsed, see
+    /// [`crate::overrides`].
+    pub(crate) fn set_override(
+        &self,
+        intent: IntentConfiguration,
+        target: Target,
+        ttl: Duration,
+    ) -> Result<(), Status> {
+        self.0.write().unwrap().overrides.set(intent, target, ttl, Instant::now())
+    }
+
+    /// Removes the override of an intent, returning whether there was one.
+    pub(crate) fn clear_override(&self, intent: &IntentConfiguration) -> bool {
+        self.0.write().unwrap().overrides.clear(intent, Instant::now())
+    }
+
+    /// Returns the overrides which did not expire, together with the time
+    /// for which they still apply.
+    pub(crate) fn overrides(&self) -> Vec<(IntentConfiguration, Target, Duration)> {
+        self.0.write().unwrap().overrides.list(Instant::now())
+    }
+
+    /// Returns the deprecated intents of a namespace.
+    pub fn deprecations(&self, namespace: &str) -> Vec<(IntentKind, Deprecation)> {
+        self.0
+            .read()
+            .unwrap()
+            .deprecations_by_intent
+            .iter()
+            .filter(|(intent, _)| intent.namespace() == namespace)
+            .map(|(intent, deprecation)| (intent.intent(), deprecation.clone()))
+            .collect()
+    }
 }
 
 impl Observer for IntentBroker {
     fn on_change<'a>(&self, changes: impl IntoIterator<Item = Change<'a>>) {
         self.0.write().unwrap().refresh(changes)
     }
+
+    fn compact(&self) -> usize {
+        self.0.write().unwrap().compact()
+    }
 }
 
 #[cfg(test)]
@@ -160,19 +825,26 @@ mod tests {
     use std::{
         collections::{HashMap, HashSet},
         sync::Arc,
+        time::Duration,
     };
 
     use intent_brokering_common::streaming_ess::StreamingEss;
+    use intent_brokering_proto::common::{
+        FulfillmentEnum, IntentEnum, SourceType, ValueEnum, ValueMessage,
+    };
+    use tonic::{Code, Status};
     use url::Url;
 
     use crate::{
         connection_provider::{GrpcProvider, ReusableProvider},
         execution::RuntimeBinding,
-        intent_broker::{IntentBroker, Observer as _},
+        intent_broker::{explain, CandidateRole, IntentBroker, Observer as _},
+        overrides::{Canned, Target},
         registry::{
             tests::{IntentConfigurationBuilder, ServiceConfigurationBuilder},
-            Change, ExecutionLocality, IntentConfiguration, IntentKind,
+            Change, Deprecation, ExecutionLocality, IntentConfiguration, IntentKind,
         },
+        system::SystemPlugin,
     };
 
     #[test]
@@ -220,6 +892,196 @@ mod tests {
         assert!(subject.resolve(&setup.intent).is_none());
     }
 
+    #[test]
+    fn intern_shares_namespace_of_bound_intents() {
+        // arrange
+        let setup = Setup::new();
+        let subject = setup.clone().build();
+
+        // act
+        let first = subject.intern(setup.intent.namespace().to_owned());
+        let second = subject.intern(setup.intent.namespace().to_owned());
+        let unknown = subject.intern("sdv.unknown".to_owned());
+
+        // assert
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!("sdv.unknown", &*unknown);
+        assert_eq!(None, subject.0.read().unwrap().namespaces.get("sdv.unknown"));
+    }
+
+    #[test]
+    fn compact_forgets_namespaces_of_removed_intents() {
+        // arrange
+        let subject = IntentBroker::new(Setup::STREAMING_URL.parse().unwrap(), StreamingEss::new());
+        let intent = IntentConfiguration::new("sdv.camera", IntentKind::Read);
+        let services = HashSet::from([ServiceConfigurationBuilder::new().build()]);
+        subject.on_change([Change::Add(&intent, &services)].into_iter());
+        subject.on_change([Change::Remove(&intent)].into_iter());
+        drop(intent);
+
+        // act
+        subject.compact();
+
+        // assert
+        assert_eq!(None, subject.0.read().unwrap().namespaces.get("sdv.camera"));
+    }
+
+    #[test]
+    fn resolve_at_resolves_only_providers_serving_intent() {
+        // arrange
+        const SERVING_URL: &str = "http://localhost:50001"; // DevSkim: ignore DS137138
+        let subject = IntentBroker::new(Setup::STREAMING_URL.parse().unwrap(), StreamingEss::new());
+        let intent = IntentConfiguration::new("sdv.camera", IntentKind::Read);
+        let services = HashSet::from([ServiceConfigurationBuilder::new().url(SERVING_URL).build()]);
+        subject.on_change([Change::Add(&intent, &services)].into_iter());
+
+        // act
+        let serving = subject.resolve_at(&intent, &SERVING_URL.parse().unwrap());
+        let other = subject.resolve_at(&intent, &"http://localhost:50002".parse().unwrap()); // DevSkim: ignore DS137138
+
+        // assert
+        assert_grpc_binding(&serving.unwrap(), |url| {
+            assert_eq!(&SERVING_URL.parse::<Url>().unwrap(), url)
+        });
+        assert!(other.is_none());
+    }
+
+    #[test]
+    fn departed_at_is_recorded_until_intent_is_bound_again() {
+        // arrange
+        let subject = IntentBroker::new(Setup::STREAMING_URL.parse().unwrap(), StreamingEss::new());
+        let intent = IntentConfiguration::new("sdv.camera", IntentKind::Read);
+        let services = HashSet::from([ServiceConfigurationBuilder::new().build()]);
+        subject.on_change([Change::Add(&intent, &services)].into_iter());
+
+        // act
+        let bound = subject.departed_at(&intent);
+        subject.on_change([Change::Remove(&intent)].into_iter());
+        let departed = subject.departed_at(&intent);
+        subject.on_change([Change::Add(&intent, &services)].into_iter());
+        let rebound = subject.departed_at(&intent);
+
+        // assert
+        assert_eq!(None, bound);
+        assert!(departed.is_some());
+        assert_eq!(None, rebound);
+    }
+
+    #[test]
+    fn set_deprecations_records_deprecated_intents_until_removed() {
+        // arrange
+        let setup = Setup::new();
+        let subject = setup.clone().build();
+        let deprecation = Deprecation::new(None, Some("sdv.replacement".into()));
+
+        // act
+        subject.set_deprecations([(setup.intent.clone(), Some(deprecation.clone()))]);
+        let deprecated = subject.deprecation(&setup.intent);
+        let deprecations = subject.deprecations(setup.intent.namespace());
+        subject.on_change([Change::Remove(&setup.intent)].into_iter());
+
+        // assert
+        assert_eq!(Some(deprecation.clone()), deprecated);
+        assert_eq!(vec![(setup.intent.intent(), deprecation)], deprecations);
+        assert_eq!(None, subject.deprecation(&setup.intent));
+    }
+
+    #[test]
+    fn set_fallbacks_records_fallback_values_until_removed() {
+        // arrange
+        let setup = Setup::new();
+        let subject = setup.clone().build();
+        let value = ValueMessage { value: Some(ValueEnum::Bool(false)) };
+
+        // act
+        subject.set_fallbacks([(
+            setup.intent.clone(),
+            HashMap::from([("locked".to_owned(), value.clone())]),
+        )]);
+        let fallback = subject.fallback(&setup.intent, "locked");
+        let unknown = subject.fallback(&setup.intent, "unknown");
+        subject.on_change([Change::Remove(&setup.intent)].into_iter());
+
+        // assert
+        assert_eq!(Some(value), fallback);
+        assert_eq!(None, unknown);
+        assert_eq!(None, subject.fallback(&setup.intent, "locked"));
+    }
+
+    #[test]
+    fn set_source_types_declares_types_of_relayed_sources_until_removed() {
+        // arrange
+        let subject = IntentBroker::new(Setup::STREAMING_URL.parse().unwrap(), StreamingEss::new());
+        let subscribe = IntentConfiguration::new("sdv.vdt", IntentKind::Subscribe);
+        let services = HashSet::from([ServiceConfigurationBuilder::new().build()]);
+        subject.on_change([Change::Add(&subscribe, &services)].into_iter());
+        let source_type = SourceType { value_type: "int32".to_owned(), schema: "".to_owned() };
+
+        // act
+        subject.set_source_types([(
+            subscribe.clone(),
+            HashMap::from([("Vehicle.Speed".to_owned(), source_type.clone())]),
+        )]);
+        let declared = subject.streaming_ess().source_type("sdv.vdt/Vehicle.Speed");
+        subject.on_change([Change::Remove(&subscribe)].into_iter());
+
+        // assert
+        assert_eq!(Some(source_type), declared);
+        assert_eq!(None, subject.streaming_ess().source_type("sdv.vdt/Vehicle.Speed"));
+    }
+
+    #[test]
+    fn compact_drops_deprecations_of_intents_which_are_not_bound() {
+        // arrange
+        let setup = Setup::new();
+        let intent = setup.intent.clone();
+        let unbound = IntentConfigurationBuilder::new().namespace("sdv.unbound").build();
+        let subject = setup.build();
+        let deprecation = Deprecation::new(None, None);
+        subject.set_deprecations([
+            (intent.clone(), Some(deprecation.clone())),
+            (unbound.clone(), Some(deprecation.clone())),
+        ]);
+
+        // act
+        let result = subject.compact();
+
+        // assert
+        assert_eq!(1, result);
+        assert_eq!(Some(deprecation), subject.deprecation(&intent));
+        assert_eq!(None, subject.deprecation(&unbound));
+    }
+
+    #[tokio::test]
+    async fn when_resolve_if_service_declares_max_concurrency_intents_share_limit() {
+        // arrange
+        let subject = IntentBroker::new(Setup::STREAMING_URL.parse().unwrap(), StreamingEss::new())
+            .with_provider_queue_timeout(Duration::ZERO);
+        let service = ServiceConfigurationBuilder::new().max_concurrency(1).build();
+        let read = IntentConfiguration::new("sdv.camera", IntentKind::Read);
+        let invoke = IntentConfiguration::new("sdv.camera", IntentKind::Invoke);
+        let services = HashSet::from([service]);
+        subject.on_change(
+            [Change::Add(&read, &services), Change::Add(&invoke, &services)].into_iter(),
+        );
+
+        // act
+        let (
+            Some(RuntimeBinding::Limited(read_limit, _)),
+            Some(RuntimeBinding::Limited(invoke_limit, _)),
+        ) = (subject.resolve(&read), subject.resolve(&invoke))
+        else {
+            panic!("Wrong binding")
+        };
+        let _permit = read_limit.acquire().await.unwrap();
+        let result = invoke_limit.acquire().await;
+        subject.on_change([Change::Remove(&read), Change::Remove(&invoke)].into_iter());
+
+        // assert
+        assert_eq!(Code::ResourceExhausted, result.unwrap_err().code());
+        assert!(subject.0.read().unwrap().limits_by_url.is_empty());
+    }
+
     #[test]
     fn when_resolve_if_services_are_cloud_and_local_returns_fallback() {
         // arrange
@@ -273,6 +1135,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn explain_returns_role_of_each_candidate_as_bound() {
+        // arrange
+        let intent = IntentConfigurationBuilder::new().build();
+        let services: HashSet<_> = [
+            (ExecutionLocality::Local, "local1"),
+            (ExecutionLocality::Local, "local2"),
+            (ExecutionLocality::Cloud, "cloud1"),
+        ]
+        .map(|(locality, name)| {
+            ServiceConfigurationBuilder::new()
+                .name(name)
+                .url(&format!("http://{}", name)) // DevSkim: ignore DS137138
+                .execution_locality(locality)
+                .build()
+        })
+        .into_iter()
+        .collect();
+        let subject = IntentBroker::default();
+        subject.on_change([Change::Add(&intent, &services)].into_iter());
+
+        // act
+        let result = explain(services.iter());
+
+        // assert
+        let service_of = |role| {
+            let candidates: Vec<_> = result.iter().filter(|(_, r, _)| *r == role).collect();
+            assert_eq!(1, candidates.len());
+            candidates[0].0
+        };
+        assert_eq!(3, result.len());
+        assert_remote_fallback_binding(
+            &subject.resolve(&intent).unwrap(),
+            |primary| assert_eq!(service_of(CandidateRole::Primary).url(), primary),
+            |fallback| assert_eq!(service_of(CandidateRole::Fallback).url(), fallback),
+        );
+        assert_eq!(&ExecutionLocality::Local, service_of(CandidateRole::Unselected).locality());
+    }
+
     #[test]
     fn when_resolve_with_single_locality_is_remote() {
         test([ExecutionLocality::Cloud]);
@@ -310,7 +1211,7 @@ mod tests {
         let result = subject.resolve(&intent).unwrap();
 
         // assert
-        if let RuntimeBinding::SystemInspect(context) = result {
+        if let RuntimeBinding::SystemInspect(context, _) = result {
             assert!(context.contains(&Arc::new(intent)));
             assert!(context.contains(&Arc::new(setup.intent)));
         } else {
@@ -350,6 +1251,128 @@ mod tests {
         }
     }
 
+    #[test]
+    fn resolve_succeeds_for_system_statistics() {
+        // arrange
+        let intent = IntentConfiguration::new("system.ess".to_owned(), IntentKind::Read);
+
+        // act
+        let result = Setup::new().build().resolve(&intent).unwrap();
+
+        // assert
+        if let RuntimeBinding::System(plugin) = result {
+            assert_eq!("system.ess", plugin.namespace());
+        } else {
+            panic!()
+        }
+    }
+
+    #[test]
+    fn mount_binds_intents_of_plugin() {
+        // arrange
+        let subject = Setup::new().build();
+        let intent = IntentConfiguration::new("system.test", IntentKind::Invoke);
+
+        // act
+        let result = subject.mount(Arc::new(TestPlugin("system.test")));
+
+        // assert
+        assert!(result.is_ok());
+        assert!(matches!(subject.resolve(&intent), Some(RuntimeBinding::System(_))));
+        let registry = IntentConfiguration::new("system.registry", IntentKind::Inspect);
+        let Some(RuntimeBinding::SystemInspect(intents, _)) = subject.resolve(&registry) else {
+            panic!()
+        };
+        assert!(intents.contains(&intent));
+    }
+
+    #[test]
+    fn mount_fails_outside_system_namespace_or_for_bound_intents() {
+        // arrange
+        let subject = Setup::new().build();
+
+        // act
+        let outside = subject.mount(Arc::new(TestPlugin("sdv.test")));
+        let bound = subject.mount(Arc::new(TestPlugin("system.test")));
+        let rebound = subject.mount(Arc::new(TestPlugin("system.test")));
+
+        // assert
+        assert!(outside.is_err());
+        assert!(bound.is_ok());
+        assert!(rebound.is_err());
+    }
+
+    struct TestPlugin(&'static str);
+
+    #[async_trait::async_trait]
+    impl SystemPlugin for TestPlugin {
+        fn namespace(&self) -> &str {
+            self.0
+        }
+
+        fn intents(&self) -> Vec<IntentKind> {
+            vec![IntentKind::Invoke]
+        }
+
+        async fn fulfill(&self, _: IntentEnum) -> Result<FulfillmentEnum, Status> {
+            Err(Status::unimplemented("test"))
+        }
+    }
+
+    #[test]
+    fn resolve_subscribe_intent_returns_proxy_subscribe_binding() {
+        // arrange
+        let setup = Setup::new();
+        let intent = IntentConfiguration::new(setup.intent.namespace(), IntentKind::Subscribe);
+        let subject = Setup { intent: intent.clone(), ..setup }.build();
+
+        // act
+        let result = subject.resolve(&intent).unwrap();
+
+        // assert
+        if let RuntimeBinding::ProxySubscribe(_, namespace, inner) = result {
+            assert_eq!(intent.namespace(), namespace.as_ref());
+            assert_grpc_binding(&inner, |_| {});
+        } else {
+            panic!()
+        }
+    }
+
+    #[test]
+    fn resolve_participant_returns_whether_service_is_transactional() {
+        test(true);
+        test(false);
+
+        fn test(transactional: bool) {
+            // arrange
+            let setup = Setup::new();
+            let subject = Setup {
+                service: setup.service.clone().transactional(transactional),
+                ..setup.clone()
+            }
+            .build();
+
+            // act
+            let result = subject.resolve_participant(&setup.intent).unwrap();
+
+            // assert
+            assert_eq!(transactional, result.transactional());
+        }
+    }
+
+    #[test]
+    fn when_removing_does_no_longer_resolve_participant() {
+        // arrange
+        let setup = Setup::new();
+        let subject = setup.clone().build();
+
+        // act
+        subject.on_change([Change::Remove(&setup.intent)].into_iter());
+
+        // assert
+        assert!(subject.resolve_participant(&setup.intent).is_none());
+    }
+
     #[test]
     fn when_refreshing_does_not_depend_on_previous_state() {
         // arrange
@@ -366,11 +1389,45 @@ mod tests {
         assert_grpc_binding(&result, |url| assert_eq!(&SERVICE_URL.parse::<Url>().unwrap(), url));
     }
 
+    #[tokio::test]
+    async fn resolve_returns_target_of_override_until_cleared() {
+        // arrange
+        const FIXTURE_URL: &str = "http://fixture"; // DevSkim: ignore DS137138
+        let setup = Setup::new();
+        let intent = setup.intent.clone();
+        let unbound = IntentConfigurationBuilder::new().namespace("sdv.unbound").build();
+        let subject = setup.build();
+        let error = Canned::Error(Code::Unavailable, "simulated".into());
+        let fixture = Target::Provider(FIXTURE_URL.parse().unwrap());
+
+        // act
+        subject
+            .set_override(intent.clone(), Target::Canned(error), Duration::from_secs(60))
+            .unwrap();
+        subject.set_override(unbound.clone(), fixture, Duration::from_secs(60)).unwrap();
+        let overridden = subject.resolve(&intent).unwrap();
+        let fixture = subject.resolve(&unbound).unwrap();
+        let cleared = subject.clear_override(&intent);
+
+        // assert
+        let result = overridden.execute(Default::default()).await;
+        assert_eq!(Code::Unavailable, result.unwrap_err().code());
+        assert_grpc_binding(&fixture, |url| assert_eq!(&FIXTURE_URL.parse::<Url>().unwrap(), url));
+        assert!(cleared);
+        assert_eq!(1, subject.overrides().len());
+        assert_grpc_binding(&subject.resolve(&intent).unwrap(), |url| {
+            assert_ne!(&FIXTURE_URL.parse::<Url>().unwrap(), url)
+        });
+    }
+
     fn assert_grpc_binding(
         actual: &RuntimeBinding<ReusableProvider<GrpcProvider>>,
         assert: impl FnOnce(&Url),
     ) {
-        if let RuntimeBinding::Remote(ReusableProvider { inner: GrpcProvider(url), .. }) = actual {
+        if let RuntimeBinding::Remote(ReusableProvider {
+            inner: GrpcProvider { url, .. }, ..
+        }) = actual
+        {
             assert(url);
         } else {
             panic!()
@@ -386,10 +1443,12 @@ mod tests {
             match (primary.as_ref(), secondary.as_ref()) {
                 (
                     RuntimeBinding::Remote(ReusableProvider {
-                        inner: GrpcProvider(primary), ..
+                        inner: GrpcProvider { url: primary, .. },
+                        ..
                     }),
                     RuntimeBinding::Remote(ReusableProvider {
-                        inner: GrpcProvider(secondary), ..
+                        inner: GrpcProvider { url: secondary, .. },
+                        ..
                     }),
                 ) => {
                     assert_primary(primary);
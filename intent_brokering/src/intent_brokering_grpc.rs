@@ -2,25 +2,70 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
+use std::collections::HashSet;
 use std::sync::{Arc, RwLock};
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use intent_brokering_proto::{
-    common::intent::Intent,
+    common::{
+        freshness::Quality, intent::Intent, DiscoverFulfillment, Freshness, FulfillmentEnum,
+        FulfillmentMessage, IntentMessage, ReadFulfillment, SubscribeFulfillment, ValueMessage,
+    },
+    provider::FulfillResponse as ProviderFulfillResponse,
     runtime::{
-        intent_brokering_service_server::IntentBrokeringService, AnnounceRequest, AnnounceResponse,
-        FulfillRequest, FulfillResponse, IntentRegistration, IntentServiceRegistration,
-        RegisterRequest, RegisterResponse, RegistrationState,
+        intent_brokering_service_server::IntentBrokeringService,
+        intent_override::Target as OverrideTarget,
+        registration_service_client::RegistrationServiceClient, AnnounceRequest, AnnounceResponse,
+        CancelOperationRequest, CancelOperationResponse, Candidate,
+        CandidateRole as CandidateRoleMessage, ClearOverrideRequest, ClearOverrideResponse,
+        CompactRegistryRequest, CompactRegistryResponse, Deprecation as DeprecationMessage,
+        ExportRegistryRequest, ExportRegistryResponse, FulfillRequest, FulfillResponse,
+        FulfillTransactionRequest, FulfillTransactionResponse, GetRegistrationRequest,
+        ImportRegistryRequest, ImportRegistryResponse, IntentOverride, IntentRegistration,
+        IntentServiceRegistration, ListOverridesRequest, ListOverridesResponse, OperationState,
+        OverrideError, RegisterRequest, RegisterResponse, RegistrationState, ReportProgressRequest,
+        ReportProgressResponse, ResolveIntentRequest, ResolveIntentResponse, SetOverrideRequest,
+        SetOverrideResponse, WaitForServiceRequest, WaitForServiceResponse,
     },
 };
-use tonic::{async_trait, Request, Response, Status};
+use tonic::{
+    async_trait, metadata::MetadataMap, transport::Endpoint, Code, Request, Response, Status,
+};
+use tracing::Instrument as _;
 use url::Url;
 
-use crate::intent_broker::IntentBroker;
+use crate::accounting::Accounting;
+use crate::acl::Acl;
+use crate::activation::Activation;
+use crate::admission::{self, Admission};
+use crate::bridge::Bridges;
+use crate::chaos::Chaos;
+use crate::connection_provider::{GrpcProvider, ReusableProvider};
+use crate::correlation;
+use crate::execution::{deprecations_value, DEPRECATIONS_KEY};
+use crate::federation::Federation;
+use crate::fulfillment_log::FulfillmentLog;
+use crate::idempotency::{self, IdempotencyCache};
+use crate::identity::{Caller, Extractors};
+use crate::intent_broker::{self, CandidateRole, IntentBroker};
+use crate::latency::{self, Timings};
+use crate::operation::{self, Operations};
+use crate::overrides::{Canned, Target, OVERRIDES_NAMESPACE};
+use crate::provider_url::{self, InvalidUrl};
+use crate::registration_signature::ProtectedNamespaces;
 use crate::registry::{
-    ExecutionLocality, IntentConfiguration, IntentKind, Observer, Registry, ServiceConfiguration,
-    ServiceId,
+    Compaction, Deprecation, ExecutionLocality, IntentConfiguration, IntentKind, Observer,
+    Registry, ServiceConfiguration, ServiceId, Snapshot,
 };
+use crate::response_limits::{self, ResponseLimits, CONTINUATION_METADATA_KEY};
+use crate::restart_queue::{self, RestartQueue};
+use crate::session::{self, Sessions};
+use crate::streaming::{proxied_source, VALUE_TYPES};
+use crate::transaction::Transaction;
+use crate::transform::Transforms;
+use crate::upstream::Upstream;
+use crate::validation;
+use crate::write_events::WriteEvents;
 
 // Enums are mapped to i32 in proto, we map
 // the values here to the actual values in the proto.
@@ -33,15 +78,359 @@ const INTENT_MAPPING_READ: i32 = 2;
 const INTENT_MAPPING_WRITE: i32 = 3;
 const INTENT_MAPPING_INVOKE: i32 = 4;
 const INTENT_MAPPING_SUBSCRIBE: i32 = 5;
+const INTENT_MAPPING_DELETE: i32 = 6;
+
+const FETCH_REGISTRATION_TIMEOUT: Duration = Duration::from_secs(5);
+/// The namespace whose `read` and `write` intents authorize exporting and
+/// importing the registry.
+const ADMIN_NAMESPACE: &str = "system.admin";
 
 pub struct IntentBrokeringServer<T: Observer> {
     broker: IntentBroker,
     registry: Arc<RwLock<Registry<T>>>,
+    acl: Option<Acl>,
+    identity: Extractors,
+    idempotency: IdempotencyCache,
+    admission: Option<Admission>,
+    transforms: Option<Transforms>,
+    operations: Operations<ReusableProvider<GrpcProvider>>,
+    read_fallbacks: bool,
+    latency_budgets: Option<latency::Budgets>,
+    write_events: Option<WriteEvents>,
+    accounting: Option<Accounting>,
+    resolve_provider_urls: bool,
+    overrides: bool,
+    activation: Option<Activation>,
+    upstream: Option<Upstream>,
+    federation: Option<Arc<Federation>>,
+    strict_fulfillments: bool,
+    chaos: Option<Chaos>,
+    sessions: Option<Sessions>,
+    restart_queue: Option<RestartQueue>,
+    bridges: Option<Arc<Bridges>>,
+    response_limits: Option<ResponseLimits>,
+    fulfillment_log: Option<FulfillmentLog>,
+    protected_namespaces: Option<ProtectedNamespaces>,
 }
 
 impl<T: Observer> IntentBrokeringServer<T> {
     pub fn new(registry: Registry<T>, broker: IntentBroker) -> Self {
-        Self { registry: Arc::new(RwLock::new(registry)), broker }
+        Self {
+            registry: Arc::new(RwLock::new(registry)),
+            operations: Operations::new(broker.streaming_ess()),
+            broker,
+            acl: None,
+            identity: Extractors::default(),
+            idempotency: Default::default(),
+            admission: None,
+            transforms: None,
+            read_fallbacks: false,
+            latency_budgets: None,
+            write_events: None,
+            accounting: None,
+            resolve_provider_urls: false,
+            overrides: false,
+            activation: None,
+            upstream: None,
+            federation: None,
+            strict_fulfillments: false,
+            chaos: None,
+            sessions: None,
+            restart_queue: None,
+            bridges: None,
+            response_limits: None,
+            fulfillment_log: None,
+            protected_namespaces: None,
+        }
+    }
+
+    /// Restricts the intents callers may fulfill to those allowed by the
+    /// access control list.
+    pub fn with_acl(self, acl: Acl) -> Self {
+        Self { acl: Some(acl), ..self }
+    }
+
+    /// Identifies callers with the given extractors instead of by their
+    /// bearer token only.
+    pub fn with_identity(self, identity: Extractors) -> Self {
+        Self { identity, ..self }
+    }
+
+    /// Transforms the intents of namespaces with transformations for their
+    /// providers, and their fulfillments back for the consumers.
+    pub fn with_transforms(self, transforms: Transforms) -> Self {
+        Self { transforms: Some(transforms), ..self }
+    }
+
+    /// Sets for how long and for how many idempotency keys the responses to
+    /// `Write` and `Invoke` requests are remembered.
+    pub fn with_idempotency(self, config: idempotency::Config) -> Self {
+        Self { idempotency: IdempotencyCache::new(config), ..self }
+    }
+
+    /// Returns the fallback values declared in the registrations of providers
+    /// for `Read` intents, instead of an error, if the providers are
+    /// unreachable.
+    pub fn with_read_fallbacks(self) -> Self {
+        Self { read_fallbacks: true, ..self }
+    }
+
+    /// Limits the rate at which providers are registered.
+    pub fn with_admission(self, config: admission::Config) -> Self {
+        Self { admission: Some(Admission::new(config)), ..self }
+    }
+
+    /// Annotates the responses to fulfill requests with the timestamps of
+    /// their hops through the broker, and warns about those exceeding the
+    /// latency budget of their namespace.
+    pub fn with_latency_budgets(self, budgets: latency::Budgets) -> Self {
+        Self { latency_budgets: Some(budgets), ..self }
+    }
+
+    /// Publishes the fulfilled writes of the given namespaces as change
+    /// events, see [`crate::write_events`].
+    pub fn with_write_events(self, namespaces: impl IntoIterator<Item = Box<str>>) -> Self {
+        let write_events = WriteEvents::new(self.broker.streaming_ess(), namespaces);
+        Self { write_events: Some(write_events), ..self }
+    }
+
+    /// Accounts the resources used by each caller, rejecting the requests and
+    /// subscriptions exceeding its ceilings.
+    pub fn with_accounting(self, accounting: Accounting) -> Self {
+        Self { accounting: Some(accounting), ..self }
+    }
+
+    /// Resolves the host names of the URLs of registering providers, and
+    /// rejects the registration if a host cannot be resolved or resolves to
+    /// a blocked address, see [`crate::provider_url`].
+    pub fn with_provider_url_resolution(self) -> Self {
+        Self { resolve_provider_urls: true, ..self }
+    }
+
+    /// Allows overriding how intents are fulfilled, see
+    /// [`crate::overrides`].
+    pub fn with_overrides(self) -> Self {
+        Self { overrides: true, ..self }
+    }
+
+    /// Activates the providers of namespaces which are not served when an
+    /// intent arrives for them, see [`crate::activation`].
+    pub fn with_activation(self, activation: Activation) -> Self {
+        Self { activation: Some(activation), ..self }
+    }
+
+    /// Forwards intents for namespaces which are not served locally to an
+    /// upstream Intent Broker, see [`crate::upstream`].
+    pub fn with_upstream(self, upstream: Upstream) -> Self {
+        Self { upstream: Some(upstream), ..self }
+    }
+
+    /// Forwards intents for namespaces which are not served locally to the
+    /// federated peers serving them, see [`crate::federation`]. Peers are
+    /// preferred over the upstream Intent Broker.
+    pub fn with_federation(self, federation: Arc<Federation>) -> Self {
+        Self { federation: Some(federation), ..self }
+    }
+
+    /// Rejects malformed fulfillments of providers with diagnostics, instead
+    /// of passing them to the consumer, see [`crate::validation`].
+    pub fn with_strict_fulfillments(self) -> Self {
+        Self { strict_fulfillments: true, ..self }
+    }
+
+    /// Injects faults into the fulfillment of intents, see [`crate::chaos`].
+    pub fn with_chaos(self, chaos: Chaos) -> Self {
+        Self { chaos: Some(chaos), ..self }
+    }
+
+    /// Keeps the intents of sessions on the same provider, see
+    /// [`crate::session`].
+    pub fn with_sessions(self, config: session::Config) -> Self {
+        Self { sessions: Some(Sessions::new(config)), ..self }
+    }
+
+    /// Holds intents for namespaces whose provider is restarting, see
+    /// [`crate::restart_queue`].
+    pub fn with_restart_queue(self, config: restart_queue::Config) -> Self {
+        Self { restart_queue: Some(RestartQueue::new(config)), ..self }
+    }
+
+    /// Serves subscriptions to bridged sources from the events re-published
+    /// by the bridges, see [`crate::bridge`].
+    pub fn with_bridges(self, bridges: Arc<Bridges>) -> Self {
+        Self { bridges: Some(bridges), ..self }
+    }
+
+    /// Rejects or truncates fulfillments of providers exceeding the maximum
+    /// size of their intent, see [`crate::response_limits`].
+    pub fn with_response_limits(self, config: response_limits::Config) -> Self {
+        Self { response_limits: Some(ResponseLimits::new(config)), ..self }
+    }
+
+    /// Logs the fulfilled intents, summarizing hot intents, see
+    /// [`crate::fulfillment_log`].
+    pub fn with_fulfillment_log(self, log: FulfillmentLog) -> Self {
+        Self { fulfillment_log: Some(log), ..self }
+    }
+
+    /// Rejects registrations of protected namespaces which are not signed
+    /// with their secret, see [`crate::registration_signature`].
+    pub fn with_protected_namespaces(self, namespaces: ProtectedNamespaces) -> Self {
+        Self { protected_namespaces: Some(namespaces), ..self }
+    }
+
+    /// Identifies and authorizes the caller of a method managing overrides
+    /// with the given intent of `system.overrides`.
+    fn authorize_overrides<U>(
+        &self,
+        request: &Request<U>,
+        intent: IntentKind,
+    ) -> Result<Caller, Status> {
+        if !self.overrides {
+            return Err(Status::failed_precondition("Overrides are not enabled."));
+        }
+
+        let caller = self.identity.identify(request.metadata())?;
+        self.authorize(&caller, &IntentConfiguration::new(OVERRIDES_NAMESPACE, intent))?;
+        Ok(caller)
+    }
+
+    /// Identifies and authorizes the caller of a method managing the registry
+    /// with the given intent of `system.admin`.
+    fn authorize_admin<U>(&self, request: &Request<U>, intent: IntentKind) -> Result<(), Status> {
+        let caller = self.identity.identify(request.metadata())?;
+        self.authorize(&caller, &IntentConfiguration::new(ADMIN_NAMESPACE, intent))
+    }
+
+    /// Validates the URL of a registering provider before it is contacted or
+    /// registered.
+    async fn validate_url(&self, url: &Url) -> Result<(), Status> {
+        let validation = match self.resolve_provider_urls {
+            true => provider_url::validate_resolved(url).await,
+            false => provider_url::validate(url),
+        };
+
+        validation.map_err(|e| Status::invalid_argument(format!("Service URL is not allowed: {e}")))
+    }
+
+    /// Verifies the signature of a registration, if namespaces are protected.
+    fn verify_signature(
+        &self,
+        registration: &RegisterRequest,
+        metadata: &MetadataMap,
+    ) -> Result<(), Status> {
+        match &self.protected_namespaces {
+            Some(protected_namespaces) => protected_namespaces.verify(registration, metadata),
+            None => Ok(()),
+        }
+    }
+
+    fn admit(&self, intents: &[IntentConfiguration]) -> Result<(), Status> {
+        match &self.admission {
+            Some(admission) => admission
+                .admit(intents.iter().map(|intent| intent.namespace()), Instant::now())
+                .map_err(|delay| {
+                    tracing::debug!("Rejected registration, retry after {delay:?}.");
+                    admission::retry_later(delay)
+                }),
+            None => Ok(()),
+        }
+    }
+
+    fn authorize(&self, caller: &Caller, intent: &IntentConfiguration) -> Result<(), Status> {
+        match &self.acl {
+            Some(acl) => acl.authorize(caller, intent),
+            None => Ok(()),
+        }
+    }
+
+    /// Waits for a provider of an intent which is not served, if its provider
+    /// is restarting or can be activated.
+    async fn await_provider(
+        &self,
+        intent: &IntentConfiguration,
+        metadata: &MetadataMap,
+    ) -> Result<(), Status> {
+        match self.hold_until_restarted(intent, metadata).await {
+            Err(status) if status.code() == Code::NotFound => self.activate(intent).await,
+            result => result,
+        }
+    }
+
+    /// Holds an intent until its provider registered again, if the provider
+    /// departed within the restart window of the namespace.
+    async fn hold_until_restarted(
+        &self,
+        intent: &IntentConfiguration,
+        metadata: &MetadataMap,
+    ) -> Result<(), Status> {
+        let deadline = match (&self.restart_queue, self.broker.departed_at(intent)) {
+            (Some(queue), Some(departed_at)) => queue
+                .deadline(intent.namespace(), departed_at.into(), metadata)
+                .map(|deadline| (queue, deadline)),
+            _ => None,
+        };
+
+        let Some((queue, deadline)) = deadline else {
+            return Err(Status::not_found("No provider found."));
+        };
+
+        let intents = [intent.intent()];
+        let served = self.wait_until_served(intent.namespace(), &intents, Some(deadline));
+        queue.hold(intent.namespace(), deadline, served).await
+    }
+
+    /// Activates the provider of the namespace of an intent, if it has an
+    /// activator, and waits until the intent is served.
+    async fn activate(&self, intent: &IntentConfiguration) -> Result<(), Status> {
+        let deadline = match &self.activation {
+            Some(activation) => activation.activate(intent.namespace())?,
+            None => None,
+        };
+
+        match deadline {
+            Some(deadline) => {
+                self.wait_until_served(intent.namespace(), &[intent.intent()], Some(deadline)).await
+            }
+            None => Err(Status::not_found("No provider found.")),
+        }
+    }
+
+    /// Waits until services are registered for all of the given intents of a
+    /// namespace, or for any intent if none are given.
+    async fn wait_until_served(
+        &self,
+        namespace: &str,
+        intents: &[IntentKind],
+        deadline: Option<tokio::time::Instant>,
+    ) -> Result<(), Status> {
+        // Subscribing before checking the registry ensures that no change is
+        // missed in between.
+        let mut changes = self.registry.read().unwrap().subscribe_changes();
+
+        loop {
+            if self.registry.read().unwrap().serves(namespace, intents) {
+                return Ok(());
+            }
+
+            let timeout = async {
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                result = changes.changed() => {
+                    result.map_err(|_| Status::unavailable("The registry is no longer available."))?;
+                }
+                _ = timeout => {
+                    return Err(Status::deadline_exceeded(format!(
+                        "Namespace '{namespace}' is not served."
+                    )));
+                }
+            }
+        }
     }
 
     pub fn registry_do<U>(&self, f: impl FnOnce(&mut Registry<T>) -> U) -> U {
@@ -49,6 +438,95 @@ impl<T: Observer> IntentBrokeringServer<T> {
         f(&mut registry)
     }
 
+    /// Returns whether the registry and the broker are healthy. Blocks while
+    /// their locks are held, hence a task holding a lock indefinitely keeps
+    /// this from returning.
+    pub fn is_healthy(&self) -> bool {
+        self.registry.read().is_ok() && self.broker.is_healthy()
+    }
+
+    /// Compacts the registry, see [`Registry::compact`].
+    pub fn compact(&self) -> Compaction {
+        let compaction = self.registry_do(|registry| registry.compact());
+        if compaction.total() > 0 {
+            tracing::info!(
+                empty_intents = compaction.empty_intents,
+                orphaned_registrations = compaction.orphaned_registrations,
+                stale_entries = compaction.stale_entries,
+                "Compacted registry, reclaiming {} entries.",
+                compaction.total()
+            );
+        }
+
+        compaction
+    }
+
+    /// Registers a service for the intents of its registration, including
+    /// which of them are deprecated, the fallback values of keys read and the
+    /// types of sources subscribed to, if the registration is admitted.
+    fn register_service(
+        &self,
+        service: ServiceConfiguration,
+        registrations: Vec<IntentRegistration>,
+    ) -> Result<(), Status> {
+        let registrations = registrations
+            .into_iter()
+            .map(|mut registration| {
+                let deprecation =
+                    registration.deprecation.clone().map(resolve_deprecation).transpose()?;
+                let fallbacks = std::mem::take(&mut registration.fallbacks);
+                let source_types = std::mem::take(&mut registration.source_types);
+                let intent = IntentBrokeringServer::<T>::create_configruation_from_registration(
+                    registration,
+                )?;
+                if !fallbacks.is_empty() && !matches!(intent.intent(), IntentKind::Read) {
+                    return Err(Status::invalid_argument(format!(
+                        "Fallbacks of namespace '{}' can only be declared for 'Read' intents.",
+                        intent.namespace()
+                    )));
+                }
+                if !source_types.is_empty() && !matches!(intent.intent(), IntentKind::Subscribe) {
+                    return Err(Status::invalid_argument(format!(
+                        "Source types of '{}' can only be declared for 'Subscribe' intents.",
+                        intent.namespace()
+                    )));
+                }
+                if let Some((source, source_type)) = source_types.iter().find(|(_, source_type)| {
+                    !VALUE_TYPES.contains(&source_type.value_type.as_str())
+                }) {
+                    return Err(Status::invalid_argument(format!(
+                        "Type '{}' of source '{source}' is not known.",
+                        source_type.value_type
+                    )));
+                }
+                Ok((intent, deprecation, fallbacks, source_types))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let intents: Vec<_> = registrations.iter().map(|(intent, ..)| intent.clone()).collect();
+        self.admit(&intents)?;
+        self.registry.write().unwrap().upsert(service, intents, Instant::now()).map_err(|e| {
+            match std::error::Error::source(&e) {
+                Some(source) if source.is::<InvalidUrl>() => Status::invalid_argument(e.message()),
+                _ => Status::unknown(e.message()),
+            }
+        })?;
+
+        let mut deprecations = Vec::with_capacity(registrations.len());
+        let mut fallbacks = Vec::with_capacity(registrations.len());
+        let mut source_types = Vec::with_capacity(registrations.len());
+        for (intent, deprecation, intent_fallbacks, intent_source_types) in registrations {
+            deprecations.push((intent.clone(), deprecation));
+            fallbacks.push((intent.clone(), intent_fallbacks));
+            source_types.push((intent, intent_source_types));
+        }
+        self.broker.set_deprecations(deprecations);
+        self.broker.set_fallbacks(fallbacks);
+        self.broker.set_source_types(source_types);
+
+        Ok(())
+    }
+
     fn create_configruation_from_registration(
         intent: IntentRegistration,
     ) -> Result<IntentConfiguration, Status> {
@@ -64,19 +542,422 @@ impl<T: Observer> IntentBrokeringServer<T> {
             INTENT_MAPPING_WRITE => Ok(IntentKind::Write),
             INTENT_MAPPING_INVOKE => Ok(IntentKind::Invoke),
             INTENT_MAPPING_SUBSCRIBE => Ok(IntentKind::Subscribe),
+            INTENT_MAPPING_DELETE => Ok(IntentKind::Delete),
             _ => Err(Status::invalid_argument("No such intent known.")),
         }
     }
 
+    fn map_intent_kind(intent: IntentKind) -> i32 {
+        match intent {
+            IntentKind::Discover => INTENT_MAPPING_DISCOVER,
+            IntentKind::Inspect => INTENT_MAPPING_INSPECT,
+            IntentKind::Read => INTENT_MAPPING_READ,
+            IntentKind::Write => INTENT_MAPPING_WRITE,
+            IntentKind::Invoke => INTENT_MAPPING_INVOKE,
+            IntentKind::Subscribe => INTENT_MAPPING_SUBSCRIBE,
+            IntentKind::Delete => INTENT_MAPPING_DELETE,
+        }
+    }
+
     fn map_intent_variant(intent: &Intent) -> IntentKind {
         match intent {
             Intent::Discover(_) => IntentKind::Discover,
             Intent::Inspect(_) => IntentKind::Inspect,
             Intent::Read(_) => IntentKind::Read,
-            Intent::Write(_) => IntentKind::Write,
+            Intent::Write(_) | Intent::WriteBatch(_) => IntentKind::Write,
             Intent::Invoke(_) => IntentKind::Invoke,
             Intent::Subscribe(_) => IntentKind::Subscribe,
+            Intent::Delete(_) => IntentKind::Delete,
+        }
+    }
+
+    /// Fulfills a request within the scope of its correlation ID.
+    async fn fulfill_correlated(
+        &self,
+        request: Request<FulfillRequest>,
+        timings: &Timings,
+    ) -> Result<Response<FulfillResponse>, Status> {
+        let (metadata, _, request) = request.into_parts();
+        let caller = self.identity.identify(&metadata)?;
+        let _request = self.accounting.as_ref().map(|a| a.begin_request(&caller)).transpose()?;
+        let intent =
+            request.intent.ok_or_else(|| Status::invalid_argument("intent is required"))?;
+
+        let config = IntentConfiguration::new(
+            self.broker.intern(request.namespace),
+            match intent.intent {
+                Some(ref intent) => IntentBrokeringServer::<T>::validate_intent(intent)
+                    .map(|_| IntentBrokeringServer::<T>::map_intent_variant(intent)),
+                None => Err(Status::invalid_argument("Intent is not known.")),
+            }?,
+        );
+
+        // Structured logs index the activity of the broker by these fields.
+        tracing::Span::current()
+            .record("namespace", config.namespace())
+            .record("intent", tracing::field::display(config.intent()))
+            .record("identity", tracing::field::display(&caller));
+
+        self.authorize(&caller, &config)?;
+        self.audit_deprecation(&caller, &config);
+
+        #[cfg(not(test))]
+        let broker = &self.broker;
+        #[cfg(test)]
+        let _ = self.broker; // Suppress dead code warning when test feature is active.
+        #[cfg(test)]
+        let broker = tests::MockBroker;
+
+        if let Some(channel_id) = operation::progress_channel_id(&metadata) {
+            if !matches!(config.intent(), IntentKind::Invoke) {
+                return Err(Status::invalid_argument(
+                    "Only 'Invoke' intents can be started as long-running operations.",
+                ));
+            }
+
+            let participant = broker
+                .resolve_participant(&config)
+                .ok_or_else(|| Status::not_found("No provider found."))?;
+            let (operation, response) =
+                self.operations.start(config.namespace(), channel_id, participant, intent).await?;
+            tracing::debug!("Started operation '{}'.", operation.id);
+
+            return Ok(Response::new(FulfillResponse {
+                fulfillment: response.fulfillment,
+                operation: Some(operation),
+            }));
+        }
+
+        let mut intent = intent;
+        let denied_sources = self.deny_sources(&caller, &config, &mut intent);
+        if let Some(Intent::Subscribe(subscribe)) = &intent.intent {
+            if subscribe.sources.is_empty() && !denied_sources.is_empty() {
+                return Ok(Response::new(FulfillResponse {
+                    fulfillment: Some(FulfillmentMessage {
+                        fulfillment: Some(FulfillmentEnum::Subscribe(SubscribeFulfillment {
+                            denied_sources,
+                        })),
+                    }),
+                    operation: None,
+                }));
+            }
+        }
+
+        if let (Some(accounting), Some(Intent::Subscribe(subscribe))) =
+            (&self.accounting, &intent.intent)
+        {
+            accounting.admit_subscription(&caller, &subscribe.channel_id, &subscribe.sources)?;
+        }
+
+        if let (Some(bridges), Some(Intent::Subscribe(subscribe))) = (&self.bridges, &intent.intent)
+        {
+            if bridges.serve(config.namespace(), subscribe)?.is_some() {
+                return Ok(Response::new(FulfillResponse {
+                    fulfillment: Some(FulfillmentMessage {
+                        fulfillment: Some(FulfillmentEnum::Subscribe(SubscribeFulfillment {
+                            denied_sources,
+                        })),
+                    }),
+                    operation: None,
+                }));
+            }
+        }
+
+        let session = match (&self.sessions, Sessions::token(&metadata)) {
+            (Some(sessions), Some(token)) if config.intent() != IntentKind::Subscribe => {
+                Some((sessions, token))
+            }
+            _ => None,
+        };
+        let session_url = match session {
+            Some((sessions, token)) => sessions.resolve(token, &config, &self.broker)?,
+            None => None,
+        };
+
+        let binding = match session_url
+            .and_then(|url| broker.resolve_at(&config, &url))
+            .or_else(|| broker.resolve(&config))
+        {
+            Some(binding) => binding,
+            None => match self.await_provider(&config, &metadata).await {
+                Err(status) if status.code() == Code::NotFound => {
+                    let Some(upstream) = self.forward_to(config.namespace()) else {
+                        return Err(status);
+                    };
+                    let fulfillment =
+                        self.forward_upstream(upstream, &config, &metadata, intent).await?;
+                    return Ok(Response::new(FulfillResponse { fulfillment, operation: None }));
+                }
+                result => {
+                    result?;
+                    broker
+                        .resolve(&config)
+                        .ok_or_else(|| Status::not_found("No provider found."))?
+                }
+            },
+        };
+
+        let consumer_transform = self
+            .transforms
+            .as_ref()
+            .and_then(|transforms| transforms.to_provider(config.namespace(), &mut intent));
+
+        let writes = match &self.write_events {
+            Some(write_events) if write_events.covers(config.namespace()) => {
+                let read = config.with_intent(IntentKind::Read);
+                Some(WriteEvents::prepare(&intent, broker.resolve(&read)).await)
+            }
+            _ => None,
+        };
+
+        let read_key = match &intent.intent {
+            Some(Intent::Read(read)) if self.read_fallbacks => Some(read.key.clone()),
+            _ => None,
+        };
+
+        let strict_intent = match &intent.intent {
+            Some(intent) if self.strict_fulfillments => Some(intent.clone()),
+            _ => None,
+        };
+
+        let continuation = match &self.response_limits {
+            Some(limits) => limits.continuation(config.intent(), &metadata)?,
+            None => None,
+        };
+
+        let faults = self.chaos.as_ref().map(|chaos| chaos.roll(&config)).unwrap_or_default();
+        let execution = async {
+            timings.provider_started();
+            let response = faults.inject(binding.execute(intent)).await;
+            timings.provider_ended();
+            if let (Some((sessions, token)), Err(status)) = (session, &response) {
+                if status.code() == Code::Unavailable {
+                    sessions.fail(token, &config);
+                }
+            }
+            response
+        };
+        let mut response = match IdempotencyCache::key(&metadata) {
+            Some(key) if matches!(config.intent(), IntentKind::Write | IntentKind::Invoke) => {
+                self.idempotency.fulfill_once(config.namespace(), key, execution).await?
+            }
+            _ => match (execution.await, read_key) {
+                (Err(status), Some(key)) if status.code() == Code::Unavailable => {
+                    self.read_fallback(&config, &key).ok_or(status)?
+                }
+                (response, _) => response?,
+            },
+        };
+
+        if let Some(intent) = strict_intent {
+            self.validate_fulfillment(config.namespace(), &intent, response.fulfillment.as_ref())?;
+        }
+
+        let continuation = match &self.response_limits {
+            Some(limits) => limits.enforce(&config, continuation, response.fulfillment.as_mut())?,
+            None => None,
+        };
+
+        if let (Some(write_events), Some(writes)) = (&self.write_events, writes) {
+            write_events.publish(
+                config.namespace(),
+                &caller,
+                writes,
+                response.fulfillment.as_ref(),
+            );
+        }
+
+        let mut fulfillment = response.fulfillment;
+        if let (Some(consumer_transform), Some(fulfillment)) =
+            (consumer_transform, fulfillment.as_mut())
+        {
+            consumer_transform.apply(fulfillment);
+        }
+        if let Some(FulfillmentMessage { fulfillment: Some(FulfillmentEnum::Discover(discover)) }) =
+            fulfillment.as_mut()
+        {
+            self.annotate_deprecations(config.namespace(), discover);
+        }
+        if let Some(FulfillmentMessage {
+            fulfillment: Some(FulfillmentEnum::Subscribe(subscribe)),
+        }) = fulfillment.as_mut()
+        {
+            subscribe.denied_sources.extend(denied_sources);
+        }
+
+        let mut response = tonic::Response::new(FulfillResponse { fulfillment, operation: None });
+        if let Some(continuation) = continuation {
+            response.metadata_mut().insert(CONTINUATION_METADATA_KEY, continuation.into());
+        }
+
+        Ok(response)
+    }
+
+    /// Returns the Intent Broker to forward intents for a namespace which is
+    /// not served locally to: a federated peer serving it, or else the
+    /// upstream Intent Broker.
+    fn forward_to(&self, namespace: &str) -> Option<&Upstream> {
+        self.federation
+            .as_ref()
+            .and_then(|federation| federation.owner(namespace))
+            .or(self.upstream.as_ref())
+    }
+
+    /// Forwards an intent to another Intent Broker, suppressing duplicate
+    /// `Write` and `Invoke` requests with an idempotency key like intents
+    /// fulfilled by local providers.
+    async fn forward_upstream(
+        &self,
+        upstream: &Upstream,
+        config: &IntentConfiguration,
+        metadata: &MetadataMap,
+        intent: IntentMessage,
+    ) -> Result<Option<FulfillmentMessage>, Status> {
+        let forward = async {
+            let fulfillment = upstream.forward(metadata, config.namespace(), intent).await?;
+            Ok(ProviderFulfillResponse { fulfillment })
+        };
+
+        let response = match IdempotencyCache::key(metadata) {
+            Some(key) if matches!(config.intent(), IntentKind::Write | IntentKind::Invoke) => {
+                self.idempotency.fulfill_once(config.namespace(), key, forward).await?
+            }
+            _ => forward.await?,
+        };
+
+        Ok(response.fulfillment)
+    }
+
+    /// Records the fulfillment of a deprecated intent in the audit log.
+    fn audit_deprecation(&self, caller: &Caller, config: &IntentConfiguration) {
+        if let Some(deprecation) = self.broker.deprecation(config) {
+            tracing::warn!(
+                target: "audit",
+                identity = %caller,
+                namespace = config.namespace(),
+                intent = %config.intent(),
+                sunset_unix_secs = deprecation
+                    .sunset()
+                    .and_then(|sunset| sunset.duration_since(UNIX_EPOCH).ok())
+                    .map(|sunset| sunset.as_secs()),
+                replacement = deprecation.replacement(),
+                "Fulfilling deprecated intent."
+            );
+        }
+    }
+
+    /// Removes the sources of a `Subscribe` intent which the caller is not
+    /// allowed to subscribe to, returning them to be reported to the caller.
+    fn deny_sources(
+        &self,
+        caller: &Caller,
+        config: &IntentConfiguration,
+        intent: &mut IntentMessage,
+    ) -> Vec<String> {
+        let (Some(acl), Some(Intent::Subscribe(subscribe))) = (&self.acl, intent.intent.as_mut())
+        else {
+            return vec![];
+        };
+
+        let denied = acl
+            .denied_sources(caller, config.namespace(), &subscribe.sources)
+            .into_iter()
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+
+        subscribe.sources.retain(|source| !denied.contains(source));
+        for source in &denied {
+            subscribe.filters.remove(source);
+        }
+
+        denied
+    }
+
+    /// Rejects a fulfillment of a provider which is malformed for its intent.
+    fn validate_fulfillment(
+        &self,
+        namespace: &str,
+        intent: &Intent,
+        fulfillment: Option<&FulfillmentMessage>,
+    ) -> Result<(), Status> {
+        let streaming_ess = self.broker.streaming_ess();
+        let diagnostics = validation::diagnose(intent, fulfillment, |key| {
+            streaming_ess.source_type(&proxied_source(namespace, key))
+        });
+
+        if diagnostics.is_empty() {
+            return Ok(());
+        }
+
+        let message = format!(
+            "Provider of namespace '{namespace}' returned a malformed fulfillment: {}.",
+            diagnostics.join("; ")
+        );
+        tracing::warn!("{message}");
+        Err(Status::internal(message))
+    }
+
+    /// Fulfills a `Read` intent with the fallback value of the key, if its
+    /// provider declared one.
+    fn read_fallback(
+        &self,
+        config: &IntentConfiguration,
+        key: &str,
+    ) -> Option<ProviderFulfillResponse> {
+        let value = self.broker.fallback(config, key)?;
+        tracing::warn!(
+            namespace = config.namespace(),
+            key,
+            "Provider is unreachable, returning fallback value."
+        );
+
+        Some(ProviderFulfillResponse {
+            fulfillment: Some(FulfillmentMessage {
+                fulfillment: Some(FulfillmentEnum::Read(ReadFulfillment {
+                    value: Some(value),
+                    fallback: true,
+                    freshness: Some(Freshness {
+                        quality: Quality::Fallback as i32,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })),
+            }),
+        })
+    }
+
+    /// Adds the deprecated intents of the namespace to the metadata of the
+    /// discovered services.
+    fn annotate_deprecations(&self, namespace: &str, discover: &mut DiscoverFulfillment) {
+        let deprecations = self.broker.deprecations(namespace);
+        if deprecations.is_empty() {
+            return;
+        }
+
+        let value = deprecations_value(deprecations.iter().map(|(intent, d)| (*intent, d)));
+        for service in discover.services.iter_mut() {
+            service
+                .metadata
+                .insert(DEPRECATIONS_KEY.to_owned(), ValueMessage { value: Some(value.clone()) });
+        }
+    }
+
+    fn validate_intent(intent: &Intent) -> Result<(), Status> {
+        if let Intent::WriteBatch(batch) = intent {
+            if batch.writes.is_empty() {
+                return Err(Status::invalid_argument("Write batch must not be empty."));
+            }
+
+            let mut keys = HashSet::new();
+            if let Some(write) = batch.writes.iter().find(|w| !keys.insert(w.key.as_str())) {
+                return Err(Status::invalid_argument(format!(
+                    "Key '{}' is written more than once in the batch.",
+                    write.key
+                )));
+            }
         }
+
+        Ok(())
     }
 }
 
@@ -86,14 +967,23 @@ impl<T: Observer + Send + Sync + 'static> IntentBrokeringService for IntentBroke
         &self,
         request: Request<AnnounceRequest>,
     ) -> Result<Response<AnnounceResponse>, Status> {
+        let (metadata, _, request) = request.into_parts();
         let service = request
-            .into_inner()
             .service
             .ok_or_else(|| Status::new(tonic::Code::InvalidArgument, "service is required"))?;
-        let svc_cfg = resolve_service_configuration(service)?;
-        let registration_state = if self.registry.write().unwrap().touch(&svc_cfg, Instant::now()) {
+        let svc_cfg = resolve_service_configuration(service.clone())?;
+        let announced = self.registry.write().unwrap().touch(&svc_cfg, Instant::now());
+        let registration_state = if announced {
             tracing::debug!("Service {:#?} already announced", svc_cfg);
             RegistrationState::NotChanged
+        } else if request.fetch_registration {
+            self.validate_url(svc_cfg.url()).await?;
+            let intents = fetch_registration(svc_cfg.url()).await?;
+            let registration = RegisterRequest { service: Some(service), intents };
+            self.verify_signature(&registration, &metadata)?;
+            tracing::debug!("Service {:#?} registered with fetched registration", svc_cfg);
+            self.register_service(svc_cfg, registration.intents)?;
+            RegistrationState::NotChanged
         } else {
             tracing::debug!("Service {:#?} not yet announced", svc_cfg);
             RegistrationState::Announced
@@ -106,20 +996,13 @@ impl<T: Observer + Send + Sync + 'static> IntentBrokeringService for IntentBroke
         &self,
         request: Request<RegisterRequest>,
     ) -> Result<Response<RegisterResponse>, Status> {
-        let request = request.into_inner();
+        let (metadata, _, request) = request.into_parts();
+        self.verify_signature(&request, &metadata)?;
         let service =
             request.service.ok_or_else(|| Status::invalid_argument("service is required"))?;
         let svc_cfg = resolve_service_configuration(service)?;
-        let intents: Result<Vec<_>, _> = request
-            .intents
-            .into_iter()
-            .map(IntentBrokeringServer::<T>::create_configruation_from_registration)
-            .collect();
-        self.registry
-            .write()
-            .unwrap()
-            .upsert(svc_cfg, intents?, Instant::now())
-            .map_err(|e| Status::unknown(e.message()))?;
+        self.validate_url(svc_cfg.url()).await?;
+        self.register_service(svc_cfg, request.intents)?;
         Ok(Response::new(RegisterResponse {}))
     }
 
@@ -127,35 +1010,373 @@ impl<T: Observer + Send + Sync + 'static> IntentBrokeringService for IntentBroke
         &self,
         request: Request<FulfillRequest>,
     ) -> Result<Response<FulfillResponse>, Status> {
-        let request = request.into_inner();
-        let intent =
-            request.intent.ok_or_else(|| Status::invalid_argument("intent is required"))?;
-
-        let config = IntentConfiguration::new(
-            request.namespace,
-            match intent.intent {
-                Some(ref intent) => Ok(IntentBrokeringServer::<T>::map_intent_variant(intent)),
-                None => Err(Status::invalid_argument("Intent is not known.")),
-            }?,
+        let correlation_id = correlation::from_metadata(request.metadata());
+        let span = tracing::info_span!(
+            "fulfill",
+            correlation_id = %correlation_id,
+            namespace = tracing::field::Empty,
+            intent = tracing::field::Empty,
+            identity = tracing::field::Empty,
         );
 
+        let timings = Timings::start();
+        let namespace = request.get_ref().namespace.clone();
+        let intent_kind = request
+            .get_ref()
+            .intent
+            .as_ref()
+            .and_then(|intent| intent.intent.as_ref())
+            .map(Self::map_intent_variant);
+        let started = Instant::now();
+
+        let result = match correlation::scope(
+            correlation_id.clone(),
+            self.fulfill_correlated(request, &timings),
+        )
+        .instrument(span.clone())
+        .await
+        {
+            Ok(mut response) => {
+                correlation::insert(response.metadata_mut(), &correlation_id);
+                if let Some(budgets) = &self.latency_budgets {
+                    timings.sent();
+                    budgets.check(&namespace, &timings);
+                    timings.annotate(response.metadata_mut());
+                }
+                Ok(response)
+            }
+            Err(mut status) => {
+                correlation::insert(status.metadata_mut(), &correlation_id);
+                Err(status)
+            }
+        };
+
+        if let (Some(log), Some(intent_kind)) = (&self.fulfillment_log, intent_kind) {
+            let code = result.as_ref().map_or_else(Status::code, |_| Code::Ok);
+            span.in_scope(|| log.record(&namespace, intent_kind, code, started.elapsed()));
+        }
+
+        result
+    }
+
+    async fn fulfill_transaction(
+        &self,
+        request: Request<FulfillTransactionRequest>,
+    ) -> Result<Response<FulfillTransactionResponse>, Status> {
+        let (metadata, _, request) = request.into_parts();
+        let caller = self.identity.identify(&metadata)?;
+        // The transaction is accounted as a single request until it completed.
+        let _request = self.accounting.as_ref().map(|a| a.begin_request(&caller)).transpose()?;
+        let operations = request.operations;
+
+        if operations.is_empty() {
+            return Err(Status::invalid_argument("Transaction must contain an operation."));
+        }
+
         #[cfg(not(test))]
         let broker = &self.broker;
         #[cfg(test)]
-        let _ = self.broker; // Suppress dead code warning when test feature is active.
-        #[cfg(test)]
         let broker = tests::MockBroker;
 
-        let binding =
-            broker.resolve(&config).ok_or_else(|| Status::not_found("No provider found."))?;
+        let operations = operations
+            .into_iter()
+            .map(|operation| {
+                let intent = operation
+                    .intent
+                    .ok_or_else(|| Status::invalid_argument("intent is required"))?;
 
-        let response = binding.execute(intent).await?;
+                let kind = match intent.intent {
+                    Some(ref intent) => IntentBrokeringServer::<T>::validate_intent(intent)
+                        .map(|_| IntentBrokeringServer::<T>::map_intent_variant(intent)),
+                    None => Err(Status::invalid_argument("Intent is not known.")),
+                }?;
 
-        Ok(tonic::Response::new(FulfillResponse { fulfillment: response.fulfillment }))
-    }
-}
+                if !matches!(kind, IntentKind::Write | IntentKind::Invoke) {
+                    return Err(Status::invalid_argument(
+                        "Only 'Write' and 'Invoke' intents can be part of a transaction.",
+                    ));
+                }
 
-fn resolve_service_configuration(
+                let config =
+                    IntentConfiguration::new(self.broker.intern(operation.namespace), kind);
+                self.authorize(&caller, &config)?;
+                self.audit_deprecation(&caller, &config);
+
+                let participant = broker
+                    .resolve_participant(&config)
+                    .ok_or_else(|| Status::not_found("No provider found."))?;
+
+                if !participant.transactional() {
+                    return Err(Status::failed_precondition(format!(
+                        "Provider for namespace '{}' does not support transactions.",
+                        config.namespace()
+                    )));
+                }
+
+                Ok((participant, intent))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let transaction = Transaction::new(operations);
+        tracing::debug!("Executing transaction '{}'.", transaction.id());
+
+        Ok(Response::new(transaction.execute().await))
+    }
+
+    async fn wait_for_service(
+        &self,
+        request: Request<WaitForServiceRequest>,
+    ) -> Result<Response<WaitForServiceResponse>, Status> {
+        let request = request.into_inner();
+        let intents = request
+            .intents
+            .into_iter()
+            .map(IntentBrokeringServer::<T>::map_intent_value)
+            .collect::<Result<Vec<_>, _>>()?;
+        let deadline = request
+            .timeout
+            .map(|timeout| {
+                Duration::try_from(timeout)
+                    .map(|timeout| tokio::time::Instant::now() + timeout)
+                    .map_err(|_| Status::invalid_argument("Timeout must not be negative."))
+            })
+            .transpose()?;
+
+        self.wait_until_served(&request.namespace, &intents, deadline).await?;
+        Ok(Response::new(WaitForServiceResponse {}))
+    }
+
+    async fn export_registry(
+        &self,
+        request: Request<ExportRegistryRequest>,
+    ) -> Result<Response<ExportRegistryResponse>, Status> {
+        self.authorize_admin(&request, IntentKind::Read)?;
+
+        let snapshot = self.registry.read().unwrap().export(Instant::now());
+        let snapshot = serde_json::to_string(&snapshot)
+            .map_err(|e| Status::internal(format!("Failed to serialize snapshot: {e}")))?;
+
+        Ok(Response::new(ExportRegistryResponse { snapshot }))
+    }
+
+    async fn import_registry(
+        &self,
+        request: Request<ImportRegistryRequest>,
+    ) -> Result<Response<ImportRegistryResponse>, Status> {
+        self.authorize_admin(&request, IntentKind::Write)?;
+
+        let snapshot: Snapshot = serde_json::from_str(&request.into_inner().snapshot)
+            .map_err(|e| Status::invalid_argument(format!("Snapshot is not valid: {e}")))?;
+        for url in snapshot.urls() {
+            self.validate_url(url).await?;
+        }
+
+        self.registry
+            .write()
+            .unwrap()
+            .import(snapshot, Instant::now())
+            .map_err(|e| Status::invalid_argument(e.message()))?;
+
+        Ok(Response::new(ImportRegistryResponse {}))
+    }
+
+    async fn compact_registry(
+        &self,
+        request: Request<CompactRegistryRequest>,
+    ) -> Result<Response<CompactRegistryResponse>, Status> {
+        self.authorize_admin(&request, IntentKind::Write)?;
+
+        let compaction = self.compact();
+
+        Ok(Response::new(CompactRegistryResponse {
+            empty_intents: compaction.empty_intents as u64,
+            orphaned_registrations: compaction.orphaned_registrations as u64,
+            stale_entries: compaction.stale_entries as u64,
+        }))
+    }
+
+    async fn report_progress(
+        &self,
+        request: Request<ReportProgressRequest>,
+    ) -> Result<Response<ReportProgressResponse>, Status> {
+        let (metadata, _, request) = request.into_parts();
+        let caller = self.identity.identify(&metadata)?;
+        let namespace = self.operations.namespace(&request.operation_id)?;
+        self.authorize(&caller, &IntentConfiguration::new(namespace, IntentKind::Invoke))?;
+
+        let state = OperationState::try_from(request.state)
+            .map_err(|_| Status::invalid_argument("Operation state is not known."))?;
+
+        self.operations.report(
+            &request.operation_id,
+            state,
+            request.value.and_then(|v| v.value),
+        )?;
+
+        Ok(Response::new(ReportProgressResponse {}))
+    }
+
+    async fn cancel_operation(
+        &self,
+        request: Request<CancelOperationRequest>,
+    ) -> Result<Response<CancelOperationResponse>, Status> {
+        let (metadata, _, request) = request.into_parts();
+        let caller = self.identity.identify(&metadata)?;
+        let namespace = self.operations.namespace(&request.operation_id)?;
+        self.authorize(&caller, &IntentConfiguration::new(namespace, IntentKind::Invoke))?;
+        self.operations.cancel(&request.operation_id).await?;
+
+        Ok(Response::new(CancelOperationResponse {}))
+    }
+
+    async fn resolve_intent(
+        &self,
+        request: Request<ResolveIntentRequest>,
+    ) -> Result<Response<ResolveIntentResponse>, Status> {
+        let request = request.into_inner();
+        let intent = IntentConfiguration::new(
+            request.namespace,
+            IntentBrokeringServer::<T>::map_intent_value(request.intent)?,
+        );
+
+        let registry = self.registry.read().unwrap();
+        let candidates: Vec<_> = intent_broker::explain(registry.services_for(&intent))
+            .into_iter()
+            .map(|(service, role, rationale)| Candidate {
+                service: Some(service_registration(service)),
+                role: match role {
+                    CandidateRole::Primary => CandidateRoleMessage::Primary,
+                    CandidateRole::Fallback => CandidateRoleMessage::Fallback,
+                    CandidateRole::Unselected => CandidateRoleMessage::Unselected,
+                } as i32,
+                rationale,
+            })
+            .collect();
+
+        if candidates.is_empty() {
+            return Err(Status::not_found(format!(
+                "No service is registered for intent '{}' of namespace '{}'.",
+                intent.intent(),
+                intent.namespace()
+            )));
+        }
+
+        Ok(Response::new(ResolveIntentResponse { candidates }))
+    }
+
+    async fn set_override(
+        &self,
+        request: Request<SetOverrideRequest>,
+    ) -> Result<Response<SetOverrideResponse>, Status> {
+        let caller = self.authorize_overrides(&request, IntentKind::Write)?;
+        let entry = request
+            .into_inner()
+            .entry
+            .ok_or_else(|| Status::invalid_argument("Override is required."))?;
+
+        let intent = IntentConfiguration::new(
+            entry.namespace,
+            IntentBrokeringServer::<T>::map_intent_value(entry.intent)?,
+        );
+        let ttl = entry
+            .ttl
+            .ok_or_else(|| Status::invalid_argument("Time to live of override is required."))
+            .and_then(|ttl| {
+                Duration::try_from(ttl).map_err(|_| {
+                    Status::invalid_argument("Time to live of override must not be negative.")
+                })
+            })?;
+        let target = match entry.target {
+            Some(OverrideTarget::ProviderUrl(url)) => {
+                let url: Url = url
+                    .parse()
+                    .map_err(|_| Status::invalid_argument("Fixture provider URL is not valid."))?;
+                provider_url::validate(&url).map_err(|e| {
+                    Status::invalid_argument(format!("Fixture provider URL is not allowed: {e}"))
+                })?;
+                Target::Provider(url)
+            }
+            Some(OverrideTarget::Fulfillment(fulfillment)) => {
+                Target::Canned(Canned::Fulfillment(fulfillment))
+            }
+            Some(OverrideTarget::Error(OverrideError { code, message })) => {
+                Target::Canned(Canned::Error(Code::from(code), message.into()))
+            }
+            None => return Err(Status::invalid_argument("Target of override is required.")),
+        };
+
+        tracing::warn!(
+            target: "audit",
+            identity = %caller,
+            namespace = intent.namespace(),
+            intent = %intent.intent(),
+            ttl_secs = ttl.as_secs(),
+            "Overriding intent."
+        );
+        self.broker.set_override(intent, target, ttl)?;
+
+        Ok(Response::new(SetOverrideResponse {}))
+    }
+
+    async fn clear_override(
+        &self,
+        request: Request<ClearOverrideRequest>,
+    ) -> Result<Response<ClearOverrideResponse>, Status> {
+        let caller = self.authorize_overrides(&request, IntentKind::Write)?;
+        let request = request.into_inner();
+        let intent = IntentConfiguration::new(
+            request.namespace,
+            IntentBrokeringServer::<T>::map_intent_value(request.intent)?,
+        );
+
+        let cleared = self.broker.clear_override(&intent);
+        if cleared {
+            tracing::warn!(
+                target: "audit",
+                identity = %caller,
+                namespace = intent.namespace(),
+                intent = %intent.intent(),
+                "Cleared override of intent."
+            );
+        }
+
+        Ok(Response::new(ClearOverrideResponse { cleared }))
+    }
+
+    async fn list_overrides(
+        &self,
+        request: Request<ListOverridesRequest>,
+    ) -> Result<Response<ListOverridesResponse>, Status> {
+        self.authorize_overrides(&request, IntentKind::Read)?;
+
+        let entries = self
+            .broker
+            .overrides()
+            .into_iter()
+            .map(|(intent, target, ttl)| IntentOverride {
+                namespace: intent.namespace().to_owned(),
+                intent: IntentBrokeringServer::<T>::map_intent_kind(intent.intent()),
+                target: Some(match target {
+                    Target::Provider(url) => OverrideTarget::ProviderUrl(url.to_string()),
+                    Target::Canned(Canned::Fulfillment(fulfillment)) => {
+                        OverrideTarget::Fulfillment(fulfillment)
+                    }
+                    Target::Canned(Canned::Error(code, message)) => {
+                        OverrideTarget::Error(OverrideError {
+                            code: code as i32,
+                            message: message.into(),
+                        })
+                    }
+                }),
+                ttl: prost_types::Duration::try_from(ttl).ok(),
+            })
+            .collect();
+
+        Ok(Response::new(ListOverridesResponse { entries }))
+    }
+}
+
+fn resolve_service_configuration(
     service: IntentServiceRegistration,
 ) -> Result<ServiceConfiguration, Status> {
     map_locality_value(service.locality)
@@ -170,9 +1391,57 @@ fn resolve_service_configuration(
                 url,
                 locality,
             )
+            .set_transactional(service.transactional)
+            .set_max_concurrency(Some(service.max_concurrency).filter(|limit| *limit > 0))
         })
 }
 
+/// Converts the configuration of a registered service back into its
+/// registration.
+fn service_registration(service: &ServiceConfiguration) -> IntentServiceRegistration {
+    IntentServiceRegistration {
+        name: service.id().name().into(),
+        version: service.id().version().into(),
+        url: service.url().to_string(),
+        locality: match service.locality() {
+            ExecutionLocality::Local => 0,
+            ExecutionLocality::Cloud => 1,
+        },
+        transactional: service.transactional(),
+        max_concurrency: service.max_concurrency().unwrap_or(0),
+    }
+}
+
+/// Fetches the intents of a provider from the `RegistrationService` served at
+/// its URL.
+async fn fetch_registration(url: &Url) -> Result<Vec<IntentRegistration>, Status> {
+    let endpoint = Endpoint::from_shared(url.to_string())
+        .map_err(|_| Status::invalid_argument("Service URL is not valid."))?
+        .connect_timeout(FETCH_REGISTRATION_TIMEOUT)
+        .timeout(FETCH_REGISTRATION_TIMEOUT);
+
+    let channel = endpoint.connect().await.map_err(|e| {
+        Status::unavailable(format!("Failed to connect to service to fetch registration: {e}."))
+    })?;
+
+    RegistrationServiceClient::new(channel)
+        .get_registration(GetRegistrationRequest {})
+        .await
+        .map(|response| response.into_inner().intents)
+        .map_err(|e| Status::unavailable(format!("Failed to fetch registration of service: {e}.")))
+}
+
+fn resolve_deprecation(deprecation: DeprecationMessage) -> Result<Deprecation, Status> {
+    let sunset = deprecation
+        .sunset
+        .map(SystemTime::try_from)
+        .transpose()
+        .map_err(|_| Status::invalid_argument("Sunset of deprecation is not valid."))?;
+    let replacement = Some(deprecation.replacement).filter(|r| !r.is_empty()).map(|r| r.into());
+
+    Ok(Deprecation::new(sunset, replacement))
+}
+
 fn map_locality_value(locality: i32) -> Result<ExecutionLocality, Status> {
     match locality {
         0 => Ok(ExecutionLocality::Local),
@@ -183,19 +1452,35 @@ fn map_locality_value(locality: i32) -> Result<ExecutionLocality, Status> {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
+    use crate::accounting::Limits;
+    use crate::acl::tests::TempFile;
     use crate::execution::RuntimeBinding;
+    use crate::idempotency::IDEMPOTENCY_KEY_METADATA_KEY;
+    use crate::registration_signature::SIGNATURE_METADATA_KEY;
     use crate::registry::{Change, Observer, Registry};
     use crate::streaming::StreamingEss;
-    use crate::{connection_provider::GrpcProvider, execution::tests::TestBinding};
+    use crate::transaction::Participant;
+    use crate::upstream;
+    use crate::{
+        connection_provider::{ConnectionProvider as _, GrpcProvider, ReusableProvider},
+        execution::tests::TestBinding,
+    };
     use intent_brokering_proto::{
         common,
         runtime::{
-            intent_brokering_service_server::IntentBrokeringService, intent_registration,
-            AnnounceRequest, IntentRegistration, IntentServiceRegistration, RegisterRequest,
-            RegistrationState,
+            intent_brokering_service_server::IntentBrokeringService,
+            intent_registration,
+            registration_service_server::{RegistrationService, RegistrationServiceServer},
+            AnnounceRequest, GetRegistrationResponse, IntentRegistration,
+            IntentServiceRegistration, RegisterRequest, RegistrationState, WaitForServiceRequest,
         },
     };
-    use tonic::Code;
+    use test_case::test_case;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::{transport::Server, Code};
 
     use super::*;
 
@@ -208,6 +1493,64 @@ mod tests {
         assert_eq!(response.registration_state, RegistrationState::Announced as i32);
     }
 
+    #[tokio::test]
+    async fn announce_with_fetch_registration_registers_fetched_intents() {
+        // arrange
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap()); // DevSkim: ignore DS137138
+        tokio::spawn(
+            Server::builder()
+                .add_service(RegistrationServiceServer::new(TestRegistrationService))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let server = setup();
+        let mut request = create_announce_request();
+        request.service.as_mut().unwrap().url = url;
+        request.fetch_registration = true;
+
+        // act
+        let response = server.announce(Request::new(request)).await.unwrap();
+
+        // assert
+        assert_eq!(RegistrationState::NotChanged as i32, response.into_inner().registration_state);
+        assert_eq!(2, server.registry.read().unwrap().count_external_intents());
+    }
+
+    #[tokio::test]
+    async fn announce_with_fetch_registration_fails_if_service_is_not_serving() {
+        // arrange
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap()); // DevSkim: ignore DS137138
+        drop(listener);
+
+        let server = setup();
+        let mut request = create_announce_request();
+        request.service.as_mut().unwrap().url = url;
+        request.fetch_registration = true;
+
+        // act
+        let result = server.announce(Request::new(request)).await;
+
+        // assert
+        assert_eq!(Code::Unavailable, result.unwrap_err().code());
+        assert_eq!(0, server.registry.read().unwrap().count_external_intents());
+    }
+
+    struct TestRegistrationService;
+
+    #[async_trait]
+    impl RegistrationService for TestRegistrationService {
+        async fn get_registration(
+            &self,
+            _: Request<GetRegistrationRequest>,
+        ) -> Result<Response<GetRegistrationResponse>, Status> {
+            Ok(Response::new(GetRegistrationResponse {
+                intents: create_register_request().intents,
+            }))
+        }
+    }
+
     #[tokio::test]
     async fn test_register_service_with_intents() {
         let server = setup();
@@ -219,6 +1562,84 @@ mod tests {
         assert_eq!(response.registration_state, RegistrationState::NotChanged as i32);
     }
 
+    #[tokio::test]
+    async fn register_rejects_registrations_beyond_admitted_rate() {
+        // arrange
+        let server = setup().with_admission(admission::Config::new(1));
+        let request = create_register_request();
+        _ = server.register(Request::new(request.clone())).await.unwrap();
+
+        // act
+        let result = server.register(Request::new(request)).await;
+
+        // assert
+        let status = result.unwrap_err();
+        assert_eq!(Code::ResourceExhausted, status.code());
+        assert!(status.metadata().get(admission::RETRY_AFTER_METADATA_KEY).is_some());
+    }
+
+    #[tokio::test]
+    async fn register_rejects_service_with_blocked_url() {
+        // arrange
+        let server = setup();
+        let mut request = create_register_request();
+        request.service.as_mut().unwrap().url = "http://169.254.169.254".to_owned(); // DevSkim: ignore DS137138
+
+        // act
+        let result = server.register(Request::new(request)).await;
+
+        // assert
+        assert_eq!(Code::InvalidArgument, result.unwrap_err().code());
+        assert_eq!(0, server.registry_do(|registry| registry.services().count()));
+    }
+
+    #[tokio::test]
+    async fn register_rejects_unsigned_registration_of_protected_namespace() {
+        // arrange
+        let protected = ProtectedNamespaces::parse("foo=secret").unwrap();
+        let server = setup().with_protected_namespaces(protected);
+        let request = create_register_request();
+        let signature = ProtectedNamespaces::sign(b"secret", &request);
+        let mut signed = Request::new(request.clone());
+        signed.metadata_mut().insert(SIGNATURE_METADATA_KEY, signature.parse().unwrap());
+
+        // act
+        let unsigned = server.register(Request::new(request)).await;
+        let signed = server.register(signed).await;
+
+        // assert
+        assert_eq!(Code::PermissionDenied, unsigned.unwrap_err().code());
+        assert!(signed.is_ok());
+        assert_eq!(1, server.registry_do(|registry| registry.services().count()));
+    }
+
+    #[tokio::test]
+    async fn register_records_deprecated_intents() {
+        // arrange
+        let server = setup();
+        let mut request = create_register_request();
+        request.intents[0].deprecation = Some(DeprecationMessage {
+            sunset: Some(prost_types::Timestamp { seconds: 1_900_000_000, nanos: 0 }),
+            replacement: "baz".to_owned(),
+        });
+
+        // act
+        _ = server.register(Request::new(request)).await.unwrap();
+
+        // assert
+        assert_eq!(
+            Some(Deprecation::new(
+                Some(UNIX_EPOCH + Duration::from_secs(1_900_000_000)),
+                Some("baz".into())
+            )),
+            server.broker.deprecation(&IntentConfiguration::new("foo", IntentKind::Discover))
+        );
+        assert_eq!(
+            None,
+            server.broker.deprecation(&IntentConfiguration::new("bar", IntentKind::Discover))
+        );
+    }
+
     #[tokio::test]
     async fn test_register_service_twice_doesnt_throw_error() {
         let server = setup();
@@ -246,7 +1667,13 @@ mod tests {
         // arrange
         let subject = setup();
         let request = RegisterRequest {
-            intents: vec![IntentRegistration { namespace: "test".to_owned(), intent: -1 }],
+            intents: vec![IntentRegistration {
+                namespace: "test".to_owned(),
+                intent: -1,
+                deprecation: None,
+                fallbacks: Default::default(),
+                source_types: Default::default(),
+            }],
             ..create_register_request()
         };
 
@@ -276,6 +1703,7 @@ mod tests {
             IntentKind::Write => {}
             IntentKind::Invoke => {}
             IntentKind::Subscribe => {}
+            IntentKind::Delete => {}
         }
 
         fn test(intent_value: i32, kind: IntentKind) {
@@ -291,6 +1719,7 @@ mod tests {
         test(INTENT_MAPPING_WRITE, IntentKind::Write);
         test(INTENT_MAPPING_INVOKE, IntentKind::Invoke);
         test(INTENT_MAPPING_SUBSCRIBE, IntentKind::Subscribe);
+        test(INTENT_MAPPING_DELETE, IntentKind::Delete);
     }
 
     #[test]
@@ -307,6 +1736,7 @@ mod tests {
             IntentKind::Write => {}
             IntentKind::Invoke => {}
             IntentKind::Subscribe => {}
+            IntentKind::Delete => {}
         }
 
         // mapping validations
@@ -316,6 +1746,7 @@ mod tests {
         assert_eq!(intent_registration::Intent::Write as i32, INTENT_MAPPING_WRITE);
         assert_eq!(intent_registration::Intent::Invoke as i32, INTENT_MAPPING_INVOKE);
         assert_eq!(intent_registration::Intent::Subscribe as i32, INTENT_MAPPING_SUBSCRIBE);
+        assert_eq!(intent_registration::Intent::Delete as i32, INTENT_MAPPING_DELETE);
     }
 
     #[test]
@@ -331,6 +1762,48 @@ mod tests {
         assert_eq!(map_locality_value(-1).unwrap_err().code(), Code::InvalidArgument);
     }
 
+    #[tokio::test]
+    async fn fulfill_with_progress_channel_fails_if_channel_is_not_open() {
+        // arrange
+        let subject = setup();
+        let mut request = Request::new(FulfillRequest {
+            namespace: "system".to_owned(),
+            intent: Some(create_fulfill()),
+        });
+        request
+            .metadata_mut()
+            .insert(operation::PROGRESS_CHANNEL_ID_METADATA_KEY, "unknown".parse().unwrap());
+
+        // act
+        let result = subject.fulfill(request).await;
+
+        // assert
+        assert_eq!(Code::FailedPrecondition, result.unwrap_err().code());
+    }
+
+    #[tokio::test]
+    async fn report_progress_and_cancel_fail_for_unknown_operation() {
+        // arrange
+        let subject = setup();
+
+        // act
+        let report = subject
+            .report_progress(Request::new(ReportProgressRequest {
+                operation_id: "unknown".to_owned(),
+                ..Default::default()
+            }))
+            .await;
+        let cancel = subject
+            .cancel_operation(Request::new(CancelOperationRequest {
+                operation_id: "unknown".to_owned(),
+            }))
+            .await;
+
+        // assert
+        assert_eq!(Code::NotFound, report.unwrap_err().code());
+        assert_eq!(Code::NotFound, cancel.unwrap_err().code());
+    }
+
     #[tokio::test]
     async fn fulfill_ensures_binding_is_executed() {
         // arrange
@@ -351,6 +1824,207 @@ mod tests {
         );
     }
 
+    #[test_case(true, Ok(true) ; "returns fallback if enabled")]
+    #[test_case(false, Err(Code::Unavailable) ; "returns error if disabled")]
+    #[tokio::test]
+    async fn fulfill_read_when_provider_is_unreachable(
+        read_fallbacks: bool,
+        expected: Result<bool, Code>,
+    ) {
+        // arrange
+        let subject = if read_fallbacks { setup().with_read_fallbacks() } else { setup() };
+        let fallback = common::Value { value: Some(common::value::Value::Bool(true)) };
+        subject
+            .register(Request::new(RegisterRequest {
+                intents: vec![IntentRegistration {
+                    namespace: "unreachable".to_owned(),
+                    intent: intent_registration::Intent::Read as i32,
+                    deprecation: None,
+                    fallbacks: [("test".to_owned(), fallback.clone())].into(),
+                    source_types: Default::default(),
+                }],
+                ..create_register_request()
+            }))
+            .await
+            .unwrap();
+
+        // act
+        let result = subject
+            .fulfill(Request::new(FulfillRequest {
+                namespace: "unreachable".to_owned(),
+                intent: Some(create_read()),
+            }))
+            .await;
+
+        // assert
+        let result = result.map_err(|status| status.code()).map(|response| {
+            match response.into_inner().fulfillment.unwrap().fulfillment.unwrap() {
+                FulfillmentEnum::Read(read) => {
+                    assert_eq!(Some(fallback), read.value);
+                    assert_eq!(Quality::Fallback as i32, read.freshness.unwrap().quality);
+                    read.fallback
+                }
+                _ => panic!("Wrong fulfillment"),
+            }
+        });
+        assert_eq!(expected, result);
+    }
+
+    #[tokio::test]
+    async fn when_registering_fallbacks_for_other_than_read_should_return_invalid_argument_error() {
+        // arrange
+        let subject = setup();
+        let request = RegisterRequest {
+            intents: vec![IntentRegistration {
+                namespace: "test".to_owned(),
+                intent: intent_registration::Intent::Invoke as i32,
+                deprecation: None,
+                fallbacks: [("locked".to_owned(), common::Value { value: None })].into(),
+                source_types: Default::default(),
+            }],
+            ..create_register_request()
+        };
+
+        // act
+        let result = subject.register(Request::new(request)).await;
+
+        // assert
+        assert_eq!(Code::InvalidArgument, result.unwrap_err().code())
+    }
+
+    #[test_case(intent_registration::Intent::Read, "int32" ; "for other than subscribe")]
+    #[test_case(intent_registration::Intent::Subscribe, "integer" ; "of unknown type")]
+    #[tokio::test]
+    async fn when_registering_invalid_source_types_should_return_invalid_argument_error(
+        intent: intent_registration::Intent,
+        value_type: &str,
+    ) {
+        // arrange
+        let subject = setup();
+        let source_type =
+            common::SourceType { value_type: value_type.to_owned(), schema: "".to_owned() };
+        let request = RegisterRequest {
+            intents: vec![IntentRegistration {
+                namespace: "test".to_owned(),
+                intent: intent as i32,
+                deprecation: None,
+                fallbacks: Default::default(),
+                source_types: [("Vehicle.Speed".to_owned(), source_type)].into(),
+            }],
+            ..create_register_request()
+        };
+
+        // act
+        let result = subject.register(Request::new(request)).await;
+
+        // assert
+        assert_eq!(Code::InvalidArgument, result.unwrap_err().code())
+    }
+
+    #[tokio::test]
+    async fn register_attaches_declared_source_types_to_relayed_sources() {
+        // arrange
+        let subject = setup();
+        let source_type =
+            common::SourceType { value_type: "int32".to_owned(), schema: "".to_owned() };
+        let request = RegisterRequest {
+            intents: vec![IntentRegistration {
+                namespace: "test".to_owned(),
+                intent: intent_registration::Intent::Subscribe as i32,
+                deprecation: None,
+                fallbacks: Default::default(),
+                source_types: [("Vehicle.Speed".to_owned(), source_type.clone())].into(),
+            }],
+            ..create_register_request()
+        };
+
+        // act
+        subject.register(Request::new(request)).await.unwrap();
+
+        // assert
+        let ess = subject.broker.streaming_ess();
+        assert_eq!(Some(source_type), ess.source_type("test/Vehicle.Speed"));
+        assert_eq!(None, ess.source_type("test/Vehicle.Cabin"));
+    }
+
+    #[tokio::test]
+    async fn fulfill_with_idempotency_key_returns_same_result_for_duplicates() {
+        // arrange
+        let subject = setup();
+        let request = || {
+            let mut request = Request::new(FulfillRequest {
+                namespace: "system".to_owned(),
+                intent: Some(create_fulfill()),
+            });
+            request.metadata_mut().insert("x-chariott-idempotency-key", "key".parse().unwrap());
+            request
+        };
+
+        for _ in 0..2 {
+            // act
+            let result = subject.fulfill(request()).await;
+
+            // assert
+            assert_eq!(
+                MockBroker::RETURN_VALUE,
+                TestBinding::parse_result(result.map(|r| r.into_inner().fulfillment.unwrap()))
+                    .unwrap()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn fulfill_returns_correlation_id_with_response_and_error() {
+        // arrange
+        let subject = setup();
+        let mut request = Request::new(FulfillRequest {
+            namespace: "system".to_owned(),
+            intent: Some(create_fulfill()),
+        });
+        request.metadata_mut().insert("x-chariott-correlation-id", "request-1".parse().unwrap());
+
+        // act
+        let response = subject.fulfill(request).await.unwrap();
+        let error = subject
+            .fulfill(Request::new(FulfillRequest { namespace: "system".to_owned(), intent: None }))
+            .await
+            .unwrap_err();
+
+        // assert
+        assert_eq!(
+            "request-1",
+            response.metadata().get("x-chariott-correlation-id").unwrap().to_str().unwrap()
+        );
+        assert!(error.metadata().get("x-chariott-correlation-id").is_some());
+    }
+
+    #[tokio::test]
+    async fn fulfill_annotates_timings_if_latency_budgets_set() {
+        // arrange
+        let subject = setup().with_latency_budgets(latency::Budgets::parse("system=0").unwrap());
+        let request = || {
+            Request::new(FulfillRequest {
+                namespace: "system".to_owned(),
+                intent: Some(create_fulfill()),
+            })
+        };
+
+        // act
+        let response = subject.fulfill(request()).await.unwrap();
+        let unannotated = setup().fulfill(request()).await.unwrap();
+
+        // assert
+        let timing = response.metadata().get(latency::TIMING_METADATA_KEY).unwrap();
+        let hops = timing
+            .to_str()
+            .unwrap()
+            .split(',')
+            .map(|hop| hop.split_once('=').unwrap().0)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["received", "provider_start", "provider_end", "sent"], hops);
+        assert!(unannotated.metadata().get(latency::TIMING_METADATA_KEY).is_none());
+    }
+
     #[tokio::test]
     async fn fulfill_returns_error_if_intent_not_set() {
         // arrange
@@ -365,6 +2039,130 @@ mod tests {
         assert_eq!(Code::InvalidArgument, result.unwrap_err().code());
     }
 
+    #[tokio::test]
+    async fn fulfill_returns_error_if_write_batch_is_empty() {
+        // arrange
+        let subject = setup();
+        let intent = common::Intent {
+            intent: Some(common::intent::Intent::WriteBatch(common::WriteBatchIntent {
+                writes: vec![],
+            })),
+        };
+
+        // act
+        let result = subject
+            .fulfill(Request::new(FulfillRequest {
+                namespace: "system".to_owned(),
+                intent: Some(intent),
+            }))
+            .await;
+
+        // assert
+        assert_eq!(Code::InvalidArgument, result.unwrap_err().code());
+    }
+
+    #[tokio::test]
+    async fn fulfill_returns_error_if_write_batch_contains_duplicate_keys() {
+        // arrange
+        let subject = setup();
+        let write = |key: &str| common::WriteIntent {
+            key: key.to_owned(),
+            value: Some(common::Value { value: Some(common::value::Value::Int32(1)) }),
+            precondition: None,
+        };
+        let intent = common::Intent {
+            intent: Some(common::intent::Intent::WriteBatch(common::WriteBatchIntent {
+                writes: vec![write("a"), write("b"), write("a")],
+            })),
+        };
+
+        // act
+        let result = subject
+            .fulfill(Request::new(FulfillRequest {
+                namespace: "system".to_owned(),
+                intent: Some(intent),
+            }))
+            .await;
+
+        // assert
+        let status = result.unwrap_err();
+        assert_eq!(Code::InvalidArgument, status.code());
+        assert!(status.message().contains("'a'"));
+    }
+
+    #[test_case(vec![], Code::InvalidArgument ; "when empty")]
+    #[test_case(vec![("system", create_read())], Code::InvalidArgument ; "when not write or invoke")]
+    #[test_case(vec![("unknown", create_fulfill())], Code::NotFound ; "when provider not found")]
+    #[test_case(vec![("system", create_fulfill())], Code::FailedPrecondition ; "when provider not transactional")]
+    #[tokio::test]
+    async fn fulfill_transaction_returns_error(
+        operations: Vec<(&str, common::Intent)>,
+        expected: Code,
+    ) {
+        // arrange
+        let subject = setup();
+        let operations = operations
+            .into_iter()
+            .map(|(namespace, intent)| FulfillRequest {
+                namespace: namespace.to_owned(),
+                intent: Some(intent),
+            })
+            .collect();
+
+        // act
+        let result = subject
+            .fulfill_transaction(Request::new(FulfillTransactionRequest { operations }))
+            .await;
+
+        // assert
+        assert_eq!(expected, result.unwrap_err().code());
+    }
+
+    #[tokio::test]
+    async fn forward_upstream_suppresses_duplicate_requests_with_idempotency_key() {
+        // arrange
+        let (upstream, received) = upstream::tests::serve_upstream().await;
+        let subject = setup();
+        let config = IntentConfiguration::new("sdv.kvs", IntentKind::Write);
+        let mut metadata = MetadataMap::new();
+        metadata.insert(IDEMPOTENCY_KEY_METADATA_KEY, "key".parse().unwrap());
+        let write = || common::Intent {
+            intent: Some(common::intent::Intent::Write(common::WriteIntent {
+                key: "door".to_owned(),
+                value: Some(common::Value { value: Some(common::value::Value::Bool(true)) }),
+                precondition: None,
+            })),
+        };
+
+        // act
+        let first = subject.forward_upstream(&upstream, &config, &metadata, write()).await;
+        let duplicate = subject.forward_upstream(&upstream, &config, &metadata, write()).await;
+
+        // assert
+        assert!(first.as_ref().unwrap().is_some());
+        assert_eq!(first.unwrap(), duplicate.unwrap());
+        assert_eq!(1, received.lock().unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn fulfill_transaction_accounts_request_of_caller() {
+        // arrange
+        let subject = setup().with_accounting(Accounting::new(
+            StreamingEss::new(),
+            Limits::default().set_outstanding_requests(0),
+        ));
+        let operations =
+            vec![FulfillRequest { namespace: "system".to_owned(), intent: Some(create_fulfill()) }];
+
+        // act
+        let result = subject
+            .fulfill_transaction(Request::new(FulfillTransactionRequest { operations }))
+            .await;
+
+        // assert
+        assert_eq!(Code::ResourceExhausted, result.unwrap_err().code());
+    }
+
     #[test]
     fn test_map_intent_variant() {
         use common::intent::Intent;
@@ -382,6 +2180,7 @@ mod tests {
             IntentKind::Write => {}
             IntentKind::Invoke => {}
             IntentKind::Subscribe => {}
+            IntentKind::Delete => {}
         }
 
         // assert
@@ -389,15 +2188,27 @@ mod tests {
             (Intent::Discover(DiscoverIntent {}), IntentKind::Discover),
             (Intent::Inspect(InspectIntent { query: "".to_owned() }), IntentKind::Inspect),
             (Intent::Read(ReadIntent { key: "".to_owned() }), IntentKind::Read),
-            (Intent::Write(WriteIntent { key: "".to_owned(), value: None }), IntentKind::Write),
+            (
+                Intent::Write(WriteIntent { key: "".to_owned(), value: None, precondition: None }),
+                IntentKind::Write,
+            ),
+            (Intent::WriteBatch(WriteBatchIntent { writes: vec![] }), IntentKind::Write),
             (
                 Intent::Invoke(InvokeIntent { command: "".to_owned(), args: vec![] }),
                 IntentKind::Invoke,
             ),
             (
-                Intent::Subscribe(SubscribeIntent { channel_id: "".to_owned(), sources: vec![] }),
+                Intent::Subscribe(SubscribeIntent {
+                    channel_id: "".to_owned(),
+                    sources: vec![],
+                    ..Default::default()
+                }),
                 IntentKind::Subscribe,
             ),
+            (
+                Intent::Delete(DeleteIntent { key: "".to_owned(), precondition: None }),
+                IntentKind::Delete,
+            ),
         ] {
             assert_eq!(
                 expected,
@@ -411,11 +2222,51 @@ mod tests {
     impl MockBroker {
         const RETURN_VALUE: i32 = 10;
 
-        pub fn resolve(&self, _: &IntentConfiguration) -> Option<RuntimeBinding<GrpcProvider>> {
-            Some(RuntimeBinding::Test(TestBinding::new(
-                Ok(Self::RETURN_VALUE),
-                Some(create_fulfill().intent.unwrap()),
-            )))
+        pub fn resolve_participant(
+            &self,
+            intent: &IntentConfiguration,
+        ) -> Option<Participant<ReusableProvider<GrpcProvider>>> {
+            match intent.namespace() {
+                "system" => Some(Participant::new(
+                    "http://system".parse().unwrap(), // DevSkim: ignore DS137138
+                    ReusableProvider::new("http://system".parse().unwrap()), // DevSkim: ignore DS137138
+                    false,
+                )),
+                _ => None,
+            }
+        }
+
+        pub fn resolve_at(
+            &self,
+            intent: &IntentConfiguration,
+            _: &Url,
+        ) -> Option<RuntimeBinding<GrpcProvider>> {
+            self.resolve(intent)
+        }
+
+        pub fn resolve(
+            &self,
+            intent: &IntentConfiguration,
+        ) -> Option<RuntimeBinding<GrpcProvider>> {
+            match intent.namespace() {
+                "unreachable" => {
+                    Some(RuntimeBinding::Test(TestBinding::from_result(Err(Code::Unavailable))))
+                }
+                "sdv.kvs" => {
+                    Some(RuntimeBinding::Test(TestBinding::from_result(Ok(Self::RETURN_VALUE))))
+                }
+                "system.registry" => Some(RuntimeBinding::SystemInspect(
+                    vec![
+                        IntentConfiguration::new("sdv.vdt", IntentKind::Invoke),
+                        IntentConfiguration::new("system.info", IntentKind::Read),
+                    ],
+                    HashMap::new(),
+                )),
+                _ => Some(RuntimeBinding::Test(TestBinding::new(
+                    Ok(Self::RETURN_VALUE),
+                    Some(create_fulfill().intent.unwrap()),
+                ))),
+            }
         }
     }
 
@@ -434,6 +2285,285 @@ mod tests {
         }
     }
 
+    fn create_read() -> common::Intent {
+        common::Intent {
+            intent: Some(common::intent::Intent::Read(common::ReadIntent {
+                key: "test".to_owned(),
+            })),
+        }
+    }
+
+    #[tokio::test]
+    async fn import_registry_restores_exported_registry() {
+        // arrange
+        let source = setup();
+        _ = source.register(Request::new(create_register_request())).await.unwrap();
+        let snapshot = source
+            .export_registry(Request::new(ExportRegistryRequest {}))
+            .await
+            .unwrap()
+            .into_inner()
+            .snapshot;
+        let subject = setup();
+
+        // act
+        let result =
+            subject.import_registry(Request::new(ImportRegistryRequest { snapshot })).await;
+
+        // assert
+        assert!(result.is_ok());
+        assert!(subject.registry_do(|registry| registry.serves("foo", &[IntentKind::Discover])));
+    }
+
+    #[tokio::test]
+    async fn import_registry_fails_for_invalid_snapshot() {
+        // arrange
+        let subject = setup();
+
+        // act
+        let result = subject
+            .import_registry(Request::new(ImportRegistryRequest { snapshot: "{}".to_owned() }))
+            .await;
+
+        // assert
+        assert_eq!(Code::InvalidArgument, result.unwrap_err().code());
+    }
+
+    #[tokio::test]
+    async fn import_registry_fails_for_blocked_url() {
+        // arrange
+        let source = setup();
+        _ = source.register(Request::new(create_register_request())).await.unwrap();
+        let snapshot = source
+            .export_registry(Request::new(ExportRegistryRequest {}))
+            .await
+            .unwrap()
+            .into_inner()
+            .snapshot
+            .replace("test.com", "169.254.169.254");
+        let subject = setup();
+
+        // act
+        let result =
+            subject.import_registry(Request::new(ImportRegistryRequest { snapshot })).await;
+
+        // assert
+        assert_eq!(Code::InvalidArgument, result.unwrap_err().code());
+        assert!(!subject.registry_do(|registry| registry.serves("foo", &[IntentKind::Discover])));
+    }
+
+    #[tokio::test]
+    async fn export_and_import_registry_require_admin_intents() {
+        // arrange
+        let file = TempFile::new(
+            r#"{ "identities": [], "anonymous": [{ "namespace": "system.admin", "intents": ["read"] }] }"#,
+        );
+        let subject = setup().with_acl(Acl::load(file.path()).unwrap());
+
+        // act
+        let export = subject.export_registry(Request::new(ExportRegistryRequest {})).await;
+        let import = subject
+            .import_registry(Request::new(ImportRegistryRequest {
+                snapshot: export.as_ref().unwrap().get_ref().snapshot.clone(),
+            }))
+            .await;
+
+        // assert
+        assert!(export.is_ok());
+        assert_eq!(Code::PermissionDenied, import.unwrap_err().code());
+    }
+
+    #[tokio::test]
+    async fn compact_registry_keeps_registrations_of_known_services() {
+        // arrange
+        let subject = setup();
+        _ = subject.register(Request::new(create_register_request())).await.unwrap();
+
+        // act
+        let result = subject.compact_registry(Request::new(CompactRegistryRequest {})).await;
+
+        // assert
+        assert_eq!(CompactRegistryResponse::default(), result.unwrap().into_inner());
+        assert!(subject.registry_do(|registry| registry.serves("foo", &[IntentKind::Discover])));
+    }
+
+    #[tokio::test]
+    async fn compact_registry_requires_admin_write_intent() {
+        // arrange
+        let file = TempFile::new(
+            r#"{ "identities": [], "anonymous": [{ "namespace": "system.admin", "intents": ["read"] }] }"#,
+        );
+        let subject = setup().with_acl(Acl::load(file.path()).unwrap());
+
+        // act
+        let result = subject.compact_registry(Request::new(CompactRegistryRequest {})).await;
+
+        // assert
+        assert_eq!(Code::PermissionDenied, result.unwrap_err().code());
+    }
+
+    #[tokio::test]
+    async fn resolve_intent_returns_candidates_with_role() {
+        // arrange
+        let subject = setup();
+        _ = subject.register(Request::new(create_register_request())).await.unwrap();
+        let request = |namespace: &str| {
+            Request::new(ResolveIntentRequest {
+                namespace: namespace.to_owned(),
+                intent: intent_registration::Intent::Discover as i32,
+            })
+        };
+
+        // act
+        let result = subject.resolve_intent(request("foo")).await;
+        let unknown = subject.resolve_intent(request("unknown")).await;
+
+        // assert
+        let candidates = result.unwrap().into_inner().candidates;
+        assert_eq!(1, candidates.len());
+        let expected = create_register_request().service.unwrap();
+        let actual = candidates[0].service.clone().unwrap();
+        assert_eq!(expected.name, actual.name);
+        // The URL of the service is normalized when it is registered.
+        assert_eq!(expected.url.parse::<Url>().unwrap().as_str(), actual.url);
+        assert_eq!(CandidateRoleMessage::Primary as i32, candidates[0].role);
+        assert_eq!(Code::NotFound, unknown.unwrap_err().code());
+    }
+
+    fn create_override(namespace: &str) -> IntentOverride {
+        IntentOverride {
+            namespace: namespace.to_owned(),
+            intent: intent_registration::Intent::Read as i32,
+            target: Some(OverrideTarget::Error(OverrideError {
+                code: Code::Unavailable as i32,
+                message: "simulated".to_owned(),
+            })),
+            ttl: Some(prost_types::Duration { seconds: 60, nanos: 0 }),
+        }
+    }
+
+    #[tokio::test]
+    async fn set_override_fails_if_overrides_are_not_enabled() {
+        // arrange
+        let subject = setup();
+
+        // act
+        let result = subject
+            .set_override(Request::new(SetOverrideRequest { entry: Some(create_override("sdv")) }))
+            .await;
+
+        // assert
+        assert_eq!(Code::FailedPrecondition, result.unwrap_err().code());
+    }
+
+    #[tokio::test]
+    async fn set_override_is_listed_until_cleared() {
+        // arrange
+        let subject = setup().with_overrides();
+        let clear = || {
+            Request::new(ClearOverrideRequest {
+                namespace: "sdv".to_owned(),
+                intent: intent_registration::Intent::Read as i32,
+            })
+        };
+
+        // act
+        subject
+            .set_override(Request::new(SetOverrideRequest { entry: Some(create_override("sdv")) }))
+            .await
+            .unwrap();
+        let listed = subject.list_overrides(Request::new(ListOverridesRequest {})).await.unwrap();
+        let cleared = subject.clear_override(clear()).await.unwrap();
+        let again = subject.clear_override(clear()).await.unwrap();
+
+        // assert
+        let entries = listed.into_inner().entries;
+        assert_eq!(1, entries.len());
+        assert_eq!(create_override("sdv").target, entries[0].target);
+        assert!(entries[0].ttl.as_ref().unwrap().seconds <= 60);
+        assert!(cleared.into_inner().cleared);
+        assert!(!again.into_inner().cleared);
+    }
+
+    #[test_case(IntentOverride { ttl: None, ..create_override("sdv") }; "without ttl")]
+    #[test_case(IntentOverride { target: None, ..create_override("sdv") }; "without target")]
+    #[test_case(create_override("system.registry"); "of system namespace")]
+    #[test_case(
+        IntentOverride {
+            target: Some(OverrideTarget::ProviderUrl("http://169.254.169.254".to_owned())), // DevSkim: ignore DS137138
+            ..create_override("sdv")
+        };
+        "with blocked fixture url"
+    )]
+    #[tokio::test]
+    async fn set_override_fails_for_invalid_override(entry: IntentOverride) {
+        // arrange
+        let subject = setup().with_overrides();
+
+        // act
+        let result =
+            subject.set_override(Request::new(SetOverrideRequest { entry: Some(entry) })).await;
+
+        // assert
+        assert_eq!(Code::InvalidArgument, result.unwrap_err().code());
+    }
+
+    #[tokio::test]
+    async fn wait_for_service_completes_once_namespace_is_registered() {
+        // arrange
+        let server = setup();
+        let request = WaitForServiceRequest {
+            namespace: "foo".to_owned(),
+            intents: vec![intent_registration::Intent::Discover as i32],
+            timeout: Some(Duration::from_secs(5).try_into().unwrap()),
+        };
+
+        // act
+        let (result, _) = tokio::join!(server.wait_for_service(Request::new(request)), async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            server.register(Request::new(create_register_request())).await.unwrap()
+        });
+
+        // assert
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn wait_for_service_completes_if_namespace_is_served() {
+        // arrange
+        let server = setup();
+        _ = server.register(Request::new(create_register_request())).await.unwrap();
+
+        // act
+        let result = server
+            .wait_for_service(Request::new(WaitForServiceRequest {
+                namespace: "bar".to_owned(),
+                ..Default::default()
+            }))
+            .await;
+
+        // assert
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn wait_for_service_fails_after_timeout() {
+        // arrange
+        let server = setup();
+        _ = server.register(Request::new(create_register_request())).await.unwrap();
+        let request = WaitForServiceRequest {
+            namespace: "foo".to_owned(),
+            intents: vec![intent_registration::Intent::Read as i32],
+            timeout: Some(Duration::from_millis(10).try_into().unwrap()),
+        };
+
+        // act
+        let result = server.wait_for_service(Request::new(request)).await;
+
+        // assert
+        assert_eq!(Code::DeadlineExceeded, result.unwrap_err().code());
+    }
+
     fn setup() -> IntentBrokeringServer<IntentBroker> {
         let broker =
             IntentBroker::new("https://localhost:4243".parse().unwrap(), StreamingEss::new()); // DevSkim: ignore DS162092
@@ -447,7 +2577,10 @@ mod tests {
                 version: "1.0".to_string(),
                 url: "http://test.com".to_string(), // DevSkim: ignore DS137138
                 locality: ExecutionLocality::Local as i32,
+                transactional: false,
+                max_concurrency: 0,
             }),
+            fetch_registration: false,
         }
     }
 
@@ -458,15 +2591,23 @@ mod tests {
                 version: "1.0".to_string(),
                 url: "http://test.com".to_string(), // DevSkim: ignore DS137138
                 locality: ExecutionLocality::Local as i32,
+                transactional: false,
+                max_concurrency: 0,
             }),
             intents: vec![
                 IntentRegistration {
                     namespace: "foo".to_string(),
                     intent: intent_registration::Intent::Discover as i32,
+                    deprecation: None,
+                    fallbacks: Default::default(),
+                    source_types: Default::default(),
                 },
                 IntentRegistration {
                     namespace: "bar".to_string(),
                     intent: intent_registration::Intent::Discover as i32,
+                    deprecation: None,
+                    fallbacks: Default::default(),
+                    source_types: Default::default(),
                 },
             ],
         }
@@ -479,19 +2620,30 @@ mod tests {
                 version: "1.0".to_string(),
                 url: "http://test.com".to_string(), // DevSkim: ignore DS137138
                 locality: ExecutionLocality::Local as i32,
+                transactional: false,
+                max_concurrency: 0,
             }),
             intents: vec![
                 IntentRegistration {
                     namespace: "foo".to_string(),
                     intent: intent_registration::Intent::Discover as i32,
+                    deprecation: None,
+                    fallbacks: Default::default(),
+                    source_types: Default::default(),
                 },
                 IntentRegistration {
                     namespace: "bar".to_string(),
                     intent: intent_registration::Intent::Discover as i32,
+                    deprecation: None,
+                    fallbacks: Default::default(),
+                    source_types: Default::default(),
                 },
                 IntentRegistration {
                     namespace: "baz".to_string(),
                     intent: intent_registration::Intent::Discover as i32,
+                    deprecation: None,
+                    fallbacks: Default::default(),
+                    source_types: Default::default(),
                 },
             ],
         }
@@ -0,0 +1,197 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Measures the latency of fulfilling intents per hop and enforces latency
+//! budgets per namespace.
+//!
+//! The Intent Broker timestamps when it received a request, when it started
+//! and finished calling the provider, and when it sent the response. The
+//! timestamps are annotated in the `x-chariott-timing` metadata of the
+//! response, as comma-separated `{hop}={microseconds since the Unix epoch}`
+//! pairs, such that callers can tell the time spent in the broker from the
+//! time spent in the provider. If the total latency exceeds the budget of the
+//! namespace, a warning is logged with the time spent per hop.
+
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use intent_brokering_common::error::Error;
+use tonic::metadata::{MetadataMap, MetadataValue};
+
+use crate::namespace::Durations;
+
+pub const TIMING_METADATA_KEY: &str = "x-chariott-timing";
+
+/// The latency budgets of namespaces. A namespace also covers its
+/// sub-namespaces, e.g. `sdv` covers `sdv.vdt`, and the budget of the most
+/// specific namespace applies.
+#[derive(Debug, Clone, Default)]
+pub struct Budgets(Durations);
+
+impl Budgets {
+    pub fn new(budgets: impl IntoIterator<Item = (Box<str>, Duration)>) -> Self {
+        Self(Durations::new(budgets))
+    }
+
+    /// Parses a comma-separated list of namespaces and their budgets in
+    /// milliseconds, e.g. `sdv.vdt=50,sdv=200`.
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        Durations::parse(value, "Latency budget").map(Self)
+    }
+
+    /// Returns the budget of the most specific namespace covering the given
+    /// one, if any.
+    pub fn budget(&self, namespace: &str) -> Option<Duration> {
+        self.0.get(namespace)
+    }
+
+    /// Logs a warning if fulfilling an intent of the namespace took longer
+    /// than its budget, returning whether it did.
+    pub(crate) fn check(&self, namespace: &str, timings: &Timings) -> bool {
+        let (Some(budget), Some(total)) = (self.budget(namespace), timings.total()) else {
+            return false;
+        };
+
+        if total <= budget {
+            return false;
+        }
+
+        tracing::warn!(
+            target: "latency",
+            namespace,
+            budget_ms = budget.as_millis() as u64,
+            total_ms = total.as_millis() as u64,
+            provider_ms = timings.provider().map(|provider| provider.as_millis() as u64),
+            "Fulfilling intent exceeded the latency budget of the namespace."
+        );
+
+        true
+    }
+}
+
+/// The timestamps of the hops of a request through the Intent Broker.
+#[derive(Debug)]
+pub(crate) struct Timings {
+    received: SystemTime,
+    provider_started: OnceLock<SystemTime>,
+    provider_ended: OnceLock<SystemTime>,
+    sent: OnceLock<SystemTime>,
+}
+
+impl Timings {
+    /// Starts timing a request, which was received now.
+    pub fn start() -> Self {
+        Self {
+            received: SystemTime::now(),
+            provider_started: OnceLock::new(),
+            provider_ended: OnceLock::new(),
+            sent: OnceLock::new(),
+        }
+    }
+
+    pub fn provider_started(&self) {
+        _ = self.provider_started.set(SystemTime::now());
+    }
+
+    pub fn provider_ended(&self) {
+        _ = self.provider_ended.set(SystemTime::now());
+    }
+
+    pub fn sent(&self) {
+        _ = self.sent.set(SystemTime::now());
+    }
+
+    /// The time from receiving the request until sending the response.
+    pub fn total(&self) -> Option<Duration> {
+        self.sent.get().and_then(|sent| sent.duration_since(self.received).ok())
+    }
+
+    /// The time spent calling the provider.
+    pub fn provider(&self) -> Option<Duration> {
+        let (started, ended) = (self.provider_started.get()?, self.provider_ended.get()?);
+        ended.duration_since(*started).ok()
+    }
+
+    /// Annotates the timestamps of the hops passed so far in the metadata of
+    /// a response.
+    pub fn annotate(&self, metadata: &mut MetadataMap) {
+        let value = [
+            ("received", Some(&self.received)),
+            ("provider_start", self.provider_started.get()),
+            ("provider_end", self.provider_ended.get()),
+            ("sent", self.sent.get()),
+        ]
+        .into_iter()
+        .filter_map(|(hop, time)| {
+            let micros = time?.duration_since(UNIX_EPOCH).ok()?.as_micros();
+            Some(format!("{hop}={micros}"))
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+
+        if let Ok(value) = MetadataValue::try_from(value) {
+            metadata.insert(TIMING_METADATA_KEY, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tonic::metadata::MetadataMap;
+
+    use super::{Budgets, Timings, TIMING_METADATA_KEY};
+
+    #[test]
+    fn budget_is_of_most_specific_namespace() {
+        // arrange
+        let subject = Budgets::parse("sdv=200, sdv.vdt=50").unwrap();
+
+        // act + assert
+        assert_eq!(Some(Duration::from_millis(50)), subject.budget("sdv.vdt"));
+        assert_eq!(Some(Duration::from_millis(50)), subject.budget("sdv.vdt.cabin"));
+        assert_eq!(Some(Duration::from_millis(200)), subject.budget("sdv.kvs"));
+        assert_eq!(None, subject.budget("sdvx"));
+        assert_eq!(None, subject.budget("system"));
+    }
+
+    #[test]
+    fn parse_fails_for_malformed_budget() {
+        assert!(Budgets::parse("sdv.vdt").is_err());
+        assert!(Budgets::parse("sdv.vdt=fast").is_err());
+        assert!(Budgets::parse("").unwrap().budget("sdv").is_none());
+    }
+
+    #[test]
+    fn check_is_true_if_budget_exceeded() {
+        // arrange
+        let subject = Budgets::parse("sdv=0").unwrap();
+        let timings = Timings::start();
+        std::thread::sleep(Duration::from_millis(2));
+        timings.sent();
+
+        // act + assert
+        assert!(subject.check("sdv.vdt", &timings));
+        assert!(!subject.check("system", &timings));
+        assert!(!Budgets::parse("sdv=60000").unwrap().check("sdv.vdt", &timings));
+    }
+
+    #[test]
+    fn annotate_writes_hops_passed() {
+        // arrange
+        let subject = Timings::start();
+        subject.provider_started();
+        subject.provider_ended();
+        let mut metadata = MetadataMap::new();
+
+        // act
+        subject.annotate(&mut metadata);
+
+        // assert
+        let value = metadata.get(TIMING_METADATA_KEY).unwrap().to_str().unwrap();
+        let hops = value.split(',').map(|hop| hop.split_once('=').unwrap().0).collect::<Vec<_>>();
+        assert_eq!(vec!["received", "provider_start", "provider_end"], hops);
+    }
+}
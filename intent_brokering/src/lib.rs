@@ -2,10 +2,42 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
+pub mod accounting;
+pub mod acl;
+pub mod activation;
+pub mod admission;
+pub mod bridge;
+pub mod chaos;
+pub mod concurrency;
 mod connection_provider;
+mod correlation;
 mod execution;
+pub mod federation;
+pub mod fulfillment_log;
+pub mod grpc_web;
+pub mod idempotency;
+pub mod identity;
 mod intent_broker;
 pub mod intent_brokering_grpc;
+pub mod latency;
 pub use intent_broker::IntentBroker;
+pub mod liveness;
+pub mod namespace;
+pub mod operation;
+pub mod overrides;
+pub mod provider_url;
+pub mod registration_signature;
 pub mod registry;
+pub mod response_limits;
+pub mod restart_queue;
+pub mod session;
 pub mod streaming;
+pub mod system;
+pub mod systemd;
+mod transaction;
+pub mod transform;
+pub mod upstream;
+pub mod validation;
+#[cfg(feature = "webhooks")]
+pub mod webhook;
+pub mod write_events;
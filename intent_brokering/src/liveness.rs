@@ -0,0 +1,172 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Probes the registered providers and tracks which of them are unreachable,
+//! so that they can be deregistered before their registration expires.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use tonic::transport::Endpoint;
+use url::Url;
+
+use crate::registry::ServiceConfiguration;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    interval: Duration,
+    failure_threshold: u32,
+}
+
+impl Config {
+    pub const INTERVAL_MIN: Duration = Duration::from_secs(1);
+    pub const PROBE_TIMEOUT_MAX: Duration = Duration::from_secs(1);
+
+    pub fn new(interval: Duration) -> Self {
+        Self { interval: std::cmp::max(interval, Self::INTERVAL_MIN), failure_threshold: 3 }
+    }
+
+    /// The interval in which all registered providers are probed.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// The duration after which a probe fails if the provider did not
+    /// respond.
+    pub fn probe_timeout(&self) -> Duration {
+        std::cmp::min(self.interval, Self::PROBE_TIMEOUT_MAX)
+    }
+
+    /// The number of consecutive failed probes after which a provider is
+    /// unreachable.
+    pub fn failure_threshold(&self) -> u32 {
+        self.failure_threshold
+    }
+
+    pub fn set_failure_threshold_bounded(self, value: u32) -> Self {
+        Self { failure_threshold: std::cmp::max(value, 1), ..self }
+    }
+}
+
+/// Counts the consecutive failed probes of each provider.
+pub struct Liveness {
+    config: Config,
+    failures: HashMap<ServiceConfiguration, u32>,
+}
+
+impl Liveness {
+    pub fn new(config: Config) -> Self {
+        Self { config, failures: HashMap::new() }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Records the results of probing the registered providers and returns
+    /// the providers which became unreachable. Providers which were not
+    /// probed, e.g. because they are no longer registered, are forgotten.
+    pub fn record(
+        &mut self,
+        results: impl IntoIterator<Item = (ServiceConfiguration, bool)>,
+    ) -> Vec<ServiceConfiguration> {
+        let mut failures = HashMap::new();
+        let mut unreachable = Vec::new();
+
+        for (service, reachable) in results {
+            if reachable {
+                continue;
+            }
+
+            let count = self.failures.get(&service).copied().unwrap_or_default() + 1;
+            if count >= self.config.failure_threshold {
+                unreachable.push(service);
+            } else {
+                failures.insert(service, count);
+            }
+        }
+
+        self.failures = failures;
+        unreachable
+    }
+}
+
+/// Probes a provider by establishing a connection with its endpoint.
+pub async fn probe(url: &Url, timeout: Duration) -> bool {
+    match Endpoint::from_shared(url.to_string()) {
+        Ok(endpoint) => endpoint.connect_timeout(timeout).timeout(timeout).connect().await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::registry::tests::ServiceConfigurationBuilder;
+
+    use super::{Config, Liveness};
+
+    fn liveness(failure_threshold: u32) -> Liveness {
+        Liveness::new(
+            Config::new(Duration::from_secs(5)).set_failure_threshold_bounded(failure_threshold),
+        )
+    }
+
+    #[test]
+    fn config_bounds_interval_and_failure_threshold() {
+        // act
+        let config = Config::new(Duration::ZERO).set_failure_threshold_bounded(0);
+
+        // assert
+        assert_eq!(Config::INTERVAL_MIN, config.interval());
+        assert_eq!(1, config.failure_threshold());
+    }
+
+    #[test]
+    fn record_returns_service_after_consecutive_failures() {
+        // arrange
+        let service = ServiceConfigurationBuilder::new().build();
+        let mut subject = liveness(2);
+
+        // act
+        let first = subject.record([(service.clone(), false)]);
+        let second = subject.record([(service.clone(), false)]);
+
+        // assert
+        assert!(first.is_empty());
+        assert_eq!(vec![service], second);
+    }
+
+    #[test]
+    fn record_resets_failures_when_service_is_reachable() {
+        // arrange
+        let service = ServiceConfigurationBuilder::new().build();
+        let mut subject = liveness(2);
+
+        // act
+        let mut result = subject.record([(service.clone(), false)]);
+        result.extend(subject.record([(service.clone(), true)]));
+        result.extend(subject.record([(service, false)]));
+
+        // assert
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn record_forgets_services_which_were_not_probed() {
+        // arrange
+        let service = ServiceConfigurationBuilder::new().build();
+        let other = ServiceConfigurationBuilder::with_nonce("1").build();
+        let mut subject = liveness(2);
+        _ = subject.record([(service.clone(), false)]);
+
+        // act
+        _ = subject.record([(other, false)]);
+        let result = subject.record([(service, false)]);
+
+        // assert
+        assert!(result.is_empty());
+    }
+}
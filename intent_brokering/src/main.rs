@@ -2,27 +2,61 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
+use intent_brokering::accounting::{self, Accounting};
+use intent_brokering::acl::Acl;
+use intent_brokering::activation::Activation;
+use intent_brokering::admission;
+use intent_brokering::bridge::Bridges;
+use intent_brokering::chaos::Chaos;
+use intent_brokering::federation::{self, Federation};
+use intent_brokering::fulfillment_log::{self, FulfillmentLog};
+use intent_brokering::grpc_web::{self, AllowedOrigins};
+use intent_brokering::idempotency;
+use intent_brokering::identity::Extractors;
 use intent_brokering::intent_brokering_grpc::IntentBrokeringServer;
+use intent_brokering::latency;
+use intent_brokering::liveness::{self, Liveness};
+use intent_brokering::registration_signature::ProtectedNamespaces;
 use intent_brokering::registry::{self, Registry};
+use intent_brokering::response_limits;
+use intent_brokering::restart_queue;
+use intent_brokering::session;
 use intent_brokering::streaming::StreamingEss;
+use intent_brokering::system::{LogSettings, ResourceAccounting, RuntimeInfo};
+use intent_brokering::systemd;
+use intent_brokering::transform::Transforms;
+use intent_brokering::upstream::{self, Upstream};
+#[cfg(feature = "webhooks")]
+use intent_brokering::webhook::Webhooks;
 use intent_brokering::IntentBroker;
 use intent_brokering_common::config::{env, try_env};
 use intent_brokering_common::ext::OptionExt as _;
 use intent_brokering_common::shutdown::{ctrl_c_cancellation, RouterExt as _};
+use intent_brokering_common::storage::{FileStorage, Storage};
 use intent_brokering_proto::{
     runtime::intent_brokering_service_server::IntentBrokeringServiceServer,
     streaming::channel_service_server::ChannelServiceServer,
 };
 use registry::Composite;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::{select, time::sleep_until, time::Instant as TokioInstant};
+use tokio::net::TcpListener;
+use tokio::task::JoinSet;
+use tokio::{select, time::sleep, time::sleep_until, time::Instant as TokioInstant};
 use tokio_util::sync::CancellationToken;
 use tonic::transport::Server;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
+#[cfg(feature = "webhooks")]
+use url::Url;
 
-#[cfg(build = "debug")]
+#[cfg(feature = "webhooks")]
+type Observers = Composite<Composite<IntentBroker, StreamingEss>, Webhooks>;
+#[cfg(not(feature = "webhooks"))]
+type Observers = Composite<IntentBroker, StreamingEss>;
+
+#[cfg(all(build = "debug", feature = "reflection"))]
 pub(crate) const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("descriptor");
 
 #[tokio::main]
@@ -30,18 +64,70 @@ pub(crate) const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     const EXTERNAL_HOST_NAME_ENV: &str = "EXTERNAL_HOST_NAME";
     const PORT: u16 = 4243;
+    const REGISTRY_COMPACTION_INTERVAL: Duration = Duration::from_secs(600);
 
-    let collector = tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::builder()
-                .with_default_directive(tracing::Level::INFO.into())
-                .from_env_lossy(),
-        )
-        .finish();
+    let env_filter =
+        EnvFilter::builder().with_default_directive(tracing::Level::INFO.into()).from_env_lossy();
+
+    // Log pipelines can index JSON logs by the fields of the current span,
+    // e.g. the correlation ID, namespace, intent and identity of a fulfillment.
+    match env::<String>("INTENT_BROKERING_LOG_FORMAT").as_deref() {
+        Some("json") => tracing_subscriber::fmt()
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .with_env_filter(env_filter)
+            .finish()
+            .init(),
+        Some("text") | None => {
+            tracing_subscriber::fmt().with_env_filter(env_filter).finish().init()
+        }
+        Some(format) => return Err(format!("Log format '{format}' is not known.").into()),
+    }
 
-    collector.init();
+    let mut ess_config = ess::Config::default();
+    if let Some(size) = env::<usize>("INTENT_BROKERING_CHANNEL_BUFFER_SIZE") {
+        ess_config.set_client_buffer_size(size);
+    }
+    if let Some(budget) = env::<usize>("INTENT_BROKERING_CHANNEL_BUFFER_BUDGET") {
+        ess_config.set_client_buffer_budget(budget);
+    }
+    // Sources are prioritized by prefix, e.g. `sdv.vdt/Vehicle.Powertrain=high,sdv.media=low`.
+    for rule in env::<String>("INTENT_BROKERING_SOURCE_PRIORITIES")
+        .iter()
+        .flat_map(|rules| rules.split(','))
+        .map(str::trim)
+        .filter(|rule| !rule.is_empty())
+    {
+        let (prefix, priority) =
+            match rule.split_once('=').map(|(prefix, priority)| (prefix, priority.trim())) {
+                Some((prefix, "high")) => (prefix, ess::Priority::High),
+                Some((prefix, "normal")) => (prefix, ess::Priority::Normal),
+                Some((prefix, "low")) => (prefix, ess::Priority::Low),
+                _ => {
+                    return Err(format!(
+                        "Source priority '{rule}' is not of the form 'prefix=high|normal|low'."
+                    )
+                    .into())
+                }
+            };
+        ess_config.set_source_priority(prefix.trim(), priority);
+    }
 
-    let streaming_ess = StreamingEss::new();
+    // The features enabled by the configuration are reported under `system.info`.
+    let mut features = Vec::new();
+
+    let streaming_ess = StreamingEss::new_with_config(ess_config);
+    let clock_skew_estimation =
+        env::<bool>("INTENT_BROKERING_CLOCK_SKEW_ESTIMATION").unwrap_or_default();
+    if clock_skew_estimation {
+        features.push("clock_skew_estimation");
+    }
+    let source_type_validation =
+        env::<bool>("INTENT_BROKERING_VALIDATE_SOURCE_TYPES").unwrap_or_default();
+    if source_type_validation {
+        features.push("source_type_validation");
+    }
     let broker = IntentBroker::new(
         format!(
             "http://{}:{}", // DevSkim: ignore DS137138
@@ -51,7 +137,50 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .parse()
         .unwrap(),
         streaming_ess.clone(),
-    );
+    )
+    .with_clock_skew_estimation(clock_skew_estimation)
+    .with_source_type_validation(source_type_validation);
+    let broker = match env::<u64>("INTENT_BROKERING_PROVIDER_QUEUE_TIMEOUT_MS") {
+        Some(timeout) => broker.with_provider_queue_timeout(Duration::from_millis(timeout)),
+        None => broker,
+    };
+    let broker = match env::<u64>("INTENT_BROKERING_PROVIDER_RE_RESOLUTION_SECS") {
+        Some(interval) => {
+            features.push("provider_re_resolution");
+            broker.with_provider_re_resolution(Duration::from_secs(interval))
+        }
+        None => broker,
+    };
+    let response_limits = match env::<String>("INTENT_BROKERING_MAX_RESPONSE_SIZES") {
+        Some(limits) => {
+            let mut config = response_limits::Config::parse(&limits)?;
+            if let Some(truncate) = env::<bool>("INTENT_BROKERING_TRUNCATE_INSPECT_RESPONSES") {
+                config = config.set_truncate_inspect(truncate);
+            }
+            Some(config)
+        }
+        None => None,
+    };
+    let broker = match response_limits.as_ref().and_then(|config| config.max_decoding_size()) {
+        Some(size) => broker.with_provider_max_response_size(size),
+        None => broker,
+    };
+
+    let mut limits = accounting::Limits::default();
+    if let Some(limit) = env::<usize>("INTENT_BROKERING_MAX_OUTSTANDING_REQUESTS") {
+        limits = limits.set_outstanding_requests(limit);
+    }
+    if let Some(limit) = env::<usize>("INTENT_BROKERING_MAX_CHANNELS") {
+        limits = limits.set_channels(limit);
+    }
+    if let Some(limit) = env::<usize>("INTENT_BROKERING_MAX_SUBSCRIPTIONS") {
+        limits = limits.set_subscriptions(limit);
+    }
+    if let Some(limit) = env::<usize>("INTENT_BROKERING_MAX_BUFFERED_EVENTS") {
+        limits = limits.set_buffered_events(limit);
+    }
+    let accounting = Accounting::new(streaming_ess.clone(), limits);
+    broker.mount(Arc::new(ResourceAccounting::new(accounting.clone())))?;
 
     let registry_config = try_env::<u64>("INTENT_BROKERING_REGISTRY_TTL_SECS")
         .ok()?
@@ -61,37 +190,316 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     tracing::debug!("Registry entry TTL = {} (seconds)", registry_config.entry_ttl().as_secs_f64());
 
-    let registry =
-        Registry::new(Composite::new(broker.clone(), streaming_ess.clone()), registry_config);
+    let liveness_config = env::<u64>("INTENT_BROKERING_PROBE_INTERVAL_SECS").map(|v| {
+        let config = liveness::Config::new(Duration::from_secs(v));
+        match env::<u32>("INTENT_BROKERING_PROBE_FAILURE_THRESHOLD") {
+            Some(threshold) => config.set_failure_threshold_bounded(threshold),
+            None => config,
+        }
+    });
+    if liveness_config.is_some() {
+        features.push("liveness");
+    }
+
+    let observers = Composite::new(broker.clone(), streaming_ess.clone());
+    #[cfg(feature = "webhooks")]
+    let observers = {
+        let webhook_urls = env::<String>("INTENT_BROKERING_WEBHOOK_URLS")
+            .iter()
+            .flat_map(|urls| urls.split(','))
+            .map(|url| url.trim().parse::<Url>())
+            .collect::<Result<Vec<_>, _>>()?;
+        if !webhook_urls.is_empty() {
+            features.push("webhooks");
+        }
+        let webhook_secret = env::<String>("INTENT_BROKERING_WEBHOOK_SECRET")
+            .map(|secret| secret.into_bytes().into());
+        Composite::new(observers, Webhooks::new(webhook_urls, webhook_secret))
+    };
+
+    let registry = Registry::new(observers, registry_config);
 
-    #[cfg(build = "debug")]
+    #[cfg(all(build = "debug", feature = "reflection"))]
     let reflection_service = tonic_reflection::server::Builder::configure()
         .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
         .build()?;
 
     tracing::info!("starting grpc services");
-    let addr = format!("0.0.0.0:{PORT}").parse().unwrap();
+    let addr: SocketAddr = format!("0.0.0.0:{PORT}").parse().unwrap();
     tracing::info!("Intent Broker listening on {addr}");
 
-    let server = Arc::new(IntentBrokeringServer::new(registry, broker));
+    // Browser-based clients use gRPC-Web, which is served over HTTP/1.1.
+    let allowed_origins = Arc::new(
+        env::<String>("INTENT_BROKERING_GRPC_WEB_ALLOWED_ORIGINS")
+            .map(|origins| AllowedOrigins::parse(&origins))
+            .unwrap_or_default(),
+    );
+
+    // Persisted state, e.g. registrations and idempotency keys, survives
+    // restarts when a storage directory is configured.
+    let storage = env::<String>("INTENT_BROKERING_STORAGE_PATH")
+        .map(|path| Arc::new(FileStorage::new(path)) as Arc<dyn Storage>);
+
+    let mut idempotency_config = idempotency::Config::default();
+    if let Some(ttl) = env::<u64>("INTENT_BROKERING_IDEMPOTENCY_TTL_SECS") {
+        idempotency_config = idempotency_config.set_ttl(Duration::from_secs(ttl));
+    }
+    if let Some(capacity) = env::<usize>("INTENT_BROKERING_IDEMPOTENCY_CAPACITY") {
+        idempotency_config = idempotency_config.set_capacity_bounded(capacity);
+    }
+    if let Some(storage) = &storage {
+        idempotency_config = idempotency_config.set_storage(Arc::clone(storage));
+        features.push("storage");
+    }
+
+    let mut server = IntentBrokeringServer::new(registry, broker.clone())
+        .with_idempotency(idempotency_config)
+        .with_accounting(accounting);
+    if let Some(rate) = env::<u32>("INTENT_BROKERING_REGISTRATION_RATE") {
+        let config = admission::Config::new(rate).set_priority_namespaces(
+            env::<String>("INTENT_BROKERING_PRIORITY_NAMESPACES")
+                .iter()
+                .flat_map(|namespaces| namespaces.split(','))
+                .map(|namespace| namespace.trim().into()),
+        );
+        server = server.with_admission(config);
+        features.push("admission");
+    }
+    if let Some(secrets) = env::<String>("INTENT_BROKERING_REGISTRATION_SECRETS") {
+        server = server.with_protected_namespaces(ProtectedNamespaces::parse(&secrets)?);
+        features.push("protected_namespaces");
+    }
+    let mut verified_identities = true;
+    if let Some(extractors) = env::<String>("INTENT_BROKERING_IDENTITY_EXTRACTORS") {
+        let extractors = Extractors::parse(&extractors)?;
+        verified_identities = extractors.verify();
+        server = server.with_identity(extractors);
+        features.push("identity");
+    }
+    if let Some(path) = env::<String>("INTENT_BROKERING_ACL_PATH") {
+        let mut acl = Acl::load(path)?;
+        if !verified_identities
+            && !env::<bool>("INTENT_BROKERING_TRUST_UNVERIFIED_IDENTITIES").unwrap_or_default()
+        {
+            acl = acl.without_named_identities()?;
+        }
+        tokio::spawn(acl.clone().watch()?);
+        server = server.with_acl(acl);
+        features.push("acl");
+    }
+    if env::<bool>("INTENT_BROKERING_RESOLVE_PROVIDER_URLS").unwrap_or_default() {
+        server = server.with_provider_url_resolution();
+        features.push("provider_url_resolution");
+    }
+    if env::<bool>("INTENT_BROKERING_OVERRIDES").unwrap_or_default() {
+        server = server.with_overrides();
+        features.push("overrides");
+    }
+    if env::<bool>("INTENT_BROKERING_READ_FALLBACKS").unwrap_or_default() {
+        server = server.with_read_fallbacks();
+        features.push("read_fallbacks");
+    }
+    if env::<bool>("INTENT_BROKERING_STRICT_FULFILLMENTS").unwrap_or_default() {
+        server = server.with_strict_fulfillments();
+        features.push("strict_fulfillments");
+    }
+    if let Some(path) = env::<String>("INTENT_BROKERING_CHAOS_PATH") {
+        server = server.with_chaos(Chaos::load(path)?);
+        features.push("chaos");
+    }
+    if env::<bool>("INTENT_BROKERING_SESSIONS").unwrap_or_default() {
+        let mut config = session::Config::default();
+        if let Some(ttl) = env::<u64>("INTENT_BROKERING_SESSION_TTL_SECS") {
+            config = config.set_ttl(Duration::from_secs(ttl));
+        }
+        if let Some(failover) = env::<String>("INTENT_BROKERING_SESSION_FAILOVER") {
+            config = config.set_failover(failover.parse()?);
+        }
+        server = server.with_sessions(config);
+        features.push("sessions");
+    }
+    if let Some(windows) = env::<String>("INTENT_BROKERING_RESTART_WINDOWS") {
+        let mut config = restart_queue::Config::parse(&windows)?;
+        if let Some(capacity) = env::<usize>("INTENT_BROKERING_RESTART_QUEUE_CAPACITY") {
+            config = config.set_capacity(capacity);
+        }
+        server = server.with_restart_queue(config);
+        features.push("restart_queue");
+    }
+    if let Some(config) = response_limits {
+        server = server.with_response_limits(config);
+        features.push("response_limits");
+    }
+    if env::<bool>("INTENT_BROKERING_LOG_FULFILLMENTS").unwrap_or_default() {
+        let mut config = fulfillment_log::Config::default();
+        if let Some(rate) = env::<u32>("INTENT_BROKERING_LOG_SAMPLE_RATE") {
+            config = config.set_sample_rate(rate);
+        }
+        if let Some(interval) = env::<u64>("INTENT_BROKERING_LOG_FLUSH_INTERVAL_SECS") {
+            config = config.set_flush_interval_bounded(Duration::from_secs(interval));
+        }
+        if let Some(intents) = env::<String>("INTENT_BROKERING_LOG_SUMMARIZED_INTENTS") {
+            config = config.set_summarized(fulfillment_log::parse_intents(&intents)?);
+        }
+        let log = FulfillmentLog::new(config);
+        broker.mount(Arc::new(LogSettings::new(log.clone())))?;
+        tokio::spawn(log.clone().run());
+        server = server.with_fulfillment_log(log);
+        features.push("fulfillment_log");
+    }
+    if let Some(path) = env::<String>("INTENT_BROKERING_TRANSFORMS_PATH") {
+        server = server.with_transforms(Transforms::load(path)?);
+        features.push("transforms");
+    }
+    if let Some(path) = env::<String>("INTENT_BROKERING_ACTIVATION_PATH") {
+        server = server.with_activation(Activation::load(path)?);
+        features.push("activation");
+    }
+    if let Some(url) = env::<String>("INTENT_BROKERING_UPSTREAM_URL") {
+        let mut config = upstream::Config::new(url.parse()?);
+        if let Some(ttl) = env::<u64>("INTENT_BROKERING_UPSTREAM_CACHE_TTL_SECS") {
+            config = config.set_cache_ttl(Duration::from_secs(ttl));
+        }
+        server = server.with_upstream(Upstream::new(config)?);
+        features.push("upstream");
+    }
+    if let Some(peers) = env::<String>("INTENT_BROKERING_FEDERATION_PEERS") {
+        let peers = peers
+            .split(',')
+            .map(str::trim)
+            .filter(|peer| !peer.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut config = federation::Config::new(peers);
+        if let Some(interval) = env::<u64>("INTENT_BROKERING_FEDERATION_REFRESH_SECS") {
+            config = config.set_refresh_interval(Duration::from_secs(interval));
+        }
+        let federation = Arc::new(Federation::new(config)?);
+        tokio::spawn(Arc::clone(&federation).run());
+        server = server.with_federation(federation);
+        features.push("federation");
+    }
+    if let Some(budgets) = env::<String>("INTENT_BROKERING_LATENCY_BUDGETS") {
+        server = server.with_latency_budgets(latency::Budgets::parse(&budgets)?);
+        features.push("latency_budgets");
+    }
+    if let Some(namespaces) = env::<String>("INTENT_BROKERING_WRITE_EVENT_NAMESPACES") {
+        server = server
+            .with_write_events(namespaces.split(',').map(|namespace| namespace.trim().into()));
+        features.push("write_events");
+    }
+    let bridges = match env::<String>("INTENT_BROKERING_BRIDGES_PATH") {
+        Some(path) => {
+            let bridges = Arc::new(Bridges::load(path, streaming_ess.clone())?);
+            server = server.with_bridges(Arc::clone(&bridges));
+            features.push("bridges");
+            Some(bridges)
+        }
+        None => None,
+    };
+
+    let configuration = std::env::vars()
+        .filter(|(key, _)| key.starts_with("INTENT_BROKERING_") || key == EXTERNAL_HOST_NAME_ENV);
+    broker.mount(Arc::new(RuntimeInfo::new(&broker, features, configuration)))?;
+
+    // Registrations persisted before a restart, or else seeded from a
+    // snapshot, e.g. of providers which are part of the image, are loaded
+    // before the Intent Broker reports being ready.
+    let loaded = match &storage {
+        Some(storage) => server.registry_do(|reg| reg.load(storage.as_ref(), Instant::now()))?,
+        None => false,
+    };
+    if let (false, Some(path)) = (loaded, env::<String>("INTENT_BROKERING_REGISTRY_SNAPSHOT_PATH"))
+    {
+        let snapshot = serde_json::from_slice(&std::fs::read(path)?)?;
+        server.registry_do(|reg| reg.import(snapshot, Instant::now()))?;
+    }
+
+    let server = Arc::new(server);
+    if let Some(storage) = storage {
+        tokio::spawn(registry_save_loop(Arc::clone(&server), storage));
+    }
+    if let Some(bridges) = bridges {
+        let changes = server.registry_do(|reg| reg.subscribe_changes());
+        let broker = broker.clone();
+        tokio::spawn(async move {
+            if let Err(e) = bridges.run(broker, changes).await {
+                tracing::error!("Bridging events failed: {e}");
+            }
+        });
+    }
     let router = Server::builder()
-        .add_service(IntentBrokeringServiceServer::from_arc(Arc::clone(&server)))
-        .add_service(ChannelServiceServer::new(streaming_ess));
+        .accept_http1(true)
+        .add_service(grpc_web::enable(
+            IntentBrokeringServiceServer::from_arc(Arc::clone(&server)),
+            Arc::clone(&allowed_origins),
+        ))
+        .add_service(grpc_web::enable(ChannelServiceServer::new(streaming_ess), allowed_origins));
 
-    #[cfg(build = "debug")]
+    #[cfg(all(build = "debug", feature = "reflection"))]
     let router = router.add_service(reflection_service);
 
     let error_cancellation_token = CancellationToken::new();
     let ctrl_c_cancellation_token = ctrl_c_cancellation();
 
+    let provider_probe_loop = {
+        let server = Arc::clone(&server);
+        let ctrl_c_cancellation_token = ctrl_c_cancellation_token.clone();
+        let error_cancellation_token = error_cancellation_token.child_token();
+        async move {
+            if let Some(config) = liveness_config {
+                provider_probe_loop(
+                    server,
+                    Liveness::new(config),
+                    ctrl_c_cancellation_token,
+                    error_cancellation_token,
+                )
+                .await;
+            }
+        }
+    };
+
+    let registry_compaction_loop = registry_compaction_loop(
+        Arc::clone(&server),
+        env::<u64>("INTENT_BROKERING_REGISTRY_COMPACTION_INTERVAL_SECS")
+            .map(Duration::from_secs)
+            .unwrap_or(REGISTRY_COMPACTION_INTERVAL),
+        ctrl_c_cancellation_token.clone(),
+        error_cancellation_token.child_token(),
+    );
+
+    let watchdog_loop = {
+        let server = Arc::clone(&server);
+        let ctrl_c_cancellation_token = ctrl_c_cancellation_token.clone();
+        let error_cancellation_token = error_cancellation_token.child_token();
+        async move {
+            if let Some(interval) = systemd::watchdog_interval() {
+                watchdog_loop(
+                    server,
+                    interval,
+                    ctrl_c_cancellation_token,
+                    error_cancellation_token,
+                )
+                .await;
+            }
+        }
+    };
+
     let registry_prune_loop = registry_prune_loop(
         server,
         ctrl_c_cancellation_token.clone(),
         error_cancellation_token.child_token(),
     );
 
+    let listener = TcpListener::bind(addr).await?;
+    match systemd::notify(systemd::READY) {
+        Ok(true) => tracing::debug!("Notified systemd that the Intent Broker is ready."),
+        Ok(false) => {}
+        Err(e) => tracing::warn!("Failed to notify systemd: {e}"),
+    }
+
     let router_serve = async {
-        match router.serve_with_cancellation(addr, ctrl_c_cancellation_token).await {
+        match router.serve_listener_with_cancellation(listener, ctrl_c_cancellation_token).await {
             err @ Err(_) => {
                 error_cancellation_token.cancel();
                 err
@@ -100,15 +508,38 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
-    let (router_serve_result, _) = tokio::join!(router_serve, registry_prune_loop);
+    let (router_serve_result, _, _, _, _) = tokio::join!(
+        router_serve,
+        registry_prune_loop,
+        registry_compaction_loop,
+        provider_probe_loop,
+        watchdog_loop
+    );
+
+    if let Err(e) = systemd::notify(systemd::STOPPING) {
+        tracing::warn!("Failed to notify systemd: {e}");
+    }
 
     router_serve_result?;
 
     Ok(())
 }
 
+/// Saves the registrations to the storage whenever they change.
+async fn registry_save_loop(
+    server: Arc<IntentBrokeringServer<Observers>>,
+    storage: Arc<dyn Storage>,
+) {
+    let mut changes = server.registry_do(|reg| reg.subscribe_changes());
+    while changes.changed().await.is_ok() {
+        if let Err(e) = server.registry_do(|reg| reg.save(storage.as_ref(), Instant::now())) {
+            tracing::warn!("Failed to save the registry: {e}");
+        }
+    }
+}
+
 async fn registry_prune_loop(
-    server: Arc<IntentBrokeringServer<Composite<IntentBroker, StreamingEss>>>,
+    server: Arc<IntentBrokeringServer<Observers>>,
     ctrl_c_cancellation_token: CancellationToken,
     error_cancellation_token: CancellationToken,
 ) {
@@ -131,3 +562,110 @@ async fn registry_prune_loop(
         }
     }
 }
+
+async fn registry_compaction_loop(
+    server: Arc<IntentBrokeringServer<Observers>>,
+    interval: Duration,
+    ctrl_c_cancellation_token: CancellationToken,
+    error_cancellation_token: CancellationToken,
+) {
+    if interval.is_zero() {
+        return;
+    }
+
+    tracing::debug!("Compaction loop running.");
+    loop {
+        select! {
+            _ = sleep(interval) => {}
+            _ = error_cancellation_token.cancelled() => {
+                tracing::debug!("Compaction loop aborting due to server error.");
+                break;
+            }
+            _ = ctrl_c_cancellation_token.cancelled() => {
+                tracing::debug!("Compaction loop aborting due to cancellation.");
+                break;
+            }
+        }
+
+        server.compact();
+    }
+}
+
+/// Pets the systemd watchdog at half of its interval for as long as the
+/// Intent Broker is healthy. Once it is not, the watchdog is left to expire,
+/// such that systemd restarts the Intent Broker.
+async fn watchdog_loop(
+    server: Arc<IntentBrokeringServer<Observers>>,
+    interval: Duration,
+    ctrl_c_cancellation_token: CancellationToken,
+    error_cancellation_token: CancellationToken,
+) {
+    tracing::debug!("Watchdog loop running.");
+    loop {
+        if !server.is_healthy() {
+            tracing::error!("Intent Broker is unhealthy, no longer petting the watchdog.");
+            break;
+        }
+
+        if let Err(e) = systemd::notify(systemd::WATCHDOG) {
+            tracing::warn!("Failed to pet the watchdog: {e}");
+        }
+
+        select! {
+            _ = sleep(interval / 2) => {}
+            _ = error_cancellation_token.cancelled() => {
+                tracing::debug!("Watchdog loop aborting due to server error.");
+                break;
+            }
+            _ = ctrl_c_cancellation_token.cancelled() => {
+                tracing::debug!("Watchdog loop aborting due to cancellation.");
+                break;
+            }
+        }
+    }
+}
+
+async fn provider_probe_loop(
+    server: Arc<IntentBrokeringServer<Observers>>,
+    mut liveness: Liveness,
+    ctrl_c_cancellation_token: CancellationToken,
+    error_cancellation_token: CancellationToken,
+) {
+    tracing::debug!("Probe loop running.");
+    loop {
+        select! {
+            _ = sleep(liveness.config().interval()) => {}
+            _ = error_cancellation_token.cancelled() => {
+                tracing::debug!("Probe loop aborting due to server error.");
+                break;
+            }
+            _ = ctrl_c_cancellation_token.cancelled() => {
+                tracing::debug!("Probe loop aborting due to cancellation.");
+                break;
+            }
+        }
+
+        let timeout = liveness.config().probe_timeout();
+        let mut probes = JoinSet::new();
+        for service in server.registry_do(|reg| reg.services().cloned().collect::<Vec<_>>()) {
+            probes.spawn(async move {
+                let reachable = liveness::probe(service.url(), timeout).await;
+                (service, reachable)
+            });
+        }
+
+        let mut results = Vec::new();
+        while let Some(result) = probes.join_next().await {
+            results.extend(result.ok());
+        }
+
+        for service in liveness.record(results) {
+            tracing::warn!(
+                "Deregistering provider '{}' at '{}', which is unreachable.",
+                service.id().name(),
+                service.url()
+            );
+            server.registry_do(|reg| reg.remove(&service));
+        }
+    }
+}
@@ -0,0 +1,180 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Interns the namespaces of intents, such that the configurations of all
+//! intents of a namespace share a single allocation of the namespace, no
+//! matter how often they are cloned by the registry and the broker.
+//!
+//! A namespace stays interned for as long as it is shared by a configuration
+//! outside of the pool, and is forgotten when the pool is pruned after that.
+//!
+//! A namespace also covers its sub-namespaces, e.g. `sdv` covers `sdv.vdt`,
+//! such that settings can be configured for a tree of namespaces at once.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use intent_brokering_common::error::Error;
+
+/// A pool of interned namespaces.
+#[derive(Clone, Debug, Default)]
+pub struct Namespaces(HashSet<Arc<str>>);
+
+impl Namespaces {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the interned namespace equal to the given one, interning the
+    /// given namespace if there is none.
+    pub fn intern(&mut self, namespace: &Arc<str>) -> Arc<str> {
+        match self.0.get(namespace) {
+            Some(interned) => Arc::clone(interned),
+            None => {
+                self.0.insert(Arc::clone(namespace));
+                Arc::clone(namespace)
+            }
+        }
+    }
+
+    /// Returns the interned namespace equal to the given one, if any.
+    pub fn get(&self, namespace: &str) -> Option<Arc<str>> {
+        self.0.get(namespace).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Forgets the namespaces which are no longer shared outside of the pool.
+    pub fn prune(&mut self) {
+        self.0.retain(|namespace| Arc::strong_count(namespace) > 1);
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.0.shrink_to_fit();
+    }
+}
+
+/// Returns whether a namespace equals the covering one or is one of its
+/// sub-namespaces.
+pub fn covers(covering: &str, namespace: &str) -> bool {
+    namespace.strip_prefix(covering).map_or(false, |rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// Durations configured per namespace, of which the duration of the most
+/// specific namespace covering a namespace applies.
+#[derive(Clone, Debug, Default)]
+pub struct Durations(Vec<(Box<str>, Duration)>);
+
+impl Durations {
+    pub fn new(durations: impl IntoIterator<Item = (Box<str>, Duration)>) -> Self {
+        Self(durations.into_iter().collect())
+    }
+
+    /// Parses a comma-separated list of namespaces and their durations in
+    /// milliseconds, e.g. `sdv.vdt=50,sdv=200`. The name of what is parsed,
+    /// e.g. `Latency budget`, is used in the error for a malformed entry.
+    pub fn parse(value: &str, name: &str) -> Result<Self, Error> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                entry
+                    .split_once('=')
+                    .and_then(|(namespace, millis)| {
+                        let millis = millis.trim().parse().ok()?;
+                        Some((namespace.trim().into(), Duration::from_millis(millis)))
+                    })
+                    .ok_or_else(|| {
+                        Error::new(format!(
+                            "{name} '{entry}' is not of the form 'namespace=milliseconds'."
+                        ))
+                    })
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+
+    /// Returns the duration of the most specific namespace covering the given
+    /// one, if any.
+    pub fn get(&self, namespace: &str) -> Option<Duration> {
+        self.0
+            .iter()
+            .filter(|(covering, _)| covers(covering, namespace))
+            .max_by_key(|(covering, _)| covering.len())
+            .map(|(_, duration)| *duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::{covers, Durations, Namespaces};
+
+    #[test]
+    fn intern_returns_first_interned_namespace() {
+        // arrange
+        let mut subject = Namespaces::new();
+        let first: Arc<str> = "sdv.vdt".into();
+        let second: Arc<str> = "sdv.vdt".into();
+
+        // act
+        let first = subject.intern(&first);
+        let second = subject.intern(&second);
+
+        // assert
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(Arc::ptr_eq(&first, &subject.get("sdv.vdt").unwrap()));
+        assert_eq!(None, subject.get("sdv.kvs"));
+        assert_eq!(1, subject.len());
+    }
+
+    #[test]
+    fn prune_forgets_namespaces_not_shared_outside_of_pool() {
+        // arrange
+        let mut subject = Namespaces::new();
+        let shared = subject.intern(&"sdv.vdt".into());
+        _ = subject.intern(&"sdv.kvs".into());
+
+        // act
+        subject.prune();
+
+        // assert
+        assert_eq!(Some(shared), subject.get("sdv.vdt"));
+        assert_eq!(None, subject.get("sdv.kvs"));
+    }
+
+    #[test]
+    fn covers_namespace_and_sub_namespaces() {
+        // act + assert
+        assert!(covers("sdv", "sdv"));
+        assert!(covers("sdv", "sdv.vdt"));
+        assert!(!covers("sdv", "sdvx"));
+        assert!(!covers("sdv.vdt", "sdv"));
+    }
+
+    #[test]
+    fn duration_of_most_specific_namespace_applies() {
+        // arrange
+        let subject = Durations::parse("sdv=500, sdv.vdt=2000", "Window").unwrap();
+
+        // act + assert
+        assert_eq!(Some(Duration::from_millis(2000)), subject.get("sdv.vdt.cabin"));
+        assert_eq!(Some(Duration::from_millis(500)), subject.get("sdv.camera"));
+        assert_eq!(None, subject.get("sdvx"));
+        assert_eq!(
+            "Window 'sdv' is not of the form 'namespace=milliseconds'.",
+            Durations::parse("sdv", "Window").unwrap_err().message()
+        );
+    }
+}
@@ -0,0 +1,378 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Tracks long-running operations, e.g. OTA update checks or diagnostic
+//! routines, which are started by `Invoke` intents and report their progress
+//! while they run.
+//!
+//! A consumer starts an operation by fulfilling an `Invoke` intent with the
+//! `x-chariott-progress-channel-id` metadata, which holds the ID of a channel
+//! the consumer opened with the Intent Broker. The Intent Broker generates an
+//! ID for the operation, subscribes the channel to the source
+//! `operations/{id}` and forwards the intent together with the ID to the
+//! provider, which returns once the operation started. The progress, partial
+//! results and outcome reported by the provider are published as events of
+//! the source. An operation ends once its outcome is reported, or when the
+//! consumer cancels it, which is forwarded to the provider.
+//!
+//! Operations are forwarded to the provider which is tried first for the
+//! intent, without falling back, such that a cancellation reaches the
+//! provider running the operation.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use intent_brokering_common::streaming_ess::Timestamped;
+use intent_brokering_proto::{
+    common::{IntentMessage, Map, SubscribeIntent, ValueEnum, ValueMessage},
+    provider::{operation::Phase, FulfillResponse},
+    runtime::{Operation as OperationMessage, OperationState},
+};
+use tonic::{metadata::MetadataMap, Status};
+use uuid::Uuid;
+
+use crate::{
+    connection_provider::ConnectionProvider, streaming::StreamingEss, transaction::Participant,
+};
+
+pub const PROGRESS_CHANNEL_ID_METADATA_KEY: &str = "x-chariott-progress-channel-id";
+
+const STATE_KEY: &str = "state";
+const VALUE_KEY: &str = "value";
+
+/// Returns the ID of the channel to which the progress of an operation is
+/// delivered, if the request starts a long-running operation.
+pub fn progress_channel_id(metadata: &MetadataMap) -> Option<&str> {
+    metadata.get(PROGRESS_CHANNEL_ID_METADATA_KEY).and_then(|value| value.to_str().ok())
+}
+
+/// The source under which the progress of an operation is published.
+pub fn operation_source(id: &str) -> String {
+    format!("operations/{id}")
+}
+
+struct Operation<T> {
+    namespace: Box<str>,
+    participant: Participant<T>,
+}
+
+/// The running operations, keyed by their ID.
+pub struct Operations<T> {
+    ess: StreamingEss,
+    operations: Mutex<HashMap<Box<str>, Operation<T>>>,
+}
+
+impl<T> Operations<T> {
+    pub fn new(ess: StreamingEss) -> Self {
+        Self { ess, operations: Mutex::new(HashMap::new()) }
+    }
+
+    /// Returns the namespace of the intent which started a running operation.
+    pub fn namespace(&self, id: &str) -> Result<Box<str>, Status> {
+        self.operations
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|operation| operation.namespace.clone())
+            .ok_or_else(|| not_running(id))
+    }
+
+    /// Publishes the progress of a running operation, which ends unless it is
+    /// still running.
+    pub fn report(
+        &self,
+        id: &str,
+        state: OperationState,
+        value: Option<ValueEnum>,
+    ) -> Result<(), Status> {
+        {
+            let mut operations = self.operations.lock().unwrap();
+            if !operations.contains_key(id) {
+                return Err(not_running(id));
+            }
+
+            if state != OperationState::Running {
+                operations.remove(id);
+            }
+        }
+
+        self.publish(id, state, value);
+        Ok(())
+    }
+
+    fn publish(&self, id: &str, state: OperationState, value: Option<ValueEnum>) {
+        let value = progress_value(state, value);
+        self.ess.publish(operation_source(id).as_str(), Timestamped::now(value));
+    }
+}
+
+impl<T: ConnectionProvider + Clone + Send> Operations<T>
+where
+    T::ConnectedProvider: Send,
+{
+    /// Starts an operation by forwarding the intent to the provider, once the
+    /// progress channel is subscribed to the source of the operation.
+    pub async fn start(
+        &self,
+        namespace: &str,
+        channel_id: &str,
+        mut participant: Participant<T>,
+        intent: IntentMessage,
+    ) -> Result<(OperationMessage, FulfillResponse), Status> {
+        if !self.ess.is_reading_events(channel_id) {
+            return Err(Status::failed_precondition(format!(
+                "Progress channel '{channel_id}' is not open."
+            )));
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let source = operation_source(&id);
+        self.ess.serve_timestamped_subscriptions(
+            SubscribeIntent {
+                channel_id: channel_id.to_owned(),
+                sources: vec![source.clone()],
+                ..Default::default()
+            },
+            |v| v,
+        )?;
+
+        // The operation is tracked before it is started, such that progress
+        // reported before the provider returns is not rejected.
+        self.operations.lock().unwrap().insert(
+            id.as_str().into(),
+            Operation { namespace: namespace.into(), participant: participant.clone() },
+        );
+
+        match participant.fulfill_operation(&id, Phase::Start, Some(intent)).await {
+            Ok(response) => Ok((OperationMessage { id, source }, response)),
+            Err(e) => {
+                self.operations.lock().unwrap().remove(id.as_str());
+                Err(Status::unknown(format!("Error when invoking provider: '{e}'.")))
+            }
+        }
+    }
+
+    /// Cancels a running operation with its provider.
+    pub async fn cancel(&self, id: &str) -> Result<(), Status> {
+        let mut participant = self
+            .operations
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|operation| operation.participant.clone())
+            .ok_or_else(|| not_running(id))?;
+
+        participant.fulfill_operation(id, Phase::Cancel, None).await.map_err(|e| {
+            Status::unknown(format!("Error when cancelling operation with provider: '{e}'."))
+        })?;
+
+        // The outcome of the operation may have been reported meanwhile.
+        if self.operations.lock().unwrap().remove(id).is_some() {
+            self.publish(id, OperationState::Cancelled, None);
+        }
+
+        Ok(())
+    }
+}
+
+/// Converts progress into a map holding the state of the operation under
+/// `state` and the reported value, if any, under `value`.
+fn progress_value(state: OperationState, value: Option<ValueEnum>) -> ValueEnum {
+    let state = match state {
+        OperationState::Running => "running",
+        OperationState::Succeeded => "succeeded",
+        OperationState::Failed => "failed",
+        OperationState::Cancelled => "cancelled",
+    };
+
+    ValueEnum::Map(Map {
+        map: [(STATE_KEY.to_owned(), Some(ValueEnum::String(state.to_owned())))]
+            .into_iter()
+            .chain(value.map(|value| (VALUE_KEY.to_owned(), Some(value))))
+            .map(|(key, value)| (key, ValueMessage { value }))
+            .collect(),
+    })
+}
+
+fn not_running(id: &str) -> Status {
+    Status::not_found(format!("Operation '{id}' is not running."))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use intent_brokering_common::error::Error;
+    use intent_brokering_proto::{
+        common::{
+            FulfillmentEnum, FulfillmentMessage, IntentEnum, InvokeFulfillment, InvokeIntent,
+        },
+        provider::FulfillRequest,
+        streaming::{channel_service_server::ChannelService, Event, OpenRequest},
+    };
+    use tokio_stream::Stream;
+    use tonic::{Code, Request};
+    use url::Url;
+
+    use super::*;
+    use crate::execution::tests::StreamExt as _;
+
+    #[tokio::test]
+    async fn start_forwards_intent_and_publishes_progress_until_outcome() {
+        // arrange
+        let (subject, log) = setup();
+        let (channel_id, stream) = open_channel(&subject.ess).await;
+
+        // act
+        let (operation, response) =
+            subject.start("sdv.ota", &channel_id, log.participant(), invoke()).await.unwrap();
+        subject.report(&operation.id, OperationState::Running, Some(ValueEnum::Int32(50))).unwrap();
+        subject.report(&operation.id, OperationState::Succeeded, None).unwrap();
+        let after_outcome = subject.report(&operation.id, OperationState::Running, None);
+
+        // assert
+        assert_eq!(operation_source(&operation.id), operation.source);
+        assert!(response.fulfillment.is_some());
+        assert_eq!(vec![(Phase::Start, true)], log.requests(&operation.id));
+        assert_eq!(Code::NotFound, after_outcome.unwrap_err().code());
+        assert_eq!(
+            vec![
+                progress(&operation.source, OperationState::Running, Some(ValueEnum::Int32(50))),
+                progress(&operation.source, OperationState::Succeeded, None),
+            ],
+            events(stream).await
+        );
+    }
+
+    #[tokio::test]
+    async fn start_fails_if_progress_channel_is_not_open() {
+        // arrange
+        let (subject, log) = setup();
+
+        // act
+        let result = subject.start("sdv.ota", "unknown", log.participant(), invoke()).await;
+
+        // assert
+        assert_eq!(Code::FailedPrecondition, result.unwrap_err().code());
+        assert!(log.0.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancel_forwards_cancellation_and_publishes_it() {
+        // arrange
+        let (subject, log) = setup();
+        let (channel_id, stream) = open_channel(&subject.ess).await;
+        let (operation, _) =
+            subject.start("sdv.ota", &channel_id, log.participant(), invoke()).await.unwrap();
+
+        // act
+        subject.cancel(&operation.id).await.unwrap();
+        let cancel_again = subject.cancel(&operation.id).await;
+
+        // assert
+        assert_eq!(vec![(Phase::Start, true), (Phase::Cancel, false)], log.requests(&operation.id));
+        assert_eq!(Code::NotFound, cancel_again.unwrap_err().code());
+        assert_eq!(
+            vec![progress(&operation.source, OperationState::Cancelled, None)],
+            events(stream).await
+        );
+    }
+
+    fn setup() -> (Operations<MockProvider>, Log) {
+        (Operations::new(StreamingEss::new()), Log::default())
+    }
+
+    async fn open_channel(
+        ess: &StreamingEss,
+    ) -> (String, impl Stream<Item = Result<Event, Status>> + Send) {
+        let response = ess.open(Request::new(OpenRequest::default())).await.unwrap();
+        let channel_id =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+        (channel_id, response.into_inner())
+    }
+
+    async fn events(
+        stream: impl Stream<Item = Result<Event, Status>> + Send,
+    ) -> Vec<(String, Option<ValueEnum>)> {
+        stream
+            .collect_when_stable()
+            .await
+            .into_iter()
+            .map(|event| {
+                let event = event.unwrap();
+                (event.source, event.value.and_then(|v| v.value))
+            })
+            .collect()
+    }
+
+    fn progress(
+        source: &str,
+        state: OperationState,
+        value: Option<ValueEnum>,
+    ) -> (String, Option<ValueEnum>) {
+        (source.to_owned(), Some(progress_value(state, value)))
+    }
+
+    fn invoke() -> IntentMessage {
+        IntentMessage {
+            intent: Some(IntentEnum::Invoke(InvokeIntent {
+                command: "check_updates".to_owned(),
+                args: vec![],
+            })),
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct Log(Arc<Mutex<Vec<FulfillRequest>>>);
+
+    impl Log {
+        fn participant(&self) -> Participant<MockProvider> {
+            let url = "http://ota".parse().unwrap(); // DevSkim: ignore DS137138
+            Participant::new(url, MockProvider { log: self.clone() }, false)
+        }
+
+        fn requests(&self, operation_id: &str) -> Vec<(Phase, bool)> {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|request| {
+                    let operation = request.operation.clone().unwrap();
+                    assert_eq!(operation_id, operation.id);
+                    (Phase::try_from(operation.phase).unwrap(), request.intent.is_some())
+                })
+                .collect()
+        }
+    }
+
+    #[derive(Clone)]
+    struct MockProvider {
+        log: Log,
+    }
+
+    #[async_trait]
+    impl ConnectionProvider for MockProvider {
+        type ConnectedProvider = Self;
+
+        fn new(_: Url) -> Self {
+            unimplemented!()
+        }
+
+        async fn connect(&mut self) -> Result<Self::ConnectedProvider, Error> {
+            Ok(self.clone())
+        }
+    }
+
+    #[async_trait]
+    impl crate::connection_provider::ConnectedProvider for MockProvider {
+        async fn fulfill(&mut self, request: FulfillRequest) -> Result<FulfillResponse, Error> {
+            self.log.0.lock().unwrap().push(request);
+            Ok(FulfillResponse {
+                fulfillment: Some(FulfillmentMessage {
+                    fulfillment: Some(FulfillmentEnum::Invoke(InvokeFulfillment::default())),
+                }),
+            })
+        }
+    }
+}
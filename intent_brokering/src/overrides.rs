@@ -0,0 +1,190 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Overrides how intents are fulfilled, such that test engineers can simulate
+//! faults and values on a vehicle without touching its providers.
+//!
+//! An override applies to an intent of a namespace, and either forwards the
+//! intent to a fixture provider, or fulfills it with a canned fulfillment or
+//! error, instead of resolving it with the registered providers. Each
+//! override expires after its time to live, after which the intent is
+//! resolved as usual again. Overrides are managed with the `SetOverride`,
+//! `ClearOverride` and `ListOverrides` methods of the Intent Broker, and are
+//! disabled unless enabled explicitly.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use intent_brokering_proto::{common::FulfillmentMessage, provider::FulfillResponse};
+use tonic::{Code, Status};
+use url::Url;
+
+use crate::registry::IntentConfiguration;
+use crate::system::SYSTEM_NAMESPACE_PREFIX;
+
+/// The namespace whose `Write` and `Read` intents the access control list
+/// must allow for a caller to manage and list overrides.
+pub const OVERRIDES_NAMESPACE: &str = "system.overrides";
+
+/// A canned outcome of fulfilling an overridden intent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Canned {
+    Fulfillment(FulfillmentMessage),
+    /// An error with a status code other than `OK`.
+    Error(Code, Box<str>),
+}
+
+impl Canned {
+    pub fn fulfill(self) -> Result<FulfillResponse, Status> {
+        match self {
+            Canned::Fulfillment(fulfillment) => {
+                Ok(FulfillResponse { fulfillment: Some(fulfillment) })
+            }
+            Canned::Error(code, message) => Err(Status::new(code, message)),
+        }
+    }
+}
+
+/// How an overridden intent is fulfilled.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    /// Forwards the intent to a fixture provider.
+    Provider(Url),
+    Canned(Canned),
+}
+
+/// The overrides of intents, which are forgotten once they expire.
+#[derive(Debug, Default)]
+pub struct Overrides(HashMap<IntentConfiguration, (Target, Instant)>);
+
+impl Overrides {
+    /// Overrides an intent until the time to live elapsed, replacing any
+    /// previous override of the intent. Intents of system namespaces cannot
+    /// be overridden.
+    pub fn set(
+        &mut self,
+        intent: IntentConfiguration,
+        target: Target,
+        ttl: Duration,
+        now: Instant,
+    ) -> Result<(), Status> {
+        if intent.namespace().starts_with(SYSTEM_NAMESPACE_PREFIX) {
+            return Err(Status::invalid_argument(format!(
+                "Intents of '{SYSTEM_NAMESPACE_PREFIX}*' namespaces cannot be overridden."
+            )));
+        }
+        if ttl.is_zero() {
+            return Err(Status::invalid_argument("Time to live of override must be positive."));
+        }
+        if let Target::Canned(Canned::Error(Code::Ok, _)) = target {
+            return Err(Status::invalid_argument("Canned error must not have the code 'OK'."));
+        }
+
+        self.prune(now);
+        self.0.insert(intent, (target, now + ttl));
+        Ok(())
+    }
+
+    /// Removes the override of an intent, returning whether there was one.
+    pub fn clear(&mut self, intent: &IntentConfiguration, now: Instant) -> bool {
+        self.prune(now);
+        self.0.remove(intent).is_some()
+    }
+
+    /// Returns the target of an intent, if it is overridden and the override
+    /// did not expire.
+    pub fn get(&self, intent: &IntentConfiguration, now: Instant) -> Option<&Target> {
+        self.0.get(intent).filter(|(_, expires)| *expires > now).map(|(target, _)| target)
+    }
+
+    /// Returns the overrides which did not expire, together with the time
+    /// for which they still apply.
+    pub fn list(&mut self, now: Instant) -> Vec<(IntentConfiguration, Target, Duration)> {
+        self.prune(now);
+        self.0
+            .iter()
+            .map(|(intent, (target, expires))| {
+                (intent.clone(), target.clone(), expires.duration_since(now))
+            })
+            .collect()
+    }
+
+    fn prune(&mut self, now: Instant) {
+        self.0.retain(|_, (_, expires)| *expires > now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use tonic::Code;
+
+    use super::{Canned, Overrides, Target};
+    use crate::registry::{IntentConfiguration, IntentKind};
+
+    fn read(namespace: &str) -> IntentConfiguration {
+        IntentConfiguration::new(namespace, IntentKind::Read)
+    }
+
+    fn error() -> Target {
+        Target::Canned(Canned::Error(Code::Unavailable, "simulated".into()))
+    }
+
+    #[test]
+    fn get_returns_target_until_expired() {
+        // arrange
+        let now = Instant::now();
+        let mut subject = Overrides::default();
+        subject.set(read("sdv.vdt"), error(), Duration::from_secs(10), now).unwrap();
+
+        // act + assert
+        assert_eq!(Some(&error()), subject.get(&read("sdv.vdt"), now + Duration::from_secs(9)));
+        assert_eq!(None, subject.get(&read("sdv.vdt"), now + Duration::from_secs(10)));
+        assert_eq!(None, subject.get(&read("sdv.kvs"), now));
+        assert_eq!(None, subject.get(&IntentConfiguration::new("sdv.vdt", IntentKind::Write), now));
+    }
+
+    #[test]
+    fn list_returns_remaining_time_of_overrides_not_expired() {
+        // arrange
+        let now = Instant::now();
+        let mut subject = Overrides::default();
+        subject.set(read("sdv.vdt"), error(), Duration::from_secs(10), now).unwrap();
+        subject.set(read("sdv.kvs"), error(), Duration::from_secs(1), now).unwrap();
+
+        // act
+        let result = subject.list(now + Duration::from_secs(4));
+
+        // assert
+        assert_eq!(vec![(read("sdv.vdt"), error(), Duration::from_secs(6))], result);
+    }
+
+    #[test]
+    fn clear_returns_whether_intent_was_overridden() {
+        // arrange
+        let now = Instant::now();
+        let mut subject = Overrides::default();
+        subject.set(read("sdv.vdt"), error(), Duration::from_secs(10), now).unwrap();
+
+        // act + assert
+        assert!(subject.clear(&read("sdv.vdt"), now));
+        assert!(!subject.clear(&read("sdv.vdt"), now));
+        assert_eq!(None, subject.get(&read("sdv.vdt"), now));
+    }
+
+    #[test]
+    fn set_fails_for_invalid_override() {
+        let now = Instant::now();
+        let mut subject = Overrides::default();
+        let ok = Target::Canned(Canned::Error(Code::Ok, "".into()));
+
+        assert!(subject
+            .set(read("system.registry"), error(), Duration::from_secs(1), now)
+            .is_err());
+        assert!(subject.set(read("sdv.vdt"), error(), Duration::ZERO, now).is_err());
+        assert!(subject.set(read("sdv.vdt"), ok, Duration::from_secs(1), now).is_err());
+        assert!(subject.list(now).is_empty());
+    }
+}
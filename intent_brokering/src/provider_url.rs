@@ -0,0 +1,179 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Validates the URLs of providers when they register, such that a bad or
+//! hostile registration cannot point intents at arbitrary endpoints.
+//!
+//! Only the `http` and `https` schemes are allowed, as providers are dialed
+//! over TCP and cannot be reached through Unix domain sockets. Link-local
+//! addresses, which include the instance metadata endpoints of cloud
+//! providers, and the well-known host names of such endpoints are blocked.
+//! Optionally, the host names are resolved, which rejects hosts that cannot be
+//! resolved and host names resolving to blocked addresses.
+
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use url::{Host, Url};
+
+const ALLOWED_SCHEMES: [&str; 2] = ["http", "https"];
+const BLOCKED_HOSTS: [&str; 2] = ["metadata", "metadata.google.internal"];
+// The instance metadata endpoint of Alibaba Cloud, which is not link-local.
+const ALIBABA_METADATA: Ipv4Addr = Ipv4Addr::new(100, 100, 100, 200);
+// The IPv6 instance metadata endpoint of AWS, which is not link-local.
+const AWS_METADATA_V6: Ipv6Addr = Ipv6Addr::new(0xfd00, 0xec2, 0, 0, 0, 0, 0, 0x254);
+
+/// The reason the URL of a provider is rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidUrl {
+    /// The scheme is not one of `http` and `https`.
+    Scheme(Box<str>),
+    /// The host is the well-known name of an instance metadata endpoint.
+    BlockedHost(Box<str>),
+    /// The host is, or resolves to, a link-local or metadata address.
+    BlockedAddress(IpAddr),
+    /// The host name cannot be resolved.
+    Unresolvable(Box<str>),
+}
+
+impl fmt::Display for InvalidUrl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvalidUrl::Scheme(scheme) => write!(f, "URL scheme '{scheme}' is not allowed."),
+            InvalidUrl::BlockedHost(host) => write!(f, "URL host '{host}' is blocked."),
+            InvalidUrl::BlockedAddress(address) => {
+                write!(f, "URL address '{address}' is blocked.")
+            }
+            InvalidUrl::Unresolvable(host) => write!(f, "URL host '{host}' cannot be resolved."),
+        }
+    }
+}
+
+impl std::error::Error for InvalidUrl {}
+
+/// Validates the scheme and host of the URL of a provider, without resolving
+/// its host name.
+pub fn validate(url: &Url) -> Result<(), InvalidUrl> {
+    if !ALLOWED_SCHEMES.contains(&url.scheme()) {
+        return Err(InvalidUrl::Scheme(url.scheme().into()));
+    }
+
+    match url.host() {
+        // URLs of the allowed schemes do not parse without a host.
+        None => Ok(()),
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            match BLOCKED_HOSTS.contains(&domain.as_str()) {
+                true => Err(InvalidUrl::BlockedHost(domain.into())),
+                false => Ok(()),
+            }
+        }
+        Some(Host::Ipv4(address)) => validate_address(address.into()),
+        Some(Host::Ipv6(address)) => validate_address(address.into()),
+    }
+}
+
+/// Validates the URL of a provider like [`validate`], and additionally
+/// resolves its host name, validating each address it resolves to.
+pub async fn validate_resolved(url: &Url) -> Result<(), InvalidUrl> {
+    validate(url)?;
+
+    let Some(Host::Domain(domain)) = url.host() else {
+        return Ok(());
+    };
+
+    let port = url.port_or_known_default().unwrap_or_default();
+    let addresses = tokio::net::lookup_host((domain, port))
+        .await
+        .map_err(|_| InvalidUrl::Unresolvable(domain.into()))?;
+
+    let mut resolved = false;
+    for address in addresses {
+        validate_address(address.ip())?;
+        resolved = true;
+    }
+
+    match resolved {
+        true => Ok(()),
+        false => Err(InvalidUrl::Unresolvable(domain.into())),
+    }
+}
+
+/// Validates an address the host name of a provider resolves to.
+pub(crate) fn validate_address(address: IpAddr) -> Result<(), InvalidUrl> {
+    let blocked = match address {
+        IpAddr::V4(v4) => is_blocked_v4(v4),
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => is_blocked_v4(v4),
+            // fe80::/10 is link-local.
+            None => (v6.segments()[0] & 0xffc0) == 0xfe80 || v6 == AWS_METADATA_V6,
+        },
+    };
+
+    match blocked {
+        true => Err(InvalidUrl::BlockedAddress(address)),
+        false => Ok(()),
+    }
+}
+
+fn is_blocked_v4(address: Ipv4Addr) -> bool {
+    address.is_link_local() || address == ALIBABA_METADATA
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use test_case::test_case;
+    use url::Url;
+
+    use super::{validate, validate_resolved, InvalidUrl};
+
+    #[test_case("http://localhost:50051"; "loopback host")]
+    #[test_case("https://provider.vehicle:443"; "domain")]
+    #[test_case("http://10.0.0.5:8080"; "private address")]
+    #[test_case("http://[::1]:50051"; "ipv6 loopback")]
+    fn validate_accepts_url(url: &str) {
+        assert_eq!(Ok(()), validate(&Url::parse(url).unwrap()));
+    }
+
+    #[test_case("ftp://provider", InvalidUrl::Scheme("ftp".into()); "scheme")]
+    #[test_case(
+        "unix:///run/provider.sock",
+        InvalidUrl::Scheme("unix".into());
+        "unix socket"
+    )]
+    #[test_case(
+        "http://169.254.169.254/latest",
+        InvalidUrl::BlockedAddress("169.254.169.254".parse().unwrap());
+        "metadata address"
+    )]
+    #[test_case(
+        "http://[fe80::1]:80",
+        InvalidUrl::BlockedAddress("fe80::1".parse().unwrap());
+        "ipv6 link-local"
+    )]
+    #[test_case(
+        "http://[::ffff:169.254.169.254]",
+        InvalidUrl::BlockedAddress("::ffff:169.254.169.254".parse::<IpAddr>().unwrap());
+        "ipv4 mapped"
+    )]
+    #[test_case(
+        "http://Metadata.Google.Internal.",
+        InvalidUrl::BlockedHost("metadata.google.internal".into());
+        "metadata host"
+    )]
+    fn validate_rejects_url(url: &str, expected: InvalidUrl) {
+        assert_eq!(Err(expected), validate(&Url::parse(url).unwrap()));
+    }
+
+    #[tokio::test]
+    async fn validate_resolved_rejects_unresolvable_host() {
+        // act
+        let result = validate_resolved(&Url::parse("http://provider.invalid").unwrap()).await;
+
+        // assert
+        assert_eq!(Err(InvalidUrl::Unresolvable("provider.invalid".into())), result);
+    }
+}
@@ -0,0 +1,271 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Protects namespaces from being registered by providers which cannot prove
+//! that they are trusted, e.g. a rogue process registering itself as the
+//! provider of `vehicle.brakes`.
+//!
+//! Each protected namespace is provisioned with a secret which is shared with
+//! its trusted providers, and covers its sub-namespaces. Registrations with
+//! intents of a protected namespace must be signed with HMAC-SHA256 over the
+//! protobuf encoding of the `RegisterRequest`, and the signature is sent as
+//! `sha256={hex digest}` in the `x-chariott-registration-signature`
+//! metadata. Providers registering intents of namespaces protected by
+//! different secrets send a signature per secret. When a provider is
+//! announced, the registration fetched from it is verified against the
+//! signatures sent with the `AnnounceRequest`. Registrations which are not
+//! signed, or whose signature is not valid, are rejected before they are
+//! added to the registry.
+
+use std::fmt;
+
+use hmac::{Hmac, Mac as _};
+use intent_brokering_common::error::Error;
+use intent_brokering_proto::runtime::RegisterRequest;
+use prost::Message as _;
+use sha2::Sha256;
+use tonic::{metadata::MetadataMap, Status};
+
+use crate::namespace::covers;
+
+pub const SIGNATURE_METADATA_KEY: &str = "x-chariott-registration-signature";
+const SIGNATURE_PREFIX: &str = "sha256=";
+
+/// The protected namespaces and their secrets.
+#[derive(Clone, Default)]
+pub struct ProtectedNamespaces(Vec<(Box<str>, Box<[u8]>)>);
+
+impl ProtectedNamespaces {
+    pub fn new(namespaces: impl IntoIterator<Item = (Box<str>, Box<[u8]>)>) -> Self {
+        Self(namespaces.into_iter().collect())
+    }
+
+    /// Parses a comma-separated list of namespaces and their secrets, e.g.
+    /// `vehicle.brakes=secret,vehicle.steering=other`.
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.split_once('=') {
+                Some((namespace, secret)) if !namespace.trim().is_empty() && !secret.is_empty() => {
+                    Ok((namespace.trim().into(), secret.as_bytes().into()))
+                }
+                // The entry is not included, as it may contain a secret.
+                _ => {
+                    Err(Error::new("Protected namespaces must be of the form 'namespace=secret'."))
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+
+    /// Signs a registration with a secret, returning the value of its
+    /// signature metadata.
+    pub fn sign(secret: &[u8], registration: &RegisterRequest) -> String {
+        let digest = mac(secret, registration).finalize().into_bytes();
+        format!(
+            "{SIGNATURE_PREFIX}{}",
+            digest.iter().map(|byte| format!("{byte:02x}")).collect::<String>()
+        )
+    }
+
+    /// Verifies that a registration is signed with the secret of each
+    /// protected namespace covering one of its intents.
+    pub fn verify(
+        &self,
+        registration: &RegisterRequest,
+        metadata: &MetadataMap,
+    ) -> Result<(), Status> {
+        let signatures = metadata
+            .get_all(SIGNATURE_METADATA_KEY)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(decode_signature)
+            .collect::<Vec<_>>();
+
+        for (protected, secret) in &self.0 {
+            let Some(intent) =
+                registration.intents.iter().find(|intent| covers(protected, &intent.namespace))
+            else {
+                continue;
+            };
+
+            if signatures.is_empty() {
+                tracing::warn!(
+                    target: "audit",
+                    namespace = intent.namespace,
+                    "Rejected unsigned registration of protected namespace."
+                );
+                return Err(Status::permission_denied(format!(
+                    "Registration of protected namespace '{}' must be signed.",
+                    intent.namespace
+                )));
+            }
+
+            if !signatures
+                .iter()
+                .any(|signature| mac(secret, registration).verify_slice(signature).is_ok())
+            {
+                tracing::warn!(
+                    target: "audit",
+                    namespace = intent.namespace,
+                    "Rejected registration of protected namespace with invalid signature."
+                );
+                return Err(Status::permission_denied(format!(
+                    "Signature of the registration of protected namespace '{}' is not valid.",
+                    intent.namespace
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ProtectedNamespaces {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.0.iter().map(|(namespace, _)| namespace)).finish()
+    }
+}
+
+fn mac(secret: &[u8], registration: &RegisterRequest) -> Hmac<Sha256> {
+    // HMAC accepts keys of any length.
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+    mac.update(&registration.encode_to_vec());
+    mac
+}
+
+fn decode_signature(value: &str) -> Option<Vec<u8>> {
+    let digest = value.strip_prefix(SIGNATURE_PREFIX)?;
+    if digest.len() % 2 != 0 || !digest.is_ascii() {
+        return None;
+    }
+
+    (0..digest.len()).step_by(2).map(|i| u8::from_str_radix(&digest[i..i + 2], 16).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use intent_brokering_proto::runtime::{IntentRegistration, IntentServiceRegistration};
+    use tonic::{metadata::MetadataMap, Code};
+
+    use super::{ProtectedNamespaces, SIGNATURE_METADATA_KEY};
+
+    fn registration(namespaces: &[&str]) -> super::RegisterRequest {
+        super::RegisterRequest {
+            service: Some(IntentServiceRegistration {
+                name: "brakes".to_owned(),
+                version: "1.0".to_owned(),
+                url: "http://localhost:50051".to_owned(), // DevSkim: ignore DS137138
+                ..Default::default()
+            }),
+            intents: namespaces
+                .iter()
+                .map(|namespace| IntentRegistration {
+                    namespace: (*namespace).to_owned(),
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    fn signed(signatures: &[String]) -> MetadataMap {
+        let mut metadata = MetadataMap::new();
+        for signature in signatures {
+            metadata.append(SIGNATURE_METADATA_KEY, signature.parse().unwrap());
+        }
+        metadata
+    }
+
+    fn subject() -> ProtectedNamespaces {
+        ProtectedNamespaces::parse("vehicle.brakes=secret,vehicle.steering=other").unwrap()
+    }
+
+    #[test]
+    fn verify_accepts_registrations_signed_with_secrets_of_protected_namespaces() {
+        // arrange
+        let registration = registration(&["vehicle.brakes.front", "vehicle.steering"]);
+        let signatures = [
+            ProtectedNamespaces::sign(b"secret", &registration),
+            ProtectedNamespaces::sign(b"other", &registration),
+        ];
+
+        // act
+        let result = subject().verify(&registration, &signed(&signatures));
+
+        // assert
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn verify_accepts_unsigned_registrations_of_other_namespaces() {
+        // arrange
+        let registration = registration(&["vehicle.brakesystem", "sdv.kvs"]);
+
+        // act
+        let result = subject().verify(&registration, &MetadataMap::new());
+
+        // assert
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn verify_rejects_unsigned_registrations_of_protected_namespaces() {
+        // arrange
+        let registration = registration(&["sdv.kvs", "vehicle.brakes"]);
+
+        // act
+        let result = subject().verify(&registration, &MetadataMap::new());
+
+        // assert
+        assert_eq!(Code::PermissionDenied, result.unwrap_err().code());
+    }
+
+    #[test]
+    fn verify_rejects_registrations_with_invalid_signature() {
+        // arrange
+        let registration = registration(&["vehicle.brakes", "vehicle.steering"]);
+        let mut tampered = registration.clone();
+        tampered.service.as_mut().unwrap().url = "http://localhost:50052".to_owned(); // DevSkim: ignore DS137138
+        let cases = [
+            vec![ProtectedNamespaces::sign(b"secret", &registration)],
+            vec![
+                ProtectedNamespaces::sign(b"secret", &tampered),
+                ProtectedNamespaces::sign(b"other", &tampered),
+            ],
+            vec!["sha256=zz".to_owned(), ProtectedNamespaces::sign(b"other", &registration)],
+        ];
+
+        for signatures in cases {
+            // act
+            let result = subject().verify(&registration, &signed(&signatures));
+
+            // assert
+            assert_eq!(Code::PermissionDenied, result.unwrap_err().code());
+        }
+    }
+
+    #[test]
+    fn sign_computes_hmac_sha256_of_encoded_registration() {
+        // arrange
+        let registration = super::RegisterRequest::default();
+
+        // act
+        let signature = ProtectedNamespaces::sign(b"key", &registration);
+
+        // assert
+        assert_eq!(
+            "sha256=5d5d139563c95b5967b9bd9a8c9b233a9dedb45072794cd232dc1b74832607d0",
+            signature
+        );
+    }
+
+    #[test]
+    fn parse_fails_for_entries_without_secret() {
+        assert!(ProtectedNamespaces::parse("vehicle.brakes").is_err());
+        assert!(ProtectedNamespaces::parse("vehicle.brakes=").is_err());
+        assert!(ProtectedNamespaces::parse("=secret").is_err());
+    }
+}
@@ -4,15 +4,28 @@
 
 use core::fmt;
 use std::collections::{HashMap, HashSet};
-use std::time::{Duration, Instant};
-
-use intent_brokering_common::error::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use intent_brokering_common::{
+    error::{Error, ResultExt as _},
+    storage::Storage,
+    streaming_ess::Timestamped,
+};
+use intent_brokering_proto::common::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
 use url::Url;
 
+use crate::namespace::Namespaces;
+use crate::provider_url;
 use crate::streaming::StreamingEss;
 
 const SYSTEM_NAMESPACE: &str = "system";
 const SYSTEM_NAMESPACE_PREFIX: &str = "system.";
+const SNAPSHOT_VERSION: u32 = 1;
+const STORAGE_NAMESPACE: &str = "registry";
+const SNAPSHOT_KEY: &str = "snapshot";
 
 #[derive(Clone)]
 pub enum Change<'a> {
@@ -25,6 +38,12 @@ pub enum Change<'a> {
 pub trait Observer {
     /// Handles observation on changed registry state.
     fn on_change<'a>(&self, changes: impl Iterator<Item = Change<'a>> + Clone);
+
+    /// Drops state derived from registrations which no longer exist, when
+    /// the registry is compacted. Returns the number of reclaimed entries.
+    fn compact(&self) -> usize {
+        0
+    }
 }
 
 impl Observer for StreamingEss {
@@ -38,7 +57,10 @@ impl Observer for StreamingEss {
             })
             .collect::<HashSet<_>>()
         {
-            self.publish(format!("namespaces/{}", namespace).as_str(), ());
+            self.publish(
+                format!("namespaces/{}", namespace).as_str(),
+                Timestamped::now(ValueEnum::Null(0)),
+            );
         }
     }
 }
@@ -59,6 +81,10 @@ impl<T: Observer, U: Observer> Observer for Composite<T, U> {
         self.left.on_change(changes.clone());
         self.right.on_change(changes);
     }
+
+    fn compact(&self) -> usize {
+        self.left.compact() + self.right.compact()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -84,6 +110,23 @@ impl Default for Config {
     }
 }
 
+/// The entries reclaimed by compacting a registry, see [`Registry::compact`].
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Compaction {
+    /// Intents for which no service was registered.
+    pub empty_intents: usize,
+    /// Registrations of intents by services which were no longer known.
+    pub orphaned_registrations: usize,
+    /// Entries the observer derived from registrations which no longer exist.
+    pub stale_entries: usize,
+}
+
+impl Compaction {
+    pub fn total(&self) -> usize {
+        self.empty_intents + self.orphaned_registrations + self.stale_entries
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Specificity {
     Default,
@@ -94,8 +137,12 @@ pub enum Specificity {
 pub struct Registry<T: Observer> {
     external_services_by_intent: HashMap<IntentConfiguration, HashSet<ServiceConfiguration>>,
     known_services: HashMap<ServiceConfiguration, Instant>,
+    // The namespaces of the registered intents, which the configurations of
+    // the intents share.
+    namespaces: Namespaces,
     observer: T,
     config: Config,
+    changed: Arc<watch::Sender<()>>,
 }
 
 impl<T: Observer> Registry<T> {
@@ -103,8 +150,30 @@ impl<T: Observer> Registry<T> {
         Self {
             external_services_by_intent: HashMap::new(),
             known_services: HashMap::new(),
+            namespaces: Namespaces::new(),
             observer,
             config,
+            changed: Arc::new(watch::channel(()).0),
+        }
+    }
+
+    /// Returns a receiver which is marked as changed whenever the
+    /// registrations change, after the observer handled the changes.
+    pub fn subscribe_changes(&self) -> watch::Receiver<()> {
+        self.changed.subscribe()
+    }
+
+    /// Returns whether services are registered for all of the given intents
+    /// of a namespace, or for any intent of the namespace if no intents are
+    /// given.
+    pub fn serves(&self, namespace: &str, intents: &[IntentKind]) -> bool {
+        if intents.is_empty() {
+            self.external_services_by_intent.keys().any(|intent| intent.namespace() == namespace)
+        } else {
+            intents.iter().all(|intent| {
+                self.external_services_by_intent
+                    .contains_key(&IntentConfiguration::new(namespace, *intent))
+            })
         }
     }
 
@@ -164,6 +233,7 @@ impl<T: Observer> Registry<T> {
         let ttl = self.config.entry_ttl;
         let change_series = self.prune_by(|_, ts| timestamp.duration_since(ts) > ttl);
         change_series.observe(&self.observer, self);
+        self.namespaces.prune();
 
         self.known_services
             .values()
@@ -173,12 +243,45 @@ impl<T: Observer> Registry<T> {
             .unwrap_or((Default, timestamp + ttl))
     }
 
-    pub fn upsert(
-        &mut self,
-        service_configuration: ServiceConfiguration,
-        intent_configurations: Vec<IntentConfiguration>,
-        timestamp: Instant,
-    ) -> Result<(), Error> {
+    /// Removes intents for which no service is registered and registrations
+    /// by services which are no longer known, which may be left behind over
+    /// long uptimes, and releases the capacity no longer used. The observer
+    /// is notified of the removed registrations and compacted, too.
+    pub fn compact(&mut self) -> Compaction {
+        let mut compaction = Compaction::default();
+        let mut change_series = ChangeSeries::new();
+        let known_services = &self.known_services;
+
+        self.external_services_by_intent.retain(|intent_configuration, services| {
+            let service_count = services.len();
+            services.retain(|service| known_services.contains_key(service));
+            compaction.orphaned_registrations += service_count - services.len();
+
+            if services.is_empty() {
+                compaction.empty_intents += 1;
+                change_series.change(intent_configuration.clone(), ChangeKind::Remove);
+                return false;
+            }
+
+            if service_count != services.len() {
+                change_series.change(intent_configuration.clone(), ChangeKind::Modify);
+            }
+
+            services.shrink_to_fit();
+            true
+        });
+
+        self.external_services_by_intent.shrink_to_fit();
+        self.known_services.shrink_to_fit();
+
+        change_series.observe(&self.observer, self);
+        self.namespaces.prune();
+        self.namespaces.shrink_to_fit();
+        compaction.stale_entries = self.observer.compact();
+        compaction
+    }
+
+    fn validate(intent_configurations: &[IntentConfiguration]) -> Result<(), Error> {
         fn starts_with_ignore_ascii_case(string: &str, prefix: &str) -> bool {
             string.len() >= prefix.len()
                 && string.as_bytes()[0..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
@@ -186,13 +289,30 @@ impl<T: Observer> Registry<T> {
 
         if intent_configurations.iter().any(|ic| {
             ic.namespace.eq_ignore_ascii_case(SYSTEM_NAMESPACE)
-                || starts_with_ignore_ascii_case(ic.namespace.as_str(), SYSTEM_NAMESPACE_PREFIX)
+                || starts_with_ignore_ascii_case(&ic.namespace, SYSTEM_NAMESPACE_PREFIX)
         }) {
             return Err(Error::new(
                 "It is not possible to overwrite an existing system registration",
             ));
         }
 
+        Ok(())
+    }
+
+    fn validate_url(url: &Url) -> Result<(), Error> {
+        provider_url::validate(url)
+            .map_err(|e| Error::from_error(format!("Service URL is not allowed: {e}"), Box::new(e)))
+    }
+
+    pub fn upsert(
+        &mut self,
+        service_configuration: ServiceConfiguration,
+        intent_configurations: Vec<IntentConfiguration>,
+        timestamp: Instant,
+    ) -> Result<(), Error> {
+        Self::validate(&intent_configurations)?;
+        Self::validate_url(service_configuration.url())?;
+
         // Upserting a registration should not happen frequently and has worse
         // performance than service resolution.
 
@@ -202,6 +322,8 @@ impl<T: Observer> Registry<T> {
         // used for each intent.
 
         for intent_configuration in intent_configurations {
+            let intent_configuration = intent_configuration.intern(&mut self.namespaces);
+
             // Update the list of registry changes.
 
             match self.external_services_by_intent.contains_key(&intent_configuration) {
@@ -223,11 +345,147 @@ impl<T: Observer> Registry<T> {
 
         // Notify the observer
 
+        change_series.observe(&self.observer, self);
+        self.namespaces.prune();
+
+        Ok(())
+    }
+
+    /// Returns the configurations of all known services.
+    pub fn services(&self) -> impl Iterator<Item = &ServiceConfiguration> {
+        self.known_services.keys()
+    }
+
+    /// Returns the services registered for an intent, in the order in which
+    /// they are bound.
+    pub fn services_for(
+        &self,
+        intent: &IntentConfiguration,
+    ) -> impl Iterator<Item = &ServiceConfiguration> + Clone {
+        self.external_services_by_intent.get(intent).into_iter().flatten()
+    }
+
+    /// Removes a service along with its registrations, e.g. because it is no
+    /// longer reachable. Returns whether the service was known.
+    pub fn remove(&mut self, key: &ServiceConfiguration) -> bool {
+        if !self.known_services.contains_key(key) {
+            return false;
+        }
+
+        let change_series = self.prune_by(|service, _| service == key);
+        change_series.observe(&self.observer, self);
+        self.namespaces.prune();
+        true
+    }
+
+    /// Captures all registrations in a snapshot, together with the time
+    /// since each service was last announced.
+    pub fn export(&self, timestamp: Instant) -> Snapshot {
+        let mut intents_by_service: HashMap<&ServiceConfiguration, Vec<IntentConfiguration>> =
+            self.known_services.keys().map(|service| (service, Vec::new())).collect();
+
+        for (intent, services) in &self.external_services_by_intent {
+            for service in services {
+                intents_by_service.entry(service).or_default().push(intent.clone());
+            }
+        }
+
+        let mut services: Vec<_> = intents_by_service
+            .into_iter()
+            .map(|(service, mut intents)| {
+                intents.sort_by(|a, b| (&a.namespace, a.intent).cmp(&(&b.namespace, b.intent)));
+                ServiceSnapshot {
+                    name: service.id.name(),
+                    version: service.id.version(),
+                    url: service.url.clone(),
+                    locality: service.locality.clone(),
+                    transactional: service.transactional,
+                    max_concurrency: service.max_concurrency,
+                    intents: intents.into_iter().map(IntentSnapshot::from).collect(),
+                    last_announced_ms_ago: timestamp
+                        .saturating_duration_since(self.known_services[service])
+                        .as_millis()
+                        .try_into()
+                        .unwrap_or(u64::MAX),
+                }
+            })
+            .collect();
+
+        services.sort_by(|a, b| (&a.name, &a.version, &a.url).cmp(&(&b.name, &b.version, &b.url)));
+
+        Snapshot { version: SNAPSHOT_VERSION, services }
+    }
+
+    /// Replaces all registrations with the ones captured in a snapshot. The
+    /// registry is not changed if the snapshot is not valid.
+    pub fn import(&mut self, snapshot: Snapshot, timestamp: Instant) -> Result<(), Error> {
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(Error::new(format!(
+                "Snapshot version {} is not supported.",
+                snapshot.version
+            )));
+        }
+
+        let registrations = snapshot
+            .services
+            .into_iter()
+            .map(|service| {
+                let intents: Vec<_> =
+                    service.intents.into_iter().map(IntentConfiguration::from).collect();
+                Self::validate(&intents)?;
+                Self::validate_url(&service.url)?;
+
+                let configuration = ServiceConfiguration::new(
+                    ServiceId::new(service.name, service.version),
+                    service.url,
+                    service.locality,
+                )
+                .set_transactional(service.transactional)
+                .set_max_concurrency(service.max_concurrency);
+
+                let announced = timestamp
+                    .checked_sub(Duration::from_millis(service.last_announced_ms_ago))
+                    .unwrap_or(timestamp);
+
+                Ok((configuration, intents, announced))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // Services which are part of the snapshot are replaced when upserted,
+        // hence only the other services are removed up front.
+        let ids: HashSet<_> =
+            registrations.iter().map(|(service, ..)| service.id.clone()).collect();
+        let change_series = self.prune_by(|service, _| !ids.contains(&service.id));
         change_series.observe(&self.observer, self);
 
+        for (configuration, intents, announced) in registrations {
+            self.upsert(configuration, intents, announced)?;
+        }
+
         Ok(())
     }
 
+    /// Persists a snapshot of all registrations to a storage, see
+    /// [`Self::export`].
+    pub fn save(&self, storage: &dyn Storage, timestamp: Instant) -> Result<(), Error> {
+        let snapshot = serde_json::to_vec(&self.export(timestamp))
+            .map_err_with("Failed to serialize the registry snapshot.")?;
+        storage.put(STORAGE_NAMESPACE, SNAPSHOT_KEY, &snapshot)
+    }
+
+    /// Replaces all registrations with the ones persisted to a storage, see
+    /// [`Self::import`]. Returns whether registrations were persisted.
+    pub fn load(&mut self, storage: &dyn Storage, timestamp: Instant) -> Result<bool, Error> {
+        let Some(snapshot) = storage.get(STORAGE_NAMESPACE, SNAPSHOT_KEY)? else {
+            return Ok(false);
+        };
+
+        let snapshot = serde_json::from_slice(&snapshot)
+            .map_err_with("The persisted registry snapshot is not valid.")?;
+        self.import(snapshot, timestamp)?;
+        Ok(true)
+    }
+
     #[cfg(test)]
     pub fn count_external_intents(&self) -> usize {
         self.external_services_by_intent.len()
@@ -281,10 +539,60 @@ impl ChangeSeries {
 
         if changes.len() > 0 {
             observer.on_change(changes);
+            registry.changed.send_replace(());
         };
     }
 }
 
+/// A versioned capture of the registrations of a registry, which can be
+/// serialized to JSON.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Snapshot {
+    version: u32,
+    services: Vec<ServiceSnapshot>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ServiceSnapshot {
+    name: Box<str>,
+    version: Box<str>,
+    url: Url,
+    locality: ExecutionLocality,
+    transactional: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_concurrency: Option<u32>,
+    intents: Vec<IntentSnapshot>,
+    last_announced_ms_ago: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IntentSnapshot {
+    namespace: String,
+    intent: IntentKind,
+}
+
+impl Snapshot {
+    /// The URLs of the services captured in the snapshot.
+    pub fn urls(&self) -> impl Iterator<Item = &Url> {
+        self.services.iter().map(|service| &service.url)
+    }
+}
+
+impl From<IntentConfiguration> for IntentSnapshot {
+    fn from(value: IntentConfiguration) -> Self {
+        Self { namespace: value.namespace.as_ref().into(), intent: value.intent }
+    }
+}
+
+impl From<IntentSnapshot> for IntentConfiguration {
+    fn from(value: IntentSnapshot) -> Self {
+        Self::new(value.namespace, value.intent)
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Debug, Hash)]
 pub struct ServiceId(Box<str>, Box<str>);
 
@@ -307,11 +615,33 @@ pub struct ServiceConfiguration {
     id: ServiceId,
     url: Url,
     locality: ExecutionLocality,
+    transactional: bool,
+    max_concurrency: Option<u32>,
 }
 
 impl ServiceConfiguration {
     pub fn new(id: ServiceId, url: Url, locality: ExecutionLocality) -> Self {
-        Self { id, url, locality }
+        Self { id, url, locality, transactional: false, max_concurrency: None }
+    }
+
+    /// Sets whether the service can take part in transactions.
+    pub fn set_transactional(mut self, value: bool) -> Self {
+        self.transactional = value;
+        self
+    }
+
+    pub fn transactional(&self) -> bool {
+        self.transactional
+    }
+
+    /// Limits the number of requests forwarded to the service at once.
+    pub fn set_max_concurrency(mut self, value: Option<u32>) -> Self {
+        self.max_concurrency = value;
+        self
+    }
+
+    pub fn max_concurrency(&self) -> Option<u32> {
+        self.max_concurrency
     }
 
     pub fn locality(&self) -> &ExecutionLocality {
@@ -327,33 +657,81 @@ impl ServiceConfiguration {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ExecutionLocality {
     Local,
     Cloud,
 }
 
+/// An intent of a namespace. The namespace is shared between clones and the
+/// configurations created with [`Self::with_intent`], such that copying a
+/// configuration on the hot path does not allocate.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct IntentConfiguration {
-    namespace: String,
+    namespace: Arc<str>,
     intent: IntentKind,
 }
 
 impl IntentConfiguration {
-    pub fn new(namespace: impl Into<String>, intent: IntentKind) -> Self {
+    pub fn new(namespace: impl Into<Arc<str>>, intent: IntentKind) -> Self {
         Self { namespace: namespace.into(), intent }
     }
 
-    pub fn into_namespaced_intent(self) -> (String, IntentKind) {
+    /// Returns the configuration of another intent of the same namespace.
+    pub fn with_intent(&self, intent: IntentKind) -> Self {
+        Self { namespace: Arc::clone(&self.namespace), intent }
+    }
+
+    pub fn into_namespaced_intent(self) -> (Arc<str>, IntentKind) {
         (self.namespace, self.intent)
     }
 
     pub fn namespace(&self) -> &str {
         &self.namespace
     }
+
+    /// Returns the namespace, shared with the configuration.
+    pub fn shared_namespace(&self) -> &Arc<str> {
+        &self.namespace
+    }
+
+    /// Shares the namespace interned in a pool, see [`crate::namespace`].
+    pub fn intern(self, namespaces: &mut Namespaces) -> Self {
+        Self { namespace: namespaces.intern(&self.namespace), intent: self.intent }
+    }
+
+    pub fn intent(&self) -> IntentKind {
+        self.intent
+    }
+}
+
+/// Marks a registered intent as deprecated, such that consumers can migrate
+/// before the provider stops serving it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Deprecation {
+    sunset: Option<SystemTime>,
+    replacement: Option<Box<str>>,
+}
+
+impl Deprecation {
+    pub fn new(sunset: Option<SystemTime>, replacement: Option<Box<str>>) -> Self {
+        Self { sunset, replacement }
+    }
+
+    /// When the intent will be removed, if known.
+    pub fn sunset(&self) -> Option<SystemTime> {
+        self.sunset
+    }
+
+    /// What to use instead, e.g. a namespace, if any.
+    pub fn replacement(&self) -> Option<&str> {
+        self.replacement.as_deref()
+    }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum IntentKind {
     Discover,
     Inspect,
@@ -361,6 +739,19 @@ pub enum IntentKind {
     Write,
     Invoke,
     Subscribe,
+    Delete,
+}
+
+impl IntentKind {
+    pub(crate) const ALL: [IntentKind; 7] = [
+        IntentKind::Discover,
+        IntentKind::Inspect,
+        IntentKind::Read,
+        IntentKind::Write,
+        IntentKind::Invoke,
+        IntentKind::Subscribe,
+        IntentKind::Delete,
+    ];
 }
 
 impl fmt::Display for IntentKind {
@@ -372,6 +763,7 @@ impl fmt::Display for IntentKind {
             IntentKind::Write => "write",
             IntentKind::Invoke => "invoke",
             IntentKind::Subscribe => "subscribe",
+            IntentKind::Delete => "delete",
         })
     }
 }
@@ -617,6 +1009,24 @@ pub(crate) mod tests {
         }
     }
 
+    #[test]
+    fn upsert_rejects_service_with_blocked_url() {
+        // arrange
+        let mut registry = create_registry();
+        let service = ServiceConfigurationBuilder::new().url("http://169.254.169.254").build();
+
+        // act
+        let result =
+            registry.upsert(service, vec![IntentConfigurationBuilder::new().build()], now());
+
+        // assert
+        let error = result.unwrap_err();
+        assert!(std::error::Error::source(&error)
+            .and_then(|source| source.downcast_ref::<provider_url::InvalidUrl>())
+            .is_some());
+        assert_eq!(0, registry.services().count());
+    }
+
     #[test_case(Specificity::Default, 15, 0, [])]
     #[test_case(Specificity::Default, 15, 5, [])]
     #[test_case(Specificity::Default, 15, 15, [])]
@@ -751,6 +1161,244 @@ pub(crate) mod tests {
         assert!(found2);
     }
 
+    #[test]
+    fn remove_removes_service_and_its_intents() {
+        // arrange
+        let mut registry = create_registry();
+        let service = ServiceConfigurationBuilder::new().build();
+        let intent = IntentConfigurationBuilder::new().build();
+        registry.upsert(service.clone(), vec![intent.clone()], now()).unwrap();
+
+        // act
+        let removed = registry.remove(&service);
+
+        // assert
+        assert!(removed);
+        assert!(!registry.has_service(&service));
+        assert_eq!(0, registry.count_external_intents());
+        registry.observer.assert_removed(&intent);
+    }
+
+    #[test]
+    fn upsert_shares_namespace_of_intents_until_removed() {
+        // arrange
+        let mut registry = create_registry();
+        let first = ServiceConfigurationBuilder::with_nonce("1").build();
+        let second = ServiceConfigurationBuilder::with_nonce("2").build();
+        let read = IntentConfiguration::new("sdv.vdt", IntentKind::Read);
+        let write = IntentConfiguration::new("sdv.vdt", IntentKind::Write);
+
+        // act
+        registry.upsert(first.clone(), vec![read], now()).unwrap();
+        registry.upsert(second.clone(), vec![write], now()).unwrap();
+
+        // assert
+        let namespaces: Vec<_> = registry
+            .external_services_by_intent
+            .keys()
+            .map(|intent| Arc::clone(intent.shared_namespace()))
+            .collect();
+        assert_eq!(2, namespaces.len());
+        assert!(Arc::ptr_eq(&namespaces[0], &namespaces[1]));
+        drop(namespaces);
+        registry.remove(&first);
+        registry.remove(&second);
+        registry.observer.clear();
+        registry.compact();
+        assert!(registry.namespaces.is_empty());
+    }
+
+    #[test]
+    fn remove_returns_false_if_service_is_unregistered() {
+        // arrange
+        let mut registry = create_registry();
+        let service = ServiceConfigurationBuilder::new().build();
+
+        // act
+        let removed = registry.remove(&service);
+
+        // assert
+        assert!(!removed);
+    }
+
+    #[test]
+    fn import_restores_exported_registrations() {
+        // arrange
+        let mut source = create_registry();
+        let timestamp = now();
+        let service = ServiceConfigurationBuilder::new().transactional(true).build();
+        let intent = IntentConfigurationBuilder::new().build();
+        source.upsert(service.clone(), vec![intent.clone()], timestamp).unwrap();
+        let empty_service = ServiceConfigurationBuilder::with_nonce("1").build();
+        source.upsert(empty_service.clone(), vec![], timestamp).unwrap();
+        let snapshot: Snapshot =
+            serde_json::from_str(&serde_json::to_string(&source.export(timestamp)).unwrap())
+                .unwrap();
+        let mut subject = create_registry();
+
+        // act
+        subject.import(snapshot, timestamp).unwrap();
+
+        // assert
+        assert!(subject.has_service(&service));
+        assert!(subject.has_service(&empty_service));
+        assert!(subject.serves(intent.namespace(), &[intent.intent()]));
+        assert_eq!(source.export(timestamp), subject.export(timestamp));
+        subject.observer.assert_added(&intent, |services| assert_eq!(&vec![service], services));
+    }
+
+    #[test]
+    fn import_replaces_existing_registrations() {
+        // arrange
+        let mut source = create_registry();
+        let service = ServiceConfigurationBuilder::new().build();
+        source.upsert(service.clone(), vec![], now()).unwrap();
+        let mut subject = Setup::new().build();
+        let other = ServiceConfigurationBuilder::with_nonce("1").build();
+        let other_intent = IntentConfigurationBuilder::with_nonce("1").build();
+        subject.upsert(other.clone(), vec![other_intent.clone()], now()).unwrap();
+
+        // act
+        subject.import(source.export(now()), now()).unwrap();
+
+        // assert
+        assert!(subject.has_service(&service));
+        assert!(!subject.has_service(&other));
+        assert_eq!(0, subject.count_external_intents());
+        subject.observer.assert_removed(&other_intent);
+    }
+
+    #[test]
+    fn load_restores_saved_registrations() {
+        // arrange
+        let storage = intent_brokering_common::storage::InMemoryStorage::new();
+        let mut source = create_registry();
+        let service = ServiceConfigurationBuilder::new().build();
+        let intent = IntentConfigurationBuilder::new().build();
+        source.upsert(service.clone(), vec![intent.clone()], now()).unwrap();
+        let mut subject = create_registry();
+        let not_loaded = subject.load(&storage, now()).unwrap();
+        source.save(&storage, now()).unwrap();
+
+        // act
+        let loaded = subject.load(&storage, now()).unwrap();
+
+        // assert
+        assert!(!not_loaded);
+        assert!(loaded);
+        assert!(subject.has_service(&service));
+        assert_eq!(1, subject.count_external_intents());
+    }
+
+    #[test]
+    fn import_keeps_time_since_last_announcement() {
+        // arrange
+        let mut source = create_registry();
+        let announced = now();
+        let service = ServiceConfigurationBuilder::new().build();
+        source.upsert(service.clone(), vec![], announced).unwrap();
+        let ttl = source.config.entry_ttl();
+        let mut subject = create_registry();
+
+        // act
+        subject.import(source.export(announced + ttl), announced + ttl).unwrap();
+        subject.prune(announced + ttl + Duration::from_millis(1));
+
+        // assert
+        assert!(!subject.has_service(&service));
+    }
+
+    #[test]
+    fn import_fails_for_unsupported_version_or_system_namespace() {
+        // arrange
+        let mut subject = Setup::new().build();
+        let service = ServiceConfigurationBuilder::new().build();
+        let snapshot = |version: u32, namespace: &str| Snapshot {
+            version,
+            services: vec![ServiceSnapshot {
+                name: service.id.name(),
+                version: service.id.version(),
+                url: service.url.clone(),
+                locality: ExecutionLocality::Local,
+                transactional: false,
+                max_concurrency: None,
+                intents: vec![IntentSnapshot {
+                    namespace: namespace.to_owned(),
+                    intent: IntentKind::Read,
+                }],
+                last_announced_ms_ago: 0,
+            }],
+        };
+
+        // act
+        let unsupported_version = subject.import(snapshot(SNAPSHOT_VERSION + 1, "foo"), now());
+        let system_namespace = subject.import(snapshot(SNAPSHOT_VERSION, "system.foo"), now());
+
+        // assert
+        assert!(unsupported_version.is_err());
+        assert!(system_namespace.is_err());
+        assert_eq!(1, subject.count_external_intents());
+        assert!(subject.observer.is_empty());
+    }
+
+    #[test]
+    fn import_fails_for_blocked_url_without_changing_registrations() {
+        // arrange
+        let mut source = create_registry();
+        source.upsert(ServiceConfigurationBuilder::new().build(), vec![], now()).unwrap();
+        let mut snapshot = source.export(now());
+        snapshot.services.push(ServiceSnapshot {
+            name: "blocked".into(),
+            url: "http://169.254.169.254".parse().unwrap(), // DevSkim: ignore DS137138
+            ..snapshot.services[0].clone()
+        });
+        let mut subject = Setup::new().build();
+
+        // act
+        let result = subject.import(snapshot, now());
+
+        // assert
+        assert!(result.is_err());
+        assert_eq!(1, subject.count_external_intents());
+        assert!(subject.observer.is_empty());
+    }
+
+    #[test]
+    fn serves_returns_whether_intents_are_registered() {
+        // arrange
+        let mut registry = create_registry();
+        let service = ServiceConfigurationBuilder::new().build();
+        let intent = IntentConfigurationBuilder::new().build();
+        registry.upsert(service, vec![intent.clone()], now()).unwrap();
+
+        // act
+        let any = registry.serves(intent.namespace(), &[]);
+        let registered = registry.serves(intent.namespace(), &[intent.intent()]);
+        let unregistered = registry.serves(intent.namespace(), &[IntentKind::Delete]);
+        let other_namespace = registry.serves("other", &[]);
+
+        // assert
+        assert!(any);
+        assert!(registered);
+        assert!(!unregistered);
+        assert!(!other_namespace);
+    }
+
+    #[test]
+    fn subscribe_changes_is_notified_on_change() {
+        // arrange
+        let mut registry = create_registry();
+        let changes = registry.subscribe_changes();
+        let service = ServiceConfigurationBuilder::new().build();
+        let intent = IntentConfigurationBuilder::new().build();
+
+        // act
+        registry.upsert(service, vec![intent], now()).unwrap();
+
+        // assert
+        assert!(changes.has_changed().unwrap());
+    }
+
     #[test]
     fn test_create_new_service_configuration() {
         let service = ServiceConfiguration::new(
@@ -767,7 +1415,7 @@ pub(crate) mod tests {
     #[test]
     fn test_create_new_intent_configuration() {
         let intent = IntentConfiguration::new("namespace".to_string(), IntentKind::Discover);
-        assert_eq!(intent.namespace, "namespace");
+        assert_eq!(&*intent.namespace, "namespace");
         assert_eq!(intent.intent, IntentKind::Discover);
     }
 
@@ -796,6 +1444,7 @@ pub(crate) mod tests {
             IntentKind::Write => {}
             IntentKind::Invoke => {}
             IntentKind::Subscribe => {}
+            IntentKind::Delete => {}
         }
 
         test("discover", IntentKind::Discover);
@@ -804,6 +1453,7 @@ pub(crate) mod tests {
         test("write", IntentKind::Write);
         test("invoke", IntentKind::Invoke);
         test("subscribe", IntentKind::Subscribe);
+        test("delete", IntentKind::Delete);
 
         fn test(expected: &str, intent_kind: IntentKind) {
             assert_eq!(expected, format!("{}", intent_kind));
@@ -886,7 +1536,7 @@ pub(crate) mod tests {
             const CLIENT_ID: &str = "CLIENT";
 
             let subject = StreamingEss::new();
-            let (_, stream) = subject.read_events(CLIENT_ID.into());
+            let (_, stream) = subject.read_events(CLIENT_ID.into()).unwrap();
 
             // always subscribe to all possible namespace changes.
             for nonce in [INTENT_A, INTENT_B, INTENT_C] {
@@ -896,6 +1546,7 @@ pub(crate) mod tests {
                         SubscribeIntent {
                             channel_id: CLIENT_ID.into(),
                             sources: vec![namespace_event(intent.namespace())],
+                            ..Default::default()
                         },
                         |_| Value::Null(0),
                     )
@@ -1040,6 +1691,37 @@ pub(crate) mod tests {
         }
     }
 
+    #[test]
+    fn compact_removes_empty_intents_and_orphaned_registrations() {
+        // arrange
+        let mut registry = create_registry();
+        let known = ServiceConfigurationBuilder::new().name("known").build();
+        let unknown = ServiceConfigurationBuilder::new().name("unknown").build();
+        let shared = IntentConfigurationBuilder::new().namespace("sdv.shared").build();
+        let orphaned = IntentConfigurationBuilder::new().namespace("sdv.orphaned").build();
+        let empty = IntentConfigurationBuilder::new().namespace("sdv.empty").build();
+        registry.upsert(known.clone(), vec![shared.clone()], now()).unwrap();
+        registry.observer.clear();
+        registry.external_services_by_intent.get_mut(&shared).unwrap().insert(unknown.clone());
+        registry.external_services_by_intent.insert(orphaned.clone(), HashSet::from([unknown]));
+        registry.external_services_by_intent.insert(empty.clone(), HashSet::new());
+
+        // act
+        let result = registry.compact();
+        let again = registry.compact();
+
+        // assert
+        assert_eq!(
+            Compaction { empty_intents: 2, orphaned_registrations: 2, stale_entries: 0 },
+            result
+        );
+        assert_eq!(0, again.total());
+        assert_eq!(1, registry.count_external_intents());
+        registry.observer.assert_modified(&shared, |services| assert_eq!(&vec![known], services));
+        registry.observer.assert_removed(&orphaned);
+        registry.observer.assert_removed(&empty);
+    }
+
     fn create_registry() -> Registry<MockBroker> {
         Registry::new(MockBroker::new(), Default::default())
     }
@@ -1113,6 +1795,16 @@ pub(crate) mod tests {
             self.0.locality = execution_locality;
             self
         }
+
+        pub fn transactional(mut self, transactional: bool) -> Self {
+            self.0.transactional = transactional;
+            self
+        }
+
+        pub fn max_concurrency(mut self, max_concurrency: u32) -> Self {
+            self.0.max_concurrency = Some(max_concurrency);
+            self
+        }
     }
 
     #[derive(Clone)]
@@ -1133,7 +1825,7 @@ pub(crate) mod tests {
             Self(IntentConfiguration::new(format!("namespace-{nonce}"), IntentKind::Discover))
         }
 
-        pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        pub fn namespace(mut self, namespace: impl Into<Arc<str>>) -> Self {
             self.0 = IntentConfiguration::new(namespace, self.0.intent);
             self
         }
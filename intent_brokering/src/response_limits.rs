@@ -0,0 +1,357 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Limits the size of the fulfillments of providers, such that a misbehaving
+//! provider cannot exhaust the memory of the Intent Broker or its consumers.
+//!
+//! The maximum size of an encoded fulfillment is configured per intent.
+//! Fulfillments exceeding it are rejected with `RESOURCE_EXHAUSTED`, except
+//! for `Inspect` fulfillments, whose entries are sorted by path and truncated
+//! to the maximum size, unless truncation is disabled. The response to a
+//! truncated `Inspect` intent carries a continuation token in its
+//! `x-chariott-continuation-token` metadata, which consumers pass in the
+//! metadata of the same intent to inspect the following entries.
+//!
+//! Responses of providers are not decoded beyond the largest limit, such
+//! that oversized fulfillments are rejected before they are buffered. As the
+//! size is limited per connection rather than per intent, intents without a
+//! limit and truncated `Inspect` fulfillments may still be as large as the
+//! default of gRPC, unless it is exceeded by the largest limit.
+
+use std::collections::HashMap;
+
+use intent_brokering_common::error::Error;
+use intent_brokering_proto::common::{FulfillmentEnum, FulfillmentMessage, InspectFulfillment};
+use prost::Message as _;
+use tonic::{metadata::MetadataMap, Status};
+
+use crate::acl::parse_intent_kind;
+use crate::registry::{IntentConfiguration, IntentKind};
+
+pub const CONTINUATION_METADATA_KEY: &str = "x-chariott-continuation-token";
+
+// The maximum size of messages decoded by gRPC by default.
+const DEFAULT_MAX_DECODING_SIZE: usize = 4 * 1024 * 1024;
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    limits: HashMap<IntentKind, usize>,
+    truncate_inspect: bool,
+}
+
+impl Config {
+    /// Parses a comma-separated list of intents and the maximum sizes of
+    /// their fulfillments in bytes, e.g. `inspect=65536,read=4096`.
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        let limits = value
+            .split(',')
+            .map(str::trim)
+            .filter(|limit| !limit.is_empty())
+            .map(|limit| {
+                let (intent, size) = limit
+                    .split_once('=')
+                    .and_then(|(intent, size)| Some((intent.trim(), size.trim().parse().ok()?)))
+                    .ok_or_else(|| {
+                        Error::new(format!(
+                            "Response size limit '{limit}' is not of the form 'intent=bytes'."
+                        ))
+                    })?;
+                Ok((parse_intent_kind(intent)?, size))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Self { limits, truncate_inspect: true })
+    }
+
+    /// The maximum size of the fulfillments of an intent, if limited.
+    pub fn limit(&self, intent: IntentKind) -> Option<usize> {
+        self.limits.get(&intent).copied()
+    }
+
+    /// Whether oversized `Inspect` fulfillments are truncated instead of
+    /// rejected.
+    pub fn truncate_inspect(&self) -> bool {
+        self.truncate_inspect
+    }
+
+    pub fn set_truncate_inspect(self, value: bool) -> Self {
+        Self { truncate_inspect: value, ..self }
+    }
+
+    fn truncates(&self, intent: IntentKind) -> bool {
+        intent == IntentKind::Inspect && self.truncate_inspect && self.limit(intent).is_some()
+    }
+
+    /// The maximum size of the responses decoded from providers, i.e. the
+    /// largest limit of the intents whose fulfillments are rejected if they
+    /// exceed it. It is at least the default of gRPC if an intent is not
+    /// limited or truncated.
+    pub fn max_decoding_size(&self) -> Option<usize> {
+        let largest = self
+            .limits
+            .iter()
+            .filter(|(intent, _)| !self.truncates(**intent))
+            .map(|(_, limit)| {
+                // The fulfillment is a length-delimited field of the response.
+                limit.saturating_add(1 + prost::length_delimiter_len(*limit))
+            })
+            .max();
+
+        if IntentKind::ALL
+            .iter()
+            .all(|intent| self.limit(*intent).is_some() && !self.truncates(*intent))
+        {
+            largest
+        } else {
+            largest.map(|largest| largest.max(DEFAULT_MAX_DECODING_SIZE))
+        }
+    }
+}
+
+pub struct ResponseLimits {
+    config: Config,
+}
+
+impl ResponseLimits {
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+
+    fn truncates(&self, intent: IntentKind) -> bool {
+        self.config.truncates(intent)
+    }
+
+    /// Returns the number of entries to skip for an `Inspect` intent, from
+    /// the continuation token in the metadata of its request, if any.
+    pub fn continuation(
+        &self,
+        intent: IntentKind,
+        metadata: &MetadataMap,
+    ) -> Result<Option<usize>, Status> {
+        if !self.truncates(intent) {
+            return Ok(None);
+        }
+
+        metadata
+            .get(CONTINUATION_METADATA_KEY)
+            .map(|token| {
+                token
+                    .to_str()
+                    .ok()
+                    .and_then(|token| token.parse().ok())
+                    .ok_or_else(|| Status::invalid_argument("Continuation token is not valid."))
+            })
+            .transpose()
+    }
+
+    /// Rejects a fulfillment exceeding the maximum size of its intent, or
+    /// truncates it if it is an `Inspect` fulfillment. Returns the
+    /// continuation token for the following entries if it was truncated.
+    pub fn enforce(
+        &self,
+        intent: &IntentConfiguration,
+        skip: Option<usize>,
+        fulfillment: Option<&mut FulfillmentMessage>,
+    ) -> Result<Option<usize>, Status> {
+        let (Some(limit), Some(fulfillment)) = (self.config.limit(intent.intent()), fulfillment)
+        else {
+            return Ok(None);
+        };
+
+        let exceeded = || {
+            tracing::warn!(
+                "Provider of namespace '{}' returned a fulfillment exceeding {limit} bytes.",
+                intent.namespace()
+            );
+            Status::resource_exhausted(format!(
+                "Fulfillment of '{}' intent of namespace '{}' exceeds the maximum size of {limit} bytes.",
+                intent.intent(),
+                intent.namespace()
+            ))
+        };
+
+        if self.truncates(intent.intent()) {
+            if let Some(FulfillmentEnum::Inspect(inspect)) = fulfillment.fulfillment.as_mut() {
+                return truncate(inspect, skip.unwrap_or(0), limit).ok_or_else(exceeded);
+            }
+        }
+
+        if fulfillment.encoded_len() > limit {
+            return Err(exceeded());
+        }
+
+        Ok(None)
+    }
+}
+
+/// Sorts the entries of an `Inspect` fulfillment by path, skips the given
+/// number of entries and keeps as many of the following entries as fit into
+/// the limit. Returns the number of entries to skip for the next page if
+/// entries were dropped, or `None` if not even one entry fits.
+fn truncate(inspect: &mut InspectFulfillment, skip: usize, limit: usize) -> Option<Option<usize>> {
+    if skip == 0 && inspect.encoded_len() <= limit {
+        return Some(None);
+    }
+
+    inspect.entries.sort_by(|a, b| a.path.cmp(&b.path));
+    inspect.entries.drain(..skip.min(inspect.entries.len()));
+
+    let mut size = 0;
+    let fitting = inspect
+        .entries
+        .iter()
+        .take_while(|entry| {
+            size += prost::encoding::message::encoded_len(1, *entry);
+            size <= limit
+        })
+        .count();
+
+    match fitting {
+        fitting if fitting == inspect.entries.len() => Some(None),
+        0 => None,
+        fitting => {
+            inspect.entries.truncate(fitting);
+            Some(Some(skip + fitting))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use intent_brokering_proto::common::{
+        inspect_fulfillment::Entry, FulfillmentEnum, FulfillmentMessage, InspectFulfillment,
+        ReadFulfillment, ValueEnum, ValueMessage,
+    };
+    use prost::Message as _;
+    use tonic::{metadata::MetadataMap, Code};
+
+    use super::{Config, ResponseLimits, CONTINUATION_METADATA_KEY, DEFAULT_MAX_DECODING_SIZE};
+    use crate::registry::{IntentConfiguration, IntentKind};
+
+    fn inspect(paths: &[&str]) -> FulfillmentMessage {
+        FulfillmentMessage {
+            fulfillment: Some(FulfillmentEnum::Inspect(InspectFulfillment {
+                entries: paths
+                    .iter()
+                    .map(|path| Entry { path: path.to_string(), items: HashMap::new() })
+                    .collect(),
+            })),
+        }
+    }
+
+    fn paths(fulfillment: &FulfillmentMessage) -> Vec<&str> {
+        match &fulfillment.fulfillment {
+            Some(FulfillmentEnum::Inspect(inspect)) => {
+                inspect.entries.iter().map(|entry| entry.path.as_str()).collect()
+            }
+            _ => panic!("Expected an inspect fulfillment."),
+        }
+    }
+
+    #[test]
+    fn parse_fails_for_invalid_limits() {
+        // act + assert
+        assert_eq!(Some(4096), Config::parse("read=4096").unwrap().limit(IntentKind::Read));
+        assert!(Config::parse("read").is_err());
+        assert!(Config::parse("read=-1").is_err());
+        assert!(Config::parse("listen=1").is_err());
+    }
+
+    #[test]
+    fn max_decoding_size_is_largest_limit_if_all_intents_are_limited() {
+        // arrange
+        let all = "discover=64,inspect=64,read=4096,write=64,invoke=64,subscribe=64,delete=64";
+
+        // act
+        let limited = Config::parse(all).unwrap().set_truncate_inspect(false);
+        let truncated = Config::parse(all).unwrap();
+        let partial = Config::parse("read=4096").unwrap();
+        let large = Config::parse("read=8388608").unwrap();
+
+        // assert
+        assert_eq!(Some(4096 + 3), limited.max_decoding_size());
+        assert_eq!(Some(DEFAULT_MAX_DECODING_SIZE), truncated.max_decoding_size());
+        assert_eq!(Some(DEFAULT_MAX_DECODING_SIZE), partial.max_decoding_size());
+        assert_eq!(Some(8388608 + 5), large.max_decoding_size());
+        assert_eq!(None, Config::parse("").unwrap().max_decoding_size());
+    }
+
+    #[test]
+    fn enforce_rejects_oversized_fulfillments() {
+        // arrange
+        let subject = ResponseLimits::new(Config::parse("read=64").unwrap());
+        let read = |value: &str| FulfillmentMessage {
+            fulfillment: Some(FulfillmentEnum::Read(ReadFulfillment {
+                value: Some(ValueMessage { value: Some(ValueEnum::String(value.to_owned())) }),
+                ..Default::default()
+            })),
+        };
+        let intent = IntentConfiguration::new("sdv.vdt", IntentKind::Read);
+
+        // act
+        let small = subject.enforce(&intent, None, Some(&mut read("small")));
+        let oversized = subject.enforce(&intent, None, Some(&mut read(&"x".repeat(64))));
+
+        // assert
+        assert_eq!(None, small.unwrap());
+        assert_eq!(Code::ResourceExhausted, oversized.unwrap_err().code());
+    }
+
+    #[test]
+    fn enforce_truncates_inspect_fulfillments_and_continues() {
+        // arrange
+        let entry_size = inspect(&["a"]).encoded_len() - 2;
+        let subject =
+            ResponseLimits::new(Config::parse(&format!("inspect={}", 2 * entry_size)).unwrap());
+        let intent = IntentConfiguration::new("sdv.vdt", IntentKind::Inspect);
+        let mut first = inspect(&["c", "a", "b"]);
+        let mut second = inspect(&["c", "a", "b"]);
+
+        // act
+        let token = subject.enforce(&intent, None, Some(&mut first)).unwrap();
+        let next = subject.enforce(&intent, token, Some(&mut second)).unwrap();
+
+        // assert
+        assert_eq!(Some(2), token);
+        assert_eq!(vec!["a", "b"], paths(&first));
+        assert_eq!(None, next);
+        assert_eq!(vec!["c"], paths(&second));
+    }
+
+    #[test]
+    fn enforce_rejects_inspect_fulfillments_if_truncation_is_disabled() {
+        // arrange
+        let subject =
+            ResponseLimits::new(Config::parse("inspect=4").unwrap().set_truncate_inspect(false));
+        let intent = IntentConfiguration::new("sdv.vdt", IntentKind::Inspect);
+
+        // act
+        let result = subject.enforce(&intent, None, Some(&mut inspect(&["a", "b"])));
+
+        // assert
+        assert_eq!(Code::ResourceExhausted, result.unwrap_err().code());
+    }
+
+    #[test]
+    fn continuation_is_read_from_metadata() {
+        // arrange
+        let subject = ResponseLimits::new(Config::parse("inspect=1024").unwrap());
+        let metadata = |token: &str| {
+            let mut metadata = MetadataMap::new();
+            metadata.insert(CONTINUATION_METADATA_KEY, token.parse().unwrap());
+            metadata
+        };
+
+        // act + assert
+        assert_eq!(Some(2), subject.continuation(IntentKind::Inspect, &metadata("2")).unwrap());
+        assert_eq!(None, subject.continuation(IntentKind::Inspect, &MetadataMap::new()).unwrap());
+        assert_eq!(None, subject.continuation(IntentKind::Read, &metadata("2")).unwrap());
+        assert_eq!(
+            Code::InvalidArgument,
+            subject.continuation(IntentKind::Inspect, &metadata("x")).unwrap_err().code()
+        );
+    }
+}
@@ -0,0 +1,280 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Holds intents for namespaces whose provider is restarting, instead of
+//! failing them, such that a provider which crashes and restarts is not
+//! noticed by its consumers.
+//!
+//! When the last provider of an intent unregisters or expires, its namespace
+//! enters the restart window configured for it. Intents which arrive during
+//! the window are held until a provider registers for them again, the window
+//! ends or the deadline of the request passes, whichever comes first. At most
+//! a bounded number of intents is held per namespace, and they are forwarded
+//! in the order in which they arrived once the provider is back.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use intent_brokering_common::error::Error;
+use tokio::time::{timeout_at, Instant};
+use tonic::{metadata::MetadataMap, Status};
+
+use crate::namespace::Durations;
+
+const GRPC_TIMEOUT_METADATA_KEY: &str = "grpc-timeout";
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    windows: Durations,
+    capacity: usize,
+}
+
+impl Config {
+    /// Parses a comma-separated list of namespaces and their restart windows
+    /// in milliseconds, e.g. `sdv.vdt=2000,sdv=500`. A namespace also covers
+    /// its sub-namespaces, and the window of the most specific namespace
+    /// applies.
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        let windows = Durations::parse(value, "Restart window")?;
+        Ok(Self { windows, capacity: 64 })
+    }
+
+    /// The maximum number of intents held per namespace.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn set_capacity(self, value: usize) -> Self {
+        Self { capacity: value, ..self }
+    }
+
+    /// Returns the restart window of the most specific namespace covering the
+    /// given one, if any.
+    pub fn window(&self, namespace: &str) -> Option<Duration> {
+        self.windows.get(namespace)
+    }
+}
+
+/// The intents held for a namespace.
+#[derive(Default)]
+struct Line {
+    held: usize,
+    /// Passed on to the held intents in the order in which they arrived.
+    turn: Arc<tokio::sync::Mutex<()>>,
+}
+
+pub struct RestartQueue {
+    config: Config,
+    lines: Mutex<HashMap<Box<str>, Line>>,
+}
+
+impl RestartQueue {
+    pub fn new(config: Config) -> Self {
+        Self { config, lines: Mutex::new(HashMap::new()) }
+    }
+
+    /// Returns until when to hold an intent of a namespace whose provider
+    /// departed at the given time, if its restart window did not end yet.
+    /// The deadline of the request, if any, cuts the window short.
+    pub fn deadline(
+        &self,
+        namespace: &str,
+        departed_at: Instant,
+        metadata: &MetadataMap,
+    ) -> Option<Instant> {
+        let now = Instant::now();
+        let end = departed_at + self.config.window(namespace)?;
+        let end = match request_timeout(metadata) {
+            Some(timeout) => end.min(now + timeout),
+            None => end,
+        };
+
+        (end > now).then_some(end)
+    }
+
+    /// Holds an intent of a namespace until it is its turn and `served`
+    /// completes, or the deadline passes.
+    pub async fn hold(
+        &self,
+        namespace: &str,
+        deadline: Instant,
+        served: impl Future<Output = Result<(), Status>>,
+    ) -> Result<(), Status> {
+        let turn = {
+            let mut lines = self.lines.lock().unwrap();
+            let line = lines.entry(namespace.into()).or_default();
+            if line.held >= self.config.capacity {
+                return Err(Status::resource_exhausted(format!(
+                    "Too many intents are held for namespace '{namespace}'."
+                )));
+            }
+            line.held += 1;
+            Arc::clone(&line.turn)
+        };
+        let _held = Held { queue: self, namespace };
+
+        tracing::debug!("Holding intent until the provider of '{namespace}' is back.");
+
+        let not_served =
+            |_| Status::deadline_exceeded(format!("Namespace '{namespace}' is not served."));
+        let _turn = timeout_at(deadline, turn.lock()).await.map_err(not_served)?;
+        timeout_at(deadline, served).await.map_err(not_served)?
+    }
+}
+
+/// Releases the place of an intent in the line of its namespace when
+/// dropped.
+struct Held<'a> {
+    queue: &'a RestartQueue,
+    namespace: &'a str,
+}
+
+impl Drop for Held<'_> {
+    fn drop(&mut self) {
+        let mut lines = self.queue.lines.lock().unwrap();
+        if let Some(line) = lines.get_mut(self.namespace) {
+            line.held -= 1;
+            if line.held == 0 {
+                lines.remove(self.namespace);
+            }
+        }
+    }
+}
+
+/// Returns the timeout of a request from its `grpc-timeout` metadata, e.g.
+/// `500m` for 500 milliseconds. The value has at most 8 digits, as per the
+/// gRPC protocol.
+fn request_timeout(metadata: &MetadataMap) -> Option<Duration> {
+    let timeout = metadata.get(GRPC_TIMEOUT_METADATA_KEY)?.to_str().ok()?;
+    let (value, unit) = timeout.split_at(timeout.len().checked_sub(1)?);
+    if value.is_empty() || value.len() > 8 || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let value: u64 = value.parse().ok()?;
+
+    Some(match unit {
+        "H" => Duration::from_secs(value.checked_mul(3600)?),
+        "M" => Duration::from_secs(value.checked_mul(60)?),
+        "S" => Duration::from_secs(value),
+        "m" => Duration::from_millis(value),
+        "u" => Duration::from_micros(value),
+        "n" => Duration::from_nanos(value),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::sync::Notify;
+    use tokio::time::Instant;
+    use tonic::{metadata::MetadataMap, Code};
+
+    use super::{request_timeout, Config, RestartQueue, GRPC_TIMEOUT_METADATA_KEY};
+
+    #[test]
+    fn window_of_most_specific_namespace_applies() {
+        // arrange
+        let subject = Config::parse("sdv=500, sdv.vdt=2000").unwrap();
+
+        // act + assert
+        assert_eq!(Some(Duration::from_millis(2000)), subject.window("sdv.vdt.cabin"));
+        assert_eq!(Some(Duration::from_millis(500)), subject.window("sdv.camera"));
+        assert_eq!(None, subject.window("sdvx"));
+        assert!(Config::parse("sdv").is_err());
+    }
+
+    #[test]
+    fn deadline_is_cut_short_by_request_timeout() {
+        // arrange
+        let subject = RestartQueue::new(Config::parse("sdv=60000").unwrap());
+        let mut metadata = MetadataMap::new();
+        metadata.insert(GRPC_TIMEOUT_METADATA_KEY, "100m".parse().unwrap());
+        let now = Instant::now();
+
+        // act
+        let window = subject.deadline("sdv.vdt", now, &MetadataMap::new()).unwrap();
+        let cut = subject.deadline("sdv.vdt", now, &metadata).unwrap();
+        let ended = subject.deadline("sdv.vdt", now - Duration::from_secs(60), &metadata);
+
+        // assert
+        assert!(window >= now + Duration::from_secs(60));
+        assert!(cut < now + Duration::from_secs(1));
+        assert_eq!(None, ended);
+    }
+
+    #[test]
+    fn request_timeout_is_parsed() {
+        // arrange
+        let metadata = |timeout: &str| {
+            let mut metadata = MetadataMap::new();
+            metadata.insert(GRPC_TIMEOUT_METADATA_KEY, timeout.parse().unwrap());
+            metadata
+        };
+
+        // act + assert
+        assert_eq!(Some(Duration::from_secs(120)), request_timeout(&metadata("2M")));
+        assert_eq!(Some(Duration::from_millis(500)), request_timeout(&metadata("500m")));
+        assert_eq!(None, request_timeout(&metadata("500x")));
+        assert_eq!(None, request_timeout(&metadata("123456789H")));
+        assert_eq!(None, request_timeout(&metadata("+5S")));
+        assert_eq!(None, request_timeout(&MetadataMap::new()));
+    }
+
+    #[tokio::test]
+    async fn hold_releases_intents_in_order_of_arrival() {
+        // arrange
+        let subject = Arc::new(RestartQueue::new(Config::parse("sdv=60000").unwrap()));
+        let served = Arc::new(Notify::new());
+        let order = Arc::new(std::sync::Mutex::new(vec![]));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut tasks = vec![];
+        for i in 0..3 {
+            let (subject, served, order) = (subject.clone(), served.clone(), order.clone());
+            tasks.push(tokio::spawn(async move {
+                subject
+                    .hold("sdv.vdt", deadline, async {
+                        if i == 0 {
+                            served.notified().await;
+                        }
+                        order.lock().unwrap().push(i);
+                        Ok(())
+                    })
+                    .await
+            }));
+            tokio::task::yield_now().await;
+        }
+
+        // act
+        served.notify_one();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        // assert
+        assert_eq!(vec![0, 1, 2], *order.lock().unwrap());
+        assert!(subject.lines.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn hold_fails_if_too_many_intents_are_held_or_deadline_passes() {
+        // arrange
+        let subject = RestartQueue::new(Config::parse("sdv=60000").unwrap().set_capacity(1));
+        let deadline = Instant::now() + Duration::from_millis(50);
+
+        // act
+        let (first, second) = tokio::join!(
+            subject.hold("sdv.vdt", deadline, std::future::pending()),
+            subject.hold("sdv.vdt", deadline, std::future::pending()),
+        );
+
+        // assert
+        assert_eq!(Code::ResourceExhausted, second.unwrap_err().code());
+        assert_eq!(Code::DeadlineExceeded, first.unwrap_err().code());
+    }
+}
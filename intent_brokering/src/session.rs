@@ -0,0 +1,332 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Keeps the intents of a session on the same provider, e.g. for multi-step
+//! dialogs which hold state in the provider instance.
+//!
+//! Consumers name their session with a token in the `x-chariott-session`
+//! metadata of their requests. The first intent of a session for a namespace
+//! is forwarded to the provider tried first, as without a session, and the
+//! following intents of the session for the namespace are forwarded to the
+//! same provider, even if another provider is tried first by then. Intents
+//! which the provider does not serve are fulfilled as without a session, and
+//! `Subscribe` intents are not bound to sessions, as channels outlive them.
+//!
+//! Sessions expire once they were not used for their time to live. If the
+//! provider of a session unregisters or is unavailable, the session either is
+//! rebound to the provider tried first, or is aborted, such that its intents
+//! fail with `ABORTED` until the session expires, depending on the failover
+//! of the configuration.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use intent_brokering_common::error::Error;
+use tonic::{metadata::MetadataMap, Status};
+use url::Url;
+
+use crate::intent_broker::IntentBroker;
+use crate::registry::IntentConfiguration;
+
+pub const SESSION_METADATA_KEY: &str = "x-chariott-session";
+
+const SESSION_CAPACITY: usize = 10_000;
+
+/// What happens to a session whose provider unregistered or is unavailable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Failover {
+    /// The session is bound to the provider tried first.
+    Rebind,
+    /// The intents of the session fail until it expires.
+    Abort,
+}
+
+impl FromStr for Failover {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "rebind" => Ok(Self::Rebind),
+            "abort" => Ok(Self::Abort),
+            _ => Err(Error::new(format!("Failover '{value}' is not one of 'rebind' or 'abort'."))),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    ttl: Duration,
+    failover: Failover,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { ttl: Duration::from_secs(300), failover: Failover::Rebind }
+    }
+}
+
+impl Config {
+    /// The duration after its last use after which a session expires.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn set_ttl(self, value: Duration) -> Self {
+        Self { ttl: value, ..self }
+    }
+
+    pub fn failover(&self) -> Failover {
+        self.failover
+    }
+
+    pub fn set_failover(self, value: Failover) -> Self {
+        Self { failover: value, ..self }
+    }
+}
+
+struct Session {
+    /// The provider of the session, or `None` if the session was aborted.
+    url: Option<Url>,
+    expiry: Instant,
+}
+
+type SessionKey = (Box<str>, Arc<str>);
+
+/// The providers of the sessions, by token and namespace.
+pub struct Sessions {
+    config: Config,
+    sessions: Mutex<HashMap<SessionKey, Session>>,
+}
+
+impl Sessions {
+    pub fn new(config: Config) -> Self {
+        Self { config, sessions: Mutex::new(HashMap::new()) }
+    }
+
+    /// Returns the token of the session of a request, if any.
+    pub fn token(metadata: &MetadataMap) -> Option<&str> {
+        metadata
+            .get(SESSION_METADATA_KEY)
+            .and_then(|value| value.to_str().ok())
+            .filter(|token| !token.is_empty())
+    }
+
+    /// Resolves the URL of the provider of a session for an intent, binding
+    /// the session to the provider tried first if it is not bound yet.
+    /// Returns `None` if the intent is to be fulfilled as without a session.
+    pub(crate) fn resolve(
+        &self,
+        token: &str,
+        intent: &IntentConfiguration,
+        broker: &IntentBroker,
+    ) -> Result<Option<Url>, Status> {
+        let now = Instant::now();
+        let key = (token.into(), Arc::clone(intent.shared_namespace()));
+        let mut sessions = self.sessions.lock().unwrap();
+
+        if let Some(session) = sessions.get_mut(&key).filter(|session| session.expiry > now) {
+            // An aborted session is not renewed, such that it expires.
+            match session.url.clone() {
+                Some(url) if broker.serves(intent, &url) => {
+                    session.expiry = now + self.config.ttl;
+                    return Ok(Some(url));
+                }
+                Some(url) if broker.serves_namespace(intent.namespace(), &url) => {
+                    session.expiry = now + self.config.ttl;
+                    return Ok(None);
+                }
+                Some(_) => {}
+                None => return Err(aborted(intent)),
+            }
+
+            if self.config.failover == Failover::Abort {
+                session.url = None;
+                return Err(aborted(intent));
+            }
+        }
+
+        let Some(url) = broker.primary_url(intent) else {
+            return Ok(None);
+        };
+
+        if !sessions.contains_key(&key) && sessions.len() >= SESSION_CAPACITY {
+            sessions.retain(|_, session| session.expiry > now);
+            if sessions.len() >= SESSION_CAPACITY {
+                return Err(Status::resource_exhausted("Too many sessions are open."));
+            }
+        }
+
+        tracing::debug!("Binding session to '{url}' for namespace '{}'.", intent.namespace());
+        sessions.insert(key, Session { url: Some(url.clone()), expiry: now + self.config.ttl });
+        Ok(Some(url))
+    }
+
+    /// Records that the provider of a session was unavailable, such that the
+    /// session fails over with its next intent.
+    pub(crate) fn fail(&self, token: &str, intent: &IntentConfiguration) {
+        let key = (token.into(), Arc::clone(intent.shared_namespace()));
+        let mut sessions = self.sessions.lock().unwrap();
+        match self.config.failover {
+            Failover::Rebind => _ = sessions.remove(&key),
+            Failover::Abort => {
+                if let Some(session) = sessions.get_mut(&key) {
+                    session.url = None;
+                }
+            }
+        }
+    }
+}
+
+fn aborted(intent: &IntentConfiguration) -> Status {
+    Status::aborted(format!(
+        "The provider of the session for namespace '{}' is no longer available.",
+        intent.namespace()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::thread;
+    use std::time::Duration;
+
+    use tonic::{metadata::MetadataMap, Code};
+    use url::Url;
+
+    use super::{Config, Failover, Sessions, SESSION_METADATA_KEY};
+    use crate::registry::{
+        tests::ServiceConfigurationBuilder, Change, ExecutionLocality, IntentConfiguration,
+        IntentKind, Observer as _, ServiceConfiguration,
+    };
+    use crate::streaming::StreamingEss;
+    use crate::IntentBroker;
+
+    const LOCAL_URL: &str = "http://localhost:50001"; // DevSkim: ignore DS137138
+    const CLOUD_URL: &str = "http://localhost:50002"; // DevSkim: ignore DS137138
+
+    fn intent() -> IntentConfiguration {
+        IntentConfiguration::new("sdv.diagnostics", IntentKind::Invoke)
+    }
+
+    fn service(url: &str, locality: ExecutionLocality) -> ServiceConfiguration {
+        ServiceConfigurationBuilder::new().url(url).execution_locality(locality).build()
+    }
+
+    fn setup(services: impl IntoIterator<Item = ServiceConfiguration>) -> IntentBroker {
+        let broker =
+            IntentBroker::new("https://localhost:4243".parse().unwrap(), StreamingEss::new()); // DevSkim: ignore DS162092
+        bind(&broker, services);
+        broker
+    }
+
+    fn bind(broker: &IntentBroker, services: impl IntoIterator<Item = ServiceConfiguration>) {
+        let services = services.into_iter().collect::<HashSet<_>>();
+        broker.on_change([Change::Modify(&intent(), &services)].into_iter());
+    }
+
+    #[test]
+    fn token_is_read_from_metadata() {
+        // arrange
+        let mut metadata = MetadataMap::new();
+        metadata.insert(SESSION_METADATA_KEY, "dialog-1".parse().unwrap());
+
+        // act
+        let token = Sessions::token(&metadata);
+
+        // assert
+        assert_eq!(Some("dialog-1"), token);
+        assert_eq!(None, Sessions::token(&MetadataMap::new()));
+    }
+
+    #[test]
+    fn resolve_keeps_session_on_provider_of_first_intent() {
+        // arrange
+        let broker = setup([service(LOCAL_URL, ExecutionLocality::Local)]);
+        let subject = Sessions::new(Config::default());
+        let first = subject.resolve("dialog-1", &intent(), &broker).unwrap();
+        bind(
+            &broker,
+            [
+                service(LOCAL_URL, ExecutionLocality::Local),
+                service(CLOUD_URL, ExecutionLocality::Cloud),
+            ],
+        );
+
+        // act
+        let second = subject.resolve("dialog-1", &intent(), &broker).unwrap();
+        let other = subject.resolve("dialog-2", &intent(), &broker).unwrap();
+
+        // assert
+        assert_eq!(LOCAL_URL.parse::<Url>().unwrap(), first.unwrap());
+        assert_eq!(LOCAL_URL.parse::<Url>().unwrap(), second.unwrap());
+        assert_eq!(CLOUD_URL.parse::<Url>().unwrap(), other.unwrap());
+    }
+
+    #[test]
+    fn resolve_rebinds_session_if_provider_unregistered() {
+        // arrange
+        let broker = setup([service(LOCAL_URL, ExecutionLocality::Local)]);
+        let subject = Sessions::new(Config::default());
+        subject.resolve("dialog-1", &intent(), &broker).unwrap();
+        bind(&broker, [service(CLOUD_URL, ExecutionLocality::Cloud)]);
+
+        // act
+        let result = subject.resolve("dialog-1", &intent(), &broker).unwrap();
+
+        // assert
+        assert_eq!(CLOUD_URL.parse::<Url>().unwrap(), result.unwrap());
+    }
+
+    #[test]
+    fn resolve_aborts_session_if_provider_unregistered_or_unavailable() {
+        // arrange
+        let broker = setup([service(LOCAL_URL, ExecutionLocality::Local)]);
+        let subject = Sessions::new(Config::default().set_failover(Failover::Abort));
+        subject.resolve("dialog-1", &intent(), &broker).unwrap();
+        bind(&broker, [service(CLOUD_URL, ExecutionLocality::Cloud)]);
+        subject.resolve("dialog-3", &intent(), &broker).unwrap();
+
+        // act
+        subject.fail("dialog-3", &intent());
+        let unregistered = subject.resolve("dialog-1", &intent(), &broker);
+        let still_aborted = subject.resolve("dialog-1", &intent(), &broker);
+        let unavailable = subject.resolve("dialog-3", &intent(), &broker);
+
+        // assert
+        assert_eq!(Code::Aborted, unregistered.unwrap_err().code());
+        assert_eq!(Code::Aborted, still_aborted.unwrap_err().code());
+        assert_eq!(Code::Aborted, unavailable.unwrap_err().code());
+    }
+
+    #[test]
+    fn resolve_does_not_renew_aborted_session() {
+        // arrange
+        let broker = setup([service(LOCAL_URL, ExecutionLocality::Local)]);
+        let subject = Sessions::new(
+            Config::default().set_failover(Failover::Abort).set_ttl(Duration::from_millis(50)),
+        );
+        subject.resolve("dialog-1", &intent(), &broker).unwrap();
+        subject.fail("dialog-1", &intent());
+        thread::sleep(Duration::from_millis(30));
+        let aborted = subject.resolve("dialog-1", &intent(), &broker);
+        thread::sleep(Duration::from_millis(30));
+
+        // act
+        let result = subject.resolve("dialog-1", &intent(), &broker).unwrap();
+
+        // assert
+        assert_eq!(Code::Aborted, aborted.unwrap_err().code());
+        assert_eq!(LOCAL_URL.parse::<Url>().unwrap(), result.unwrap());
+    }
+
+    #[test]
+    fn failover_is_parsed() {
+        // act + assert
+        assert_eq!(Failover::Rebind, "rebind".parse().unwrap());
+        assert_eq!(Failover::Abort, "abort".parse().unwrap());
+        assert!("retry".parse::<Failover>().is_err());
+    }
+}
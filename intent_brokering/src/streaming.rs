@@ -2,4 +2,402 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
-pub type StreamingEss = intent_brokering_common::streaming_ess::StreamingEss<()>;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use intent_brokering_common::{
+    error::{Error, ResultExt as _},
+    streaming_ess::Timestamped,
+};
+use intent_brokering_proto::{
+    common::ValueEnum,
+    streaming::{channel_service_client::ChannelServiceClient, CloseRequest, OpenRequest},
+};
+use tokio::{spawn, sync::OnceCell, time::timeout};
+use tokio_stream::StreamExt as _;
+use tonic::{transport::Endpoint, Request};
+use url::Url;
+
+pub type StreamingEss =
+    intent_brokering_common::streaming_ess::StreamingEss<Timestamped<ValueEnum>>;
+
+const CHANNEL_ID_HEADER_NAME: &str = "x-chariott-channel-id";
+
+/// The number of recent events the clock skew of a provider is estimated from.
+const CLOCK_SKEW_WINDOW: usize = 64;
+
+/// How long connecting to a provider streaming endpoint and opening an
+/// upstream channel on it may take, each.
+const UPSTREAM_OPEN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Identifies an upstream channel by namespace and provider streaming endpoint.
+type UpstreamKey = (Box<str>, Url);
+
+/// The identifier of an upstream channel, which is set once the channel is
+/// open. Consumers of the same namespace and endpoint wait for the same
+/// channel to open, while other upstream channels are not held up.
+type UpstreamChannel = Arc<OnceCell<Box<str>>>;
+
+/// The names of the fields of `Value`, which are the types of values sources
+/// may declare, see [`value_type`].
+pub const VALUE_TYPES: [&str; 12] = [
+    "null",
+    "any",
+    "bool",
+    "int32",
+    "int64",
+    "float32",
+    "float64",
+    "string",
+    "timestamp",
+    "list",
+    "map",
+    "blob",
+];
+
+/// Returns the name of the field of `Value` which is set for a value.
+pub fn value_type(value: &ValueEnum) -> &'static str {
+    match value {
+        ValueEnum::Null(_) => "null",
+        ValueEnum::Any(_) => "any",
+        ValueEnum::Bool(_) => "bool",
+        ValueEnum::Int32(_) => "int32",
+        ValueEnum::Int64(_) => "int64",
+        ValueEnum::Float32(_) => "float32",
+        ValueEnum::Float64(_) => "float64",
+        ValueEnum::String(_) => "string",
+        ValueEnum::Timestamp(_) => "timestamp",
+        ValueEnum::List(_) => "list",
+        ValueEnum::Map(_) => "map",
+        ValueEnum::Blob(_) => "blob",
+    }
+}
+
+/// Rewrites the source of an event relayed from a provider into the source
+/// under which it is published through the Intent Broker's ESS. Prefixing with
+/// the namespace prevents collisions between providers exposing sources with
+/// the same name.
+pub fn proxied_source(namespace: &str, source: &str) -> String {
+    format!("{namespace}/{source}")
+}
+
+/// Estimates the offset of a provider clock from the clock of the Intent
+/// Broker as the smallest difference between the time at which an event was
+/// received and the time at which it was generated, over the recent events.
+/// The estimate hence includes the smallest transmission delay, which is
+/// negligible for providers running on the same vehicle.
+#[derive(Default)]
+struct ClockSkew {
+    /// The recent differences, in nanoseconds.
+    offsets: VecDeque<i128>,
+}
+
+impl ClockSkew {
+    /// Adds the difference for an event and returns its timestamp corrected
+    /// by the estimated offset.
+    fn normalize(&mut self, timestamp: SystemTime, received: SystemTime) -> SystemTime {
+        let offset = match received.duration_since(timestamp) {
+            Ok(offset) => offset.as_nanos() as i128,
+            Err(e) => -(e.duration().as_nanos() as i128),
+        };
+
+        if self.offsets.len() == CLOCK_SKEW_WINDOW {
+            self.offsets.pop_front();
+        }
+        self.offsets.push_back(offset);
+
+        let offset = self.offsets.iter().copied().min().unwrap_or(offset);
+        let magnitude = Duration::from_nanos(offset.unsigned_abs() as u64);
+        if offset < 0 {
+            timestamp - magnitude
+        } else {
+            timestamp + magnitude
+        }
+    }
+}
+
+/// Proxies subscriptions for consumers that opened a channel with the Intent
+/// Broker to providers exposing their own `ChannelService`. For each
+/// namespace and provider streaming endpoint, a single upstream channel is
+/// opened and all events received through it are relayed to the ESS of the
+/// Intent Broker. Cloning is cheap and refers to the same upstream channels.
+///
+/// The relay reads one event at a time from the upstream channel and hands it
+/// to the ESS before reading the next one, which lets the HTTP/2 flow control
+/// of the upstream channel push back on the provider when the broker cannot
+/// keep up. Slow consumers are handled by the ESS, which drops events for
+/// subscriptions whose buffer is full.
+///
+/// An upstream channel is closed once no consumer remains subscribed to any
+/// of the sources of its namespace, which is detected when an event of the
+/// provider cannot be published to any consumer.
+///
+/// Relayed events keep the timestamp of the provider. If clock skew
+/// estimation is enabled, the skew of each provider clock is estimated from
+/// the events of its upstream channel and relayed events carry their
+/// normalized timestamp, too.
+///
+/// If source type validation is enabled, events whose value does not have the
+/// type declared for their source are dropped with a warning, which helps
+/// integrating new providers.
+#[derive(Clone, Default)]
+pub struct SubscriptionProxy {
+    ess: StreamingEss,
+    upstream_channels: Arc<Mutex<HashMap<UpstreamKey, UpstreamChannel>>>,
+    estimate_clock_skew: bool,
+    validate_source_types: bool,
+}
+
+impl SubscriptionProxy {
+    pub fn new(ess: StreamingEss) -> Self {
+        Self {
+            ess,
+            upstream_channels: Default::default(),
+            estimate_clock_skew: false,
+            validate_source_types: false,
+        }
+    }
+
+    pub fn set_clock_skew_estimation(&mut self, enabled: bool) {
+        self.estimate_clock_skew = enabled;
+    }
+
+    pub fn set_source_type_validation(&mut self, enabled: bool) {
+        self.validate_source_types = enabled;
+    }
+
+    pub fn ess(&self) -> &StreamingEss {
+        &self.ess
+    }
+
+    /// Returns whether the channel was opened on the Intent Broker itself, in
+    /// which case subscriptions need to be proxied.
+    pub fn is_local_channel(&self, channel_id: &str) -> bool {
+        self.ess.is_reading_events(channel_id)
+    }
+
+    /// Returns the identifier of the upstream channel for a namespace on a
+    /// provider streaming endpoint, opening the channel and starting to relay
+    /// its events if it is not open yet.
+    pub async fn upstream_channel(&self, namespace: &str, url: Url) -> Result<Box<str>, Error> {
+        let key: UpstreamKey = (namespace.into(), url);
+        let channel =
+            Arc::clone(self.upstream_channels.lock().unwrap().entry(key.clone()).or_default());
+
+        channel
+            .get_or_try_init(|| self.open_upstream_channel(key, Arc::clone(&channel)))
+            .await
+            .cloned()
+    }
+
+    /// Opens an upstream channel and starts to relay its events, without
+    /// holding the lock of the upstream channels across the network calls.
+    async fn open_upstream_channel(
+        &self,
+        key: UpstreamKey,
+        channel: UpstreamChannel,
+    ) -> Result<Box<str>, Error> {
+        let endpoint = Endpoint::from_shared(key.1.to_string())
+            .map_err_with("Provider streaming endpoint is not valid.")?
+            .connect_timeout(UPSTREAM_OPEN_TIMEOUT);
+        let mut client = ChannelServiceClient::new(
+            endpoint
+                .connect()
+                .await
+                .map_err_with("Connecting to provider streaming endpoint failed.")?,
+        );
+
+        let response =
+            timeout(UPSTREAM_OPEN_TIMEOUT, client.open(Request::new(OpenRequest::default())))
+                .await
+                .map_err_with("Opening channel with provider timed out.")?
+                .map_err_with("Opening channel with provider failed.")?;
+
+        let channel_id: Box<str> = response
+            .metadata()
+            .get(CHANNEL_ID_HEADER_NAME)
+            .and_then(|h| h.to_str().ok())
+            .ok_or_else(|| Error::new("Channel ID header not found."))?
+            .into();
+
+        let mut stream = response.into_inner();
+        let upstream_channel_id = channel_id.clone();
+        let ess = self.ess.clone();
+        let upstream_channels = Arc::clone(&self.upstream_channels);
+        let mut clock_skew = self.estimate_clock_skew.then(ClockSkew::default);
+        let validate_source_types = self.validate_source_types;
+
+        spawn(async move {
+            let (namespace, url) = &key;
+
+            while let Some(event) = stream.next().await {
+                match event {
+                    Ok(event) if event.end_of_stream => break,
+                    Ok(event) => {
+                        if let Some(gap) = event.gap {
+                            // Relayed events are numbered by the ESS of the Intent Broker,
+                            // which is hence unable to forward the gap to its consumers.
+                            tracing::warn!(
+                                "Events {}-{} of '{}' were dropped on '{url}'.",
+                                gap.from_seq,
+                                gap.to_seq,
+                                proxied_source(namespace, &event.source)
+                            );
+                            continue;
+                        }
+
+                        let Some(value) = event.value.and_then(|v| v.value) else {
+                            continue;
+                        };
+
+                        let source = proxied_source(namespace, &event.source);
+                        if validate_source_types {
+                            if let Some(source_type) = ess.source_type(&source) {
+                                if source_type.value_type != value_type(&value) {
+                                    tracing::warn!(
+                                        "Dropping event of '{source}' of type '{}' instead of '{}'.",
+                                        value_type(&value),
+                                        source_type.value_type
+                                    );
+                                    continue;
+                                }
+                            }
+                        }
+
+                        let received = SystemTime::now();
+                        let timestamp = event
+                            .timestamp
+                            .and_then(|t| SystemTime::try_from(t).ok())
+                            .unwrap_or(received);
+
+                        let data = Timestamped {
+                            data: value,
+                            timestamp,
+                            normalized_timestamp: clock_skew
+                                .as_mut()
+                                .map(|clock_skew| clock_skew.normalize(timestamp, received)),
+                            freshness: event.freshness,
+                        };
+
+                        if ess.publish(source.as_str(), data) {
+                            continue;
+                        }
+
+                        // Consumers subscribe locally before subscribing upstream, hence
+                        // holding the lock prevents closing a channel which is about to
+                        // be subscribed to. Once removed, new consumers open a new one.
+                        {
+                            let mut upstream_channels = upstream_channels.lock().unwrap();
+                            if has_consumers(&ess, namespace) {
+                                continue;
+                            }
+                            remove_upstream_channel(&mut upstream_channels, &key, &channel);
+                        }
+
+                        tracing::debug!(
+                            "No consumers remain for upstream channel for '{namespace}' on '{url}'."
+                        );
+
+                        if let Err(e) = client
+                            .close(Request::new(CloseRequest {
+                                channel_id: upstream_channel_id.to_string(),
+                            }))
+                            .await
+                        {
+                            tracing::warn!(
+                                "Closing upstream channel for '{namespace}' on '{url}' failed: {e}"
+                            );
+                        }
+
+                        return;
+                    }
+                    Err(e) => {
+                        tracing::warn!("Upstream channel for '{namespace}' on '{url}' failed: {e}");
+                        break;
+                    }
+                }
+            }
+
+            tracing::debug!("Upstream channel for '{namespace}' on '{url}' closed.");
+            remove_upstream_channel(&mut upstream_channels.lock().unwrap(), &key, &channel);
+        });
+
+        Ok(channel_id)
+    }
+}
+
+/// Removes an upstream channel, unless it was replaced by a newer one.
+fn remove_upstream_channel(
+    upstream_channels: &mut HashMap<UpstreamKey, UpstreamChannel>,
+    key: &UpstreamKey,
+    channel: &UpstreamChannel,
+) {
+    if upstream_channels.get(key).map_or(false, |current| Arc::ptr_eq(current, channel)) {
+        upstream_channels.remove(key);
+    }
+}
+
+/// Returns whether any consumer is subscribed to a source of the namespace.
+fn has_consumers(ess: &StreamingEss, namespace: &str) -> bool {
+    let prefix = proxied_source(namespace, "");
+    ess.get_subscribed_events().into_iter().any(|source| source.starts_with(&prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use intent_brokering_proto::common::ValueEnum;
+
+    use super::{value_type, ClockSkew, VALUE_TYPES};
+
+    #[test]
+    fn clock_skew_normalizes_by_smallest_offset() {
+        // arrange
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let mut subject = ClockSkew::default();
+
+        // act
+        let first = subject.normalize(now - Duration::from_millis(500), now);
+        let second = subject.normalize(now + Duration::from_millis(200), now);
+        let third = subject.normalize(now - Duration::from_millis(100), now);
+
+        // assert
+        assert_eq!(now, first);
+        assert_eq!(now, second);
+        assert_eq!(now - Duration::from_millis(300), third);
+    }
+
+    #[test]
+    fn value_type_returns_declarable_type() {
+        // act
+        let int32 = value_type(&ValueEnum::Int32(1));
+        let string = value_type(&ValueEnum::String("door".to_owned()));
+
+        // assert
+        assert_eq!("int32", int32);
+        assert_eq!("string", string);
+        assert!(VALUE_TYPES.contains(&int32));
+        assert!(VALUE_TYPES.contains(&string));
+    }
+
+    #[test]
+    fn clock_skew_forgets_offsets_outside_window() {
+        // arrange
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let mut subject = ClockSkew::default();
+        subject.normalize(now + Duration::from_secs(10), now);
+
+        // act
+        let mut result = now;
+        for _ in 0..super::CLOCK_SKEW_WINDOW {
+            result = subject.normalize(now - Duration::from_millis(10), now);
+        }
+
+        // assert
+        assert_eq!(now, result);
+    }
+}
@@ -0,0 +1,887 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Plugins which serve the intents of `system.*` namespaces within the Intent
+//! Broker, e.g. to expose the statistics of its ESS under `system.ess`, or to
+//! report the components running on a vehicle under `system.info`.
+//!
+//! A plugin declares its namespace and the intents it serves, and is mounted
+//! with [`crate::IntentBroker::mount`]. Its intents are then resolved like
+//! the ones of providers, and are listed by the `Inspect` intent of
+//! `system.registry`. Providers cannot register intents of `system.*`
+//! namespaces, hence plugins are never shadowed by providers.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use intent_brokering_proto::common::{
+    FulfillmentEnum, InspectFulfillment, IntentEnum, IntentMessage, List, Map, ReadFulfillment,
+    ValueEnum, ValueMessage, WriteFulfillment,
+};
+use tokio::{sync::Semaphore, task::JoinSet};
+use tonic::Status;
+
+use crate::accounting::{Accounting, Usage};
+use crate::execution::IterGroupingExt as _;
+use crate::fulfillment_log::FulfillmentLog;
+use crate::intent_broker::WeakIntentBroker;
+use crate::registry::{ExecutionLocality, IntentConfiguration, IntentKind};
+use crate::streaming::StreamingEss;
+use crate::IntentBroker;
+
+/// The prefix of the namespaces which plugins may serve.
+pub const SYSTEM_NAMESPACE_PREFIX: &str = "system.";
+
+const SYSTEM_ESS_NAMESPACE: &str = "system.ess";
+const STATISTICS_KEY: &str = "statistics";
+const SYSTEM_CATALOG_NAMESPACE: &str = "system.catalog";
+const NAMESPACE_ITEM: &str = "namespace";
+const CATALOG_CONCURRENCY: usize = 8;
+const SYSTEM_ACCOUNTING_NAMESPACE: &str = "system.accounting";
+const USAGE_KEY: &str = "usage";
+const SYSTEM_INFO_NAMESPACE: &str = "system.info";
+const REPORT_KEY: &str = "report";
+const SYSTEM_LOGGING_NAMESPACE: &str = "system.logging";
+const SAMPLE_RATE_KEY: &str = "sample_rate";
+const FLUSH_INTERVAL_KEY: &str = "flush_interval_secs";
+/// The packages of the gRPC contract served by the Intent Broker.
+/// The words in the keys of configuration entries which hold secrets, e.g.
+/// `INTENT_BROKERING_WEBHOOK_SECRET`, which are excluded from its digest.
+const SECRET_WORDS: [&str; 5] = ["SECRET", "TOKEN", "PASSWORD", "CREDENTIAL", "KEY"];
+const PROTO_PACKAGES: [&str; 4] = [
+    "intent_brokering.common.v1",
+    "intent_brokering.provider.v1",
+    "intent_brokering.runtime.v1",
+    "intent_brokering.streaming.v1",
+];
+
+/// Serves the intents of a `system.*` namespace within the Intent Broker.
+#[async_trait]
+pub trait SystemPlugin: Send + Sync {
+    /// The namespace served by the plugin, which must start with `system.`.
+    fn namespace(&self) -> &str;
+
+    /// The intents served by the plugin.
+    fn intents(&self) -> Vec<IntentKind>;
+
+    /// Fulfills an intent, which is one of the intents served by the plugin.
+    async fn fulfill(&self, intent: IntentEnum) -> Result<FulfillmentEnum, Status>;
+}
+
+/// Reads the statistics of the ESS of the Intent Broker with the `statistics`
+/// key of `system.ess`.
+pub struct EssStatistics(StreamingEss);
+
+impl EssStatistics {
+    pub fn new(ess: StreamingEss) -> Self {
+        Self(ess)
+    }
+}
+
+#[async_trait]
+impl SystemPlugin for EssStatistics {
+    fn namespace(&self) -> &str {
+        SYSTEM_ESS_NAMESPACE
+    }
+
+    fn intents(&self) -> Vec<IntentKind> {
+        vec![IntentKind::Read]
+    }
+
+    async fn fulfill(&self, intent: IntentEnum) -> Result<FulfillmentEnum, Status> {
+        let IntentEnum::Read(read_intent) = intent else {
+            return Err(Status::unimplemented(format!(
+                "Namespace '{SYSTEM_ESS_NAMESPACE}' only supports 'Read'."
+            )));
+        };
+
+        let value = (read_intent.key == STATISTICS_KEY).then(|| statistics_value(&self.0));
+        Ok(FulfillmentEnum::Read(ReadFulfillment {
+            value: Some(ValueMessage { value }),
+            ..Default::default()
+        }))
+    }
+}
+
+/// Reads the resources used by each caller, see [`crate::accounting`], with
+/// the `usage` key of `system.accounting`.
+pub struct ResourceAccounting(Accounting);
+
+impl ResourceAccounting {
+    pub fn new(accounting: Accounting) -> Self {
+        Self(accounting)
+    }
+}
+
+#[async_trait]
+impl SystemPlugin for ResourceAccounting {
+    fn namespace(&self) -> &str {
+        SYSTEM_ACCOUNTING_NAMESPACE
+    }
+
+    fn intents(&self) -> Vec<IntentKind> {
+        vec![IntentKind::Read]
+    }
+
+    async fn fulfill(&self, intent: IntentEnum) -> Result<FulfillmentEnum, Status> {
+        let IntentEnum::Read(read_intent) = intent else {
+            return Err(Status::unimplemented(format!(
+                "Namespace '{SYSTEM_ACCOUNTING_NAMESPACE}' only supports 'Read'."
+            )));
+        };
+
+        let value = (read_intent.key == USAGE_KEY).then(|| usage_value(self.0.usage()));
+        Ok(FulfillmentEnum::Read(ReadFulfillment {
+            value: Some(ValueMessage { value }),
+            ..Default::default()
+        }))
+    }
+}
+
+/// Reports the components running in the Intent Broker with the `report` key
+/// of `system.info`, such that fleet management can audit what runs on each
+/// vehicle. The report contains the build of the Intent Broker under `build`,
+/// its enabled features under `features`, the namespaces of its plugins under
+/// `plugins`, the packages of its gRPC contract under `protos` and the digest
+/// of its configuration under `config_digest`. The version, URL and other
+/// metadata which each registered provider reported, along with the
+/// namespaces it serves, are listed under `providers`.
+pub struct RuntimeInfo {
+    broker: WeakIntentBroker,
+    features: Vec<Box<str>>,
+    config_digest: Box<str>,
+}
+
+impl RuntimeInfo {
+    /// Creates the report of a broker with the given enabled features and
+    /// configuration, of which only a digest is reported. The digest does not
+    /// depend on the order of the configuration entries, and excludes the
+    /// entries holding secrets, such that it cannot be used to guess them.
+    pub fn new(
+        broker: &IntentBroker,
+        features: impl IntoIterator<Item = impl Into<Box<str>>>,
+        configuration: impl IntoIterator<Item = (String, String)>,
+    ) -> Self {
+        Self {
+            broker: broker.downgrade(),
+            features: features.into_iter().map(Into::into).collect(),
+            config_digest: digest(
+                configuration.into_iter().filter(|(key, _)| !is_secret(key)).collect(),
+            )
+            .into(),
+        }
+    }
+
+    fn report_value(&self, broker: &IntentBroker) -> ValueEnum {
+        let build = map([
+            ("name".to_owned(), string(env!("CARGO_PKG_NAME"))),
+            ("version".to_owned(), string(env!("CARGO_PKG_VERSION"))),
+            (
+                "profile".to_owned(),
+                string(if cfg!(debug_assertions) { "debug" } else { "release" }),
+            ),
+            (
+                "target".to_owned(),
+                string(&format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS)),
+            ),
+        ]);
+
+        let providers = broker.services().into_iter().map(|(service, intents)| {
+            let namespaces: BTreeSet<_> =
+                intents.iter().map(|intent| intent.namespace().to_owned()).collect();
+            let locality = match service.locality() {
+                ExecutionLocality::Local => "local",
+                ExecutionLocality::Cloud => "cloud",
+            };
+
+            map([
+                ("name".to_owned(), string(&service.id().name())),
+                ("version".to_owned(), string(&service.id().version())),
+                ("url".to_owned(), string(service.url().as_str())),
+                ("locality".to_owned(), string(locality)),
+                ("transactional".to_owned(), ValueEnum::Bool(service.transactional())),
+                ("max_concurrency".to_owned(), count(service.max_concurrency().unwrap_or(0))),
+                ("namespaces".to_owned(), list(namespaces)),
+            ])
+        });
+
+        map([
+            ("build".to_owned(), build),
+            ("features".to_owned(), list(self.features.iter().map(|f| f.to_string()))),
+            ("plugins".to_owned(), list(broker.plugins())),
+            ("protos".to_owned(), list(PROTO_PACKAGES.map(str::to_owned))),
+            ("config_digest".to_owned(), string(&self.config_digest)),
+            (
+                "providers".to_owned(),
+                ValueEnum::List(List {
+                    value: providers.map(|value| ValueMessage { value: Some(value) }).collect(),
+                }),
+            ),
+        ])
+    }
+}
+
+#[async_trait]
+impl SystemPlugin for RuntimeInfo {
+    fn namespace(&self) -> &str {
+        SYSTEM_INFO_NAMESPACE
+    }
+
+    fn intents(&self) -> Vec<IntentKind> {
+        vec![IntentKind::Read]
+    }
+
+    async fn fulfill(&self, intent: IntentEnum) -> Result<FulfillmentEnum, Status> {
+        let IntentEnum::Read(read_intent) = intent else {
+            return Err(Status::unimplemented(format!(
+                "Namespace '{SYSTEM_INFO_NAMESPACE}' only supports 'Read'."
+            )));
+        };
+
+        let broker = self
+            .broker
+            .upgrade()
+            .ok_or_else(|| Status::unavailable("The broker was shut down."))?;
+
+        let value = (read_intent.key == REPORT_KEY).then(|| self.report_value(&broker));
+        Ok(FulfillmentEnum::Read(ReadFulfillment {
+            value: Some(ValueMessage { value }),
+            ..Default::default()
+        }))
+    }
+}
+
+/// Reads and changes the logging of fulfillments, see
+/// [`crate::fulfillment_log`], at runtime with the `sample_rate` and
+/// `flush_interval_secs` keys of `system.logging`.
+pub struct LogSettings(FulfillmentLog);
+
+impl LogSettings {
+    pub fn new(log: FulfillmentLog) -> Self {
+        Self(log)
+    }
+}
+
+#[async_trait]
+impl SystemPlugin for LogSettings {
+    fn namespace(&self) -> &str {
+        SYSTEM_LOGGING_NAMESPACE
+    }
+
+    fn intents(&self) -> Vec<IntentKind> {
+        vec![IntentKind::Read, IntentKind::Write]
+    }
+
+    async fn fulfill(&self, intent: IntentEnum) -> Result<FulfillmentEnum, Status> {
+        match intent {
+            IntentEnum::Read(read_intent) => {
+                let value = match read_intent.key.as_str() {
+                    SAMPLE_RATE_KEY => Some(count(self.0.sample_rate())),
+                    FLUSH_INTERVAL_KEY => Some(count(self.0.flush_interval().as_secs())),
+                    _ => None,
+                };
+
+                Ok(FulfillmentEnum::Read(ReadFulfillment {
+                    value: Some(ValueMessage { value }),
+                    ..Default::default()
+                }))
+            }
+            IntentEnum::Write(write_intent) => {
+                let value = match write_intent.value.and_then(|v| v.value) {
+                    Some(ValueEnum::Int32(value)) => u64::try_from(value).ok(),
+                    Some(ValueEnum::Int64(value)) => u64::try_from(value).ok(),
+                    _ => None,
+                }
+                .ok_or_else(|| {
+                    Status::invalid_argument(format!(
+                        "Key '{}' must be written with a non-negative integer.",
+                        write_intent.key
+                    ))
+                })?;
+
+                match write_intent.key.as_str() {
+                    SAMPLE_RATE_KEY => {
+                        self.0.set_sample_rate(u32::try_from(value).unwrap_or(u32::MAX))
+                    }
+                    FLUSH_INTERVAL_KEY => {
+                        self.0.set_flush_interval_bounded(Duration::from_secs(value))
+                    }
+                    key => {
+                        return Err(Status::not_found(format!(
+                            "Key '{key}' is not known in '{SYSTEM_LOGGING_NAMESPACE}'."
+                        )))
+                    }
+                }
+
+                tracing::info!(
+                    "Changed '{}' of '{SYSTEM_LOGGING_NAMESPACE}' to {value}.",
+                    write_intent.key
+                );
+
+                Ok(FulfillmentEnum::Write(WriteFulfillment::default()))
+            }
+            _ => Err(Status::unimplemented(format!(
+                "Namespace '{SYSTEM_LOGGING_NAMESPACE}' only supports 'Read' and 'Write'."
+            ))),
+        }
+    }
+}
+
+/// Whether the key of a configuration entry names a secret, i.e. one of its
+/// words, separated by underscores, starts with a secret word, e.g.
+/// `INTENT_BROKERING_REGISTRATION_SECRETS`.
+fn is_secret(key: &str) -> bool {
+    key.split('_').any(|word| {
+        let word = word.to_ascii_uppercase();
+        SECRET_WORDS.iter().any(|secret| word.starts_with(secret))
+    })
+}
+
+/// Computes the 64-bit FNV-1a digest of the configuration entries, which is
+/// stable across builds and platforms, in contrast to the hasher of the
+/// standard library.
+fn digest(configuration: BTreeMap<String, String>) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    let mut hash = OFFSET_BASIS;
+    for (key, value) in &configuration {
+        // Entries are delimited by bytes which cannot occur in UTF-8.
+        for byte in key.bytes().chain([0xff]).chain(value.bytes()).chain([0xfe]) {
+            hash = (hash ^ u64::from(byte)).wrapping_mul(PRIME);
+        }
+    }
+
+    format!("{hash:016x}")
+}
+
+/// Aggregates the entries of all namespaces serving the `Inspect` intent into
+/// a catalog of the properties, commands and events available right now, with
+/// the `Inspect` intent of `system.catalog`. The query is passed on to each
+/// namespace, and each entry of the catalog names its namespace with the
+/// `namespace` item. At most 8 namespaces are inspected at once, and the ones
+/// failing to be inspected are left out of the catalog.
+pub struct Catalog(WeakIntentBroker);
+
+impl Catalog {
+    pub(crate) fn new(broker: WeakIntentBroker) -> Self {
+        Self(broker)
+    }
+}
+
+#[async_trait]
+impl SystemPlugin for Catalog {
+    fn namespace(&self) -> &str {
+        SYSTEM_CATALOG_NAMESPACE
+    }
+
+    fn intents(&self) -> Vec<IntentKind> {
+        vec![IntentKind::Inspect]
+    }
+
+    async fn fulfill(&self, intent: IntentEnum) -> Result<FulfillmentEnum, Status> {
+        let IntentEnum::Inspect(inspect_intent) = intent else {
+            return Err(Status::unimplemented(format!(
+                "Namespace '{SYSTEM_CATALOG_NAMESPACE}' only supports 'Inspect'."
+            )));
+        };
+
+        let broker =
+            self.0.upgrade().ok_or_else(|| Status::unavailable("The broker was shut down."))?;
+
+        let semaphore = Arc::new(Semaphore::new(CATALOG_CONCURRENCY));
+        let mut inspections = JoinSet::new();
+        for namespace in broker.namespaces(IntentKind::Inspect) {
+            if namespace == SYSTEM_CATALOG_NAMESPACE {
+                continue;
+            }
+
+            let config = IntentConfiguration::new(namespace.as_str(), IntentKind::Inspect);
+            let Some(binding) = broker.resolve(&config) else {
+                continue;
+            };
+
+            let intent =
+                IntentMessage { intent: Some(IntentEnum::Inspect(inspect_intent.clone())) };
+            let semaphore = Arc::clone(&semaphore);
+            inspections.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                (namespace, binding.execute(intent).await)
+            });
+        }
+
+        let mut entries_by_namespace = BTreeMap::new();
+        while let Some(inspection) = inspections.join_next().await {
+            let Ok((namespace, response)) = inspection else {
+                continue;
+            };
+
+            match response.map(|response| response.fulfillment.and_then(|f| f.fulfillment)) {
+                Ok(Some(FulfillmentEnum::Inspect(inspection))) => {
+                    entries_by_namespace.insert(namespace, inspection.entries);
+                }
+                Ok(_) => tracing::warn!(
+                    namespace,
+                    "Leaving namespace out of the catalog, it did not fulfill 'Inspect'."
+                ),
+                Err(status) => tracing::warn!(
+                    namespace,
+                    "Leaving namespace out of the catalog, inspecting it failed: {}",
+                    status.message()
+                ),
+            }
+        }
+
+        let entries = entries_by_namespace
+            .into_iter()
+            .flat_map(|(namespace, entries)| {
+                entries.into_iter().map(move |mut entry| {
+                    entry.items.insert(
+                        NAMESPACE_ITEM.to_owned(),
+                        ValueMessage { value: Some(ValueEnum::String(namespace.clone())) },
+                    );
+                    entry
+                })
+            })
+            .collect();
+
+        Ok(FulfillmentEnum::Inspect(InspectFulfillment { entries }))
+    }
+}
+
+/// Converts the statistics of the ESS into a map with the statistics of each
+/// channel under `channels` and of each source under `sources`. The statistics
+/// of a channel include whether the task serving each of its subscriptions is
+/// alive under `tasks`, and the number of live tasks under `live_tasks`.
+fn statistics_value(ess: &StreamingEss) -> ValueEnum {
+    let statistics = ess.statistics();
+    let tasks =
+        ess.task_statistics().into_iter().map(|task| (task.channel_id.clone(), task)).group();
+
+    let channels = statistics.clients.into_iter().map(|client| {
+        let tasks = tasks.get(&client.client_id).map(Vec::as_slice).unwrap_or_default();
+        let live_tasks = tasks.iter().filter(|task| task.alive).count();
+        let tasks = tasks.iter().map(|task| (task.source.to_string(), ValueEnum::Bool(task.alive)));
+
+        (
+            client.client_id.to_string(),
+            map([
+                (
+                    "subscriptions".to_owned(),
+                    list(client.subscriptions.iter().map(|s| s.to_string())),
+                ),
+                ("delivered".to_owned(), count(client.delivered)),
+                ("dropped".to_owned(), count(client.dropped)),
+                ("buffered".to_owned(), count(client.buffered)),
+                ("buffer_size".to_owned(), count(client.buffer_size)),
+                ("tasks".to_owned(), map(tasks)),
+                ("live_tasks".to_owned(), count(live_tasks)),
+            ]),
+        )
+    });
+
+    let sources = statistics.events.into_iter().map(|event| {
+        (
+            event.event_id.to_string(),
+            map([
+                ("subscriptions".to_owned(), count(event.subscriptions)),
+                ("published".to_owned(), count(event.published)),
+                ("delivered".to_owned(), count(event.delivered)),
+                ("dropped".to_owned(), count(event.dropped)),
+            ]),
+        )
+    });
+
+    map([("channels".to_owned(), map(channels)), ("sources".to_owned(), map(sources))])
+}
+
+/// Converts the usage of resources into a map with the usage of each caller.
+fn usage_value(usage: BTreeMap<String, Usage>) -> ValueEnum {
+    map(usage.into_iter().map(|(caller, usage)| {
+        (
+            caller,
+            map([
+                ("outstanding_requests".to_owned(), count(usage.outstanding_requests)),
+                ("channels".to_owned(), count(usage.channels)),
+                ("subscriptions".to_owned(), count(usage.subscriptions)),
+                ("buffered_events".to_owned(), count(usage.buffered_events)),
+            ]),
+        )
+    }))
+}
+
+fn map(entries: impl IntoIterator<Item = (String, ValueEnum)>) -> ValueEnum {
+    ValueEnum::Map(Map {
+        map: entries
+            .into_iter()
+            .map(|(key, value)| (key, ValueMessage { value: Some(value) }))
+            .collect(),
+    })
+}
+
+fn count(value: impl TryInto<i64>) -> ValueEnum {
+    ValueEnum::Int64(value.try_into().unwrap_or(i64::MAX))
+}
+
+fn string(value: &str) -> ValueEnum {
+    ValueEnum::String(value.to_owned())
+}
+
+fn list(values: impl IntoIterator<Item = String>) -> ValueEnum {
+    ValueEnum::List(List {
+        value: values
+            .into_iter()
+            .map(|value| ValueMessage { value: Some(ValueEnum::String(value)) })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use intent_brokering_common::streaming_ess::Timestamped;
+    use intent_brokering_proto::{
+        common::{
+            inspect_fulfillment::Entry, InspectIntent, ReadIntent, SubscribeIntent, WriteIntent,
+        },
+        streaming::{channel_service_server::ChannelService, OpenRequest},
+    };
+    use tonic::{Code, Request};
+
+    use std::collections::HashSet;
+
+    use crate::execution::tests::StreamExt as _;
+    use crate::identity::Caller;
+    use crate::registry::tests::{IntentConfigurationBuilder, ServiceConfigurationBuilder};
+    use crate::registry::{Change, Observer as _};
+    use crate::IntentBroker;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn ess_statistics_rejects_unsupported_intents() {
+        // act
+        let result = EssStatistics::new(StreamingEss::new())
+            .fulfill(IntentEnum::Inspect(InspectIntent { query: "**".to_owned() }))
+            .await;
+
+        // assert
+        assert_eq!(Code::Unimplemented, result.unwrap_err().code());
+    }
+
+    #[tokio::test]
+    async fn ess_statistics_returns_source_statistics() {
+        // arrange
+        const EVENT: &str = "test-event";
+
+        let streaming_ess = StreamingEss::new();
+        let response = streaming_ess.open(Request::new(OpenRequest::default())).await.unwrap();
+        let channel_id: String =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+        let mut stream = response.into_inner();
+
+        streaming_ess
+            .serve_timestamped_subscriptions(
+                SubscribeIntent {
+                    channel_id: channel_id.clone(),
+                    sources: vec![EVENT.into()],
+                    ..Default::default()
+                },
+                |v| v,
+            )
+            .unwrap();
+
+        streaming_ess.publish(EVENT, Timestamped::now(ValueEnum::Null(0)));
+        // The channel is closed when the stream is dropped.
+        _ = (&mut stream).collect_when_stable().await;
+
+        // act
+        let result = execute_system_statistics(streaming_ess, STATISTICS_KEY).await;
+
+        // assert
+        let Some(ValueEnum::Map(Map { map: statistics })) = result else { panic!() };
+        let Some(ValueEnum::Map(Map { map: sources })) =
+            statistics.get("sources").and_then(|v| v.value.clone())
+        else {
+            panic!()
+        };
+        let Some(ValueEnum::Map(Map { map: source })) =
+            sources.get(EVENT).and_then(|v| v.value.clone())
+        else {
+            panic!()
+        };
+        let count = |key: &str| source.get(key).and_then(|v| v.value.clone());
+        assert_eq!(Some(ValueEnum::Int64(1)), count("subscriptions"));
+        assert_eq!(Some(ValueEnum::Int64(1)), count("published"));
+        assert_eq!(Some(ValueEnum::Int64(1)), count("delivered"));
+        assert_eq!(Some(ValueEnum::Int64(0)), count("dropped"));
+
+        let Some(ValueEnum::Map(Map { map: channels })) =
+            statistics.get("channels").and_then(|v| v.value.clone())
+        else {
+            panic!()
+        };
+        let Some(ValueEnum::Map(Map { map: channel })) =
+            channels.get(&channel_id).and_then(|v| v.value.clone())
+        else {
+            panic!()
+        };
+        assert_eq!(
+            Some(ValueEnum::Int64(1)),
+            channel.get("live_tasks").and_then(|v| v.value.clone())
+        );
+    }
+
+    #[tokio::test]
+    async fn ess_statistics_returns_no_value_for_unknown_key() {
+        // act
+        let result = execute_system_statistics(StreamingEss::new(), "unknown").await;
+
+        // assert
+        assert_eq!(None, result);
+    }
+
+    #[tokio::test]
+    async fn resource_accounting_returns_usage_of_callers() {
+        // arrange
+        let accounting = Accounting::new(StreamingEss::new(), Default::default());
+        let _request = accounting.begin_request(&Caller::Named("hmi".into())).unwrap();
+
+        // act
+        let result = ResourceAccounting::new(accounting)
+            .fulfill(IntentEnum::Read(ReadIntent { key: USAGE_KEY.to_owned() }))
+            .await
+            .unwrap();
+
+        // assert
+        let FulfillmentEnum::Read(ReadFulfillment {
+            value: Some(ValueMessage { value: Some(ValueEnum::Map(Map { map: usage })) }),
+            ..
+        }) = result
+        else {
+            panic!()
+        };
+        let Some(ValueEnum::Map(Map { map: caller })) =
+            usage.get("hmi").and_then(|v| v.value.clone())
+        else {
+            panic!()
+        };
+        assert_eq!(
+            Some(ValueEnum::Int64(1)),
+            caller.get("outstanding_requests").and_then(|v| v.value.clone())
+        );
+        assert_eq!(Some(ValueEnum::Int64(0)), caller.get("channels").and_then(|v| v.value.clone()));
+    }
+
+    #[tokio::test]
+    async fn catalog_aggregates_entries_of_all_namespaces() {
+        // arrange
+        let broker =
+            IntentBroker::new("http://localhost:4243".parse().unwrap(), StreamingEss::new());
+        broker.mount(Arc::new(InspectPlugin)).unwrap();
+
+        // act
+        let result = Catalog::new(broker.downgrade())
+            .fulfill(IntentEnum::Inspect(InspectIntent { query: "**".to_owned() }))
+            .await;
+
+        // assert
+        let FulfillmentEnum::Inspect(InspectFulfillment { entries }) = result.unwrap() else {
+            panic!("Wrong fulfillment")
+        };
+        let namespace = |entry: &Entry| match entry.items.get(NAMESPACE_ITEM) {
+            Some(ValueMessage { value: Some(ValueEnum::String(namespace)) }) => namespace.clone(),
+            _ => panic!("Entry without namespace"),
+        };
+        let test_entries: Vec<_> =
+            entries.iter().filter(|entry| namespace(entry) == "system.test").collect();
+        assert_eq!(1, test_entries.len());
+        assert_eq!("speed", test_entries[0].path);
+        assert!(entries.iter().any(|entry| namespace(entry) == "system.registry"));
+        assert!(entries.iter().all(|entry| namespace(entry) != SYSTEM_CATALOG_NAMESPACE));
+    }
+
+    #[tokio::test]
+    async fn catalog_is_unavailable_once_broker_is_dropped() {
+        // arrange
+        let broker =
+            IntentBroker::new("http://localhost:4243".parse().unwrap(), StreamingEss::new());
+        let subject = Catalog::new(broker.downgrade());
+        drop(broker);
+
+        // act
+        let result =
+            subject.fulfill(IntentEnum::Inspect(InspectIntent { query: "**".to_owned() })).await;
+
+        // assert
+        assert_eq!(Code::Unavailable, result.unwrap_err().code());
+    }
+
+    #[tokio::test]
+    async fn runtime_info_reports_build_plugins_and_providers() {
+        // arrange
+        let broker =
+            IntentBroker::new("http://localhost:4243".parse().unwrap(), StreamingEss::new());
+        let intent = IntentConfigurationBuilder::new().namespace("sdv.vdt").build();
+        let services = HashSet::from([ServiceConfigurationBuilder::new()
+            .name("vdt")
+            .version("1.2.0")
+            .url("http://vdt:50051")
+            .build()]);
+        broker.on_change([Change::Add(&intent, &services)].into_iter());
+        let subject = RuntimeInfo::new(&broker, ["overrides"], [("A".to_owned(), "1".to_owned())]);
+
+        // act
+        let result = subject.fulfill(IntentEnum::Read(ReadIntent { key: REPORT_KEY.to_owned() }));
+
+        // assert
+        let FulfillmentEnum::Read(ReadFulfillment {
+            value: Some(ValueMessage { value: Some(ValueEnum::Map(Map { map: report })) }),
+            ..
+        }) = result.await.unwrap()
+        else {
+            panic!()
+        };
+        let get = |key: &str| report.get(key).and_then(|v| v.value.clone());
+        let strings = |key: &str| match get(key) {
+            Some(ValueEnum::List(List { value })) => value
+                .into_iter()
+                .map(|v| match v.value {
+                    Some(ValueEnum::String(s)) => s,
+                    _ => panic!(),
+                })
+                .collect::<Vec<_>>(),
+            _ => panic!(),
+        };
+        assert_eq!(vec!["overrides"], strings("features"));
+        assert_eq!(vec!["system.catalog", "system.ess"], strings("plugins"));
+        assert!(strings("protos").contains(&"intent_brokering.runtime.v1".to_owned()));
+        assert_eq!(
+            Some(ValueEnum::String(digest([("A".into(), "1".into())].into()))),
+            get("config_digest")
+        );
+
+        let Some(ValueEnum::List(List { value: providers })) = get("providers") else { panic!() };
+        let [ValueMessage { value: Some(ValueEnum::Map(Map { map: provider })) }] =
+            providers.as_slice()
+        else {
+            panic!()
+        };
+        let item = |key: &str| provider.get(key).and_then(|v| v.value.clone());
+        assert_eq!(Some(ValueEnum::String("vdt".to_owned())), item("name"));
+        assert_eq!(Some(ValueEnum::String("1.2.0".to_owned())), item("version"));
+        assert_eq!(Some(ValueEnum::String("http://vdt:50051/".to_owned())), item("url"));
+    }
+
+    #[test]
+    fn new_excludes_secrets_from_config_digest() {
+        // arrange
+        let broker =
+            IntentBroker::new("http://localhost:4243".parse().unwrap(), StreamingEss::new());
+        let entry = |key: &str, value: &str| (key.to_owned(), value.to_owned());
+        let configuration = [
+            entry("INTENT_BROKERING_PORT", "4243"),
+            entry("INTENT_BROKERING_WEBHOOK_SECRET", "secret"),
+            entry("INTENT_BROKERING_REGISTRATION_SECRETS", "sdv=secret"),
+            entry("INTENT_BROKERING_UPSTREAM_TOKEN", "token"),
+        ];
+
+        // act
+        let subject = RuntimeInfo::new(&broker, Vec::<String>::new(), configuration);
+
+        // assert
+        assert_eq!(digest([entry("INTENT_BROKERING_PORT", "4243")].into()), *subject.config_digest);
+    }
+
+    #[test]
+    fn is_secret_matches_words_of_key() {
+        assert!(is_secret("INTENT_BROKERING_WEBHOOK_SECRET"));
+        assert!(is_secret("INTENT_BROKERING_API_KEYS"));
+        assert!(is_secret("intent_brokering_password"));
+        assert!(!is_secret("INTENT_BROKERING_ACL_PATH"));
+        assert!(!is_secret("INTENT_BROKERING_IDEMPOTENCY_CAPACITY"));
+        assert!(!is_secret("EXTERNAL_HOST_NAME"));
+    }
+
+    #[test]
+    fn digest_depends_on_entries_only() {
+        // arrange
+        let entry = |key: &str, value: &str| (key.to_owned(), value.to_owned());
+
+        // act
+        let result = digest([entry("a", "1"), entry("b", "2")].into());
+
+        // assert
+        assert_eq!(result, digest([entry("b", "2"), entry("a", "1")].into()));
+        assert_ne!(result, digest([entry("a", "12")].into()));
+        assert_ne!(result, digest([entry("a", "1"), entry("b", "3")].into()));
+        assert_eq!(16, result.len());
+    }
+
+    struct InspectPlugin;
+
+    #[async_trait]
+    impl SystemPlugin for InspectPlugin {
+        fn namespace(&self) -> &str {
+            "system.test"
+        }
+
+        fn intents(&self) -> Vec<IntentKind> {
+            vec![IntentKind::Inspect]
+        }
+
+        async fn fulfill(&self, _: IntentEnum) -> Result<FulfillmentEnum, Status> {
+            Ok(FulfillmentEnum::Inspect(InspectFulfillment {
+                entries: vec![Entry { path: "speed".to_owned(), items: Default::default() }],
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn log_settings_are_changed_at_runtime() {
+        // arrange
+        let log = FulfillmentLog::new(Default::default());
+        let subject = LogSettings::new(log.clone());
+        let write = |key: &str, value: ValueEnum| {
+            IntentEnum::Write(WriteIntent {
+                key: key.to_owned(),
+                value: Some(ValueMessage { value: Some(value) }),
+                precondition: None,
+            })
+        };
+
+        // act
+        subject.fulfill(write(SAMPLE_RATE_KEY, ValueEnum::Int32(10))).await.unwrap();
+        subject.fulfill(write(FLUSH_INTERVAL_KEY, ValueEnum::Int64(30))).await.unwrap();
+        let negative = subject.fulfill(write(SAMPLE_RATE_KEY, ValueEnum::Int32(-1))).await;
+        let unknown = subject.fulfill(write("level", ValueEnum::Int32(1))).await;
+        let read = subject
+            .fulfill(IntentEnum::Read(ReadIntent { key: FLUSH_INTERVAL_KEY.to_owned() }))
+            .await;
+
+        // assert
+        assert_eq!(10, log.sample_rate());
+        assert_eq!(Duration::from_secs(30), log.flush_interval());
+        assert_eq!(Code::InvalidArgument, negative.unwrap_err().code());
+        assert_eq!(Code::NotFound, unknown.unwrap_err().code());
+        match read.unwrap() {
+            FulfillmentEnum::Read(ReadFulfillment { value, .. }) => {
+                assert_eq!(Some(ValueEnum::Int64(30)), value.unwrap().value)
+            }
+            _ => panic!("Wrong fulfillment"),
+        }
+    }
+
+    async fn execute_system_statistics(ess: StreamingEss, key: &str) -> Option<ValueEnum> {
+        let response = EssStatistics::new(ess)
+            .fulfill(IntentEnum::Read(ReadIntent { key: key.to_owned() }))
+            .await;
+
+        match response.unwrap() {
+            FulfillmentEnum::Read(ReadFulfillment { value, .. }) => value.unwrap().value,
+            _ => panic!("Wrong fulfillment"),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Notifies systemd about the state of the Intent Broker, see `sd_notify(3)`.
+//!
+//! When started as a service of `Type=notify`, the Intent Broker sends
+//! `READY=1` once it listens for requests and loaded its static registrations,
+//! and `STOPPING=1` when it shuts down. If the service sets `WatchdogSec=`,
+//! the watchdog is petted with `WATCHDOG=1` at half of its interval for as
+//! long as the Intent Broker is healthy, such that systemd restarts it if it
+//! hangs. Without the `NOTIFY_SOCKET` set by systemd, nothing is sent.
+
+use std::{env, io, time::Duration};
+
+const NOTIFY_SOCKET_ENV: &str = "NOTIFY_SOCKET";
+const WATCHDOG_USEC_ENV: &str = "WATCHDOG_USEC";
+const WATCHDOG_PID_ENV: &str = "WATCHDOG_PID";
+
+pub const READY: &str = "READY=1";
+pub const STOPPING: &str = "STOPPING=1";
+pub const WATCHDOG: &str = "WATCHDOG=1";
+
+/// Sends a state to systemd, returning whether it was sent, which it is not
+/// if the Intent Broker was not started by systemd.
+pub fn notify(state: &str) -> io::Result<bool> {
+    match env::var(NOTIFY_SOCKET_ENV) {
+        Ok(socket) => send(&socket, state).map(|_| true),
+        Err(_) => Ok(false),
+    }
+}
+
+#[cfg(unix)]
+fn send(socket: &str, state: &str) -> io::Result<()> {
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+    // Sockets starting with '@' are in the abstract namespace.
+    let address = match socket.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt as _;
+            SocketAddr::from_abstract_name(name)?
+        }
+        _ => SocketAddr::from_pathname(socket)?,
+    };
+
+    UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &address).map(|_| ())
+}
+
+#[cfg(not(unix))]
+fn send(_: &str, _: &str) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "Notifying systemd requires Unix sockets."))
+}
+
+/// Returns the interval in which the watchdog expects to be petted, if it is
+/// enabled for the Intent Broker.
+pub fn watchdog_interval() -> Option<Duration> {
+    parse_watchdog_interval(
+        env::var(WATCHDOG_USEC_ENV).ok().as_deref(),
+        env::var(WATCHDOG_PID_ENV).ok().as_deref(),
+        std::process::id(),
+    )
+}
+
+/// The watchdog is enabled for another process, e.g. the parent, if its
+/// process ID is set and differs.
+fn parse_watchdog_interval(
+    usec: Option<&str>,
+    pid: Option<&str>,
+    own_pid: u32,
+) -> Option<Duration> {
+    if pid.is_some_and(|pid| pid.parse() != Ok(own_pid)) {
+        return None;
+    }
+
+    usec.and_then(|usec| usec.parse().ok()).filter(|usec| *usec > 0).map(Duration::from_micros)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::parse_watchdog_interval;
+
+    #[test]
+    fn parse_watchdog_interval_is_none_if_disabled_or_for_other_process() {
+        assert_eq!(None, parse_watchdog_interval(None, None, 1));
+        assert_eq!(None, parse_watchdog_interval(Some("0"), None, 1));
+        assert_eq!(None, parse_watchdog_interval(Some("1000"), Some("2"), 1));
+        assert_eq!(
+            Some(Duration::from_millis(1)),
+            parse_watchdog_interval(Some("1000"), Some("1"), 1)
+        );
+        assert_eq!(Some(Duration::from_millis(1)), parse_watchdog_interval(Some("1000"), None, 1));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn send_writes_state_to_socket() {
+        use std::os::unix::net::UnixDatagram;
+
+        // arrange
+        let path = std::env::temp_dir().join(format!("notify-{}.sock", uuid::Uuid::new_v4()));
+        let socket = UnixDatagram::bind(&path).unwrap();
+
+        // act
+        let result = super::send(path.to_str().unwrap(), super::READY);
+
+        // assert
+        let mut buffer = [0; 16];
+        let length = socket.recv(&mut buffer).unwrap();
+        _ = std::fs::remove_file(&path);
+        assert!(result.is_ok());
+        assert_eq!(super::READY.as_bytes(), &buffer[..length]);
+    }
+}
@@ -0,0 +1,345 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use intent_brokering_common::error::Error;
+use intent_brokering_proto::{
+    common::IntentMessage,
+    provider::{
+        operation::Phase as OperationPhase, transaction::Phase, FulfillRequest, FulfillResponse,
+        Operation as OperationMessage, Transaction as TransactionMessage,
+    },
+    runtime::{
+        fulfill_transaction_response::{
+            operation_result::Result as OperationResultEnum, OperationResult,
+        },
+        FulfillTransactionResponse, TransactionOutcome,
+    },
+};
+use url::Url;
+use uuid::Uuid;
+
+use crate::connection_provider::{ConnectedProvider as _, ConnectionProvider};
+
+/// A provider which an operation of a transaction is forwarded to. The URL
+/// identifies the provider, such that each provider is asked to commit or
+/// abort only once, regardless of how many operations it prepared.
+#[derive(Clone, Debug)]
+pub struct Participant<T> {
+    url: Url,
+    provider: T,
+    transactional: bool,
+}
+
+impl<T> Participant<T> {
+    pub fn new(url: Url, provider: T, transactional: bool) -> Self {
+        Self { url, provider, transactional }
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Returns whether the provider registered as able to prepare, commit and
+    /// abort transactions.
+    pub fn transactional(&self) -> bool {
+        self.transactional
+    }
+}
+
+impl<T: ConnectionProvider + Send> Participant<T>
+where
+    T::ConnectedProvider: Send,
+{
+    async fn fulfill(
+        &mut self,
+        transaction_id: &str,
+        phase: Phase,
+        intent: Option<IntentMessage>,
+    ) -> Result<FulfillResponse, Error> {
+        self.provider
+            .connect()
+            .await?
+            .fulfill(FulfillRequest {
+                intent,
+                transaction: Some(TransactionMessage {
+                    id: transaction_id.to_owned(),
+                    phase: phase as i32,
+                }),
+                operation: None,
+            })
+            .await
+    }
+
+    /// Forwards a phase of a long-running operation, see
+    /// [`crate::operation`].
+    pub(crate) async fn fulfill_operation(
+        &mut self,
+        operation_id: &str,
+        phase: OperationPhase,
+        intent: Option<IntentMessage>,
+    ) -> Result<FulfillResponse, Error> {
+        self.provider
+            .connect()
+            .await?
+            .fulfill(FulfillRequest {
+                intent,
+                transaction: None,
+                operation: Some(OperationMessage {
+                    id: operation_id.to_owned(),
+                    phase: phase as i32,
+                }),
+            })
+            .await
+    }
+}
+
+/// Coordinates a transaction over a set of operations using a two-phase
+/// commit. All operations are prepared in order. If all of them are prepared,
+/// each participant is asked to commit, otherwise each participant that
+/// received an operation is asked to abort.
+pub struct Transaction<T> {
+    id: Box<str>,
+    operations: Vec<(Participant<T>, IntentMessage)>,
+}
+
+impl<T: ConnectionProvider + Clone + Send> Transaction<T>
+where
+    T::ConnectedProvider: Send,
+{
+    pub fn new(operations: impl IntoIterator<Item = (Participant<T>, IntentMessage)>) -> Self {
+        Self { id: Uuid::new_v4().to_string().into(), operations: operations.into_iter().collect() }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub async fn execute(self) -> FulfillTransactionResponse {
+        let operation_count = self.operations.len();
+        let mut participants: Vec<Participant<T>> = vec![];
+        let mut results = Vec::with_capacity(operation_count);
+        let mut failure = None;
+
+        for (mut participant, intent) in self.operations {
+            if !participants.iter().any(|p| p.url == participant.url) {
+                participants.push(participant.clone());
+            }
+
+            match participant.fulfill(&self.id, Phase::Prepare, Some(intent)).await {
+                Ok(response) => results.push(OperationResultEnum::Fulfillment(
+                    response.fulfillment.unwrap_or_default(),
+                )),
+                Err(e) => {
+                    failure = Some(e);
+                    break;
+                }
+            }
+        }
+
+        let (outcome, results) = if let Some(failure) = failure {
+            tracing::debug!("Aborting transaction '{}': {failure}", self.id);
+
+            for participant in participants.iter_mut() {
+                if let Err(e) = participant.fulfill(&self.id, Phase::Abort, None).await {
+                    tracing::warn!(
+                        "Provider '{}' failed to abort transaction '{}': {e}",
+                        participant.url,
+                        self.id
+                    );
+                }
+            }
+
+            let prepared_count = results.len();
+            let results = (0..operation_count)
+                .map(|index| {
+                    OperationResultEnum::Error(match index {
+                        i if i < prepared_count => "Transaction was aborted.".to_owned(),
+                        i if i == prepared_count => failure.to_string(),
+                        _ => {
+                            "Transaction was aborted before the operation was prepared.".to_owned()
+                        }
+                    })
+                })
+                .collect();
+
+            (TransactionOutcome::Aborted, results)
+        } else {
+            let mut outcome = TransactionOutcome::Committed;
+
+            for participant in participants.iter_mut() {
+                if let Err(e) = participant.fulfill(&self.id, Phase::Commit, None).await {
+                    tracing::warn!(
+                        "Provider '{}' failed to commit transaction '{}': {e}",
+                        participant.url,
+                        self.id
+                    );
+                    outcome = TransactionOutcome::Incomplete;
+                }
+            }
+
+            (outcome, results)
+        };
+
+        FulfillTransactionResponse {
+            transaction_id: self.id.into(),
+            outcome: outcome as i32,
+            results: results.into_iter().map(|r| OperationResult { result: Some(r) }).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+    use intent_brokering_proto::common::{
+        FulfillmentEnum, FulfillmentMessage, IntentEnum, WriteFulfillment, WriteIntent,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn when_all_operations_are_prepared_commits_each_participant_once() {
+        // arrange
+        let log = Log::default();
+        let a = MockProvider::participant("a", &log, false);
+        let b = MockProvider::participant("b", &log, false);
+        let subject = Transaction::new([(a.clone(), write("1")), (b, write("2")), (a, write("3"))]);
+        let id = subject.id().to_owned();
+
+        // act
+        let response = subject.execute().await;
+
+        // assert
+        assert_eq!(id, response.transaction_id);
+        assert_eq!(TransactionOutcome::Committed as i32, response.outcome);
+        assert_eq!(3, response.results.len());
+        assert!(response
+            .results
+            .iter()
+            .all(|r| matches!(r.result, Some(OperationResultEnum::Fulfillment(_)))));
+        assert_eq!(
+            vec![
+                ("a".to_owned(), Phase::Prepare, Some("1".to_owned())),
+                ("b".to_owned(), Phase::Prepare, Some("2".to_owned())),
+                ("a".to_owned(), Phase::Prepare, Some("3".to_owned())),
+                ("a".to_owned(), Phase::Commit, None),
+                ("b".to_owned(), Phase::Commit, None),
+            ],
+            log.requests(&id)
+        );
+    }
+
+    #[tokio::test]
+    async fn when_an_operation_fails_to_prepare_aborts_participants() {
+        // arrange
+        let log = Log::default();
+        let a = MockProvider::participant("a", &log, false);
+        let b = MockProvider::participant("b", &log, true);
+        let c = MockProvider::participant("c", &log, false);
+        let subject = Transaction::new([(a, write("1")), (b, write("2")), (c, write("3"))]);
+        let id = subject.id().to_owned();
+
+        // act
+        let response = subject.execute().await;
+
+        // assert
+        assert_eq!(TransactionOutcome::Aborted as i32, response.outcome);
+        assert_eq!(3, response.results.len());
+        assert!(response
+            .results
+            .iter()
+            .all(|r| matches!(r.result, Some(OperationResultEnum::Error(_)))));
+        assert_eq!(
+            vec![
+                ("a".to_owned(), Phase::Prepare, Some("1".to_owned())),
+                ("b".to_owned(), Phase::Prepare, Some("2".to_owned())),
+                ("a".to_owned(), Phase::Abort, None),
+                ("b".to_owned(), Phase::Abort, None),
+            ],
+            log.requests(&id)
+        );
+    }
+
+    fn write(key: &str) -> IntentMessage {
+        IntentMessage {
+            intent: Some(IntentEnum::Write(WriteIntent {
+                key: key.to_owned(),
+                value: None,
+                precondition: None,
+            })),
+        }
+    }
+
+    type Request = (String, Phase, Option<String>);
+
+    #[derive(Clone, Default)]
+    struct Log(Arc<Mutex<Vec<(String, FulfillRequest)>>>);
+
+    impl Log {
+        fn requests(&self, transaction_id: &str) -> Vec<Request> {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(name, request)| {
+                    let transaction = request.transaction.clone().unwrap();
+                    assert_eq!(transaction_id, transaction.id);
+                    let key = match request.intent.clone().and_then(|i| i.intent) {
+                        Some(IntentEnum::Write(write)) => Some(write.key),
+                        _ => None,
+                    };
+                    (name.clone(), Phase::try_from(transaction.phase).unwrap(), key)
+                })
+                .collect()
+        }
+    }
+
+    #[derive(Clone)]
+    struct MockProvider {
+        name: String,
+        log: Log,
+        fail_prepare: bool,
+    }
+
+    impl MockProvider {
+        fn participant(name: &str, log: &Log, fail_prepare: bool) -> Participant<Self> {
+            let url = format!("http://{name}").parse().unwrap(); // DevSkim: ignore DS137138
+            let provider = Self { name: name.to_owned(), log: log.clone(), fail_prepare };
+            Participant::new(url, provider, true)
+        }
+    }
+
+    #[async_trait]
+    impl ConnectionProvider for MockProvider {
+        type ConnectedProvider = Self;
+
+        fn new(_: Url) -> Self {
+            unimplemented!()
+        }
+
+        async fn connect(&mut self) -> Result<Self::ConnectedProvider, Error> {
+            Ok(self.clone())
+        }
+    }
+
+    #[async_trait]
+    impl crate::connection_provider::ConnectedProvider for MockProvider {
+        async fn fulfill(&mut self, request: FulfillRequest) -> Result<FulfillResponse, Error> {
+            let is_prepare = request.intent.is_some();
+            self.log.0.lock().unwrap().push((self.name.clone(), request));
+
+            if is_prepare && self.fail_prepare {
+                return Err(Error::new("Preparing failed."));
+            }
+
+            Ok(FulfillResponse {
+                fulfillment: Some(FulfillmentMessage {
+                    fulfillment: Some(FulfillmentEnum::Write(WriteFulfillment::default())),
+                }),
+            })
+        }
+    }
+}
@@ -0,0 +1,400 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Transforms intents and their fulfillments between the conventions of
+//! consumers and providers, e.g. when a consumer reads speeds in km/h from a
+//! provider which reports them in m/s.
+//!
+//! The transformations are declared per namespace and per key of the
+//! consumer. A key can be renamed to the key of the provider, numbers read
+//! from the provider are converted with `value * scale + offset` and numbers
+//! written to it with the inverse, and strings are mapped with `enum`, which
+//! maps the values of the consumer to the ones of the provider. `Read`,
+//! `Write`, write batches and `Delete` are transformed, including the values
+//! of preconditions. Other intents are forwarded unchanged.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use intent_brokering_common::error::{Error, ResultExt as _};
+use intent_brokering_proto::common::{
+    write_precondition::Precondition, FulfillmentEnum, FulfillmentMessage, IntentEnum,
+    IntentMessage, ValueEnum, WriteIntent, WritePrecondition,
+};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyDefinition {
+    /// The key of the provider, if it differs from the key of the consumer.
+    key: Option<String>,
+    #[serde(default = "scale_default")]
+    scale: f64,
+    #[serde(default)]
+    offset: f64,
+    #[serde(default, rename = "enum")]
+    values: HashMap<String, String>,
+}
+
+fn scale_default() -> f64 {
+    1.0
+}
+
+struct KeyTransform {
+    provider_key: Option<Box<str>>,
+    scale: f64,
+    offset: f64,
+    to_provider: HashMap<String, String>,
+    to_consumer: HashMap<String, String>,
+}
+
+impl KeyTransform {
+    fn parse(key: &str, definition: KeyDefinition) -> Result<Self, Error> {
+        if !definition.scale.is_finite() || definition.scale == 0.0 {
+            return Err(Error::new(format!("Scale of '{key}' must be a non-zero number.")));
+        }
+
+        if !definition.offset.is_finite() {
+            return Err(Error::new(format!("Offset of '{key}' must be a number.")));
+        }
+
+        let mut to_consumer = HashMap::new();
+        for (consumer, provider) in &definition.values {
+            if to_consumer.insert(provider.clone(), consumer.clone()).is_some() {
+                return Err(Error::new(format!(
+                    "Value '{provider}' of '{key}' is mapped to more than once."
+                )));
+            }
+        }
+
+        Ok(Self {
+            provider_key: definition.key.map(Into::into),
+            scale: definition.scale,
+            offset: definition.offset,
+            to_provider: definition.values,
+            to_consumer,
+        })
+    }
+
+    fn value_to_provider(&self, value: &mut ValueEnum) {
+        self.convert(value, |v| (v - self.offset) / self.scale, &self.to_provider);
+    }
+
+    fn value_to_consumer(&self, value: &mut ValueEnum) {
+        self.convert(value, |v| v * self.scale + self.offset, &self.to_consumer);
+    }
+
+    fn convert(
+        &self,
+        value: &mut ValueEnum,
+        f: impl Fn(f64) -> f64,
+        values: &HashMap<String, String>,
+    ) {
+        // Numbers are only converted if needed, as 64-bit integers may lose
+        // precision.
+        let scaled = self.scale != 1.0 || self.offset != 0.0;
+
+        match value {
+            ValueEnum::Int32(v) if scaled => *v = f(*v as f64).round() as i32,
+            ValueEnum::Int64(v) if scaled => *v = f(*v as f64).round() as i64,
+            ValueEnum::Float32(v) if scaled => *v = f(*v as f64) as f32,
+            ValueEnum::Float64(v) if scaled => *v = f(*v),
+            ValueEnum::String(v) => {
+                if let Some(mapped) = values.get(v.as_str()) {
+                    *v = mapped.clone();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+struct Namespace {
+    by_key: HashMap<Box<str>, KeyTransform>,
+    key_by_provider_key: HashMap<Box<str>, Box<str>>,
+}
+
+impl Namespace {
+    fn parse(namespace: &str, keys: HashMap<String, KeyDefinition>) -> Result<Self, Error> {
+        let mut by_key = HashMap::new();
+        let mut key_by_provider_key = HashMap::new();
+
+        for (key, definition) in keys {
+            let transform = KeyTransform::parse(&key, definition)?;
+            if let Some(provider_key) = &transform.provider_key {
+                if key_by_provider_key.insert(provider_key.clone(), key.clone().into()).is_some() {
+                    return Err(Error::new(format!(
+                        "Key '{provider_key}' of '{namespace}' is renamed to more than once."
+                    )));
+                }
+            }
+            by_key.insert(key.into(), transform);
+        }
+
+        Ok(Self { by_key, key_by_provider_key })
+    }
+
+    fn write_to_provider(&self, write: &mut WriteIntent) {
+        let Some(transform) = self.by_key.get(write.key.as_str()) else {
+            return;
+        };
+
+        if let Some(value) = write.value.as_mut().and_then(|v| v.value.as_mut()) {
+            transform.value_to_provider(value);
+        }
+
+        precondition_to_provider(transform, write.precondition.as_mut());
+
+        if let Some(provider_key) = &transform.provider_key {
+            write.key = provider_key.to_string();
+        }
+    }
+
+    fn consumer_key<'a>(&'a self, provider_key: &'a str) -> &'a str {
+        self.key_by_provider_key.get(provider_key).map_or(provider_key, |key| &**key)
+    }
+}
+
+fn precondition_to_provider(
+    transform: &KeyTransform,
+    precondition: Option<&mut WritePrecondition>,
+) {
+    if let Some(WritePrecondition { precondition: Some(Precondition::ExpectedValue(expected)) }) =
+        precondition
+    {
+        if let Some(value) = expected.value.as_mut() {
+            transform.value_to_provider(value);
+        }
+    }
+}
+
+/// Transformations loaded from a JSON file, which maps namespaces to the
+/// transformations of their keys, e.g.
+/// `{ "sdv.vdt": { "Vehicle.Speed": { "key": "speed", "scale": 3.6 } } }`.
+pub struct Transforms {
+    namespaces: HashMap<Box<str>, Namespace>,
+}
+
+impl Transforms {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let transforms = fs::read_to_string(path)
+            .map_err_with(format!("Failed to read '{}'.", path.display()))?;
+        Self::parse(&transforms)
+    }
+
+    pub fn parse(transforms: &str) -> Result<Self, Error> {
+        let definition: HashMap<String, HashMap<String, KeyDefinition>> =
+            serde_json::from_str(transforms).map_err_with("Failed to parse the transforms.")?;
+
+        let namespaces = definition
+            .into_iter()
+            .map(|(namespace, keys)| {
+                let parsed = Namespace::parse(&namespace, keys)?;
+                Ok((namespace.into(), parsed))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Self { namespaces })
+    }
+
+    /// Transforms an intent of a consumer for the provider of the namespace.
+    /// Returns how to transform the fulfillment back for the consumer, if the
+    /// namespace has transformations.
+    pub fn to_provider(
+        &self,
+        namespace: &str,
+        intent: &mut IntentMessage,
+    ) -> Option<ConsumerTransform<'_>> {
+        let namespace = self.namespaces.get(namespace)?;
+        let mut read_key = None;
+
+        match intent.intent.as_mut() {
+            Some(IntentEnum::Read(read)) => {
+                if let Some(transform) = namespace.by_key.get(read.key.as_str()) {
+                    read_key = Some(read.key.as_str().into());
+                    if let Some(provider_key) = &transform.provider_key {
+                        read.key = provider_key.to_string();
+                    }
+                }
+            }
+            Some(IntentEnum::Write(write)) => namespace.write_to_provider(write),
+            Some(IntentEnum::WriteBatch(batch)) => {
+                batch.writes.iter_mut().for_each(|write| namespace.write_to_provider(write))
+            }
+            Some(IntentEnum::Delete(delete)) => {
+                if let Some(transform) = namespace.by_key.get(delete.key.as_str()) {
+                    precondition_to_provider(transform, delete.precondition.as_mut());
+                    if let Some(provider_key) = &transform.provider_key {
+                        delete.key = provider_key.to_string();
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Some(ConsumerTransform { namespace, read_key })
+    }
+}
+
+/// Transforms the fulfillment of an intent, which was transformed for the
+/// provider, back to the conventions of the consumer.
+pub struct ConsumerTransform<'a> {
+    namespace: &'a Namespace,
+    read_key: Option<Box<str>>,
+}
+
+impl ConsumerTransform<'_> {
+    pub fn apply(&self, fulfillment: &mut FulfillmentMessage) {
+        match fulfillment.fulfillment.as_mut() {
+            Some(FulfillmentEnum::Read(read)) => {
+                let transform =
+                    self.read_key.as_ref().and_then(|key| self.namespace.by_key.get(key));
+                if let (Some(transform), Some(value)) =
+                    (transform, read.value.as_mut().and_then(|v| v.value.as_mut()))
+                {
+                    transform.value_to_consumer(value);
+                }
+            }
+            Some(FulfillmentEnum::WriteBatch(batch)) => {
+                for entry in batch.entries.iter_mut() {
+                    entry.key = self.namespace.consumer_key(&entry.key).to_owned();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use intent_brokering_proto::common::{
+        write_batch_fulfillment::Entry, ReadFulfillment, ReadIntent, ValueMessage,
+        WriteBatchFulfillment, WriteBatchIntent,
+    };
+
+    use super::*;
+
+    const TRANSFORMS: &str = r#"{
+        "sdv.vdt": {
+            "Vehicle.Speed": { "key": "speed", "scale": 3.6 },
+            "Vehicle.Temperature": { "scale": 1.8, "offset": 32 },
+            "Vehicle.Gear": { "key": "gear", "enum": { "park": "P", "drive": "D" } }
+        }
+    }"#;
+
+    fn value(value: ValueEnum) -> Option<ValueMessage> {
+        Some(ValueMessage { value: Some(value) })
+    }
+
+    fn write(key: &str, value: ValueEnum) -> WriteIntent {
+        WriteIntent { key: key.to_owned(), value: self::value(value), precondition: None }
+    }
+
+    fn intent(intent: IntentEnum) -> IntentMessage {
+        IntentMessage { intent: Some(intent) }
+    }
+
+    #[test]
+    fn to_provider_renames_key_and_converts_read_value_for_consumer() {
+        // arrange
+        let subject = Transforms::parse(TRANSFORMS).unwrap();
+        let mut read = intent(IntentEnum::Read(ReadIntent { key: "Vehicle.Speed".to_owned() }));
+        let mut fulfillment = FulfillmentMessage {
+            fulfillment: Some(FulfillmentEnum::Read(ReadFulfillment {
+                value: value(ValueEnum::Float64(10.0)),
+                ..Default::default()
+            })),
+        };
+
+        // act
+        let consumer_transform = subject.to_provider("sdv.vdt", &mut read).unwrap();
+        consumer_transform.apply(&mut fulfillment);
+
+        // assert
+        assert_eq!(intent(IntentEnum::Read(ReadIntent { key: "speed".to_owned() })), read);
+        let Some(FulfillmentEnum::Read(read)) = fulfillment.fulfillment else { panic!() };
+        assert_eq!(value(ValueEnum::Float64(36.0)), read.value);
+    }
+
+    #[test]
+    fn to_provider_converts_written_values() {
+        // arrange
+        let subject = Transforms::parse(TRANSFORMS).unwrap();
+        let mut batch = intent(IntentEnum::WriteBatch(WriteBatchIntent {
+            writes: vec![
+                write("Vehicle.Temperature", ValueEnum::Int32(212)),
+                write("Vehicle.Gear", ValueEnum::String("park".to_owned())),
+                write("Vehicle.Unknown", ValueEnum::Int32(1)),
+            ],
+        }));
+
+        // act
+        subject.to_provider("sdv.vdt", &mut batch).unwrap();
+
+        // assert
+        assert_eq!(
+            intent(IntentEnum::WriteBatch(WriteBatchIntent {
+                writes: vec![
+                    write("Vehicle.Temperature", ValueEnum::Int32(100)),
+                    write("gear", ValueEnum::String("P".to_owned())),
+                    write("Vehicle.Unknown", ValueEnum::Int32(1)),
+                ],
+            })),
+            batch
+        );
+    }
+
+    #[test]
+    fn apply_restores_keys_of_write_batch_entries() {
+        // arrange
+        let subject = Transforms::parse(TRANSFORMS).unwrap();
+        let mut batch = intent(IntentEnum::WriteBatch(WriteBatchIntent {
+            writes: vec![write("Vehicle.Speed", ValueEnum::Float64(36.0))],
+        }));
+        let entry = |key: &str| Entry { key: key.to_owned(), result: None };
+        let mut fulfillment = FulfillmentMessage {
+            fulfillment: Some(FulfillmentEnum::WriteBatch(WriteBatchFulfillment {
+                entries: vec![entry("speed")],
+            })),
+        };
+
+        // act
+        subject.to_provider("sdv.vdt", &mut batch).unwrap().apply(&mut fulfillment);
+
+        // assert
+        let Some(FulfillmentEnum::WriteBatch(batch)) = fulfillment.fulfillment else { panic!() };
+        assert_eq!(vec![entry("Vehicle.Speed")], batch.entries);
+    }
+
+    #[test]
+    fn to_provider_ignores_namespaces_without_transforms() {
+        // arrange
+        let subject = Transforms::parse(TRANSFORMS).unwrap();
+        let mut read = intent(IntentEnum::Read(ReadIntent { key: "Vehicle.Speed".to_owned() }));
+
+        // act
+        let result = subject.to_provider("sdv.kvs", &mut read);
+
+        // assert
+        assert!(result.is_none());
+        assert_eq!(intent(IntentEnum::Read(ReadIntent { key: "Vehicle.Speed".to_owned() })), read);
+    }
+
+    #[test]
+    fn parse_fails_for_invalid_transforms() {
+        // act
+        let zero_scale = Transforms::parse(r#"{ "sdv.vdt": { "a": { "scale": 0 } } }"#);
+        let ambiguous_enum =
+            Transforms::parse(r#"{ "sdv.vdt": { "a": { "enum": { "x": "1", "y": "1" } } } }"#);
+        let ambiguous_key =
+            Transforms::parse(r#"{ "sdv.vdt": { "a": { "key": "c" }, "b": { "key": "c" } } }"#);
+
+        // assert
+        assert!(zero_scale.is_err());
+        assert!(ambiguous_enum.is_err());
+        assert!(ambiguous_key.is_err());
+    }
+}
@@ -0,0 +1,368 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Forwards intents for namespaces which are not served locally to an
+//! upstream Intent Broker, e.g. from a zone controller to the central
+//! computer, such that brokers can be arranged hierarchically.
+//!
+//! The `authorization`, idempotency key and session metadata of a request are
+//! forwarded along with its intent, such that the upstream broker identifies
+//! and authorizes the original caller instead of the forwarding broker,
+//! suppresses duplicates of the same idempotency key and keeps the session
+//! on the same provider. Each broker which forwards an intent appends its ID
+//! to the `x-chariott-via` metadata of the request. A broker does not forward an
+//! intent which already passed through it, or which passed through too many
+//! brokers, such that misconfigured hierarchies do not forward in a loop.
+//!
+//! The responses to `Discover` and `Inspect` intents, including the lack of
+//! a provider upstream, are cached per caller for a short time, as they
+//! rarely change.
+//! Other intents, in particular those which change state, are always
+//! forwarded.
+//! `Subscribe` intents are not forwarded, as channels are local to a broker.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use intent_brokering_common::error::{Error, ResultExt as _};
+use intent_brokering_proto::{
+    common::{intent::Intent, FulfillmentMessage, IntentMessage},
+    runtime::{intent_brokering_service_client::IntentBrokeringServiceClient, FulfillRequest},
+};
+use prost::Message as _;
+use tonic::{
+    metadata::{MetadataMap, MetadataValue},
+    transport::{Channel, Endpoint},
+    Code, Request, Status,
+};
+use url::Url;
+use uuid::Uuid;
+
+use crate::correlation;
+use crate::idempotency::IDEMPOTENCY_KEY_METADATA_KEY;
+use crate::identity::AUTHORIZATION_METADATA_KEY;
+use crate::session::SESSION_METADATA_KEY;
+
+pub const VIA_METADATA_KEY: &str = "x-chariott-via";
+
+const MAX_HOPS: usize = 8;
+const NO_PROVIDER_MESSAGE: &str = "No provider found.";
+const CACHE_CAPACITY: usize = 1000;
+const FORWARDED_METADATA_KEYS: [&str; 3] =
+    [AUTHORIZATION_METADATA_KEY, IDEMPOTENCY_KEY_METADATA_KEY, SESSION_METADATA_KEY];
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    url: Url,
+    cache_ttl: Duration,
+}
+
+impl Config {
+    pub fn new(url: Url) -> Self {
+        Self { url, cache_ttl: Duration::from_secs(5) }
+    }
+
+    /// The URL of the upstream Intent Broker.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The duration for which responses of the upstream Intent Broker are
+    /// cached. Zero disables the cache.
+    pub fn cache_ttl(&self) -> Duration {
+        self.cache_ttl
+    }
+
+    pub fn set_cache_ttl(self, value: Duration) -> Self {
+        Self { cache_ttl: value, ..self }
+    }
+}
+
+type CacheKey = (Box<str>, Option<Box<[u8]>>, Vec<u8>);
+type CachedResult = Result<Option<FulfillmentMessage>, (Code, String)>;
+
+/// Responses of the upstream Intent Broker by namespace, authorization of the
+/// caller and encoded intent.
+struct Cache {
+    ttl: Duration,
+    entries: HashMap<CacheKey, (Instant, CachedResult)>,
+}
+
+impl Cache {
+    fn new(ttl: Duration) -> Self {
+        Self { ttl, entries: HashMap::new() }
+    }
+
+    fn get(&self, key: &CacheKey, now: Instant) -> Option<CachedResult> {
+        self.entries.get(key).filter(|(expiry, _)| *expiry > now).map(|(_, result)| result.clone())
+    }
+
+    fn insert(&mut self, key: CacheKey, result: CachedResult, now: Instant) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        if self.entries.len() >= CACHE_CAPACITY {
+            self.entries.retain(|_, (expiry, _)| *expiry > now);
+            if self.entries.len() >= CACHE_CAPACITY {
+                return;
+            }
+        }
+
+        self.entries.insert(key, (now + self.ttl, result));
+    }
+}
+
+/// Forwards intents to the upstream Intent Broker.
+pub struct Upstream {
+    id: Box<str>,
+    client: IntentBrokeringServiceClient<Channel>,
+    cache: Mutex<Cache>,
+}
+
+impl Upstream {
+    pub fn new(config: Config) -> Result<Self, Error> {
+        let endpoint = Endpoint::from_shared(config.url.to_string())
+            .map_err_with("Upstream URL is not valid.")?;
+
+        Ok(Self {
+            id: Uuid::new_v4().to_string().into(),
+            client: IntentBrokeringServiceClient::new(endpoint.connect_lazy()),
+            cache: Mutex::new(Cache::new(config.cache_ttl)),
+        })
+    }
+
+    /// Forwards an intent of a request with the given metadata to the
+    /// upstream Intent Broker, returning its fulfillment.
+    pub async fn forward(
+        &self,
+        metadata: &MetadataMap,
+        namespace: &str,
+        intent: IntentMessage,
+    ) -> Result<Option<FulfillmentMessage>, Status> {
+        if matches!(intent.intent, Some(Intent::Subscribe(_))) {
+            return Err(Status::not_found(NO_PROVIDER_MESSAGE));
+        }
+
+        let via = via(metadata);
+        if via.iter().any(|id| *id == &*self.id) || via.len() >= MAX_HOPS {
+            return Err(Status::failed_precondition(format!(
+                "Intent for namespace '{namespace}' was forwarded in a loop or too often."
+            )));
+        }
+
+        let cacheable = matches!(intent.intent, Some(Intent::Discover(_) | Intent::Inspect(_)));
+        let authorization =
+            metadata.get(AUTHORIZATION_METADATA_KEY).map(|value| value.as_bytes().into());
+        let key = (namespace.into(), authorization, intent.encode_to_vec());
+        if let Some(result) = self.cache.lock().unwrap().get(&key, Instant::now()) {
+            return result.map_err(|(code, message)| Status::new(code, message));
+        }
+
+        let mut request =
+            Request::new(FulfillRequest { namespace: namespace.to_owned(), intent: Some(intent) });
+        let via = via.into_iter().chain([&*self.id]).collect::<Vec<_>>().join(",");
+        if let Ok(via) = MetadataValue::try_from(via) {
+            request.metadata_mut().insert(VIA_METADATA_KEY, via);
+        }
+        for key in FORWARDED_METADATA_KEYS {
+            if let Some(value) = metadata.get(key) {
+                request.metadata_mut().insert(key, value.clone());
+            }
+        }
+        if let Some(correlation_id) = correlation::current() {
+            correlation::insert(request.metadata_mut(), &correlation_id);
+        }
+
+        tracing::debug!("Forwarding intent for namespace '{namespace}' upstream.");
+
+        let result = self
+            .client
+            .clone()
+            .fulfill(request)
+            .await
+            .map(|response| response.into_inner().fulfillment);
+
+        if let Some(cached) = cacheable.then(|| to_cached(&result)).flatten() {
+            self.cache.lock().unwrap().insert(key, cached, Instant::now());
+        }
+
+        result
+    }
+}
+
+/// Returns the cache entry for a result of the upstream Intent Broker, if
+/// it is cached. Errors are only cached if the upstream broker lacks a
+/// provider, as other errors, e.g. of the provider, may be transient.
+fn to_cached(result: &Result<Option<FulfillmentMessage>, Status>) -> Option<CachedResult> {
+    match result {
+        Ok(fulfillment) => Some(Ok(fulfillment.clone())),
+        Err(status)
+            if status.code() == Code::NotFound && status.message() == NO_PROVIDER_MESSAGE =>
+        {
+            Some(Err((status.code(), status.message().to_owned())))
+        }
+        Err(_) => None,
+    }
+}
+
+/// Returns the IDs of the brokers a request was forwarded by.
+fn via(metadata: &MetadataMap) -> Vec<&str> {
+    metadata
+        .get(VIA_METADATA_KEY)
+        .and_then(|value| value.to_str().ok())
+        .map(|via| via.split(',').filter(|id| !id.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use intent_brokering_proto::{
+        common::{
+            intent::Intent, value::Value, FulfillmentEnum, FulfillmentMessage, IntentMessage,
+            InvokeFulfillment, InvokeIntent, ValueMessage,
+        },
+        runtime::intent_brokering_service_server::IntentBrokeringServiceServer,
+    };
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::{metadata::MetadataMap, transport::Server, Code, Status};
+    use url::Url;
+
+    use super::{to_cached, Cache, Config, Upstream, NO_PROVIDER_MESSAGE, VIA_METADATA_KEY};
+    use crate::idempotency::IDEMPOTENCY_KEY_METADATA_KEY;
+    use crate::intent_brokering_grpc::IntentBrokeringServer;
+    use crate::registry::Registry;
+    use crate::streaming::StreamingEss;
+    use crate::IntentBroker;
+
+    fn invoke() -> IntentMessage {
+        IntentMessage {
+            intent: Some(Intent::Invoke(InvokeIntent {
+                command: "test".to_owned(),
+                args: vec![ValueMessage { value: Some(Value::Int32(1)) }],
+            })),
+        }
+    }
+
+    /// Serves an Intent Broker, returning its URL and the metadata of the
+    /// requests it received.
+    pub(crate) async fn serve_broker() -> (Url, Arc<Mutex<Vec<MetadataMap>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap()); // DevSkim: ignore DS137138
+        let broker =
+            IntentBroker::new("https://localhost:4243".parse().unwrap(), StreamingEss::new()); // DevSkim: ignore DS162092
+        let server =
+            IntentBrokeringServer::new(Registry::new(broker.clone(), Default::default()), broker);
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let interceptor = {
+            let received = Arc::clone(&received);
+            move |request: tonic::Request<()>| {
+                received.lock().unwrap().push(request.metadata().clone());
+                Ok(request)
+            }
+        };
+        tokio::spawn(
+            Server::builder()
+                .add_service(IntentBrokeringServiceServer::with_interceptor(server, interceptor))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        (url.parse().unwrap(), received)
+    }
+
+    /// Serves an upstream Intent Broker, returning the client for it and the
+    /// metadata of the requests it received.
+    pub(crate) async fn serve_upstream() -> (Upstream, Arc<Mutex<Vec<MetadataMap>>>) {
+        let (url, received) = serve_broker().await;
+        (Upstream::new(Config::new(url)).unwrap(), received)
+    }
+
+    #[tokio::test]
+    async fn forward_returns_fulfillment_of_upstream() {
+        // arrange
+        let (subject, _) = serve_upstream().await;
+
+        // act
+        let result = subject.forward(&MetadataMap::new(), "sdv.vdt", invoke()).await;
+
+        // assert
+        assert_eq!(
+            Some(FulfillmentMessage {
+                fulfillment: Some(FulfillmentEnum::Invoke(InvokeFulfillment {
+                    r#return: Some(ValueMessage { value: Some(Value::Int32(10)) }),
+                })),
+            }),
+            result.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn forward_forwards_credentials_and_idempotency_key_of_caller() {
+        // arrange
+        let (subject, received) = serve_upstream().await;
+        let mut metadata = MetadataMap::new();
+        metadata.insert("authorization", "Bearer secret".parse().unwrap());
+        metadata.insert(IDEMPOTENCY_KEY_METADATA_KEY, "key".parse().unwrap());
+        metadata.insert("x-other", "other".parse().unwrap());
+
+        // act
+        subject.forward(&metadata, "sdv.vdt", invoke()).await.unwrap();
+
+        // assert
+        let received = received.lock().unwrap();
+        let [received] = &received[..] else { panic!() };
+        assert_eq!("Bearer secret", received.get("authorization").unwrap());
+        assert_eq!("key", received.get(IDEMPOTENCY_KEY_METADATA_KEY).unwrap());
+        assert!(received.get("x-other").is_none());
+    }
+
+    #[tokio::test]
+    async fn forward_fails_if_intent_passed_through_broker() {
+        // arrange
+        let subject = Upstream::new(Config::new("http://localhost:1".parse().unwrap())).unwrap(); // DevSkim: ignore DS137138
+        let mut metadata = MetadataMap::new();
+        metadata.insert(VIA_METADATA_KEY, format!("other,{}", subject.id).parse().unwrap());
+
+        // act
+        let result = subject.forward(&metadata, "sdv.vdt", invoke()).await;
+
+        // assert
+        assert_eq!(Code::FailedPrecondition, result.unwrap_err().code());
+    }
+
+    #[test]
+    fn cache_returns_results_until_expired() {
+        // arrange
+        let now = Instant::now();
+        let mut subject = Cache::new(Duration::from_secs(5));
+        let key = ("sdv.vdt".into(), None, vec![1]);
+        subject.insert(key.clone(), Err((Code::NotFound, "No provider found.".to_owned())), now);
+
+        // act
+        let cached = subject.get(&key, now + Duration::from_secs(4));
+        let expired = subject.get(&key, now + Duration::from_secs(5));
+
+        // assert
+        assert_eq!(Some(Err((Code::NotFound, "No provider found.".to_owned()))), cached);
+        assert_eq!(None, expired);
+    }
+
+    #[test]
+    fn to_cached_only_caches_lack_of_provider() {
+        // act
+        let no_provider = to_cached(&Err(Status::not_found(NO_PROVIDER_MESSAGE)));
+        let not_found = to_cached(&Err(Status::not_found("Key not found.")));
+        let unavailable = to_cached(&Err(Status::unavailable(NO_PROVIDER_MESSAGE)));
+
+        // assert
+        assert_eq!(Some(Err((Code::NotFound, NO_PROVIDER_MESSAGE.to_owned()))), no_provider);
+        assert_eq!(None, not_found);
+        assert_eq!(None, unavailable);
+    }
+}
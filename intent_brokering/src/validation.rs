@@ -0,0 +1,365 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Validates the fulfillments returned by providers in strict mode, which
+//! helps to debug providers while integrating them.
+//!
+//! A fulfillment is malformed if it does not match the kind of its intent, if
+//! values it holds are not set or violate their invariants, e.g. timestamps
+//! with out of range nanoseconds, or if the value read from a key does not
+//! have the type declared for the source of the same name. Malformed
+//! fulfillments are rejected with a diagnostic for each problem found.
+
+use intent_brokering_proto::common::{
+    FulfillmentEnum, FulfillmentMessage, IntentEnum, SourceType, ValueEnum, ValueMessage,
+};
+use url::Url;
+
+use crate::streaming::value_type;
+
+/// Returns the problems of a fulfillment of an intent, or nothing if it is
+/// well-formed. The declared types of the sources of a namespace are looked
+/// up by key with `source_type`.
+pub fn diagnose(
+    intent: &IntentEnum,
+    fulfillment: Option<&FulfillmentMessage>,
+    source_type: impl Fn(&str) -> Option<SourceType>,
+) -> Vec<String> {
+    let mut diagnostics = Diagnostics::default();
+
+    let Some(fulfillment) = fulfillment.and_then(|f| f.fulfillment.as_ref()) else {
+        diagnostics.report("fulfillment", "is not set");
+        return diagnostics.0;
+    };
+
+    match (intent, fulfillment) {
+        (IntentEnum::Read(read), FulfillmentEnum::Read(fulfillment)) => {
+            diagnostics.required_value("read.value", fulfillment.value.as_ref());
+            if let (Some(ValueMessage { value: Some(value) }), Some(source_type)) =
+                (&fulfillment.value, source_type(&read.key))
+            {
+                let actual = value_type(value);
+                if actual != "null" && actual != source_type.value_type {
+                    diagnostics.report(
+                        "read.value",
+                        format!(
+                            "is of type '{actual}' instead of '{}' declared for '{}'",
+                            source_type.value_type, read.key
+                        ),
+                    );
+                }
+            }
+        }
+        (IntentEnum::WriteBatch(batch), FulfillmentEnum::WriteBatch(fulfillment)) => {
+            let mut written = batch.writes.iter().map(|w| w.key.as_str()).collect::<Vec<_>>();
+            for (i, entry) in fulfillment.entries.iter().enumerate() {
+                let path = format!("write_batch.entries[{i}]");
+                if entry.result.is_none() {
+                    diagnostics.report(&format!("{path}.result"), "is not set");
+                }
+                match written.iter().position(|key| *key == entry.key) {
+                    Some(position) => {
+                        written.remove(position);
+                    }
+                    None => diagnostics.report(
+                        &format!("{path}.key"),
+                        format!("'{}' was not written or is reported twice", entry.key),
+                    ),
+                }
+            }
+            for key in written {
+                diagnostics.report("write_batch.entries", format!("'{key}' is missing"));
+            }
+        }
+        (IntentEnum::Invoke(_), FulfillmentEnum::Invoke(fulfillment)) => {
+            if let Some(value) = &fulfillment.r#return {
+                diagnostics.value("invoke.return", value);
+            }
+        }
+        (IntentEnum::Discover(_), FulfillmentEnum::Discover(fulfillment)) => {
+            for (i, service) in fulfillment.services.iter().enumerate() {
+                let path = format!("discover.services[{i}]");
+                if let Err(e) = service.url.parse::<Url>() {
+                    diagnostics.report(&format!("{path}.url"), format!("is not valid: {e}"));
+                }
+                if service.schema_kind.is_empty() {
+                    diagnostics.report(&format!("{path}.schema_kind"), "is empty");
+                }
+                diagnostics.values(&format!("{path}.metadata"), &service.metadata);
+            }
+        }
+        (IntentEnum::Inspect(_), FulfillmentEnum::Inspect(fulfillment)) => {
+            for (i, entry) in fulfillment.entries.iter().enumerate() {
+                let path = format!("inspect.entries[{i}]");
+                if entry.path.is_empty() {
+                    diagnostics.report(&format!("{path}.path"), "is empty");
+                }
+                diagnostics.values(&format!("{path}.items"), &entry.items);
+            }
+        }
+        (IntentEnum::Write(_), FulfillmentEnum::Write(_))
+        | (IntentEnum::Subscribe(_), FulfillmentEnum::Subscribe(_))
+        | (IntentEnum::Delete(_), FulfillmentEnum::Delete(_)) => {}
+        (intent, fulfillment) => diagnostics.report(
+            "fulfillment",
+            format!(
+                "'{}' does not fulfill a '{}' intent",
+                fulfillment_name(fulfillment),
+                intent_name(intent)
+            ),
+        ),
+    }
+
+    diagnostics.0
+}
+
+#[derive(Default)]
+struct Diagnostics(Vec<String>);
+
+impl Diagnostics {
+    fn report(&mut self, path: &str, problem: impl AsRef<str>) {
+        self.0.push(format!("{path} {}", problem.as_ref()));
+    }
+
+    fn required_value(&mut self, path: &str, value: Option<&ValueMessage>) {
+        match value {
+            Some(value) => self.value(path, value),
+            None => self.report(path, "is not set"),
+        }
+    }
+
+    fn values<'a>(
+        &mut self,
+        path: &str,
+        values: impl IntoIterator<Item = (&'a String, &'a ValueMessage)>,
+    ) {
+        let mut values = values.into_iter().collect::<Vec<_>>();
+        values.sort_unstable_by_key(|(key, _)| *key);
+        for (key, value) in values {
+            self.value(&format!("{path}.{key}"), value);
+        }
+    }
+
+    fn value(&mut self, path: &str, value: &ValueMessage) {
+        match &value.value {
+            None => self.report(path, "is not set"),
+            Some(ValueEnum::Any(any)) if any.type_url.is_empty() => {
+                self.report(path, "has an empty type URL")
+            }
+            Some(ValueEnum::Timestamp(timestamp))
+                if !(0..1_000_000_000).contains(&timestamp.nanos) =>
+            {
+                self.report(path, format!("has out of range nanoseconds {}", timestamp.nanos))
+            }
+            Some(ValueEnum::List(list)) => {
+                for (i, value) in list.value.iter().enumerate() {
+                    self.value(&format!("{path}[{i}]"), value);
+                }
+            }
+            Some(ValueEnum::Map(map)) => self.values(path, &map.map),
+            Some(_) => {}
+        }
+    }
+}
+
+fn intent_name(intent: &IntentEnum) -> &'static str {
+    match intent {
+        IntentEnum::Discover(_) => "discover",
+        IntentEnum::Invoke(_) => "invoke",
+        IntentEnum::Read(_) => "read",
+        IntentEnum::Write(_) => "write",
+        IntentEnum::Inspect(_) => "inspect",
+        IntentEnum::Subscribe(_) => "subscribe",
+        IntentEnum::WriteBatch(_) => "write_batch",
+        IntentEnum::Delete(_) => "delete",
+    }
+}
+
+fn fulfillment_name(fulfillment: &FulfillmentEnum) -> &'static str {
+    match fulfillment {
+        FulfillmentEnum::Discover(_) => "discover",
+        FulfillmentEnum::Inspect(_) => "inspect",
+        FulfillmentEnum::Read(_) => "read",
+        FulfillmentEnum::Write(_) => "write",
+        FulfillmentEnum::Invoke(_) => "invoke",
+        FulfillmentEnum::Subscribe(_) => "subscribe",
+        FulfillmentEnum::WriteBatch(_) => "write_batch",
+        FulfillmentEnum::Delete(_) => "delete",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use intent_brokering_proto::common::{
+        discover_fulfillment::Service, write_batch_fulfillment::entry::Result as EntryResult,
+        write_batch_fulfillment::Entry, DiscoverFulfillment, DiscoverIntent, FulfillmentEnum,
+        FulfillmentMessage, IntentEnum, InvokeFulfillment, InvokeIntent, List, ReadFulfillment,
+        ReadIntent, SourceType, ValueEnum, ValueMessage, WriteBatchFulfillment, WriteBatchIntent,
+        WriteFulfillment, WriteIntent,
+    };
+
+    use super::diagnose;
+
+    fn read(key: &str) -> IntentEnum {
+        IntentEnum::Read(ReadIntent { key: key.to_owned() })
+    }
+
+    fn read_fulfillment(value: ValueEnum) -> FulfillmentMessage {
+        FulfillmentMessage {
+            fulfillment: Some(FulfillmentEnum::Read(ReadFulfillment {
+                value: Some(ValueMessage { value: Some(value) }),
+                ..Default::default()
+            })),
+        }
+    }
+
+    fn int32() -> Option<SourceType> {
+        Some(SourceType { value_type: "int32".to_owned(), schema: String::new() })
+    }
+
+    #[test]
+    fn diagnose_accepts_well_formed_fulfillments() {
+        // act
+        let result =
+            diagnose(&read("Vehicle.Speed"), Some(&read_fulfillment(ValueEnum::Int32(42))), |_| {
+                int32()
+            });
+
+        // assert
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn diagnose_reports_missing_and_mismatching_fulfillments() {
+        // arrange
+        let invoke = FulfillmentMessage {
+            fulfillment: Some(FulfillmentEnum::Invoke(InvokeFulfillment { r#return: None })),
+        };
+
+        // act
+        let missing = diagnose(&read("Vehicle.Speed"), None, |_| None);
+        let mismatching = diagnose(&read("Vehicle.Speed"), Some(&invoke), |_| None);
+
+        // assert
+        assert_eq!(vec!["fulfillment is not set".to_owned()], missing);
+        assert_eq!(
+            vec!["fulfillment 'invoke' does not fulfill a 'read' intent".to_owned()],
+            mismatching
+        );
+    }
+
+    #[test]
+    fn diagnose_reports_invalid_nested_values() {
+        // arrange
+        let intent = IntentEnum::Invoke(InvokeIntent { command: "test".to_owned(), args: vec![] });
+        let fulfillment = FulfillmentMessage {
+            fulfillment: Some(FulfillmentEnum::Invoke(InvokeFulfillment {
+                r#return: Some(ValueMessage {
+                    value: Some(ValueEnum::List(List {
+                        value: vec![
+                            ValueMessage { value: Some(ValueEnum::Bool(true)) },
+                            ValueMessage { value: None },
+                            ValueMessage {
+                                value: Some(ValueEnum::Timestamp(prost_types::Timestamp {
+                                    seconds: 0,
+                                    nanos: 1_000_000_000,
+                                })),
+                            },
+                        ],
+                    })),
+                }),
+            })),
+        };
+
+        // act
+        let result = diagnose(&intent, Some(&fulfillment), |_| None);
+
+        // assert
+        assert_eq!(
+            vec![
+                "invoke.return[1] is not set".to_owned(),
+                "invoke.return[2] has out of range nanoseconds 1000000000".to_owned()
+            ],
+            result
+        );
+    }
+
+    #[test]
+    fn diagnose_reports_values_not_of_declared_source_type() {
+        // act
+        let mismatching = diagnose(
+            &read("Vehicle.Speed"),
+            Some(&read_fulfillment(ValueEnum::Bool(true))),
+            |_| int32(),
+        );
+        let null =
+            diagnose(&read("Vehicle.Speed"), Some(&read_fulfillment(ValueEnum::Null(0))), |_| {
+                int32()
+            });
+
+        // assert
+        assert_eq!(
+            vec!["read.value is of type 'bool' instead of 'int32' declared for 'Vehicle.Speed'"
+                .to_owned()],
+            mismatching
+        );
+        assert!(null.is_empty());
+    }
+
+    #[test]
+    fn diagnose_reports_write_batch_entries_not_matching_writes() {
+        // arrange
+        let write = |key: &str| WriteIntent { key: key.to_owned(), ..Default::default() };
+        let intent =
+            IntentEnum::WriteBatch(WriteBatchIntent { writes: vec![write("a"), write("b")] });
+        let fulfillment = FulfillmentMessage {
+            fulfillment: Some(FulfillmentEnum::WriteBatch(WriteBatchFulfillment {
+                entries: vec![
+                    Entry {
+                        key: "a".to_owned(),
+                        result: Some(EntryResult::Fulfillment(WriteFulfillment::default())),
+                    },
+                    Entry { key: "c".to_owned(), result: None },
+                ],
+            })),
+        };
+
+        // act
+        let result = diagnose(&intent, Some(&fulfillment), |_| None);
+
+        // assert
+        assert_eq!(
+            vec![
+                "write_batch.entries[1].result is not set".to_owned(),
+                "write_batch.entries[1].key 'c' was not written or is reported twice".to_owned(),
+                "write_batch.entries 'b' is missing".to_owned(),
+            ],
+            result
+        );
+    }
+
+    #[test]
+    fn diagnose_reports_invalid_services() {
+        // arrange
+        let fulfillment = FulfillmentMessage {
+            fulfillment: Some(FulfillmentEnum::Discover(DiscoverFulfillment {
+                services: vec![Service {
+                    url: "not a url".to_owned(),
+                    schema_kind: String::new(),
+                    schema_reference: String::new(),
+                    metadata: Default::default(),
+                }],
+            })),
+        };
+
+        // act
+        let result =
+            diagnose(&IntentEnum::Discover(DiscoverIntent {}), Some(&fulfillment), |_| None);
+
+        // assert
+        assert_eq!(2, result.len());
+        assert!(result[0].starts_with("discover.services[0].url is not valid"));
+        assert_eq!("discover.services[0].schema_kind is empty", result[1]);
+    }
+}
@@ -0,0 +1,328 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Notifies HTTP endpoints of changes to the registry, so that orchestrators
+//! or monitoring backends can react to providers coming and going without
+//! speaking gRPC.
+//!
+//! Each change set is posted as JSON to every endpoint, e.g.:
+//!
+//! ```json
+//! {
+//!   "changes": [
+//!     {
+//!       "kind": "add",
+//!       "namespace": "sdv.vdt",
+//!       "intent": "read",
+//!       "services": [
+//!         { "name": "vdt", "version": "0.0.1", "url": "http://localhost:50051/", "locality": "local", "transactional": false }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Removed intents are reported without services. If a secret is configured,
+//! each notification is signed with HMAC-SHA256 over its body, and the
+//! signature is sent as `sha256={hex digest}` in the `x-chariott-signature`
+//! header, such that endpoints can reject notifications which were not sent
+//! by the Intent Broker. Notifications are delivered in order per endpoint
+//! and retried with an exponential backoff.
+//! Notifications which cannot be delivered, or which do not fit into the
+//! queue of an endpoint, are dropped with a warning.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac as _};
+use hyper::{client::HttpConnector, header::CONTENT_TYPE, Body, Client, Request};
+use serde::Serialize;
+use sha2::Sha256;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use url::Url;
+
+use crate::registry::{Change, ExecutionLocality, IntentKind, Observer, ServiceConfiguration};
+
+const QUEUE_SIZE: usize = 100;
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const SIGNATURE_HEADER_NAME: &str = "x-chariott-signature";
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ChangeKind {
+    Add,
+    Modify,
+    Remove,
+}
+
+#[derive(Serialize)]
+struct ServiceNotification<'a> {
+    name: Box<str>,
+    version: Box<str>,
+    url: &'a Url,
+    locality: &'a ExecutionLocality,
+    transactional: bool,
+}
+
+impl<'a> From<&'a ServiceConfiguration> for ServiceNotification<'a> {
+    fn from(service: &'a ServiceConfiguration) -> Self {
+        Self {
+            name: service.id().name(),
+            version: service.id().version(),
+            url: service.url(),
+            locality: service.locality(),
+            transactional: service.transactional(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ChangeNotification<'a> {
+    kind: ChangeKind,
+    namespace: &'a str,
+    intent: IntentKind,
+    services: Vec<ServiceNotification<'a>>,
+}
+
+#[derive(Serialize)]
+struct Notification<'a> {
+    changes: Vec<ChangeNotification<'a>>,
+}
+
+/// Serializes a change set, ordering the services of each change by name and
+/// URL.
+fn serialize<'a>(changes: impl IntoIterator<Item = Change<'a>>) -> String {
+    let changes = changes
+        .into_iter()
+        .map(|change| {
+            let (kind, intent, services) = match change {
+                Change::Add(intent, services) => (ChangeKind::Add, intent, Some(services)),
+                Change::Modify(intent, services) => (ChangeKind::Modify, intent, Some(services)),
+                Change::Remove(intent) => (ChangeKind::Remove, intent, None),
+            };
+
+            let mut services: Vec<ServiceNotification> =
+                services.into_iter().flatten().map(ServiceNotification::from).collect();
+            services.sort_by(|a, b| (&a.name, a.url).cmp(&(&b.name, b.url)));
+
+            ChangeNotification {
+                kind,
+                namespace: intent.namespace(),
+                intent: intent.intent(),
+                services,
+            }
+        })
+        .collect();
+
+    // Serializing strings, URLs and unit variants cannot fail.
+    serde_json::to_string(&Notification { changes }).unwrap()
+}
+
+/// Signs a notification with HMAC-SHA256, returning the value of its
+/// signature header.
+fn sign(secret: &[u8], notification: &str) -> String {
+    // HMAC accepts keys of any length.
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+    mac.update(notification.as_bytes());
+    let digest = mac.finalize().into_bytes();
+    format!("sha256={}", digest.iter().map(|byte| format!("{byte:02x}")).collect::<String>())
+}
+
+/// Posts registry changes to the configured endpoints. Cloning is cheap and
+/// refers to the same delivery queues.
+#[derive(Clone, Default)]
+pub struct Webhooks {
+    endpoints: Vec<(Arc<Url>, mpsc::Sender<Arc<str>>)>,
+}
+
+impl Webhooks {
+    /// Starts delivering notifications to the given endpoints, each of which
+    /// is served by a task of its own, signing them with the secret if any.
+    /// Only `http` endpoints are supported.
+    pub fn new(urls: impl IntoIterator<Item = Url>, secret: Option<Arc<[u8]>>) -> Self {
+        let client = Client::new();
+
+        let endpoints = urls
+            .into_iter()
+            .map(|url| {
+                let url = Arc::new(url);
+                let (sender, receiver) = mpsc::channel(QUEUE_SIZE);
+                tokio::spawn(deliver(client.clone(), Arc::clone(&url), secret.clone(), receiver));
+                (url, sender)
+            })
+            .collect();
+
+        Self { endpoints }
+    }
+}
+
+impl Observer for Webhooks {
+    fn on_change<'a>(&self, changes: impl Iterator<Item = Change<'a>> + Clone) {
+        if self.endpoints.is_empty() {
+            return;
+        }
+
+        let notification: Arc<str> = serialize(changes).into();
+
+        for (url, sender) in self.endpoints.iter() {
+            match sender.try_send(Arc::clone(&notification)) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => tracing::warn!(
+                    "Dropping registry change notification for '{url}', which has too many pending notifications."
+                ),
+                Err(TrySendError::Closed(_)) => {
+                    tracing::warn!("Dropping registry change notification for '{url}'.")
+                }
+            }
+        }
+    }
+}
+
+async fn deliver(
+    client: Client<HttpConnector>,
+    url: Arc<Url>,
+    secret: Option<Arc<[u8]>>,
+    mut notifications: mpsc::Receiver<Arc<str>>,
+) {
+    while let Some(notification) = notifications.recv().await {
+        let signature = secret.as_deref().map(|secret| sign(secret, &notification));
+        let mut backoff = INITIAL_BACKOFF;
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            match post(&client, &url, &notification, signature.as_deref()).await {
+                Ok(()) => break,
+                Err(e) if attempt == MAX_ATTEMPTS => {
+                    tracing::warn!("Dropping registry change notification for '{url}': {e}");
+                }
+                Err(e) => {
+                    tracing::debug!(
+                        "Posting registry change notification to '{url}' failed, retrying: {e}"
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+            }
+        }
+    }
+}
+
+async fn post(
+    client: &Client<HttpConnector>,
+    url: &Url,
+    notification: &str,
+    signature: Option<&str>,
+) -> Result<(), String> {
+    let mut request = Request::post(url.as_str()).header(CONTENT_TYPE, "application/json");
+    if let Some(signature) = signature {
+        request = request.header(SIGNATURE_HEADER_NAME, signature);
+    }
+
+    let request = request.body(Body::from(notification.to_owned())).map_err(|e| e.to_string())?;
+
+    let response = tokio::time::timeout(REQUEST_TIMEOUT, client.request(request))
+        .await
+        .map_err(|_| "Request timed out.".to_owned())?
+        .map_err(|e| e.to_string())?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("Endpoint responded with status {}.", response.status()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::registry::tests::{IntentConfigurationBuilder, ServiceConfigurationBuilder};
+
+    #[test]
+    fn serialize_reports_changes_with_services() {
+        // arrange
+        let added = IntentConfigurationBuilder::new().build();
+        let removed = IntentConfigurationBuilder::with_nonce("1").build();
+        let service = ServiceConfigurationBuilder::new().transactional(true).build();
+        let services = HashSet::from([service.clone()]);
+
+        // act
+        let result = serialize([Change::Add(&added, &services), Change::Remove(&removed)]);
+
+        // assert
+        assert_eq!(
+            json!({
+                "changes": [
+                    {
+                        "kind": "add",
+                        "namespace": added.namespace(),
+                        "intent": added.intent(),
+                        "services": [{
+                            "name": service.id().name(),
+                            "version": service.id().version(),
+                            "url": service.url(),
+                            "locality": service.locality(),
+                            "transactional": true,
+                        }],
+                    },
+                    {
+                        "kind": "remove",
+                        "namespace": removed.namespace(),
+                        "intent": removed.intent(),
+                        "services": [],
+                    },
+                ]
+            }),
+            serde_json::from_str::<serde_json::Value>(&result).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn on_change_posts_notification_to_endpoints() {
+        // arrange
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap()).parse().unwrap(); // DevSkim: ignore DS137138
+        let subject = Webhooks::new([url], Some(b"secret".as_slice().into()));
+        let intent = IntentConfigurationBuilder::new().build();
+
+        // act
+        subject.on_change([Change::Remove(&intent)].into_iter());
+
+        // assert
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        while !request.ends_with(b"}") {
+            let read = stream.read(&mut buffer).await.unwrap();
+            assert!(read > 0);
+            request.extend_from_slice(&buffer[..read]);
+        }
+        stream.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").await.unwrap();
+
+        let request = String::from_utf8(request).unwrap();
+        let notification = serialize([Change::Remove(&intent)]);
+        assert!(request.starts_with("POST /hook HTTP/1.1"));
+        assert!(request
+            .contains(&format!("{SIGNATURE_HEADER_NAME}: {}", sign(b"secret", &notification))));
+        assert!(request.ends_with(&notification));
+    }
+
+    #[test]
+    fn sign_computes_hmac_sha256() {
+        // act
+        let result = sign(b"Jefe", "what do ya want for nothing?");
+
+        // assert
+        assert_eq!(
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            result
+        );
+    }
+}
@@ -0,0 +1,289 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Mirrors the writes fulfilled in namespaces as change events, such that
+//! consumers, e.g. UIs and loggers, can observe changes of state even if the
+//! provider does not publish them.
+//!
+//! Before forwarding a `Write` or `WriteBatch` intent of a mirrored namespace,
+//! the Intent Broker reads the current values of the written keys with the
+//! `Read` intent of the namespace, if it is registered. Once the provider
+//! fulfilled the write, an event is published for each written key under the
+//! source `writes/{namespace}`, which consumers subscribe to through the
+//! `system.registry` namespace. The value of an event is a map holding the
+//! `key`, the `old` and `new` value and the `identity` of the writer. The old
+//! value is null if it could not be read.
+
+use std::collections::HashMap;
+
+use intent_brokering_common::streaming_ess::Timestamped;
+use intent_brokering_proto::common::{
+    write_batch_fulfillment::entry::Result as EntryResult, FulfillmentEnum, FulfillmentMessage,
+    IntentEnum, IntentMessage, Map, ReadIntent, ValueEnum, ValueMessage,
+};
+
+use crate::connection_provider::ConnectionProvider;
+use crate::execution::RuntimeBinding;
+use crate::identity::Caller;
+use crate::namespace;
+use crate::streaming::StreamingEss;
+
+const KEY_KEY: &str = "key";
+const OLD_KEY: &str = "old";
+const NEW_KEY: &str = "new";
+const IDENTITY_KEY: &str = "identity";
+
+/// The source under which the writes of a namespace are published.
+pub fn write_source(namespace: &str) -> String {
+    format!("writes/{namespace}")
+}
+
+/// A write of a key which is mirrored once fulfilled.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Write {
+    key: String,
+    old: ValueEnum,
+    new: ValueEnum,
+}
+
+/// Publishes the writes of the mirrored namespaces.
+pub struct WriteEvents {
+    ess: StreamingEss,
+    namespaces: Vec<Box<str>>,
+}
+
+impl WriteEvents {
+    pub fn new(ess: StreamingEss, namespaces: impl IntoIterator<Item = Box<str>>) -> Self {
+        Self { ess, namespaces: namespaces.into_iter().collect() }
+    }
+
+    /// Returns whether the writes of a namespace are mirrored. A namespace
+    /// also covers its sub-namespaces, e.g. `sdv` covers `sdv.vdt`.
+    pub fn covers(&self, namespace: &str) -> bool {
+        self.namespaces.iter().any(|mirrored| namespace::covers(mirrored, namespace))
+    }
+
+    /// Returns the writes of an intent together with the current values of
+    /// their keys, which are read with the binding of the `Read` intent of
+    /// the namespace.
+    pub(crate) async fn prepare<T>(
+        intent: &IntentMessage,
+        read: Option<RuntimeBinding<T>>,
+    ) -> Vec<Write>
+    where
+        T::ConnectedProvider: Send,
+        T: ConnectionProvider + Clone + Send + 'static,
+    {
+        let writes = match &intent.intent {
+            Some(IntentEnum::Write(write)) => vec![write],
+            Some(IntentEnum::WriteBatch(batch)) => batch.writes.iter().collect(),
+            _ => return vec![],
+        };
+
+        let mut prepared = Vec::with_capacity(writes.len());
+        for write in writes {
+            let old = match &read {
+                Some(read) => read_value(read.clone(), &write.key).await,
+                None => ValueEnum::Null(0),
+            };
+
+            prepared.push(Write {
+                key: write.key.clone(),
+                old,
+                new: write.value.clone().and_then(|v| v.value).unwrap_or(ValueEnum::Null(0)),
+            });
+        }
+
+        prepared
+    }
+
+    /// Publishes the writes which the provider fulfilled. Keys of a batch
+    /// which failed to be written are not published.
+    pub(crate) fn publish(
+        &self,
+        namespace: &str,
+        caller: &Caller,
+        writes: Vec<Write>,
+        fulfillment: Option<&FulfillmentMessage>,
+    ) {
+        let failed: Vec<_> = match fulfillment.and_then(|f| f.fulfillment.as_ref()) {
+            Some(FulfillmentEnum::WriteBatch(batch)) => batch
+                .entries
+                .iter()
+                .filter(|entry| matches!(entry.result, Some(EntryResult::Error(_)) | None))
+                .map(|entry| entry.key.as_str())
+                .collect(),
+            _ => vec![],
+        };
+
+        let source = write_source(namespace);
+        for write in writes.into_iter().filter(|write| !failed.contains(&write.key.as_str())) {
+            let value = ValueEnum::Map(Map {
+                map: HashMap::from([
+                    (KEY_KEY.to_owned(), message(ValueEnum::String(write.key))),
+                    (OLD_KEY.to_owned(), message(write.old)),
+                    (NEW_KEY.to_owned(), message(write.new)),
+                    (IDENTITY_KEY.to_owned(), message(ValueEnum::String(caller.to_string()))),
+                ]),
+            });
+
+            self.ess.publish(source.as_str(), Timestamped::now(value));
+        }
+    }
+}
+
+fn message(value: ValueEnum) -> ValueMessage {
+    ValueMessage { value: Some(value) }
+}
+
+async fn read_value<T>(read: RuntimeBinding<T>, key: &str) -> ValueEnum
+where
+    T::ConnectedProvider: Send,
+    T: ConnectionProvider + Clone + Send + 'static,
+{
+    let intent =
+        IntentMessage { intent: Some(IntentEnum::Read(ReadIntent { key: key.to_owned() })) };
+
+    match read.execute(intent).await {
+        Ok(response) => match response.fulfillment.and_then(|f| f.fulfillment) {
+            Some(FulfillmentEnum::Read(read)) => {
+                read.value.and_then(|v| v.value).unwrap_or(ValueEnum::Null(0))
+            }
+            _ => ValueEnum::Null(0),
+        },
+        Err(e) => {
+            tracing::debug!("Reading the value of '{key}' before writing it failed: {e}");
+            ValueEnum::Null(0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use intent_brokering_proto::{
+        common::{
+            write_batch_fulfillment::{entry::Result as EntryResult, Entry},
+            FulfillmentEnum, FulfillmentMessage, IntentEnum, IntentMessage, SubscribeIntent,
+            ValueEnum, WriteBatchFulfillment, WriteBatchIntent, WriteFulfillment, WriteIntent,
+        },
+        streaming::{channel_service_server::ChannelService, OpenRequest},
+    };
+    use tonic::Request;
+
+    use super::{message, write_source, Write, WriteEvents};
+    use crate::connection_provider::GrpcProvider;
+    use crate::execution::{tests::StreamExt as _, RuntimeBinding};
+    use crate::identity::Caller;
+    use crate::streaming::StreamingEss;
+
+    fn write(key: &str, value: i32) -> WriteIntent {
+        WriteIntent {
+            key: key.to_owned(),
+            value: Some(message(ValueEnum::Int32(value))),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn covers_namespace_and_sub_namespaces() {
+        // arrange
+        let subject = WriteEvents::new(StreamingEss::new(), ["sdv.kvs".into()]);
+
+        // act + assert
+        assert!(subject.covers("sdv.kvs"));
+        assert!(subject.covers("sdv.kvs.cabin"));
+        assert!(!subject.covers("sdv.kvsx"));
+        assert!(!subject.covers("sdv"));
+    }
+
+    #[tokio::test]
+    async fn prepare_returns_writes_of_batch_with_null_if_unreadable() {
+        // arrange
+        let intent = IntentMessage {
+            intent: Some(IntentEnum::WriteBatch(WriteBatchIntent {
+                writes: vec![write("a", 1), write("b", 2)],
+            })),
+        };
+
+        // act
+        let result = WriteEvents::prepare::<GrpcProvider>(&intent, None).await;
+
+        // assert
+        assert_eq!(
+            vec![
+                Write { key: "a".to_owned(), old: ValueEnum::Null(0), new: ValueEnum::Int32(1) },
+                Write { key: "b".to_owned(), old: ValueEnum::Null(0), new: ValueEnum::Int32(2) },
+            ],
+            result
+        );
+    }
+
+    #[tokio::test]
+    async fn prepare_ignores_other_intents() {
+        // arrange
+        let intent = IntentMessage { intent: Some(IntentEnum::Read(Default::default())) };
+        let read = RuntimeBinding::<GrpcProvider>::SystemSubscribe(StreamingEss::new());
+
+        // act
+        let result = WriteEvents::prepare(&intent, Some(read)).await;
+
+        // assert
+        assert!(result.is_empty());
+    }
+
+    #[tokio::test]
+    async fn publish_skips_keys_which_failed_to_be_written() {
+        // arrange
+        let ess = StreamingEss::new();
+        let response = ess.open(Request::new(OpenRequest::default())).await.unwrap();
+        let channel_id =
+            response.metadata().get("x-chariott-channel-id").unwrap().to_str().unwrap().into();
+        let stream = response.into_inner();
+        RuntimeBinding::<GrpcProvider>::SystemSubscribe(ess.clone())
+            .execute(IntentMessage {
+                intent: Some(IntentEnum::Subscribe(SubscribeIntent {
+                    channel_id,
+                    sources: vec![write_source("sdv.kvs")],
+                    ..Default::default()
+                })),
+            })
+            .await
+            .unwrap();
+        let subject = WriteEvents::new(ess, ["sdv.kvs".into()]);
+        let writes = vec![
+            Write { key: "a".to_owned(), old: ValueEnum::Null(0), new: ValueEnum::Int32(1) },
+            Write { key: "b".to_owned(), old: ValueEnum::Int32(3), new: ValueEnum::Int32(2) },
+        ];
+        let fulfillment = FulfillmentMessage {
+            fulfillment: Some(FulfillmentEnum::WriteBatch(WriteBatchFulfillment {
+                entries: vec![
+                    Entry {
+                        key: "a".to_owned(),
+                        result: Some(EntryResult::Error("rejected".to_owned())),
+                    },
+                    Entry {
+                        key: "b".to_owned(),
+                        result: Some(EntryResult::Fulfillment(WriteFulfillment::default())),
+                    },
+                ],
+            })),
+        };
+
+        // act
+        subject.publish("sdv.kvs", &Caller::Named("hmi".into()), writes, Some(&fulfillment));
+
+        // assert
+        let mut events = stream.collect_when_stable().await;
+        assert_eq!(1, events.len());
+        let event = events.remove(0).unwrap();
+        let Some(ValueEnum::Map(map)) = event.value.and_then(|v| v.value) else {
+            panic!("Event is not a map.");
+        };
+        let value = |key: &str| map.map[key].value.clone().unwrap();
+        assert_eq!(ValueEnum::String("b".to_owned()), value("key"));
+        assert_eq!(ValueEnum::Int32(3), value("old"));
+        assert_eq!(ValueEnum::Int32(2), value("new"));
+        assert_eq!(ValueEnum::String("hmi".to_owned()), value("identity"));
+    }
+}
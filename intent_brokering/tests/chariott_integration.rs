@@ -3,7 +3,7 @@
 // SPDX-License-Identifier: MIT
 
 use std::sync::atomic::{AtomicU16, Ordering};
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime};
 
 use async_trait::async_trait;
 use common::get_uuid;
@@ -20,15 +20,20 @@ use intent_brokering::{
 };
 use intent_brokering_common::error::{Error, ResultExt as _};
 use intent_brokering_common::shutdown::RouterExt as _;
+use intent_brokering_common::streaming_ess::Timestamped;
 use intent_brokering_proto::{
-    common::{IntentEnum, IntentMessage},
+    common::{IntentEnum, IntentMessage, ValueEnum},
     runtime::{
         intent_brokering_service_server::IntentBrokeringService, FulfillRequest, FulfillResponse,
     },
-    streaming::channel_service_server::ChannelServiceServer,
+    streaming::{
+        channel_service_server::{ChannelService, ChannelServiceServer},
+        CloseRequest, OpenRequest,
+    },
 };
 use provider::Provider;
 use tokio::task::spawn;
+use tokio_stream::StreamExt as _;
 use tokio_util::sync::CancellationToken;
 use tonic::transport::Server;
 use tonic::{Request, Response};
@@ -140,8 +145,77 @@ async fn when_cancelled_shuts_down_provider() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn when_subscribing_on_broker_channel_relays_provider_events() -> anyhow::Result<()> {
+    // arrange
+    const SOURCE: &str = "foo";
+    const VALUE: i32 = 42;
+
+    let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    let provider_ess = StreamingEss::new();
+    let mut subject = setup_multiple([ProviderSetup::local(
+        Provider::new().with_streaming(provider_ess.clone()),
+    )
+    .intent(IntentKind::Subscribe)])
+    .await;
+
+    let response = subject.streaming_ess.open(Request::new(OpenRequest::default())).await?;
+    let channel_id = response.metadata().get("x-chariott-channel-id").unwrap().to_str()?.to_owned();
+    let mut stream = Box::pin(response.into_inner().timeout(Duration::from_secs(5)));
+
+    // act
+    subject.subscribe(subject.namespace.clone(), channel_id, vec![SOURCE.into()]).await?;
+    provider_ess.publish(SOURCE, Timestamped::new(ValueEnum::Int32(VALUE), timestamp));
+
+    // assert
+    let event = stream.next().await.unwrap()??;
+    assert_eq!(format!("{}/{SOURCE}", subject.namespace), event.source);
+    assert_eq!(Some(ValueEnum::Int32(VALUE)), event.value.and_then(|v| v.value));
+    assert_eq!(Some(timestamp.into()), event.timestamp);
+    assert_eq!(None, event.normalized_timestamp);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn when_closing_broker_channel_closes_upstream_channel() -> anyhow::Result<()> {
+    // arrange
+    const SOURCE: &str = "foo";
+
+    let provider_ess = StreamingEss::new();
+    let mut subject = setup_multiple([ProviderSetup::local(
+        Provider::new().with_streaming(provider_ess.clone()),
+    )
+    .intent(IntentKind::Subscribe)])
+    .await;
+
+    let response = subject.streaming_ess.open(Request::new(OpenRequest::default())).await?;
+    let channel_id = response.metadata().get("x-chariott-channel-id").unwrap().to_str()?.to_owned();
+    let stream = response.into_inner();
+
+    subject.subscribe(subject.namespace.clone(), channel_id.clone(), vec![SOURCE.into()]).await?;
+
+    // act
+    subject.streaming_ess.close(Request::new(CloseRequest { channel_id })).await?;
+    let events: Vec<_> =
+        tokio::time::timeout(Duration::from_secs(5), stream.collect::<Vec<_>>()).await?;
+    provider_ess.publish(SOURCE, Timestamped::now(ValueEnum::Int32(42)));
+
+    // assert
+    assert!(events.last().unwrap().as_ref().unwrap().end_of_stream);
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !provider_ess.statistics().clients.is_empty() {
+        assert!(Instant::now() < deadline, "Upstream channel was not closed.");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    Ok(())
+}
+
 struct Subject {
     namespace: String,
+    streaming_ess: StreamingEss,
     subject: IntentBrokeringServer<IntentBroker>,
 }
 
@@ -150,16 +224,27 @@ struct ProviderSetup {
     name: Box<str>,
     port: u16,
     locality: ExecutionLocality,
+    intent: IntentKind,
 }
 
 impl ProviderSetup {
     pub fn local(provider: Provider) -> Self {
-        Self { provider, name: get_uuid(), port: get_port(), locality: ExecutionLocality::Local }
+        Self {
+            provider,
+            name: get_uuid(),
+            port: get_port(),
+            locality: ExecutionLocality::Local,
+            intent: IntentKind::Invoke,
+        }
     }
 
     pub fn cloud(provider: Provider) -> Self {
         Self { locality: ExecutionLocality::Cloud, ..Self::local(provider) }
     }
+
+    pub fn intent(self, intent: IntentKind) -> Self {
+        Self { intent, ..self }
+    }
 }
 
 async fn setup(provider: Provider) -> Subject {
@@ -168,22 +253,24 @@ async fn setup(provider: Provider) -> Subject {
 
 async fn setup_multiple(providers: impl IntoIterator<Item = ProviderSetup>) -> Subject {
     let namespace = "sdv.integration".to_owned();
-    let broker = IntentBroker::new("https://localhost:4243".parse().unwrap(), StreamingEss::new()); // DevSkim: ignore DS162092
+    let streaming_ess = StreamingEss::new();
+    let broker =
+        IntentBroker::new("https://localhost:4243".parse().unwrap(), streaming_ess.clone()); // DevSkim: ignore DS162092
     let mut registry = Registry::new(broker.clone(), Default::default());
 
-    for ProviderSetup { provider, port, name, locality } in providers {
+    for ProviderSetup { provider, port, name, locality, intent } in providers {
         let url = provider.serve(port).await;
 
         registry
             .upsert(
                 ServiceConfiguration::new(ServiceId::new(name, "1.0.0"), url, locality),
-                vec![IntentConfiguration::new(namespace.clone(), IntentKind::Invoke)],
+                vec![IntentConfiguration::new(namespace.clone(), intent)],
                 Instant::now(),
             )
             .unwrap();
     }
 
-    Subject { namespace, subject: IntentBrokeringServer::new(registry, broker) }
+    Subject { namespace, streaming_ess, subject: IntentBrokeringServer::new(registry, broker) }
 }
 
 #[async_trait]
@@ -0,0 +1,34 @@
+#!/bin/bash
+# Copyright (c) Microsoft Corporation.
+# Licensed under the MIT license.
+# SPDX-License-Identifier: MIT
+
+# This script runs the recovery e2e tests against the services of
+# compose.e2e.yaml, which are built from the given project root.
+
+set -e
+
+COMPOSE_FILE="$(dirname "$0")/compose.e2e.yaml"
+
+function cleanup {
+    echo "Cleaning up containers and network"
+    docker compose --file "$COMPOSE_FILE" down
+}
+
+trap cleanup EXIT
+
+# first parameter is required
+if [ -z "$1" ]; then
+    echo "The first parameter must be the project root"
+    exit 1
+fi
+PROJECT_ROOT=$1
+
+# the examples are built from the shared base image
+docker build --tag intent_brokering_examples:base --file "$PROJECT_ROOT/intent_brokering/examples/applications/Dockerfile.base" "$PROJECT_ROOT"
+docker compose --file "$COMPOSE_FILE" up --build --detach
+
+# the tests inject faults into the same containers, hence they run one at a time
+cargo test --test recovery-e2e -- --ignored --test-threads=1
+
+# No need to stop containers here as the cleanup trap will be called
@@ -6,12 +6,17 @@ use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
 use async_trait::async_trait;
 use examples_common::intent_brokering::value::Value;
+use intent_brokering_common::streaming_ess::{StreamingEss, Timestamped};
 use intent_brokering_proto::{
-    common::{FulfillmentEnum, FulfillmentMessage, IntentEnum, InvokeFulfillment, InvokeIntent},
+    common::{
+        discover_fulfillment::Service, DiscoverFulfillment, FulfillmentEnum, FulfillmentMessage,
+        IntentEnum, InvokeFulfillment, InvokeIntent, ValueEnum,
+    },
     provider::{
         provider_service_server::{ProviderService, ProviderServiceServer},
         FulfillRequest, FulfillResponse,
     },
+    streaming::channel_service_server::ChannelServiceServer,
 };
 use tokio::{net::TcpSocket, spawn};
 use tokio_stream::wrappers::TcpListenerStream;
@@ -21,30 +26,46 @@ use url::Url;
 #[derive(Default)]
 pub struct Provider {
     on_invoke: Option<fn(InvokeIntent) -> Option<Value>>,
+    streaming_ess: Option<StreamingEss<Timestamped<ValueEnum>>>,
+    url: Option<Url>,
     // Expand this type with other intents that are used for integration tests.
 }
 
 impl Provider {
     pub fn new() -> Self {
-        Self { on_invoke: None }
+        Self::default()
     }
 
     pub fn with_on_invoke(self, on_invoke: fn(InvokeIntent) -> Option<Value>) -> Self {
-        Self { on_invoke: Some(on_invoke) }
+        Self { on_invoke: Some(on_invoke), ..self }
+    }
+
+    /// Serves a `ChannelService` backed by the specified ESS, and fulfills
+    /// the Discover and Subscribe intents for it.
+    pub fn with_streaming(self, streaming_ess: StreamingEss<Timestamped<ValueEnum>>) -> Self {
+        Self { streaming_ess: Some(streaming_ess), ..self }
     }
 
-    pub async fn serve(self, port: u16) -> Url {
+    pub async fn serve(mut self, port: u16) -> Url {
         let socket = TcpSocket::new_v4().unwrap();
+        // Allows rebinding ports left in TIME_WAIT by a previous test run.
+        socket.set_reuseaddr(true).unwrap();
         socket.bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port)).unwrap();
         let listener = TcpListenerStream::new(socket.listen(2).unwrap());
 
+        let url: Url = format!("http://localhost:{port}").parse().unwrap(); // DevSkim: ignore DS162092
+        self.url = Some(url.clone());
+
+        let channel_service = self.streaming_ess.clone().map(ChannelServiceServer::new);
+
         spawn(
             Server::builder()
                 .add_service(ProviderServiceServer::new(self))
+                .add_optional_service(channel_service)
                 .serve_with_incoming(listener),
         );
 
-        format!("http://localhost:{port}").parse().unwrap() // DevSkim: ignore DS162092
+        url
     }
 }
 
@@ -70,6 +91,22 @@ impl ProviderService for Provider {
                     unimplemented!()
                 }
             }
+            IntentEnum::Discover(_) if self.streaming_ess.is_some() => {
+                FulfillmentEnum::Discover(DiscoverFulfillment {
+                    services: vec![Service {
+                        url: self.url.as_ref().unwrap().to_string(),
+                        schema_kind: "grpc+proto".to_owned(),
+                        schema_reference: "intent_brokering.streaming.v1".to_owned(),
+                        metadata: Default::default(),
+                    }],
+                })
+            }
+            IntentEnum::Subscribe(intent) => match &self.streaming_ess {
+                Some(ess) => {
+                    FulfillmentEnum::Subscribe(ess.serve_timestamped_subscriptions(intent, |v| v)?)
+                }
+                None => unimplemented!(),
+            },
             _ => Err(Status::not_found(""))?,
         };
 
@@ -0,0 +1,148 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+//! Injects faults into the services of `compose.e2e.yaml` and asserts that
+//! they recover. The tests change the state of shared containers, hence they
+//! must run one at a time and are ignored unless run explicitly, see
+//! README.md.
+
+use std::process::Command;
+use std::time::Duration;
+
+use common::get_uuid;
+use examples_common::intent_brokering::{
+    api::{GrpcIntentBrokering, IntentBrokering as _, IntentBrokeringExt as _},
+    value::Value,
+};
+use tokio::time::{sleep, timeout, Instant};
+use tokio_stream::StreamExt as _;
+
+mod common;
+
+const COMPOSE_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/compose.e2e.yaml");
+const NETWORK: &str = "intent_brokering_e2e_e2e";
+const KV_NAMESPACE: &str = "sdv.kvs";
+const KV_APP: &str = "kv-app";
+const KV_APP_ADDRESS: &str = "172.28.0.20";
+const MQTT_NAMESPACE: &str = "sdv.mqtt.cabin-light";
+const RECOVERY_TIMEOUT: Duration = Duration::from_secs(60);
+// Exceeds the registry TTL of 7 seconds set in the compose file.
+const PRUNE_DELAY: Duration = Duration::from_secs(10);
+
+#[tokio::test]
+#[ignore = "requires the services of compose.e2e.yaml"]
+async fn provider_reregisters_after_intent_broker_is_killed() -> Result<(), anyhow::Error> {
+    // arrange
+    wait_for_registration(KV_NAMESPACE, true).await;
+
+    // act
+    compose(&["kill", "intent_brokering"]);
+    compose(&["start", "intent_brokering"]);
+
+    // assert
+    wait_for_registration(KV_NAMESPACE, true).await;
+    let key = get_uuid();
+    let mut intent_broker = GrpcIntentBrokering::connect().await?;
+    intent_broker.write(KV_NAMESPACE, key.clone(), 1.into()).await?;
+    assert_eq!(Some(Value::from(1)), intent_broker.read(KV_NAMESPACE, key).await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "requires the services of compose.e2e.yaml"]
+async fn provider_is_pruned_when_killed_and_reregisters_when_restarted() {
+    // arrange
+    wait_for_registration(KV_NAMESPACE, true).await;
+
+    // act
+    compose(&["kill", KV_APP]);
+    wait_for_registration(KV_NAMESPACE, false).await;
+    compose(&["start", KV_APP]);
+
+    // assert
+    wait_for_registration(KV_NAMESPACE, true).await;
+}
+
+#[tokio::test]
+#[ignore = "requires the services of compose.e2e.yaml"]
+async fn subscription_is_renewed_after_network_partition() -> Result<(), anyhow::Error> {
+    // arrange
+    wait_for_registration(KV_NAMESPACE, true).await;
+
+    // act
+    docker(&["network", "disconnect", NETWORK, &format!("e2e-{KV_APP}")]);
+    sleep(PRUNE_DELAY).await;
+    docker(&["network", "connect", "--ip", KV_APP_ADDRESS, NETWORK, &format!("e2e-{KV_APP}")]);
+
+    // assert
+    wait_for_registration(KV_NAMESPACE, true).await;
+    let key = get_uuid();
+    let mut intent_broker = GrpcIntentBrokering::connect().await?;
+    let events = intent_broker.listen(KV_NAMESPACE, [key.clone()]).await?;
+    intent_broker.write(KV_NAMESPACE, key.clone(), 2.into()).await?;
+    let event = timeout(RECOVERY_TIMEOUT, events.take(1).collect::<Vec<_>>()).await?;
+    assert_eq!(Value::from(2), event[0].as_ref().unwrap().data);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "requires the services of compose.e2e.yaml"]
+async fn retained_state_is_read_after_adapter_is_killed() -> Result<(), anyhow::Error> {
+    // arrange
+    compose(&[
+        "exec",
+        "-T",
+        "mosquitto",
+        "mosquitto_pub",
+        "-t",
+        "devices/cabin-light/state/brightness",
+        "-m",
+        "50",
+        "-r",
+    ]);
+
+    // act
+    compose(&["kill", "mqtt-adapter"]);
+    compose(&["start", "mqtt-adapter"]);
+
+    // assert
+    wait_for_registration(MQTT_NAMESPACE, true).await;
+    let mut intent_broker = GrpcIntentBrokering::connect().await?;
+    assert_eq!(Some(Value::from(50)), intent_broker.read(MQTT_NAMESPACE, "brightness").await?);
+
+    Ok(())
+}
+
+fn compose(args: &[&str]) {
+    docker(&[&["compose", "--file", COMPOSE_FILE][..], args].concat());
+}
+
+fn docker(args: &[&str]) {
+    let status = Command::new("docker").args(args).status().expect("Failed to run docker.");
+    assert!(status.success(), "'docker {}' failed with {status}.", args.join(" "));
+}
+
+/// Waits until a namespace is registered or deregistered, connecting to the
+/// Intent Broker anew for each attempt, as it may have been restarted.
+async fn wait_for_registration(namespace: &str, registered: bool) {
+    let deadline = Instant::now() + RECOVERY_TIMEOUT;
+    loop {
+        if let Ok(mut intent_broker) = GrpcIntentBrokering::connect().await {
+            if let Ok(entries) = intent_broker.inspect("system.registry", namespace).await {
+                if entries.is_empty() != registered {
+                    return;
+                }
+            }
+        }
+
+        assert!(
+            Instant::now() < deadline,
+            "Namespace '{namespace}' was not {} in time.",
+            if registered { "registered" } else { "deregistered" }
+        );
+        sleep(Duration::from_millis(500)).await;
+    }
+}